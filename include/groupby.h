@@ -0,0 +1,31 @@
+/* C declarations for the groupby library's C ABI. Build the library with the "ffi" feature; see
+ * src/ffi.rs for details. */
+
+#ifndef GROUPBY_H
+#define GROUPBY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Groups input_len bytes at input as specified by the argc groupby command-line arguments in
+ * argv, e.g. {"-w", "--extension"}, and returns the groups as a JSON object mapping each key to
+ * an array of values.
+ *
+ * On success, returns a string that the caller must free with groupby_string_free. On failure,
+ * returns NULL and, if error is not NULL, stores an error message in *error, which the caller
+ * must also free with groupby_string_free. */
+char *groupby_group_json(const uint8_t *input, size_t input_len, const char *const *argv,
+                         size_t argc, char **error);
+
+/* Frees a string returned by this library. Does nothing if s is NULL. */
+void groupby_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GROUPBY_H */
@@ -12,6 +12,40 @@ use clap::{command, Arg, ArgGroup, Command};
 
 type Cmd = Command<'static>;
 
+// The names of every TimeBucket, for options that take one.
+const TIME_SPANS: [&str; 6] = ["year", "month", "week", "day", "hour", "minute"];
+
+/// The IDs of every grouper option, in the order they appear in the help text.
+pub const GROUPERS: [&str; 27] = [
+    "groupers_by_first_chars",
+    "groupers_by_last_chars",
+    "groupers_by_regex",
+    "groupers_by_file_extension",
+    "groupers_by_counter",
+    "groupers_by_key_value",
+    "groupers_by_field",
+    "groupers_by_nth_word",
+    "groupers_by_timestamp",
+    "groupers_by_numeric_bucket",
+    "groupers_by_size_bucket",
+    "groupers_by_mtime",
+    "groupers_by_mime_type",
+    "groupers_by_path_component",
+    "groupers_by_basename",
+    "groupers_by_dirname",
+    "groupers_by_length",
+    "groupers_by_shard",
+    "groupers_by_chunk",
+    "groupers_by_glob",
+    "groupers_by_url_host",
+    "groupers_by_json_key",
+    "groupers_by_csv_column",
+    "groupers_by_logfmt",
+    "groupers_by_key_expr",
+    "groupers_by_key_command",
+    "groupers_by_named",
+];
+
 /// Provides individual methods for adding parts of the `groupby` command-line interface.
 ///
 /// If the methods here were bare functions, calling them would be painful and far from idiomatic.
@@ -32,14 +66,64 @@ pub fn args() -> Cmd {
 pub fn command(command: Cmd) -> Cmd {
     CommandBuilder::new(command)
         .about()
+        .general_options()
         .input_split_options()
+        .input_file_options()
         .groupers()
         .grouper_options()
         .output_separator_options()
         .output_options()
+        .subcommands()
         .command
 }
 
+/// Creates the `bench` subcommand, which times `groupby` on synthetic input. See
+/// [crate::command_line::bench].
+pub fn bench_command() -> Cmd {
+    Command::new("bench")
+        .about("Time each phase of groupby on synthetic input, comparing backends.")
+        .long_about(
+            "Generate synthetic input in memory, then time each phase of processing it (splitting, \
+            grouping, output, and optionally running commands) with each grouped collection \
+            backend, and print a table comparing them. Output is discarded, so only the work \
+            groupby itself does is measured.\n\
+            \n\
+            Each item looks like KEY-N, where KEY is one of the group keys, zero-padded to the \
+            same width, and items are grouped by that key.",
+        )
+        .arg(
+            Arg::new("bench_items")
+                .long("items")
+                .value_name("n")
+                .takes_value(true)
+                .default_value("1000000")
+                .help("Generate n items of input."),
+        )
+        .arg(
+            Arg::new("bench_keys")
+                .long("keys")
+                .value_name("n")
+                .takes_value(true)
+                .default_value("1000")
+                .help("Spread the items randomly across n distinct group keys."),
+        )
+        .arg(
+            Arg::new("bench_run_command")
+                .short('c')
+                .long("run-command")
+                .value_name("cmd")
+                .takes_value(true)
+                .help("Also time running cmd once for each group, as with groupby -c."),
+        )
+        .arg(
+            Arg::new("bench_seed")
+                .long("seed")
+                .value_name("n")
+                .takes_value(true)
+                .help("Seed the input generator with n, for reproducible input."),
+        )
+}
+
 // Handles the boilerplate so that CommandBuilder's methods can simply focus on writing clap
 // builder logic. For instance, to add an argument, you might write:
 //
@@ -80,8 +164,8 @@ impl CommandBuilder {
         build!(
             self,
             about,
-            "\nReads lines from standard input and groups them by common substrings. By default, \
-            prints the resulting groups to standard output."
+            "\nReads lines from the given files, or standard input, and groups them by common \
+            substrings. By default, prints the resulting groups to standard output."
         )
     }
 
@@ -90,8 +174,8 @@ impl CommandBuilder {
         build!(
             self,
             long_about,
-            "\nReads lines from standard input and groups them by common substrings. By default, \
-            prints the resulting groups to standard output.\n\
+            "\nReads lines from the given files, or standard input, and groups them by common \
+            substrings. By default, prints the resulting groups to standard output.\n\
             \n\
             For example, to group lines in a structured log file by the first 10 characters:
 
@@ -104,10 +188,125 @@ impl CommandBuilder {
     find ~/Pictures/ -not -type d -print0 \\
         | groupby -0 --extension --print0 -c \"xargs -0 du -chL | tail -n1\"\n\
             \n\
-            Note: the lack of an option to group by the first or last n words is an intional \
-            omission. There are many ways to define a word, and when grouping by words, the exact \
-            definition matters. To match based on words, please use --regex and supply a \
-            definition that works for your use case."
+            Note: there is no built-in definition of a word. There are many ways to define one, \
+            and when grouping by words, the exact definition matters. To group by the nth word, \
+            use --word together with --word-regex, supplying a definition that works for your use \
+            case."
+        )
+    }
+
+    /// Adds options that apply across categories. These appear alongside clap's own options, e.g.
+    /// `--help`, so they must be added before any heading.
+    pub fn general_options(self) -> Self {
+        self.general_seed()
+            .general_literal()
+            .general_profile()
+            .general_preset()
+            .general_explain()
+    }
+
+    /// Adds an option to seed every randomized feature.
+    pub fn general_seed(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("general_seed")
+                .long("seed")
+                .value_name("n")
+                .takes_value(true)
+                .help("Seed every randomized feature with n, for reproducible runs.")
+                .long_help(
+                    "Seed every randomized feature with the number n, so that repeated runs over \
+                    the same input make exactly the same choices. Currently, only --sample is \
+                    randomized; the bench subcommand takes a --seed of its own for the input it \
+                    generates. Without this option, each run uses a different, unpredictable seed."
+                )
+        )
+    }
+
+    /// Adds an option to disable escape sequences in delimiters.
+    pub fn general_literal(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("general_literal")
+                .long("literal")
+                .help("Do not interpret escape sequences, e.g. \\t, in delimiters.")
+                .long_help(
+                    "Do not interpret escape sequences in delimiters, e.g. for --split or \
+                    --print-sep. By default, \
+                    \\t, \\n, \\r, and \\0 stand for a tab, newline, carriage return, and null \
+                    character; \\xNN stands for the ASCII character with hexadecimal code NN; and \
+                    \\\\ stands for a single backslash. Any other backslash is kept as-is."
+                )
+        )
+    }
+
+    /// Adds an option to load a named preset. See [crate::command_line::profiles].
+    pub fn general_preset(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("general_preset")
+                .long("preset")
+                .value_name("name")
+                .takes_value(true)
+                .help("Load options for a common workflow, e.g. extensions or log-by-day.")
+                .long_help(
+                    "Load the options for a common workflow. The built-in presets are:\n\
+                    \n    \
+                    extensions    Group file names by extension.\n    \
+                    log-by-day    Group log lines by their first YYYY-MM-DD date.\n    \
+                    dupe-names    Group paths by file name, keeping repeated names.\n    \
+                    domains       Group URLs by registrable domain.\n\
+                    \n\
+                    The config file (see --profile) can define more presets, or replace these, \
+                    in [preset.<name>] tables that work just like profiles. Options given on the \
+                    command line or by --profile take precedence over the preset's."
+                )
+        )
+    }
+
+    /// Adds an option to load a named profile. See [crate::command_line::profiles].
+    pub fn general_profile(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("general_profile")
+                .long("profile")
+                .value_name("name")
+                .takes_value(true)
+                .help("Load options from the named profile in the config file.")
+                .long_help(
+                    "Load options from the profile with the given name in \
+                    $XDG_CONFIG_HOME/groupby/config.toml (by default, \
+                    ~/.config/groupby/config.toml). Each profile is a table of options, named \
+                    without leading hyphens, e.g.:\n\
+                    \n    [profile.logs]\n    regex = '^(\\d{4}-\\d{2}-\\d{2})'\n    \
+                    run-command = \"wc -l\"\n\
+                    \n\
+                    Options given on the command line take precedence over the profile's, \
+                    including options that conflict with the profile's, e.g. a different \
+                    grouper."
+                )
+        )
+    }
+
+    /// Adds an option to describe what would be done instead of doing it.
+    pub fn general_explain(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("general_explain")
+                .long("explain")
+                .help("Print a plan of what would be done with the other options, then exit.")
+                .long_help(
+                    "Instead of reading input, print a human-readable plan of what the other \
+                    options would do: how input would be split, how keys would be computed and \
+                    relabeled, how groups would be collected, how commands would run, and how \
+                    output would be formatted. Options loaded with --profile or --preset are \
+                    included."
+                )
         )
     }
 
@@ -116,8 +315,186 @@ impl CommandBuilder {
         self.input_split_options_heading()
             .input_split_on_whitespace()
             .input_split_on_null()
+            .input_split_on_paragraphs()
             .input_split_on_custom()
+            .input_csv()
             .group_input_split_options()
+            .input_invalid_utf8()
+            .input_trim()
+            .input_skip_empty()
+            .input_limit()
+            .input_sample()
+    }
+
+    /// Adds options for choosing which files to read input from.
+    pub fn input_file_options(self) -> Self {
+        self.input_file_options_heading()
+            .input_files()
+            .input_files0_from()
+            .input_with_filename()
+            .input_tee()
+            .input_walk()
+            .input_hidden()
+            .input_follow_symlinks()
+    }
+
+    /// Adds the input file options heading.
+    pub fn input_file_options_heading(self) -> Self {
+        build!(self, next_help_heading, "INPUT FILE OPTIONS")
+    }
+
+    /// Adds positional arguments naming the files to read input from.
+    pub fn input_files(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_files")
+                .value_name("file")
+                .takes_value(true)
+                .multiple_values(true)
+                .allow_invalid_utf8(true)
+                .help("Read input from these files, in order, instead of standard input.")
+                .long_help(
+                    "Read input from these files, in order, instead of standard input. Each file \
+                    is split into tokens on its own, so a token never spans two files; with \
+                    --csv, each file has its own header row, which must put named columns where \
+                    the first file's does. A file named - means standard input. Input compressed with gzip or zstd, such as rotated logs, is \
+                    decompressed as it's read, if groupby was built with the gzip or zstd \
+                    feature."
+                )
+        )
+    }
+
+    /// Adds an option to read the names of input files from a file.
+    pub fn input_files0_from(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_files0_from")
+                .long("files0-from")
+                .value_name("list")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .conflicts_with("input_files")
+                .help("Read input from the files named in list, each followed by a null character.")
+                .long_help(
+                    "Read input from the files named in list, in order, as if they were given as \
+                    arguments. Each name is followed by a null character, as find -print0 writes \
+                    them, so there's no limit on how many files can be named. If list is -, read \
+                    the names from standard input. For example:\n\
+                    \n    \
+                    find /var/log -name '*.log' -print0 | groupby --files0-from - -f 15"
+                )
+        )
+    }
+
+    /// Adds an option to prefix each value with the name of the file it came from.
+    pub fn input_with_filename(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_with_filename")
+                .short('H')
+                .long("with-filename")
+                .conflicts_with("output_presorted_streaming")
+                .help("Prefix each value with the name of the file it came from.")
+                .long_help(
+                    "Prefix each value with the name of the file it came from and a colon, like \
+                    grep -H, e.g. app.log:ERROR timeout. Keys are found before the prefix is \
+                    added, so it doesn't affect grouping, but commands run with -c see it. \
+                    Values read from standard input are prefixed with \"(standard input):\"."
+                )
+        )
+    }
+
+    /// Adds an option to copy input to standard output or a file as it's read.
+    pub fn input_tee(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_tee")
+                .long("tee")
+                .value_name("file")
+                .takes_value(true)
+                .min_values(0)
+                .max_values(1)
+                .require_equals(true)
+                .default_missing_value("-")
+                .allow_invalid_utf8(true)
+                .conflicts_with_all(&["input_walk", "output_live", "output_incremental"])
+                .help("Copy input to standard output, or to file, and print results to stderr.")
+                .long_help(
+                    "Copy the input, unchanged, to standard output as it's read, or to file if \
+                    one is given with --tee=file, like tee. Groups are still built, and the \
+                    results are printed to standard error once the input ends, so groupby can sit \
+                    in the middle of an existing pipeline. Compressed input is copied after it's \
+                    decompressed. For example, to keep the errors from a log while counting \
+                    every level:\n\
+                    \n    \
+                    groupby --tee --logfmt level --count-only app.log | grep level=error"
+                )
+        )
+    }
+
+    /// Adds an option to walk a directory tree instead of reading input.
+    pub fn input_walk(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_walk")
+                .long("walk")
+                .value_name("dir")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .conflicts_with_all(&[
+                    "input_files",
+                    "input_files0_from",
+                    "input_with_filename",
+                    "input_split",
+                    "output_presorted_streaming",
+                ])
+                .help("Group the paths of the files under dir instead of reading input.")
+                .long_help(
+                    "Instead of reading input, walk dir recursively and group the path of each \
+                    file under it, like find dir -not -type d, but in sorted order. Hidden files \
+                    and directories, whose names start with a dot, are skipped unless --hidden \
+                    is given. Symbolic links are grouped like files unless --follow-symlinks is \
+                    given. Entries that can't be read are skipped with a warning. For example, \
+                    to see how much space each type of file uses:\n\
+                    \n    \
+                    groupby --walk ~/Pictures --extension --print0 -c \"xargs -0 du -ch | tail \
+                    -n1\""
+                )
+        )
+    }
+
+    /// Adds an option to include hidden files in a walk.
+    pub fn input_hidden(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_hidden")
+                .long("hidden")
+                .requires("input_walk")
+                .help("With --walk, include hidden files and directories.")
+        )
+    }
+
+    /// Adds an option to follow symbolic links in a walk.
+    pub fn input_follow_symlinks(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_follow_symlinks")
+                .long("follow-symlinks")
+                .requires("input_walk")
+                .help("With --walk, walk symbolic links to directories.")
+                .long_help(
+                    "With --walk, walk symbolic links to directories as if they were \
+                    directories. Each directory is walked only once, so links that form a loop \
+                    are safe."
+                )
+        )
     }
 
     /// Adds the input options heading.
@@ -151,6 +528,24 @@ impl CommandBuilder {
         )
     }
 
+    /// Adds an option to split input into paragraphs.
+    pub fn input_split_on_paragraphs(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_split_on_paragraphs")
+                .short('p')
+                .long("paragraphs")
+                .help("Split input into paragraphs separated by blank lines.")
+                .long_help(
+                    "Split input into paragraphs separated by blank lines, like awk's paragraph \
+                    mode, so that multi-line records such as stack traces or config stanzas are \
+                    grouped as units. Lines that are only whitespace count as blank. Each \
+                    paragraph is one token, with its lines joined by newlines."
+                )
+        )
+    }
+
     /// Adds an option to split on a custom string.
     pub fn input_split_on_custom(self) -> Self {
         build!(
@@ -160,7 +555,113 @@ impl CommandBuilder {
                 .long("split")
                 .value_name("delim")
                 .takes_value(true)
-                .help("Split input on a custom delimiter of your choice.")
+                .help("Split input on a custom delimiter of your choice, e.g. '\\t'.")
+        )
+    }
+
+    /// Adds an option to read input as CSV records with a header row.
+    pub fn input_csv(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_csv")
+                .long("csv")
+                .help("Read CSV records, which may span lines, and skip the header row.")
+                .long_help(
+                    "Read input as CSV records: one per line, except that a quoted field may \
+                    contain newlines. The first record is a header row. It isn't grouped, but \
+                    --column can refer to its column names."
+                )
+        )
+    }
+
+    /// Adds an option to choose what to do with input that isn't valid UTF-8.
+    pub fn input_invalid_utf8(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_invalid_utf8")
+                .long("invalid-utf8")
+                .value_name("policy")
+                .takes_value(true)
+                .possible_values(["lossy", "skip", "strict"])
+                .help("What to do with input that isn't valid UTF-8 (default: lossy).")
+                .long_help(
+                    "What to do with input tokens that aren't valid UTF-8, such as file names \
+                    from find -print0. \"lossy\" (the default) replaces each invalid sequence \
+                    with U+FFFD, the replacement character. \"skip\" skips the token and prints \
+                    a warning to standard error; with -w, it skips the whole line. \"strict\" \
+                    exits with an error. With --csv, the policy applies to each record."
+                )
+        )
+    }
+
+    /// Adds an option to strip whitespace from the ends of each token.
+    pub fn input_trim(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_trim")
+                .long("trim")
+                .help("Strip leading and trailing whitespace from each token.")
+                .long_help(
+                    "Strip leading and trailing whitespace from each token before it's grouped, \
+                    so that e.g. \"a\" and \"a \" go in the same group. This is most useful with \
+                    --split, e.g. --split , --trim for comma-separated lists written with spaces."
+                )
+        )
+    }
+
+    /// Adds an option to drop empty tokens.
+    pub fn input_skip_empty(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_skip_empty")
+                .long("skip-empty")
+                .help("Don't group empty tokens, e.g. blank lines.")
+                .long_help(
+                    "Don't group empty tokens, such as blank lines or the empty token after a \
+                    trailing --split delimiter. With --trim, tokens that are only whitespace are \
+                    skipped too."
+                )
+        )
+    }
+
+    /// Adds an option to stop reading input after a number of tokens.
+    pub fn input_limit(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_limit")
+                .long("limit")
+                .value_name("n")
+                .takes_value(true)
+                .help("Stop reading input after grouping n tokens.")
+                .long_help(
+                    "Stop reading input once n tokens have been grouped, e.g. to preview the \
+                    groups of a huge input before a full run. Tokens dropped by --skip-empty or \
+                    --sample don't count toward n."
+                )
+        )
+    }
+
+    /// Adds an option to group a random sample of the input.
+    pub fn input_sample(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("input_sample")
+                .long("sample")
+                .value_name("p")
+                .takes_value(true)
+                .help("Keep each token with probability p, e.g. 0.01 or 1%, and drop the rest.")
+                .long_help(
+                    "Keep each token at random with probability p, written as a number from 0 \
+                    to 1 or as a percentage, e.g. 0.01 or 1%, and drop the rest. Combined with \
+                    --count-only, this estimates group sizes cheaply. Use --seed to sample the same tokens on \
+                    every run."
+                )
         )
     }
 
@@ -172,7 +673,9 @@ impl CommandBuilder {
             ArgGroup::new("input_split").args(&[
                 "input_split_on_whitespace",
                 "input_split_on_null",
-                "input_split_on_custom"
+                "input_split_on_paragraphs",
+                "input_split_on_custom",
+                "input_csv"
             ])
         )
     }
@@ -185,12 +688,38 @@ impl CommandBuilder {
             .groupers_by_regex()
             .groupers_by_file_extension()
             .groupers_by_counter()
+            .groupers_by_key_value()
+            .groupers_by_field()
+            .groupers_by_nth_word()
+            .groupers_by_timestamp()
+            .groupers_by_numeric_bucket()
+            .groupers_by_size_bucket()
+            .groupers_by_mtime()
+            .groupers_by_mime_type()
+            .groupers_by_path_component()
+            .groupers_by_basename()
+            .groupers_by_dirname()
+            .groupers_by_length()
+            .groupers_by_shard()
+            .groupers_by_chunk()
+            .groupers_by_glob()
+            .groupers_by_url_host()
+            .groupers_by_json_key()
+            .groupers_by_csv_column()
+            .groupers_by_logfmt()
+            .groupers_by_key_expr()
+            .groupers_by_key_command()
+            .groupers_by_named()
             .group_groupers()
     }
 
     /// Adds the grouper heading.
     pub fn groupers_heading(self) -> Self {
-        build!(self, next_help_heading, "GROUPERS (choose exactly one)")
+        build!(
+            self,
+            next_help_heading,
+            "GROUPERS (choose one, or several for a composite key)"
+        )
     }
 
     /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_first_chars] grouper.
@@ -229,11 +758,19 @@ impl CommandBuilder {
                 .long("regex")
                 .value_name("pattern")
                 .takes_value(true)
+                .multiple_occurrences(true)
                 .help("Group by equivalence on the first match against the specified pattern.")
                 .long_help(
                     "Group by equivalence on the first match against the specified regex pattern. \
                     If capture groups are present, group by equivalence on the first capture \
-                    group. If a line does not match, it is stored in the blank group, \"\"."
+                    group. If a line does not match, it is stored in the blank group, \"\", \
+                    unless --unmatched says otherwise.\n\n\
+                    -r may be given many times to classify lines with a list of rules. Each line \
+                    is tried against each pattern in order and grouped by the first one that \
+                    matches. When -r is repeated, a pattern may be named by writing it as \
+                    NAME=PATTERN, e.g. errors=ERROR|FATAL, and lines it matches go in the group \
+                    NAME instead. Only names made of letters, digits, _, and - are recognized, so \
+                    write a pattern that starts with something like key= as (?:key)=."
                 )
         )
     }
@@ -273,216 +810,1792 @@ impl CommandBuilder {
         )
     }
 
-    /// Adds the grouper choices into a group: choose exactly one.
-    pub fn group_groupers(self) -> Self {
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_key_value]
+    /// grouper.
+    pub fn groupers_by_key_value(self) -> Self {
         build!(
             self,
-            group,
-            ArgGroup::new("groupers")
-                .args(&[
-                    "groupers_by_first_chars",
-                    "groupers_by_last_chars",
-                    "groupers_by_regex",
-                    "groupers_by_file_extension",
-                    "groupers_by_counter",
-                ])
-                .required(true)
+            arg,
+            Arg::new("groupers_by_key_value")
+                .long("kv")
+                .value_name("sep")
+                .takes_value(true)
+                .min_values(0)
+                .max_values(1)
+                .require_equals(true)
+                .default_missing_value("\t")
+                .help("Treat each token as a key, then sep (default: tab), then a value.")
+                .long_help(
+                    "Treat each token as a pre-keyed record: a key, then sep, then a value. Group \
+                    each value by its key, skipping matching entirely. Splits at the first \
+                    occurrence of sep, which is given with --kv=sep and defaults to a tab. Tokens without sep are stored, \
+                    whole, in the blank group, \"\". This is useful when an upstream tool has \
+                    already computed each token's key."
+                )
         )
     }
 
-    /// Adds a section for customizing the behavior of groupers.
-    pub fn grouper_options(self) -> Self {
-        self.grouper_options_heading()
-            .grouper_options_capture_group()
-    }
-
-    /// Adds the grouper options heading.
-    pub fn grouper_options_heading(self) -> Self {
-        build!(self, next_help_heading, "GROUPER OPTIONS")
-    }
-
-    /// Adds an option to specify a capture group number or name when using a regex grouper.
-    pub fn grouper_options_capture_group(self) -> Self {
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_field] grouper.
+    pub fn groupers_by_field(self) -> Self {
         build!(
             self,
             arg,
-            Arg::new("grouper_options_capture_group")
-                .long("capture-group")
+            Arg::new("groupers_by_field")
+                .long("field")
+                .value_name("n")
                 .takes_value(true)
-                .value_name("grp")
-                .help("When used with -r, match a capture group by number or name.")
+                .help("Group by equivalence on the nth field (from 1), like cut -f. See -d.")
                 .long_help(
-                    "When used with -r, match a specific capture group by number or name. Group \
-                    number 0 matches the entire pattern."
+                    "Group by equivalence on the nth field, counting from 1, like cut -f. Fields \
+                    are separated by the delimiter given with -d, which defaults to a tab. If a \
+                    line has fewer than n fields, it is stored in the blank group, \"\"."
                 )
         )
     }
 
-    /// Adds a section for output options.
-    pub fn output_separator_options(self) -> Self {
-        self.output_separator_heading()
-            .output_null_separators()
-            .output_space_separators()
-            .group_output_separator_options()
-    }
-
-    /// Adds the output separator heading.
-    pub fn output_separator_heading(self) -> Self {
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_nth_word]
+    /// grouper.
+    pub fn groupers_by_nth_word(self) -> Self {
         build!(
             self,
-            next_help_heading,
-            "OUTPUT SEPARATOR OPTIONS (choose zero or one)"
+            arg,
+            Arg::new("groupers_by_nth_word")
+                .long("word")
+                .value_name("n")
+                .takes_value(true)
+                .requires("grouper_options_word_regex")
+                .help("Group by equivalence on the nth word (from 1). Requires --word-regex.")
+                .long_help(
+                    "Group by equivalence on the nth word, counting from 1, where a word is any \
+                    match of the pattern given with --word-regex. There are many ways to define a \
+                    word, and the exact definition matters, so you must choose one, e.g. '\\S+' \
+                    for runs of non-whitespace or '\\w+' for runs of letters, digits, and \
+                    underscores. If a line has fewer than n words, it is stored in the blank \
+                    group, \"\"."
+                )
         )
     }
 
-    /// Adds an option to separate records by null characters on output.
-    pub fn output_null_separators(self) -> Self {
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_timestamp]
+    /// grouper.
+    pub fn groupers_by_timestamp(self) -> Self {
         build!(
             self,
             arg,
-            Arg::new("output_null_separators")
-                .long("print0")
-                .help("When outputting lines, separate them with a null character, not a newline.")
+            Arg::new("groupers_by_timestamp")
+                .long("date-format")
+                .value_name("fmt")
+                .takes_value(true)
+                .help("Group by timestamps written like fmt, e.g. '%Y-%m-%d'. See --bucket.")
                 .long_help(
-                    "When outputting lines, separate them with a null character rather than a \
-                    newline. This option is meant for compatibility with xargs -0."
+                    "Find the first timestamp in each line that's written in the format fmt and \
+                    group by its day, or by the time span chosen with --bucket. Keys look like \
+                    2024-03-09 (or 2024, 2024-03, 2024-W10, 2024-03-09T14, or \
+                    2024-03-09T14:05), so they sort chronologically. The format may contain %Y \
+                    (four-digit year), %m \
+                    (two-digit month), %b (month abbreviation, e.g. Mar), %d (two-digit day), %H \
+                    (two-digit hour), %M (two-digit minute), %S (two-digit second), and %% (a \
+                    literal %); everything else matches itself. For example, Apache access logs \
+                    use '%d/%b/%Y:%H:%M:%S'. If a line contains no matching timestamp, it is \
+                    stored in the blank group, \"\"."
                 )
         )
     }
 
-    /// Adds an option to separate records by spaces on output.
-    pub fn output_space_separators(self) -> Self {
+    /// Adds an option to specify the
+    /// [crate::groupers::string::Groupers::group_by_numeric_bucket] grouper.
+    pub fn groupers_by_numeric_bucket(self) -> Self {
         build!(
             self,
             arg,
-            Arg::new("output_space_separators")
-                .long("printspace")
-                .help("When outputting lines, separate them with a space rather than a newline.")
-        )
+            Arg::new("groupers_by_numeric_bucket")
+                .long("bucket-size")
+                .value_name("n")
+                .takes_value(true)
+                .help("Group by the first number in each line, in ranges of size n, e.g. 0-99.")
+                .long_help(
+                    "Find the first number in each line and group it into a range of size n, e.g. \
+                    0-99, 100-199, etc. for n = 100. This is useful for histograms of sizes and \
+                    latencies. A number is a run of digits, optionally with a leading - and a \
+                    fractional part, e.g. -1.5. If a line contains no number, it is stored in the \
+                    blank group, \"\". Ranges are listed in order of their lower bounds, \
+                    negative ones included, unless --sort says otherwise."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_size_bucket]
+    /// grouper.
+    pub fn groupers_by_size_bucket(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_size_bucket")
+                .long("size-buckets")
+                .value_name("sizes")
+                .takes_value(true)
+                .help("Group files by size, e.g. 1K,1M,100M makes <1K, 1K-1M, 1M-100M, 100M+.")
+                .long_help(
+                    "Treat each line as a path and group by the size of the file it names, in \
+                    ranges divided at sizes, a comma-separated list in increasing order. For \
+                    example, 1K,1M,100M makes the groups <1K, 1K-1M, 1M-100M, and 100M+. Each \
+                    range includes its lower boundary. Sizes are in bytes, optionally followed \
+                    by K, M, G, or T for powers of 1024. Symbolic links are followed. If a file's \
+                    size can't be read, e.g. because it doesn't exist, its path is stored in the \
+                    blank group, \"\"."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_mtime] grouper.
+    pub fn groupers_by_mtime(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_mtime")
+                .long("mtime-bucket")
+                .value_name("span")
+                .takes_value(true)
+                .possible_values(TIME_SPANS)
+                .help("Group files by when they were last modified, e.g. by day or week.")
+                .long_help(
+                    "Treat each line as a path and group by the time span, in UTC, in which the \
+                    file it names was last modified. Keys look like 2024 (year), 2024-03 \
+                    (month), 2024-W10 (ISO 8601 week, starting Monday), 2024-03-09 (day), \
+                    2024-03-09T14 (hour), or 2024-03-09T14:05 (minute), so they sort \
+                    chronologically. Symbolic links are followed. If a file's modification time \
+                    can't be read, e.g. because it doesn't exist, its path is stored in the \
+                    blank group, \"\"."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_mime_type]
+    /// grouper.
+    pub fn groupers_by_mime_type(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_mime_type")
+                .long("mime-type")
+                .help("Group files by MIME type, detected from their contents, e.g. image/png.")
+                .long_help(
+                    "Treat each line as a path and group by the MIME type of the file it names, \
+                    e.g. image/png or application/zip, detected from the first few bytes of the \
+                    file rather than its name. Recognizes common image, audio, video, document, \
+                    archive, and executable formats; other files are text/plain if they look \
+                    like text and application/octet-stream otherwise. Empty files are \
+                    inode/x-empty and directories are inode/directory. Symbolic links are \
+                    followed. If a file can't be read, e.g. because it doesn't exist, its path is \
+                    stored in the blank group, \"\"."
+                )
+        )
+    }
+
+    /// Adds an option to specify the
+    /// [crate::groupers::string::Groupers::group_by_path_component] grouper.
+    pub fn groupers_by_path_component(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_path_component")
+                .long("path-depth")
+                .value_name("n")
+                .takes_value(true)
+                .help("Group paths by their nth component (from 1), e.g. top-level directory.")
+                .long_help(
+                    "Treat each line as a path and group by its nth component, counting from 1. \
+                    For instance, with n = 1, ./src/lib.rs and ./src/bin/main.rs are both stored \
+                    in the group \"src\". A leading / or ./ is skipped, and repeated separators \
+                    are ignored. If a path has fewer than n components, it is stored in the blank \
+                    group, \"\"."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_basename]
+    /// grouper.
+    pub fn groupers_by_basename(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_basename")
+                .long("basename")
+                .help("Group paths by file name, ignoring directories, to find duplicate names.")
+                .long_help(
+                    "Treat each line as a path and group by its final component, like basename. \
+                    Files with the same name in different directories share a group, which is \
+                    handy for finding duplicate names. A trailing / is ignored. Paths without a \
+                    final component, e.g. /, are stored in the blank group, \"\"."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_dirname]
+    /// grouper.
+    pub fn groupers_by_dirname(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_dirname")
+                .long("dirname")
+                .help("Group paths by parent directory.")
+                .long_help(
+                    "Treat each line as a path and group by everything but its final component, \
+                    like dirname, i.e. by parent directory. A trailing / is ignored. Paths with \
+                    only one component, e.g. Cargo.toml, are stored in the group \".\"; roots, \
+                    e.g. /, are stored in the blank group, \"\"."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_length] grouper.
+    pub fn groupers_by_length(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_length")
+                .long("length")
+                .help("Group by length in characters. Sorts naturally unless --sort is given.")
+                .long_help(
+                    "Group by length, counting characters rather than bytes, so \"naïve\" has \
+                    length 5. Groups print in numeric order, e.g. 9 before 10, unless you choose \
+                    a different order with --sort."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_shard] grouper.
+    pub fn groupers_by_shard(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_shard")
+                .long("shard")
+                .value_name("n")
+                .takes_value(true)
+                .help("Split lines into n groups, 0 to n-1, by a stable hash of each line.")
+                .long_help(
+                    "Partition lines into n groups, numbered 0 through n-1, by a hash of each \
+                    line. Identical lines always land in the same group, and lines are spread \
+                    evenly across groups. The hash is stable across runs, platforms, and \
+                    versions, so the same input always shards the same way. This is handy for \
+                    splitting work across n parallel jobs with -c. Groups print in numeric \
+                    order unless you choose a different order with --sort."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_chunk] grouper.
+    pub fn groupers_by_chunk(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_chunk")
+                .long("chunk")
+                .value_name("n")
+                .takes_value(true)
+                .help("Split input into numbered groups of n consecutive lines.")
+                .long_help(
+                    "Split input into groups of n consecutive lines, numbered from 0: lines 1 \
+                    through n go in group 0, the next n lines in group 1, and so on. The last \
+                    group may be smaller. With -c, this runs one command per batch while keeping \
+                    track of which batch produced which output. Groups print in numeric order \
+                    unless you choose a different order with --sort."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_glob] grouper.
+    pub fn groupers_by_glob(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_glob")
+                .long("glob")
+                .value_name("rule")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("Group paths by named glob patterns, e.g. images=*.{jpg,png}. Repeatable.")
+                .long_help(
+                    "Group paths by named glob patterns. Each rule looks like NAME=PATTERN, e.g. \
+                    images=*.{jpg,png}, and --glob may be given many times. Each token goes in the \
+                    group named by the first rule whose pattern matches it, or in the group \
+                    \"other\" if none match.\n\n\
+                    In patterns, * matches anything but /, ** matches anything, ? matches one \
+                    character, [a-z] and [!a-z] match character classes, {a,b} matches either \
+                    alternative, and \\ escapes the next character. A pattern without a / matches \
+                    the last component of the path, so *.rs matches src/lib.rs."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_url_host]
+    /// grouper.
+    pub fn groupers_by_url_host(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_url_host")
+                .long("url-host")
+                .help("Group URLs by host, e.g. www.example.com. See also --domain.")
+                .long_help(
+                    "Treat each token as a URL and group by its host, e.g. www.example.com for \
+                    https://www.example.com/index.html. User info and ports are dropped. The URL \
+                    must have a scheme, like https://, or start with //. Tokens that aren't URLs \
+                    are stored in the blank group, \"\". Hosts are case-insensitive, so consider \
+                    adding -i. With --domain, group by registrable domain instead."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_json_key]
+    /// grouper.
+    pub fn groupers_by_json_key(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_json_key")
+                .long("json-key")
+                .value_name("path")
+                .takes_value(true)
+                .help("Group JSON Lines by the value at path, e.g. .user.id or .tags[0].")
+                .long_help(
+                    "Treat each line as a JSON document and group by the value at path, written \
+                    as in jq: .user.id for the id field of the user object, .tags[0] for the \
+                    first element of the tags array, or .[\"odd key\"] for keys that aren't plain \
+                    names. Strings are used without quotes; other values are used as written. \
+                    Lines without a value at path, or that aren't valid JSON, are stored in the \
+                    blank group, \"\"."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_csv_column]
+    /// grouper.
+    pub fn groupers_by_csv_column(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_csv_column")
+                .long("column")
+                .value_name("col")
+                .takes_value(true)
+                .help("Group CSV records by a column: a number (from 1) or, with --csv, a name.")
+                .long_help(
+                    "Treat each line as a CSV record and group by one of its columns, with quotes \
+                    removed. col is either a column number, counting from 1, or, with --csv, a \
+                    column name from the header row. Fields are separated by commas unless you \
+                    choose another delimiter with -d. Use --csv as well if quoted fields may \
+                    contain newlines. Records with too few fields are stored in the blank group, \
+                    \"\"."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_logfmt] grouper.
+    pub fn groupers_by_logfmt(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_logfmt")
+                .long("logfmt")
+                .value_name("key")
+                .takes_value(true)
+                .help("Group logfmt lines (key=value pairs) by the value for key.")
+                .long_help(
+                    "Scan each token for logfmt-style key=value pairs, separated by whitespace, \
+                    and group by the value for key. Values may be double-quoted to include \
+                    spaces, e.g. msg=\"disk full\". Tokens without key are stored in the blank \
+                    group, \"\". Unlike --kv, which splits pre-keyed records once, this finds key \
+                    anywhere in the token."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::groupers::string::Groupers::group_by_key_expr]
+    /// grouper.
+    pub fn groupers_by_key_expr(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_key_expr")
+                .long("key-expr")
+                .value_name("expr")
+                .takes_value(true)
+                .help("Group by the key that a small expression computes, e.g. lower(word(2)).")
+                .long_help(
+                    "Compute each token's key with a small expression, for rules that no other \
+                    grouper covers, e.g. lower(field(2, ':')). Expressions nest calls to \
+                    field(n, delim), word(n), first(n), last(n), regex(pattern), ext(), \
+                    basename(), dirname(), host(), lower(s), upper(s), trim(s), \
+                    replace(s, from, to), concat(s, ...), and or(s, ...), which returns its first \
+                    non-blank argument. Functions with an optional final argument work on the \
+                    whole token, written line, unless given another string. Quote strings with ' \
+                    or \". Anything a function can't find, like a missing field, is \"\"."
+                )
+        )
+    }
+
+    /// Adds an option to specify the [crate::command_line::key_command] grouper.
+    pub fn groupers_by_key_command(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_key_command")
+                .long("key-command")
+                .value_name("cmd")
+                .takes_value(true)
+                .help("Group by the line that cmd prints for each token.")
+                .long_help(
+                    "Compute keys by piping tokens, one per line, to the shell command cmd, which \
+                    must print one key per line for each token, in order. To save starting a \
+                    process per token, cmd runs once per batch of tokens; see --key-batch. Like \
+                    -c, cmd runs in the shell named by SHELL, or by --shell. Tokens can't contain \
+                    newlines. Any classifier that reads lines works, e.g. \
+                    --key-command 'cut -d: -f2 | tr A-Z a-z'."
+                )
+        )
+    }
+
+    /// Adds an option to select a grouper from [crate::groupers::registry].
+    pub fn groupers_by_named(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("groupers_by_named")
+                .long("grouper")
+                .value_name("name")
+                .takes_value(true)
+                .help("Group by the grouper that this program registered as name.")
+                .long_help(
+                    "Group by a grouper that the program registered as name at runtime. The \
+                    groupby binary registers none, but programs built on the groupby library \
+                    can register their own; see groupby::groupers::registry."
+                )
+        )
+    }
+
+    /// Adds the grouper choices into a group: choose at least one. Choosing several groups by a
+    /// composite key; see [crate::command_line::options::GroupingSpecifier::Composite].
+    pub fn group_groupers(self) -> Self {
+        build!(
+            self,
+            group,
+            ArgGroup::new("groupers")
+                .args(&GROUPERS)
+                .multiple(true)
+                .required(true)
+        )
+    }
+
+    /// Adds a section for customizing the behavior of groupers.
+    pub fn grouper_options(self) -> Self {
+        self.grouper_options_heading()
+            .grouper_options_capture_group()
+            .grouper_options_unmatched()
+            .grouper_options_field_sep()
+            .grouper_options_word_regex()
+            .grouper_options_bucket()
+            .grouper_options_bytes()
+            .grouper_options_domain()
+            .grouper_options_ignore_case()
+            .grouper_options_key_sep()
+            .grouper_options_key_batch()
+            .grouper_options_label()
+            .grouper_options_unique()
+            .grouper_options_min_size()
+            .grouper_options_max_size()
+            .grouper_options_count_values()
+    }
+
+    /// Adds the grouper options heading.
+    pub fn grouper_options_heading(self) -> Self {
+        build!(self, next_help_heading, "GROUPER OPTIONS")
+    }
+
+    /// Adds an option to specify a capture group number or name when using a regex grouper.
+    pub fn grouper_options_capture_group(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_capture_group")
+                .long("capture-group")
+                .alias("capture-groups")
+                .takes_value(true)
+                .value_name("grp")
+                .help("When used with -r, match a capture group by number or name.")
+                .long_help(
+                    "When used with -r, match a specific capture group by number or name. Group \
+                    number 0 matches the entire pattern. To key by several groups at once, list \
+                    them separated by commas, e.g. --capture-groups date,level; their matches are \
+                    joined with --key-sep. Tokens where any of them doesn't take part in the \
+                    match count as unmatched. Naming a group that the pattern doesn't have is an \
+                    error."
+                )
+        )
+    }
+
+    /// Adds an option to choose what happens to tokens that the regex grouper doesn't match.
+    pub fn grouper_options_unmatched(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_unmatched")
+                .long("unmatched")
+                .takes_value(true)
+                .value_name("policy")
+                .requires("groupers_by_regex")
+                .help("When used with -r, choose what happens to lines that don't match.")
+                .long_help(
+                    "When used with -r, choose what happens to lines that don't match the \
+                    pattern: \"empty\" (the default) stores them in the blank group, \"\"; \
+                    \"skip\" drops them; \"error\" stops with an error at the first one; and \
+                    \"group:NAME\" stores them in the group NAME."
+                )
+        )
+    }
+
+    /// Adds an option to specify the field delimiter when using the field grouper.
+    pub fn grouper_options_field_sep(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_field_sep")
+                .short('d')
+                .long("field-sep")
+                .takes_value(true)
+                .value_name("delim")
+                .help(
+                    "When used with --field, separate fields with delim instead of a tab. With \
+                    --column, use delim instead of a comma."
+                )
+        )
+    }
+
+    /// Adds an option to define a word when using the nth word grouper.
+    pub fn grouper_options_word_regex(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_word_regex")
+                .long("word-regex")
+                .takes_value(true)
+                .value_name("pattern")
+                .help("When used with --word, define a word as any match of pattern.")
+        )
+    }
+
+    /// Adds an option to choose the time span of each group when grouping by timestamp.
+    pub fn grouper_options_bucket(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_bucket")
+                .long("bucket")
+                .takes_value(true)
+                .value_name("span")
+                .possible_values(TIME_SPANS)
+                .default_value("day")
+                .help("When used with --date-format, group timestamps by this time span.")
+        )
+    }
+
+    /// Adds an option to count bytes rather than characters with -f and -l.
+    pub fn grouper_options_bytes(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_bytes")
+                .long("bytes")
+                .help("When used with -f or -l, count bytes rather than characters.")
+                .long_help(
+                    "When used with -f or -l, count bytes rather than characters. A multibyte \
+                    character that's cut in two is replaced with U+FFFD, the Unicode replacement \
+                    character."
+                )
+        )
+    }
+
+    /// Adds an option to group URLs by registrable domain rather than by host.
+    pub fn grouper_options_domain(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_domain")
+                .long("domain")
+                .help("When used with --url-host, group by registrable domain, e.g. example.com.")
+                .long_help(
+                    "When used with --url-host, group by the host's registrable domain, so that \
+                    www.example.com and api.example.com share the group example.com. This keeps \
+                    the last two labels of the host, or three for common country-code suffixes \
+                    like .co.uk, rather than consulting the Public Suffix List. IP addresses are \
+                    left as they are."
+                )
+        )
+    }
+
+    /// Adds an option to lowercase keys, so that keys differing only in case share a group.
+    pub fn grouper_options_ignore_case(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_ignore_case")
+                .short('i')
+                .long("ignore-case")
+                .help("Ignore case when grouping, e.g. group Foo.TXT with foo.txt.")
+                .long_help(
+                    "Ignore case when grouping: lowercase every key, so that keys differing only \
+                    in case share a group. For instance, with --extension, Foo.TXT and foo.txt \
+                    are both stored in the group \"txt\". Tokens themselves are unchanged. Works \
+                    with every grouper. Labels (see --label) are matched against the lowercased \
+                    keys."
+                )
+        )
+    }
+
+    /// Adds an option to choose the separator between the parts of a composite key.
+    pub fn grouper_options_key_sep(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_key_sep")
+                .long("key-sep")
+                .takes_value(true)
+                .value_name("sep")
+                .default_value(" ")
+                .help("When using several groupers, join their keys with sep.")
+                .long_help(
+                    "When using several groupers at once, join their keys with sep, in the order \
+                    the groupers appear on the command line. For instance, -f 10 --field 3 -d ' ' \
+                    --key-sep / puts \"2024-03-01 12:00 ERROR disk full\" in the group \
+                    \"2024-03-01/ERROR\". Several capture groups listed with --capture-groups \
+                    are joined with sep, too."
+                )
+        )
+    }
+
+    /// Adds an option to set how many tokens --key-command sends to each run of its command.
+    pub fn grouper_options_key_batch(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_key_batch")
+                .long("key-batch")
+                .takes_value(true)
+                .value_name("n")
+                .requires("groupers_by_key_command")
+                .help("When used with --key-command, run the command once per n tokens.")
+                .long_help(
+                    "When used with --key-command, send the command n tokens each time it runs, \
+                    rather than 1000. Larger batches start fewer processes; smaller ones group \
+                    tokens sooner, e.g. with --stream. When --key-command is combined with other \
+                    groupers, tokens are grouped by their combined keys once their batch is full."
+                )
+        )
+    }
+
+    /// Adds an option to coalesce keys into labeled groups. See [crate::command_line::labels].
+    pub fn grouper_options_label(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_label")
+                .long("label")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .value_name("rule")
+                .help("Rename matching keys to a label, e.g. 'a-m:first-half'. Repeatable.")
+                .long_help(
+                    "After grouping, place every key that matches a rule into the group named by \
+                    that rule's label. Each rule has the form PATTERN:LABEL. A pattern of the \
+                    form LOW-HIGH matches keys whose leading characters fall between LOW and HIGH \
+                    (inclusive), so 'a-m:first-half' matches \"apple\" and \"mango\". Either \
+                    bound may be empty to leave that end open. Any other pattern matches keys \
+                    that start with it, e.g. 'jp:images'. Labels cannot contain colons.\n\
+                    \n\
+                    This option may be repeated; the first matching rule wins. Keys that match no \
+                    rule are left unchanged."
+                )
+        )
+    }
+
+    /// Adds an option to remove duplicate values from each group.
+    pub fn grouper_options_unique(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_unique")
+                .long("unique")
+                .help("Remove duplicate values from each group, keeping the first of each.")
+                .long_help(
+                    "After grouping, remove duplicate values from each group, keeping only the \
+                    first occurrence of each, in input order. The same value may still appear in \
+                    different groups. Commands run with -c receive the deduplicated groups.\n\
+                    \n\
+                    With --stats, also print the number of duplicates removed."
+                )
+        )
+    }
+
+    /// Adds an option to drop groups with too few values.
+    pub fn grouper_options_min_size(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_min_size")
+                .long("min-size")
+                .value_name("n")
+                .takes_value(true)
+                .help("Drop groups with fewer than n values.")
+                .long_help(
+                    "After grouping (and after --unique), drop groups with fewer than n values. \
+                    Dropped groups aren't printed or counted in --stats, and commands run with \
+                    -c don't run over them. For instance, --min-size 2 shows only groups with \
+                    duplicates, such as files with the same content hash."
+                )
+        )
+    }
+
+    /// Adds an option to drop groups with too many values.
+    pub fn grouper_options_max_size(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_max_size")
+                .long("max-size")
+                .value_name("n")
+                .takes_value(true)
+                .help("Drop groups with more than n values.")
+                .long_help(
+                    "After grouping (and after --unique), drop groups with more than n values, \
+                    like --min-size."
+                )
+        )
+    }
+
+    /// Adds an option to print each distinct value in a group once, with its count.
+    pub fn grouper_options_count_values(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("grouper_options_count_values")
+                .long("count-values")
+                .conflicts_with_all(&["grouper_options_unique", "output_count_only"])
+                .help("Collapse identical values in each group into one, prefixed with its count.")
+                .long_help(
+                    "After grouping (and after --min-size and --max-size), collapse identical \
+                    values within each group into a single value prefixed with the number of \
+                    times it occurred, like uniq -c, e.g. \"3 foo.txt\". Values appear in the \
+                    order of their first occurrence. Commands run with -c receive the counted \
+                    values, and --sort-groups size orders groups by their number of distinct \
+                    values."
+                )
+        )
+    }
+
+    /// Adds a section for output options.
+    pub fn output_separator_options(self) -> Self {
+        self.output_separator_heading()
+            .output_null_separators()
+            .output_space_separators()
+            .output_custom_separators()
+            .group_output_separator_options()
+    }
+
+    /// Adds the output separator heading.
+    pub fn output_separator_heading(self) -> Self {
+        build!(
+            self,
+            next_help_heading,
+            "OUTPUT SEPARATOR OPTIONS (choose zero or one)"
+        )
+    }
+
+    /// Adds an option to separate records by null characters on output.
+    pub fn output_null_separators(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_null_separators")
+                .long("print0")
+                .help("When outputting lines, separate them with a null character, not a newline.")
+                .long_help(
+                    "When outputting lines, separate them with a null character rather than a \
+                    newline. This option is meant for compatibility with xargs -0."
+                )
+        )
+    }
+
+    /// Adds an option to separate records by spaces on output.
+    pub fn output_space_separators(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_space_separators")
+                .long("printspace")
+                .help("When outputting lines, separate them with a space rather than a newline.")
+        )
+    }
+
+    /// Adds an option to separate records by a custom string on output.
+    pub fn output_custom_separators(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_custom_separators")
+                .long("print-sep")
+                .value_name("sep")
+                .takes_value(true)
+                .help("When outputting lines, separate them with sep, e.g. ', ' or '\\t'.")
+                .long_help(
+                    "When outputting lines, separate them with sep rather than a newline, e.g. \
+                    \", \" or '\\t'. Escape sequences are interpreted as in --split (see \
+                    --literal)."
+                )
+        )
+    }
+
+    /// Adds the output separator options into a group: choose zero or one.
+    pub fn group_output_separator_options(self) -> Self {
+        build!(
+            self,
+            group,
+            ArgGroup::new("output_separators").args(&[
+                "output_null_separators",
+                "output_space_separators",
+                "output_custom_separators",
+            ])
+        )
+    }
+
+    /// Adds a section for general output options.
+    pub fn output_options(self) -> Self {
+        self.output_options_header()
+            .output_no_headers()
+            .output_header_format()
+            .output_no_header_colon()
+            .output_only_group_names()
+            .output_run_command()
+            .output_shell()
+            .output_dry_run()
+            .output_sequential()
+            .output_jobs()
+            .output_max_args()
+            .output_max_stdin_bytes()
+            .output_nice()
+            .output_max_memory()
+            .output_max_open_files()
+            .output_capture_stderr()
+            .output_live()
+            .output_incremental()
+            .output_with_values()
+            .output_fail_fast()
+            .output_keep_going()
+            .output_stats()
+            .output_stats_detail()
+            .output_timings()
+            .output_checkpoint()
+            .output_resume()
+            .output_sort()
+            .output_sort_groups()
+            .output_reverse()
+            .output_presorted_streaming()
+            .output_stream()
+            .output_low_memory()
+            .output_intern()
+            .output_json()
+            .output_count_only()
+            .output_format()
+            .output_quote()
+            .group_output_failure_policy_options()
+            .group_shell_commands()
+    }
+
+    /// Adds the general output options header.
+    pub fn output_options_header(self) -> Self {
+        build!(self, next_help_heading, "GENERAL OUTPUT OPTIONS")
+    }
+
+    /// Adds an option to skip outputting group names at final output. No interaction with -c.
+    pub fn output_no_headers(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_no_headers")
+                .long("no-headers")
+                .help("At final output, do not print group headers. Does not affect -c.")
+                .long_help(
+                    "When printing final output, do not print a header before each group. Only print the final output for each group, back-to-back. Groups are still sorted by group name.\n\
+                    \n\
+                    When used with -c, commands are not affected in any way. The only difference is that final results will be printed back-to-back, with no delimiter between them. This may be useful for chaining terminal filters on this program's stdout."
+                )
+        )
+    }
+
+    /// Adds an option to customize each group's header.
+    pub fn output_header_format(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_header_format")
+                .long("header-format")
+                .value_name("template")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "output_no_headers",
+                    "output_only_group_names",
+                    "output_format",
+                    "output_json",
+                    "output_count_only",
+                ])
+                .help("Print each group's header by filling in a template, e.g. \"# {key}\".")
+                .long_help(
+                    "Print each group's header by filling in template instead of printing the \
+                    group's key followed by a colon. The template uses the same placeholders \
+                    and escapes as --format, e.g. --header-format '### {key} ({count})'. With \
+                    --stats, the group's statistics still follow the header in parentheses."
+                )
+        )
+    }
+
+    /// Adds an option to leave the colon off each group's header.
+    pub fn output_no_header_colon(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_no_header_colon")
+                .long("no-header-colon")
+                .conflicts_with_all(&[
+                    "output_header_format",
+                    "output_no_headers",
+                    "output_only_group_names",
+                    "output_format",
+                    "output_json",
+                    "output_count_only",
+                ])
+                .help("Print each group's header as its key alone, without a colon.")
+                .long_help(
+                    "Print each group's header as its key alone, without the trailing colon. \
+                    This is the same as --header-format '{key}'."
+                )
+        )
+    }
+
+    /// Adds an option to output only group names, omitting group contents.
+    pub fn output_only_group_names(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_only_group_names")
+                .long("only-group-names")
+                .help("Output only group names, omitting group contents.")
+                .long_help(
+                    "Output only group names, omitting group contents.\n\
+                    \n\
+                    When used with -c, passes the name of each group to its command instead of \
+                    passing the group's contents. Otherwise, when used with --print0, prints each \
+                    name exactly as it is, followed by a null character, for xargs -0; \
+                    --stats and --quote don't change the names."
+                )
+        )
+    }
+
+    /// Adds an option to run a command over each group.
+    pub fn output_run_command(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_run_command")
+                .short('c')
+                .long("run-command")
+                .value_name("cmd")
+                .takes_value(true)
+                .help(
+                    "Execute command cmd for each group, passing the group via stdin."
+                )
+                .long_help(
+                    "Execute cmd as a shell command for each group, passing the group via standard \
+                    input, one match per line. Each command runs as a command in the shell \
+                    specified by --shell or else the SHELL variable (or /bin/sh if SHELL isn't \
+                    set), just as if you had written $SHELL -c \"cmd\". After all commands are run, the output for each group's command will \
+                    be printed instead of the group's contents.\n\
+                    \n\
+                    Each {} or {key} in cmd is replaced with the group's name, quoted for the \
+                    shell, e.g. -c 'mkdir -p out/{} && cat > out/{}/list.txt'. Write {{}} or \
+                    {{key}} for a literal {} or {key}, e.g. -c 'xargs -I{{}} cp {{}} dest/'. \
+                    Each command's environment also describes its group: GROUPBY_KEY is the group's name, \
+                    GROUPBY_SIZE is its number of values, and GROUPBY_INDEX is its position in the \
+                    output, counting from 0.\n\
+                    \n\
+                    When you use this option, most other output options affect the way each group \
+                    is passed to a command's standard input. When printing the outputs of the \
+                    commands, groupby resets most output-formatting options to their defaults.\n\
+                    \n\
+                    The commands are run in parallel and may run in arbitrary order. The commands' \
+                    outputs are printed in order by group name."
+                )
+        )
+    }
+
+    /// Adds an option to choose the shell that runs commands.
+    pub fn output_shell(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_shell")
+                .long("shell")
+                .value_name("path")
+                .takes_value(true)
+                .requires("shell_commands")
+                .help("When used with -c or --key-command, run commands in the shell at path.")
+                .long_help(
+                    "When used with -c or --key-command, run commands in the shell at path, e.g. \
+                    /bin/bash, instead of the shell named by the SHELL environment variable. The \
+                    shell must accept -c followed by a command string."
+                )
+        )
+    }
+
+    /// Adds an option to describe commands instead of running them.
+    pub fn output_dry_run(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_dry_run")
+                .long("dry-run")
+                .requires("output_run_command")
+                .conflicts_with_all(&[
+                    "output_presorted_streaming",
+                    "output_count_only",
+                    "output_json",
+                ])
+                .help("When used with -c, show each group's command without running it.")
+                .long_help(
+                    "When used with -c, print each group's command line, shell, working \
+                    directory, and environment, along with a preview of the standard input it \
+                    would receive, instead of running anything. If --max-args or \
+                    --max-stdin-bytes would split a group over several invocations, each \
+                    invocation's input is shown separately. --checkpoint is ignored, so every \
+                    group is shown and no checkpoint file is created."
+                )
+        )
+    }
+
+    /// Adds an option to run commands sequentially rather than in parallel.
+    pub fn output_sequential(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_sequential")
+                .long("sequential")
+                .help("When used with -c, run commands in sequence, ordered by group name.")
+                .long_help(
+                    "When used with -c, run commands in sequence, ordered by group name, using a \
+                    single thread. This may be much slower. This option has no effect if used \
+                    without -c."
+                )
+        )
+    }
+
+    /// Adds an option to limit how many commands run at a time.
+    pub fn output_jobs(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_jobs")
+                .short('j')
+                .long("jobs")
+                .value_name("n")
+                .takes_value(true)
+                .requires("output_run_command")
+                .conflicts_with("output_sequential")
+                .help("When used with -c, run at most n commands at a time.")
+                .long_help(
+                    "When used with -c, run at most n commands at a time, rather than one per CPU \
+                    core. This is useful for commands that use a lot of memory or call a \
+                    rate-limited service. Commands still run in arbitrary order; to run them in \
+                    order, use --sequential instead."
+                )
+        )
+    }
+
+    /// Adds an option to limit how many values are passed to each invocation of a command.
+    pub fn output_max_args(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_max_args")
+                .long("max-args")
+                .value_name("n")
+                .takes_value(true)
+                .requires("output_run_command")
+                .help("When used with -c, pass at most n values to each invocation of a command.")
+                .long_help(
+                    "When used with -c, pass at most n values to each invocation of a command, \
+                    like xargs -n. A larger group runs its command several times, one after \
+                    another, and the outputs are printed together under the group's header. If \
+                    any invocation fails, the group's command counts as failed. Has no effect \
+                    with --only-group-names."
+                )
+        )
+    }
+
+    /// Adds an option to limit how much input is passed to each invocation of a command.
+    pub fn output_max_stdin_bytes(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_max_stdin_bytes")
+                .long("max-stdin-bytes")
+                .value_name("n")
+                .takes_value(true)
+                .requires("output_run_command")
+                .help("When used with -c, pass at most n bytes to each invocation of a command.")
+                .long_help(
+                    "When used with -c, pass at most n bytes of values, counting separators, to \
+                    each invocation of a command, running it several times for a larger group \
+                    as with --max-args. A single value larger than n is passed on its own. May be \
+                    combined with --max-args, in which case both limits apply."
+                )
+        )
+    }
+
+    /// Adds an option to run commands at reduced priority.
+    pub fn output_nice(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_nice")
+                .long("nice")
+                .value_name("n")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .requires("output_run_command")
+                .help("When used with -c, run commands at niceness n, from -20 to 19.")
+                .long_help(
+                    "When used with -c, run commands at niceness n, from -20 (the highest \
+                    priority) to 19 (the lowest), so that they yield the CPU to other work. Only \
+                    root can run commands at a lower niceness than groupby's own. Unix only, as \
+                    are --max-memory and --max-open-files."
+                )
+        )
+    }
+
+    /// Adds an option to limit how much memory each command may use.
+    pub fn output_max_memory(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_max_memory")
+                .long("max-memory")
+                .value_name("size")
+                .takes_value(true)
+                .requires("output_run_command")
+                .help("When used with -c, limit each command's virtual memory to size, e.g. 2G.")
+                .long_help(
+                    "When used with -c, limit each command's virtual memory to size, a number of \
+                    bytes optionally followed by K, M, G, or T, e.g. 2G. Allocations beyond the \
+                    limit fail, so a runaway command fails on its own instead of exhausting the \
+                    machine's memory. The limit covers each command separately, not all of them \
+                    together."
+                )
+        )
+    }
+
+    /// Adds an option to limit how many files each command may have open.
+    pub fn output_max_open_files(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_max_open_files")
+                .long("max-open-files")
+                .value_name("n")
+                .takes_value(true)
+                .requires("output_run_command")
+                .help("When used with -c, limit each command to n open files at once.")
+        )
+    }
+
+    /// Adds an option to capture each command's standard error along with its standard output.
+    pub fn output_capture_stderr(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_capture_stderr")
+                .long("capture-stderr")
+                .requires("output_run_command")
+                .help("When used with -c, print each command's stderr under its group's header.")
+                .long_help(
+                    "When used with -c, capture each command's standard error as well as its \
+                    standard output, and print it after the command's output under the group's \
+                    header, instead of letting it appear on the terminal as soon as the command \
+                    writes it. With --json, each group maps to an object with \"stdout\" and \
+                    \"stderr\" strings."
+                )
+        )
+    }
+
+    /// Adds an option to print commands' output as they run.
+    pub fn output_live(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_live")
+                .long("live")
+                .requires("output_run_command")
+                .conflicts_with_all(&[
+                    "output_capture_stderr",
+                    "output_presorted_streaming",
+                    "output_json",
+                ])
+                .help("When used with -c, print output as commands run, prefixed with group names.")
+                .long_help(
+                    "When used with -c, print each line of each command's output as soon as the \
+                    command writes it, prefixed with the group's name and \" | \", like the logs \
+                    of docker compose, instead of printing each group's output under its header \
+                    once every command has finished. Lines from commands running in parallel \
+                    may be interleaved, but never mixed together. Standard error isn't prefixed."
+                )
+        )
+    }
+
+    /// Adds an option to print each group's command output as soon as it's ready, in order.
+    pub fn output_incremental(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_incremental")
+                .long("incremental")
+                .requires("output_run_command")
+                .conflicts_with_all(&["output_live", "output_presorted_streaming", "output_json",])
+                .help("When used with -c, print each group's output as soon as it's ready.")
+                .long_help(
+                    "When used with -c, print each group's output as soon as its command has \
+                    finished and every group before it has been printed, instead of once every \
+                    command has finished. The output is the same either way; only its timing \
+                    changes. Statistics, if requested, are printed at the end."
+                )
+        )
+    }
+
+    /// Adds an option to print each group's contents along with its command output.
+    pub fn output_with_values(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_with_values")
+                .long("with-values")
+                .requires("output_run_command")
+                .conflicts_with_all(&["output_live", "output_dry_run"])
+                .help("When used with -c, print each group's contents above its output.")
+                .long_help(
+                    "When used with -c, print each group's contents under its header, followed \
+                    by an \"Output:\" line and then the command's output, instead of the \
+                    command's output alone. With --no-headers, the \"Output:\" line is left \
+                    out. With --json, each group's result is an object with a \"values\" array \
+                    alongside its \"stdout\" string."
+                )
+        )
+    }
+
+    /// Adds an option to stop running commands as soon as one fails.
+    pub fn output_fail_fast(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_fail_fast")
+                .long("fail-fast")
+                .requires("output_run_command")
+                .help("When used with -c, stop as soon as a command fails.")
+                .long_help(
+                    "When used with -c, stop as soon as a group's command exits unsuccessfully: \
+                    start no more commands, print no output, and exit with status 1, naming the \
+                    group whose command failed. Commands that are already running in parallel \
+                    still run to completion."
+                )
+        )
+    }
+
+    /// Adds an option to run every command even if some fail, which is the default.
+    pub fn output_keep_going(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_keep_going")
+                .long("keep-going")
+                .requires("output_run_command")
+                .help("When used with -c, run every command even if some fail (the default).")
+                .long_help(
+                    "When used with -c, run every group's command even if some exit \
+                    unsuccessfully, print the output as usual, and then exit with status 1, \
+                    naming the groups whose commands failed. This is the default."
+                )
+        )
+    }
+
+    /// Adds the failure policy options into a group: choose zero or one.
+    pub fn group_output_failure_policy_options(self) -> Self {
+        build!(
+            self,
+            group,
+            ArgGroup::new("output_failure_policy").args(&["output_fail_fast", "output_keep_going"])
+        )
+    }
+
+    /// Adds the options that run shell commands into a group, for --shell to require.
+    pub fn group_shell_commands(self) -> Self {
+        build!(
+            self,
+            group,
+            ArgGroup::new("shell_commands")
+                .args(&["output_run_command", "groupers_by_key_command"])
+                .multiple(true)
+        )
+    }
+
+    /// Adds an option to display statistics for each group and for the collection as a whole.
+    pub fn output_stats(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_stats")
+                .long("stats")
+                .help("Print statistics about groups alongside normal output.")
+                .long_help(
+                    "Print an item count and byte count for each group, plus statistics about \
+                    the overall collection, in addition to any other output (as specified by \
+                    other options). Byte counts include a separator after each value.\n\
+                    \n\
+                    This option does not affect the text sent to each command by -c. When used \
+                    with -c, the final output is augmented with statistics about the groups and \
+                    their contents, plus how long each group's command took, its exit status if it \
+                    failed, and the number of commands that failed."
+                )
+        )
     }
 
-    /// Adds the output separator options into a group: choose zero or one.
-    pub fn group_output_separator_options(self) -> Self {
+    /// Adds an option to include more detailed statistics about group sizes.
+    pub fn output_stats_detail(self) -> Self {
         build!(
             self,
-            group,
-            ArgGroup::new("output_separators")
-                .args(&["output_null_separators", "output_space_separators"])
+            arg,
+            Arg::new("output_stats_detail")
+                .long("stats-detail")
+                .value_name("percentiles")
+                .takes_value(true)
+                .min_values(0)
+                .max_values(1)
+                .require_equals(true)
+                .default_missing_value("90,99")
+                .requires("output_stats")
+                .help("With --stats, also describe the spread of group sizes.")
+                .long_help(
+                    "When used with --stats, also print the standard deviation of group sizes, \
+                    the percentiles of group sizes given with --stats-detail=percentiles (a \
+                    comma-separated list, from 0 to 100; default: 90,99), and a histogram of group sizes, with a bucket for each \
+                    power of two. Percentiles use the nearest-rank method, so with an even number \
+                    of groups, p50 is the smaller of the two middle sizes, while Median is the \
+                    larger. Useful when group sizes are heavily skewed, which the median, \
+                    average, minimum, and maximum alone can hide."
+                )
         )
     }
 
-    /// Adds a section for general output options.
-    pub fn output_options(self) -> Self {
-        self.output_options_header()
-            .output_no_headers()
-            .output_only_group_names()
-            .output_run_command()
-            .output_sequential()
-            .output_stats()
+    /// Adds an option to report how long each phase of the run took.
+    pub fn output_timings(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_timings")
+                .long("timings")
+                .conflicts_with_all(&[
+                    "output_presorted_streaming",
+                    "output_stream",
+                    "output_low_memory",
+                    "output_intern",
+                    "output_dry_run",
+                ])
+                .help("Report how long each phase of the run took on standard error.")
+                .long_help(
+                    "When groupby finishes, print how long it spent reading input, grouping it, \
+                    running commands, and writing output, plus the total, to standard error. \
+                    When used with -c, also print how long each group's command took, slowest \
+                    first. Useful for finding out where a big run spends its time."
+                )
+        )
     }
 
-    /// Adds the general output options header.
-    pub fn output_options_header(self) -> Self {
-        build!(self, next_help_heading, "GENERAL OUTPUT OPTIONS")
+    /// Adds an option to record which groups' commands have completed in a checkpoint file.
+    pub fn output_checkpoint(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_checkpoint")
+                .long("checkpoint")
+                .value_name("file")
+                .takes_value(true)
+                .requires("output_run_command")
+                .help("When used with -c, record each group whose command succeeds in file.")
+                .long_help(
+                    "When used with -c, record the name of each group whose command exits \
+                    successfully in file, so that an interrupted run can be resumed later with \
+                    --resume. Without --resume, any existing checkpoint file is overwritten."
+                )
+        )
     }
 
-    /// Adds an option to skip outputting group names at final output. No interaction with -c.
-    pub fn output_no_headers(self) -> Self {
+    /// Adds an option to resume from a checkpoint file, skipping groups that already completed.
+    pub fn output_resume(self) -> Self {
         build!(
             self,
             arg,
-            Arg::new("output_no_headers")
-                .long("no-headers")
-                .help("At final output, do not print group headers. Does not affect -c.")
+            Arg::new("output_resume")
+                .long("resume")
+                .requires("output_checkpoint")
+                .help("When used with --checkpoint, skip groups that already completed.")
                 .long_help(
-                    "When printing final output, do not print a header before each group. Only print the final output for each group, back-to-back. Groups are still sorted by group name.\n\
-                    \n\
-                    When used with -c, commands are not affected in any way. The only difference is that final results will be printed back-to-back, with no delimiter between them. This may be useful for chaining terminal filters on this program's stdout."
+                    "When used with --checkpoint, skip the groups that the checkpoint file lists as \
+                    complete, and add newly completed groups to it. Skipped groups are omitted \
+                    from the final output. A missing checkpoint file is treated as empty."
                 )
         )
     }
 
-    /// Adds an option to output only group names, omitting group contents.
-    pub fn output_only_group_names(self) -> Self {
+    /// Adds an option to choose the order in which groups are printed.
+    pub fn output_sort(self) -> Self {
         build!(
             self,
             arg,
-            Arg::new("output_only_group_names")
-                .long("only-group-names")
-                .help("Output only group names, omitting group contents.")
+            Arg::new("output_sort")
+                .long("sort")
+                .value_name("order")
+                .takes_value(true)
+                .possible_values(["bytes", "collate", "locale", "natural", "numeric"])
+                .help("Print groups in the given order of their names (default: bytes).")
                 .long_help(
-                    "Output only group names, omitting group contents.\n\
+                    "Print groups in the given order of their names. \"bytes\" (the default, \
+                    except with --length, --shard, and --chunk, which default to \"natural\", \
+                    and --bucket-size, which defaults to \"numeric\") sorts \
+                    names by their raw bytes, so \"Zebra\" comes before \"apple\". \"collate\" \
+                    sorts names like a dictionary: ignoring case and accents first, then placing \
+                    unaccented letters before accented ones and lowercase before uppercase. For \
+                    instance, \"Äpfel\", \"apple\", and \"Apple\" print in that order. \
+                    \"locale\" is like \"collate\", but follows the alphabet of the language \
+                    chosen by LC_ALL, LC_COLLATE, or LANG; for instance, with LANG=sv_SE.UTF-8, \
+                    \"ö\" is a letter of its own that comes after \"z\". Danish, Norwegian, \
+                    Finnish, Spanish, and Swedish have their own alphabets; other languages sort \
+                    as with \"collate\". \
+                    \"natural\" compares runs of digits by their numeric values, so \"img2.png\" \
+                    comes before \"img10.png\". \"numeric\" compares names that are numbers, \
+                    like \"-1\", \"10\", or \"2.5\", by value and ranges like \"-30--21\" by \
+                    their lower bounds, putting other names last.\n\
                     \n\
-                    When used with -c, passes the name of each group to its command instead of \
-                    passing the group's contents."
+                    This option is not affected by -c, except that with --sequential, commands \
+                    also run in the order their groups are printed."
                 )
         )
     }
 
-    /// Adds an option to run a command over each group.
-    pub fn output_run_command(self) -> Self {
+    /// Adds an option to order groups by size.
+    pub fn output_sort_groups(self) -> Self {
         build!(
             self,
             arg,
-            Arg::new("output_run_command")
-                .short('c')
-                .long("run-command")
-                .value_name("cmd")
+            Arg::new("output_sort_groups")
+                .long("sort-groups")
+                .value_name("order")
                 .takes_value(true)
-                .help(
-                    "Execute command cmd for each group, passing the group via stdin."
+                .possible_values(["key", "size", "size-desc", "natural", "numeric"])
+                .conflicts_with_all(&["output_presorted_streaming", "output_live"])
+                .help("Print groups by key (the default), or by size: smallest or largest first.")
+                .long_help(
+                    "Print groups in the given order. \"key\" (the default) orders groups by \
+                    name, as given by --sort. \"size\" prints the smallest groups first, and \
+                    \"size-desc\" the largest. Groups of the same size are printed in --sort \
+                    order. A group's size is its number of values, even if -c replaces them \
+                    with a command's output. \"natural\" and \"numeric\" are the same as \
+                    --sort natural and --sort numeric, and can't be combined with --sort.\n\
+                    \n\
+                    With -c, GROUPBY_INDEX follows this order, and so do commands run with \
+                    --sequential."
                 )
+        )
+    }
+
+    /// Adds an option to print groups in reverse order.
+    pub fn output_reverse(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_reverse")
+                .long("reverse")
+                .conflicts_with_all(&["output_presorted_streaming", "output_live"])
+                .help("Print groups in reverse order of their names.")
                 .long_help(
-                    "Execute cmd as a shell command for each group, passing the group via standard \
-                    input, one match per line. Each command runs as a command in the shell \
-                    specified by the SHELL variable, just as if you had written $SHELL -c \
-                    \"cmd\". After all commands are run, the output for each group's command will \
-                    be printed instead of the group's contents.\n\
+                    "Print groups in the reverse of the order given by --sort, e.g. \"z\" before \
+                    \"a\". With --sort-groups, groups of the same size are printed in reverse \
+                    order, too.\n\
                     \n\
-                    When you use this option, most other output options affect the way each group \
-                    is passed to a command's standard input. When printing the outputs of the \
-                    commands, groupby resets most output-formatting options to their defaults.\n\
+                    With -c, GROUPBY_INDEX follows this order, and so do commands run with \
+                    --sequential."
+                )
+        )
+    }
+
+    /// Adds an option to process presorted input one group at a time.
+    pub fn output_presorted_streaming(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_presorted_streaming")
+                .long("presorted-streaming")
+                .conflicts_with_all(&["output_stats", "output_sort"])
+                .help("Input is sorted by key: output each group as soon as it's complete.")
+                .long_help(
+                    "Promise that the input is already sorted by key, so that each group is \
+                    complete as soon as a token with a different key arrives. Output each group \
+                    (or run its command) immediately, then discard it, so that memory use is \
+                    proportional to the largest group rather than the whole input. Groups print \
+                    in input order, and commands run one at a time.\n\
                     \n\
-                    The commands are run in parallel and may run in arbitrary order. The commands' \
-                    outputs are printed in order by group name."
+                    If the input is not actually sorted, a key that appears in several separate \
+                    runs produces several separate groups."
                 )
         )
     }
 
-    /// Adds an option to run commands sequentially rather than in parallel.
-    pub fn output_sequential(self) -> Self {
+    /// Adds an option to write each token with its key as soon as the key is known.
+    pub fn output_stream(self) -> Self {
         build!(
             self,
             arg,
-            Arg::new("output_sequential")
-                .long("sequential")
-                .help("When used with -c, run commands in sequence, ordered by group name.")
+            Arg::new("output_stream")
+                .long("stream")
+                .conflicts_with_all(&[
+                    "output_only_group_names",
+                    "output_run_command",
+                    "output_stats",
+                    "output_sort",
+                    "output_sort_groups",
+                    "output_reverse",
+                    "output_presorted_streaming",
+                    "output_json",
+                    "output_count_only",
+                    "output_format",
+                    "output_quote",
+                    "grouper_options_unique",
+                    "grouper_options_min_size",
+                    "grouper_options_max_size",
+                    "grouper_options_count_values",
+                ])
+                .help("Print each token and its key as soon as it's read, without grouping.")
                 .long_help(
-                    "When used with -c, run commands in sequence, ordered by group name, using a \
-                    single thread. This may be much slower. This option has no effect if used \
-                    without -c."
+                    "Instead of collecting groups, print each token as soon as its key is known, \
+                    as the key, a tab, and the token, each followed by the output separator \
+                    (newline by default). Nothing is stored, so memory use stays flat however \
+                    long the input runs, which suits long-running pipelines. Tokens aren't \
+                    gathered into groups; pipe the output through groupby --kv to gather them \
+                    later. For example:\n\
+                    \n    \
+                    tail -f access.log | groupby --stream --url-host | grep -v '^example.com'"
                 )
         )
     }
 
-    /// Adds an option to display statistics for each group and for the collection as a whole.
-    pub fn output_stats(self) -> Self {
+    /// Adds an option to spill groups to disk when they outgrow memory.
+    pub fn output_low_memory(self) -> Self {
         build!(
             self,
             arg,
-            Arg::new("output_stats")
-                .long("stats")
-                .help("Print statistics about groups alongside normal output.")
+            Arg::new("output_low_memory")
+                .long("low-memory")
+                .conflicts_with_all(&[
+                    "output_stats",
+                    "output_sort",
+                    "output_sort_groups",
+                    "output_reverse",
+                    "output_presorted_streaming",
+                    "output_stream",
+                    "output_json",
+                    "output_count_only",
+                    "output_dry_run",
+                    "output_incremental",
+                ])
+                .help("Spill groups to a temporary file when they outgrow memory.")
+                .long_help(
+                    "Keep at most 64 MiB of values in memory while grouping, writing the rest to \
+                    a temporary file that's removed when groupby exits. This lets groupby group \
+                    inputs far larger than RAM. Groups are printed in the default order that \
+                    --sort describes, e.g. by number with --length, one at a time, reading each \
+                    group's values back from disk only when it's printed. With --run-command, commands run one at a time as each group is \
+                    read back. For example:\n\
+                    \n    \
+                    zcat huge.log.gz | groupby --low-memory -f1 -c 'wc -l'"
+                )
+        )
+    }
+
+    /// Adds an option to store each distinct value only once.
+    pub fn output_intern(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_intern")
+                .long("intern")
+                .conflicts_with_all(&[
+                    "output_stats",
+                    "output_sort",
+                    "output_sort_groups",
+                    "output_reverse",
+                    "output_presorted_streaming",
+                    "output_stream",
+                    "output_low_memory",
+                    "output_json",
+                    "output_count_only",
+                    "output_dry_run",
+                    "output_incremental",
+                ])
+                .help("Store each distinct value only once, to save memory on repetitive input.")
+                .long_help(
+                    "Store each distinct value only once, however many times it appears, and \
+                    share it between every token that repeats it. On input with many repeated \
+                    lines, like most logs, this takes far less memory; on input with few \
+                    repeats, it takes a little more. Groups are printed one at a time, in the \
+                    default order that --sort describes, e.g. by number with --shard. For example:\n\
+                    \n    \
+                    groupby --intern --word 1 access.log"
+                )
+        )
+    }
+
+    /// Adds an option to write final output as JSON.
+    pub fn output_json(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_json")
+                .long("json")
+                .conflicts_with_all(&[
+                    "output_only_group_names",
+                    "output_stats",
+                    "output_presorted_streaming",
+                ])
+                .help("Write final output as a JSON object mapping group names to values.")
+                .long_help(
+                    "Write final output as a single JSON object, followed by a newline, that maps \
+                    each group's name to an array of its values, e.g. for processing with jq. \
+                    Groups appear in the order given by --sort.\n\
+                    \n\
+                    When used with -c, maps each group's name to its command's output, as a \
+                    string, instead. Commands are not affected in any way."
+                )
+        )
+    }
+
+    /// Adds an option to count the values in each group instead of keeping them.
+    pub fn output_count_only(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_count_only")
+                .long("count-only")
+                .conflicts_with_all(&[
+                    "output_only_group_names",
+                    "output_run_command",
+                    "output_stats",
+                    "output_presorted_streaming",
+                    "grouper_options_unique",
+                ])
+                .help("Print each group's name and number of values, without storing the values.")
+                .long_help(
+                    "Keep only a count of the values in each group, discarding the values \
+                    themselves, and print each group's name followed by \": \" and its count, \
+                    e.g. \"txt: 42\". Memory use is proportional to the number of groups rather \
+                    than the size of the input, so this can tally inputs too large to hold in \
+                    memory.\n\
+                    \n\
+                    Groups are printed in the order given by --sort. With --json, writes a JSON \
+                    object mapping each group's name to its count."
+                )
+        )
+    }
+
+    /// Adds an option to print each group using a template.
+    pub fn output_format(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_format")
+                .long("format")
+                .value_name("template")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "output_json",
+                    "output_only_group_names",
+                    "output_count_only",
+                    "output_live",
+                    "output_with_values",
+                    "output_no_headers",
+                ])
+                .help("Print each group by filling in a template, e.g. \"{key}: {count}\".")
                 .long_help(
-                    "Print an item count for each group, plus statistics about the overall \
-                    collection, in addition to any other output (as specified by other options).\n\
+                    "Print each group by filling in template, followed by the output separator, \
+                    instead of printing a header and the group's values. The template may use \
+                    these placeholders:\n\
+                    \n  \
+                    {key}           the group's key\n  \
+                    {count}         the number of values in the group\n  \
+                    {values}        the group's values, joined by the output separator\n  \
+                    {values:JOINER} the group's values, joined by JOINER, e.g. {values:, }\n  \
+                    {output}        with -c, the command's standard output, without trailing \
+                    newlines\n  \
+                    {stderr}        with -c and --capture-stderr, the command's standard error\n\
                     \n\
-                    This option is not affected by -c. When used with -c, the text sent to each \
-                    command does not change. The final output is augmented with statistics about \
-                    the groups and their contents (not about the commands or their outputs)."
+                    Write {{ and }} for literal braces. Escape sequences such as \\t are \
+                    interpreted as in delimiters (see --literal). For instance, \
+                    --format '{key}\\t{count}\\t{values:,}' prints one tab-separated line per \
+                    group. Statistics, if requested, are still printed at the end."
+                )
+        )
+    }
+
+    /// Adds an option to quote printed group names and values.
+    pub fn output_quote(self) -> Self {
+        build!(
+            self,
+            arg,
+            Arg::new("output_quote")
+                .long("quote")
+                .value_name("style")
+                .takes_value(true)
+                .possible_values(["shell"])
+                .conflicts_with("output_json")
+                .help("Quote printed group names and values so a shell reads them back as is.")
+                .long_help(
+                    "Quote each group name and value when printing it. \"shell\" leaves words \
+                    made only of letters, digits, and %+,-./:=@_ as they are, and wraps anything \
+                    else in single quotes, so the output can be pasted into a POSIX shell \
+                    command. This applies to headers, --format templates, and --count-only, but \
+                    not to the output of -c, which is printed as is."
                 )
         )
     }
+
+    /// Adds `groupby`'s subcommands. A subcommand replaces grouping entirely, so it can't be
+    /// combined with any other option.
+    pub fn subcommands(self) -> Self {
+        let command = self
+            .command
+            .subcommand_negates_reqs(true)
+            .args_conflicts_with_subcommands(true);
+        CommandBuilder { command }.subcommand_bench()
+    }
+
+    /// Adds the `bench` subcommand. See [bench_command()].
+    pub fn subcommand_bench(self) -> Self {
+        build!(self, subcommand, bench_command())
+    }
 }
 
 /// To hopefully balance simplicity with correctness, since this is heavily hand-crafted by design,
@@ -507,41 +2620,238 @@ mod args_tests {
 groupby {}
 Dylan Laufenberg <dylan.laufenberg@gmail.com>
 
-Reads lines from standard input and groups them by common substrings. By default, prints the
-resulting groups to standard output.
+Reads lines from the given files, or standard input, and groups them by common substrings. By
+default, prints the resulting groups to standard output.
 
 USAGE:
-    groupby [OPTIONS] <-f <n>|-l <n>|--regex <pattern>|--extension|--counter>
+    groupby [OPTIONS] <-f <n>|-l <n>|--regex <pattern>|--extension|--counter|--kv[=<sep>...]|--field <n>|--word <n>|--date-format <fmt>|--bucket-size <n>|--size-buckets <sizes>|--mtime-bucket <span>|--mime-type|--path-depth <n>|--basename|--dirname|--length|--shard <n>|--chunk <n>|--glob <rule>|--url-host|--json-key <path>|--column <col>|--logfmt <key>|--key-expr <expr>|--key-command <cmd>|--grouper <name>> [file]...
+    groupby <SUBCOMMAND>
 
 OPTIONS:
-    -h, --help       Print help information
-    -V, --version    Print version information
+        --explain           Print a plan of what would be done with the other options, then exit.
+    -h, --help              Print help information
+        --literal           Do not interpret escape sequences, e.g. \\t, in delimiters.
+        --preset <name>     Load options for a common workflow, e.g. extensions or log-by-day.
+        --profile <name>    Load options from the named profile in the config file.
+        --seed <n>          Seed every randomized feature with n, for reproducible runs.
+    -V, --version           Print version information
 
 INPUT-SPLITTING OPTIONS (choose zero or one):
-    -0                     Split input by null characters rather than lines.
-        --split <delim>    Split input on a custom delimiter of your choice.
-    -w                     Group words instead of lines; that is, split input on whitespace.
-
-GROUPERS (choose exactly one):
-        --counter            Place each token in its own, numbered group, starting from 0.
-        --extension          Group by file extension (excluding the leading period).
-    -f <n>                   Group by equivalence on the first n characters.
-    -l <n>                   Group by equivalence on the last n characters.
-    -r, --regex <pattern>    Group by equivalence on the first match against the specified pattern.
+    -0                             Split input by null characters rather than lines.
+        --csv                      Read CSV records, which may span lines, and skip the header row.
+        --invalid-utf8 <policy>    What to do with input that isn't valid UTF-8 (default: lossy).
+                                   [possible values: lossy, skip, strict]
+        --limit <n>                Stop reading input after grouping n tokens.
+    -p, --paragraphs               Split input into paragraphs separated by blank lines.
+        --sample <p>               Keep each token with probability p, e.g. 0.01 or 1%, and drop the
+                                   rest.
+        --skip-empty               Don't group empty tokens, e.g. blank lines.
+        --split <delim>            Split input on a custom delimiter of your choice, e.g. '\\t'.
+        --trim                     Strip leading and trailing whitespace from each token.
+    -w                             Group words instead of lines; that is, split input on whitespace.
+
+INPUT FILE OPTIONS:
+        --files0-from <list>    Read input from the files named in list, each followed by a null
+                                character.
+        --follow-symlinks       With --walk, walk symbolic links to directories.
+    -H, --with-filename         Prefix each value with the name of the file it came from.
+        --hidden                With --walk, include hidden files and directories.
+        --tee[=<file>...]       Copy input to standard output, or to file, and print results to
+                                stderr.
+        --walk <dir>            Group the paths of the files under dir instead of reading input.
+    <file>...               Read input from these files, in order, instead of standard input.
+
+GROUPERS (choose one, or several for a composite key):
+        --basename                Group paths by file name, ignoring directories, to find duplicate
+                                  names.
+        --bucket-size <n>         Group by the first number in each line, in ranges of size n, e.g.
+                                  0-99.
+        --chunk <n>               Split input into numbered groups of n consecutive lines.
+        --column <col>            Group CSV records by a column: a number (from 1) or, with --csv, a
+                                  name.
+        --counter                 Place each token in its own, numbered group, starting from 0.
+        --date-format <fmt>       Group by timestamps written like fmt, e.g. '%Y-%m-%d'. See
+                                  --bucket.
+        --dirname                 Group paths by parent directory.
+        --extension               Group by file extension (excluding the leading period).
+    -f <n>                        Group by equivalence on the first n characters.
+        --field <n>               Group by equivalence on the nth field (from 1), like cut -f. See
+                                  -d.
+        --glob <rule>             Group paths by named glob patterns, e.g. images=*.{{jpg,png}}.
+                                  Repeatable.
+        --grouper <name>          Group by the grouper that this program registered as name.
+        --json-key <path>         Group JSON Lines by the value at path, e.g. .user.id or .tags[0].
+        --key-command <cmd>       Group by the line that cmd prints for each token.
+        --key-expr <expr>         Group by the key that a small expression computes, e.g.
+                                  lower(word(2)).
+        --kv[=<sep>...]           Treat each token as a key, then sep (default: tab), then a value.
+    -l <n>                        Group by equivalence on the last n characters.
+        --length                  Group by length in characters. Sorts naturally unless --sort is
+                                  given.
+        --logfmt <key>            Group logfmt lines (key=value pairs) by the value for key.
+        --mime-type               Group files by MIME type, detected from their contents, e.g.
+                                  image/png.
+        --mtime-bucket <span>     Group files by when they were last modified, e.g. by day or week.
+                                  [possible values: year, month, week, day, hour, minute]
+        --path-depth <n>          Group paths by their nth component (from 1), e.g. top-level
+                                  directory.
+    -r, --regex <pattern>         Group by equivalence on the first match against the specified
+                                  pattern.
+        --shard <n>               Split lines into n groups, 0 to n-1, by a stable hash of each
+                                  line.
+        --size-buckets <sizes>    Group files by size, e.g. 1K,1M,100M makes <1K, 1K-1M, 1M-100M,
+                                  100M+.
+        --url-host                Group URLs by host, e.g. www.example.com. See also --domain.
+        --word <n>                Group by equivalence on the nth word (from 1). Requires
+                                  --word-regex.
 
 GROUPER OPTIONS:
-        --capture-group <grp>    When used with -r, match a capture group by number or name.
+        --bucket <span>           When used with --date-format, group timestamps by this time span.
+                                  [default: day] [possible values: year, month, week, day, hour,
+                                  minute]
+        --bytes                   When used with -f or -l, count bytes rather than characters.
+        --capture-group <grp>     When used with -r, match a capture group by number or name.
+        --count-values            Collapse identical values in each group into one, prefixed with
+                                  its count.
+    -d, --field-sep <delim>       When used with --field, separate fields with delim instead of a
+                                  tab. With --column, use delim instead of a comma.
+        --domain                  When used with --url-host, group by registrable domain, e.g.
+                                  example.com.
+    -i, --ignore-case             Ignore case when grouping, e.g. group Foo.TXT with foo.txt.
+        --key-batch <n>           When used with --key-command, run the command once per n tokens.
+        --key-sep <sep>           When using several groupers, join their keys with sep. [default: \"
+                                  \"]
+        --label <rule>            Rename matching keys to a label, e.g. 'a-m:first-half'.
+                                  Repeatable.
+        --max-size <n>            Drop groups with more than n values.
+        --min-size <n>            Drop groups with fewer than n values.
+        --unique                  Remove duplicate values from each group, keeping the first of
+                                  each.
+        --unmatched <policy>      When used with -r, choose what happens to lines that don't match.
+        --word-regex <pattern>    When used with --word, define a word as any match of pattern.
 
 OUTPUT SEPARATOR OPTIONS (choose zero or one):
-        --print0        When outputting lines, separate them with a null character, not a newline.
-        --printspace    When outputting lines, separate them with a space rather than a newline.
+        --print-sep <sep>    When outputting lines, separate them with sep, e.g. ', ' or '\\t'.
+        --print0             When outputting lines, separate them with a null character, not a
+                             newline.
+        --printspace         When outputting lines, separate them with a space rather than a
+                             newline.
 
 GENERAL OUTPUT OPTIONS:
-    -c, --run-command <cmd>    Execute command cmd for each group, passing the group via stdin.
-        --no-headers           At final output, do not print group headers. Does not affect -c.
-        --only-group-names     Output only group names, omitting group contents.
-        --sequential           When used with -c, run commands in sequence, ordered by group name.
-        --stats                Print statistics about groups alongside normal output.\n",
+    -c, --run-command <cmd>
+            Execute command cmd for each group, passing the group via stdin.
+
+        --capture-stderr
+            When used with -c, print each command's stderr under its group's header.
+
+        --checkpoint <file>
+            When used with -c, record each group whose command succeeds in file.
+
+        --count-only
+            Print each group's name and number of values, without storing the values.
+
+        --dry-run
+            When used with -c, show each group's command without running it.
+
+        --fail-fast
+            When used with -c, stop as soon as a command fails.
+
+        --format <template>
+            Print each group by filling in a template, e.g. \"{{key}}: {{count}}\".
+
+        --header-format <template>
+            Print each group's header by filling in a template, e.g. \"# {{key}}\".
+
+        --incremental
+            When used with -c, print each group's output as soon as it's ready.
+
+        --intern
+            Store each distinct value only once, to save memory on repetitive input.
+
+    -j, --jobs <n>
+            When used with -c, run at most n commands at a time.
+
+        --json
+            Write final output as a JSON object mapping group names to values.
+
+        --keep-going
+            When used with -c, run every command even if some fail (the default).
+
+        --live
+            When used with -c, print output as commands run, prefixed with group names.
+
+        --low-memory
+            Spill groups to a temporary file when they outgrow memory.
+
+        --max-args <n>
+            When used with -c, pass at most n values to each invocation of a command.
+
+        --max-memory <size>
+            When used with -c, limit each command's virtual memory to size, e.g. 2G.
+
+        --max-open-files <n>
+            When used with -c, limit each command to n open files at once.
+
+        --max-stdin-bytes <n>
+            When used with -c, pass at most n bytes to each invocation of a command.
+
+        --nice <n>
+            When used with -c, run commands at niceness n, from -20 to 19.
+
+        --no-header-colon
+            Print each group's header as its key alone, without a colon.
+
+        --no-headers
+            At final output, do not print group headers. Does not affect -c.
+
+        --only-group-names
+            Output only group names, omitting group contents.
+
+        --presorted-streaming
+            Input is sorted by key: output each group as soon as it's complete.
+
+        --quote <style>
+            Quote printed group names and values so a shell reads them back as is. [possible values:
+            shell]
+
+        --resume
+            When used with --checkpoint, skip groups that already completed.
+
+        --reverse
+            Print groups in reverse order of their names.
+
+        --sequential
+            When used with -c, run commands in sequence, ordered by group name.
+
+        --shell <path>
+            When used with -c or --key-command, run commands in the shell at path.
+
+        --sort <order>
+            Print groups in the given order of their names (default: bytes). [possible values:
+            bytes, collate, locale, natural, numeric]
+
+        --sort-groups <order>
+            Print groups by key (the default), or by size: smallest or largest first. [possible
+            values: key, size, size-desc, natural, numeric]
+
+        --stats
+            Print statistics about groups alongside normal output.
+
+        --stats-detail[=<percentiles>...]
+            With --stats, also describe the spread of group sizes.
+
+        --stream
+            Print each token and its key as soon as it's read, without grouping.
+
+        --timings
+            Report how long each phase of the run took on standard error.
+
+        --with-values
+            When used with -c, print each group's contents above its output.
+
+SUBCOMMANDS:
+    bench    Time each phase of groupby on synthetic input, comparing backends.
+    help     Print this message or the help of the given subcommand(s)\n",
                 env!("CARGO_PKG_VERSION")
             )
         );
@@ -559,8 +2869,8 @@ GENERAL OUTPUT OPTIONS:
 groupby {}
 Dylan Laufenberg <dylan.laufenberg@gmail.com>
 
-Reads lines from standard input and groups them by common substrings. By default, prints the
-resulting groups to standard output.
+Reads lines from the given files, or standard input, and groups them by common substrings. By
+default, prints the resulting groups to standard output.
 
 For example, to group lines in a structured log file by the first 10 characters:
 
@@ -572,17 +2882,60 @@ extension (case-sensitive) and print how much disk space each type of file is us
     find ~/Pictures/ -not -type d -print0 \\
         | groupby -0 --extension --print0 -c \"xargs -0 du -chL | tail -n1\"
 
-Note: the lack of an option to group by the first or last n words is an intional omission. There are
-many ways to define a word, and when grouping by words, the exact definition matters. To match based
-on words, please use --regex and supply a definition that works for your use case.
+Note: there is no built-in definition of a word. There are many ways to define one, and when
+grouping by words, the exact definition matters. To group by the nth word, use --word together with
+--word-regex, supplying a definition that works for your use case.
 
 USAGE:
-    groupby [OPTIONS] <-f <n>|-l <n>|--regex <pattern>|--extension|--counter>
+    groupby [OPTIONS] <-f <n>|-l <n>|--regex <pattern>|--extension|--counter|--kv[=<sep>...]|--field <n>|--word <n>|--date-format <fmt>|--bucket-size <n>|--size-buckets <sizes>|--mtime-bucket <span>|--mime-type|--path-depth <n>|--basename|--dirname|--length|--shard <n>|--chunk <n>|--glob <rule>|--url-host|--json-key <path>|--column <col>|--logfmt <key>|--key-expr <expr>|--key-command <cmd>|--grouper <name>> [file]...
+    groupby <SUBCOMMAND>
 
 OPTIONS:
+        --explain
+            Instead of reading input, print a human-readable plan of what the other options would
+            do: how input would be split, how keys would be computed and relabeled, how groups would
+            be collected, how commands would run, and how output would be formatted. Options loaded
+            with --profile or --preset are included.
+
     -h, --help
             Print help information
 
+        --literal
+            Do not interpret escape sequences in delimiters, e.g. for --split or --print-sep. By
+            default, \\t, \\n, \\r, and \\0 stand for a tab, newline, carriage return, and null
+            character; \\xNN stands for the ASCII character with hexadecimal code NN; and \\\\ stands
+            for a single backslash. Any other backslash is kept as-is.
+
+        --preset <name>
+            Load the options for a common workflow. The built-in presets are:
+            
+                extensions    Group file names by extension.
+                log-by-day    Group log lines by their first YYYY-MM-DD date.
+                dupe-names    Group paths by file name, keeping repeated names.
+                domains       Group URLs by registrable domain.
+            
+            The config file (see --profile) can define more presets, or replace these, in
+            [preset.<name>] tables that work just like profiles. Options given on the command line
+            or by --profile take precedence over the preset's.
+
+        --profile <name>
+            Load options from the profile with the given name in
+            $XDG_CONFIG_HOME/groupby/config.toml (by default, ~/.config/groupby/config.toml). Each
+            profile is a table of options, named without leading hyphens, e.g.:
+            
+                [profile.logs]
+                regex = '^(\\d{{4}}-\\d{{2}}-\\d{{2}})'
+                run-command = \"wc -l\"
+            
+            Options given on the command line take precedence over the profile's, including options
+            that conflict with the profile's, e.g. a different grouper.
+
+        --seed <n>
+            Seed every randomized feature with the number n, so that repeated runs over the same
+            input make exactly the same choices. Currently, only --sample is randomized; the bench
+            subcommand takes a --seed of its own for the input it generates. Without this option,
+            each run uses a different, unpredictable seed.
+
     -V, --version
             Print version information
 
@@ -590,17 +2943,145 @@ INPUT-SPLITTING OPTIONS (choose zero or one):
     -0
             Split input by null characters rather than lines.
 
+        --csv
+            Read input as CSV records: one per line, except that a quoted field may contain
+            newlines. The first record is a header row. It isn't grouped, but --column can refer to
+            its column names.
+
+        --invalid-utf8 <policy>
+            What to do with input tokens that aren't valid UTF-8, such as file names from find
+            -print0. \"lossy\" (the default) replaces each invalid sequence with U+FFFD, the
+            replacement character. \"skip\" skips the token and prints a warning to standard error;
+            with -w, it skips the whole line. \"strict\" exits with an error. With --csv, the policy
+            applies to each record.
+            
+            [possible values: lossy, skip, strict]
+
+        --limit <n>
+            Stop reading input once n tokens have been grouped, e.g. to preview the groups of a huge
+            input before a full run. Tokens dropped by --skip-empty or --sample don't count toward
+            n.
+
+    -p, --paragraphs
+            Split input into paragraphs separated by blank lines, like awk's paragraph mode, so that
+            multi-line records such as stack traces or config stanzas are grouped as units. Lines
+            that are only whitespace count as blank. Each paragraph is one token, with its lines
+            joined by newlines.
+
+        --sample <p>
+            Keep each token at random with probability p, written as a number from 0 to 1 or as a
+            percentage, e.g. 0.01 or 1%, and drop the rest. Combined with --count-only, this
+            estimates group sizes cheaply. Use --seed to sample the same tokens on every run.
+
+        --skip-empty
+            Don't group empty tokens, such as blank lines or the empty token after a trailing
+            --split delimiter. With --trim, tokens that are only whitespace are skipped too.
+
         --split <delim>
-            Split input on a custom delimiter of your choice.
+            Split input on a custom delimiter of your choice, e.g. '\\t'.
+
+        --trim
+            Strip leading and trailing whitespace from each token before it's grouped, so that e.g.
+            \"a\" and \"a \" go in the same group. This is most useful with --split, e.g. --split ,
+            --trim for comma-separated lists written with spaces.
 
     -w
             Group words instead of lines; that is, split input on whitespace.
 
-GROUPERS (choose exactly one):
+INPUT FILE OPTIONS:
+        --files0-from <list>
+            Read input from the files named in list, in order, as if they were given as arguments.
+            Each name is followed by a null character, as find -print0 writes them, so there's no
+            limit on how many files can be named. If list is -, read the names from standard input.
+            For example:
+            
+                find /var/log -name '*.log' -print0 | groupby --files0-from - -f 15
+
+        --follow-symlinks
+            With --walk, walk symbolic links to directories as if they were directories. Each
+            directory is walked only once, so links that form a loop are safe.
+
+    -H, --with-filename
+            Prefix each value with the name of the file it came from and a colon, like grep -H, e.g.
+            app.log:ERROR timeout. Keys are found before the prefix is added, so it doesn't affect
+            grouping, but commands run with -c see it. Values read from standard input are prefixed
+            with \"(standard input):\".
+
+        --hidden
+            With --walk, include hidden files and directories.
+
+        --tee[=<file>...]
+            Copy the input, unchanged, to standard output as it's read, or to file if one is given
+            with --tee=file, like tee. Groups are still built, and the results are printed to
+            standard error once the input ends, so groupby can sit in the middle of an existing
+            pipeline. Compressed input is copied after it's decompressed. For example, to keep the
+            errors from a log while counting every level:
+            
+                groupby --tee --logfmt level --count-only app.log | grep level=error
+
+        --walk <dir>
+            Instead of reading input, walk dir recursively and group the path of each file under it,
+            like find dir -not -type d, but in sorted order. Hidden files and directories, whose
+            names start with a dot, are skipped unless --hidden is given. Symbolic links are grouped
+            like files unless --follow-symlinks is given. Entries that can't be read are skipped
+            with a warning. For example, to see how much space each type of file uses:
+            
+                groupby --walk ~/Pictures --extension --print0 -c \"xargs -0 du -ch | tail -n1\"
+
+    <file>...
+            Read input from these files, in order, instead of standard input. Each file is split
+            into tokens on its own, so a token never spans two files; with --csv, each file has its
+            own header row, which must put named columns where the first file's does. A file named -
+            means standard input. Input compressed with gzip or zstd, such as rotated logs, is
+            decompressed as it's read, if groupby was built with the gzip or zstd feature.
+
+GROUPERS (choose one, or several for a composite key):
+        --basename
+            Treat each line as a path and group by its final component, like basename. Files with
+            the same name in different directories share a group, which is handy for finding
+            duplicate names. A trailing / is ignored. Paths without a final component, e.g. /, are
+            stored in the blank group, \"\".
+
+        --bucket-size <n>
+            Find the first number in each line and group it into a range of size n, e.g. 0-99,
+            100-199, etc. for n = 100. This is useful for histograms of sizes and latencies. A
+            number is a run of digits, optionally with a leading - and a fractional part, e.g. -1.5.
+            If a line contains no number, it is stored in the blank group, \"\". Ranges are listed in
+            order of their lower bounds, negative ones included, unless --sort says otherwise.
+
+        --chunk <n>
+            Split input into groups of n consecutive lines, numbered from 0: lines 1 through n go in
+            group 0, the next n lines in group 1, and so on. The last group may be smaller. With -c,
+            this runs one command per batch while keeping track of which batch produced which
+            output. Groups print in numeric order unless you choose a different order with --sort.
+
+        --column <col>
+            Treat each line as a CSV record and group by one of its columns, with quotes removed.
+            col is either a column number, counting from 1, or, with --csv, a column name from the
+            header row. Fields are separated by commas unless you choose another delimiter with -d.
+            Use --csv as well if quoted fields may contain newlines. Records with too few fields are
+            stored in the blank group, \"\".
+
         --counter
             Place each token in its own, numbered group, starting from 0. This is useful for running
             a command over every token of input, i.e. acting as a splitter filter.
 
+        --date-format <fmt>
+            Find the first timestamp in each line that's written in the format fmt and group by its
+            day, or by the time span chosen with --bucket. Keys look like 2024-03-09 (or 2024,
+            2024-03, 2024-W10, 2024-03-09T14, or 2024-03-09T14:05), so they sort chronologically.
+            The format may contain %Y (four-digit year), %m (two-digit month), %b (month
+            abbreviation, e.g. Mar), %d (two-digit day), %H (two-digit hour), %M (two-digit minute),
+            %S (two-digit second), and %% (a literal %); everything else matches itself. For
+            example, Apache access logs use '%d/%b/%Y:%H:%M:%S'. If a line contains no matching
+            timestamp, it is stored in the blank group, \"\".
+
+        --dirname
+            Treat each line as a path and group by everything but its final component, like dirname,
+            i.e. by parent directory. A trailing / is ignored. Paths with only one component, e.g.
+            Cargo.toml, are stored in the group \".\"; roots, e.g. /, are stored in the blank group,
+            \"\".
+
         --extension
             Group by file extension (excluding the leading period). Files with multiple extensions
             will match the last extension, e.g. foo.tar.gz will match \"gz\". Files with only a
@@ -610,20 +3091,230 @@ GROUPERS (choose exactly one):
     -f <n>
             Group by equivalence on the first n characters.
 
+        --field <n>
+            Group by equivalence on the nth field, counting from 1, like cut -f. Fields are
+            separated by the delimiter given with -d, which defaults to a tab. If a line has fewer
+            than n fields, it is stored in the blank group, \"\".
+
+        --glob <rule>
+            Group paths by named glob patterns. Each rule looks like NAME=PATTERN, e.g.
+            images=*.{{jpg,png}}, and --glob may be given many times. Each token goes in the group
+            named by the first rule whose pattern matches it, or in the group \"other\" if none match.
+            
+            In patterns, * matches anything but /, ** matches anything, ? matches one character,
+            [a-z] and [!a-z] match character classes, {{a,b}} matches either alternative, and \\
+            escapes the next character. A pattern without a / matches the last component of the
+            path, so *.rs matches src/lib.rs.
+
+        --grouper <name>
+            Group by a grouper that the program registered as name at runtime. The groupby binary
+            registers none, but programs built on the groupby library can register their own; see
+            groupby::groupers::registry.
+
+        --json-key <path>
+            Treat each line as a JSON document and group by the value at path, written as in jq:
+            .user.id for the id field of the user object, .tags[0] for the first element of the tags
+            array, or .[\"odd key\"] for keys that aren't plain names. Strings are used without
+            quotes; other values are used as written. Lines without a value at path, or that aren't
+            valid JSON, are stored in the blank group, \"\".
+
+        --key-command <cmd>
+            Compute keys by piping tokens, one per line, to the shell command cmd, which must print
+            one key per line for each token, in order. To save starting a process per token, cmd
+            runs once per batch of tokens; see --key-batch. Like -c, cmd runs in the shell named by
+            SHELL, or by --shell. Tokens can't contain newlines. Any classifier that reads lines
+            works, e.g. --key-command 'cut -d: -f2 | tr A-Z a-z'.
+
+        --key-expr <expr>
+            Compute each token's key with a small expression, for rules that no other grouper
+            covers, e.g. lower(field(2, ':')). Expressions nest calls to field(n, delim), word(n),
+            first(n), last(n), regex(pattern), ext(), basename(), dirname(), host(), lower(s),
+            upper(s), trim(s), replace(s, from, to), concat(s, ...), and or(s, ...), which returns
+            its first non-blank argument. Functions with an optional final argument work on the
+            whole token, written line, unless given another string. Quote strings with ' or \".
+            Anything a function can't find, like a missing field, is \"\".
+
+        --kv[=<sep>...]
+            Treat each token as a pre-keyed record: a key, then sep, then a value. Group each value
+            by its key, skipping matching entirely. Splits at the first occurrence of sep, which is
+            given with --kv=sep and defaults to a tab. Tokens without sep are stored, whole, in the
+            blank group, \"\". This is useful when an upstream tool has already computed each token's
+            key.
+
     -l <n>
             Group by equivalence on the last n characters.
 
+        --length
+            Group by length, counting characters rather than bytes, so \"naïve\" has length 5. Groups
+            print in numeric order, e.g. 9 before 10, unless you choose a different order with
+            --sort.
+
+        --logfmt <key>
+            Scan each token for logfmt-style key=value pairs, separated by whitespace, and group by
+            the value for key. Values may be double-quoted to include spaces, e.g. msg=\"disk full\".
+            Tokens without key are stored in the blank group, \"\". Unlike --kv, which splits
+            pre-keyed records once, this finds key anywhere in the token.
+
+        --mime-type
+            Treat each line as a path and group by the MIME type of the file it names, e.g.
+            image/png or application/zip, detected from the first few bytes of the file rather than
+            its name. Recognizes common image, audio, video, document, archive, and executable
+            formats; other files are text/plain if they look like text and application/octet-stream
+            otherwise. Empty files are inode/x-empty and directories are inode/directory. Symbolic
+            links are followed. If a file can't be read, e.g. because it doesn't exist, its path is
+            stored in the blank group, \"\".
+
+        --mtime-bucket <span>
+            Treat each line as a path and group by the time span, in UTC, in which the file it names
+            was last modified. Keys look like 2024 (year), 2024-03 (month), 2024-W10 (ISO 8601 week,
+            starting Monday), 2024-03-09 (day), 2024-03-09T14 (hour), or 2024-03-09T14:05 (minute),
+            so they sort chronologically. Symbolic links are followed. If a file's modification time
+            can't be read, e.g. because it doesn't exist, its path is stored in the blank group, \"\".
+            
+            [possible values: year, month, week, day, hour, minute]
+
+        --path-depth <n>
+            Treat each line as a path and group by its nth component, counting from 1. For instance,
+            with n = 1, ./src/lib.rs and ./src/bin/main.rs are both stored in the group \"src\". A
+            leading / or ./ is skipped, and repeated separators are ignored. If a path has fewer
+            than n components, it is stored in the blank group, \"\".
+
     -r, --regex <pattern>
             Group by equivalence on the first match against the specified regex pattern. If capture
             groups are present, group by equivalence on the first capture group. If a line does not
-            match, it is stored in the blank group, \"\".
+            match, it is stored in the blank group, \"\", unless --unmatched says otherwise.
+            
+            -r may be given many times to classify lines with a list of rules. Each line is tried
+            against each pattern in order and grouped by the first one that matches. When -r is
+            repeated, a pattern may be named by writing it as NAME=PATTERN, e.g. errors=ERROR|FATAL,
+            and lines it matches go in the group NAME instead. Only names made of letters, digits,
+            _, and - are recognized, so write a pattern that starts with something like key= as
+            (?:key)=.
+
+        --shard <n>
+            Partition lines into n groups, numbered 0 through n-1, by a hash of each line. Identical
+            lines always land in the same group, and lines are spread evenly across groups. The hash
+            is stable across runs, platforms, and versions, so the same input always shards the same
+            way. This is handy for splitting work across n parallel jobs with -c. Groups print in
+            numeric order unless you choose a different order with --sort.
+
+        --size-buckets <sizes>
+            Treat each line as a path and group by the size of the file it names, in ranges divided
+            at sizes, a comma-separated list in increasing order. For example, 1K,1M,100M makes the
+            groups <1K, 1K-1M, 1M-100M, and 100M+. Each range includes its lower boundary. Sizes are
+            in bytes, optionally followed by K, M, G, or T for powers of 1024. Symbolic links are
+            followed. If a file's size can't be read, e.g. because it doesn't exist, its path is
+            stored in the blank group, \"\".
+
+        --url-host
+            Treat each token as a URL and group by its host, e.g. www.example.com for
+            https://www.example.com/index.html. User info and ports are dropped. The URL must have a
+            scheme, like https://, or start with //. Tokens that aren't URLs are stored in the blank
+            group, \"\". Hosts are case-insensitive, so consider adding -i. With --domain, group by
+            registrable domain instead.
+
+        --word <n>
+            Group by equivalence on the nth word, counting from 1, where a word is any match of the
+            pattern given with --word-regex. There are many ways to define a word, and the exact
+            definition matters, so you must choose one, e.g. '\\S+' for runs of non-whitespace or
+            '\\w+' for runs of letters, digits, and underscores. If a line has fewer than n words, it
+            is stored in the blank group, \"\".
 
 GROUPER OPTIONS:
+        --bucket <span>
+            When used with --date-format, group timestamps by this time span.
+            
+            [default: day]
+            [possible values: year, month, week, day, hour, minute]
+
+        --bytes
+            When used with -f or -l, count bytes rather than characters. A multibyte character
+            that's cut in two is replaced with U+FFFD, the Unicode replacement character.
+
         --capture-group <grp>
             When used with -r, match a specific capture group by number or name. Group number 0
-            matches the entire pattern.
+            matches the entire pattern. To key by several groups at once, list them separated by
+            commas, e.g. --capture-groups date,level; their matches are joined with --key-sep.
+            Tokens where any of them doesn't take part in the match count as unmatched. Naming a
+            group that the pattern doesn't have is an error.
+
+        --count-values
+            After grouping (and after --min-size and --max-size), collapse identical values within
+            each group into a single value prefixed with the number of times it occurred, like uniq
+            -c, e.g. \"3 foo.txt\". Values appear in the order of their first occurrence. Commands run
+            with -c receive the counted values, and --sort-groups size orders groups by their number
+            of distinct values.
+
+    -d, --field-sep <delim>
+            When used with --field, separate fields with delim instead of a tab. With --column, use
+            delim instead of a comma.
+
+        --domain
+            When used with --url-host, group by the host's registrable domain, so that
+            www.example.com and api.example.com share the group example.com. This keeps the last two
+            labels of the host, or three for common country-code suffixes like .co.uk, rather than
+            consulting the Public Suffix List. IP addresses are left as they are.
+
+    -i, --ignore-case
+            Ignore case when grouping: lowercase every key, so that keys differing only in case
+            share a group. For instance, with --extension, Foo.TXT and foo.txt are both stored in
+            the group \"txt\". Tokens themselves are unchanged. Works with every grouper. Labels (see
+            --label) are matched against the lowercased keys.
+
+        --key-batch <n>
+            When used with --key-command, send the command n tokens each time it runs, rather than
+            1000. Larger batches start fewer processes; smaller ones group tokens sooner, e.g. with
+            --stream. When --key-command is combined with other groupers, tokens are grouped by
+            their combined keys once their batch is full.
+
+        --key-sep <sep>
+            When using several groupers at once, join their keys with sep, in the order the groupers
+            appear on the command line. For instance, -f 10 --field 3 -d ' ' --key-sep / puts
+            \"2024-03-01 12:00 ERROR disk full\" in the group \"2024-03-01/ERROR\". Several capture
+            groups listed with --capture-groups are joined with sep, too.
+            
+            [default: \" \"]
+
+        --label <rule>
+            After grouping, place every key that matches a rule into the group named by that rule's
+            label. Each rule has the form PATTERN:LABEL. A pattern of the form LOW-HIGH matches keys
+            whose leading characters fall between LOW and HIGH (inclusive), so 'a-m:first-half'
+            matches \"apple\" and \"mango\". Either bound may be empty to leave that end open. Any other
+            pattern matches keys that start with it, e.g. 'jp:images'. Labels cannot contain colons.
+            
+            This option may be repeated; the first matching rule wins. Keys that match no rule are
+            left unchanged.
+
+        --max-size <n>
+            After grouping (and after --unique), drop groups with more than n values, like
+            --min-size.
+
+        --min-size <n>
+            After grouping (and after --unique), drop groups with fewer than n values. Dropped
+            groups aren't printed or counted in --stats, and commands run with -c don't run over
+            them. For instance, --min-size 2 shows only groups with duplicates, such as files with
+            the same content hash.
+
+        --unique
+            After grouping, remove duplicate values from each group, keeping only the first
+            occurrence of each, in input order. The same value may still appear in different groups.
+            Commands run with -c receive the deduplicated groups.
+            
+            With --stats, also print the number of duplicates removed.
+
+        --unmatched <policy>
+            When used with -r, choose what happens to lines that don't match the pattern: \"empty\"
+            (the default) stores them in the blank group, \"\"; \"skip\" drops them; \"error\" stops with
+            an error at the first one; and \"group:NAME\" stores them in the group NAME.
+
+        --word-regex <pattern>
+            When used with --word, define a word as any match of pattern.
 
 OUTPUT SEPARATOR OPTIONS (choose zero or one):
+        --print-sep <sep>
+            When outputting lines, separate them with sep rather than a newline, e.g. \", \" or '\\t'.
+            Escape sequences are interpreted as in --split (see --literal).
+
         --print0
             When outputting lines, separate them with a null character rather than a newline. This
             option is meant for compatibility with xargs -0.
@@ -634,9 +3325,16 @@ OUTPUT SEPARATOR OPTIONS (choose zero or one):
 GENERAL OUTPUT OPTIONS:
     -c, --run-command <cmd>
             Execute cmd as a shell command for each group, passing the group via standard input, one
-            match per line. Each command runs as a command in the shell specified by the SHELL
-            variable, just as if you had written $SHELL -c \"cmd\". After all commands are run, the
-            output for each group's command will be printed instead of the group's contents.
+            match per line. Each command runs as a command in the shell specified by --shell or else
+            the SHELL variable (or /bin/sh if SHELL isn't set), just as if you had written $SHELL -c
+            \"cmd\". After all commands are run, the output for each group's command will be printed
+            instead of the group's contents.
+            
+            Each {{}} or {{key}} in cmd is replaced with the group's name, quoted for the shell, e.g. -c
+            'mkdir -p out/{{}} && cat > out/{{}}/list.txt'. Write {{{{}}}} or {{{{key}}}} for a literal {{}} or
+            {{key}}, e.g. -c 'xargs -I{{{{}}}} cp {{{{}}}} dest/'. Each command's environment also describes
+            its group: GROUPBY_KEY is the group's name, GROUPBY_SIZE is its number of values, and
+            GROUPBY_INDEX is its position in the output, counting from 0.
             
             When you use this option, most other output options affect the way each group is passed
             to a command's standard input. When printing the outputs of the commands, groupby resets
@@ -645,6 +3343,140 @@ GENERAL OUTPUT OPTIONS:
             The commands are run in parallel and may run in arbitrary order. The commands' outputs
             are printed in order by group name.
 
+        --capture-stderr
+            When used with -c, capture each command's standard error as well as its standard output,
+            and print it after the command's output under the group's header, instead of letting it
+            appear on the terminal as soon as the command writes it. With --json, each group maps to
+            an object with \"stdout\" and \"stderr\" strings.
+
+        --checkpoint <file>
+            When used with -c, record the name of each group whose command exits successfully in
+            file, so that an interrupted run can be resumed later with --resume. Without --resume,
+            any existing checkpoint file is overwritten.
+
+        --count-only
+            Keep only a count of the values in each group, discarding the values themselves, and
+            print each group's name followed by \": \" and its count, e.g. \"txt: 42\". Memory use is
+            proportional to the number of groups rather than the size of the input, so this can
+            tally inputs too large to hold in memory.
+            
+            Groups are printed in the order given by --sort. With --json, writes a JSON object
+            mapping each group's name to its count.
+
+        --dry-run
+            When used with -c, print each group's command line, shell, working directory, and
+            environment, along with a preview of the standard input it would receive, instead of
+            running anything. If --max-args or --max-stdin-bytes would split a group over several
+            invocations, each invocation's input is shown separately. --checkpoint is ignored, so
+            every group is shown and no checkpoint file is created.
+
+        --fail-fast
+            When used with -c, stop as soon as a group's command exits unsuccessfully: start no more
+            commands, print no output, and exit with status 1, naming the group whose command
+            failed. Commands that are already running in parallel still run to completion.
+
+        --format <template>
+            Print each group by filling in template, followed by the output separator, instead of
+            printing a header and the group's values. The template may use these placeholders:
+            
+              {{key}}           the group's key
+              {{count}}         the number of values in the group
+              {{values}}        the group's values, joined by the output separator
+              {{values:JOINER}} the group's values, joined by JOINER, e.g. {{values:, }}
+              {{output}}        with -c, the command's standard output, without trailing newlines
+              {{stderr}}        with -c and --capture-stderr, the command's standard error
+            
+            Write {{{{ and }}}} for literal braces. Escape sequences such as \\t are interpreted as in
+            delimiters (see --literal). For instance, --format '{{key}}\\t{{count}}\\t{{values:,}}' prints
+            one tab-separated line per group. Statistics, if requested, are still printed at the
+            end.
+
+        --header-format <template>
+            Print each group's header by filling in template instead of printing the group's key
+            followed by a colon. The template uses the same placeholders and escapes as --format,
+            e.g. --header-format '### {{key}} ({{count}})'. With --stats, the group's statistics still
+            follow the header in parentheses.
+
+        --incremental
+            When used with -c, print each group's output as soon as its command has finished and
+            every group before it has been printed, instead of once every command has finished. The
+            output is the same either way; only its timing changes. Statistics, if requested, are
+            printed at the end.
+
+        --intern
+            Store each distinct value only once, however many times it appears, and share it between
+            every token that repeats it. On input with many repeated lines, like most logs, this
+            takes far less memory; on input with few repeats, it takes a little more. Groups are
+            printed one at a time, in the default order that --sort describes, e.g. by number with
+            --shard. For example:
+            
+                groupby --intern --word 1 access.log
+
+    -j, --jobs <n>
+            When used with -c, run at most n commands at a time, rather than one per CPU core. This
+            is useful for commands that use a lot of memory or call a rate-limited service. Commands
+            still run in arbitrary order; to run them in order, use --sequential instead.
+
+        --json
+            Write final output as a single JSON object, followed by a newline, that maps each
+            group's name to an array of its values, e.g. for processing with jq. Groups appear in
+            the order given by --sort.
+            
+            When used with -c, maps each group's name to its command's output, as a string, instead.
+            Commands are not affected in any way.
+
+        --keep-going
+            When used with -c, run every group's command even if some exit unsuccessfully, print the
+            output as usual, and then exit with status 1, naming the groups whose commands failed.
+            This is the default.
+
+        --live
+            When used with -c, print each line of each command's output as soon as the command
+            writes it, prefixed with the group's name and \" | \", like the logs of docker compose,
+            instead of printing each group's output under its header once every command has
+            finished. Lines from commands running in parallel may be interleaved, but never mixed
+            together. Standard error isn't prefixed.
+
+        --low-memory
+            Keep at most 64 MiB of values in memory while grouping, writing the rest to a temporary
+            file that's removed when groupby exits. This lets groupby group inputs far larger than
+            RAM. Groups are printed in the default order that --sort describes, e.g. by number with
+            --length, one at a time, reading each group's values back from disk only when it's
+            printed. With --run-command, commands run one at a time as each group is read back. For
+            example:
+            
+                zcat huge.log.gz | groupby --low-memory -f1 -c 'wc -l'
+
+        --max-args <n>
+            When used with -c, pass at most n values to each invocation of a command, like xargs -n.
+            A larger group runs its command several times, one after another, and the outputs are
+            printed together under the group's header. If any invocation fails, the group's command
+            counts as failed. Has no effect with --only-group-names.
+
+        --max-memory <size>
+            When used with -c, limit each command's virtual memory to size, a number of bytes
+            optionally followed by K, M, G, or T, e.g. 2G. Allocations beyond the limit fail, so a
+            runaway command fails on its own instead of exhausting the machine's memory. The limit
+            covers each command separately, not all of them together.
+
+        --max-open-files <n>
+            When used with -c, limit each command to n open files at once.
+
+        --max-stdin-bytes <n>
+            When used with -c, pass at most n bytes of values, counting separators, to each
+            invocation of a command, running it several times for a larger group as with --max-args.
+            A single value larger than n is passed on its own. May be combined with --max-args, in
+            which case both limits apply.
+
+        --nice <n>
+            When used with -c, run commands at niceness n, from -20 (the highest priority) to 19
+            (the lowest), so that they yield the CPU to other work. Only root can run commands at a
+            lower niceness than groupby's own. Unix only, as are --max-memory and --max-open-files.
+
+        --no-header-colon
+            Print each group's header as its key alone, without the trailing colon. This is the same
+            as --header-format '{{key}}'.
+
         --no-headers
             When printing final output, do not print a header before each group. Only print the
             final output for each group, back-to-back. Groups are still sorted by group name.
@@ -657,19 +3489,124 @@ GENERAL OUTPUT OPTIONS:
             Output only group names, omitting group contents.
             
             When used with -c, passes the name of each group to its command instead of passing the
-            group's contents.
+            group's contents. Otherwise, when used with --print0, prints each name exactly as it is,
+            followed by a null character, for xargs -0; --stats and --quote don't change the names.
+
+        --presorted-streaming
+            Promise that the input is already sorted by key, so that each group is complete as soon
+            as a token with a different key arrives. Output each group (or run its command)
+            immediately, then discard it, so that memory use is proportional to the largest group
+            rather than the whole input. Groups print in input order, and commands run one at a
+            time.
+            
+            If the input is not actually sorted, a key that appears in several separate runs
+            produces several separate groups.
+
+        --quote <style>
+            Quote each group name and value when printing it. \"shell\" leaves words made only of
+            letters, digits, and %+,-./:=@_ as they are, and wraps anything else in single quotes,
+            so the output can be pasted into a POSIX shell command. This applies to headers,
+            --format templates, and --count-only, but not to the output of -c, which is printed as
+            is.
+            
+            [possible values: shell]
+
+        --resume
+            When used with --checkpoint, skip the groups that the checkpoint file lists as complete,
+            and add newly completed groups to it. Skipped groups are omitted from the final output.
+            A missing checkpoint file is treated as empty.
+
+        --reverse
+            Print groups in the reverse of the order given by --sort, e.g. \"z\" before \"a\". With
+            --sort-groups, groups of the same size are printed in reverse order, too.
+            
+            With -c, GROUPBY_INDEX follows this order, and so do commands run with --sequential.
 
         --sequential
             When used with -c, run commands in sequence, ordered by group name, using a single
             thread. This may be much slower. This option has no effect if used without -c.
 
+        --shell <path>
+            When used with -c or --key-command, run commands in the shell at path, e.g. /bin/bash,
+            instead of the shell named by the SHELL environment variable. The shell must accept -c
+            followed by a command string.
+
+        --sort <order>
+            Print groups in the given order of their names. \"bytes\" (the default, except with
+            --length, --shard, and --chunk, which default to \"natural\", and --bucket-size, which
+            defaults to \"numeric\") sorts names by their raw bytes, so \"Zebra\" comes before \"apple\".
+            \"collate\" sorts names like a dictionary: ignoring case and accents first, then placing
+            unaccented letters before accented ones and lowercase before uppercase. For instance,
+            \"Äpfel\", \"apple\", and \"Apple\" print in that order. \"locale\" is like \"collate\", but
+            follows the alphabet of the language chosen by LC_ALL, LC_COLLATE, or LANG; for
+            instance, with LANG=sv_SE.UTF-8, \"ö\" is a letter of its own that comes after \"z\".
+            Danish, Norwegian, Finnish, Spanish, and Swedish have their own alphabets; other
+            languages sort as with \"collate\". \"natural\" compares runs of digits by their numeric
+            values, so \"img2.png\" comes before \"img10.png\". \"numeric\" compares names that are
+            numbers, like \"-1\", \"10\", or \"2.5\", by value and ranges like \"-30--21\" by their lower
+            bounds, putting other names last.
+            
+            This option is not affected by -c, except that with --sequential, commands also run in
+            the order their groups are printed.
+            
+            [possible values: bytes, collate, locale, natural, numeric]
+
+        --sort-groups <order>
+            Print groups in the given order. \"key\" (the default) orders groups by name, as given by
+            --sort. \"size\" prints the smallest groups first, and \"size-desc\" the largest. Groups of
+            the same size are printed in --sort order. A group's size is its number of values, even
+            if -c replaces them with a command's output. \"natural\" and \"numeric\" are the same as
+            --sort natural and --sort numeric, and can't be combined with --sort.
+            
+            With -c, GROUPBY_INDEX follows this order, and so do commands run with --sequential.
+            
+            [possible values: key, size, size-desc, natural, numeric]
+
         --stats
-            Print an item count for each group, plus statistics about the overall collection, in
-            addition to any other output (as specified by other options).
+            Print an item count and byte count for each group, plus statistics about the overall
+            collection, in addition to any other output (as specified by other options). Byte counts
+            include a separator after each value.
+            
+            This option does not affect the text sent to each command by -c. When used with -c, the
+            final output is augmented with statistics about the groups and their contents, plus how
+            long each group's command took, its exit status if it failed, and the number of commands
+            that failed.
+
+        --stats-detail[=<percentiles>...]
+            When used with --stats, also print the standard deviation of group sizes, the
+            percentiles of group sizes given with --stats-detail=percentiles (a comma-separated
+            list, from 0 to 100; default: 90,99), and a histogram of group sizes, with a bucket for
+            each power of two. Percentiles use the nearest-rank method, so with an even number of
+            groups, p50 is the smaller of the two middle sizes, while Median is the larger. Useful
+            when group sizes are heavily skewed, which the median, average, minimum, and maximum
+            alone can hide.
+
+        --stream
+            Instead of collecting groups, print each token as soon as its key is known, as the key,
+            a tab, and the token, each followed by the output separator (newline by default).
+            Nothing is stored, so memory use stays flat however long the input runs, which suits
+            long-running pipelines. Tokens aren't gathered into groups; pipe the output through
+            groupby --kv to gather them later. For example:
             
-            This option is not affected by -c. When used with -c, the text sent to each command does
-            not change. The final output is augmented with statistics about the groups and their
-            contents (not about the commands or their outputs).\n",
+                tail -f access.log | groupby --stream --url-host | grep -v '^example.com'
+
+        --timings
+            When groupby finishes, print how long it spent reading input, grouping it, running
+            commands, and writing output, plus the total, to standard error. When used with -c, also
+            print how long each group's command took, slowest first. Useful for finding out where a
+            big run spends its time.
+
+        --with-values
+            When used with -c, print each group's contents under its header, followed by an
+            \"Output:\" line and then the command's output, instead of the command's output alone.
+            With --no-headers, the \"Output:\" line is left out. With --json, each group's result is
+            an object with a \"values\" array alongside its \"stdout\" string.
+
+SUBCOMMANDS:
+    bench
+            Time each phase of groupby on synthetic input, comparing backends.
+    help
+            Print this message or the help of the given subcommand(s)\n",
                 env!("CARGO_PKG_VERSION")
             )
         );
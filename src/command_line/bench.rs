@@ -0,0 +1,266 @@
+//! A built-in benchmark, run as `groupby bench`.
+//!
+//! [bench()] generates synthetic input in memory, then processes it once with each
+//! [GroupedCollection] backend, timing each phase separately:
+//!
+//! 1. **Split:** splitting the input into tokens, as [build_groups] does for line-separated input.
+//! 1. **Group:** adding every token to the collection through [Runner].
+//! 1. **Commands:** if requested, running a command over each group with [run_command].
+//! 1. **Output:** writing the results with [write_results], to a writer that discards them.
+//!
+//! It then prints a table comparing the backends. This gives users a quick way to evaluate
+//! backends and options on their own hardware and data sizes.
+//!
+//! Each item of input looks like `KEY-N`, where `KEY` is one of [BenchOptions::keys] group keys,
+//! chosen at random and zero-padded to the same width, and `N` is the item's index. Items are
+//! grouped by `KEY`.
+//!
+//! [build_groups]: crate::command_line::build_groups
+//! [run_command]: crate::command_line::run_command
+//! [write_results]: crate::command_line::write_results
+
+use crate::command_line::error::GroupByError;
+use crate::command_line::options::*;
+use crate::command_line::run_command::run_command;
+use crate::command_line::write_results::write_results;
+use crate::grouped_collections::{GroupedCollection, ParallelGroupedCollection};
+use crate::groupers::string::Runner;
+use crate::random::Rng;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+/// How long each phase took for one backend.
+#[derive(Clone, Debug)]
+pub struct Timings {
+    pub backend: &'static str,
+    pub split: Duration,
+    pub group: Duration,
+
+    /// `None` if no command was run.
+    pub commands: Option<Duration>,
+
+    pub output: Duration,
+}
+
+impl Timings {
+    /// The total time across all phases.
+    pub fn total(&self) -> Duration {
+        self.split + self.group + self.commands.unwrap_or_default() + self.output
+    }
+}
+
+/// Generates synthetic input as described in the [module documentation](self).
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::bench::generate_input;
+/// use groupby::command_line::options::BenchOptions;
+///
+/// let options = BenchOptions {
+///     items: 3,
+///     keys: 20,
+///     run_command: None,
+///     seed: Some(1),
+/// };
+///
+/// let input = String::from_utf8(generate_input(&options)).unwrap();
+/// let lines: Vec<&str> = input.lines().collect();
+/// assert_eq!(lines.len(), 3);
+/// assert!(lines[2].ends_with("-2"));
+/// assert_eq!(lines[2].find('-'), Some(2)); // Keys 0-19 are padded to two digits.
+/// ```
+pub fn generate_input(options: &BenchOptions) -> Vec<u8> {
+    let width = key_width(options.keys);
+    let mut rng = Rng::for_stream(options.seed, "bench");
+    let mut input = Vec::new();
+    for i in 0..options.items {
+        let key = rng.below(options.keys as u64);
+        writeln!(input, "{:0width$}-{}", key, i, width = width).unwrap();
+    }
+    input
+}
+
+// The number of digits in the largest key.
+fn key_width(keys: usize) -> usize {
+    keys.saturating_sub(1).to_string().len()
+}
+
+/// Times every phase with every backend over the same synthetic input.
+///
+/// # Errors
+///
+/// Returns an error under the same conditions as [run_command].
+pub fn time_backends(options: &BenchOptions) -> Result<Vec<Timings>, GroupByError> {
+    let input = generate_input(options);
+    Ok(vec![
+        time_backend("BTreeMap", &input, options, BTreeMap::new())?,
+        time_backend("HashMap", &input, options, HashMap::new())?,
+    ])
+}
+
+// Times every phase with a single backend, map.
+fn time_backend<Map>(
+    backend: &'static str,
+    input: &[u8],
+    options: &BenchOptions,
+    mut map: Map,
+) -> Result<Timings, GroupByError>
+where
+    Map: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
+    Map: for<'s> ParallelGroupedCollection<'s, String, String, Vec<String>>,
+{
+    let grouping = GroupingSpecifier::FirstChars(key_width(options.keys));
+    let output_options = OutputOptions {
+        run_command: options.run_command.clone(),
+        ..Default::default()
+    };
+
+    let start = Instant::now();
+    let tokens: Vec<String> = input.lines().map(Result::unwrap).collect();
+    let split = start.elapsed();
+
+    let start = Instant::now();
+    let mut runner = Runner::new(&mut map, &grouping);
+    for token in tokens {
+        runner.run(token)?;
+    }
+    drop(runner);
+    let group = start.elapsed();
+
+    let start = Instant::now();
+    let results = run_command(io::sink(), &map, &output_options)?;
+    let commands = results.as_ref().map(|_| start.elapsed());
+
+    let start = Instant::now();
+    write_results(io::sink(), &map, &results, &output_options)?;
+    let output = start.elapsed();
+
+    Ok(Timings {
+        backend,
+        split,
+        group,
+        commands,
+        output,
+    })
+}
+
+/// Runs the benchmark and writes a table of results to `output`.
+///
+/// # Errors
+///
+/// Returns an error under the same conditions as [run_command], or if writing to `output` fails.
+pub fn bench<O: Write>(mut output: O, options: &BenchOptions) -> Result<(), GroupByError> {
+    let timings = time_backends(options)?;
+
+    writeln!(
+        output,
+        "{} items in up to {} groups{}\n",
+        options.items,
+        options.keys,
+        match &options.run_command {
+            Some(command) => format!(", running: {}", command),
+            None => "".to_string(),
+        },
+    )?;
+    write_table(&mut output, &timings)?;
+    Ok(())
+}
+
+// Writes one row per backend, with a column per phase.
+fn write_table<O: Write>(output: &mut O, timings: &[Timings]) -> io::Result<()> {
+    let header = ["Backend", "Split", "Group", "Commands", "Output", "Total"];
+    let rows: Vec<[String; 6]> = timings
+        .iter()
+        .map(|t| {
+            [
+                t.backend.to_string(),
+                format_duration(t.split),
+                format_duration(t.group),
+                t.commands
+                    .map(format_duration)
+                    .unwrap_or_else(|| "-".to_string()),
+                format_duration(t.output),
+                format_duration(t.total()),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let header = header.map(str::to_string);
+    for row in std::iter::once(&header).chain(&rows) {
+        // Left-align the backend name and right-align the numbers.
+        let mut line = format!("{:<width$}", row[0], width = widths[0]);
+        for (cell, width) in row.iter().zip(widths).skip(1) {
+            line.push_str(&format!("  {:>width$}", cell, width = width));
+        }
+        writeln!(output, "{}", line)?;
+    }
+    Ok(())
+}
+
+// Formats a duration in milliseconds, e.g. "12.34 ms".
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(items: usize, keys: usize, run_command: Option<&str>) -> BenchOptions {
+        BenchOptions {
+            items,
+            keys,
+            run_command: run_command.map(str::to_string),
+            seed: Some(0),
+        }
+    }
+
+    mod generate_input {
+        use super::*;
+
+        #[test]
+        fn is_reproducible() {
+            let options = options(100, 10, None);
+            assert_eq!(generate_input(&options), generate_input(&options));
+        }
+
+        #[test]
+        fn handles_zero_keys() {
+            let input = generate_input(&options(2, 0, None));
+            assert_eq!(String::from_utf8(input).unwrap(), "0-0\n0-1\n");
+        }
+    }
+
+    mod bench {
+        use super::*;
+
+        #[test]
+        fn writes_a_row_per_backend() {
+            let mut output = vec![];
+            bench(&mut output, &options(50, 5, None)).unwrap();
+            let output = String::from_utf8(output).unwrap();
+            let lines: Vec<&str> = output.lines().collect();
+
+            assert_eq!(lines[0], "50 items in up to 5 groups");
+            assert!(lines[2].starts_with("Backend"));
+            assert!(lines[3].starts_with("BTreeMap"));
+            assert!(lines[4].starts_with("HashMap"));
+            assert!(lines[3].contains(" - "));
+        }
+
+        #[test]
+        fn times_commands() {
+            let timings = time_backends(&options(10, 2, Some("cat"))).unwrap();
+            assert!(timings.iter().all(|t| t.commands.is_some()));
+        }
+    }
+}
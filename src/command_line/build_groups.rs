@@ -1,7 +1,8 @@
 //! Parses an input stream into a [GroupedCollection].
 //!
 //! Provides functions to parse an input stream, obeying options in [GroupByOptions], and add
-//! parsed tokens into a [GroupedCollection].
+//! parsed tokens into a [GroupedCollection]. [send_tokens_from_inputs] instead sends each token to
+//! a [Sink] as soon as its key is known, without collecting anything.
 //!
 //! # Examples
 //!
@@ -16,26 +17,33 @@
 //! let options = GroupByOptions {
 //!     input: InputOptions {
 //!         separator: Separator::Space,
+//!         ..Default::default()
 //!     },
 //!     grouping: GroupingSpecifier::FirstChars(1),
-//!     output: OutputOptions {
-//!         separator: Separator::Line,
-//!         only_group_names: false,
-//!         run_command: None,
-//!         parallel: true,
-//!         headers: true,
-//!         stats: false,
-//!     },
+//!     ..Default::default()
 //! };
 //!
-//! build_groups(input, &mut map, &options);
+//! build_groups(input, &mut map, &options).unwrap();
 //! assert_eq!(map.get(&"w".to_string()), Some(&vec!["words".to_string()]));
 //! ```
 
+use crate::command_line::csv::{self, Records};
+use crate::command_line::error::GroupByError;
+use crate::command_line::input_files::{self, Tee, WithFilename};
+use crate::command_line::labels::Labeled;
 use crate::command_line::options::*;
-use crate::grouped_collections::GroupedCollection;
+use crate::command_line::timings::Timed;
+use crate::command_line::walk;
+use crate::grouped_collections::{GroupEntry, GroupedCollection};
 use crate::groupers::string::Runner;
-use std::io::BufRead;
+use crate::random::Rng;
+use memchr::memmem::Finder;
+use std::cell::{Cell, RefCell};
+use std::io::{self, BufRead, BufReader, Write};
+use std::iter;
+
+// Called after each token is added, to stop building groups early by returning an error.
+type Check<'a> = &'a dyn Fn() -> Result<(), GroupByError>;
 
 /// Single-threaded input processing.
 ///
@@ -43,59 +51,764 @@ use std::io::BufRead;
 /// as fast as multi-threaded input processing, perhaps because of the small and frequent locking
 /// and unlocking of mutexes. Therefore, we do not provide a multi-threaded equivalent to
 /// `build_groups`.
-pub fn build_groups<I, Map>(mut input: I, map: &mut Map, options: &GroupByOptions)
+///
+/// Input is read as bytes and split into tokens before each token is converted to a [String],
+/// following [InputOptions::invalid_utf8]. With [Separator::Space], input is converted a line at a
+/// time, so [InvalidUtf8::Skip] skips every word on a line that isn't valid UTF-8. With
+/// [Separator::Paragraph], it's also converted a line at a time, so it skips just the line.
+///
+/// Tokens are then trimmed, skipped, and sampled as [InputOptions] says. Once
+/// [InputOptions::limit] tokens have been grouped, no more input is read.
+///
+/// # Errors
+///
+/// Returns [GroupByError::Io] if reading `input` fails, e.g. because of a broken pipe or a disk
+/// error. Returns [GroupByError::InvalidInput] if a token isn't valid UTF-8 under
+/// [InvalidUtf8::Strict] or if a CSV column name isn't in the header row. In either case, tokens
+/// before the error have already been added to `map`.
+pub fn build_groups<I, Map, List>(
+    input: I,
+    map: &mut Map,
+    options: &GroupByOptions,
+) -> Result<(), GroupByError>
+where
+    I: BufRead,
+    Map: for<'s> GroupedCollection<'s, String, String, List>,
+    List: 'static,
+{
+    build_groups_checked(input, map, options, &mut TokenFilter::new(options), &|| {
+        Ok(())
+    })
+}
+
+// Like build_groups, but calls check after each token and stops at the first error it returns.
+// Tokens go through filter, which may be shared with earlier inputs.
+fn build_groups_checked<I, Map, List>(
+    input: I,
+    map: &mut Map,
+    options: &GroupByOptions,
+    filter: &mut TokenFilter,
+    check: Check,
+) -> Result<(), GroupByError>
 where
     I: BufRead,
-    Map: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
+    Map: for<'s> GroupedCollection<'s, String, String, List>,
+    List: 'static,
 {
-    let mut runner = Runner::new(map, &options.grouping);
+    let mut map = Labeled::new(map, &options.labels);
+    if options.input.csv {
+        // The header names the columns, so it isn't grouped itself. A skipped header names none.
+        let mut records = Records::new(input);
+        let grouping = match csv_grouping(&mut records, options)? {
+            Some(grouping) => grouping,
+            None => return Ok(()),
+        };
+        let mut runner = Runner::new(&mut map, &grouping);
+        read_records(records, &mut runner, options, filter, check)?;
+        runner.finish()?;
+        return check();
+    }
+
+    let mut runner = Runner::new(&mut map, &options.grouping);
+    read_tokens(input, &mut runner, options, filter, check)?;
+    runner.finish()?;
+    check()
+}
+
+// Reads the header row from a CSV input and resolves the column names in options.grouping
+// against it. Returns None if the input is empty.
+fn csv_grouping<R: BufRead>(
+    records: &mut Records<R>,
+    options: &GroupByOptions,
+) -> Result<Option<GroupingSpecifier>, GroupByError> {
+    let header = match records.next() {
+        Some(header) => decode(header?, options.input.invalid_utf8)?.unwrap_or_default(),
+        None => return Ok(None),
+    };
+    csv::resolve_columns(&options.grouping, &header).map(Some)
+}
+
+// Runs each of the CSV records after the header through runner. Returns whether to keep reading
+// input.
+fn read_records<R: BufRead>(
+    records: Records<R>,
+    runner: &mut Runner<String>,
+    options: &GroupByOptions,
+    filter: &mut TokenFilter,
+    check: Check,
+) -> Result<bool, GroupByError> {
+    for record in records {
+        if let Some(record) = decode(record?, options.input.invalid_utf8)? {
+            if !run(runner, record, filter, check)? {
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
+// Splits input into tokens as options say and runs each through runner. Returns whether to keep
+// reading input.
+fn read_tokens<I: BufRead>(
+    mut input: I,
+    runner: &mut Runner<String>,
+    options: &GroupByOptions,
+    filter: &mut TokenFilter,
+    check: Check,
+) -> Result<bool, GroupByError> {
+    let policy = options.input.invalid_utf8;
     match options.input.separator {
         Separator::Null => {
             // Split on null characters and process every resulting token.
             // Note: UTF-8 is designed so the only code point with a null byte is NUL itself,
             // so we won't split a UTF-8 code point by splitting our byte stream before parsing
             // to a String value.
-            for result in input.split(0) {
-                let token = result.unwrap();
-                let token = String::from_utf8(token).unwrap();
-                runner.run(token);
+            for result in Split::new(input, b"\0", false) {
+                if let Some(token) = decode(result?, policy)? {
+                    if !run(runner, token, filter, check)? {
+                        return Ok(false);
+                    }
+                }
             }
         }
         Separator::Space => {
             // Split on whitespace and process every resulting token.
-            for line in input.lines() {
-                let line = line.unwrap();
-                for word in line.split(char::is_whitespace) {
+            for line in lines(input, policy) {
+                for word in line?.split(char::is_whitespace) {
                     // Skip reapted whitespace; split will go character-by-character, so it will
                     // return every second whitespace character in a sequence, which we don't want.
                     if word.chars().all(char::is_whitespace) {
                         continue;
                     }
-                    runner.run(word.to_string());
+                    if !run(runner, word.to_string(), filter, check)? {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+        Separator::Paragraph => {
+            // Process each run of non-blank lines as a single token.
+            for paragraph in paragraphs(input, policy) {
+                if !run(runner, paragraph?, filter, check)? {
+                    return Ok(false);
                 }
             }
         }
         Separator::Line => {
             // Process each line as a single token.
-            for line in input.lines() {
-                let line = line.unwrap();
-                runner.run(line.clone());
+            for line in lines(input, policy) {
+                if !run(runner, line?, filter, check)? {
+                    return Ok(false);
+                }
+            }
+        }
+        Separator::Custom(ref s) if s.is_empty() => {
+            // Splitting on "" splits between characters, so decode all of the input at once.
+            let mut buffer = vec![];
+            input.read_to_end(&mut buffer)?;
+            if let Some(buffer) = decode(buffer, policy)? {
+                for token in buffer.split(s) {
+                    if !run(runner, token.to_string(), filter, check)? {
+                        return Ok(false);
+                    }
+                }
             }
         }
         Separator::Custom(ref s) => {
-            // Split on custom delimiter s.
-            //
-            // The rest of the library isn't written around streaming, so we won't worry about
-            // using a string buffer here to store everything. We'll do things the simple and
-            // obviously correct way rather than trying to get fancy. If benchmarks show it's too
-            // slow or design goals change, we can rewrite it with something more advanced.
+            // Split on custom delimiter s, like str::split, as the input arrives.
+            for result in Split::new(input, s.as_bytes(), true) {
+                if let Some(token) = decode(result?, policy)? {
+                    if !run(runner, token, filter, check)? {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+    }
+    Ok(true)
+}
+
+// Runs the grouper on a token, unless filter drops it, and then calls check. Returns whether to
+// keep reading input.
+fn run(
+    runner: &mut Runner<String>,
+    token: String,
+    filter: &mut TokenFilter,
+    check: Check,
+) -> Result<bool, GroupByError> {
+    if let Some(token) = filter.admit(token) {
+        runner.run(token)?;
+        check()?;
+    }
+    Ok(!filter.is_done())
+}
+
+// Decides which tokens reach the grouper, as InputOptions says. One filter is used for every
+// input, so that the limit and the sample cover all of them together. The Python bindings use it,
+// too, for the items they're given.
+pub(crate) struct TokenFilter<'a> {
+    input: &'a InputOptions,
+    sample: Option<(f64, Rng)>,
+    taken: usize,
+}
+
+impl<'a> TokenFilter<'a> {
+    pub(crate) fn new(options: &'a GroupByOptions) -> Self {
+        TokenFilter {
+            input: &options.input,
+            sample: options
+                .input
+                .sample
+                .map(|Fraction(p)| (p, Rng::for_stream(options.seed, "sample"))),
+            taken: 0,
+        }
+    }
+
+    // Returns the token to group, as InputOptions::prepare leaves it, or None if it's dropped.
+    pub(crate) fn admit(&mut self, token: String) -> Option<String> {
+        if self.is_done() {
+            return None;
+        }
+        let token = self.input.prepare(token)?;
+        if let Some((p, rng)) = &mut self.sample {
+            if rng.next_f64() >= *p {
+                return None;
+            }
+        }
+        self.taken += 1;
+        Some(token)
+    }
+
+    // Whether InputOptions::limit tokens have been grouped, so no more input should be read.
+    pub(crate) fn is_done(&self) -> bool {
+        self.input.limit.is_some_and(|limit| self.taken >= limit)
+    }
+}
+
+/// Reads each of the inputs named by [InputOptions::files] and [InputOptions::files0_from] in
+/// turn, or standard input if there are none, and adds their tokens to `map` as [build_groups]
+/// would. Each input is split into tokens on its own, but one grouper runs over all of them, so
+/// e.g. [GroupingSpecifier::Chunk] counts tokens across inputs, as if they were concatenated.
+/// With [InputOptions::with_filename], each value is prefixed with its input's name; see
+/// [WithFilename]. A [GroupingSpecifier::KeyCommand] then runs over each input's tokens
+/// separately, so that every value gets its own input's name.
+///
+/// With [InputOptions::csv], each input has its own header row, which isn't grouped. Column names
+/// are looked up in the first input's header, and every other header must put them in the same
+/// columns.
+///
+/// If [InputOptions::walk] is set, no input is read. Instead, the path of each file in the walk is
+/// a token. Otherwise, with [InputOptions::tee], every input is copied as it's read; see
+/// [input_files::Tee].
+///
+/// # Errors
+///
+/// Returns [GroupByError::Input] if an input file can't be opened or the walk's root can't be
+/// read, [GroupByError::Tee] if the copy's file can't be created, [GroupByError::InvalidInput] if
+/// a CSV header puts a named column somewhere other than the first header did, any error that
+/// [input_files::paths] returns, or any error that [build_groups] returns. Inputs before the error
+/// have already been added to `map`.
+pub fn build_groups_from_inputs<Map, List>(
+    map: &mut Map,
+    options: &GroupByOptions,
+) -> Result<(), GroupByError>
+where
+    Map: for<'s> GroupedCollection<'s, String, String, List>,
+    List: 'static,
+{
+    build_groups_from_inputs_checked(map, options, &|| Ok(()))
+}
+
+// Like build_groups_from_inputs, but calls check after each token, as build_groups_checked does.
+fn build_groups_from_inputs_checked<Map, List>(
+    map: &mut Map,
+    options: &GroupByOptions,
+    check: Check,
+) -> Result<(), GroupByError>
+where
+    Map: for<'s> GroupedCollection<'s, String, String, List>,
+    List: 'static,
+{
+    let filter = &mut TokenFilter::new(options);
+    let mut map = Labeled::new(map, &options.labels);
+    if let Some(walk) = &options.input.walk {
+        let mut runner = Runner::new(&mut map, &options.grouping);
+        for path in walk.paths()? {
+            if let Some(token) = decode(walk::path_bytes(path), options.input.invalid_utf8)? {
+                if !run(&mut runner, token, filter, check)? {
+                    break;
+                }
+            }
+        }
+        runner.finish()?;
+        return check();
+    }
+
+    let mut tee = input_files::tee_output(&options.input)?;
+    let mut inputs = input_files::inputs(&options.input)?;
+
+    // With CSV, the grouper needs the first header's columns before it can run, so read up to the
+    // first input with a header now. Empty inputs before it have nothing to group.
+    let (grouping, first) = if options.input.csv {
+        loop {
+            let input = match inputs.next() {
+                Some(input) => input?,
+                None => return check(),
+            };
+            let mut records = Records::new(reader(input.reader, &mut tee));
+            if let Some(grouping) = csv_grouping(&mut records, options)? {
+                break (grouping, Some((input.name, records)));
+            }
+        }
+    } else {
+        (options.grouping.clone(), None)
+    };
+
+    // One runner serves every input. With -H, the name is updated as each input is read.
+    let name = RefCell::new(String::new());
+    let mut with_filename;
+    let mut runner = if options.input.with_filename {
+        with_filename = WithFilename::new(&mut map, &name);
+        Runner::new(&mut with_filename, &grouping)
+    } else {
+        Runner::new(&mut map, &grouping)
+    };
+    let finish_input = |runner: &mut Runner<String>| {
+        if options.input.with_filename {
+            runner.finish()
+        } else {
+            Ok(())
+        }
+    };
+
+    let mut more = first.map_or(Ok(true), |(input_name, records)| {
+        *name.borrow_mut() = input_name;
+        read_records(records, &mut runner, options, filter, check)
+    })?;
+    finish_input(&mut runner)?;
+    while more && !filter.is_done() {
+        let input = match inputs.next() {
+            Some(input) => input?,
+            None => break,
+        };
+        let input_reader = reader(input.reader, &mut tee);
+        *name.borrow_mut() = input.name;
+        more = if options.input.csv {
+            let mut records = Records::new(input_reader);
+            match csv_grouping(&mut records, options)? {
+                Some(other) if other != grouping => {
+                    return Err(GroupByError::InvalidInput(format!(
+                        "The CSV header of {} puts its columns in different places than the \
+                        first input's header",
+                        name.borrow()
+                    )))
+                }
+                Some(_) => read_records(records, &mut runner, options, filter, check)?,
+                None => true,
+            }
+        } else {
+            read_tokens(input_reader, &mut runner, options, filter, check)?
+        };
+        finish_input(&mut runner)?;
+    }
+    runner.finish()?;
+    check()
+}
+
+// Returns a timed reader over input, copying it to tee if that's requested.
+fn reader<'a>(
+    input: Box<dyn BufRead>,
+    tee: &'a mut Option<Box<dyn Write>>,
+) -> Timed<Box<dyn BufRead + 'a>> {
+    Timed::new(match tee {
+        Some(output) => Box::new(BufReader::new(Tee::new(input, output))),
+        None => input,
+    })
+}
+
+/// Receives each token from [send_tokens_from_inputs] as soon as its key is known.
+///
+/// Unlike a [GroupedCollection], a sink doesn't keep what it receives, so it can pass each token
+/// along while input is still being read, e.g. to
+/// [stream_tokens](crate::command_line::stream_tokens). Any `FnMut(String, String)` that returns
+/// a [Result] is a sink.
+pub trait Sink {
+    /// Receives a token's key and the token itself.
+    ///
+    /// # Errors
+    ///
+    /// An error stops [send_tokens_from_inputs], which returns it.
+    fn send(&mut self, key: String, value: String) -> Result<(), GroupByError>;
+}
+
+impl<F> Sink for F
+where
+    F: FnMut(String, String) -> Result<(), GroupByError>,
+{
+    fn send(&mut self, key: String, value: String) -> Result<(), GroupByError> {
+        self(key, value)
+    }
+}
+
+/// Reads inputs as [build_groups_from_inputs] would, but sends each token and its key to `sink`
+/// as soon as the key is known, instead of adding them to a collection. Labels and
+/// [InputOptions::with_filename] apply as usual.
+///
+/// ```
+/// use groupby::command_line::build_groups::send_tokens_from_inputs;
+/// use groupby::command_line::options::*;
+/// use std::path::PathBuf;
+///
+/// let path = std::env::temp_dir().join("groupby-send-tokens-doctest");
+/// std::fs::write(&path, "apple\nbanana\navocado\n").unwrap();
+///
+/// let options = GroupByOptions {
+///     input: InputOptions {
+///         files: vec![path.clone()],
+///         ..Default::default()
+///     },
+///     grouping: GroupingSpecifier::FirstChars(1),
+///     ..Default::default()
+/// };
+///
+/// let mut sent = vec![];
+/// send_tokens_from_inputs(
+///     &mut |key, value| {
+///         sent.push(format!("{}={}", key, value));
+///         Ok(())
+///     },
+///     &options,
+/// )
+/// .unwrap();
+/// assert_eq!(sent, vec!["a=apple", "b=banana", "a=avocado"]);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+///
+/// # Errors
+///
+/// Returns any error that [build_groups_from_inputs] returns, or the first error that `sink`
+/// returns, in which case no more input is read.
+pub fn send_tokens_from_inputs<S: Sink>(
+    sink: &mut S,
+    options: &GroupByOptions,
+) -> Result<(), GroupByError> {
+    let error = Cell::new(None);
+    let mut sending = Sending {
+        sink,
+        error: &error,
+    };
+    build_groups_from_inputs_checked(&mut sending, options, &|| error.take().map_or(Ok(()), Err))
+}
+
+/// Groups each of `tokens` as [build_groups] would, but sends each token and its key to `sink` as
+/// soon as the key is known, like [send_tokens_from_inputs]. Labels apply as usual. Tokens are
+/// taken as they are, so [InputOptions] doesn't apply, and neither do CSV column names.
+///
+/// ```
+/// use groupby::command_line::build_groups::send_tokens;
+/// use groupby::command_line::options::*;
+///
+/// let options = GroupByOptions {
+///     grouping: GroupingSpecifier::LastChars(1),
+///     ..Default::default()
+/// };
+///
+/// let mut sent = vec![];
+/// let tokens = vec!["apple".to_string(), "kiwi".to_string()];
+/// send_tokens(
+///     tokens,
+///     &mut |key, value| {
+///         sent.push(format!("{}={}", key, value));
+///         Ok(())
+///     },
+///     &options,
+/// )
+/// .unwrap();
+/// assert_eq!(sent, vec!["e=apple", "i=kiwi"]);
+/// ```
+///
+/// # Errors
+///
+/// Returns the first error that the grouper or `sink` returns, in which case no more tokens are
+/// taken.
+pub fn send_tokens<T, S>(
+    tokens: T,
+    sink: &mut S,
+    options: &GroupByOptions,
+) -> Result<(), GroupByError>
+where
+    T: IntoIterator<Item = String>,
+    S: Sink,
+{
+    let error = Cell::new(None);
+    let mut sending = Sending {
+        sink,
+        error: &error,
+    };
+    let mut map = Labeled::new(&mut sending, &options.labels);
+    let mut runner = Runner::new(&mut map, &options.grouping);
+    for token in tokens {
+        runner.run(token)?;
+        if let Some(e) = error.take() {
+            return Err(e);
+        }
+    }
+    runner.finish()?;
+    error.take().map_or(Ok(()), Err)
+}
+
+// A GroupedCollection that sends every value added to it on to a Sink, so that groupers can feed
+// a sink. It's always empty. The sink's first error is kept in error, for the check that
+// send_tokens_from_inputs passes to build_groups_checked.
+struct Sending<'a, S> {
+    sink: &'a mut S,
+    error: &'a Cell<Option<GroupByError>>,
+}
+
+impl<'s, 'a, S: Sink> GroupedCollection<'s, String, String, Vec<String>> for Sending<'a, S> {
+    type Iter = iter::Empty<(&'s String, &'s Vec<String>)>;
+    type IntoIter = iter::Empty<(String, Vec<String>)>;
+    type Entry = SendingEntry<'s>;
+
+    fn add(&mut self, key: String, value: String) {
+        send(self.sink, self.error, key, value);
+    }
+
+    fn entry(&'s mut self, key: String) -> Self::Entry {
+        SendingEntry {
+            sink: self.sink,
+            error: self.error,
+            key,
+        }
+    }
+
+    fn get(&'s self, _key: &String) -> Option<&'s Vec<String>> {
+        None
+    }
+
+    fn remove(&mut self, _key: &String) -> Option<Vec<String>> {
+        None
+    }
+
+    fn retain<F>(&mut self, _keep: F)
+    where
+        F: FnMut(&String, &mut Vec<String>) -> bool,
+    {
+    }
+
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn contains_key(&self, _key: &String) -> bool {
+        false
+    }
+
+    fn iter(&'s self) -> Self::Iter {
+        iter::empty()
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        iter::empty()
+    }
+}
+
+// Sends a key and value to sink, keeping the sink's first error in error.
+fn send(sink: &mut dyn Sink, error: &Cell<Option<GroupByError>>, key: String, value: String) {
+    if let Err(e) = sink.send(key, value) {
+        let first = error.take();
+        error.set(first.or(Some(e)));
+    }
+}
+
+// Sending's entry, which sends its key and a value when the value is pushed.
+struct SendingEntry<'s> {
+    sink: &'s mut dyn Sink,
+    error: &'s Cell<Option<GroupByError>>,
+    key: String,
+}
+
+impl<'s> GroupEntry<'s, String, Vec<String>> for SendingEntry<'s> {
+    fn push(self, value: String) {
+        send(self.sink, self.error, self.key, value);
+    }
+
+    fn or_insert_with<F>(self, _default: F) -> &'s mut Vec<String>
+    where
+        F: FnOnce() -> Vec<String>,
+    {
+        panic!("A sink doesn't keep groups, so it has none to return");
+    }
+}
+
+/// Converts `token` to a [String] following `policy`, or returns `None` if it should be skipped.
+///
+/// # Errors
+///
+/// Returns [GroupByError::InvalidInput] if `token` isn't valid UTF-8 and `policy` is
+/// [InvalidUtf8::Strict].
+///
+/// ```
+/// use groupby::command_line::build_groups::decode;
+/// use groupby::command_line::options::InvalidUtf8;
+///
+/// let decode = |token: &[u8], policy| decode(token.to_vec(), policy).ok();
+/// assert_eq!(decode(b"caf\xc3\xa9", InvalidUtf8::Strict), Some(Some("café".to_string())));
+/// assert_eq!(decode(b"caf\xe9", InvalidUtf8::Lossy), Some(Some("caf\u{fffd}".to_string())));
+/// assert_eq!(decode(b"caf\xe9", InvalidUtf8::Skip), Some(None));
+/// assert_eq!(decode(b"caf\xe9", InvalidUtf8::Strict), None);
+/// ```
+pub fn decode(token: Vec<u8>, policy: InvalidUtf8) -> Result<Option<String>, GroupByError> {
+    let error = match String::from_utf8(token) {
+        Ok(token) => return Ok(Some(token)),
+        Err(error) => error,
+    };
+    match policy {
+        InvalidUtf8::Lossy => Ok(Some(String::from_utf8_lossy(error.as_bytes()).into_owned())),
+        InvalidUtf8::Skip => {
+            eprintln!(
+                "Skipping input that isn't valid UTF-8: {}",
+                String::from_utf8_lossy(error.as_bytes())
+            );
+            Ok(None)
+        }
+        InvalidUtf8::Strict => Err(GroupByError::InvalidInput(format!(
+            "Input isn't valid UTF-8 ({}): {}",
+            error.utf8_error(),
+            String::from_utf8_lossy(error.as_bytes())
+        ))),
+    }
+}
+
+// Iterates over the lines of input, like BufRead::lines, converting each one following policy.
+fn lines<I: BufRead>(
+    input: I,
+    policy: InvalidUtf8,
+) -> impl Iterator<Item = Result<String, GroupByError>> {
+    input.split(b'\n').filter_map(move |line| {
+        let mut line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e.into())),
+        };
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        decode(line, policy).transpose()
+    })
+}
+
+// Reads input as paragraphs, i.e. runs of lines that aren't blank, joining the lines of each with
+// newlines. Blank lines, which are empty or only whitespace, just separate paragraphs.
+fn paragraphs<I: BufRead>(
+    input: I,
+    policy: InvalidUtf8,
+) -> impl Iterator<Item = Result<String, GroupByError>> {
+    let mut lines = lines(input, policy);
+    iter::from_fn(move || {
+        let mut paragraph: Option<String> = None;
+        loop {
+            match lines.next() {
+                Some(Ok(line)) if line.trim().is_empty() => {
+                    if paragraph.is_some() {
+                        return paragraph.map(Ok);
+                    }
+                }
+                Some(Ok(line)) => match &mut paragraph {
+                    Some(paragraph) => {
+                        paragraph.push('\n');
+                        paragraph.push_str(&line);
+                    }
+                    None => paragraph = Some(line),
+                },
+                Some(Err(e)) => return Some(Err(e)),
+                None => return paragraph.map(Ok),
+            }
+        }
+    })
+}
+
+// Splits input into tokens on every occurrence of a separator, which must not be empty, reading
+// only a buffer at a time and searching it with memchr's memmem.
+//
+// With final_empty, input that ends with the separator, or is empty, yields a final empty token,
+// like str::split. Without it, it doesn't, like BufRead::split.
+struct Split<'f, I> {
+    input: I,
+    finder: Finder<'f>,
+    final_empty: bool,
+
+    // Bytes read but not yet returned, which start at pending[start].
+    pending: Vec<u8>,
+    start: usize,
+
+    // How far into pending we've already searched without finding the separator.
+    searched: usize,
+
+    done: bool,
+}
+
+impl<'f, I: BufRead> Split<'f, I> {
+    fn new(input: I, separator: &'f [u8], final_empty: bool) -> Self {
+        Split {
+            input,
+            finder: Finder::new(separator),
+            final_empty,
+            pending: vec![],
+            start: 0,
+            searched: 0,
+            done: false,
+        }
+    }
+}
+
+impl<'f, I: BufRead> Iterator for Split<'f, I> {
+    type Item = io::Result<Vec<u8>>;
 
-            let mut buffer = String::new();
-            input.read_to_string(&mut buffer).unwrap();
-            for token in buffer.split(s) {
-                runner.run(token.to_string());
+    fn next(&mut self) -> Option<Self::Item> {
+        let separator_len = self.finder.needle().len();
+        while !self.done {
+            if let Some(i) = self.finder.find(&self.pending[self.searched..]) {
+                let end = self.searched + i;
+                let token = self.pending[self.start..end].to_vec();
+                self.start = end + separator_len;
+                self.searched = self.start;
+                return Some(Ok(token));
             }
+
+            // A separator might straddle the end of what we have, so search its start again.
+            self.searched = self
+                .pending
+                .len()
+                .saturating_sub(separator_len - 1)
+                .max(self.start);
+
+            let buffer = match self.input.fill_buf() {
+                Ok(buffer) => buffer,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+            if buffer.is_empty() {
+                self.done = true;
+                let token = self.pending.split_off(self.start);
+                if token.is_empty() && !self.final_empty {
+                    return None;
+                }
+                return Some(Ok(token));
+            }
+
+            // Drop the bytes we've already returned before reading more.
+            self.pending.drain(..self.start);
+            self.searched -= self.start;
+            self.start = 0;
+
+            let read = buffer.len();
+            self.pending.extend_from_slice(buffer);
+            self.input.consume(read);
         }
+        None
     }
 }
 
@@ -105,22 +818,82 @@ mod tests {
     mod build_groups {
         use super::*;
         use crate::grouped_collections::fake_map::*;
-        use std::io::BufReader;
+        use std::io::{self, BufReader, Read};
+
+        // A reader that returns its data, then fails.
+        struct FailingReader(&'static [u8]);
+
+        impl Read for FailingReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0.is_empty() {
+                    return Err(io::Error::other("disk on fire"));
+                }
+                self.0.read(buf)
+            }
+        }
 
         fn works_with(
             input_separator: Separator,
             input: &'static str,
             expected: Vec<&'static str>,
         ) {
-            let input: BufReader<&[u8]> = BufReader::new(input.as_bytes());
+            works_with_labels(input_separator, Labels::default(), input, expected);
+        }
+
+        fn works_with_labels(
+            input_separator: Separator,
+            labels: Labels,
+            input: &'static str,
+            expected: Vec<&'static str>,
+        ) {
+            works_with_options(
+                input_separator,
+                labels,
+                InvalidUtf8::Lossy,
+                input.as_bytes(),
+                expected,
+            );
+        }
+
+        fn works_with_options(
+            input_separator: Separator,
+            labels: Labels,
+            invalid_utf8: InvalidUtf8,
+            input: &'static [u8],
+            expected: Vec<&'static str>,
+        ) {
+            let input: BufReader<&[u8]> = BufReader::new(input);
             let mut map = FakeMap::new();
+            let options = options_for(input_separator, labels, invalid_utf8);
+
+            build_groups(input, &mut map, &options).unwrap();
+            assert_eq!(
+                *map.calls(),
+                expected
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<String>>()
+            );
+        }
 
+        fn options_for(
+            input_separator: Separator,
+            labels: Labels,
+            invalid_utf8: InvalidUtf8,
+        ) -> GroupByOptions {
             // Only input and grouping are relevant; output is unused.
-            let options = GroupByOptions {
+            GroupByOptions {
                 input: InputOptions {
                     separator: input_separator,
+                    invalid_utf8,
+                    ..Default::default()
                 },
                 grouping: GroupingSpecifier::FirstChars(2000),
+                labels,
+                unique: false,
+                min_size: None,
+                max_size: None,
+                count_values: false,
                 output: OutputOptions {
                     separator: Separator::Line,
                     only_group_names: false,
@@ -128,17 +901,11 @@ mod tests {
                     parallel: true,
                     headers: true,
                     stats: false,
+                    ..Default::default()
                 },
-            };
-
-            build_groups(input, &mut map, &options);
-            assert_eq!(
-                *map.calls(),
-                expected
-                    .iter()
-                    .map(ToString::to_string)
-                    .collect::<Vec<String>>()
-            );
+                seed: None,
+                explain: false,
+            }
         }
 
         #[test]
@@ -163,11 +930,20 @@ mod tests {
         fn works_with_null_separators() {
             works_with(
                 Separator::Null,
-                "1\02\03\04",
+                "1\x002\x003\x004",
                 vec!["1:1", "2:2", "3:3", "4:4"],
             );
         }
 
+        #[test]
+        fn works_with_paragraph_separators() {
+            works_with(
+                Separator::Paragraph,
+                "\na\nb\n\n\nc\r\n \t\nd",
+                vec!["a\nb:a\nb", "c:c", "d:d"],
+            );
+        }
+
         #[test]
         fn works_with_custom_separators() {
             works_with(
@@ -182,5 +958,331 @@ mod tests {
                 vec!["A:A", "B:B", "C:C"],
             );
         }
+
+        #[test]
+        fn splits_across_reads() {
+            // A tiny buffer, so that separators and tokens straddle reads.
+            for (separator, input, expected) in [
+                (
+                    Separator::Custom("--".to_string()),
+                    "ab--c----d--",
+                    vec!["ab", "c", "", "d", ""],
+                ),
+                (Separator::Custom("--".to_string()), "", vec![""]),
+                (Separator::Null, "ab\0c\0\0d\0", vec!["ab", "c", "", "d"]),
+                (Separator::Null, "", vec![]),
+            ] {
+                let mut map = FakeMap::new();
+                let options = options_for(separator, Labels::default(), InvalidUtf8::Lossy);
+                let input = BufReader::with_capacity(1, input.as_bytes());
+                build_groups(input, &mut map, &options).unwrap();
+                let expected: Vec<String> = expected
+                    .into_iter()
+                    .map(|token| format!("{}:{}", token, token))
+                    .collect();
+                assert_eq!(*map.calls(), expected);
+            }
+        }
+
+        #[test]
+        fn works_with_crlf_line_endings() {
+            works_with(Separator::Line, "1\r\n2\r\n", vec!["1:1", "2:2"]);
+        }
+
+        #[test]
+        fn replaces_invalid_utf8() {
+            works_with_options(
+                Separator::Null,
+                Labels::default(),
+                InvalidUtf8::Lossy,
+                b"a\xff\x00b",
+                vec!["a\u{fffd}:a\u{fffd}", "b:b"],
+            );
+        }
+
+        #[test]
+        fn skips_invalid_utf8() {
+            for separator in [
+                Separator::Line,
+                Separator::Space,
+                Separator::Custom("--".to_string()),
+            ] {
+                let input: &[u8] = match separator {
+                    Separator::Custom(_) => b"a--\xff--b",
+                    _ => b"a\n\xff\nb",
+                };
+                works_with_options(
+                    separator,
+                    Labels::default(),
+                    InvalidUtf8::Skip,
+                    input,
+                    vec!["a:a", "b:b"],
+                );
+            }
+        }
+
+        #[test]
+        fn rejects_invalid_utf8_when_strict() {
+            let mut map = FakeMap::new();
+            let options = options_for(Separator::Line, Labels::default(), InvalidUtf8::Strict);
+            let result = build_groups(&b"a\n\xff\nb"[..], &mut map, &options);
+            assert!(matches!(result, Err(GroupByError::InvalidInput(_))));
+            assert_eq!(*map.calls(), vec!["a:a"]);
+        }
+
+        #[test]
+        fn trims_and_skips_empty_tokens() {
+            for (trim, skip_empty, expected) in [
+                (false, false, vec![" a: a", "b :b ", "  :  ", ":"]),
+                (true, false, vec!["a:a", "b:b", ":", ":"]),
+                (false, true, vec![" a: a", "b :b ", "  :  "]),
+                (true, true, vec!["a:a", "b:b"]),
+            ] {
+                let mut map = FakeMap::new();
+                let mut options = options_for(
+                    Separator::Custom(",".to_string()),
+                    Labels::default(),
+                    InvalidUtf8::Lossy,
+                );
+                options.input.trim = trim;
+                options.input.skip_empty = skip_empty;
+                build_groups(&b" a,b ,  ,"[..], &mut map, &options).unwrap();
+                assert_eq!(*map.calls(), expected);
+            }
+        }
+
+        #[test]
+        fn stops_reading_at_the_limit() {
+            for (separator, input) in [
+                (Separator::Line, &b"a\n\nb\n"[..]),
+                (Separator::Space, &b"a b\n"[..]),
+            ] {
+                let mut map = FakeMap::new();
+                let mut options = options_for(separator, Labels::default(), InvalidUtf8::Lossy);
+                options.input.skip_empty = true;
+                options.input.limit = Some(2);
+                // Reading any further would fail.
+                let input = BufReader::new(FailingReader(input));
+                build_groups(input, &mut map, &options).unwrap();
+                assert_eq!(*map.calls(), vec!["a:a", "b:b"]);
+            }
+        }
+
+        #[test]
+        fn samples_tokens_reproducibly() {
+            let input: String = (0..200).map(|i| format!("{}\n", i)).collect();
+            let sample = |p: f64, seed: u64| {
+                let mut map = FakeMap::new();
+                let mut options =
+                    options_for(Separator::Line, Labels::default(), InvalidUtf8::Lossy);
+                options.input.sample = Some(Fraction(p));
+                options.seed = Some(seed);
+                build_groups(input.as_bytes(), &mut map, &options).unwrap();
+                let calls = map.calls().clone();
+                calls
+            };
+
+            assert_eq!(sample(0.0, 1).len(), 0);
+            assert_eq!(sample(1.0, 1).len(), 200);
+            let half = sample(0.5, 1);
+            assert!((50..150).contains(&half.len()));
+            assert_eq!(half, sample(0.5, 1));
+            assert_ne!(half, sample(0.5, 2));
+        }
+
+        #[test]
+        fn returns_read_errors() {
+            for separator in [
+                Separator::Line,
+                Separator::Space,
+                Separator::Null,
+                Separator::Custom(",".to_string()),
+            ] {
+                let mut map = FakeMap::new();
+                let options = options_for(separator, Labels::default(), InvalidUtf8::Lossy);
+                let input = BufReader::new(FailingReader(b"a\n"));
+                match build_groups(input, &mut map, &options) {
+                    Err(GroupByError::Io(e)) => assert_eq!(e.to_string(), "disk on fire"),
+                    result => panic!("Expected an I/O error, got {:?}", result),
+                }
+            }
+        }
+
+        #[test]
+        fn applies_labels() {
+            works_with_labels(
+                Separator::Line,
+                Labels::new(vec!["1-2:low".parse().unwrap()]),
+                "1\n2\n3",
+                vec!["low:1", "low:2", "3:3"],
+            );
+        }
+
+        #[test]
+        fn reads_csv_records_by_column_name() {
+            let input = BufReader::new("id,city\n1,\"Portland,\nOR\"\n2,Salem".as_bytes());
+            let mut map = FakeMap::new();
+            let options = GroupByOptions {
+                input: InputOptions {
+                    csv: true,
+                    ..Default::default()
+                },
+                grouping: GroupingSpecifier::CsvColumn(Column::Name("city".to_string()), ','),
+                labels: Labels::default(),
+                unique: false,
+                min_size: None,
+                max_size: None,
+                count_values: false,
+                output: Default::default(),
+                seed: None,
+                explain: false,
+            };
+
+            build_groups(input, &mut map, &options).unwrap();
+            assert_eq!(
+                *map.calls(),
+                vec!["Portland,\nOR:1,\"Portland,\nOR\"", "Salem:2,Salem"]
+            );
+        }
+
+        #[test]
+        fn decodes_csv_records_by_policy() {
+            let input = &b"a,b\n\xff,c\nd,e"[..];
+            let group = |invalid_utf8| {
+                let mut map = FakeMap::new();
+                let options = GroupByOptions {
+                    input: InputOptions {
+                        csv: true,
+                        invalid_utf8,
+                        ..Default::default()
+                    },
+                    grouping: GroupingSpecifier::CsvColumn(Column::Index(1), ','),
+                    labels: Labels::default(),
+                    unique: false,
+                    min_size: None,
+                    max_size: None,
+                    count_values: false,
+                    output: Default::default(),
+                    seed: None,
+                    explain: false,
+                };
+                build_groups(input, &mut map, &options).map(|_| map.calls().clone())
+            };
+
+            assert_eq!(
+                group(InvalidUtf8::Lossy).unwrap(),
+                vec!["\u{fffd}:\u{fffd},c", "d:d,e"]
+            );
+            assert_eq!(group(InvalidUtf8::Skip).unwrap(), vec!["d:d,e"]);
+            assert!(matches!(
+                group(InvalidUtf8::Strict),
+                Err(GroupByError::InvalidInput(_))
+            ));
+        }
+
+        #[test]
+        fn reads_inputs_in_order_with_filenames() {
+            let dir = std::env::temp_dir();
+            let first = dir.join(format!("groupby-build-first-{}", std::process::id()));
+            let second = dir.join(format!("groupby-build-second-{}", std::process::id()));
+            std::fs::write(&first, "ab").unwrap();
+            std::fs::write(&second, "cd\nef").unwrap();
+
+            let mut options = options_for(Separator::Line, Labels::default(), InvalidUtf8::Lossy);
+            options.grouping = GroupingSpecifier::FirstChars(1);
+            options.input.files = vec![first.clone(), second.clone()];
+            options.input.with_filename = true;
+            let mut map = FakeMap::new();
+            build_groups_from_inputs(&mut map, &options).unwrap();
+
+            // The last line of the first file doesn't run into the second file.
+            let (first_name, second_name) = (first.display(), second.display());
+            assert_eq!(
+                *map.calls(),
+                vec![
+                    format!("a:{}:ab", first_name),
+                    format!("c:{}:cd", second_name),
+                    format!("e:{}:ef", second_name),
+                ]
+            );
+
+            std::fs::remove_file(&first).unwrap();
+            std::fs::remove_file(&second).unwrap();
+        }
+
+        #[test]
+        fn copies_every_input_with_tee() {
+            let dir = std::env::temp_dir();
+            let first = dir.join(format!("groupby-tee-first-{}", std::process::id()));
+            let second = dir.join(format!("groupby-tee-second-{}", std::process::id()));
+            let copy = dir.join(format!("groupby-tee-copy-{}", std::process::id()));
+            std::fs::write(&first, "a\r\nb").unwrap();
+            std::fs::write(&second, "c\n").unwrap();
+
+            let mut options = options_for(Separator::Line, Labels::default(), InvalidUtf8::Lossy);
+            options.input.files = vec![first.clone(), second.clone()];
+            options.input.tee = Some(copy.clone());
+            let mut map = FakeMap::new();
+            build_groups_from_inputs(&mut map, &options).unwrap();
+
+            assert_eq!(*map.calls(), vec!["a:a", "b:b", "c:c"]);
+            assert_eq!(std::fs::read(&copy).unwrap(), b"a\r\nbc\n");
+
+            for path in [first, second, copy] {
+                std::fs::remove_file(path).unwrap();
+            }
+        }
+
+        #[test]
+        fn runs_one_grouper_over_every_input() {
+            let dir = std::env::temp_dir();
+            let first = dir.join(format!("groupby-chunk-first-{}", std::process::id()));
+            let second = dir.join(format!("groupby-chunk-second-{}", std::process::id()));
+            std::fs::write(&first, "a\nb\nc\n").unwrap();
+            std::fs::write(&second, "d\ne\nf\n").unwrap();
+
+            let mut options = options_for(Separator::Line, Labels::default(), InvalidUtf8::Lossy);
+            options.grouping = GroupingSpecifier::Chunk(2);
+            options.input.files = vec![first.clone(), second.clone()];
+            let mut map = FakeMap::new();
+            build_groups_from_inputs(&mut map, &options).unwrap();
+
+            // Chunks count tokens across inputs, as if the inputs were concatenated.
+            assert_eq!(*map.calls(), vec!["0:a", "0:b", "1:c", "1:d", "2:e", "2:f"]);
+
+            std::fs::remove_file(&first).unwrap();
+            std::fs::remove_file(&second).unwrap();
+        }
+
+        #[test]
+        fn reads_csv_inputs_against_the_first_header() {
+            let dir = std::env::temp_dir();
+            let first = dir.join(format!("groupby-csv-first-{}", std::process::id()));
+            let second = dir.join(format!("groupby-csv-second-{}", std::process::id()));
+            let third = dir.join(format!("groupby-csv-third-{}", std::process::id()));
+            std::fs::write(&first, "").unwrap();
+            std::fs::write(&second, "id,city\n1,Salem\n").unwrap();
+            std::fs::write(&third, "id,city\n2,Bend\n").unwrap();
+
+            let mut options = options_for(Separator::Line, Labels::default(), InvalidUtf8::Lossy);
+            options.input.csv = true;
+            options.grouping = GroupingSpecifier::CsvColumn(Column::Name("city".to_string()), ',');
+            options.input.files = vec![first.clone(), second.clone(), third.clone()];
+            let mut map = FakeMap::new();
+            build_groups_from_inputs(&mut map, &options).unwrap();
+            assert_eq!(*map.calls(), vec!["Salem:1,Salem", "Bend:2,Bend"]);
+
+            // A header that moves the column can't share the first header's grouper.
+            std::fs::write(&third, "city,id\nBend,2\n").unwrap();
+            let mut map = FakeMap::new();
+            assert!(matches!(
+                build_groups_from_inputs(&mut map, &options),
+                Err(GroupByError::InvalidInput(_))
+            ));
+
+            for path in [first, second, third] {
+                std::fs::remove_file(path).unwrap();
+            }
+        }
     }
 }
@@ -0,0 +1,278 @@
+//! The [Checkpoint] type, which records which groups' commands have completed successfully.
+//!
+//! Running a command over thousands of groups can take a long time, and repeating all of that work
+//! after a crash or an interrupted run is painful. A checkpoint file records the key of each group
+//! whose command exits successfully. A later run over the same input can then open the checkpoint
+//! with `resume` set and skip every group it lists.
+//!
+//! # File format
+//!
+//! The file holds one record per completed group: the group's key followed by a null character.
+//! Records are appended as commands finish, so the file is always valid up to its last complete
+//! record. If a run is interrupted partway through writing a record, resuming discards that
+//! partial record, truncating the file after the last complete one, and its group simply runs
+//! again. Because the null character ends each record, keys that contain one can't be recorded.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::checkpoint::Checkpoint;
+//!
+//! let path = std::env::temp_dir().join("groupby-checkpoint-doctest");
+//!
+//! // Start a fresh checkpoint and record a group.
+//! let checkpoint = Checkpoint::open(&path, false).unwrap();
+//! checkpoint.record("ecs440").unwrap();
+//! drop(checkpoint);
+//!
+//! // Later, resume from the same file.
+//! let checkpoint = Checkpoint::open(&path, true).unwrap();
+//! assert!(checkpoint.is_complete("ecs440"));
+//! assert!(!checkpoint.is_complete("ecs450"));
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The byte that terminates each record in a checkpoint file.
+const TERMINATOR: u8 = b'\0';
+
+/// A checkpoint file that records which groups' commands have completed successfully.
+///
+/// This type is safe to share across threads: [Checkpoint::record] locks the underlying file, so
+/// parallel command runners can record groups as they finish.
+#[derive(Debug)]
+pub struct Checkpoint {
+    /// Keys of groups that were already complete when the checkpoint was opened.
+    completed: HashSet<String>,
+
+    /// The checkpoint file, opened for writing.
+    file: Mutex<File>,
+}
+
+impl Checkpoint {
+    /// Opens the checkpoint file at `path`, creating it if necessary.
+    ///
+    /// If `resume` is true, reads the groups already recorded in the file (if it exists), discards
+    /// any partial record at the end of the file, and appends new records after the last complete
+    /// one. Otherwise, starts a fresh checkpoint, truncating any existing file.
+    pub fn open<P: AsRef<Path>>(path: P, resume: bool) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        let (completed, complete_len) = if resume {
+            read_completed(path)?
+        } else {
+            (HashSet::new(), 0)
+        };
+
+        let mut open_options = OpenOptions::new();
+        open_options.create(true);
+        if resume {
+            open_options.append(true);
+        } else {
+            open_options.write(true).truncate(true);
+        }
+        let file = open_options.open(path)?;
+
+        // Drop any partial record, so that the next record doesn't run into it.
+        if resume {
+            file.set_len(complete_len)?;
+        }
+        let file = Mutex::new(file);
+
+        Ok(Checkpoint { completed, file })
+    }
+
+    /// Returns true if the checkpoint file listed `key` as complete when it was opened.
+    ///
+    /// Groups recorded through [Checkpoint::record] after opening the checkpoint are not reported
+    /// here; each group is expected to run at most once per run.
+    pub fn is_complete(&self, key: &str) -> bool {
+        self.completed.contains(key)
+    }
+
+    /// Records `key` as complete by appending it to the checkpoint file.
+    ///
+    /// Returns an error of kind [io::ErrorKind::InvalidInput] if `key` contains a null character,
+    /// since the file format can't represent it.
+    pub fn record(&self, key: &str) -> io::Result<()> {
+        if key.as_bytes().contains(&TERMINATOR) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "keys containing a null character can't be recorded",
+            ));
+        }
+
+        // Build the whole record first so that it reaches the file in a single write.
+        let mut record = Vec::with_capacity(key.len() + 1);
+        record.extend_from_slice(key.as_bytes());
+        record.push(TERMINATOR);
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&record)?;
+        file.flush()
+    }
+}
+
+// Reads the set of completed keys from the checkpoint file at `path`, along with the length in
+// bytes of the file's complete records. A missing file simply means that nothing has completed yet.
+fn read_completed(path: &Path) -> io::Result<(HashSet<String>, u64)> {
+    let mut buffer = vec![];
+    match File::open(path) {
+        Ok(mut file) => {
+            file.read_to_end(&mut buffer)?;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => return Err(e),
+    }
+
+    // Anything after the last terminator is a partial record from an interrupted write, so we
+    // only keep records that precede a terminator.
+    let complete_len = match buffer.iter().rposition(|b| *b == TERMINATOR) {
+        Some(i) => i + 1,
+        None => 0,
+    };
+    let mut records: Vec<&[u8]> = buffer[..complete_len].split(|b| *b == TERMINATOR).collect();
+    records.pop();
+
+    let completed = records
+        .into_iter()
+        .map(|record| String::from_utf8_lossy(record).to_string())
+        .collect();
+    Ok((completed, complete_len as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Returns a path in the temporary directory that's unique to the calling test.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "groupby-checkpoint-{}-{}",
+            name,
+            std::process::id()
+        ))
+    }
+
+    mod open {
+        use super::*;
+
+        #[test]
+        fn without_resume_truncates_existing_file() {
+            let path = temp_path("truncates");
+            fs::write(&path, b"old\0").unwrap();
+
+            let checkpoint = Checkpoint::open(&path, false).unwrap();
+            assert!(!checkpoint.is_complete("old"));
+            drop(checkpoint);
+
+            assert_eq!(fs::read(&path).unwrap(), b"");
+            fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn with_resume_reads_existing_records() {
+            let path = temp_path("reads");
+            fs::write(&path, b"Cats\0Dogs\0").unwrap();
+
+            let checkpoint = Checkpoint::open(&path, true).unwrap();
+            assert!(checkpoint.is_complete("Cats"));
+            assert!(checkpoint.is_complete("Dogs"));
+            assert!(!checkpoint.is_complete("Birds"));
+
+            fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn with_resume_ignores_partial_record() {
+            let path = temp_path("partial");
+            fs::write(&path, b"Cats\0Do").unwrap();
+
+            let checkpoint = Checkpoint::open(&path, true).unwrap();
+            assert!(checkpoint.is_complete("Cats"));
+            assert!(!checkpoint.is_complete("Do"));
+
+            fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn with_resume_truncates_partial_record() {
+            let path = temp_path("truncates-partial");
+            fs::write(&path, b"Cats\0Do").unwrap();
+
+            let checkpoint = Checkpoint::open(&path, true).unwrap();
+            checkpoint.record("Dogs").unwrap();
+            drop(checkpoint);
+            assert_eq!(fs::read(&path).unwrap(), b"Cats\0Dogs\0");
+
+            let checkpoint = Checkpoint::open(&path, true).unwrap();
+            assert!(checkpoint.is_complete("Cats"));
+            assert!(checkpoint.is_complete("Dogs"));
+
+            fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn with_resume_truncates_file_without_complete_records() {
+            let path = temp_path("truncates-all");
+            fs::write(&path, b"Ca").unwrap();
+
+            let checkpoint = Checkpoint::open(&path, true).unwrap();
+            checkpoint.record("Cats").unwrap();
+            drop(checkpoint);
+
+            assert_eq!(fs::read(&path).unwrap(), b"Cats\0");
+            fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn with_resume_creates_missing_file() {
+            let path = temp_path("missing");
+            let _ = fs::remove_file(&path);
+
+            let checkpoint = Checkpoint::open(&path, true).unwrap();
+            assert!(!checkpoint.is_complete(""));
+            assert!(path.exists());
+
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    mod record {
+        use super::*;
+
+        #[test]
+        fn appends_records() {
+            let path = temp_path("appends");
+            fs::write(&path, b"Cats\0").unwrap();
+
+            let checkpoint = Checkpoint::open(&path, true).unwrap();
+            checkpoint.record("Dogs").unwrap();
+            checkpoint.record("").unwrap();
+            drop(checkpoint);
+
+            assert_eq!(fs::read(&path).unwrap(), b"Cats\0Dogs\0\0");
+            fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn rejects_key_containing_null() {
+            let path = temp_path("rejects-null");
+
+            let checkpoint = Checkpoint::open(&path, false).unwrap();
+            let error = checkpoint.record("Cats\0Dogs").unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            drop(checkpoint);
+
+            assert_eq!(fs::read(&path).unwrap(), b"");
+            fs::remove_file(&path).unwrap();
+        }
+    }
+}
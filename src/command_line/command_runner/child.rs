@@ -24,6 +24,9 @@ pub trait Child {
     /// Calling this method more than once may result in a panic, depending on the implementation.
     fn stdout(&mut self) -> Self::Stdout;
 
+    /// Returns the child's process ID.
+    fn id(&self) -> u32;
+
     /// Synchronously wait for the child process to finish, then return its captured output.
     ///
     /// Depending on the implementor, output may include, for instance, return status, standard
@@ -48,6 +51,10 @@ impl Child for process::Child {
         self.stdout.take().unwrap()
     }
 
+    fn id(&self) -> u32 {
+        process::Child::id(self)
+    }
+
     fn wait_with_output(self) -> io::Result<Self::Output> {
         process::Child::wait_with_output(self)
     }
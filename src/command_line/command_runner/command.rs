@@ -27,6 +27,19 @@ pub trait Command {
     fn stdin<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self;
 
     fn stdout<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self;
+
+    fn stderr<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self;
+
+    /// Mirrors [std::os::unix::process::CommandExt::process_group] on Unix. Does nothing elsewhere.
+    fn process_group(&mut self, pgroup: i32) -> &mut Self;
+
+    /// Applies `limits` to the child when it's spawned. See [Limits::apply].
+    fn limits(&mut self, limits: Limits) -> &mut Self;
+
+    fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>;
 }
 
 // These methods are not tested, since it is not feasible to test them.
@@ -56,4 +69,37 @@ impl Command for process::Command {
     fn stdout<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
         self.stdout(cfg)
     }
+
+    fn stderr<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.stderr(cfg)
+    }
+
+    #[cfg(unix)]
+    fn process_group(&mut self, pgroup: i32) -> &mut Self {
+        std::os::unix::process::CommandExt::process_group(self, pgroup)
+    }
+
+    #[cfg(not(unix))]
+    fn process_group(&mut self, _pgroup: i32) -> &mut Self {
+        self
+    }
+
+    #[cfg(unix)]
+    fn limits(&mut self, limits: Limits) -> &mut Self {
+        // SAFETY: Limits::apply() only makes system calls that are safe between fork and exec.
+        unsafe { std::os::unix::process::CommandExt::pre_exec(self, move || limits.apply()) }
+    }
+
+    #[cfg(not(unix))]
+    fn limits(&mut self, _limits: Limits) -> &mut Self {
+        self
+    }
+
+    fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.env(key, val)
+    }
 }
@@ -31,6 +31,19 @@ impl<'a, CC: Child> Handle<'a, CC> {
         self.child
     }
 
+    /// Returns a reader for the child's standard output, so that it can be read while the child
+    /// runs. [Handle::wait_with_output()] then returns empty standard output.
+    ///
+    /// Calling this method more than once may result in a panic, depending on the implementation.
+    pub fn stdout(&mut self) -> CC::Stdout {
+        self.child.stdout()
+    }
+
+    /// Returns the child's process ID.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
     /// Equivalent to [std::process::Child::wait_with_output].
     ///
     /// If you mean to call that method, **call this one instead**! Because the handle's
@@ -66,8 +79,8 @@ mod tests {
             // There's no easy way to establish equality due to the nature of the values involved,
             // so we have to write a mini integration test to reach a mocked stdin we can check.
             let mut handle = handle();
-            let inputs = vec!["1", "2"];
-            handle.stdin.write_all(inputs.iter());
+            let inputs = ["1", "2"];
+            handle.stdin.write_all(inputs.iter()).unwrap();
             let buffer = handle.stdin.writer().into_inner().unwrap();
             assert_eq!(buffer, b"1 >> 2 >> ");
         }
@@ -81,6 +94,16 @@ mod tests {
         }
     }
 
+    mod stdout {
+        use super::*;
+
+        #[test]
+        fn works() {
+            let mut handle = handle();
+            assert_eq!(handle.stdout(), b"the program output is a lie");
+        }
+    }
+
     mod child {
         use super::*;
 
@@ -0,0 +1,142 @@
+//! The [Limits] type, which restricts the resources available to a child process.
+
+use std::io;
+
+/// Limits on the resources a child process may use.
+///
+/// Each field that's `None` leaves the corresponding setting as the child inherits it. Limits only
+/// apply on Unix; elsewhere, they're ignored.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::command_runner::Limits;
+///
+/// let limits = Limits {
+///     nice: Some(10),
+///     ..Default::default()
+/// };
+/// assert!(!limits.is_unlimited());
+/// assert!(Limits::default().is_unlimited());
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Limits {
+    /// The niceness to run at, from -20 (the highest priority) to 19 (the lowest). Only a
+    /// privileged user can run a child at a lower niceness than its parent.
+    pub nice: Option<i32>,
+
+    /// The most virtual memory the child may use, in bytes (`RLIMIT_AS`). Allocations beyond this
+    /// fail.
+    pub max_memory: Option<u64>,
+
+    /// The most files the child may have open at once (`RLIMIT_NOFILE`).
+    pub max_open_files: Option<u64>,
+}
+
+impl Limits {
+    /// Returns true if no limits are set.
+    pub fn is_unlimited(&self) -> bool {
+        *self == Limits::default()
+    }
+
+    /// Applies these limits to the current process.
+    ///
+    /// This is meant to be called in a child process between `fork` and `exec`, e.g. with
+    /// [pre_exec](std::os::unix::process::CommandExt::pre_exec), so it only makes system calls that
+    /// are safe to make there. A resource limit is never raised above the current hard limit.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from the first system call that fails, e.g. if an unprivileged process
+    /// asks for a lower niceness.
+    #[cfg(unix)]
+    pub fn apply(&self) -> io::Result<()> {
+        if let Some(nice) = self.nice {
+            // SAFETY: setpriority() has no memory-safety requirements.
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        let lower = |resource, limit: u64| {
+            let mut rlimit = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            // SAFETY: rlimit is a valid rlimit struct for getrlimit() to fill in.
+            if unsafe { libc::getrlimit(resource, &mut rlimit) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            let limit = (limit as libc::rlim_t).min(rlimit.rlim_max);
+            rlimit = libc::rlimit {
+                rlim_cur: limit,
+                rlim_max: limit,
+            };
+            // SAFETY: rlimit is a valid rlimit struct for setrlimit() to read.
+            if unsafe { libc::setrlimit(resource, &rlimit) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        };
+        if let Some(bytes) = self.max_memory {
+            lower(libc::RLIMIT_AS, bytes)?;
+        }
+        if let Some(files) = self.max_open_files {
+            lower(libc::RLIMIT_NOFILE, files)?;
+        }
+        Ok(())
+    }
+
+    /// Does nothing, since limits only apply on Unix.
+    #[cfg(not(unix))]
+    pub fn apply(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    // Runs `script` in a shell with `limits` applied and returns its output.
+    fn run_with(limits: Limits, script: &str) -> String {
+        let mut command = Command::new("/bin/sh");
+        command.args(["-c", script]);
+        // SAFETY: Limits::apply() only makes system calls that are safe after fork().
+        unsafe {
+            command.pre_exec(move || limits.apply());
+        }
+        let output = command.output().unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[test]
+    fn sets_niceness() {
+        let limits = Limits {
+            nice: Some(19),
+            ..Default::default()
+        };
+        assert_eq!(run_with(limits, "nice"), "19");
+    }
+
+    #[test]
+    fn limits_open_files() {
+        let limits = Limits {
+            max_open_files: Some(64),
+            ..Default::default()
+        };
+        assert_eq!(run_with(limits, "ulimit -n"), "64");
+    }
+
+    #[test]
+    fn limits_memory() {
+        let limits = Limits {
+            max_memory: Some(1 << 30),
+            ..Default::default()
+        };
+        // ulimit -v reports kibibytes.
+        assert_eq!(run_with(limits, "ulimit -v"), (1 << 20).to_string());
+    }
+}
@@ -49,6 +49,11 @@ impl Child for MockChild {
         self.stdout.take().unwrap()
     }
 
+    // There's no real process, so there's no real ID.
+    fn id(&self) -> u32 {
+        0
+    }
+
     // Always succeeds.
     fn wait_with_output(self) -> io::Result<Self::Output> {
         Ok(self.output)
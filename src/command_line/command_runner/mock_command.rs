@@ -40,7 +40,7 @@ impl Command for MockCommand {
 
     fn spawn(&mut self) -> io::Result<Self::Child> {
         self.calls.push("spawn()".to_string());
-        Ok(MockChild::new(&self))
+        Ok(MockChild::new(self))
     }
 
     fn stdin<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
@@ -52,4 +52,32 @@ impl Command for MockCommand {
         self.calls.push(format!("stdout({:?})", cfg.into()));
         self
     }
+
+    fn stderr<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.calls.push(format!("stderr({:?})", cfg.into()));
+        self
+    }
+
+    fn process_group(&mut self, pgroup: i32) -> &mut Self {
+        self.calls.push(format!("process_group({})", pgroup));
+        self
+    }
+
+    fn limits(&mut self, limits: Limits) -> &mut Self {
+        self.calls.push(format!("limits({:?})", limits));
+        self
+    }
+
+    fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.calls.push(format!(
+            "env({}={})",
+            key.as_ref().to_string_lossy(),
+            val.as_ref().to_string_lossy()
+        ));
+        self
+    }
 }
@@ -12,6 +12,7 @@
 mod child;
 mod command;
 pub mod handle;
+pub mod limits;
 #[cfg(test)]
 pub mod mock_child;
 #[cfg(test)]
@@ -22,6 +23,7 @@ pub mod run;
 pub use child::Child;
 pub use command::Command;
 pub use handle::Handle;
+pub use limits::Limits;
 #[cfg(test)]
 pub use mock_child::MockChild;
 #[cfg(test)]
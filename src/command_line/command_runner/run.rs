@@ -3,47 +3,98 @@
 use super::*;
 use std::convert::AsRef;
 use std::ffi::OsStr;
+use std::io;
 use std::process::{self, Stdio};
 
-/// Spawns a [std::process::Command] with piped I/O and returns a handle to it.
+/// Spawns a [std::process::Command] with piped I/O and returns a handle to it, or the error from
+/// spawning it, e.g. if `program` doesn't exist.
 ///
-/// Note that standard error is not piped. Because we assume that we can't possibly know how the
-/// user will want to handle error output, we simply allow it to immediately be displayed. It's
-/// possible that this behavior might change in the future.
+/// Standard error is piped only if `capture_stderr` is true, in which case it's returned with the
+/// rest of the output by [Handle::wait_with_output()]. Otherwise, the child inherits our standard
+/// error, so error output is displayed immediately.
+///
+/// The child inherits our environment, plus each variable in `env`.
+///
+/// If `new_process_group` is true, the child runs in a new process group on Unix, so that an
+/// [interrupt](crate::command_line::interrupt) can terminate it along with any processes it starts.
+/// Such a child doesn't receive signals from the terminal, e.g. when the user presses Ctrl-C, so
+/// this is only appropriate if the caller handles them.
+///
+/// The child runs within `limits`, which are applied between `fork` and `exec` on Unix. If they
+/// can't be applied, e.g. because an unprivileged user asked for a lower niceness, spawning fails.
 ///
 /// # Examples
 ///
 /// ```
-/// use groupby::command_line::command_runner::run::run;
+/// use groupby::command_line::command_runner::{run::run, Limits};
 ///
-/// let handle = run("bash", ["-c", "echo hi"], "");
+/// let env = [("NAME".to_string(), "hi".to_string())];
+/// let script = ["-c", "echo $NAME; echo oops >&2"];
+/// let handle = run("bash", script, "", true, &env, false, Limits::default()).unwrap();
 /// let output = handle.wait_with_output().unwrap();
 /// assert_eq!(String::from_utf8_lossy(&output.stdout), String::from("hi\n"));
+/// assert_eq!(String::from_utf8_lossy(&output.stderr), String::from("oops\n"));
 /// ```
-pub fn run<'a, I>(program: &'a str, shell_args: I, separator: &'a str) -> Handle<'a, process::Child>
+pub fn run<'a, I>(
+    program: &'a str,
+    shell_args: I,
+    separator: &'a str,
+    capture_stderr: bool,
+    env: &[(String, String)],
+    new_process_group: bool,
+    limits: Limits,
+) -> io::Result<Handle<'a, process::Child>>
 where
     I: IntoIterator<Item = &'a str>,
 {
-    command::<process::Command, _, _>(program, shell_args, separator)
+    command::<process::Command, _, _>(
+        program,
+        shell_args,
+        separator,
+        capture_stderr,
+        env,
+        new_process_group,
+        limits,
+    )
 }
 
 /// A testable function that holds the main logic of run().
 ///
 /// Uses dependency injection to allow tests to mock [std::process::Command].
-fn command<C, I, S>(program: S, shell_args: I, separator: &str) -> Handle<'_, C::Child>
+fn command<'a, C, I, S>(
+    program: S,
+    shell_args: I,
+    separator: &'a str,
+    capture_stderr: bool,
+    env: &[(String, String)],
+    new_process_group: bool,
+    limits: Limits,
+) -> io::Result<Handle<'a, C::Child>>
 where
     C: Command,
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
 {
-    let child = C::new(program)
+    let mut command = C::new(program);
+    command
         .args(shell_args)
         .stdin(Stdio::piped()) // Stdio::piped is not tested.
-        .stdout(Stdio::piped()) // Stdio::piped is not tested.
-        .spawn()
-        .expect("Shell command failed.");
+        .stdout(Stdio::piped()); // Stdio::piped is not tested.
+    if new_process_group {
+        command.process_group(0);
+    }
+    if !limits.is_unlimited() {
+        command.limits(limits);
+    }
+    if capture_stderr {
+        command.stderr(Stdio::piped()); // Stdio::piped is not tested.
+    }
+    for (key, val) in env {
+        command.env(key, val);
+    }
+    let child = command.spawn()?;
 
-    Handle::new(child, separator)
+    Ok(Handle::new(child, separator))
 }
 
 #[cfg(test)]
@@ -57,9 +108,18 @@ mod tests {
         fn spawns_command_correctly() {
             let program = "groupby";
             let shell_args = ["-f3", "-c", "echo recursion five!"];
-            let handle = command::<MockCommand, _, _>(program, shell_args.clone(), ", ");
+            let handle = command::<MockCommand, _, _>(
+                program,
+                shell_args,
+                ", ",
+                false,
+                &[],
+                false,
+                Limits::default(),
+            )
+            .unwrap();
 
-            let expected: Vec<String> = vec![
+            let expected: Vec<String> = [
                 "new(groupby)",
                 "args([-f3, -c, echo recursion five!])",
                 "stdin(Stdio { .. })",
@@ -72,5 +132,73 @@ mod tests {
 
             assert_eq!(expected, handle.child().command().calls);
         }
+
+        #[test]
+        fn pipes_stderr_if_requested() {
+            let handle = command::<MockCommand, _, _>(
+                "groupby",
+                ["-f1"],
+                "\n",
+                true,
+                &[],
+                false,
+                Limits::default(),
+            )
+            .unwrap();
+            let calls = handle.child().command().calls.clone();
+            assert_eq!(calls[calls.len() - 2], "stderr(Stdio { .. })");
+        }
+
+        #[test]
+        fn sets_environment_variables() {
+            let env = [
+                ("GROUPBY_KEY".to_string(), "a b".to_string()),
+                ("GROUPBY_SIZE".to_string(), "2".to_string()),
+            ];
+            let handle = command::<MockCommand, _, _>(
+                "groupby",
+                ["-f1"],
+                "\n",
+                false,
+                &env,
+                false,
+                Limits::default(),
+            )
+            .unwrap();
+            let calls = handle.child().command().calls.clone();
+            assert_eq!(
+                calls[calls.len() - 3..],
+                ["env(GROUPBY_KEY=a b)", "env(GROUPBY_SIZE=2)", "spawn()"]
+            );
+        }
+
+        #[test]
+        fn applies_limits_if_any() {
+            let limits = Limits {
+                nice: Some(5),
+                ..Default::default()
+            };
+            let handle =
+                command::<MockCommand, _, _>("groupby", ["-f1"], "\n", false, &[], false, limits)
+                    .unwrap();
+            let calls = handle.child().command().calls.clone();
+            assert_eq!(calls[calls.len() - 2], format!("limits({:?})", limits));
+        }
+
+        #[test]
+        fn starts_new_process_group_if_requested() {
+            let handle = command::<MockCommand, _, _>(
+                "groupby",
+                ["-f1"],
+                "\n",
+                false,
+                &[],
+                true,
+                Limits::default(),
+            )
+            .unwrap();
+            let calls = handle.child().command().calls.clone();
+            assert_eq!(calls[calls.len() - 2], "process_group(0)");
+        }
     }
 }
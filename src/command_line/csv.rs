@@ -0,0 +1,261 @@
+//! Just enough CSV for grouping records by a column.
+//!
+//! Records follow RFC 4180: fields are separated by a delimiter, usually `,`, and a field wrapped
+//! in double quotes may contain delimiters, newlines, and `""`, which stands for a single `"`.
+//! Unquoted fields are taken exactly as written, including any spaces.
+//!
+//! [Records] splits input into records, keeping quoted newlines inside their records, and
+//! [split_record] splits a record, once it's decoded, into its fields. A [Column] names a field either by number or by
+//! its name in the header row; [resolve_columns] turns names into numbers once the header is known.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::csv::*;
+//! use std::io::BufRead;
+//!
+//! let input = "id,note\n1,\"two\nlines\"\n2,\"say \"\"hi\"\"\"\n".as_bytes();
+//! let records: Vec<String> = Records::new(input)
+//!     .map(|record| String::from_utf8(record.unwrap()).unwrap())
+//!     .collect();
+//! assert_eq!(records, vec!["id,note", "1,\"two\nlines\"", "2,\"say \"\"hi\"\"\""]);
+//! assert_eq!(split_record(&records[2], ','), vec!["2", "say \"hi\""]);
+//! ```
+
+use crate::command_line::error::GroupByError;
+use crate::command_line::options::GroupingSpecifier;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+/// A column of a CSV record: either its number, counting from 1, or its name in the header row.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    /// Parses a column number, counting from 1, or else a column name.
+    ///
+    /// ```
+    /// use groupby::command_line::csv::Column;
+    ///
+    /// assert_eq!("3".parse(), Ok(Column::Index(3)));
+    /// assert_eq!("status".parse(), Ok(Column::Name("status".to_string())));
+    /// assert!("0".parse::<Column>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(0) => Err("Columns are numbered from 1, but got: 0".to_string()),
+            Ok(n) => Ok(Column::Index(n)),
+            Err(_) => Ok(Column::Name(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Index(n) => write!(f, "column {} (counting from 1)", n),
+            Column::Name(name) => write!(f, "the column named {:?}", name),
+        }
+    }
+}
+
+/// Splits `record` into its fields, removing quotes and undoubling `""` in quoted fields.
+///
+/// Text after a quoted field's closing quote, which RFC 4180 doesn't allow, is kept as part of the
+/// field. An unclosed quote runs to the end of the record.
+///
+/// ```
+/// use groupby::command_line::csv::split_record;
+///
+/// assert_eq!(split_record("a,\"b,c\",", ','), vec!["a", "b,c", ""]);
+/// assert_eq!(split_record("a;b", ';'), vec!["a", "b"]);
+/// assert_eq!(split_record("", ','), vec![""]);
+/// ```
+pub fn split_record(record: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut chars = record.chars().peekable();
+    let mut at_start = true;
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut field));
+            at_start = true;
+            continue;
+        } else if c == '"' && at_start {
+            quoted = true;
+        } else {
+            field.push(c);
+        }
+        at_start = false;
+    }
+    fields.push(field);
+    fields
+}
+
+/// An iterator over the CSV records in a stream. A newline inside a quoted field doesn't end the
+/// record. Line endings between records, either `\n` or `\r\n`, are removed. Records are bytes,
+/// so that input that isn't valid UTF-8 can be decoded as
+/// [InputOptions::invalid_utf8](crate::command_line::options::InputOptions::invalid_utf8) says.
+pub struct Records<R> {
+    input: R,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(input: R) -> Self {
+        Records { input }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = vec![];
+        let mut quotes = 0;
+        loop {
+            let start = record.len();
+            match self.input.read_until(b'\n', &mut record) {
+                Ok(0) if record.is_empty() => return None,
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(error) => return Some(Err(error)),
+            }
+
+            // Quotes come in pairs, so an odd count means a quoted field is still open.
+            quotes += record[start..].iter().filter(|&&b| b == b'"').count();
+            if quotes % 2 == 0 {
+                break;
+            }
+        }
+
+        if record.ends_with(b"\n") {
+            record.pop();
+            if record.ends_with(b"\r") {
+                record.pop();
+            }
+        }
+        Some(Ok(record))
+    }
+}
+
+/// Returns a copy of `spec` in which every [Column::Name] is replaced by the number of the
+/// column with that name in `header`.
+///
+/// # Errors
+///
+/// Returns [GroupByError::InvalidInput] if `header` has no column with one of the names.
+///
+/// ```
+/// use groupby::command_line::csv::*;
+/// use groupby::command_line::options::GroupingSpecifier;
+///
+/// let spec = GroupingSpecifier::CsvColumn(Column::Name("status".to_string()), ',');
+/// assert_eq!(
+///     resolve_columns(&spec, "id,status").unwrap(),
+///     GroupingSpecifier::CsvColumn(Column::Index(2), ','),
+/// );
+/// assert!(resolve_columns(&spec, "id,state").is_err());
+/// ```
+pub fn resolve_columns(
+    spec: &GroupingSpecifier,
+    header: &str,
+) -> Result<GroupingSpecifier, GroupByError> {
+    Ok(match spec {
+        GroupingSpecifier::CsvColumn(Column::Name(name), delimiter) => {
+            let names = split_record(header, *delimiter);
+            match names.iter().position(|column| column == name) {
+                Some(i) => GroupingSpecifier::CsvColumn(Column::Index(i + 1), *delimiter),
+                None => {
+                    return Err(GroupByError::InvalidInput(format!(
+                        "No column named {} in the CSV header: {}",
+                        name,
+                        names.join(", ")
+                    )))
+                }
+            }
+        }
+        GroupingSpecifier::Composite(specs, separator) => GroupingSpecifier::Composite(
+            specs
+                .iter()
+                .map(|spec| resolve_columns(spec, header))
+                .collect::<Result<_, _>>()?,
+            separator.clone(),
+        ),
+        GroupingSpecifier::IgnoreCase(spec) => {
+            GroupingSpecifier::IgnoreCase(Box::new(resolve_columns(spec, header)?))
+        }
+        spec => spec.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_quoted_fields() {
+        assert_eq!(
+            split_record("\"a\"\"b\",\"\",\" c \"x", ','),
+            vec!["a\"b", "", " c x"]
+        );
+        assert_eq!(split_record("\"open,ended", ','), vec!["open,ended"]);
+        assert_eq!(split_record("in\"side,b", ','), vec!["in\"side", "b"]);
+    }
+
+    #[test]
+    fn reads_crlf_records() {
+        let input = "a,b\r\n\"1\r\n2\",3\r\nlast".as_bytes();
+        let records: Vec<Vec<u8>> = Records::new(input).map(Result::unwrap).collect();
+        assert_eq!(records, vec![&b"a,b"[..], b"\"1\r\n2\",3", b"last"]);
+    }
+
+    #[test]
+    fn resolves_nested_columns() {
+        let name = |name: &str| GroupingSpecifier::CsvColumn(Column::Name(name.to_string()), ';');
+        let spec = GroupingSpecifier::Composite(
+            vec![
+                name("b"),
+                GroupingSpecifier::IgnoreCase(Box::new(name("a"))),
+            ],
+            " ".to_string(),
+        );
+        let index = |n| GroupingSpecifier::CsvColumn(Column::Index(n), ';');
+        assert_eq!(
+            resolve_columns(&spec, "a;b").unwrap(),
+            GroupingSpecifier::Composite(
+                vec![index(2), GroupingSpecifier::IgnoreCase(Box::new(index(1)))],
+                " ".to_string(),
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_columns() {
+        let spec = GroupingSpecifier::CsvColumn(Column::Name("c".to_string()), ',');
+        let error = resolve_columns(&spec, "a,b").unwrap_err();
+        assert!(matches!(error, GroupByError::InvalidInput(_)));
+        assert_eq!(
+            error.to_string(),
+            "No column named c in the CSV header: a, b"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Transparent decompression of gzip and zstd input, so compressed logs can be grouped directly.
+//!
+//! [decompressed] looks at the first bytes of an input for the magic number of a gzip or zstd
+//! stream, and if it finds one, wraps the input in a decoder. Other input is returned as is. The
+//! decoders are behind the `gzip` and `zstd` features; without them, compressed input is an error
+//! rather than a stream of garbled tokens.
+//!
+//! Both formats allow several compressed members or frames to be concatenated, e.g. by
+//! `cat a.gz b.gz`, and every one of them is decompressed in turn, as `zcat` would.
+
+use crate::command_line::error::GroupByError;
+use std::io::BufRead;
+
+#[cfg(feature = "gzip")]
+use flate2::bufread::MultiGzDecoder;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use std::io::BufReader;
+#[cfg(feature = "zstd")]
+use std::io::{self, Read};
+
+/// A compression format that [decompressed] recognizes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Gzip,
+    Zstd,
+}
+
+impl Format {
+    /// Returns the format whose magic number `bytes` starts with, if any.
+    ///
+    /// ```
+    /// use groupby::command_line::decompress::Format;
+    ///
+    /// assert_eq!(Format::detect(b"\x1f\x8b\x08\x00"), Some(Format::Gzip));
+    /// assert_eq!(Format::detect(b"\x28\xb5\x2f\xfd"), Some(Format::Zstd));
+    /// assert_eq!(Format::detect(b"plain text"), None);
+    /// ```
+    pub fn detect(bytes: &[u8]) -> Option<Format> {
+        if bytes.starts_with(b"\x1f\x8b") {
+            Some(Format::Gzip)
+        } else if bytes.starts_with(b"\x28\xb5\x2f\xfd") {
+            Some(Format::Zstd)
+        } else {
+            None
+        }
+    }
+
+    // The format's name, which is also the name of the feature that decompresses it.
+    fn name(self) -> &'static str {
+        match self {
+            Format::Gzip => "gzip",
+            Format::Zstd => "zstd",
+        }
+    }
+}
+
+/// Returns a reader over the decompressed contents of `input` if it's compressed, or `input`
+/// itself otherwise. `name` identifies the input in errors.
+///
+/// # Errors
+///
+/// Returns [GroupByError::Io] if reading the start of `input` fails, or
+/// [GroupByError::InvalidInput] if `input` is compressed in a format whose feature isn't enabled.
+/// Corrupt compressed data is reported as [GroupByError::Io] when it's read.
+pub fn decompressed(
+    name: &str,
+    mut input: Box<dyn BufRead>,
+) -> Result<Box<dyn BufRead>, GroupByError> {
+    let format = match Format::detect(input.fill_buf()?) {
+        Some(format) => format,
+        None => return Ok(input),
+    };
+    match format {
+        #[cfg(feature = "gzip")]
+        Format::Gzip => Ok(Box::new(BufReader::new(MultiGzDecoder::new(input)))),
+
+        #[cfg(feature = "zstd")]
+        Format::Zstd => Ok(Box::new(BufReader::new(ZstdFrames::new(input)?))),
+
+        #[allow(unreachable_patterns)]
+        format => Err(GroupByError::InvalidInput(format!(
+            "{} is {}-compressed, but groupby was built without the {} feature",
+            name,
+            format.name(),
+            format.name()
+        ))),
+    }
+}
+
+// Decodes a single zstd frame.
+#[cfg(feature = "zstd")]
+type ZstdFrame =
+    ruzstd::decoding::StreamingDecoder<Box<dyn BufRead>, ruzstd::decoding::FrameDecoder>;
+
+// Decodes every frame of a zstd stream, one after another. ruzstd's StreamingDecoder stops after
+// the first frame.
+#[cfg(feature = "zstd")]
+struct ZstdFrames {
+    // None once the input is exhausted.
+    decoder: Option<ZstdFrame>,
+}
+
+#[cfg(feature = "zstd")]
+impl ZstdFrames {
+    fn new(input: Box<dyn BufRead>) -> io::Result<Self> {
+        Ok(ZstdFrames {
+            decoder: Some(Self::frame(input)?),
+        })
+    }
+
+    // Starts decoding the frame at the start of input.
+    fn frame(input: Box<dyn BufRead>) -> io::Result<ZstdFrame> {
+        ZstdFrame::new(input).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Read for ZstdFrames {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(decoder) = &mut self.decoder {
+            let read = decoder.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+
+            // This frame is done. Start the next one, if there is one.
+            let mut input = self.decoder.take().unwrap().into_inner();
+            if !input.fill_buf()?.is_empty() {
+                self.decoder = Some(Self::frame(input)?);
+            }
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn boxed(bytes: Vec<u8>) -> Box<dyn BufRead> {
+        Box::new(std::io::Cursor::new(bytes))
+    }
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn read(input: Box<dyn BufRead>) -> String {
+        let mut contents = String::new();
+        decompressed("test", input)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    }
+
+    #[test]
+    fn passes_plain_input_through() {
+        let mut input = decompressed("test", boxed(b"a\nb\n".to_vec())).unwrap();
+        let mut contents = String::new();
+        input.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "a\nb\n");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn decompresses_every_gzip_member() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut bytes = vec![];
+        for part in ["a\n", "b\n"] {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            encoder.write_all(part.as_bytes()).unwrap();
+            bytes.extend(encoder.finish().unwrap());
+        }
+        assert_eq!(read(boxed(bytes)), "a\nb\n");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn decompresses_every_zstd_frame() {
+        use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+        let mut bytes = compress_to_vec(&b"a\n"[..], CompressionLevel::Fastest);
+        bytes.extend(compress_to_vec(&b"b\n"[..], CompressionLevel::Fastest));
+        assert_eq!(read(boxed(bytes)), "a\nb\n");
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn rejects_gzip_without_the_feature() {
+        match decompressed("logs.gz", boxed(b"\x1f\x8b\x08\x00".to_vec())) {
+            Err(GroupByError::InvalidInput(message)) => assert_eq!(
+                message,
+                "logs.gz is gzip-compressed, but groupby was built without the gzip feature"
+            ),
+            _ => panic!("expected an invalid input error"),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Provides [GroupByError], the error type for the top-level functions in this module.
+
+use std::env::VarError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::process::ExitStatus;
+
+/// Everything that can go wrong while parsing arguments, building groups, running commands, or
+/// writing results.
+///
+/// The [Display](fmt::Display) implementation describes the error for the user, and
+/// [exit_code](GroupByError::exit_code) suggests an exit code for a command-line application.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::GroupByError;
+/// use std::io;
+///
+/// let error = GroupByError::InvalidArgument("Chunk sizes must be at least 1, but got: 0".into());
+/// assert_eq!(error.to_string(), "Chunk sizes must be at least 1, but got: 0");
+/// assert_eq!(error.exit_code(), 2);
+///
+/// let error: GroupByError = io::Error::new(io::ErrorKind::Other, "disk full").into();
+/// assert_eq!(error.to_string(), "I/O error: disk full");
+/// assert_eq!(error.exit_code(), 74);
+///
+/// let error = GroupByError::CommandsFailed(vec!["a".into(), "b".into()]);
+/// assert_eq!(error.to_string(), "2 commands failed, for groups: a, b");
+/// assert_eq!(error.exit_code(), 1);
+/// ```
+#[derive(Debug)]
+pub enum GroupByError {
+    /// The command line couldn't be parsed, e.g. it has an unknown flag or is missing a required
+    /// value. [clap] also reports `--help` and `--version` this way; print the error with
+    /// [clap::Error::print] to show the usage message, help, or version as clap formats it.
+    ///
+    /// [clap]: https://crates.io/crates/clap
+    Usage(clap::Error),
+
+    /// A command-line argument is invalid, e.g. a malformed regular expression or a zero where a
+    /// positive number is needed. The message explains the problem.
+    InvalidArgument(String),
+
+    /// The input can't be grouped as requested, e.g. it isn't valid UTF-8 under
+    /// [InvalidUtf8::Strict](crate::command_line::options::InvalidUtf8::Strict), or a CSV column
+    /// name isn't in the header row. The message explains the problem.
+    InvalidInput(String),
+
+    /// The shell to run commands in couldn't be determined, because the `SHELL` environment
+    /// variable isn't valid Unicode.
+    Shell(VarError),
+
+    /// The checkpoint file at the given path couldn't be opened or read.
+    Checkpoint(String, io::Error),
+
+    /// The input file at the given path couldn't be opened.
+    Input(String, io::Error),
+
+    /// The file at the given path, which a copy of the input was to be written to, couldn't be
+    /// created.
+    Tee(String, io::Error),
+
+    /// A command couldn't be started or waited for.
+    Command(io::Error),
+
+    /// The command for the group with the given key exited unsuccessfully, and
+    /// [FailurePolicy::FailFast](crate::command_line::options::FailurePolicy::FailFast) stopped the
+    /// run.
+    CommandFailed(String, ExitStatus),
+
+    /// The commands for the groups with the given keys exited unsuccessfully, but
+    /// [FailurePolicy::KeepGoing](crate::command_line::options::FailurePolicy::KeepGoing) let every
+    /// command run.
+    CommandsFailed(Vec<String>),
+
+    /// Reading input or writing output failed.
+    Io(io::Error),
+
+    /// An [interrupt](crate::command_line::interrupt) stopped the commands after the given number
+    /// of groups, out of the given total, had finished, so the output is partial.
+    Interrupted(usize, usize),
+}
+
+impl GroupByError {
+    /// Returns true if this is an I/O error from writing to a pipe whose reader has gone away,
+    /// e.g. when output is piped to `head`. A command-line application should treat that as
+    /// success and exit quietly.
+    ///
+    /// ```
+    /// use groupby::command_line::GroupByError;
+    /// use std::io;
+    ///
+    /// let error: GroupByError = io::Error::from(io::ErrorKind::BrokenPipe).into();
+    /// assert!(error.is_broken_pipe());
+    ///
+    /// let error: GroupByError = io::Error::from(io::ErrorKind::NotFound).into();
+    /// assert!(!error.is_broken_pipe());
+    /// assert!(!GroupByError::Command(io::ErrorKind::BrokenPipe.into()).is_broken_pipe());
+    /// ```
+    pub fn is_broken_pipe(&self) -> bool {
+        matches!(self, GroupByError::Io(e) if e.kind() == io::ErrorKind::BrokenPipe)
+    }
+
+    /// Returns the exit code a command-line application should use for this error.
+    ///
+    /// Usage errors and invalid arguments exit with 2, like argument errors that [clap] reports,
+    /// while `--help` and `--version` exit with 0. Commands that ran but failed exit with 1. Other errors use the codes from BSD's `sysexits.h`: 65 for
+    /// invalid input, 66 for an input file that can't be opened, 78 for an unusable SHELL, 73 for a
+    /// checkpoint or tee file, 71 for a command that couldn't run, and 74 for other I/O errors. An
+    /// interrupt exits with 130, as a shell would report for a process killed by `SIGINT`.
+    ///
+    /// [clap]: https://crates.io/crates/clap
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GroupByError::Usage(e) if e.use_stderr() => 2,
+            GroupByError::Usage(_) => 0,
+            GroupByError::InvalidArgument(_) => 2,
+            GroupByError::InvalidInput(_) => 65,
+            GroupByError::Shell(_) => 78,
+            GroupByError::Input(_, _) => 66,
+            GroupByError::Checkpoint(_, _) | GroupByError::Tee(_, _) => 73,
+            GroupByError::Command(_) => 71,
+            GroupByError::CommandFailed(_, _) | GroupByError::CommandsFailed(_) => 1,
+            GroupByError::Io(_) => 74,
+            GroupByError::Interrupted(_, _) => crate::command_line::interrupt::EXIT_CODE,
+        }
+    }
+}
+
+impl fmt::Display for GroupByError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GroupByError::Usage(e) => write!(f, "{}", e.to_string().trim_end()),
+            GroupByError::InvalidArgument(message) | GroupByError::InvalidInput(message) => {
+                write!(f, "{}", message)
+            }
+            GroupByError::Shell(e) => {
+                write!(f, "Couldn't retrieve environment variable SHELL: {}", e)
+            }
+            GroupByError::Input(path, e) => write!(f, "Couldn't open input file {}: {}", path, e),
+            GroupByError::Checkpoint(path, e) => {
+                write!(f, "Couldn't open checkpoint file {}: {}", path, e)
+            }
+            GroupByError::Tee(path, e) => write!(f, "Couldn't create tee file {}: {}", path, e),
+            GroupByError::Command(e) => write!(f, "Couldn't run command: {}", e),
+            GroupByError::CommandFailed(key, status) => {
+                write!(f, "Command for group {} failed with {}", key, status)
+            }
+            GroupByError::CommandsFailed(keys) => write!(
+                f,
+                "{} failed, for groups: {}",
+                if keys.len() == 1 {
+                    "1 command".to_string()
+                } else {
+                    format!("{} commands", keys.len())
+                },
+                keys.join(", ")
+            ),
+            GroupByError::Io(e) => write!(f, "I/O error: {}", e),
+            GroupByError::Interrupted(finished, total) => write!(
+                f,
+                "Interrupted after {} of {} groups finished, so the output is partial",
+                finished, total
+            ),
+        }
+    }
+}
+
+impl Error for GroupByError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GroupByError::InvalidArgument(_)
+            | GroupByError::InvalidInput(_)
+            | GroupByError::CommandFailed(_, _)
+            | GroupByError::CommandsFailed(_)
+            | GroupByError::Interrupted(_, _) => None,
+            GroupByError::Usage(e) => Some(e),
+            GroupByError::Shell(e) => Some(e),
+            GroupByError::Input(_, e)
+            | GroupByError::Checkpoint(_, e)
+            | GroupByError::Tee(_, e)
+            | GroupByError::Command(e)
+            | GroupByError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for GroupByError {
+    fn from(e: io::Error) -> Self {
+        GroupByError::Io(e)
+    }
+}
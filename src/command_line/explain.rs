@@ -0,0 +1,1351 @@
+//! Describes a fully resolved [GroupByOptions] as a human-readable execution plan.
+//!
+//! With many options that interact (profiles, label rules, escape sequences, output options that
+//! apply to commands rather than final output, etc.), it isn't always obvious what a complicated
+//! invocation will actually do. [explain()] spells it out step by step, so users can check with
+//! `--explain` before running it for real.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::explain::explain;
+//! use groupby::command_line::options::*;
+//!
+//! let options = GroupByOptions {
+//!     input: InputOptions {
+//!         separator: Separator::Null,
+//!         ..Default::default()
+//!     },
+//!     grouping: GroupingSpecifier::FirstChars(3),
+//!     labels: Labels::default(),
+//!     unique: false,
+//!     min_size: None,
+//!     max_size: None,
+//!     count_values: false,
+//!     output: OutputOptions::default(),
+//!     seed: None,
+//!     explain: true,
+//! };
+//!
+//! let plan = explain(&options);
+//! assert!(plan.contains("splitting it into tokens at each null character"));
+//! assert!(plan.contains("by its first 3 characters"));
+//! ```
+
+use crate::command_line::labels::{LabelPattern, LabelRule};
+use crate::command_line::options::*;
+use crate::command_line::run_command::{current_shell, substitute_key};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Returns a human-readable, multi-line description of what `groupby` will do with `options`.
+pub fn explain(options: &GroupByOptions) -> String {
+    let mut plan = String::new();
+    let streaming = options.output.presorted_streaming;
+
+    section(&mut plan, "Input");
+    if let Some(walk) = &options.input.walk {
+        line(
+            &mut plan,
+            format!(
+                "Walk {:?} recursively{}, in sorted order, and use the path of each file{} as a \
+                token, instead of reading input.",
+                walk.root,
+                if walk.follow_symlinks {
+                    ", following symbolic links to directories"
+                } else {
+                    ""
+                },
+                if walk.hidden {
+                    ", including hidden ones,"
+                } else {
+                    " that isn't hidden"
+                },
+            ),
+        );
+    } else {
+        line(
+            &mut plan,
+            format!(
+                "Read {}, splitting {} into tokens at {}.",
+                describe_input_files(&options.input),
+                if options.input.files.len() > 1 || options.input.files0_from.is_some() {
+                    "each"
+                } else {
+                    "it"
+                },
+                if options.input.csv {
+                    "the end of each CSV record, after reading the header row".to_string()
+                } else {
+                    describe_input_separator(&options.input.separator)
+                }
+            ),
+        );
+    }
+
+    match (options.input.trim, options.input.skip_empty) {
+        (false, false) => (),
+        (true, false) => line(
+            &mut plan,
+            "Strip leading and trailing whitespace from each token.".to_string(),
+        ),
+        (false, true) => line(&mut plan, "Skip empty tokens.".to_string()),
+        (true, true) => line(
+            &mut plan,
+            "Strip leading and trailing whitespace from each token, and skip tokens that are \
+            left empty."
+                .to_string(),
+        ),
+    }
+
+    if let Some(Fraction(p)) = options.input.sample {
+        line(
+            &mut plan,
+            format!(
+                "Keep each token at random with probability {}, and skip the rest.",
+                p
+            ),
+        );
+    }
+
+    if let Some(limit) = options.input.limit {
+        line(
+            &mut plan,
+            format!(
+                "Stop reading input once {} tokens have been grouped.",
+                limit
+            ),
+        );
+    }
+
+    if options.input.with_filename {
+        line(
+            &mut plan,
+            "Prefix each value with the name of the file it came from and a colon.".to_string(),
+        );
+    }
+
+    if let Some(tee) = &options.input.tee {
+        line(
+            &mut plan,
+            format!(
+                "Copy the input, unchanged, to {} as it's read, and print the results below to \
+                standard error instead of standard output.",
+                if tee.as_os_str() == "-" {
+                    "standard output".to_string()
+                } else {
+                    format!("{:?}", tee)
+                }
+            ),
+        );
+    }
+
+    match options.input.invalid_utf8 {
+        InvalidUtf8::Lossy => (),
+        InvalidUtf8::Skip => line(
+            &mut plan,
+            "Skip tokens that aren't valid UTF-8, with a warning for each.".to_string(),
+        ),
+        InvalidUtf8::Strict => line(
+            &mut plan,
+            "Stop with an error at the first token that isn't valid UTF-8.".to_string(),
+        ),
+    }
+
+    section(&mut plan, "Grouping");
+    line(
+        &mut plan,
+        format!(
+            "Key each token by {}.",
+            describe_grouping(&options.grouping)
+        ),
+    );
+    if !options.labels.is_empty() {
+        line(
+            &mut plan,
+            "Then relabel keys, using the first rule that matches:".to_string(),
+        );
+        for rule in options.labels.rules() {
+            line(&mut plan, format!("  - {}", describe_label_rule(rule)));
+        }
+    }
+    if options.unique {
+        line(
+            &mut plan,
+            "Then remove duplicate values from each group, keeping the first of each.".to_string(),
+        );
+    }
+    match (options.min_size, options.max_size) {
+        (Some(min), Some(max)) => line(
+            &mut plan,
+            format!(
+                "Then drop groups with fewer than {} or more than {} values.",
+                min, max
+            ),
+        ),
+        (Some(min), None) => line(
+            &mut plan,
+            format!("Then drop groups with fewer than {} values.", min),
+        ),
+        (None, Some(max)) => line(
+            &mut plan,
+            format!("Then drop groups with more than {} values.", max),
+        ),
+        (None, None) => (),
+    }
+    if options.count_values {
+        line(
+            &mut plan,
+            "Then collapse identical values in each group into one, prefixed with its count."
+                .to_string(),
+        );
+    }
+
+    section(&mut plan, "Collection");
+    if options.output.stream {
+        line(
+            &mut plan,
+            "Keep nothing in memory: pass each token on as soon as its key is known.".to_string(),
+        );
+    } else if streaming {
+        line(
+            &mut plan,
+            "Hold only the current group in memory, assuming the input is sorted by key. Each \
+            group is complete as soon as a token with a different key arrives."
+                .to_string(),
+        );
+    } else if options.output.low_memory {
+        line(
+            &mut plan,
+            "Collect every group before producing any output, keeping at most 64 MiB of values \
+            in memory and writing the rest to a temporary file."
+                .to_string(),
+        );
+    } else if options.output.intern {
+        line(
+            &mut plan,
+            "Collect every group in memory before producing any output, storing each distinct \
+            value only once."
+                .to_string(),
+        );
+    } else if options.output.count_only {
+        line(
+            &mut plan,
+            "Count the tokens in each group, discarding the tokens themselves, before producing \
+            any output."
+                .to_string(),
+        );
+    } else {
+        line(
+            &mut plan,
+            "Collect every group in memory in a BTreeMap before producing any output.".to_string(),
+        );
+    }
+
+    if let Some(command) = &options.output.run_command {
+        section(&mut plan, "Commands");
+        explain_commands(&mut plan, command, &options.output);
+    }
+
+    section(&mut plan, "Output");
+    explain_output(&mut plan, &options.output);
+
+    if let Some(seed) = options.seed {
+        section(&mut plan, "Randomness");
+        line(
+            &mut plan,
+            format!("Seed every randomized feature with {}.", seed),
+        );
+    }
+
+    plan
+}
+
+// Describes how commands run over each group.
+fn explain_commands(plan: &mut String, command: &str, options: &OutputOptions) {
+    let shell = match &options.shell {
+        Some(shell) => shell.clone(),
+        None => current_shell().unwrap_or_else(|_| "$SHELL".to_string()),
+    };
+    if options.dry_run {
+        line(
+            plan,
+            "This is a dry run: describe the commands below without running any.".to_string(),
+        );
+    }
+    line(
+        plan,
+        format!(
+            "Run {} -c {:?} once for each group, {}.",
+            shell,
+            command,
+            if options.presorted_streaming {
+                "one at a time, as soon as the group is complete".to_string()
+            } else if options.low_memory || options.intern {
+                format!("one at a time, in {}", describe_key_order(options.sort))
+            } else if let (true, Some(jobs)) = (options.parallel, options.jobs) {
+                format!(
+                    "in parallel, at most {} at a time, in arbitrary order",
+                    jobs
+                )
+            } else if options.parallel {
+                "in parallel, one per CPU core, in arbitrary order".to_string()
+            } else {
+                "one at a time, in output order".to_string()
+            }
+        ),
+    );
+    line(
+        plan,
+        format!(
+            "Pass {} to each command's standard input, each followed by {}.",
+            if options.only_group_names {
+                "the group's key"
+            } else {
+                "the group's values"
+            },
+            describe_output_separator(&options.separator)
+        ),
+    );
+    let limits: Vec<String> = options
+        .max_args
+        .map(|n| format!("{} values", n))
+        .into_iter()
+        .chain(options.max_stdin_bytes.map(|n| format!("{} bytes", n)))
+        .collect();
+    if !limits.is_empty() && !options.only_group_names {
+        line(
+            plan,
+            format!(
+                "Pass at most {} to each run of a command, running it again for the rest of the \
+                group and printing the outputs together.",
+                limits.join(" and ")
+            ),
+        );
+    }
+    let limits: Vec<String> = options
+        .nice
+        .map(|n| format!("at niceness {}", n))
+        .into_iter()
+        .chain(
+            options
+                .max_memory
+                .map(|n| format!("with at most {} bytes of virtual memory", n)),
+        )
+        .chain(
+            options
+                .max_open_files
+                .map(|n| format!("with at most {} open files", n)),
+        )
+        .collect();
+    if !limits.is_empty() {
+        line(plan, format!("Run each command {}.", limits.join(", ")));
+    }
+    // The key only matters if the command has a placeholder; escaped ones don't count.
+    if substitute_key(command, "a") != substitute_key(command, "b") {
+        line(
+            plan,
+            "Replace each {} or {key} in the command with the group's key, quoted for the shell."
+                .to_string(),
+        );
+    }
+    line(
+        plan,
+        match options.failure_policy {
+            FailurePolicy::FailFast => {
+                "If a command fails, start no more commands, print nothing, and exit with status 1."
+            }
+            FailurePolicy::KeepGoing => {
+                "If any commands fail, run the rest anyway, then list them and exit with status 1."
+            }
+        }
+        .to_string(),
+    );
+    if options.capture_stderr {
+        line(
+            plan,
+            "Capture each command's standard error and print it after its standard output."
+                .to_string(),
+        );
+    }
+
+    if let Some(checkpoint) = &options.checkpoint {
+        line(
+            plan,
+            format!(
+                "Record each group whose command succeeds in the checkpoint file {:?}, {}.",
+                checkpoint,
+                if options.resume {
+                    "skipping groups it already lists"
+                } else {
+                    "starting it afresh"
+                }
+            ),
+        );
+    }
+}
+
+// Describes the final output.
+fn explain_output(plan: &mut String, options: &OutputOptions) {
+    let streaming = options.presorted_streaming;
+    let order = if streaming {
+        "in input order".to_string()
+    } else if options.low_memory || options.intern {
+        format!("in {}", describe_key_order(options.sort))
+    } else {
+        let key_order = if options.reverse {
+            format!("reverse {}", describe_key_order(options.sort))
+        } else {
+            describe_key_order(options.sort)
+        };
+        match options.group_order {
+            GroupOrder::Key => format!("in {}", key_order),
+            GroupOrder::Size => format!(
+                "from the smallest group to the largest, then in {}",
+                key_order
+            ),
+            GroupOrder::SizeDescending => format!(
+                "from the largest group to the smallest, then in {}",
+                key_order
+            ),
+        }
+    };
+
+    if options.stream {
+        line(
+            plan,
+            format!(
+                "Print each token as soon as it's read, as its key, a tab, and the token, \
+                followed by {}.",
+                describe_output_separator(&options.separator)
+            ),
+        );
+    } else if options.run_command.is_some() && options.dry_run {
+        line(
+            plan,
+            format!(
+                "For each group, {}, print its command line, working directory, and environment, \
+                and a preview of its standard input.",
+                order
+            ),
+        );
+    } else if options.json {
+        line(
+            plan,
+            format!(
+                "Print a JSON object mapping each group's key to {}, {}.",
+                if options.run_command.is_some() && options.with_values {
+                    "its values and its command's output"
+                } else if options.run_command.is_some() {
+                    "its command's output"
+                } else if options.count_only {
+                    "its count"
+                } else {
+                    "an array of its values"
+                },
+                order
+            ),
+        );
+    } else if let Some(template) = &options.format {
+        line(
+            plan,
+            format!(
+                "Print each group {} by filling in the template {:?}{}, followed by {}.",
+                order,
+                template.as_str(),
+                if template.uses_output() {
+                    " with its command's output"
+                } else {
+                    ""
+                },
+                describe_output_separator(&options.separator)
+            ),
+        );
+    } else if options.run_command.is_some() && options.live {
+        line(
+            plan,
+            "Print each line of command output as soon as it's written, prefixed with the group's \
+            key and \" | \"."
+                .to_string(),
+        );
+    } else if options.run_command.is_some() {
+        line(
+            plan,
+            format!(
+                "Print each group's {} {}{}, {}.",
+                if options.with_values {
+                    "contents and command output"
+                } else {
+                    "command output"
+                },
+                order,
+                if options.incremental {
+                    " as soon as it and every group before it are done"
+                } else {
+                    ""
+                },
+                if options.headers {
+                    format!("under {}", describe_header(options))
+                } else {
+                    "with no header".to_string()
+                }
+            ),
+        );
+    } else if options.count_only {
+        line(
+            plan,
+            format!(
+                "Print each group's key and count {}, each followed by {}.",
+                order,
+                describe_output_separator(&options.separator)
+            ),
+        );
+    } else if options.only_group_names {
+        line(
+            plan,
+            format!(
+                "Print each group's key{} {}, followed by {}.",
+                if options.prints_bare_group_names() {
+                    " exactly as it is,"
+                } else {
+                    ""
+                },
+                order,
+                describe_output_separator(&options.separator)
+            ),
+        );
+    } else {
+        line(
+            plan,
+            format!(
+                "Print each group {}: {}its values, each followed by {}.",
+                order,
+                if options.headers {
+                    format!("{}, then ", describe_header(options))
+                } else {
+                    String::new()
+                },
+                describe_output_separator(&options.separator)
+            ),
+        );
+    }
+
+    let prints_groups = !options.json
+        && !options.prints_bare_group_names()
+        && !(options.run_command.is_some() && (options.dry_run || options.live));
+    if options.quote == Quote::Shell && prints_groups {
+        line(
+            plan,
+            "Quote each printed key and value so a POSIX shell reads it as one word.".to_string(),
+        );
+    }
+
+    if options.stats && !streaming {
+        line(
+            plan,
+            if options.run_command.is_some() {
+                "Include each group's item and byte counts and its command's duration and exit status, then \
+                statistics about all groups and the number of failed commands."
+            } else if options.prints_bare_group_names() {
+                "Then print statistics about all groups."
+            } else {
+                "Include each group's item and byte counts, then statistics about all groups."
+            }
+            .to_string(),
+        );
+        if let Some(percentiles) = &options.stats_detail {
+            let percentiles: Vec<String> = percentiles.iter().map(|p| format!("p{}", p)).collect();
+            line(
+                plan,
+                format!(
+                    "The statistics also include the standard deviation of group sizes, their \
+                    percentiles ({}), and a histogram of group sizes.",
+                    percentiles.join(", ")
+                ),
+            );
+        }
+    }
+
+    if options.timings {
+        line(
+            plan,
+            format!(
+                "Finally, report how long reading input, grouping, {}writing output took to \
+                standard error{}.",
+                if options.run_command.is_some() {
+                    "running commands, and "
+                } else {
+                    "and "
+                },
+                if options.run_command.is_some() {
+                    ", then how long each group's command took, slowest first"
+                } else {
+                    ""
+                }
+            ),
+        );
+    }
+}
+
+// Describes where input is read from.
+fn describe_input_files(input: &InputOptions) -> String {
+    let describe = |file: &PathBuf| match file.to_str() {
+        Some("-") => "standard input".to_string(),
+        _ => format!("{:?}", file),
+    };
+    let mut names: Vec<String> = input.files.iter().map(describe).collect();
+    if let Some(list) = &input.files0_from {
+        names.push(format!(
+            "the files named in {}, each followed by a null character",
+            describe(list)
+        ));
+    }
+    match names.split_last() {
+        None => "standard input".to_string(),
+        Some((name, [])) => name.clone(),
+        Some((last, rest)) => format!("{} and {}, in that order", rest.join(", "), last),
+    }
+}
+
+// Describes the header printed before each group, when there is one.
+fn describe_header(options: &OutputOptions) -> String {
+    match &options.header_format {
+        Some(template) => format!(
+            "a header filled in from the template {:?}",
+            template.as_str()
+        ),
+        None => "a header with the group's key".to_string(),
+    }
+}
+
+// Appends a section heading to plan, separated from any previous section by a blank line.
+fn section(plan: &mut String, heading: &str) {
+    if !plan.is_empty() {
+        plan.push('\n');
+    }
+    writeln!(plan, "{}:", heading).unwrap();
+}
+
+// Appends an indented line to plan.
+fn line(plan: &mut String, text: String) {
+    writeln!(plan, "  {}", text).unwrap();
+}
+
+fn describe_input_separator(separator: &Separator) -> String {
+    match separator {
+        Separator::Line => "each newline".to_string(),
+        Separator::Space => "each run of whitespace".to_string(),
+        Separator::Null => "each null character".to_string(),
+        Separator::Paragraph => {
+            "each run of blank lines, joining the lines of each paragraph with newlines".to_string()
+        }
+        Separator::Custom(s) => format!("each occurrence of {:?}", s),
+    }
+}
+
+fn describe_output_separator(separator: &Separator) -> String {
+    match separator {
+        Separator::Line => "a newline".to_string(),
+        Separator::Space => "a space".to_string(),
+        Separator::Null => "a null character".to_string(),
+        Separator::Paragraph => "a blank line".to_string(),
+        Separator::Custom(s) => format!("{:?}", s),
+    }
+}
+
+fn describe_grouping(grouping: &GroupingSpecifier) -> String {
+    match grouping {
+        GroupingSpecifier::FirstChars(n) => format!("its first {} characters", n),
+        GroupingSpecifier::LastChars(n) => format!("its last {} characters", n),
+        GroupingSpecifier::FirstBytes(n) => format!("its first {} bytes", n),
+        GroupingSpecifier::LastBytes(n) => format!("its last {} bytes", n),
+        GroupingSpecifier::Regex(regex, capture_group, unmatched) => format!(
+            "the first match of the regular expression /{}/, using {} ({})",
+            regex.as_str(),
+            describe_capture_group(capture_group),
+            describe_unmatched(unmatched, "it doesn't match"),
+        ),
+        GroupingSpecifier::RegexRules(rules, capture_group, unmatched) => format!(
+            "the first matching regular expression out of {}, using the rule's name if it has \
+            one, otherwise {} ({})",
+            rules
+                .iter()
+                .map(|rule| match &rule.name {
+                    Some(name) => format!("{} (/{}/)", name, rule.regex.as_str()),
+                    None => format!("/{}/", rule.regex.as_str()),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            describe_capture_group(capture_group),
+            describe_unmatched(unmatched, "none of them match"),
+        ),
+        GroupingSpecifier::FileExtension => "its file extension".to_string(),
+        GroupingSpecifier::Counter => {
+            "a counter that counts up from 0, so each token gets its own group".to_string()
+        }
+        GroupingSpecifier::KeyValue(separator) => format!(
+            "the text before the first {:?}, keeping only the text after it as the value \
+            (tokens without one go in the group \"\" as-is)",
+            separator
+        ),
+        GroupingSpecifier::Field(n, delimiter) => format!(
+            "field {} (counting from 1), where fields are separated by {:?}",
+            n, delimiter
+        ),
+        GroupingSpecifier::NthWord(n, word_regex) => format!(
+            "word {} (counting from 1), where a word is any match of the regular expression /{}/",
+            n,
+            word_regex.as_str()
+        ),
+        GroupingSpecifier::Timestamp(format, bucket) => format!(
+            "the {} of the first timestamp written like {:?}",
+            format!("{:?}", bucket).to_lowercase(),
+            format.as_str()
+        ),
+        GroupingSpecifier::NumericBucket(size) => format!(
+            "the range of {} numbers, starting at a multiple of {}, that contains its first number",
+            size, size
+        ),
+        GroupingSpecifier::SizeBucket(buckets) => format!(
+            "the size of the file it names, as one of the ranges {}",
+            buckets.names().join(", ")
+        ),
+        GroupingSpecifier::Mtime(bucket) => format!(
+            "the {}, in UTC, in which the file it names was last modified",
+            format!("{:?}", bucket).to_lowercase()
+        ),
+        GroupingSpecifier::MimeType => {
+            "the MIME type of the file it names, detected from its first bytes".to_string()
+        }
+        GroupingSpecifier::PathComponent(depth) => format!(
+            "path component {} (counting from 1, skipping any leading / or ./)",
+            depth
+        ),
+        GroupingSpecifier::Basename => "its final path component, like basename".to_string(),
+        GroupingSpecifier::Dirname => {
+            "everything but its final path component, like dirname".to_string()
+        }
+        GroupingSpecifier::Length => "its length in characters".to_string(),
+        GroupingSpecifier::Shard(n) => format!(
+            "a stable hash of the whole token, reduced to one of {} shards numbered from 0",
+            n
+        ),
+        GroupingSpecifier::Chunk(size) => format!(
+            "its position in the input, putting each run of {} consecutive tokens in its own \
+            group, numbered from 0",
+            size
+        ),
+        GroupingSpecifier::Glob(rules) => format!(
+            "the first matching glob rule out of {}, or \"other\" if none match",
+            rules
+                .iter()
+                .map(|rule| format!("{} ({})", rule.name, rule.glob.as_str()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        GroupingSpecifier::UrlHost(false) => "the host of the URL it holds".to_string(),
+        GroupingSpecifier::UrlHost(true) => {
+            "the registrable domain of the host of the URL it holds, e.g. example.com for \
+            www.example.com"
+                .to_string()
+        }
+        GroupingSpecifier::JsonKey(path) => format!(
+            "the value at {} when it's read as a JSON document",
+            path.as_str()
+        ),
+        GroupingSpecifier::CsvColumn(column, delimiter) => format!(
+            "{} of the CSV record, where fields are separated by {:?}",
+            column, delimiter
+        ),
+        GroupingSpecifier::Logfmt(key) => format!(
+            "the value for the key {:?} among its logfmt-style key=value pairs",
+            key
+        ),
+        GroupingSpecifier::KeyExpr(expr) => {
+            format!("the result of the key expression {}", expr.as_str())
+        }
+        GroupingSpecifier::KeyCommand(command, batch, shell) => {
+            format!(
+            "the line that the command {} prints for it{}, given tokens one per line, {} at a time",
+            command,
+            shell.as_ref().map(|shell| format!(" in {}", shell)).unwrap_or_default(),
+            batch
+        )
+        }
+        GroupingSpecifier::Composite(specs, separator) => format!(
+            "the keys of several groupers, joined with {:?}: {}",
+            separator,
+            specs
+                .iter()
+                .map(describe_grouping)
+                .collect::<Vec<_>>()
+                .join("; then ")
+        ),
+        GroupingSpecifier::IgnoreCase(spec) => {
+            format!(
+                "{}, ignoring case (lowercasing the key)",
+                describe_grouping(spec)
+            )
+        }
+        GroupingSpecifier::Named(name) => {
+            format!("the key from the grouper registered as {:?}", name)
+        }
+    }
+}
+
+fn describe_capture_group(capture_group: &CaptureGroup) -> String {
+    match capture_group {
+        CaptureGroup::Number(0) => "the whole match".to_string(),
+        CaptureGroup::Number(n) => format!("capture group {}", n),
+        CaptureGroup::Name(name) => format!("the capture group named {:?}", name),
+        CaptureGroup::Default => {
+            "capture group 1 if there is one, otherwise the whole match".to_string()
+        }
+        CaptureGroup::Joined(groups, joiner) => format!(
+            "{}, joined with {:?}",
+            groups
+                .iter()
+                .map(describe_capture_group)
+                .collect::<Vec<_>>()
+                .join(" and "),
+            joiner
+        ),
+    }
+}
+
+// Describes what happens to tokens that a regular expression grouper doesn't match, where
+// doesnt_match is e.g. "it doesn't match".
+fn describe_unmatched(unmatched: &Unmatched, doesnt_match: &str) -> String {
+    match unmatched {
+        Unmatched::Empty => format!("tokens {} go in the group \"\"", doesnt_match),
+        Unmatched::Skip => format!("tokens {} are skipped", doesnt_match),
+        Unmatched::Error => format!("a token {} is an error", doesnt_match),
+        Unmatched::Group(name) => format!("tokens {} go in the group {:?}", doesnt_match, name),
+    }
+}
+
+fn describe_label_rule(rule: &LabelRule) -> String {
+    let keys = match &rule.pattern {
+        LabelPattern::Range(low, high) => match (low.is_empty(), high.is_empty()) {
+            (true, true) => "every key".to_string(),
+            (true, false) => format!("keys up to {:?}", high),
+            (false, true) => format!("keys from {:?} on", low),
+            (false, false) => format!("keys from {:?} through {:?}", low, high),
+        },
+        LabelPattern::Prefix(prefix) if prefix.is_empty() => "every key".to_string(),
+        LabelPattern::Prefix(prefix) => format!("keys starting with {:?}", prefix),
+    };
+    format!("{} become {:?}", keys, rule.label)
+}
+
+fn describe_key_order(order: KeyOrder) -> String {
+    match order {
+        KeyOrder::Bytes => "byte order of keys".to_string(),
+        KeyOrder::Collate => "dictionary order of keys".to_string(),
+        KeyOrder::Locale(tailoring) => format!("{} dictionary order of keys", tailoring.name()),
+        KeyOrder::Natural => "natural order of keys, comparing numbers by value".to_string(),
+        KeyOrder::Numeric => {
+            "numeric order of keys, with keys that aren't numbers last".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn options(grouping: GroupingSpecifier, output: OutputOptions) -> GroupByOptions {
+        GroupByOptions {
+            input: InputOptions {
+                separator: Separator::Custom(", ".to_string()),
+                ..Default::default()
+            },
+            grouping,
+            labels: Labels::new(vec![
+                "a-m:first".parse().unwrap(),
+                "img:images".parse().unwrap(),
+            ]),
+            unique: false,
+            min_size: None,
+            max_size: None,
+            count_values: false,
+            output,
+            seed: Some(7),
+            explain: true,
+        }
+    }
+
+    #[test]
+    fn explains_every_section() {
+        let plan = explain(&options(
+            GroupingSpecifier::Regex(
+                Regex::new(r"\d+").unwrap(),
+                CaptureGroup::Number(0),
+                Unmatched::Skip,
+            ),
+            OutputOptions {
+                run_command: Some("wc -l".to_string()),
+                parallel: false,
+                capture_stderr: true,
+                failure_policy: FailurePolicy::FailFast,
+                stats: true,
+                checkpoint: Some("done.txt".to_string()),
+                resume: true,
+                sort: KeyOrder::Natural,
+                ..Default::default()
+            },
+        ));
+
+        for expected in [
+            "at each occurrence of \", \".",
+            "regular expression /\\d+/, using the whole match (tokens it doesn't match are skipped).",
+            "  - keys from \"a\" through \"m\" become \"first\"",
+            "  - keys starting with \"img\" become \"images\"",
+            "in a BTreeMap",
+            "-c \"wc -l\" once for each group, one at a time, in output order.",
+            "Capture each command's standard error",
+            "If a command fails, start no more commands",
+            "its command's duration and exit status",
+            "checkpoint file \"done.txt\", skipping groups it already lists.",
+            "command output in natural order of keys",
+            "Seed every randomized feature with 7.",
+        ] {
+            assert!(plan.contains(expected), "{:?} not in:\n{}", expected, plan);
+        }
+    }
+
+    #[test]
+    fn explains_dry_run() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                run_command: Some("gzip".to_string()),
+                dry_run: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("This is a dry run"));
+        assert!(plan.contains("For each group, in byte order of keys, print its command line"));
+    }
+
+    #[test]
+    fn explains_with_values() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                run_command: Some("wc -l".to_string()),
+                with_values: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("Print each group's contents and command output in"));
+    }
+
+    #[test]
+    fn explains_group_order() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                group_order: GroupOrder::SizeDescending,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("from the largest group to the smallest, then in byte order"));
+    }
+
+    #[test]
+    fn explains_reverse() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                reverse: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("in reverse byte order of keys"));
+    }
+
+    #[test]
+    fn explains_jobs() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                run_command: Some("gzip".to_string()),
+                shell: Some("/bin/dash".to_string()),
+                jobs: Some(3),
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains(
+            "Run /bin/dash -c \"gzip\" once for each group, in parallel, at most 3 at a time"
+        ));
+    }
+
+    #[test]
+    fn explains_live_output() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                run_command: Some("make".to_string()),
+                live: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("Print each line of command output as soon as it's written"));
+        assert!(!plan.contains("under a header"));
+    }
+
+    #[test]
+    fn explains_incremental_output() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                run_command: Some("make".to_string()),
+                incremental: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("as soon as it and every group before it are done, under a header"));
+    }
+
+    #[test]
+    fn explains_batches() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                run_command: Some("rm".to_string()),
+                max_args: Some(100),
+                max_stdin_bytes: Some(4096),
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("Pass at most 100 values and 4096 bytes to each run of a command"));
+    }
+
+    #[test]
+    fn explains_limits() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                run_command: Some("ffmpeg".to_string()),
+                nice: Some(10),
+                max_open_files: Some(64),
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("Run each command at niceness 10, with at most 64 open files."));
+    }
+
+    #[test]
+    fn explains_key_placeholders() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                run_command: Some("cat > {}.txt".to_string()),
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("Replace each {} or {key} in the command with the group's key"));
+    }
+
+    #[test]
+    fn explains_streaming() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                presorted_streaming: true,
+                stats: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("sorted by key"));
+        assert!(plan.contains("Print each group in input order"));
+        assert!(!plan.contains("statistics"));
+        assert!(!plan.contains("Commands:"));
+    }
+
+    #[test]
+    fn explains_token_streaming() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                stream: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("Keep nothing in memory"));
+        assert!(plan.contains(
+            "Print each token as soon as it's read, as its key, a tab, and the token, followed \
+            by a newline."
+        ));
+        assert!(!plan.contains("Print each group"));
+    }
+
+    #[test]
+    fn explains_low_memory() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                low_memory: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("writing the rest to a temporary file"));
+        assert!(plan.contains("Print each group in byte order of keys"));
+        assert!(!plan.contains("BTreeMap"));
+
+        let plan = explain(&options(
+            GroupingSpecifier::Length,
+            OutputOptions {
+                low_memory: true,
+                sort: KeyOrder::Natural,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("Print each group in natural order of keys"));
+    }
+
+    #[test]
+    fn explains_interning() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                intern: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("storing each distinct value only once"));
+        assert!(plan.contains("Print each group in byte order of keys"));
+        assert!(!plan.contains("BTreeMap"));
+
+        let plan = explain(&options(
+            GroupingSpecifier::Shard(4),
+            OutputOptions {
+                intern: true,
+                sort: KeyOrder::Natural,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("Print each group in natural order of keys"));
+    }
+
+    #[test]
+    fn explains_timings() {
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                run_command: Some("wc -l".to_string()),
+                timings: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains(
+            "report how long reading input, grouping, running commands, and writing output took \
+            to standard error, then how long each group's command took, slowest first."
+        ));
+
+        let plan = explain(&options(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                timings: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("reading input, grouping, and writing output took"));
+    }
+
+    #[test]
+    fn explains_json() {
+        let plan = explain(&options(
+            GroupingSpecifier::FileExtension,
+            OutputOptions {
+                json: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("JSON object mapping each group's key to an array of its values"));
+    }
+
+    #[test]
+    fn explains_count_only() {
+        let plan = explain(&options(
+            GroupingSpecifier::FileExtension,
+            OutputOptions {
+                count_only: true,
+                ..Default::default()
+            },
+        ));
+        assert!(plan.contains("Count the tokens in each group"));
+        assert!(plan.contains("Print each group's key and count"));
+        assert!(!plan.contains("BTreeMap"));
+    }
+
+    #[test]
+    fn explains_key_command_shell() {
+        let spec = GroupingSpecifier::KeyCommand("cut -c1".to_string(), 10, None);
+        let output = explain(&options(spec, OutputOptions::default()));
+        assert!(output.contains("the command cut -c1 prints for it, given tokens"));
+        let spec =
+            GroupingSpecifier::KeyCommand("cut -c1".to_string(), 10, Some("/bin/dash".into()));
+        let output = explain(&options(spec, OutputOptions::default()));
+        assert!(output.contains("the command cut -c1 prints for it in /bin/dash, given tokens"));
+    }
+
+    #[test]
+    fn explains_unique() {
+        let mut options = options(GroupingSpecifier::FileExtension, OutputOptions::default());
+        assert!(!explain(&options).contains("duplicate"));
+        options.unique = true;
+        assert!(explain(&options).contains("remove duplicate values from each group"));
+    }
+
+    #[test]
+    fn explains_count_values() {
+        let mut options = options(GroupingSpecifier::FileExtension, OutputOptions::default());
+        assert!(!explain(&options).contains("prefixed with its count"));
+        options.count_values = true;
+        assert!(explain(&options).contains("collapse identical values in each group"));
+    }
+
+    #[test]
+    fn explains_stats_detail() {
+        let output = OutputOptions {
+            stats: true,
+            stats_detail: Some(vec![90, 99]),
+            ..Default::default()
+        };
+        let plan = explain(&options(GroupingSpecifier::FileExtension, output));
+        assert!(plan.contains("their percentiles (p90, p99), and a histogram of group sizes."));
+    }
+
+    #[test]
+    fn explains_format() {
+        let output = OutputOptions {
+            format: Some("{key}: {count}".parse().unwrap()),
+            ..Default::default()
+        };
+        let plan = explain(&options(GroupingSpecifier::FileExtension, output));
+        assert!(plan.contains("by filling in the template \"{key}: {count}\", followed by"));
+    }
+
+    #[test]
+    fn explains_header_format() {
+        let output = OutputOptions {
+            header_format: Some("# {key}".parse().unwrap()),
+            ..Default::default()
+        };
+        let plan = explain(&options(GroupingSpecifier::FileExtension, output));
+        assert!(plan.contains("a header filled in from the template \"# {key}\", then its values"));
+    }
+
+    #[test]
+    fn explains_shell_quoting() {
+        let mut output = OutputOptions::default();
+        assert!(
+            !explain(&options(GroupingSpecifier::FileExtension, output.clone())).contains("Quote")
+        );
+        output.quote = Quote::Shell;
+        let plan = explain(&options(GroupingSpecifier::FileExtension, output.clone()));
+        assert!(plan.contains("Quote each printed key and value"));
+        output.json = true;
+        let plan = explain(&options(GroupingSpecifier::FileExtension, output));
+        assert!(!plan.contains("Quote"));
+    }
+
+    #[test]
+    fn explains_bare_group_names() {
+        let output = OutputOptions {
+            separator: Separator::Null,
+            only_group_names: true,
+            quote: Quote::Shell,
+            ..Default::default()
+        };
+        let plan = explain(&options(GroupingSpecifier::FileExtension, output));
+        assert!(plan.contains("Print each group's key exactly as it is, in byte order of keys,"));
+        assert!(!plan.contains("Quote"));
+    }
+
+    #[test]
+    fn explains_input_files() {
+        let mut options = options(GroupingSpecifier::FileExtension, OutputOptions::default());
+        assert!(explain(&options).contains("Read standard input, splitting it"));
+        options.input.files = vec![PathBuf::from("a.log")];
+        assert!(explain(&options).contains("Read \"a.log\", splitting it"));
+        options.input.files.push(PathBuf::from("-"));
+        options.input.with_filename = true;
+        let plan = explain(&options);
+        assert!(plan.contains("Read \"a.log\" and standard input, in that order, splitting each"));
+        assert!(plan.contains("Prefix each value with the name of the file it came from"));
+
+        options.input.trim = true;
+        options.input.skip_empty = true;
+        assert!(explain(&options).contains("and skip tokens that are left empty"));
+
+        options.input.sample = Some(Fraction(0.25));
+        options.input.limit = Some(100);
+        let plan = explain(&options);
+        assert!(plan.contains("Keep each token at random with probability 0.25"));
+        assert!(plan.contains("Stop reading input once 100 tokens have been grouped."));
+
+        options.input.files.clear();
+        options.input.files0_from = Some(PathBuf::from("-"));
+        assert!(explain(&options).contains(
+            "Read the files named in standard input, each followed by a null character, \
+            splitting each"
+        ));
+    }
+
+    #[test]
+    fn explains_tee() {
+        let mut options = options(GroupingSpecifier::FileExtension, OutputOptions::default());
+        assert!(!explain(&options).contains("Copy the input"));
+        options.input.tee = Some(PathBuf::from("-"));
+        assert!(explain(&options).contains(
+            "Copy the input, unchanged, to standard output as it's read, and print the results \
+            below to standard error instead of standard output."
+        ));
+        options.input.tee = Some(PathBuf::from("copy.log"));
+        assert!(explain(&options).contains("Copy the input, unchanged, to \"copy.log\" as"));
+    }
+
+    #[test]
+    fn explains_walks() {
+        let mut options = options(GroupingSpecifier::FileExtension, OutputOptions::default());
+        options.input.walk = Some(Walk::new("src"));
+        let plan = explain(&options);
+        assert!(plan.contains(
+            "Walk \"src\" recursively, in sorted order, and use the path of each \
+            file that isn't hidden as a token"
+        ));
+        assert!(!plan.contains("Read"));
+
+        options.input.walk = Some(Walk {
+            hidden: true,
+            follow_symlinks: true,
+            ..Walk::new("src")
+        });
+        assert!(explain(&options).contains(
+            "Walk \"src\" recursively, following symbolic links to directories, in sorted \
+            order, and use the path of each file, including hidden ones, as a token"
+        ));
+    }
+
+    #[test]
+    fn explains_size_limits() {
+        let mut options = options(GroupingSpecifier::FileExtension, OutputOptions::default());
+        assert!(!explain(&options).contains("drop groups"));
+        options.min_size = Some(2);
+        assert!(explain(&options).contains("drop groups with fewer than 2 values."));
+        options.max_size = Some(9);
+        assert!(explain(&options).contains("drop groups with fewer than 2 or more than 9 values."));
+    }
+
+    #[test]
+    fn explains_invalid_utf8() {
+        let mut options = options(GroupingSpecifier::FileExtension, OutputOptions::default());
+        assert!(!explain(&options).contains("UTF-8"));
+        options.input.invalid_utf8 = InvalidUtf8::Skip;
+        assert!(explain(&options).contains("Skip tokens that aren't valid UTF-8"));
+    }
+}
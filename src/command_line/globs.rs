@@ -0,0 +1,387 @@
+//! Glob patterns and named glob rules, for classifying paths into human-chosen groups.
+//!
+//! A [GlobRule] such as `images=*.{jpg,png}` names a group and gives a [Glob] pattern for it.
+//! Given an ordered list of rules, [classify] returns the name of the first rule whose pattern
+//! matches a path. This reads far more easily than one giant regular expression with an
+//! alternation per group.
+//!
+//! # Pattern syntax
+//!
+//! | Pattern  | Matches                                                             |
+//! |----------|---------------------------------------------------------------------|
+//! | `*`      | any run of characters, except `/`                                   |
+//! | `**`     | any run of characters, including `/`                                |
+//! | `**/`    | zero or more whole directories, e.g. `src/**/*.rs` matches `src/a.rs` |
+//! | `?`      | any single character, except `/`                                    |
+//! | `[abc]`  | any one of the listed characters; ranges like `[a-z]` work too      |
+//! | `[!abc]` | any one character not listed, except `/`; `[^abc]` also works      |
+//! | `{a,b}`  | either alternative; alternatives may contain any other syntax      |
+//! | `\x`     | the character `x`, literally                                        |
+//!
+//! Every other character matches itself, case-sensitively.
+//!
+//! As with `.gitignore`, a pattern without a `/` matches the last component of a path, so `*.rs`
+//! matches `src/lib.rs`. A pattern with a `/` matches the whole path, ignoring a leading `./`.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::globs::*;
+//!
+//! let rules: Vec<GlobRule> = vec![
+//!     "images=*.{jpg,png}".parse().unwrap(),
+//!     "tests=tests/**".parse().unwrap(),
+//!     "code=*.rs".parse().unwrap(),
+//! ];
+//!
+//! assert_eq!(classify("photos/cat.jpg", &rules), Some("images"));
+//! assert_eq!(classify("./tests/cli.rs", &rules), Some("tests"));
+//! assert_eq!(classify("src/lib.rs", &rules), Some("code"));
+//! assert_eq!(classify("README.md", &rules), None);
+//! ```
+
+use std::str::FromStr;
+
+// A single element of a compiled pattern.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    Star,
+    DoubleStar,
+    // `**/`: zero or more whole directories.
+    Directories,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+/// A compiled glob pattern. See the [module documentation](self) for the syntax.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Glob {
+    pattern: String,
+
+    // One token list per alternative, after expanding braces.
+    alternatives: Vec<Vec<Token>>,
+
+    // Whether the pattern matches whole paths rather than their last component.
+    whole_path: bool,
+}
+
+impl FromStr for Glob {
+    type Err = String;
+
+    /// Compiles a pattern.
+    ///
+    /// ```
+    /// use groupby::command_line::globs::Glob;
+    ///
+    /// assert!("*.{jpg,png}".parse::<Glob>().is_ok());
+    /// assert!("[a-".parse::<Glob>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let alternatives = expand_braces(s)
+            .iter()
+            .map(|alternative| compile(alternative))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| format!("{} in glob pattern: {}", error, s))?;
+        Ok(Glob {
+            pattern: s.to_string(),
+            alternatives,
+            whole_path: s.contains('/'),
+        })
+    }
+}
+
+impl Glob {
+    /// Returns the pattern as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns true if the pattern matches `path`.
+    ///
+    /// ```
+    /// use groupby::command_line::globs::Glob;
+    ///
+    /// let glob: Glob = "src/**/*.rs".parse().unwrap();
+    /// assert!(glob.matches("src/lib.rs"));
+    /// assert!(glob.matches("./src/bin/groupby.rs"));
+    /// assert!(!glob.matches("tests/cli.rs"));
+    /// ```
+    pub fn matches(&self, path: &str) -> bool {
+        let subject = if self.whole_path {
+            path.strip_prefix("./").unwrap_or(path)
+        } else {
+            let path = path.trim_end_matches('/');
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        let subject: Vec<char> = subject.chars().collect();
+        self.alternatives
+            .iter()
+            .any(|tokens| matches_tokens(tokens, &subject))
+    }
+}
+
+/// A named glob pattern: paths that match `glob` belong in the group `name`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlobRule {
+    pub name: String,
+    pub glob: Glob,
+}
+
+impl FromStr for GlobRule {
+    type Err = String;
+
+    /// Parses a rule written as `NAME=PATTERN`. The name is everything before the first `=`, so
+    /// patterns may contain `=` but names may not.
+    ///
+    /// ```
+    /// use groupby::command_line::globs::GlobRule;
+    ///
+    /// let rule: GlobRule = "docs=*.md".parse().unwrap();
+    /// assert_eq!(rule.name, "docs");
+    /// assert_eq!(rule.glob.as_str(), "*.md");
+    ///
+    /// assert!("*.md".parse::<GlobRule>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, pattern) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected a glob rule like NAME=PATTERN, but got: {}", s))?;
+        Ok(GlobRule {
+            name: name.to_string(),
+            glob: pattern.parse()?,
+        })
+    }
+}
+
+/// Returns the name of the first rule whose pattern matches `path`, or `None` if none match.
+pub fn classify<'a>(path: &str, rules: &'a [GlobRule]) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.glob.matches(path))
+        .map(|rule| rule.name.as_str())
+}
+
+// Expands the first top-level {a,b,...} group in pattern, recursively, into one pattern per
+// combination of alternatives. Unbalanced braces are left as literal text.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let chars: Vec<(usize, char)> = pattern.char_indices().collect();
+    let mut open = None;
+    let mut depth = 0;
+    let mut commas = vec![];
+    let mut escaped = false;
+    for &(i, c) in &chars {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '{' => {
+                if depth == 0 {
+                    open = Some(i);
+                    commas.clear();
+                }
+                depth += 1;
+            }
+            ',' if depth == 1 => commas.push(i),
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let open = open.unwrap();
+                    let (prefix, suffix) = (&pattern[..open], &pattern[i + 1..]);
+                    let mut bounds = vec![open];
+                    bounds.extend(&commas);
+                    bounds.push(i);
+                    return bounds
+                        .windows(2)
+                        .flat_map(|pair| {
+                            let alternative = &pattern[pair[0] + 1..pair[1]];
+                            expand_braces(&format!("{}{}{}", prefix, alternative, suffix))
+                        })
+                        .collect();
+                }
+            }
+            _ => (),
+        }
+    }
+    vec![pattern.to_string()]
+}
+
+// Compiles a brace-free pattern into tokens.
+fn compile(pattern: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '\\' => Token::Literal(chars.next().ok_or("Trailing backslash")?),
+            '?' => Token::AnyChar,
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    Token::Directories
+                } else {
+                    Token::DoubleStar
+                }
+            }
+            '*' => Token::Star,
+            '[' => {
+                let negated = matches!(chars.peek(), Some('!') | Some('^'));
+                if negated {
+                    chars.next();
+                }
+                let mut ranges = vec![];
+                let mut first = true;
+                loop {
+                    let c = chars.next().ok_or("Unclosed [")?;
+                    if c == ']' && !first {
+                        break;
+                    }
+                    first = false;
+                    let low = if c == '\\' {
+                        chars.next().ok_or("Unclosed [")?
+                    } else {
+                        c
+                    };
+                    let high = if chars.peek() == Some(&'-') {
+                        chars.next();
+                        match chars.next().ok_or("Unclosed [")? {
+                            // A trailing - is literal, as in [a-].
+                            ']' => {
+                                ranges.push((low, low));
+                                ranges.push(('-', '-'));
+                                break;
+                            }
+                            '\\' => chars.next().ok_or("Unclosed [")?,
+                            high => high,
+                        }
+                    } else {
+                        low
+                    };
+                    ranges.push((low, high));
+                }
+                Token::Class { negated, ranges }
+            }
+            c => Token::Literal(c),
+        });
+    }
+    Ok(tokens)
+}
+
+// Returns true if tokens match all of subject.
+fn matches_tokens(tokens: &[Token], subject: &[char]) -> bool {
+    // failed[t * (subject.len() + 1) + s] records that tokens[t..] can't match subject[s..], so
+    // patterns with several stars don't take exponential time.
+    let mut failed = vec![false; (tokens.len() + 1) * (subject.len() + 1)];
+    matches_from(tokens, subject, 0, 0, &mut failed)
+}
+
+fn matches_from(
+    tokens: &[Token],
+    subject: &[char],
+    t: usize,
+    s: usize,
+    failed: &mut [bool],
+) -> bool {
+    let index = t * (subject.len() + 1) + s;
+    if failed[index] {
+        return false;
+    }
+
+    let matched = match tokens.get(t) {
+        None => s == subject.len(),
+        Some(Token::Literal(c)) => {
+            subject.get(s) == Some(c) && matches_from(tokens, subject, t + 1, s + 1, failed)
+        }
+        Some(Token::AnyChar) => {
+            matches!(subject.get(s), Some(c) if *c != '/')
+                && matches_from(tokens, subject, t + 1, s + 1, failed)
+        }
+        Some(Token::Class { negated, ranges }) => {
+            matches!(subject.get(s), Some(c) if {
+                let listed = ranges.iter().any(|(low, high)| (low..=high).contains(&c));
+                listed != *negated && !(*negated && *c == '/')
+            }) && matches_from(tokens, subject, t + 1, s + 1, failed)
+        }
+        Some(Token::Star) => (s..=subject.len())
+            .take_while(|end| *end == s || subject[end - 1] != '/')
+            .any(|end| matches_from(tokens, subject, t + 1, end, failed)),
+        Some(Token::DoubleStar) => {
+            (s..=subject.len()).any(|end| matches_from(tokens, subject, t + 1, end, failed))
+        }
+        Some(Token::Directories) => (s..=subject.len())
+            .filter(|end| *end == s || subject[end - 1] == '/')
+            .any(|end| matches_from(tokens, subject, t + 1, end, failed)),
+    };
+
+    if !matched {
+        failed[index] = true;
+    }
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str) -> Glob {
+        pattern.parse().unwrap()
+    }
+
+    #[test]
+    fn star_stays_within_a_component() {
+        assert!(glob("src/*.rs").matches("src/lib.rs"));
+        assert!(!glob("src/*.rs").matches("src/bin/groupby.rs"));
+        assert!(glob("src/**.rs").matches("src/bin/groupby.rs"));
+    }
+
+    #[test]
+    fn matches_basenames_without_slashes() {
+        assert!(glob("Cargo.*").matches("/home/me/crate/Cargo.toml"));
+        assert!(glob("src").matches("./src/"));
+        assert!(!glob("crate").matches("/home/me/crate/Cargo.toml"));
+    }
+
+    #[test]
+    fn matches_classes() {
+        let glob = glob("[!a-c]?[xyz-]");
+        assert!(glob.matches("d1-"));
+        assert!(glob.matches("e2x"));
+        assert!(!glob.matches("b1x"));
+        assert!(!glob.matches("d1w"));
+    }
+
+    #[test]
+    fn expands_nested_braces() {
+        assert_eq!(expand_braces("a{b,c{d,e}}f"), vec!["abf", "acdf", "acef"]);
+        assert_eq!(expand_braces("a{b"), vec!["a{b"]);
+        assert_eq!(expand_braces(r"a\{b,c}"), vec![r"a\{b,c}"]);
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert!(glob(r"\*.txt").matches("*.txt"));
+        assert!(!glob(r"\*.txt").matches("a.txt"));
+    }
+
+    #[test]
+    fn handles_many_stars_quickly() {
+        let glob = glob("*a*a*a*a*a*a*a*a*b");
+        assert!(!glob.matches(&"a".repeat(100)));
+    }
+
+    #[test]
+    fn classifies_with_the_first_matching_rule() {
+        let rules: Vec<GlobRule> = vec![
+            "rust=*.rs".parse().unwrap(),
+            "source=*.{rs,c}".parse().unwrap(),
+        ];
+        assert_eq!(classify("main.rs", &rules), Some("rust"));
+        assert_eq!(classify("main.c", &rules), Some("source"));
+    }
+}
@@ -0,0 +1,379 @@
+//! Opens the files that input is read from, as named by [InputOptions::files] and
+//! [InputOptions::files0_from].
+//!
+//! Files are read one after another, in the order given, but each is grouped on its own, so a
+//! token never spans two files and [InputOptions::with_filename] knows where each value came
+//! from. A file named `-`, or no files at all, means standard input. Inputs compressed with gzip
+//! or zstd are [decompressed](crate::command_line::decompress) as they're read.
+//!
+//! With [InputOptions::with_filename], each value is added to its group through [WithFilename],
+//! which prefixes it with its file's name and a colon. The key is found first, so the prefix
+//! never affects grouping.
+//!
+//! With [InputOptions::tee], each input is read through a [Tee], which copies it to standard
+//! output or a file as it's read.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::input_files::{self, WithFilename};
+//! use groupby::grouped_collections::GroupedCollection;
+//! use std::cell::RefCell;
+//! use std::collections::BTreeMap;
+//! use std::io::BufRead;
+//!
+//! let path = std::env::temp_dir().join("groupby-input-files-doctest");
+//! std::fs::write(&path, "apple\navocado\n").unwrap();
+//!
+//! let input = input_files::open(&path).unwrap();
+//! let name = RefCell::new(input.name.clone());
+//! let mut map: BTreeMap<String, Vec<String>> = BTreeMap::new();
+//! let mut with_filename = WithFilename::new(&mut map, &name);
+//! for line in input.reader.lines() {
+//!     let line = line.unwrap();
+//!     with_filename.add(line[..1].to_string(), line);
+//! }
+//!
+//! let name = name.into_inner();
+//! assert_eq!(map["a"], vec![format!("{}:apple", name), format!("{}:avocado", name)]);
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use crate::command_line::decompress::decompressed;
+use crate::command_line::error::GroupByError;
+use crate::command_line::options::InputOptions;
+use crate::grouped_collections::GroupedCollection;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+/// The name that [InputOptions::with_filename] gives standard input, as `grep` does.
+pub const STDIN_NAME: &str = "(standard input)";
+
+/// An input to read: its name and a reader over its contents.
+pub struct Input {
+    /// The file's path as given, or [STDIN_NAME] for standard input.
+    pub name: String,
+
+    pub reader: Box<dyn BufRead>,
+}
+
+/// Opens the file at `path` for reading, or standard input if `path` is `-`. If the input is
+/// compressed, the reader decompresses it; see [crate::command_line::decompress].
+///
+/// # Errors
+///
+/// Returns [GroupByError::Input] if the file can't be opened, or any error that [decompressed]
+/// returns.
+pub fn open(path: &Path) -> Result<Input, GroupByError> {
+    let (name, reader): (String, Box<dyn BufRead>) = if path == Path::new("-") {
+        (STDIN_NAME.to_string(), Box::new(io::stdin().lock()))
+    } else {
+        let name = path.display().to_string();
+        let file = File::open(path).map_err(|e| GroupByError::Input(name.clone(), e))?;
+        (name, Box::new(BufReader::new(file)))
+    };
+    let reader = decompressed(&name, reader)?;
+    Ok(Input { name, reader })
+}
+
+/// Returns the paths of the inputs named by `options`, in order: [InputOptions::files], then the
+/// files listed in [InputOptions::files0_from], if any. If there are none, the only input is
+/// standard input, `-`.
+///
+/// ```
+/// use groupby::command_line::input_files::paths;
+/// use groupby::command_line::options::*;
+/// use std::path::PathBuf;
+///
+/// let list = std::env::temp_dir().join("groupby-files0-from-doctest");
+/// std::fs::write(&list, b"b.log\0c.log\0").unwrap();
+///
+/// let options = InputOptions {
+///     files: vec![PathBuf::from("a.log")],
+///     files0_from: Some(list.clone()),
+///     ..Default::default()
+/// };
+/// let expected: Vec<PathBuf> = ["a.log", "b.log", "c.log"].iter().map(PathBuf::from).collect();
+/// assert_eq!(paths(&options).unwrap(), expected);
+/// # std::fs::remove_file(&list).unwrap();
+/// ```
+///
+/// # Errors
+///
+/// Returns [GroupByError::Input] if the list of files can't be read, or
+/// [GroupByError::InvalidInput] if it names a file with an empty name, or names standard input
+/// when the list itself is read from standard input.
+pub fn paths(options: &InputOptions) -> Result<Vec<PathBuf>, GroupByError> {
+    let mut paths = options.files.clone();
+    if let Some(list) = &options.files0_from {
+        let mut names = vec![];
+        open(list)?
+            .reader
+            .read_to_end(&mut names)
+            .map_err(|e| GroupByError::Input(list.display().to_string(), e))?;
+
+        // Like -print0 output, the list ends with a null character, which doesn't start a name.
+        let names = names.strip_suffix(b"\0").unwrap_or(&names);
+        if !names.is_empty() {
+            for name in names.split(|byte| *byte == b'\0') {
+                if name.is_empty() {
+                    return Err(GroupByError::InvalidInput(format!(
+                        "{} lists a file with an empty name",
+                        list.display()
+                    )));
+                }
+                if name == b"-" && list == Path::new("-") {
+                    return Err(GroupByError::InvalidInput(
+                        "Can't read input from standard input (-) while reading the names of \
+                        input files from it"
+                            .to_string(),
+                    ));
+                }
+                paths.push(path_from_bytes(name));
+            }
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from("-"));
+    }
+    Ok(paths)
+}
+
+// Converts a file name from a list of files to a path. On Unix, names needn't be valid UTF-8.
+#[cfg(unix)]
+fn path_from_bytes(name: &[u8]) -> PathBuf {
+    PathBuf::from(OsStr::from_bytes(name))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(name: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(name).into_owned())
+}
+
+/// Returns the inputs named by `options`, in order, opening each only as it's reached. See
+/// [paths].
+///
+/// # Errors
+///
+/// Returns any error that [paths] returns. Each input also returns [GroupByError::Input] if it
+/// can't be opened.
+pub fn inputs(
+    options: &InputOptions,
+) -> Result<impl Iterator<Item = Result<Input, GroupByError>>, GroupByError> {
+    Ok(paths(options)?.into_iter().map(|path| open(&path)))
+}
+
+/// Opens every input named by `options` and returns a reader over all of them, one after
+/// another, like `cat`. This is for consumers that read a single stream, like
+/// [stream_groups](crate::command_line::stream_groups()); unlike reading the inputs one at a
+/// time, a token may span the end of one file and the start of the next.
+///
+/// # Errors
+///
+/// Returns [GroupByError::Input] if any file can't be opened, or any error that [paths] returns.
+pub fn concatenated(options: &InputOptions) -> Result<Box<dyn BufRead>, GroupByError> {
+    let mut reader: Box<dyn BufRead> = Box::new(io::empty());
+    for input in inputs(options)? {
+        reader = Box::new(reader.chain(input?.reader));
+    }
+    if let Some(output) = tee_output(options)? {
+        reader = Box::new(BufReader::new(Tee::new(reader, output)));
+    }
+    Ok(reader)
+}
+
+/// Opens the destination of [InputOptions::tee], if any: standard output if it's `-`, or else the
+/// file it names, which is created or truncated.
+///
+/// # Errors
+///
+/// Returns [GroupByError::Tee] if the file can't be created.
+pub fn tee_output(options: &InputOptions) -> Result<Option<Box<dyn Write>>, GroupByError> {
+    Ok(match &options.tee {
+        None => None,
+        Some(path) if path == Path::new("-") => Some(Box::new(io::stdout())),
+        Some(path) => {
+            let file =
+                File::create(path).map_err(|e| GroupByError::Tee(path.display().to_string(), e))?;
+            Some(Box::new(io::BufWriter::new(file)))
+        }
+    })
+}
+
+/// A reader that copies everything read from `input` to `output`, unchanged, like `tee`. The
+/// output is flushed when the input ends.
+///
+/// ```
+/// use groupby::command_line::input_files::Tee;
+/// use std::io::{BufRead, BufReader};
+///
+/// let mut copy = vec![];
+/// let lines: Vec<String> = BufReader::new(Tee::new(&b"a\nb"[..], &mut copy))
+///     .lines()
+///     .map(Result::unwrap)
+///     .collect();
+/// assert_eq!(lines, vec!["a", "b"]);
+/// assert_eq!(copy, b"a\nb");
+/// ```
+pub struct Tee<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: Read, W: Write> Tee<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Tee { input, output }
+    }
+}
+
+impl<R: Read, W: Write> Read for Tee<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.input.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            self.output.flush()?;
+        } else {
+            self.output.write_all(&buf[..read])?;
+        }
+        Ok(read)
+    }
+}
+
+/// A [GroupedCollection] adapter that prefixes each value added through [GroupedCollection::add]
+/// with a file name and a colon, like `grep -H`. Values added through
+/// [GroupedCollection::entry] are not prefixed.
+///
+/// The name is read each time a value is added, so one adapter can serve several inputs in turn:
+/// set the name to each input's as it's read.
+pub struct WithFilename<'a, Map> {
+    map: &'a mut Map,
+    name: &'a RefCell<String>,
+}
+
+impl<'a, Map> WithFilename<'a, Map> {
+    pub fn new(map: &'a mut Map, name: &'a RefCell<String>) -> Self {
+        WithFilename { map, name }
+    }
+}
+
+impl<'s, 'a, Map, List> GroupedCollection<'s, String, String, List> for WithFilename<'a, Map>
+where
+    Map: for<'t> GroupedCollection<'t, String, String, List>,
+    List: 'static,
+{
+    type Iter = <Map as GroupedCollection<'s, String, String, List>>::Iter;
+    type IntoIter = <Map as GroupedCollection<'s, String, String, List>>::IntoIter;
+    type Entry = <Map as GroupedCollection<'s, String, String, List>>::Entry;
+
+    fn add(&mut self, key: String, value: String) {
+        self.map
+            .add(key, format!("{}:{}", self.name.borrow(), value))
+    }
+
+    fn entry(&'s mut self, key: String) -> Self::Entry {
+        <Map as GroupedCollection<'s, String, String, List>>::entry(self.map, key)
+    }
+
+    fn get(&'s self, key: &String) -> Option<&'s List> {
+        (*self.map).get(key)
+    }
+
+    fn remove(&mut self, key: &String) -> Option<List> {
+        self.map.remove(key)
+    }
+
+    fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&String, &mut List) -> bool,
+    {
+        self.map.retain(keep)
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn contains_key(&self, key: &String) -> bool {
+        self.map.contains_key(key)
+    }
+
+    fn iter(&'s self) -> Self::Iter {
+        (*self.map).iter()
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        <Map as GroupedCollection<'s, String, String, List>>::drain(self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(files: Vec<PathBuf>) -> InputOptions {
+        InputOptions {
+            files,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn open_names_the_missing_file() {
+        let path = Path::new("/nonexistent/groupby-input");
+        match open(path) {
+            Err(GroupByError::Input(name, _)) => assert_eq!(name, "/nonexistent/groupby-input"),
+            _ => panic!("expected an input error"),
+        }
+    }
+
+    #[test]
+    fn paths_default_to_standard_input() {
+        assert_eq!(paths(&options(vec![])).unwrap(), vec![PathBuf::from("-")]);
+    }
+
+    #[test]
+    fn paths_reads_lists_with_or_without_a_final_null() {
+        let list = std::env::temp_dir().join(format!("groupby-files0-{}", std::process::id()));
+        let mut options = options(vec![]);
+        options.files0_from = Some(list.clone());
+        for (contents, expected) in [
+            (&b"a b\0c"[..], vec!["a b", "c"]),
+            (b"a b\0c\0", vec!["a b", "c"]),
+            (b"", vec!["-"]),
+        ] {
+            std::fs::write(&list, contents).unwrap();
+            let expected: Vec<PathBuf> = expected.into_iter().map(PathBuf::from).collect();
+            assert_eq!(paths(&options).unwrap(), expected);
+        }
+
+        std::fs::write(&list, b"a\0\0b\0").unwrap();
+        assert!(matches!(
+            paths(&options),
+            Err(GroupByError::InvalidInput(_))
+        ));
+        std::fs::remove_file(&list).unwrap();
+    }
+
+    #[test]
+    fn concatenated_reads_files_in_order() {
+        let dir = std::env::temp_dir();
+        let first = dir.join(format!("groupby-input-first-{}", std::process::id()));
+        let second = dir.join(format!("groupby-input-second-{}", std::process::id()));
+        std::fs::write(&first, "a\n").unwrap();
+        std::fs::write(&second, "b\n").unwrap();
+
+        let mut contents = String::new();
+        concatenated(&options(vec![first.clone(), second.clone()]))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "a\nb\n");
+
+        std::fs::remove_file(&first).unwrap();
+        std::fs::remove_file(&second).unwrap();
+    }
+}
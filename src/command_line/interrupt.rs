@@ -0,0 +1,216 @@
+//! Handling for interrupts, such as Ctrl-C, while commands run.
+//!
+//! Once [install_handler()] has been called, the first interrupt doesn't stop `groupby` right away.
+//! Instead, it asks every running command to terminate and stops new commands from starting, so
+//! that [run_command()](crate::command_line::run_command()) returns the results of the commands
+//! that finished. The caller can write those results and then report that they're partial, using
+//! [is_interrupted()] and [GroupByError::Interrupted]. A second interrupt exits immediately with
+//! [EXIT_CODE].
+//!
+//! Running commands are tracked by process ID: see [Running]. Once the handler is installed, each
+//! command runs in its own process group (see [is_handled()]), so that terminating it also
+//! terminates any processes it started.
+
+use crate::command_line::error::GroupByError;
+use std::collections::BTreeSet;
+use std::io;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// The exit code for a process that was interrupted, following the shell's convention of 128 plus
+/// the number of the signal, `SIGINT`.
+pub const EXIT_CODE: i32 = 130;
+
+// The state for the process as a whole.
+static STATE: Interrupts = Interrupts::new();
+
+// Tracks whether interrupts are handled, whether one has arrived, and which commands are running.
+struct Interrupts {
+    handled: AtomicBool,
+    interrupted: AtomicBool,
+    running: Mutex<BTreeSet<u32>>,
+}
+
+impl Interrupts {
+    const fn new() -> Self {
+        Interrupts {
+            handled: AtomicBool::new(false),
+            interrupted: AtomicBool::new(false),
+            running: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    // Records the interrupt and terminates every running command. Returns true if there had
+    // already been an interrupt.
+    fn interrupt(&self) -> bool {
+        let repeated = self.interrupted.swap(true, Ordering::SeqCst);
+        for &id in self.running.lock().unwrap().iter() {
+            terminate(id);
+        }
+        repeated
+    }
+
+    fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
+
+    // Starts tracking the command with process ID `id`. If there's already been an interrupt, the
+    // command is terminated right away, since it started too late to be terminated with the rest.
+    fn start(&self, id: u32) {
+        self.running.lock().unwrap().insert(id);
+        if self.is_interrupted() {
+            terminate(id);
+        }
+    }
+
+    fn finish(&self, id: u32) {
+        self.running.lock().unwrap().remove(&id);
+    }
+}
+
+// Asks the process with ID `id` to terminate, along with the rest of its process group. Each
+// command leads its own group (see command_runner::run()), so this reaches every process a shell
+// starts, too, and none of them are left holding the command's output open.
+#[cfg(unix)]
+fn terminate(id: u32) {
+    // SAFETY: kill() has no memory-safety requirements. At worst, if the group has already exited,
+    // the signal goes nowhere or to an unrelated group that reused the ID, which is the usual risk
+    // of signalling by process ID.
+    unsafe {
+        libc::kill(-(id as libc::pid_t), libc::SIGTERM);
+    }
+}
+
+// On other platforms, an interrupt from the console reaches every process attached to it, so
+// commands are interrupted along with us.
+#[cfg(not(unix))]
+fn terminate(_id: u32) {}
+
+/// Installs a handler for `SIGINT` and `SIGTERM` (or Ctrl-C on Windows) that interrupts running
+/// commands, as described in the [module documentation](self).
+///
+/// Call this only once, right before running commands: while it's installed, an interrupt doesn't
+/// stop `groupby` while it's reading input.
+///
+/// # Errors
+///
+/// Returns [GroupByError::Io] if the handler can't be installed, e.g. because another handler
+/// already has been.
+pub fn install_handler() -> Result<(), GroupByError> {
+    ctrlc::set_handler(|| {
+        if STATE.interrupt() {
+            process::exit(EXIT_CODE);
+        }
+    })
+    .map_err(|e| GroupByError::Io(io::Error::other(e)))?;
+    STATE.handled.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Returns true if [install_handler()] has been called successfully.
+///
+/// Commands should only run in their own process groups if so, since they won't receive signals
+/// from the terminal otherwise.
+pub fn is_handled() -> bool {
+    STATE.handled.load(Ordering::SeqCst)
+}
+
+/// Returns true if an interrupt has arrived since [install_handler()] was called.
+pub fn is_interrupted() -> bool {
+    STATE.is_interrupted()
+}
+
+/// Tracks a running command, so that an interrupt can terminate it, until dropped.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::interrupt::Running;
+/// use std::process::Command;
+///
+/// let mut child = Command::new("true").spawn().unwrap();
+/// let running = Running::new(child.id());
+/// child.wait().unwrap();
+/// drop(running);
+/// ```
+pub struct Running {
+    id: u32,
+}
+
+impl Running {
+    /// Starts tracking the command with process ID `id`.
+    pub fn new(id: u32) -> Self {
+        STATE.start(id);
+        Running { id }
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        STATE.finish(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // These tests use their own Interrupts, since interrupting STATE would stop commands in other
+    // tests.
+
+    #[test]
+    fn starts_uninterrupted() {
+        assert!(!Interrupts::new().is_interrupted());
+    }
+
+    #[test]
+    fn reports_repeated_interrupts() {
+        let interrupts = Interrupts::new();
+        assert!(!interrupts.interrupt());
+        assert!(interrupts.is_interrupted());
+        assert!(interrupts.interrupt());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn terminates_running_commands() {
+        use std::os::unix::process::{CommandExt, ExitStatusExt};
+        use std::process::Command;
+
+        let interrupts = Interrupts::new();
+        let mut child = Command::new("sleep")
+            .arg("10")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        interrupts.start(child.id());
+        interrupts.interrupt();
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGTERM));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn terminates_commands_that_start_after_an_interrupt() {
+        use std::os::unix::process::{CommandExt, ExitStatusExt};
+        use std::process::Command;
+
+        let interrupts = Interrupts::new();
+        interrupts.interrupt();
+        let mut child = Command::new("sleep")
+            .arg("10")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        interrupts.start(child.id());
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGTERM));
+    }
+
+    #[test]
+    fn stops_tracking_finished_commands() {
+        let interrupts = Interrupts::new();
+        interrupts.start(1);
+        interrupts.finish(1);
+        assert!(interrupts.running.lock().unwrap().is_empty());
+    }
+}
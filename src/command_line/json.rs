@@ -0,0 +1,441 @@
+//! Just enough JSON for writing groups and for reading single values out of JSON Lines.
+//!
+//! The writing functions handle strings, arrays of strings, and objects. Each appends to a
+//! [String], so calls compose to build up a document.
+//!
+//! For reading, [lookup] finds the value at a [JsonPath] such as `.user.id` without building the
+//! whole document in memory, skipping over everything else.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::json;
+//!
+//! let groups = [("a", vec!["apple", "avocado"]), ("b", vec!["banana"])];
+//! let mut document = String::new();
+//! json::write_object(&mut document, groups, |document, values| {
+//!     json::write_array(document, values)
+//! });
+//! assert_eq!(document, r#"{"a":["apple","avocado"],"b":["banana"]}"#);
+//! ```
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Appends `s` to `json` as a quoted JSON string, escaping it as needed.
+///
+/// ```
+/// use groupby::command_line::json::write_string;
+///
+/// let mut json = String::new();
+/// write_string(&mut json, "say \"hi\"\n");
+/// assert_eq!(json, r#""say \"hi\"\n""#);
+/// ```
+pub fn write_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// Appends `values` to `json` as an array of strings.
+pub fn write_array<I, S>(json: &mut String, values: I)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    json.push('[');
+    for (i, value) in values.into_iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        write_string(json, value.as_ref());
+    }
+    json.push(']');
+}
+
+/// Appends `entries` to `json` as an object, in the order given, using `write_value` to append
+/// each entry's value.
+pub fn write_object<I, K, V, F>(json: &mut String, entries: I, mut write_value: F)
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    F: FnMut(&mut String, V),
+{
+    json.push('{');
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        write_string(json, key.as_ref());
+        json.push(':');
+        write_value(json, value);
+    }
+    json.push('}');
+}
+
+/// One step along a [JsonPath].
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// A path to a value inside a JSON document, in a subset of `jq` syntax: `.user.id` for the `id`
+/// of the `user` object, `.tags[0]` for the first element of the `tags` array, and
+/// `.["odd key"]` for keys that aren't plain names. A lone `.` is the whole document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonPath {
+    path: String,
+    steps: Vec<Step>,
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    /// Parses a path.
+    ///
+    /// ```
+    /// use groupby::command_line::json::JsonPath;
+    ///
+    /// assert!(".user.id".parse::<JsonPath>().is_ok());
+    /// assert!(".tags[0]".parse::<JsonPath>().is_ok());
+    /// assert!(r#".["user name"]"#.parse::<JsonPath>().is_ok());
+    /// assert!(".".parse::<JsonPath>().is_ok());
+    /// assert!("user.id".parse::<JsonPath>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "Expected a JSON path like .user.id or .tags[0], but got: {}",
+                s
+            )
+        };
+
+        let mut steps = vec![];
+        let mut rest = s;
+        if rest == "." {
+            rest = "";
+        }
+        while !rest.is_empty() {
+            if let Some(bracketed) = rest.strip_prefix(".[").or_else(|| rest.strip_prefix('[')) {
+                let mut parser = Parser::new(bracketed);
+                let step = if parser.peek() == Some(b'"') {
+                    Step::Key(parser.string().ok_or_else(error)?)
+                } else {
+                    let digits = bracketed
+                        .find(|c: char| !c.is_ascii_digit())
+                        .unwrap_or(bracketed.len());
+                    parser.position = digits;
+                    Step::Index(bracketed[..digits].parse().map_err(|_| error())?)
+                };
+                rest = bracketed[parser.position..]
+                    .strip_prefix(']')
+                    .ok_or_else(error)?;
+                steps.push(step);
+            } else if let Some(named) = rest.strip_prefix('.') {
+                let end = named.find(&['.', '['][..]).unwrap_or(named.len());
+                if end == 0 {
+                    return Err(error());
+                }
+                steps.push(Step::Key(named[..end].to_string()));
+                rest = &named[end..];
+            } else {
+                return Err(error());
+            }
+        }
+        Ok(JsonPath {
+            path: s.to_string(),
+            steps,
+        })
+    }
+}
+
+impl JsonPath {
+    /// Returns the path as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+}
+
+/// Returns the value at `path` in the JSON document `json`, or `None` if there's no such value
+/// or the document is malformed along the way.
+///
+/// Strings are unescaped and returned without quotes. Numbers, `true`, `false`, and `null` are
+/// returned as written, as are arrays and objects, including any whitespace inside them. If an
+/// object has a key more than once, the first wins. Parts of the document that the path doesn't
+/// lead through are skipped without being fully checked.
+///
+/// ```
+/// use groupby::command_line::json::lookup;
+///
+/// let json = r#"{"user": {"id": 42, "name": "Ann \"Al\" Lee"}, "tags": ["a", "b"]}"#;
+/// assert_eq!(lookup(json, &".user.id".parse().unwrap()), Some("42".to_string()));
+/// assert_eq!(lookup(json, &".user.name".parse().unwrap()), Some(r#"Ann "Al" Lee"#.to_string()));
+/// assert_eq!(lookup(json, &".tags[1]".parse().unwrap()), Some("b".to_string()));
+/// assert_eq!(lookup(json, &".tags".parse().unwrap()), Some(r#"["a", "b"]"#.to_string()));
+/// assert_eq!(lookup(json, &".user.email".parse().unwrap()), None);
+/// assert_eq!(lookup("not json", &".user".parse().unwrap()), None);
+/// ```
+pub fn lookup(json: &str, path: &JsonPath) -> Option<String> {
+    let mut parser = Parser::new(json);
+    for step in &path.steps {
+        match step {
+            Step::Key(key) => parser.find_member(key)?,
+            Step::Index(index) => parser.find_element(*index)?,
+        }
+    }
+
+    parser.skip_whitespace();
+    if parser.peek() == Some(b'"') {
+        parser.string()
+    } else {
+        let start = parser.position;
+        parser.skip_value()?;
+        Some(json[start..parser.position].to_string())
+    }
+}
+
+// A cursor over a JSON document, for reading just the parts that a JsonPath leads through.
+struct Parser<'a> {
+    json: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(json: &'a str) -> Self {
+        Parser { json, position: 0 }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.json.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    // Skips whitespace, then consumes c if it's next.
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.peek() == Some(c);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    // Moves to the value of the member named key in the object that comes next.
+    fn find_member(&mut self, key: &str) -> Option<()> {
+        if !self.eat(b'{') || self.eat(b'}') {
+            return None;
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+            if !self.eat(b':') {
+                return None;
+            }
+            if name == key {
+                return Some(());
+            }
+            self.skip_value()?;
+            if !self.eat(b',') {
+                return None;
+            }
+        }
+    }
+
+    // Moves to the element at index in the array that comes next.
+    fn find_element(&mut self, index: usize) -> Option<()> {
+        if !self.eat(b'[') || self.eat(b']') {
+            return None;
+        }
+        for _ in 0..index {
+            self.skip_value()?;
+            if !self.eat(b',') {
+                return None;
+            }
+        }
+        Some(())
+    }
+
+    // Consumes the value that comes next, after any whitespace.
+    fn skip_value(&mut self) -> Option<()> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'"' => {
+                self.string()?;
+            }
+            open @ (b'{' | b'[') => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.position += 1;
+                if self.eat(close) {
+                    return Some(());
+                }
+                loop {
+                    if open == b'{' {
+                        self.skip_whitespace();
+                        self.string()?;
+                        if !self.eat(b':') {
+                            return None;
+                        }
+                    }
+                    self.skip_value()?;
+                    if self.eat(close) {
+                        break;
+                    }
+                    if !self.eat(b',') {
+                        return None;
+                    }
+                }
+            }
+            _ => {
+                // A number or literal: everything up to the next delimiter.
+                let rest = &self.json[self.position..];
+                let end = rest
+                    .find(|c: char| matches!(c, ',' | '}' | ']') || c.is_whitespace())
+                    .unwrap_or(rest.len());
+                if end == 0 {
+                    return None;
+                }
+                self.position += end;
+            }
+        }
+        Some(())
+    }
+
+    // Consumes a string, which must come next, and returns it unescaped.
+    fn string(&mut self) -> Option<String> {
+        if self.peek() != Some(b'"') {
+            return None;
+        }
+        self.position += 1;
+        let mut result = String::new();
+        let mut chars = self.json[self.position..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += i + 1;
+                    return Some(result);
+                }
+                '\\' => {
+                    let (_, escape) = chars.next()?;
+                    result.push(match escape {
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let mut code = hex4(&mut chars)?;
+                            // Surrogate pairs encode characters outside the Basic Multilingual
+                            // Plane as two escapes.
+                            if (0xd800..0xdc00).contains(&code) {
+                                let (_, backslash) = chars.next()?;
+                                let (_, u) = chars.next()?;
+                                let low = hex4(&mut chars)?;
+                                if backslash != '\\' || u != 'u' || !(0xdc00..0xe000).contains(&low)
+                                {
+                                    return None;
+                                }
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+                            char::from_u32(code)?
+                        }
+                        c => c,
+                    });
+                }
+                c => result.push(c),
+            }
+        }
+        None
+    }
+}
+
+// Reads the four hex digits of a \u escape.
+fn hex4(chars: &mut std::str::CharIndices) -> Option<u32> {
+    let mut code = 0;
+    for _ in 0..4 {
+        code = code * 16 + chars.next()?.1.to_digit(16)?;
+    }
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_control_characters() {
+        let mut json = String::new();
+        write_string(&mut json, "\\\t\r\u{1}\u{7f}");
+        assert_eq!(json, "\"\\\\\\t\\r\\u0001\u{7f}\"");
+    }
+
+    #[test]
+    fn writes_empty_containers() {
+        let mut json = String::new();
+        write_object(&mut json, Vec::<(&str, Vec<&str>)>::new(), write_array);
+        write_array(&mut json, Vec::<&str>::new());
+        assert_eq!(json, "{}[]");
+    }
+
+    #[test]
+    fn reads_escapes() {
+        let json = r#"{"a\"b": "tab\there \u00e9 \ud83d\ude00"}"#;
+        let path = r#".["a\"b"]"#.parse().unwrap();
+        assert_eq!(
+            lookup(json, &path),
+            Some("tab\there \u{e9} \u{1f600}".to_string())
+        );
+    }
+
+    #[test]
+    fn skips_nested_values() {
+        let json = r#"{"skip": {"a": [1, {"b": "}"}], "c": null}, "keep": [true, -1.5e3]}"#;
+        assert_eq!(
+            lookup(json, &".keep[1]".parse().unwrap()),
+            Some("-1.5e3".to_string())
+        );
+        assert_eq!(
+            lookup(json, &".skip.c".parse().unwrap()),
+            Some("null".to_string())
+        );
+        assert_eq!(lookup(json, &".keep[2]".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn reads_whole_documents() {
+        let path = ".".parse().unwrap();
+        assert_eq!(lookup(" 7 ", &path), Some("7".to_string()));
+        assert_eq!(lookup(r#""hi""#, &path), Some("hi".to_string()));
+    }
+
+    #[test]
+    fn parses_paths() {
+        let path: JsonPath = r#".a[2].["b.c"]"#.parse().unwrap();
+        assert_eq!(
+            path.steps,
+            vec![
+                Step::Key("a".to_string()),
+                Step::Index(2),
+                Step::Key("b.c".to_string())
+            ]
+        );
+        assert!(".a.".parse::<JsonPath>().is_err());
+        assert!(".a[x]".parse::<JsonPath>().is_err());
+        assert!(".a[1".parse::<JsonPath>().is_err());
+    }
+}
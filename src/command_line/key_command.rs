@@ -0,0 +1,129 @@
+//! Computes keys by running an external command, for `--key-command`.
+//!
+//! The command reads tokens on its standard input, one per line, and writes each token's key on
+//! its standard output, one per line, in the same order. Starting a process for every token would
+//! be slow, so tokens are sent in batches: [keys] runs the command once per batch.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::key_command;
+//!
+//! let tokens = vec!["apple".to_string(), "Banana".to_string()];
+//! let keys = key_command::keys("sh", "tr a-z A-Z", &tokens).unwrap();
+//! assert_eq!(keys, vec!["APPLE", "BANANA"]);
+//! ```
+
+use crate::command_line::error::GroupByError;
+use crate::command_line::run_command::shell_args;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// The number of tokens that `--key-command` sends to each run of the command, unless
+/// `--key-batch` says otherwise.
+pub const DEFAULT_BATCH: usize = 1000;
+
+/// Runs `command` in `shell`, writing each of `tokens` to its standard input on a line of its own,
+/// and returns the lines it prints, one key per token. Invalid UTF-8 in a key is replaced with
+/// U+FFFD. The command's standard error is passed through.
+///
+/// # Errors
+///
+/// - [GroupByError::InvalidInput] if a token contains a newline, since it would be read as two
+///   tokens, or if the command prints a different number of lines than it was sent.
+/// - [GroupByError::Command] if the command can't be started or exits unsuccessfully.
+pub fn keys(shell: &str, command: &str, tokens: &[String]) -> Result<Vec<String>, GroupByError> {
+    if let Some(token) = tokens.iter().find(|token| token.contains('\n')) {
+        return Err(GroupByError::InvalidInput(format!(
+            "Tokens sent to a key command can't contain newlines, but got: {:?}",
+            token
+        )));
+    }
+
+    let mut child = Command::new(shell)
+        .args(shell_args(command))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(GroupByError::Command)?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+
+    // Write on a thread of our own while reading here, so that neither pipe fills up and stalls
+    // the command.
+    let mut output = vec![];
+    let read = thread::scope(|scope| {
+        scope.spawn(move || {
+            for token in tokens {
+                // A command that stops reading early gets no more tokens, and the count of keys
+                // it prints shows whether anything's missing.
+                if writeln!(stdin, "{}", token).is_err() {
+                    break;
+                }
+            }
+        });
+        stdout.read_to_end(&mut output)
+    });
+    read.map_err(GroupByError::Command)?;
+
+    let status = child.wait().map_err(GroupByError::Command)?;
+    if !status.success() {
+        return Err(GroupByError::Command(io::Error::other(format!(
+            "the key command {} failed with {}",
+            command, status
+        ))));
+    }
+
+    let output = String::from_utf8_lossy(&output);
+    let keys: Vec<String> = output.lines().map(str::to_string).collect();
+    if keys.len() != tokens.len() {
+        return Err(GroupByError::InvalidInput(format!(
+            "The key command {} printed {} lines for {} tokens, but it must print exactly one \
+            key per token",
+            command,
+            keys.len(),
+            tokens.len()
+        )));
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(tokens: &[&str]) -> Vec<String> {
+        tokens.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn keeps_blank_keys() {
+        let keys = keys("sh", "sed 's/[0-9]//g'", &tokens(&["a1", "22", "b3"])).unwrap();
+        assert_eq!(keys, vec!["a", "", "b"]);
+    }
+
+    #[test]
+    fn rejects_the_wrong_number_of_keys() {
+        assert!(matches!(
+            keys("sh", "head -n 1", &tokens(&["a", "b"])),
+            Err(GroupByError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_failed_commands() {
+        assert!(matches!(
+            keys("sh", "cat; exit 3", &tokens(&["a"])),
+            Err(GroupByError::Command(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_tokens_with_newlines() {
+        assert!(matches!(
+            keys("sh", "cat", &tokens(&["a\nb"])),
+            Err(GroupByError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,435 @@
+//! A tiny expression language for computing keys, for rules that no single grouper covers.
+//!
+//! A [KeyExpr] is a function call, a string, or `line`, the token being grouped. Functions take
+//! strings and return strings, so calls nest, e.g. `lower(field(2, ':'))` for the second
+//! `:`-separated field, lowercased. Functions whose last argument is the string to work on may
+//! leave it out to work on `line`. When there's nothing to return, e.g. a field past the end of
+//! the line, a function returns `""`.
+//!
+//! | Function                  | Returns                                                          |
+//! |---------------------------|------------------------------------------------------------------|
+//! | `field(n, delim[, s])`    | The `n`th field of `s`, counting from 1, split at `delim`        |
+//! | `word(n[, s])`            | The `n`th word of `s`, counting from 1, split at whitespace      |
+//! | `first(n[, s])`           | The first `n` characters of `s`                                  |
+//! | `last(n[, s])`            | The last `n` characters of `s`                                   |
+//! | `regex(pattern[, s])`     | Capture group 1 of `pattern` in `s`, or the whole match          |
+//! | `ext([s])`                | The file extension of `s`                                        |
+//! | `basename([s])`           | The final component of the path `s`                              |
+//! | `dirname([s])`            | Everything but the final component of the path `s`               |
+//! | `host([s])`               | The host of the URL `s`                                          |
+//! | `lower(s)`, `upper(s)`    | `s` in lowercase or uppercase                                    |
+//! | `trim(s)`                 | `s` without leading or trailing whitespace                       |
+//! | `replace(s, from, to)`    | `s` with every `from` replaced by `to`                           |
+//! | `concat(s, ...)`          | Every argument, joined together                                  |
+//! | `or(s, ...)`              | The first argument that isn't `""`                               |
+//!
+//! `n` must be a number, and `delim`, `pattern`, `from`, and `to` must be strings, written in
+//! single or double quotes. Inside quotes, `\'`, `\"`, and `\\` stand for the quote or backslash
+//! itself, and `\n` and `\t` for a newline and a tab. Other backslashes are kept as written, so
+//! regular expressions like `regex('\d+')` need no extra escaping.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::key_expr::KeyExpr;
+//!
+//! let expr: KeyExpr = "lower(field(2, ':'))".parse().unwrap();
+//! assert_eq!(expr.evaluate("alice:ADMIN:/bin/sh"), "admin");
+//!
+//! let expr: KeyExpr = r#"or(regex("user=(\w+)"), "anonymous")"#.parse().unwrap();
+//! assert_eq!(expr.evaluate("GET / user=bob"), "bob");
+//! assert_eq!(expr.evaluate("GET /"), "anonymous");
+//! ```
+
+use crate::command_line::options::CaptureGroup;
+use crate::matchers::string::*;
+use regex::Regex;
+use std::str::FromStr;
+
+/// A parsed key expression. See the [module documentation](self) for the language.
+#[derive(Clone, Debug)]
+pub struct KeyExpr {
+    source: String,
+    expr: Expr,
+}
+
+// A node of a parsed expression. Arguments that must be literals are stored already parsed.
+#[derive(Clone, Debug)]
+enum Expr {
+    Line,
+    Literal(String),
+    Field(usize, String, Box<Expr>),
+    Word(usize, Box<Expr>),
+    First(usize, Box<Expr>),
+    Last(usize, Box<Expr>),
+    Regex(Regex, Box<Expr>),
+    Ext(Box<Expr>),
+    Basename(Box<Expr>),
+    Dirname(Box<Expr>),
+    Host(Box<Expr>),
+    Lower(Box<Expr>),
+    Upper(Box<Expr>),
+    Trim(Box<Expr>),
+    Replace(Box<Expr>, String, String),
+    Concat(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+impl KeyExpr {
+    /// Returns the expression as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the key that the expression computes for `line`.
+    pub fn evaluate(&self, line: &str) -> String {
+        self.expr.evaluate(line)
+    }
+}
+
+impl PartialEq for KeyExpr {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for KeyExpr {}
+
+impl FromStr for KeyExpr {
+    type Err = String;
+
+    /// Parses an expression.
+    ///
+    /// ```
+    /// use groupby::command_line::key_expr::KeyExpr;
+    ///
+    /// assert!("lower(field(2, ':'))".parse::<KeyExpr>().is_ok());
+    /// assert!("concat(ext(), '/', first(1))".parse::<KeyExpr>().is_ok());
+    /// assert!("frob(line)".parse::<KeyExpr>().is_err());
+    /// assert!("field('2', ':')".parse::<KeyExpr>().is_err());
+    /// assert!("lower(line".parse::<KeyExpr>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { source: s, rest: s };
+        let expr = parser.expr()?;
+        parser.skip_whitespace();
+        if !parser.rest.is_empty() {
+            return Err(parser.error("Expected the end of the expression"));
+        }
+        Ok(KeyExpr {
+            source: s.to_string(),
+            expr,
+        })
+    }
+}
+
+impl Expr {
+    fn evaluate(&self, line: &str) -> String {
+        let optional = |key: Option<&str>| key.unwrap_or_default().to_string();
+        match self {
+            Expr::Line => line.to_string(),
+            Expr::Literal(s) => s.clone(),
+            Expr::Field(n, delim, s) => optional(match_field(&s.evaluate(line), delim, *n)),
+            Expr::Word(n, s) => {
+                let s = s.evaluate(line);
+                optional(n.checked_sub(1).and_then(|i| s.split_whitespace().nth(i)))
+            }
+            Expr::First(n, s) => match_first_n_chars(&s.evaluate(line), *n).to_string(),
+            Expr::Last(n, s) => match_last_n_chars(&s.evaluate(line), *n).to_string(),
+            Expr::Regex(re, s) => {
+                let s = s.evaluate(line);
+                optional(match_regex(&s, re, &CaptureGroup::Default).as_deref())
+            }
+            Expr::Ext(s) => optional(match_file_extension(&s.evaluate(line))),
+            Expr::Basename(s) => optional(match_basename(&s.evaluate(line))),
+            Expr::Dirname(s) => optional(match_dirname(&s.evaluate(line))),
+            Expr::Host(s) => optional(match_url_host(&s.evaluate(line))),
+            Expr::Lower(s) => s.evaluate(line).to_lowercase(),
+            Expr::Upper(s) => s.evaluate(line).to_uppercase(),
+            Expr::Trim(s) => s.evaluate(line).trim().to_string(),
+            Expr::Replace(s, from, to) => s.evaluate(line).replace(from.as_str(), to),
+            Expr::Concat(parts) => parts.iter().map(|part| part.evaluate(line)).collect(),
+            Expr::Or(choices) => choices
+                .iter()
+                .map(|choice| choice.evaluate(line))
+                .find(|key| !key.is_empty())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// A recursive-descent parser over the text that's left to parse.
+struct Parser<'a> {
+    source: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, problem: &str) -> String {
+        let position = self.source.len() - self.rest.len();
+        format!(
+            "{} at position {} of the key expression: {}",
+            problem,
+            position + 1,
+            self.source
+        )
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    // Skips whitespace, then consumes c if it's next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        self.skip_whitespace();
+        match self.rest.chars().next() {
+            Some('"' | '\'') => Ok(Expr::Literal(self.string()?)),
+            Some(c) if c.is_ascii_alphabetic() => self.call(),
+            Some(c) if c.is_ascii_digit() => Err(self.error("Expected a string, not a number")),
+            _ => Err(self.error("Expected a function call, a string, or line")),
+        }
+    }
+
+    // Parses line, or a function call and its arguments.
+    fn call(&mut self) -> Result<Expr, String> {
+        let start = self.rest;
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(self.rest.len());
+        let name = &self.rest[..end];
+        self.rest = &self.rest[end..];
+        if name == "line" {
+            return Ok(Expr::Line);
+        }
+
+        if !self.eat('(') {
+            self.rest = start;
+            return Err(self.error(&format!("Expected ( after {}", name)));
+        }
+        let mut args = Args {
+            parser: self,
+            name,
+            count: 0,
+        };
+        let expr = match name {
+            "field" => Expr::Field(args.number()?, args.string()?, args.optional()?),
+            "word" => Expr::Word(args.number()?, args.optional()?),
+            "first" => Expr::First(args.number()?, args.optional()?),
+            "last" => Expr::Last(args.number()?, args.optional()?),
+            "regex" => {
+                let pattern = args.string()?;
+                let regex = Regex::new(&pattern).map_err(|e| args.parser.error(&e.to_string()))?;
+                Expr::Regex(regex, args.optional()?)
+            }
+            "ext" => Expr::Ext(args.optional()?),
+            "basename" => Expr::Basename(args.optional()?),
+            "dirname" => Expr::Dirname(args.optional()?),
+            "host" => Expr::Host(args.optional()?),
+            "lower" => Expr::Lower(Box::new(args.expr()?)),
+            "upper" => Expr::Upper(Box::new(args.expr()?)),
+            "trim" => Expr::Trim(Box::new(args.expr()?)),
+            "replace" => Expr::Replace(Box::new(args.expr()?), args.string()?, args.string()?),
+            "concat" => Expr::Concat(args.rest()?),
+            "or" => Expr::Or(args.rest()?),
+            _ => {
+                args.parser.rest = start;
+                return Err(args.parser.error(&format!("Unknown function {}", name)));
+            }
+        };
+        if !args.parser.eat(')') {
+            return Err(args
+                .parser
+                .error(&format!("Too many arguments to {}", name)));
+        }
+        Ok(expr)
+    }
+
+    // Parses a quoted string, unescaping it.
+    fn string(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let mut chars = self.rest.char_indices();
+        let quote = match chars.next() {
+            Some((_, c @ ('"' | '\''))) => c,
+            _ => return Err(self.error("Expected a string in quotes")),
+        };
+        let mut string = String::new();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c == quote => {
+                    self.rest = &self.rest[i + c.len_utf8()..];
+                    return Ok(string);
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => string.push('\n'),
+                    Some((_, 't')) => string.push('\t'),
+                    Some((_, c @ ('\'' | '"' | '\\'))) => string.push(c),
+                    Some((_, c)) => {
+                        string.push('\\');
+                        string.push(c);
+                    }
+                    None => break,
+                },
+                c => string.push(c),
+            }
+        }
+        Err(self.error("Expected a closing quote for the string"))
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        self.skip_whitespace();
+        let end = self
+            .rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+        let n = self.rest[..end]
+            .parse()
+            .map_err(|_| self.error("Expected a number"))?;
+        self.rest = &self.rest[end..];
+        Ok(n)
+    }
+}
+
+// The arguments to one function call, parsed one at a time.
+struct Args<'p, 'a> {
+    parser: &'p mut Parser<'a>,
+    name: &'a str,
+    count: usize,
+}
+
+impl<'p, 'a> Args<'p, 'a> {
+    // Consumes the comma before every argument but the first. Returns false if the arguments
+    // have ended instead.
+    fn next(&mut self) -> bool {
+        self.parser.skip_whitespace();
+        if self.parser.rest.starts_with(')') {
+            return false;
+        }
+        if self.count > 0 && !self.parser.eat(',') {
+            return false;
+        }
+        self.count += 1;
+        true
+    }
+
+    fn missing(&self) -> String {
+        self.parser
+            .error(&format!("Too few arguments to {}", self.name))
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        if !self.next() {
+            return Err(self.missing());
+        }
+        self.parser.number()
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if !self.next() {
+            return Err(self.missing());
+        }
+        self.parser.string()
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        if !self.next() {
+            return Err(self.missing());
+        }
+        self.parser.expr()
+    }
+
+    // Parses the string to work on, which defaults to line.
+    fn optional(&mut self) -> Result<Box<Expr>, String> {
+        if !self.next() {
+            return Ok(Box::new(Expr::Line));
+        }
+        self.parser.expr().map(Box::new)
+    }
+
+    // Parses one or more arguments, up to the closing parenthesis.
+    fn rest(&mut self) -> Result<Vec<Expr>, String> {
+        let mut exprs = vec![self.expr()?];
+        while self.next() {
+            exprs.push(self.parser.expr()?);
+        }
+        Ok(exprs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(expr: &str, line: &str) -> String {
+        expr.parse::<KeyExpr>().unwrap().evaluate(line)
+    }
+
+    #[test]
+    fn evaluates_every_function() {
+        let path = "https://example.com/docs/Guide.PDF";
+        assert_eq!(evaluate("field(2, ',')", "a,b,c"), "b");
+        assert_eq!(evaluate("field(4, ',')", "a,b,c"), "");
+        assert_eq!(evaluate("word(2)", "  GET   /index "), "/index");
+        assert_eq!(evaluate("word(0)", "GET /"), "");
+        assert_eq!(evaluate("first(3)", "abcdef"), "abc");
+        assert_eq!(evaluate("last(2)", "abcdef"), "ef");
+        assert_eq!(evaluate("regex('(\\d+)ms')", "took 35ms"), "35");
+        assert_eq!(evaluate("regex('x')", "abc"), "");
+        assert_eq!(evaluate("lower(ext())", path), "pdf");
+        assert_eq!(evaluate("basename()", path), "Guide.PDF");
+        assert_eq!(evaluate("dirname(line)", "src/lib.rs"), "src");
+        assert_eq!(evaluate("host()", path), "example.com");
+        assert_eq!(evaluate("upper(trim(' a b '))", ""), "A B");
+        assert_eq!(evaluate("replace(line, '-', '')", "2024-01-02"), "20240102");
+        assert_eq!(
+            evaluate("concat(first(1), \"\\t\", last(1))", "xyz"),
+            "x\tz"
+        );
+        assert_eq!(
+            evaluate("or(field(3, ':'), field(1, ':'), 'none')", "a:b"),
+            "a"
+        );
+        assert_eq!(evaluate("or(field(3, ':'), 'none')", "a:b"), "none");
+    }
+
+    #[test]
+    fn applies_functions_to_their_last_argument() {
+        assert_eq!(evaluate("field(2, '/', field(1, ' '))", "a/b c/d"), "b");
+        assert_eq!(evaluate("first(1, word(2))", "GET /index"), "/");
+    }
+
+    #[test]
+    fn reports_where_expressions_go_wrong() {
+        let error = |expr: &str| expr.parse::<KeyExpr>().unwrap_err();
+        assert_eq!(
+            error("lower(frob())"),
+            "Unknown function frob at position 7 of the key expression: lower(frob())"
+        );
+        assert_eq!(
+            error("field(2)"),
+            "Too few arguments to field at position 8 of the key expression: field(2)"
+        );
+        assert_eq!(
+            error("lower(line, line)"),
+            "Too many arguments to lower at position 11 of the key expression: lower(line, line)"
+        );
+        assert!(error("first(x)").starts_with("Expected a number"));
+        assert!(error("lower(3)").starts_with("Expected a string, not a number"));
+        assert!(error("lower('a)").starts_with("Expected a closing quote"));
+        assert!(error("regex('(')").contains("regex parse error"));
+        assert!(error("line line").starts_with("Expected the end of the expression"));
+        assert!(error("upper").starts_with("Expected ( after upper"));
+        assert!(error("").starts_with("Expected a function call, a string, or line"));
+    }
+}
@@ -0,0 +1,647 @@
+//! The [KeyOrder] type, which controls the order in which groups are printed.
+//!
+//! A [GroupedCollection] decides the order in which it iterates over its groups; for instance,
+//! [BTreeMap](std::collections::BTreeMap) iterates in raw byte order of its keys. That's fast and
+//! predictable, but it scatters keys that a human would consider adjacent: `"apple"`, `"Apple"`,
+//! and `"Äpfel"` end up far apart, and `"img10.png"` comes before `"img2.png"`. [KeyOrder] lets
+//! the output layer re-sort groups by key before printing them (or before running commands over
+//! them in sequence).
+//!
+//! [GroupOrder] can then re-sort the groups by size, keeping [KeyOrder] among groups of the same
+//! size.
+//!
+//! [GroupedCollection]: crate::grouped_collections::GroupedCollection
+
+use std::cmp::{Ordering, Reverse};
+
+/// Specifies how to order groups by key for output.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyOrder {
+    /// Raw byte order. Groups are not re-sorted, so they are printed in the order in which the
+    /// [GroupedCollection](crate::grouped_collections::GroupedCollection) iterates; for a
+    /// [BTreeMap](std::collections::BTreeMap), that's byte order.
+    #[default]
+    Bytes,
+
+    /// Case-insensitive, accent-folding collation, similar to a dictionary. See
+    /// [KeyOrder::compare] for details.
+    Collate,
+
+    /// Like [KeyOrder::Collate], but following the alphabet of a particular language, e.g.
+    /// Swedish, in which `"ö"` is a letter of its own that sorts after `"z"`. See [Tailoring].
+    Locale(Tailoring),
+
+    /// Natural or "version" order, which compares runs of digits by their numeric values, e.g.
+    /// `"img2.png"` before `"img10.png"`. See [KeyOrder::compare] for details.
+    Natural,
+
+    /// Numeric order, which compares keys that are whole numbers, e.g. `"2"` before `"10"`, by
+    /// their values, and ranges of numbers, e.g. `"-30--21"`, by their lower bounds. See
+    /// [KeyOrder::compare] for details.
+    Numeric,
+}
+
+impl KeyOrder {
+    /// Compares two keys according to this order.
+    ///
+    /// [KeyOrder::Collate] compares keys in three passes, moving on to the next pass only if the
+    /// keys are still equal:
+    ///
+    /// 1. Compare base letters, ignoring case and accents, e.g. `"Äpfel"` as `"apfel"`.
+    /// 1. Compare accents, ignoring case, e.g. `"apfel"` before `"äpfel"`.
+    /// 1. Compare case, with lowercase first, e.g. `"apple"` before `"Apple"`.
+    ///
+    /// If all three passes find the keys equal, it falls back on byte order, so the order is total.
+    ///
+    /// Accent folding covers the Latin-1 Supplement and Latin Extended-A blocks, which include the
+    /// accented letters of most European languages.
+    ///
+    /// [KeyOrder::Locale] works the same way, except that the letters that its [Tailoring] treats
+    /// as separate letters of the alphabet are not folded in the first pass; instead, each sorts
+    /// as its own letter in its place in that alphabet.
+    ///
+    /// [KeyOrder::Natural] splits keys into runs of ASCII digits and runs of everything else, then
+    /// compares the runs pairwise. Digit runs compare by numeric value (of any length), and other
+    /// runs compare by bytes. A digit run sorts before a non-digit run. If the keys are otherwise
+    /// equal, e.g. `"v01"` and `"v1"`, it falls back on byte order, so the order is total.
+    ///
+    /// [KeyOrder::Numeric] compares keys that parse as finite numbers, e.g. `"-3"`, `"10"`, or
+    /// `"2.5"`, by value, ignoring surrounding whitespace. Keys that are ranges of two such numbers
+    /// joined by `-`, e.g. `"-30--21"` or `"100-199"`, as `--bucket-size` makes, compare by their
+    /// lower bounds. Keys that aren't numbers or ranges sort after all keys that are, in byte
+    /// order. Equal values, e.g. `"1"` and `"1.0"`, fall back on byte order, so the order is total.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::key_order::{KeyOrder, Tailoring};
+    /// use std::cmp::{Ordering, Reverse};
+    ///
+    /// assert_eq!(KeyOrder::Bytes.compare("Zebra", "apple"), Ordering::Less);
+    /// assert_eq!(KeyOrder::Collate.compare("Zebra", "apple"), Ordering::Greater);
+    /// assert_eq!(KeyOrder::Collate.compare("Äpfel", "apple"), Ordering::Less);
+    /// assert_eq!(KeyOrder::Collate.compare("apple", "Apple"), Ordering::Less);
+    ///
+    /// let swedish = KeyOrder::Locale(Tailoring::Swedish);
+    /// assert_eq!(KeyOrder::Collate.compare("öl", "zebra"), Ordering::Less);
+    /// assert_eq!(swedish.compare("öl", "zebra"), Ordering::Greater);
+    ///
+    /// assert_eq!(KeyOrder::Bytes.compare("img2.png", "img10.png"), Ordering::Greater);
+    /// assert_eq!(KeyOrder::Natural.compare("img2.png", "img10.png"), Ordering::Less);
+    /// assert_eq!(KeyOrder::Natural.compare("1.10.0", "1.9.3"), Ordering::Greater);
+    ///
+    /// assert_eq!(KeyOrder::Numeric.compare("9", "10"), Ordering::Less);
+    /// assert_eq!(KeyOrder::Numeric.compare("-1.5", "-1"), Ordering::Less);
+    /// assert_eq!(KeyOrder::Numeric.compare("-30--21", "-10--1"), Ordering::Less);
+    /// assert_eq!(KeyOrder::Numeric.compare("n/a", "100"), Ordering::Greater);
+    /// ```
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            KeyOrder::Bytes => a.cmp(b),
+            KeyOrder::Collate => KeyOrder::Locale(Tailoring::Root).compare(a, b),
+            KeyOrder::Locale(tailoring) => {
+                CollationKey::new(a, *tailoring).cmp(&CollationKey::new(b, *tailoring))
+            }
+            KeyOrder::Natural => natural_cmp(a, b),
+            KeyOrder::Numeric => numeric_cmp(a, b),
+        }
+    }
+
+    /// Sorts `(key, group)` pairs by key according to this order.
+    ///
+    /// For [KeyOrder::Bytes], this does nothing, leaving the pairs in the order in which the
+    /// collection provided them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::key_order::KeyOrder;
+    ///
+    /// let mut groups = vec![("Äpfel", 1), ("Apple", 2), ("apple", 3), ("banana", 4)];
+    /// KeyOrder::Collate.sort(&mut groups);
+    /// assert_eq!(
+    ///     groups,
+    ///     vec![("Äpfel", 1), ("apple", 3), ("Apple", 2), ("banana", 4)],
+    /// );
+    ///
+    /// let mut groups = vec![("img10.png", ()), ("img2.png", ()), ("img1.png", ())];
+    /// KeyOrder::Natural.sort(&mut groups);
+    /// assert_eq!(groups, vec![("img1.png", ()), ("img2.png", ()), ("img10.png", ())]);
+    /// ```
+    pub fn sort<K: AsRef<str>, V>(&self, groups: &mut [(K, V)]) {
+        match self {
+            KeyOrder::Bytes => (),
+            KeyOrder::Collate => KeyOrder::Locale(Tailoring::Root).sort(groups),
+            KeyOrder::Locale(tailoring) => {
+                groups.sort_by_cached_key(|(key, _)| CollationKey::new(key.as_ref(), *tailoring))
+            }
+            KeyOrder::Natural => {
+                groups.sort_by(|(a, _), (b, _)| natural_cmp(a.as_ref(), b.as_ref()))
+            }
+            KeyOrder::Numeric => {
+                groups.sort_by(|(a, _), (b, _)| numeric_cmp(a.as_ref(), b.as_ref()))
+            }
+        }
+    }
+}
+
+/// Specifies how to order groups for output: by key alone, or by size.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GroupOrder {
+    /// Order groups by key, as given by a [KeyOrder].
+    #[default]
+    Key,
+
+    /// Smallest groups first.
+    Size,
+
+    /// Largest groups first.
+    SizeDescending,
+}
+
+impl GroupOrder {
+    /// Sorts `(key, group)` pairs, already sorted by key, by the size that `size` gives each group.
+    ///
+    /// The sort is stable, so groups of the same size stay in key order. For [GroupOrder::Key],
+    /// this does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::key_order::GroupOrder;
+    ///
+    /// let mut groups = vec![("a", vec![1, 2]), ("b", vec![3]), ("c", vec![4, 5])];
+    /// GroupOrder::SizeDescending.sort(&mut groups, Vec::len);
+    /// assert_eq!(groups, vec![("a", vec![1, 2]), ("c", vec![4, 5]), ("b", vec![3])]);
+    /// ```
+    pub fn sort<K, V>(&self, groups: &mut [(K, V)], size: impl Fn(&V) -> usize) {
+        match self {
+            GroupOrder::Key => (),
+            GroupOrder::Size => groups.sort_by_key(|(_, group)| size(group)),
+            GroupOrder::SizeDescending => groups.sort_by_key(|(_, group)| Reverse(size(group))),
+        }
+    }
+}
+
+/// The alphabet that [KeyOrder::Locale] follows, named for the languages that use it.
+///
+/// Each tailoring lists the letters that its languages treat as separate letters of the alphabet,
+/// rather than as accented forms of another letter, and where they go. Letters not listed are
+/// folded as they are for [KeyOrder::Collate].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Tailoring {
+    /// No special letters; the same as [KeyOrder::Collate].
+    #[default]
+    Root,
+
+    /// Danish and Norwegian: `æ`, `ø`, and `å` follow `z`, in that order. `ä` and `ö` sort
+    /// as `æ` and `ø`.
+    Danish,
+
+    /// Spanish: `ñ` follows `n`.
+    Spanish,
+
+    /// Swedish and Finnish: `å`, `ä`, and `ö` follow `z`, in that order. `æ` and `ø` sort as
+    /// `ä` and `ö`.
+    Swedish,
+}
+
+impl Tailoring {
+    /// Returns the tailoring for a POSIX locale name such as `sv_SE.UTF-8`, which depends only on
+    /// its language code. Languages without a tailoring, as well as `C` and `POSIX`, get
+    /// [Tailoring::Root].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::key_order::Tailoring;
+    ///
+    /// assert_eq!(Tailoring::from_locale("sv_SE.UTF-8"), Tailoring::Swedish);
+    /// assert_eq!(Tailoring::from_locale("nb_NO"), Tailoring::Danish);
+    /// assert_eq!(Tailoring::from_locale("es"), Tailoring::Spanish);
+    /// assert_eq!(Tailoring::from_locale("en_US.UTF-8"), Tailoring::Root);
+    /// assert_eq!(Tailoring::from_locale("C"), Tailoring::Root);
+    /// ```
+    pub fn from_locale(locale: &str) -> Self {
+        let language = locale
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "da" | "nb" | "nn" | "no" => Tailoring::Danish,
+            "es" => Tailoring::Spanish,
+            "fi" | "sv" => Tailoring::Swedish,
+            _ => Tailoring::Root,
+        }
+    }
+
+    /// Returns the tailoring for the locale that the environment chooses for collation: the first
+    /// of `LC_ALL`, `LC_COLLATE`, and `LANG` that is set and not empty, as in POSIX.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_COLLATE", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+            .map_or(Tailoring::Root, |locale| Tailoring::from_locale(&locale))
+    }
+
+    /// Returns the name of the languages that this tailoring is for, e.g. `"Swedish"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tailoring::Root => "language-neutral",
+            Tailoring::Danish => "Danish",
+            Tailoring::Spanish => "Spanish",
+            Tailoring::Swedish => "Swedish",
+        }
+    }
+
+    // Returns the primary weight of c if this tailoring makes it a letter of its own: the letter
+    // that it follows, and its rank among the letters that follow that one.
+    fn letter(&self, c: char) -> Option<(char, u8)> {
+        let c = c.to_lowercase().next().unwrap_or(c);
+        let weight = match (self, c) {
+            (Tailoring::Danish, 'æ' | 'ä') => ('z', 1),
+            (Tailoring::Danish, 'ø' | 'ö') => ('z', 2),
+            (Tailoring::Danish, 'å') => ('z', 3),
+            (Tailoring::Spanish, 'ñ') => ('n', 1),
+            (Tailoring::Swedish, 'å') => ('z', 1),
+            (Tailoring::Swedish, 'ä' | 'æ') => ('z', 2),
+            (Tailoring::Swedish, 'ö' | 'ø') => ('z', 3),
+            _ => return None,
+        };
+        Some(weight)
+    }
+}
+
+// Compares two strings in natural order. See KeyOrder::compare for details.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_runs = Runs::new(a);
+    let mut b_runs = Runs::new(b);
+    loop {
+        let ordering = match (a_runs.next(), b_runs.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(a_run), Some(b_run)) => match (is_digit_run(a_run), is_digit_run(b_run)) {
+                (true, true) => compare_numeric(a_run, b_run),
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                (false, false) => a_run.cmp(b_run),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+// Compares two strings in numeric order. See KeyOrder::compare for details.
+fn numeric_cmp(a: &str, b: &str) -> Ordering {
+    let number = |s: &str| s.parse::<f64>().ok().filter(|value| value.is_finite());
+    let value = |s: &str| {
+        let s = s.trim();
+        number(s).or_else(|| {
+            // A range's separator is the first - after its lower bound's sign, if any.
+            let (lower, upper) = s.get(1..)?.split_once('-')?;
+            let lower = number(&s[..lower.len() + 1])?;
+            number(upper).map(|_| lower)
+        })
+    };
+    let ordering = match (value(a), value(b)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    };
+    ordering.then_with(|| a.cmp(b))
+}
+
+// Compares two runs of ASCII digits by numeric value, without risk of overflow.
+fn compare_numeric(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+
+    // Without leading zeros, a longer number is larger; numbers of equal length compare the same
+    // way as their digits do.
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+fn is_digit_run(run: &str) -> bool {
+    run.starts_with(|c: char| c.is_ascii_digit())
+}
+
+// An iterator over the maximal runs of ASCII digits and non-digits in a string.
+struct Runs<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Runs<'a> {
+    fn new(string: &'a str) -> Self {
+        Runs { rest: string }
+    }
+}
+
+impl<'a> Iterator for Runs<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let digits = is_digit_run(self.rest);
+        let end = self
+            .rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return None;
+        }
+        let (run, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(run)
+    }
+}
+
+// A precomputed sort key for KeyOrder::Collate and KeyOrder::Locale. The derived Ord compares
+// fields in order, which implements the passes described in KeyOrder::compare.
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct CollationKey {
+    // Base letters: lowercase with accents removed. Each is paired with a rank that is zero except
+    // for a tailored letter, which sorts after every other letter with the same base.
+    primary: Vec<(char, u8)>,
+
+    // Lowercase with accents intact.
+    secondary: String,
+
+    // Whether each character is uppercase, so that lowercase sorts first.
+    tertiary: Vec<bool>,
+
+    // The raw key, to break any remaining ties.
+    raw: String,
+}
+
+impl CollationKey {
+    fn new(key: &str, tailoring: Tailoring) -> Self {
+        let mut primary = Vec::with_capacity(key.len());
+        for c in key.chars() {
+            match (tailoring.letter(c), fold_accent(c)) {
+                (Some(weight), _) => primary.push(weight),
+                (None, Some(base)) => primary.extend(base.chars().map(|c| (c, 0))),
+                (None, None) => primary.extend(c.to_lowercase().map(|c| (c, 0))),
+            }
+        }
+
+        CollationKey {
+            primary,
+            secondary: key.to_lowercase(),
+            tertiary: key.chars().map(char::is_uppercase).collect(),
+            raw: key.to_string(),
+        }
+    }
+}
+
+// Returns the lowercase base letters for an accented Latin letter, or None if c isn't one.
+fn fold_accent(c: char) -> Option<&'static str> {
+    let base = match c {
+        // Latin-1 Supplement.
+        'À'..='Å' | 'à'..='å' => "a",
+        'Æ' | 'æ' => "ae",
+        'Ç' | 'ç' => "c",
+        'È'..='Ë' | 'è'..='ë' => "e",
+        'Ì'..='Ï' | 'ì'..='ï' => "i",
+        'Ð' | 'ð' => "d",
+        'Ñ' | 'ñ' => "n",
+        'Ò'..='Ö' | 'Ø' | 'ò'..='ö' | 'ø' => "o",
+        'Ù'..='Ü' | 'ù'..='ü' => "u",
+        'Ý' | 'ý' | 'ÿ' => "y",
+        'Þ' | 'þ' => "th",
+        'ß' => "ss",
+
+        // Latin Extended-A, which is laid out in runs of letters that share a base letter.
+        '\u{100}'..='\u{105}' => "a",
+        '\u{106}'..='\u{10d}' => "c",
+        '\u{10e}'..='\u{111}' => "d",
+        '\u{112}'..='\u{11b}' => "e",
+        '\u{11c}'..='\u{123}' => "g",
+        '\u{124}'..='\u{127}' => "h",
+        '\u{128}'..='\u{131}' => "i",
+        '\u{132}'..='\u{133}' => "ij",
+        '\u{134}'..='\u{135}' => "j",
+        '\u{136}'..='\u{138}' => "k",
+        '\u{139}'..='\u{142}' => "l",
+        '\u{143}'..='\u{14b}' => "n",
+        '\u{14c}'..='\u{151}' => "o",
+        '\u{152}'..='\u{153}' => "oe",
+        '\u{154}'..='\u{159}' => "r",
+        '\u{15a}'..='\u{161}' => "s",
+        '\u{162}'..='\u{167}' => "t",
+        '\u{168}'..='\u{173}' => "u",
+        '\u{174}'..='\u{175}' => "w",
+        '\u{176}'..='\u{178}' => "y",
+        '\u{179}'..='\u{17e}' => "z",
+        '\u{17f}' => "s",
+
+        _ => return None,
+    };
+    Some(base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod compare {
+        use super::*;
+
+        #[test]
+        fn collate_ignores_case_and_accents_first() {
+            assert_eq!(
+                KeyOrder::Collate.compare("éclair", "Eclipse"),
+                Ordering::Less
+            );
+            assert_eq!(
+                KeyOrder::Collate.compare("Straße", "strasse"),
+                Ordering::Greater
+            );
+            assert_eq!(KeyOrder::Collate.compare("Łódź", "lodz"), Ordering::Greater);
+            assert_eq!(
+                KeyOrder::Collate.compare("Œuvre", "oeuvre"),
+                Ordering::Greater
+            );
+        }
+
+        #[test]
+        fn locale_places_tailored_letters() {
+            let danish = KeyOrder::Locale(Tailoring::Danish);
+            assert_eq!(danish.compare("Åse", "Øre"), Ordering::Greater);
+            assert_eq!(danish.compare("ære", "øre"), Ordering::Less);
+            assert_eq!(danish.compare("ärlig", "æble"), Ordering::Greater);
+
+            let spanish = KeyOrder::Locale(Tailoring::Spanish);
+            assert_eq!(spanish.compare("ñu", "nz"), Ordering::Greater);
+            assert_eq!(spanish.compare("ñu", "o"), Ordering::Less);
+            assert_eq!(spanish.compare("Ñandú", "ñandu"), Ordering::Greater);
+
+            let swedish = KeyOrder::Locale(Tailoring::Swedish);
+            assert_eq!(swedish.compare("åsna", "älg"), Ordering::Less);
+            assert_eq!(swedish.compare("älg", "öga"), Ordering::Less);
+            assert_eq!(swedish.compare("über", "zon"), Ordering::Less);
+        }
+
+        #[test]
+        fn locale_root_matches_collate() {
+            for (a, b) in [
+                ("Äpfel", "apple"),
+                ("öl", "zebra"),
+                ("ñu", "nz"),
+                ("x", "X"),
+            ] {
+                assert_eq!(
+                    KeyOrder::Locale(Tailoring::Root).compare(a, b),
+                    KeyOrder::Collate.compare(a, b)
+                );
+            }
+        }
+
+        #[test]
+        fn natural_compares_digit_runs_numerically() {
+            assert_eq!(KeyOrder::Natural.compare("a9", "a10"), Ordering::Less);
+            assert_eq!(KeyOrder::Natural.compare("a10b2", "a10b10"), Ordering::Less);
+            assert_eq!(KeyOrder::Natural.compare("a010", "a9"), Ordering::Greater);
+            assert_eq!(
+                KeyOrder::Natural.compare("99999999999999999999999", "100000000000000000000000"),
+                Ordering::Less
+            );
+        }
+
+        #[test]
+        fn natural_handles_prefixes_and_mixed_runs() {
+            assert_eq!(KeyOrder::Natural.compare("", "a"), Ordering::Less);
+            assert_eq!(KeyOrder::Natural.compare("file", "file1"), Ordering::Less);
+            assert_eq!(KeyOrder::Natural.compare("1file", "file"), Ordering::Less);
+        }
+
+        #[test]
+        fn natural_is_total() {
+            assert_eq!(KeyOrder::Natural.compare("v1", "v1"), Ordering::Equal);
+            assert_eq!(KeyOrder::Natural.compare("v01", "v1"), Ordering::Less);
+        }
+
+        #[test]
+        fn numeric_compares_numbers_by_value() {
+            assert_eq!(KeyOrder::Numeric.compare("2", "10"), Ordering::Less);
+            assert_eq!(KeyOrder::Numeric.compare("-10", "-2"), Ordering::Less);
+            assert_eq!(KeyOrder::Numeric.compare("2.5", "2.25"), Ordering::Greater);
+            assert_eq!(KeyOrder::Numeric.compare(" 3", "20"), Ordering::Less);
+        }
+
+        #[test]
+        fn numeric_compares_ranges_by_lower_bound() {
+            assert_eq!(
+                KeyOrder::Numeric.compare("-30--21", "-10--1"),
+                Ordering::Less
+            );
+            assert_eq!(KeyOrder::Numeric.compare("-10--1", "0-9"), Ordering::Less);
+            assert_eq!(
+                KeyOrder::Numeric.compare("90-99", "100-199"),
+                Ordering::Less
+            );
+            assert_eq!(KeyOrder::Numeric.compare("5", "10-19"), Ordering::Less);
+            assert_eq!(
+                KeyOrder::Numeric.compare("2024-03-09", "0"),
+                Ordering::Greater
+            );
+            assert_eq!(KeyOrder::Numeric.compare("1-", "0"), Ordering::Greater);
+        }
+
+        #[test]
+        fn numeric_puts_non_numbers_last() {
+            assert_eq!(KeyOrder::Numeric.compare("1000", "a"), Ordering::Less);
+            assert_eq!(KeyOrder::Numeric.compare("", "0"), Ordering::Greater);
+            assert_eq!(KeyOrder::Numeric.compare("NaN", "1"), Ordering::Greater);
+            assert_eq!(KeyOrder::Numeric.compare("b", "a"), Ordering::Greater);
+        }
+
+        #[test]
+        fn numeric_is_total() {
+            assert_eq!(KeyOrder::Numeric.compare("1", "1"), Ordering::Equal);
+            assert_eq!(KeyOrder::Numeric.compare("1.0", "1"), Ordering::Greater);
+        }
+
+        #[test]
+        fn collate_is_total() {
+            assert_eq!(KeyOrder::Collate.compare("same", "same"), Ordering::Equal);
+            assert_ne!(KeyOrder::Collate.compare("ß", "ss"), Ordering::Equal);
+        }
+    }
+
+    mod sort {
+        use super::*;
+
+        #[test]
+        fn bytes_preserves_order() {
+            let mut groups = vec![("b", ()), ("a", ()), ("C", ())];
+            KeyOrder::Bytes.sort(&mut groups);
+            assert_eq!(groups, vec![("b", ()), ("a", ()), ("C", ())]);
+        }
+
+        #[test]
+        fn collate_sorts_by_collation() {
+            let mut groups = vec![("b", ()), ("Ä", ()), ("a", ()), ("C", ()), ("A", ())];
+            KeyOrder::Collate.sort(&mut groups);
+            assert_eq!(
+                groups,
+                vec![("a", ()), ("A", ()), ("Ä", ()), ("b", ()), ("C", ())]
+            );
+        }
+
+        #[test]
+        fn locale_sorts_by_tailored_alphabet() {
+            let mut groups = vec![("ö", ()), ("z", ()), ("Ä", ()), ("a", ()), ("å", ())];
+            KeyOrder::Locale(Tailoring::Swedish).sort(&mut groups);
+            assert_eq!(
+                groups,
+                vec![("a", ()), ("z", ()), ("å", ()), ("Ä", ()), ("ö", ())]
+            );
+        }
+
+        #[test]
+        fn numeric_sorts_by_value() {
+            let mut groups = vec![("10", ()), ("9", ()), ("x", ()), ("1", ()), ("-1", ())];
+            KeyOrder::Numeric.sort(&mut groups);
+            assert_eq!(
+                groups,
+                vec![("-1", ()), ("1", ()), ("9", ()), ("10", ()), ("x", ())]
+            );
+        }
+    }
+
+    mod group_order {
+        use super::*;
+
+        fn groups() -> Vec<(&'static str, usize)> {
+            vec![("a", 2), ("b", 1), ("c", 3), ("d", 1)]
+        }
+
+        #[test]
+        fn key_preserves_order() {
+            let mut groups = groups();
+            GroupOrder::Key.sort(&mut groups, |size| *size);
+            assert_eq!(groups, self::groups());
+        }
+
+        #[test]
+        fn size_sorts_smallest_first_keeping_key_order() {
+            let mut groups = groups();
+            GroupOrder::Size.sort(&mut groups, |size| *size);
+            assert_eq!(groups, vec![("b", 1), ("d", 1), ("a", 2), ("c", 3)]);
+        }
+
+        #[test]
+        fn size_descending_sorts_largest_first_keeping_key_order() {
+            let mut groups = groups();
+            GroupOrder::SizeDescending.sort(&mut groups, |size| *size);
+            assert_eq!(groups, vec![("c", 3), ("a", 2), ("b", 1), ("d", 1)]);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! Label rules, which coalesce raw group keys into coarser, named groups.
+//!
+//! A grouper produces a raw key for each token, e.g. its first character or its file extension.
+//! Label rules then map raw keys onto human-chosen labels, so that, for instance, every key from
+//! `a` through `m` lands in a group named `first-half`. Keys that no rule matches keep their raw
+//! key.
+//!
+//! # Rule syntax
+//!
+//! Each rule has the form `PATTERN:LABEL`. The label is everything after the last colon, so
+//! patterns may contain colons but labels may not. The pattern is either:
+//!
+//! - A range, `LOW-HIGH`, which matches keys that sort between `LOW` and `HIGH` (inclusive) when
+//!   compared against the key's leading characters. For instance, `a-m` matches `apple` and
+//!   `mango` but not `nectarine`, and `100-199` matches every key starting with a number in that
+//!   range of the same length. Either bound may be empty to leave that end of the range open.
+//! - A prefix, which matches keys that start with the pattern. Because any pattern containing a
+//!   hyphen is a range, prefixes cannot contain hyphens.
+//!
+//! Rules are tried in the order given; the first matching rule wins.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::labels::*;
+//! use groupby::grouped_collections::GroupedCollection;
+//! use groupby::groupers::string::Groupers;
+//! use std::collections::BTreeMap;
+//!
+//! let labels = Labels::new(vec![
+//!     "a-m:first-half".parse().unwrap(),
+//!     "n-z:second-half".parse().unwrap(),
+//! ]);
+//!
+//! let mut map = BTreeMap::new();
+//! let mut labeled = Labeled::new(&mut map, &labels);
+//! labeled.group_by_first_chars("apple".to_string(), 1);
+//! labeled.group_by_first_chars("zebra".to_string(), 1);
+//! labeled.group_by_first_chars("42".to_string(), 1);
+//!
+//! assert_eq!(map.get(&"first-half".to_string()), Some(&vec!["apple".to_string()]));
+//! assert_eq!(map.get(&"second-half".to_string()), Some(&vec!["zebra".to_string()]));
+//! assert_eq!(map.get(&"4".to_string()), Some(&vec!["42".to_string()]));
+//! ```
+
+use crate::grouped_collections::GroupedCollection;
+use std::str::FromStr;
+
+/// The part of a [LabelRule] that decides which keys it matches.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LabelPattern {
+    /// Matches keys whose leading characters fall between the two bounds, inclusive. An empty
+    /// bound leaves that end of the range open.
+    Range(String, String),
+
+    /// Matches keys that start with this string.
+    Prefix(String),
+}
+
+impl LabelPattern {
+    /// Returns true if `key` matches this pattern.
+    ///
+    /// ```
+    /// use groupby::command_line::labels::LabelPattern;
+    ///
+    /// let range = LabelPattern::Range("b".to_string(), "d".to_string());
+    /// assert!(range.matches("bat"));
+    /// assert!(range.matches("dog"));
+    /// assert!(!range.matches("apple"));
+    /// assert!(!range.matches("eel"));
+    ///
+    /// let prefix = LabelPattern::Prefix("img".to_string());
+    /// assert!(prefix.matches("img_0001"));
+    /// assert!(!prefix.matches("doc_0001"));
+    /// ```
+    pub fn matches(&self, key: &str) -> bool {
+        match self {
+            LabelPattern::Range(low, high) => {
+                (low.is_empty() || leading(key, low) >= low.as_str())
+                    && (high.is_empty() || leading(key, high) <= high.as_str())
+            }
+            LabelPattern::Prefix(prefix) => key.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+// Returns as many leading characters of key as bound has, so that a bound like "m" compares
+// against "mango" as "m" rather than sorting before it.
+fn leading<'k>(key: &'k str, bound: &str) -> &'k str {
+    let n = bound.chars().count();
+    match key.char_indices().nth(n) {
+        Some((i, _)) => &key[..i],
+        None => key,
+    }
+}
+
+/// A single label rule: keys matching `pattern` are placed in the group named `label`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LabelRule {
+    pub pattern: LabelPattern,
+    pub label: String,
+}
+
+impl FromStr for LabelRule {
+    type Err = String;
+
+    /// Parses a rule written as `PATTERN:LABEL`. See the [module documentation](self) for the
+    /// syntax.
+    ///
+    /// ```
+    /// use groupby::command_line::labels::*;
+    ///
+    /// let rule: LabelRule = "a-m:first-half".parse().unwrap();
+    /// assert_eq!(rule.pattern, LabelPattern::Range("a".to_string(), "m".to_string()));
+    /// assert_eq!(rule.label, "first-half");
+    ///
+    /// let rule: LabelRule = "img:images".parse().unwrap();
+    /// assert_eq!(rule.pattern, LabelPattern::Prefix("img".to_string()));
+    ///
+    /// assert!("no-label".parse::<LabelRule>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, label) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Expected a label rule like PATTERN:LABEL, but got: {}", s))?;
+
+        let pattern = match pattern.split_once('-') {
+            Some((low, high)) => LabelPattern::Range(low.to_string(), high.to_string()),
+            None => LabelPattern::Prefix(pattern.to_string()),
+        };
+
+        Ok(LabelRule {
+            pattern,
+            label: label.to_string(),
+        })
+    }
+}
+
+/// An ordered list of [LabelRule] values. The default value has no rules, so it leaves every key
+/// unchanged.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Labels {
+    rules: Vec<LabelRule>,
+}
+
+impl Labels {
+    pub fn new(rules: Vec<LabelRule>) -> Self {
+        Labels { rules }
+    }
+
+    /// Returns the rules, in the order they're tried.
+    pub fn rules(&self) -> &[LabelRule] {
+        &self.rules
+    }
+
+    /// Returns true if there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns the label of the first rule that matches `key`, or `key` itself if none match.
+    ///
+    /// ```
+    /// use groupby::command_line::labels::*;
+    ///
+    /// let labels = Labels::new(vec!["jp:images".parse().unwrap()]);
+    /// assert_eq!(labels.apply("jpg".to_string()), "images");
+    /// assert_eq!(labels.apply("txt".to_string()), "txt");
+    /// ```
+    pub fn apply(&self, key: String) -> String {
+        match self.rules.iter().find(|rule| rule.pattern.matches(&key)) {
+            Some(rule) => rule.label.clone(),
+            None => key,
+        }
+    }
+}
+
+/// Wraps a [GroupedCollection], applying [Labels] to every key added through it.
+///
+/// Because it is itself a [GroupedCollection], it works with every grouper and with
+/// [Runner](crate::groupers::string::Runner). Reads pass straight through to the wrapped
+/// collection, so they see labeled keys.
+pub struct Labeled<'a, Map> {
+    map: &'a mut Map,
+    labels: &'a Labels,
+}
+
+impl<'a, Map> Labeled<'a, Map> {
+    pub fn new(map: &'a mut Map, labels: &'a Labels) -> Self {
+        Labeled { map, labels }
+    }
+}
+
+impl<'s, 'a, Map, List> GroupedCollection<'s, String, String, List> for Labeled<'a, Map>
+where
+    Map: for<'t> GroupedCollection<'t, String, String, List>,
+    List: 'static,
+{
+    type Iter = <Map as GroupedCollection<'s, String, String, List>>::Iter;
+    type IntoIter = <Map as GroupedCollection<'s, String, String, List>>::IntoIter;
+    type Entry = <Map as GroupedCollection<'s, String, String, List>>::Entry;
+
+    fn add(&mut self, key: String, value: String) {
+        self.map.add(self.labels.apply(key), value)
+    }
+
+    fn entry(&'s mut self, key: String) -> Self::Entry {
+        let key = self.labels.apply(key);
+        <Map as GroupedCollection<'s, String, String, List>>::entry(self.map, key)
+    }
+
+    fn get(&'s self, key: &String) -> Option<&'s List> {
+        (*self.map).get(key)
+    }
+
+    fn remove(&mut self, key: &String) -> Option<List> {
+        self.map.remove(key)
+    }
+
+    fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&String, &mut List) -> bool,
+    {
+        self.map.retain(keep)
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn contains_key(&self, key: &String) -> bool {
+        self.map.contains_key(key)
+    }
+
+    fn iter(&'s self) -> Self::Iter {
+        (*self.map).iter()
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        <Map as GroupedCollection<'s, String, String, List>>::drain(self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod label_pattern {
+        use super::*;
+
+        fn range(low: &str, high: &str) -> LabelPattern {
+            LabelPattern::Range(low.to_string(), high.to_string())
+        }
+
+        #[test]
+        fn range_compares_leading_characters() {
+            let pattern = range("100", "199");
+            assert!(pattern.matches("100"));
+            assert!(pattern.matches("150abc"));
+            assert!(pattern.matches("199999"));
+            assert!(!pattern.matches("099"));
+            assert!(!pattern.matches("200"));
+        }
+
+        #[test]
+        fn range_with_empty_bound_is_open() {
+            assert!(range("", "m").matches(""));
+            assert!(range("", "m").matches("apple"));
+            assert!(!range("", "m").matches("nectarine"));
+            assert!(range("n", "").matches("zebra"));
+            assert!(!range("n", "").matches("mango"));
+        }
+
+        #[test]
+        fn range_handles_short_keys() {
+            assert!(!range("ba", "bz").matches("b"));
+            assert!(range("a", "bz").matches("b"));
+        }
+    }
+
+    mod label_rule {
+        use super::*;
+
+        #[test]
+        fn splits_on_last_colon() {
+            let rule: LabelRule = "12:00-12:59:noon".parse().unwrap();
+            assert_eq!(
+                rule.pattern,
+                LabelPattern::Range("12:00".to_string(), "12:59".to_string())
+            );
+            assert_eq!(rule.label, "noon");
+        }
+
+        #[test]
+        fn allows_empty_label() {
+            let rule: LabelRule = "tmp:".parse().unwrap();
+            assert_eq!(rule.label, "");
+        }
+    }
+
+    mod labels {
+        use super::*;
+
+        #[test]
+        fn first_matching_rule_wins() {
+            let labels = Labels::new(vec![
+                "a-c:early".parse().unwrap(),
+                "b:bees".parse().unwrap(),
+            ]);
+            assert_eq!(labels.apply("bee".to_string()), "early");
+        }
+
+        #[test]
+        fn default_leaves_keys_unchanged() {
+            let labels = Labels::default();
+            assert!(labels.is_empty());
+            assert_eq!(labels.apply("key".to_string()), "key");
+        }
+    }
+
+    mod labeled {
+        use super::*;
+        use crate::grouped_collections::fake_map::*;
+
+        #[test]
+        fn add_applies_labels() {
+            let labels = Labels::new(vec!["a-m:first-half".parse().unwrap()]);
+            let mut map = FakeMap::new();
+            let mut labeled = Labeled::new(&mut map, &labels);
+            labeled.add("apple".to_string(), "1".to_string());
+            labeled.add("zebra".to_string(), "2".to_string());
+            assert_eq!(*map.calls(), vec!["first-half:1", "zebra:2"]);
+        }
+    }
+}
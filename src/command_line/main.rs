@@ -0,0 +1,186 @@
+//! The whole `groupby` command-line program, as a library function.
+//!
+//! [main()] parses the command line, reads input, and writes results just as the `groupby` binary
+//! does; the binary is a thin wrapper around it. A program that extends `groupby`, e.g. by
+//! registering its own groupers in [crate::groupers::registry], can call it after doing so to get
+//! every other feature of the command-line interface for free.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use groupby::command_line::{self, GroupByError};
+//! use groupby::groupers::registry;
+//! use std::process;
+//!
+//! registry::register("vowels", Box::new(|token: &str| {
+//!     token.chars().filter(|c| "aeiou".contains(*c)).collect()
+//! }));
+//!
+//! // Now `--grouper vowels` works on this program's command line.
+//! if let Err(e) = command_line::main() {
+//!     if let GroupByError::Usage(usage) = &e {
+//!         usage.exit();
+//!     }
+//!     eprintln!("vowels: {}", e);
+//!     process::exit(e.exit_code());
+//! }
+//! ```
+
+use crate::command_line;
+use crate::command_line::timings::Timings;
+use crate::command_line::{GroupByError, Invocation};
+use crate::grouped_collections::{CountingCollection, DiskMap, GroupedCollection, InternedMap};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Runs `groupby` with the process's command-line arguments, as the `groupby` binary does.
+///
+/// Parses the arguments, including any `--profile` or `--preset`, then reads input, groups it,
+/// runs commands, and writes results to standard output (or standard error with `--tee`) as the
+/// options say. Subcommands, e.g. `groupby bench`, run instead.
+///
+/// If standard output is closed early, e.g. by `groupby -f1 big.txt | head`, this stops and
+/// returns `Ok` without an error, as most command-line tools do.
+///
+/// # Errors
+///
+/// Returns the first error from any step. The caller decides how to report it; the `groupby`
+/// binary lets clap print a [GroupByError::Usage] (which includes `--help` and `--version`), and
+/// otherwise prints the error and exits with [GroupByError::exit_code].
+pub fn main() -> Result<(), GroupByError> {
+    match run() {
+        Err(e) if e.is_broken_pipe() => Ok(()),
+        result => result,
+    }
+}
+
+// Holds the logic of main(), which only adds the treatment of broken pipes.
+fn run() -> Result<(), GroupByError> {
+    // Parse command-line arguments into GroupByOptions struct, unless they name a subcommand.
+    let options = match command_line::parse_invocation(command_line::args())? {
+        Invocation::Group(options) => options,
+        Invocation::Bench(options) => {
+            return command_line::bench::bench(io::stdout(), &options);
+        }
+    };
+
+    // If requested, describe what we would do instead of doing it.
+    if options.explain {
+        print!("{}", command_line::explain::explain(&options));
+        return Ok(());
+    }
+
+    // With --tee, a copy of the input may take standard output, so results go to standard error.
+    let output = || -> Box<dyn Write + Send> {
+        if options.input.tee.is_some() {
+            Box::new(io::stderr())
+        } else {
+            Box::new(io::stdout())
+        }
+    };
+
+    // If the input is sorted by key, output each group as soon as it's complete instead.
+    if options.output.presorted_streaming {
+        let input = command_line::input_files::concatenated(&options.input)?;
+        return command_line::stream_groups(input, output(), &options);
+    }
+
+    // If requested, write each token as soon as its key is known, without collecting groups.
+    if options.output.stream {
+        return command_line::stream_tokens(output(), &options);
+    }
+
+    // If requested, spill values to disk as they outgrow memory, then output one group at a time.
+    if options.output.low_memory {
+        let mut map = DiskMap::new();
+        command_line::build_groups::build_groups_from_inputs(&mut map, &options)?;
+        let groups = map.into_groups_by(|a, b| options.output.sort.compare(a, b))?;
+        return command_line::stream_groups::write_groups(groups, output(), &options);
+    }
+
+    // If requested, store each distinct value once, then output one group at a time.
+    if options.output.intern {
+        let mut map = InternedMap::new();
+        command_line::build_groups::build_groups_from_inputs(&mut map, &options)?;
+        let groups = map
+            .into_groups_by(|a, b| options.output.sort.compare(a, b))
+            .map(Ok);
+        return command_line::stream_groups::write_groups(groups, output(), &options);
+    }
+
+    // Record how long each phase takes, in case --timings asks for a report.
+    let mut timings = Timings::new();
+
+    // If only counts are needed, don't store the values at all.
+    if options.output.count_only {
+        let mut counts = CountingCollection::new();
+        timings.time_groups(|| -> Result<(), GroupByError> {
+            command_line::build_groups::build_groups_from_inputs(&mut counts, &options)?;
+            counts.retain(|_, count| options.keeps_size(*count));
+            Ok(())
+        })?;
+        timings.time("output", || {
+            command_line::write_results::write_counts(output(), &counts, &options.output)
+        })?;
+        if options.output.timings {
+            timings.write::<_, String>(io::stderr(), None)?;
+        }
+        return Ok(());
+    }
+
+    // Choose which GroupedCollection implementation we're going to use.
+    let mut map = BTreeMap::<String, Vec<String>>::new();
+
+    let duplicates_removed = timings.time_groups(|| -> Result<_, GroupByError> {
+        // Process the input files (or stdin), building a GroupedCollection.
+        command_line::build_groups::build_groups_from_inputs(&mut map, &options)?;
+
+        // If requested, remove duplicates, drop groups by size, and count values.
+        Ok(options.finish_groups(&mut map))
+    })?;
+
+    // If requested, describe the commands we would run instead of running them.
+    if options.output.dry_run {
+        return command_line::run_command::dry_run(output(), &map, &options.output);
+    }
+
+    // If requested, run commands over the GroupedCollection and return a map of the commands'
+    // captured standard outputs. From here on, an interrupt stops the commands instead of us.
+    if options.output.run_command.is_some() {
+        command_line::interrupt::install_handler()?;
+    }
+    let command_results = match options.output.run_command {
+        Some(_) => timings.time("commands", || {
+            command_line::run_command(output(), &map, &options.output)
+        })?,
+        None => None,
+    };
+
+    // Write the final results, per the user's options, to the output chosen above.
+    timings.time("output", || -> Result<(), GroupByError> {
+        command_line::write_results(output(), &map, &command_results, &options.output)?;
+        if let Some(count) = duplicates_removed {
+            command_line::write_results::write_duplicates_removed(
+                output(),
+                count,
+                &options.output,
+            )?;
+        }
+        Ok(())
+    })?;
+
+    // If requested, report where the time went.
+    if options.output.timings {
+        timings.write(io::stderr(), command_results.as_ref())?;
+    }
+
+    // If any commands failed, report them now that every group's output has been written. If
+    // commands were interrupted, that's more important.
+    if let Some(results) = &command_results {
+        if command_line::interrupt::is_interrupted() {
+            return Err(GroupByError::Interrupted(results.len(), map.len()));
+        }
+        command_line::run_command::check_failures(results)?;
+    }
+    Ok(())
+}
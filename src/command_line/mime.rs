@@ -0,0 +1,177 @@
+//! Content sniffing: detecting a file's MIME type from its first few bytes.
+//!
+//! File extensions are easy to change and often wrong, but most binary formats begin with a fixed
+//! signature, or "magic bytes." [sniff] checks a file's leading bytes against a table of common
+//! signatures, falling back to `text/plain` for text and `application/octet-stream` for anything
+//! else. It doesn't try to tell text formats apart, except for a few that announce themselves,
+//! such as XML and HTML.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::mime::sniff;
+//!
+//! assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "image/png");
+//! assert_eq!(sniff(b"PK\x03\x04\x14\0"), "application/zip");
+//! assert_eq!(sniff(b"Dear diary,\n"), "text/plain");
+//! assert_eq!(sniff(b"\0\x01\x02\x03"), "application/octet-stream");
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+
+/// How many leading bytes [sniff_file] reads. Enough for every signature in the table, the
+/// furthest of which is the `ustar` marker in tar headers.
+pub const SNIFF_LENGTH: u64 = 512;
+
+// Signatures that start at the beginning of the file, in the order they're checked. Where one
+// signature is a prefix of another, the longer one comes first.
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"BM", "image/bmp"),
+    (b"II*\0", "image/tiff"),
+    (b"MM\0*", "image/tiff"),
+    (b"\0\0\x01\0", "image/vnd.microsoft.icon"),
+    (b"%PDF-", "application/pdf"),
+    (b"%!PS", "application/postscript"),
+    (b"{\\rtf", "application/rtf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"PK\x05\x06", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"BZh", "application/x-bzip2"),
+    (b"\xfd7zXZ\0", "application/x-xz"),
+    (b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"Rar!\x1a\x07", "application/vnd.rar"),
+    (b"\x7fELF", "application/x-elf"),
+    (b"MZ", "application/vnd.microsoft.portable-executable"),
+    (b"\0asm", "application/wasm"),
+    (b"SQLite format 3\0", "application/vnd.sqlite3"),
+    (b"ID3", "audio/mpeg"),
+    (b"fLaC", "audio/flac"),
+    (b"OggS", "audio/ogg"),
+    (b"MThd", "audio/midi"),
+    (b"\x1a\x45\xdf\xa3", "video/x-matroska"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+    (b"<?xml", "application/xml"),
+];
+
+/// Returns the MIME type of a file that begins with `bytes`. See the
+/// [module documentation](self) for details.
+///
+/// `bytes` may be the whole file or just its first [SNIFF_LENGTH] bytes. An empty file is
+/// `inode/x-empty`, as with `file --mime-type`.
+///
+/// ```
+/// use groupby::command_line::mime::sniff;
+///
+/// assert_eq!(sniff(b"RIFF\x24\0\0\0WAVEfmt "), "audio/wav");
+/// assert_eq!(sniff(b"\0\0\0\x18ftypmp42"), "video/mp4");
+/// assert_eq!(sniff(b"<!DOCTYPE html>"), "text/html");
+/// assert_eq!(sniff(b""), "inode/x-empty");
+/// ```
+pub fn sniff(bytes: &[u8]) -> &'static str {
+    if bytes.is_empty() {
+        return "inode/x-empty";
+    }
+    if let Some((_, mime_type)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+    {
+        return mime_type;
+    }
+
+    // Containers whose subtype follows a size or other header field.
+    if bytes.starts_with(b"RIFF") && bytes.len() >= 12 {
+        match &bytes[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            b"AVI " => return "video/x-msvideo",
+            _ => (),
+        }
+    }
+    if bytes.get(4..8) == Some(b"ftyp") {
+        return match bytes.get(8..12) {
+            Some(b"heic") | Some(b"heix") | Some(b"mif1") => "image/heic",
+            Some(b"avif") => "image/avif",
+            Some(b"M4A ") => "audio/mp4",
+            Some(b"qt  ") => "video/quicktime",
+            _ => "video/mp4",
+        };
+    }
+    if bytes.get(257..262) == Some(b"ustar") {
+        return "application/x-tar";
+    }
+
+    if is_text(bytes) {
+        let start = String::from_utf8_lossy(&bytes[..bytes.len().min(64)]).to_ascii_lowercase();
+        let start = start.trim_start_matches('\u{feff}').trim_start();
+        if start.starts_with("<!doctype html") || start.starts_with("<html") {
+            "text/html"
+        } else if start.starts_with("<?xml") {
+            "application/xml"
+        } else {
+            "text/plain"
+        }
+    } else {
+        "application/octet-stream"
+    }
+}
+
+// Returns true if bytes look like text: UTF-8, allowing a character cut off at the end, with no
+// control characters other than whitespace.
+fn is_text(bytes: &[u8]) -> bool {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(error) if error.error_len().is_none() => {
+            std::str::from_utf8(&bytes[..error.valid_up_to()]).unwrap()
+        }
+        Err(_) => return false,
+    };
+    text.chars()
+        .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t' | '\x0c'))
+}
+
+/// Returns the MIME type of the file at `path`, from its first [SNIFF_LENGTH] bytes.
+///
+/// Follows symbolic links. Directories are `inode/directory`, as with `file --mime-type`.
+pub fn sniff_file(path: &str) -> io::Result<&'static str> {
+    if fs::metadata(path)?.is_dir() {
+        return Ok("inode/directory");
+    }
+    let mut bytes = Vec::new();
+    File::open(path)?
+        .take(SNIFF_LENGTH)
+        .read_to_end(&mut bytes)?;
+    Ok(sniff(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_tar() {
+        let mut header = vec![0; 512];
+        header[..8].copy_from_slice(b"file.txt");
+        header[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff(&header), "application/x-tar");
+    }
+
+    #[test]
+    fn allows_truncated_utf8() {
+        assert_eq!(sniff("naïve".as_bytes()), "text/plain");
+        assert_eq!(sniff(&"naïve".as_bytes()[..3]), "text/plain");
+    }
+
+    #[test]
+    fn sniffs_files() {
+        assert_eq!(sniff_file("Cargo.toml").unwrap(), "text/plain");
+        assert_eq!(sniff_file("src").unwrap(), "inode/directory");
+        assert!(sniff_file("no/such/file").is_err());
+    }
+}
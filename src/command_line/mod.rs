@@ -5,9 +5,10 @@
 //!
 //! # Examples
 //!
-//! The [groupby] binary offers a simple map of the top-level API for this module. If you need
-//! finer control or want to reuse specific components within the top-level methods here, this map
-//! should provide jumping-off points for further reading.
+//! [main()] runs the whole `groupby` program; the [groupby] binary just calls it. The source of
+//! [main()] offers a simple map of the top-level API for this module. If you need finer control or
+//! want to reuse specific components within the top-level methods here, this map should provide
+//! jumping-off points for further reading.
 //!
 //! # Architecture
 //!
@@ -24,42 +25,92 @@
 //!    a [GroupByOptions] value that stores command-line options in a parser-agnostic way.
 //!
 //! 1. [build_groups()]: process input through the selected [String grouper] using [Runner],
-//!    adding each token into a [GroupedCollection].
+//!    adding each token into a [GroupedCollection]. [build_groups_from_inputs()] does the same for
+//!    each of the [input files](input_files) in turn. With [OutputOptions::stream], tokens go to
+//!    a [Sink] instead, and [stream_tokens()] writes each one out at once, skipping the rest of
+//!    the steps. For programs that receive tokens over time, a [Pipeline] groups tokens sent over
+//!    a channel on a thread of its own, and sends groups back as they're finished.
 //!
 //! **Outputting results:**
 //!
 //! 1. [run_command()]: If [GroupByOptions] requests to run a command against each group, call
 //!    [run()] once for each group. Write each group to the standard input for its command
 //!    (following the options specified in [GroupByOptions::output]). Record each command's
-//!    standard output using [command_runner::report].
+//!    output (standard output, plus standard error if requested) using [command_runner::report].
 //!
-//! 1. [write_results()]: If [run_command()] returned a collection of captured standard outputs,
+//! 1. [write_results()]: If [run_command()] returned a collection of captured outputs,
 //!    print each group's captured output, using sensible default options for human consumption.
 //!    Otherwise, print the contents of the [GroupedCollection], following the options specified
 //!    in [GroupByOptions::output].
 //!
+//! Each of these steps reports failures, such as an invalid regular expression or a command that
+//! can't start, by returning a [GroupByError] instead of panicking or exiting, so applications can
+//! decide how to handle them. The [groupby] binary prints the error that [main()] returns and exits
+//! with [GroupByError::exit_code].
+//!
 //! [clap]: https://crates.io/crates/clap
+//! [build_groups_from_inputs()]: build_groups::build_groups_from_inputs
 //! [groupby]: https://github.com/edev/groupby/tree/master/src/bin/groupby.rs
+//! [main()]: main::main
 //! [GroupedCollection]: crate::grouped_collections::GroupedCollection
 //! [run()]: command_runner::run()
+//! [Pipeline]: pipeline::Pipeline
+//! [Sink]: build_groups::Sink
 //! [Runner]: crate::groupers::string::Runner
 //! [String grouper]: crate::groupers::string::Groupers
 
 pub mod args;
+#[cfg(feature = "rayon")]
+pub mod bench;
 pub mod build_groups;
+pub mod checkpoint;
 pub mod command_runner;
+pub mod csv;
+pub mod decompress;
+pub mod error;
+pub mod explain;
+pub mod globs;
+pub mod input_files;
+pub mod interrupt;
+pub mod json;
+pub mod key_command;
+pub mod key_expr;
+pub mod key_order;
+pub mod labels;
+#[cfg(feature = "rayon")]
+pub mod main;
+pub mod mime;
 pub mod options;
 pub mod parse_args;
+pub mod pipeline;
+pub mod profiles;
 pub mod record_writer;
+pub mod reorder_buffer;
 pub mod run_command;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub mod size_buckets;
+pub mod stream_groups;
+pub mod stream_tokens;
+pub mod template;
 #[cfg(test)]
 mod test_helpers;
+pub mod timestamps;
+pub mod timings;
+pub mod value_counts;
+pub mod walk;
 pub mod write_results;
 
 pub use args::{args, command};
 pub use build_groups::build_groups;
+pub use error::GroupByError;
+#[cfg(feature = "rayon")]
+pub use main::main;
 pub use options::*;
-pub use parse_args::parse;
+pub use parse_args::{parse, parse_invocation};
 pub use record_writer::RecordWriter;
+#[cfg(feature = "rayon")]
 pub use run_command::run_command;
+pub use stream_groups::stream_groups;
+pub use stream_tokens::stream_tokens;
 pub use write_results::write_results;
@@ -6,12 +6,27 @@
 //! The root data structure is [GroupByOptions]. The other types here are contained within
 //! GroupByOptions.
 
+pub use crate::command_line::csv::Column;
+pub use crate::command_line::globs::GlobRule;
+pub use crate::command_line::json::JsonPath;
+pub use crate::command_line::key_expr::KeyExpr;
+pub use crate::command_line::key_order::{GroupOrder, KeyOrder, Tailoring};
+pub use crate::command_line::labels::Labels;
+pub use crate::command_line::size_buckets::SizeBuckets;
+pub use crate::command_line::template::Template;
+pub use crate::command_line::timestamps::{TimeBucket, TimestampFormat};
+use crate::command_line::value_counts::count_values;
+pub use crate::command_line::walk::Walk;
+use crate::grouped_collections::GroupedCollection;
 use regex::Regex;
+use std::path::PathBuf;
+use std::str::FromStr;
 
 /// Specifies what character to use as a separator between records/tokens.
 ///
 /// This may be used in multiple contexts, e.g. parsing inputs and printing results.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Separator {
     /// Use a newline character (`\n`) as a separator.
     Line,
@@ -22,15 +37,163 @@ pub enum Separator {
     /// Use a null separator (`\0`).
     Null,
 
+    /// Use blank lines as separators, so that each paragraph is a token, like awk's paragraph
+    /// mode. When splitting input, any run of lines that are empty or only whitespace separates
+    /// two paragraphs, and the lines of each paragraph are joined with `\n`. When printing, a
+    /// blank line (`\n\n`) separates values.
+    Paragraph,
+
     /// Use a user-provided string as a separator.
     Custom(String),
 }
 
 /// Options for handling program input.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputOptions {
     /// Specifies what type of separator to look for when parsing records.
     pub separator: Separator,
+
+    /// Read input as CSV records with a header row, which is used to look up
+    /// [GroupingSpecifier::CsvColumn] names rather than grouped. Records are separated by
+    /// newlines, except inside quoted fields; `separator` is ignored. See
+    /// [crate::command_line::csv] for details.
+    pub csv: bool,
+
+    /// What to do with tokens that aren't valid UTF-8. With [InputOptions::csv], each record is a
+    /// token.
+    pub invalid_utf8: InvalidUtf8,
+
+    /// The files to read input from, in order. If empty, read standard input. `-` also names
+    /// standard input. See [crate::command_line::input_files].
+    pub files: Vec<PathBuf>,
+
+    /// If `Some`, read the names of more files to read input from, each followed by a null
+    /// character, from this file, or from standard input if it's `-`, as with `du --files0-from`.
+    /// These files are read after [InputOptions::files].
+    pub files0_from: Option<PathBuf>,
+
+    /// Prefix each value with the name of the file it came from and a colon, like `grep -H`. Keys
+    /// are found before the prefix is added. Values read from standard input are prefixed with
+    /// `(standard input):`.
+    pub with_filename: bool,
+
+    /// If `Some`, walk a directory tree instead of reading input, and use the path of each file
+    /// as a token. See [crate::command_line::walk].
+    pub walk: Option<Walk>,
+
+    /// If `Some`, copy the input, exactly as it's read, to this file, or to standard output if
+    /// it's `-`, like `tee`. Compressed input is copied after it's decompressed. The results then
+    /// go to standard error, so groupby can sit in the middle of a pipeline. See
+    /// [crate::command_line::input_files::Tee].
+    pub tee: Option<PathBuf>,
+
+    /// Strip leading and trailing whitespace from each token before it's grouped. See
+    /// [InputOptions::prepare].
+    pub trim: bool,
+
+    /// Drop empty tokens, after trimming them if [InputOptions::trim] is set, instead of grouping
+    /// them. See [InputOptions::prepare].
+    pub skip_empty: bool,
+
+    /// If `Some`, stop reading input once this many tokens have been grouped. Tokens that
+    /// [InputOptions::skip_empty] or [InputOptions::sample] drop don't count.
+    pub limit: Option<usize>,
+
+    /// If `Some`, keep each token at random with this probability and drop the rest, drawing from
+    /// the `"sample"` stream of [crate::random] with [GroupByOptions::seed].
+    pub sample: Option<Fraction>,
+}
+
+/// What to do with input tokens that aren't valid UTF-8.
+///
+/// Input is split into tokens as bytes, and each token is converted to a [String] on its own, so
+/// one bad token doesn't affect the others.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InvalidUtf8 {
+    /// Replace each invalid sequence with U+FFFD, the replacement character, as with
+    /// [String::from_utf8_lossy].
+    #[default]
+    Lossy,
+
+    /// Skip the token, printing a warning to standard error.
+    Skip,
+
+    /// Stop with [GroupByError::InvalidInput](crate::command_line::GroupByError::InvalidInput).
+    Strict,
+}
+
+/// A fraction from 0 to 1, e.g. the share of tokens that [InputOptions::sample] keeps.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fraction(pub f64);
+
+// Fractions are never NaN, since FromStr rejects it, so they can be Eq.
+impl PartialEq for Fraction {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Fraction {}
+
+impl FromStr for Fraction {
+    type Err = String;
+
+    /// Parses a fraction written as a number from 0 to 1, like `0.05`, or as a percentage, like
+    /// `5%`.
+    ///
+    /// ```
+    /// use groupby::command_line::options::Fraction;
+    ///
+    /// assert_eq!("0.25".parse(), Ok(Fraction(0.25)));
+    /// assert_eq!("5%".parse(), Ok(Fraction(0.05)));
+    /// assert!("1.5".parse::<Fraction>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "Expected a fraction from 0 to 1, like 0.05, or a percentage, like 5%, but got: {}",
+                s
+            )
+        };
+        let fraction = match s.strip_suffix('%') {
+            Some(percent) => percent.parse::<f64>().map_err(|_| error())? / 100.0,
+            None => s.parse::<f64>().map_err(|_| error())?,
+        };
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(error());
+        }
+        Ok(Fraction(fraction))
+    }
+}
+
+/// What to do when a group's command exits unsuccessfully.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FailurePolicy {
+    /// Run every group's command, then report the groups whose commands failed.
+    #[default]
+    KeepGoing,
+
+    /// Stop at the first failure: start no more commands, and report the group whose command
+    /// failed. Commands that are already running in parallel still run to completion.
+    FailFast,
+}
+
+/// How to quote group names and values when printing them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Quote {
+    /// Print them as they are.
+    #[default]
+    None,
+
+    /// Quote them for a POSIX shell with
+    /// [shell_quote](crate::command_line::run_command::shell_quote), so that each reads as a
+    /// single word.
+    Shell,
 }
 
 /// A named or numbered regular expression capture group.
@@ -39,6 +202,7 @@ pub struct InputOptions {
 /// capture group is present in a regular expression, nor does it even guarantee that a regular
 /// expression exists.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CaptureGroup {
     /// A numbered capture group, where 0 is the default group, i.e. the whole match.
     Number(usize),
@@ -48,10 +212,121 @@ pub enum CaptureGroup {
 
     /// Request default behavior: use capture group 1 if present; otherwise, match the whole regex.
     Default,
+
+    /// Several capture groups, joined with the `String` in the order given, e.g. a date and a
+    /// severity as one key. If any of them didn't take part in the match, the regex is treated as
+    /// not matching.
+    Joined(Vec<CaptureGroup>, String),
+}
+
+/// Specifies what to do with a token that the regular expression of a [GroupingSpecifier::Regex],
+/// or every rule of a [GroupingSpecifier::RegexRules], doesn't match.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Unmatched {
+    /// Add the token to the blank group, `""`.
+    #[default]
+    Empty,
+
+    /// Drop the token.
+    Skip,
+
+    /// Stop with [GroupByError::InvalidInput](crate::command_line::GroupByError::InvalidInput).
+    Error,
+
+    /// Add the token to the group with the given key.
+    Group(String),
+}
+
+impl FromStr for Unmatched {
+    type Err = String;
+
+    /// Parses a policy written as `empty`, `skip`, `error`, or `group:NAME`.
+    ///
+    /// ```
+    /// use groupby::command_line::options::Unmatched;
+    ///
+    /// assert_eq!("skip".parse(), Ok(Unmatched::Skip));
+    /// assert_eq!("group:other".parse(), Ok(Unmatched::Group("other".to_string())));
+    /// assert!("ignore".parse::<Unmatched>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "empty" => Ok(Unmatched::Empty),
+            "skip" => Ok(Unmatched::Skip),
+            "error" => Ok(Unmatched::Error),
+            _ => match s.strip_prefix("group:") {
+                Some(name) => Ok(Unmatched::Group(name.to_string())),
+                None => Err(format!(
+                    "Expected empty, skip, error, or group:NAME for unmatched tokens, but got: {}",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// One of the ordered regular expressions of a [GroupingSpecifier::RegexRules]. Tokens that
+/// `regex` matches belong in the group `name`, or, if the rule has no name, in the group for the
+/// part of the match that the [CaptureGroup] picks out.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegexRule {
+    pub name: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::command_line::serialization::regex_pattern")
+    )]
+    pub regex: Regex,
+}
+
+impl PartialEq for RegexRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.regex.as_str() == other.regex.as_str()
+    }
+}
+
+impl FromStr for RegexRule {
+    type Err = String;
+
+    /// Parses a rule written as `NAME=PATTERN` or just `PATTERN`. Only a prefix that looks like an
+    /// identifier (letters, digits, `_`, and `-`, not starting with a digit or `-`) followed by
+    /// `=` is read as a name, so most patterns that contain `=` are read whole. To match a literal
+    /// `word=` at the start of an unnamed pattern, write it as `(?:word)=`.
+    ///
+    /// ```
+    /// use groupby::command_line::options::RegexRule;
+    ///
+    /// let rule: RegexRule = "errors=ERROR|FATAL".parse().unwrap();
+    /// assert_eq!(rule.name.as_deref(), Some("errors"));
+    /// assert_eq!(rule.regex.as_str(), "ERROR|FATAL");
+    ///
+    /// let rule: RegexRule = r"(\w+)=\d+".parse().unwrap();
+    /// assert_eq!(rule.name, None);
+    ///
+    /// assert!("broken=(".parse::<RegexRule>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.split_once('=').map(|(name, _)| name).filter(|name| {
+            let mut chars = name.chars();
+            matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+                && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        });
+        let pattern = match name {
+            Some(name) => &s[name.len() + 1..],
+            None => s,
+        };
+        Ok(RegexRule {
+            name: name.map(str::to_string),
+            // The provided messages are actually really good.
+            regex: Regex::new(pattern).map_err(|e| e.to_string())?,
+        })
+    }
 }
 
 /// Specifies the user's chosen grouper.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GroupingSpecifier {
     /// Group by the first `usize` characters of each token.
     FirstChars(usize),
@@ -59,15 +334,149 @@ pub enum GroupingSpecifier {
     /// Group by the last `usize` characters of each token.
     LastChars(usize),
 
-    /// Group by the provided regular expression. See [crate::matchers::string::match_regex] for
-    /// details.
-    Regex(Regex, CaptureGroup),
+    /// Group by the first `usize` bytes of each token. See
+    /// [crate::matchers::string::match_first_n_bytes] for details.
+    FirstBytes(usize),
+
+    /// Group by the last `usize` bytes of each token. See
+    /// [crate::matchers::string::match_last_n_bytes] for details.
+    LastBytes(usize),
+
+    /// Group by the provided regular expression, handling tokens that it doesn't match as the
+    /// [Unmatched] policy says. See [crate::matchers::string::match_regex] for details.
+    Regex(
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::command_line::serialization::regex_pattern")
+        )]
+        Regex,
+        CaptureGroup,
+        Unmatched,
+    ),
+
+    /// Group by the first of several [RegexRule]s, tried in order, that matches each token,
+    /// handling tokens that none of them match as the [Unmatched] policy says. See
+    /// [crate::matchers::string::match_regex_rules] for details.
+    RegexRules(Vec<RegexRule>, CaptureGroup, Unmatched),
 
     /// Group by file extension. See [crate::matchers::string::match_file_extension] for details.
     FileExtension,
 
     /// Group by counter. See [crate::matchers::string::match_counter] for details.
     Counter,
+
+    /// Treat each token as a pre-keyed record, split at the first occurrence of the `String`
+    /// separator. See [crate::groupers::string::Groupers::group_by_key_value] for details.
+    KeyValue(String),
+
+    /// Group by the `usize`th field (counting from 1) of each token, where fields are separated by
+    /// the `String` delimiter. See [crate::matchers::string::match_field] for details.
+    Field(usize, String),
+
+    /// Group by the `usize`th word (counting from 1) of each token, where a word is any match of
+    /// the [Regex]. See [crate::matchers::string::match_nth_word] for details.
+    NthWord(
+        usize,
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::command_line::serialization::regex_pattern")
+        )]
+        Regex,
+    ),
+
+    /// Group by the first timestamp in each token that matches the [TimestampFormat], reduced to
+    /// the [TimeBucket]. See [crate::matchers::string::match_timestamp] for details.
+    Timestamp(TimestampFormat, TimeBucket),
+
+    /// Group by the range of width `u64` that contains the first number in each token. See
+    /// [crate::matchers::string::match_numeric_bucket] for details.
+    NumericBucket(u64),
+
+    /// Treat each token as a path and group by the size of the file it names, divided into
+    /// [SizeBuckets]. See [crate::matchers::string::match_size_bucket] for details.
+    SizeBucket(SizeBuckets),
+
+    /// Treat each token as a path and group by the modification time of the file it names,
+    /// reduced to the [TimeBucket]. See [crate::matchers::string::match_mtime] for details.
+    Mtime(TimeBucket),
+
+    /// Treat each token as a path and group by the MIME type of the file it names, detected from
+    /// its contents. See [crate::matchers::string::match_mime_type] for details.
+    MimeType,
+
+    /// Treat each token as a path and group by its component at depth `usize` (counting from 1).
+    /// See [crate::matchers::string::match_path_component] for details.
+    PathComponent(usize),
+
+    /// Treat each token as a path and group by its final component. See
+    /// [crate::matchers::string::match_basename] for details.
+    Basename,
+
+    /// Treat each token as a path and group by its parent directory. See
+    /// [crate::matchers::string::match_dirname] for details.
+    Dirname,
+
+    /// Group by the length of each token in characters. See
+    /// [crate::matchers::string::match_length] for details.
+    Length,
+
+    /// Group into `u64` shards, numbered from 0, by a stable hash of each token. See
+    /// [crate::matchers::string::match_shard] for details.
+    Shard(u64),
+
+    /// Group each consecutive run of `usize` tokens into its own group, numbered from 0. See
+    /// [crate::groupers::string::Groupers::group_by_chunk] for details.
+    Chunk(usize),
+
+    /// Group by the name of the first [GlobRule] whose pattern matches each token, or `"other"`
+    /// if none match. See [crate::matchers::string::match_glob_rules] for details.
+    Glob(Vec<GlobRule>),
+
+    /// Treat each token as a URL and group by its host, or, if the `bool` is true, by the host's
+    /// registrable domain. See [crate::matchers::string::match_url_host] for details.
+    UrlHost(bool),
+
+    /// Treat each token as a JSON document and group by the value at the [JsonPath]. See
+    /// [crate::matchers::string::match_json_key] for details.
+    JsonKey(JsonPath),
+
+    /// Treat each token as a CSV record whose fields are separated by the `char` and group by the
+    /// [Column]. Column names must be resolved against a header with
+    /// [crate::command_line::csv::resolve_columns] before running the grouper, which
+    /// [crate::command_line::build_groups()] does for CSV input. See
+    /// [crate::matchers::string::match_csv_column] for details.
+    CsvColumn(Column, char),
+
+    /// Treat each token as a logfmt-style line of `key=value` pairs and group by the value for the
+    /// key `String`. See [crate::matchers::string::match_logfmt] for details.
+    Logfmt(String),
+
+    /// Group by the key that the [KeyExpr] computes from each token. See
+    /// [crate::matchers::string::match_key_expr] for details.
+    KeyExpr(KeyExpr),
+
+    /// Group by the key that the shell command `String` prints for each token, running the
+    /// command once for every batch of up to `usize` tokens, in the shell at the `Option<String>`
+    /// path or else the [current shell](crate::command_line::run_command::current_shell()). A
+    /// [Runner] holds tokens until it has a batch, so call [Runner::finish] after the last one. See
+    /// [crate::command_line::key_command] for details.
+    ///
+    /// [Runner]: crate::groupers::string::Runner
+    /// [Runner::finish]: crate::groupers::string::Runner::finish
+    KeyCommand(String, usize, Option<String>),
+
+    /// Group by the keys of several [GroupingSpecifiers](GroupingSpecifier), joined with the
+    /// `String` separator, e.g. by date and severity at once. Each token is stored unchanged. See
+    /// [crate::groupers::string::Runner] for details.
+    Composite(Vec<GroupingSpecifier>, String),
+
+    /// Group by the wrapped [GroupingSpecifier], but lowercase each key, so that keys differing
+    /// only in case share a group. See [crate::groupers::string::CaseFolded] for details.
+    IgnoreCase(Box<GroupingSpecifier>),
+
+    /// Group by the key that the grouper registered under the `String` name returns. See
+    /// [crate::groupers::registry] for details.
+    Named(String),
 }
 
 // For ease of use implementing PartialEq below.
@@ -75,25 +484,55 @@ use GroupingSpecifier::*;
 
 /// Options for controlling the program's output.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OutputOptions {
     /// Specifies what type of separator to output between records.
     pub separator: Separator,
 
     /// Output only group names; do not group contents.
+    ///
+    /// With [Separator::Null] and no command, each name is printed exactly as it is, followed by a
+    /// null character, so the output can go straight to `xargs -0`. Per-group statistics and
+    /// [OutputOptions::quote] don't apply to the names.
     pub only_group_names: bool,
 
     /// If `Some`, pass each group to the command string as its stdin instead of printing
     /// the group's contents. Instead, print any output from the command under the
     /// group's header.
     ///
-    /// If the user specifies this option, all other members of this struct apply to the command
-    /// invocations instead of the final output; the final output should use default options. This
-    /// is for sanity as well as generality: it might make sense to provide input to a program in
-    /// an easy-to-parse, hard-to-read way (such as Separator::Null), but the final output should
-    /// be tailored for human consumption. If a need arises, we can add an option or set of options
-    /// to accommodate specific final output requirements for program output.
+    /// When this is `Some`, the members of this struct fall into three sets:
+    ///
+    /// - [OutputOptions::separator] and [OutputOptions::only_group_names] shape each command's
+    ///   standard input (values separated by the separator, or just the group's name) instead of
+    ///   the final output, which uses their defaults. It might make sense to feed a program in an
+    ///   easy-to-parse, hard-to-read way, such as [Separator::Null], but the final output should be
+    ///   tailored for human consumption. [OutputOptions::shell], [OutputOptions::parallel],
+    ///   [OutputOptions::jobs], the limits on arguments, input, and resources, and
+    ///   [OutputOptions::capture_stderr] only affect commands, too.
+    /// - The final output still follows [OutputOptions::headers],
+    ///   [OutputOptions::header_format], [OutputOptions::stats], [OutputOptions::with_values],
+    ///   [OutputOptions::json], [OutputOptions::format], and [OutputOptions::quote]. See
+    ///   [default_output_options](crate::command_line::write_results::default_output_options).
+    /// - The rest have rules of their own, described on each member: the order options
+    ///   ([OutputOptions::sort], [OutputOptions::reverse], and [OutputOptions::group_order]) order
+    ///   both the final output and sequential commands; [OutputOptions::live] and
+    ///   [OutputOptions::incremental] print results while commands run;
+    ///   [OutputOptions::checkpoint] and [OutputOptions::resume] skip groups whose commands already
+    ///   succeeded; [OutputOptions::failure_policy] decides whether a failure stops the run; and
+    ///   [OutputOptions::dry_run] describes the commands instead of running them. The copy of the
+    ///   input that [InputOptions::tee] makes is unaffected by commands.
     pub run_command: Option<String>,
 
+    /// If `Some`, the path to the shell that runs [OutputOptions::run_command]. If `None`, use
+    /// the [current shell](crate::command_line::run_command::current_shell()). A
+    /// [GroupingSpecifier::KeyCommand] carries its own copy.
+    pub shell: Option<String>,
+
+    /// If true, describe the command that [OutputOptions::run_command] would run over each group,
+    /// instead of running anything. See
+    /// [dry_run()](crate::command_line::run_command::dry_run()).
+    pub dry_run: bool,
+
     /// If true, run commands in parallel, in arbitrary order (using work stealing).
     ///
     /// If false, run commands in sequence rather than in parallel, using a single thread of
@@ -101,6 +540,61 @@ pub struct OutputOptions {
     /// groups they represent, which is sometimes necessary (e.g. for some database operations).
     pub parallel: bool,
 
+    /// If `Some`, run at most this many commands at a time when [OutputOptions::parallel] is true.
+    /// If `None`, run one at a time per logical CPU core.
+    pub jobs: Option<usize>,
+
+    /// If `Some`, pass at most this many values to each invocation of the command, running it as
+    /// many times as needed for each group, like `xargs -n`. See
+    /// [ShellCommandOptions::max_args](crate::command_line::run_command::ShellCommandOptions).
+    pub max_args: Option<usize>,
+
+    /// If `Some`, pass at most this many bytes of values, counting separators, to each invocation
+    /// of the command, running it as many times as needed for each group. A single value that's
+    /// larger than this is passed on its own.
+    pub max_stdin_bytes: Option<usize>,
+
+    /// If `Some`, run each command at this niceness, from -20 (the highest priority) to 19 (the
+    /// lowest). Only applies on Unix, like the other limits below.
+    pub nice: Option<i32>,
+
+    /// If `Some`, limit each command's virtual memory to this many bytes.
+    pub max_memory: Option<u64>,
+
+    /// If `Some`, limit each command to this many open files at once.
+    pub max_open_files: Option<u64>,
+
+    /// If true, capture each command's standard error along with its standard output, so that it
+    /// can be printed under the group's header. If false, commands write standard error directly
+    /// to the standard error inherited from the caller.
+    ///
+    /// Only applies when [OutputOptions::run_command] is a `Some` value.
+    pub capture_stderr: bool,
+
+    /// If true, print each line of each command's standard output as soon as the command writes
+    /// it, prefixed with the group's key, instead of waiting for every command to finish. Lines
+    /// from different commands may be interleaved, but never mixed together.
+    ///
+    /// Only applies when [OutputOptions::run_command] is a `Some` value.
+    pub live: bool,
+
+    /// If true, print each group's command output as soon as its command finishes, as long as
+    /// every group before it in [OutputOptions::sort] order has been printed, instead of waiting
+    /// for every command to finish. See [ReorderBuffer](crate::command_line::reorder_buffer).
+    ///
+    /// Only applies when [OutputOptions::run_command] is a `Some` value.
+    pub incremental: bool,
+
+    /// If true, print each group's contents along with its command output, rather than the
+    /// command output alone.
+    ///
+    /// Only applies when [OutputOptions::run_command] is a `Some` value.
+    pub with_values: bool,
+
+    /// What to do when a command exits unsuccessfully. Only applies when
+    /// [OutputOptions::run_command] is a `Some` value.
+    pub failure_policy: FailurePolicy,
+
     /// Whether to print a header for each group with final output.
     ///
     /// When [OutputOptions::run_command] is a `Some` value, the commands' behavior is not affected;
@@ -108,21 +602,447 @@ pub struct OutputOptions {
     /// results, includes headers for each group or just each group's contents back-to-back.
     pub headers: bool,
 
-    /// Print statistics: an item count for each group and stats about the collection overall.
-    /// Not affected by run_command.
+    /// If `Some`, print each group's header by filling in this [Template] instead of printing the
+    /// group's key followed by a colon. Only applies when [OutputOptions::headers] is true.
+    pub header_format: Option<Template>,
+
+    /// Print statistics: item and byte counts for each group and stats about the collection
+    /// overall. Bytes count each value plus the separator after it.
+    /// The commands don't see this option, but when [OutputOptions::run_command] is a `Some`
+    /// value, the statistics also describe how each group's command went.
     pub stats: bool,
+
+    /// If `Some`, the statistics also include the standard deviation of group sizes, these
+    /// percentiles of group sizes (each from 0 to 100), and a histogram of group sizes. See
+    /// [StatisticsDetail](crate::command_line::write_results::StatisticsDetail).
+    ///
+    /// Only applies when [OutputOptions::stats] is true.
+    pub stats_detail: Option<Vec<u8>>,
+
+    /// If true, report how long reading input, grouping, running commands, and writing output each
+    /// took, plus each group's command, to standard error. See [crate::command_line::timings].
+    pub timings: bool,
+
+    /// If `Some`, record the key of each group whose command exits successfully in the checkpoint
+    /// file at this path. See [crate::command_line::checkpoint] for details.
+    ///
+    /// Only applies when [OutputOptions::run_command] is a `Some` value.
+    pub checkpoint: Option<String>,
+
+    /// If true, skip the groups that [OutputOptions::checkpoint] already lists as complete and
+    /// append to it; otherwise, start a fresh checkpoint.
+    pub resume: bool,
+
+    /// The order in which to print groups. Not affected by run_command, except that when
+    /// [OutputOptions::parallel] is false, commands also run in this order.
+    pub sort: KeyOrder,
+
+    /// Whether to re-sort groups by size for printing. Groups of the same size stay in
+    /// [OutputOptions::sort] order. Commands are numbered, and run sequentially, in this order too.
+    pub group_order: GroupOrder,
+
+    /// If true, print groups in the reverse of [OutputOptions::sort] order. Groups of the same
+    /// size under [OutputOptions::group_order] are reversed, too. Commands are numbered, and run
+    /// sequentially, in this order too.
+    pub reverse: bool,
+
+    /// If true, the input is sorted by key, so write each group (or run its command) as soon as
+    /// it's complete. See [crate::command_line::stream_groups] for details.
+    pub presorted_streaming: bool,
+
+    /// If true, write each token with its key as soon as the key is known, instead of collecting
+    /// groups. Incompatible with options that need whole groups, like
+    /// [OutputOptions::run_command]. See [crate::command_line::stream_tokens] for details.
+    pub stream: bool,
+
+    /// If true, collect groups in a [DiskMap](crate::grouped_collections::DiskMap), which spills
+    /// values to a temporary file once they outgrow memory, and write groups one at a time in
+    /// [OutputOptions::sort] order. See [crate::grouped_collections::disk] for details.
+    pub low_memory: bool,
+
+    /// If true, collect groups in an [InternedMap](crate::grouped_collections::InternedMap), which
+    /// stores each distinct value only once, and write groups one at a time in
+    /// [OutputOptions::sort] order, as with [OutputOptions::low_memory].
+    pub intern: bool,
+
+    /// If true, write the final output as a JSON object mapping each group's key to an array of
+    /// its values, or to its command's output when [OutputOptions::run_command] is a `Some` value.
+    pub json: bool,
+
+    /// If true, keep only the number of values in each group, in a
+    /// [CountingCollection](crate::grouped_collections::CountingCollection), and print each
+    /// group's key with its count. Incompatible with [OutputOptions::run_command], which needs the
+    /// values.
+    pub count_only: bool,
+
+    /// If `Some`, print each group by filling in this [Template] instead of printing a header and
+    /// the group's contents. Each rendered group is followed by [OutputOptions::separator].
+    /// Incompatible with [OutputOptions::json], [OutputOptions::only_group_names],
+    /// [OutputOptions::count_only], and [OutputOptions::live].
+    pub format: Option<Template>,
+
+    /// How to quote group names and values when printing them. Command output is printed as is.
+    pub quote: Quote,
 }
 
 /// The main options struct that holds all other options.
 ///
-/// Each field in this struct is a category of options.
+/// Each field in this struct is either a category of options or an option that applies across
+/// categories.
 ///
 /// Note: for safety, users are strongly recommended to own such a struct immutably.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GroupByOptions {
     pub input: InputOptions,
     pub grouping: GroupingSpecifier,
+
+    /// Rules that coalesce the keys produced by [GroupByOptions::grouping] into labeled groups.
+    /// See [crate::command_line::labels] for details.
+    pub labels: Labels,
+
+    /// If true, remove duplicate values from each group after grouping, keeping the first
+    /// occurrence of each. See [crate::grouped_collections::GroupedCollection::dedup_values].
+    pub unique: bool,
+
+    /// If `Some`, drop groups with fewer values than this after grouping (and after removing
+    /// duplicates, if [GroupByOptions::unique] is true). Dropped groups aren't printed, and no
+    /// commands run over them.
+    pub min_size: Option<usize>,
+
+    /// If `Some`, drop groups with more values than this, like [GroupByOptions::min_size].
+    pub max_size: Option<usize>,
+
+    /// If true, collapse identical values within each group into one value prefixed with its
+    /// count, e.g. `3 foo.txt`, after dropping groups outside [GroupByOptions::min_size] and
+    /// [GroupByOptions::max_size]. See [crate::command_line::value_counts] for details.
+    pub count_values: bool,
+
     pub output: OutputOptions,
+
+    /// The seed for every randomized feature. If `None`, each run uses a different, unpredictable
+    /// seed. See [crate::random] for details.
+    pub seed: Option<u64>,
+
+    /// If true, describe what would be done, i.e. print [crate::command_line::explain::explain],
+    /// instead of doing it.
+    pub explain: bool,
+}
+
+/// Options for the `bench` subcommand. See [crate::command_line::bench] for details.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BenchOptions {
+    /// The number of items of synthetic input to generate.
+    pub items: usize,
+
+    /// The number of distinct group keys to spread the items across.
+    pub keys: usize,
+
+    /// If `Some`, also time running this command over each group, as with
+    /// [OutputOptions::run_command].
+    pub run_command: Option<String>,
+
+    /// The seed for generating input. If `None`, each run generates different input.
+    pub seed: Option<u64>,
+}
+
+/// What the command line asks `groupby` to do.
+// There's only ever one Invocation per run, so its size doesn't matter.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Invocation {
+    /// Group input, as usual.
+    Group(GroupByOptions),
+
+    /// Run the `bench` subcommand.
+    Bench(BenchOptions),
+}
+
+impl Default for InputOptions {
+    /// Returns the options that the `groupby` binary uses when no input options are specified:
+    /// read standard input, one token per line, replacing invalid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::options::*;
+    ///
+    /// let options = InputOptions {
+    ///     trim: true,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(options.separator, Separator::Line);
+    /// assert!(options.files.is_empty());
+    /// ```
+    fn default() -> Self {
+        InputOptions {
+            separator: Separator::Line,
+            csv: false,
+            invalid_utf8: InvalidUtf8::Lossy,
+            files: vec![],
+            files0_from: None,
+            with_filename: false,
+            walk: None,
+            tee: None,
+            trim: false,
+            skip_empty: false,
+            limit: None,
+            sample: None,
+        }
+    }
+}
+
+impl Default for OutputOptions {
+    /// Returns the options that the `groupby` binary uses when no output options are specified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::options::*;
+    ///
+    /// let options = OutputOptions {
+    ///     stats: true,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(options.separator, Separator::Line);
+    /// assert!(options.headers);
+    /// ```
+    fn default() -> Self {
+        OutputOptions {
+            separator: Separator::Line,
+            only_group_names: false,
+            run_command: None,
+            shell: None,
+            dry_run: false,
+            parallel: true,
+            jobs: None,
+            max_args: None,
+            max_stdin_bytes: None,
+            nice: None,
+            max_memory: None,
+            max_open_files: None,
+            capture_stderr: false,
+            live: false,
+            incremental: false,
+            with_values: false,
+            failure_policy: FailurePolicy::KeepGoing,
+            headers: true,
+            header_format: None,
+            stats: false,
+            stats_detail: None,
+            timings: false,
+            checkpoint: None,
+            resume: false,
+            sort: KeyOrder::Bytes,
+            group_order: GroupOrder::Key,
+            reverse: false,
+            presorted_streaming: false,
+            stream: false,
+            low_memory: false,
+            intern: false,
+            json: false,
+            count_only: false,
+            format: None,
+            quote: Quote::None,
+        }
+    }
+}
+
+impl Default for GroupByOptions {
+    /// Returns the default [InputOptions] and [OutputOptions], grouping by first character, with
+    /// no labels, size limits, or other processing after grouping. There's no neutral grouper, so
+    /// set [GroupByOptions::grouping] to the one you need.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::options::*;
+    ///
+    /// let options = GroupByOptions {
+    ///     grouping: GroupingSpecifier::FileExtension,
+    ///     unique: true,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(options.output, OutputOptions::default());
+    /// assert_eq!(options.min_size, None);
+    /// ```
+    fn default() -> Self {
+        GroupByOptions {
+            input: InputOptions::default(),
+            grouping: GroupingSpecifier::FirstChars(1),
+            labels: Labels::default(),
+            unique: false,
+            min_size: None,
+            max_size: None,
+            count_values: false,
+            output: OutputOptions::default(),
+            seed: None,
+            explain: false,
+        }
+    }
+}
+
+impl GroupByOptions {
+    /// Returns true if a group of `size` values is within [GroupByOptions::min_size] and
+    /// [GroupByOptions::max_size], so it should be kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::options::*;
+    ///
+    /// let options = GroupByOptions {
+    ///     grouping: GroupingSpecifier::FirstChars(1),
+    ///     min_size: Some(2),
+    ///     ..Default::default()
+    /// };
+    /// assert!(!options.keeps_size(1));
+    /// assert!(options.keeps_size(2));
+    /// assert!(options.keeps_size(100));
+    /// ```
+    pub fn keeps_size(&self, size: usize) -> bool {
+        self.min_size.is_none_or(|min| size >= min) && self.max_size.is_none_or(|max| size <= max)
+    }
+
+    /// Applies the steps that follow grouping, in order: removes duplicate values from each group
+    /// if [GroupByOptions::unique] is true, drops groups whose sizes [GroupByOptions::keeps_size]
+    /// rejects, then counts each group's values if [GroupByOptions::count_values] is true.
+    ///
+    /// Returns the number of duplicate values removed, or `None` if duplicates weren't removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::options::*;
+    /// use groupby::grouped_collections::GroupedCollection;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let options = GroupByOptions {
+    ///     grouping: GroupingSpecifier::FirstChars(1),
+    ///     unique: true,
+    ///     min_size: Some(2),
+    ///     ..Default::default()
+    /// };
+    /// let mut map: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    /// for word in ["ab", "ab", "ac", "bc", "bc"] {
+    ///     map.add(word[..1].to_string(), word.to_string());
+    /// }
+    /// assert_eq!(options.finish_groups(&mut map), Some(2));
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(map["a"], vec!["ab", "ac"]);
+    /// ```
+    pub fn finish_groups<M>(&self, map: &mut M) -> Option<usize>
+    where
+        M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
+    {
+        let duplicates_removed = self.unique.then(|| map.dedup_values());
+        map.retain(|_, values| self.keeps_size(values.len()));
+        if self.count_values {
+            count_values(map);
+        }
+        duplicates_removed
+    }
+}
+
+impl InputOptions {
+    /// Prepares a token for grouping as [InputOptions::trim] and [InputOptions::skip_empty] say:
+    /// returns the token, trimmed if asked, or `None` if it should be skipped.
+    ///
+    /// ```
+    /// use groupby::command_line::options::*;
+    ///
+    /// let options = InputOptions {
+    ///     separator: Separator::Custom(",".to_string()),
+    ///     trim: true,
+    ///     skip_empty: true,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(options.prepare(" apple ".to_string()), Some("apple".to_string()));
+    /// assert_eq!(options.prepare("  ".to_string()), None);
+    /// ```
+    pub fn prepare(&self, token: String) -> Option<String> {
+        let token = if self.trim && token.trim().len() != token.len() {
+            token.trim().to_string()
+        } else {
+            token
+        };
+        (!self.skip_empty || !token.is_empty()).then_some(token)
+    }
+
+    /// Returns true if any option chooses which inputs to read or acts on them as a whole:
+    /// [InputOptions::files], [InputOptions::files0_from], [InputOptions::walk],
+    /// [InputOptions::tee], or [InputOptions::with_filename]. These only apply when `groupby`
+    /// reads its own inputs, not when the caller hands it the input directly.
+    ///
+    /// ```
+    /// use groupby::command_line::options::*;
+    ///
+    /// let options = InputOptions {
+    ///     files: vec!["data.log".into()],
+    ///     ..Default::default()
+    /// };
+    /// assert!(options.selects_inputs());
+    /// assert!(!InputOptions::default().selects_inputs());
+    /// ```
+    pub fn selects_inputs(&self) -> bool {
+        !self.files.is_empty()
+            || self.files0_from.is_some()
+            || self.walk.is_some()
+            || self.tee.is_some()
+            || self.with_filename
+    }
+}
+
+impl OutputOptions {
+    /// Orders `(key, group)` pairs for printing: by key according to [OutputOptions::sort],
+    /// reversed if [OutputOptions::reverse] is true, then by the size that `size` gives each group
+    /// according to [OutputOptions::group_order].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::options::*;
+    ///
+    /// let options = OutputOptions {
+    ///     sort: KeyOrder::Natural,
+    ///     reverse: true,
+    ///     ..Default::default()
+    /// };
+    /// let mut groups = vec![("v2", 1), ("v10", 1), ("v1", 1)];
+    /// options.order_groups(&mut groups, |count| *count);
+    /// assert_eq!(groups, vec![("v10", 1), ("v2", 1), ("v1", 1)]);
+    /// ```
+    pub fn order_groups<K, V>(&self, groups: &mut [(K, V)], size: impl Fn(&V) -> usize)
+    where
+        K: AsRef<str>,
+    {
+        self.sort.sort(groups);
+        if self.reverse {
+            groups.reverse();
+        }
+        self.group_order.sort(groups, size);
+    }
+
+    /// Returns true if group names are printed bare, each followed by a null character and
+    /// nothing else. See [OutputOptions::only_group_names].
+    ///
+    /// ```
+    /// use groupby::command_line::options::*;
+    ///
+    /// let mut options = OutputOptions {
+    ///     separator: Separator::Null,
+    ///     only_group_names: true,
+    ///     stats: true,
+    ///     ..Default::default()
+    /// };
+    /// assert!(options.prints_bare_group_names());
+    ///
+    /// options.run_command = Some("cat".to_string());
+    /// assert!(!options.prints_bare_group_names());
+    /// ```
+    pub fn prints_bare_group_names(&self) -> bool {
+        self.only_group_names && self.separator == Separator::Null && self.run_command.is_none()
+    }
 }
 
 impl Separator {
@@ -135,61 +1055,211 @@ impl Separator {
     /// assert_eq!(Separator::Line.sep(), "\n");
     /// assert_eq!(Separator::Space.sep(), " ");
     /// assert_eq!(Separator::Null.sep(), "\0");
+    /// assert_eq!(Separator::Paragraph.sep(), "\n\n");
     /// ```
     pub fn sep(&self) -> String {
         match self {
             Separator::Line => "\n".to_string(),
             Separator::Space => " ".to_string(),
             Separator::Null => "\0".to_string(),
+            Separator::Paragraph => "\n\n".to_string(),
             Separator::Custom(s) => s.clone(),
         }
     }
 }
 
+impl GroupingSpecifier {
+    /// Returns true if grouping runs shell commands, i.e. if this is a
+    /// [GroupingSpecifier::KeyCommand] or has one among its parts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::options::*;
+    ///
+    /// let command = GroupingSpecifier::KeyCommand("cut -c1".to_string(), 10, None);
+    /// let parts = vec![GroupingSpecifier::FirstChars(1), command];
+    /// assert!(GroupingSpecifier::Composite(parts, "-".to_string()).runs_commands());
+    /// assert!(!GroupingSpecifier::FirstChars(1).runs_commands());
+    /// ```
+    pub fn runs_commands(&self) -> bool {
+        match self {
+            KeyCommand(_, _, _) => true,
+            Composite(specs, _) => specs.iter().any(GroupingSpecifier::runs_commands),
+            IgnoreCase(spec) => spec.runs_commands(),
+            _ => false,
+        }
+    }
+}
+
 /// We can't derive PartialEq and Eq for GroupingSpecifier because Regex is not PartialEq
 /// or Eq, so we manually implement them with the following definitions:
 ///
 /// FirstChars(m) == FirstChars(n) iff m == n
 /// LastChars(m) == LastChars(n) iff m == n
-/// Regex(re1, cg1) == Regex(re2, cg2) iff re1.as_str() == re2.as_str() && cg1 == cg2
+/// FirstBytes(m) == FirstBytes(n) iff m == n
+/// LastBytes(m) == LastBytes(n) iff m == n
+/// Regex(re1, cg1, u1) == Regex(re2, cg2, u2) iff re1.as_str() == re2.as_str() && cg1 == cg2 &&
+///     u1 == u2
+/// KeyValue(s) == KeyValue(t) iff s == t
+/// Field(m, s) == Field(n, t) iff m == n && s == t
+/// NthWord(m, re1) == NthWord(n, re2) iff m == n && re1.as_str() == re2.as_str()
+/// Timestamp(f1, b1) == Timestamp(f2, b2) iff f1 == f2 && b1 == b2
+/// NumericBucket(m) == NumericBucket(n) iff m == n
+/// SizeBucket(b1) == SizeBucket(b2) iff b1 == b2
+/// Mtime(b1) == Mtime(b2) iff b1 == b2
+/// PathComponent(m) == PathComponent(n) iff m == n
+/// Shard(m) == Shard(n) iff m == n
+/// Chunk(m) == Chunk(n) iff m == n
+/// Glob(r1) == Glob(r2) iff r1 == r2
+/// UrlHost(a) == UrlHost(b) iff a == b
+/// JsonKey(p1) == JsonKey(p2) iff p1 == p2
+/// CsvColumn(c1, d1) == CsvColumn(c2, d2) iff c1 == c2 && d1 == d2
+/// Logfmt(k1) == Logfmt(k2) iff k1 == k2
+/// Composite(v1, s) == Composite(v2, t) iff v1 == v2 && s == t
+/// IgnoreCase(s1) == IgnoreCase(s2) iff s1 == s2
 ///
 /// # Examples
 ///
 /// ```
-/// use groupby::command_line::options::{GroupingSpecifier::*, CaptureGroup};
+/// use groupby::command_line::options::{
+///     GroupingSpecifier::*, CaptureGroup, Column, TimeBucket, Unmatched,
+/// };
 /// use regex;
 ///
 /// // Same == same.
 /// assert_eq!(FirstChars(7), FirstChars(7));
 /// assert_eq!(LastChars(8), LastChars(8));
+/// assert_eq!(FirstBytes(7), FirstBytes(7));
+/// assert_eq!(LastBytes(8), LastBytes(8));
 /// assert_eq!(
-///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(4)),
-///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(4))
+///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(4), Unmatched::Empty),
+///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(4), Unmatched::Empty)
 /// );
 /// assert_eq!(FileExtension, FileExtension);
 /// assert_eq!(Counter, Counter);
+/// assert_eq!(KeyValue("=".to_string()), KeyValue("=".to_string()));
+/// assert_eq!(Field(2, ":".to_string()), Field(2, ":".to_string()));
+/// assert_eq!(
+///     NthWord(2, regex::Regex::new("\\w+").unwrap()),
+///     NthWord(2, regex::Regex::new("\\w+").unwrap())
+/// );
+/// assert_eq!(
+///     Timestamp("%Y".parse().unwrap(), TimeBucket::Year),
+///     Timestamp("%Y".parse().unwrap(), TimeBucket::Year)
+/// );
+/// assert_eq!(NumericBucket(100), NumericBucket(100));
+/// assert_eq!(SizeBucket("1K,1M".parse().unwrap()), SizeBucket("1K,1M".parse().unwrap()));
+/// assert_eq!(Mtime(TimeBucket::Month), Mtime(TimeBucket::Month));
+/// assert_eq!(MimeType, MimeType);
+/// assert_eq!(PathComponent(3), PathComponent(3));
+/// assert_eq!(Basename, Basename);
+/// assert_eq!(Dirname, Dirname);
+/// assert_eq!(Length, Length);
+/// assert_eq!(Shard(8), Shard(8));
+/// assert_eq!(Chunk(8), Chunk(8));
+/// assert_eq!(
+///     Glob(vec!["rust=*.rs".parse().unwrap()]),
+///     Glob(vec!["rust=*.rs".parse().unwrap()])
+/// );
+/// assert_eq!(UrlHost(true), UrlHost(true));
+/// assert_eq!(JsonKey(".a".parse().unwrap()), JsonKey(".a".parse().unwrap()));
+/// assert_eq!(CsvColumn(Column::Index(2), ','), CsvColumn(Column::Index(2), ','));
+/// assert_eq!(Logfmt("level".to_string()), Logfmt("level".to_string()));
+/// assert_eq!(
+///     Composite(vec![Length, FileExtension], " ".to_string()),
+///     Composite(vec![Length, FileExtension], " ".to_string())
+/// );
+/// assert_eq!(IgnoreCase(Box::new(FileExtension)), IgnoreCase(Box::new(FileExtension)));
 ///
 /// // Same variant with different contained values are !=.
 /// assert_ne!(FirstChars(7), FirstChars(8));
 /// assert_ne!(LastChars(8), LastChars(9));
+/// assert_ne!(FirstBytes(7), FirstBytes(8));
+/// assert_ne!(FirstBytes(7), FirstChars(7));
+/// assert_ne!(LastBytes(8), LastBytes(9));
+/// assert_ne!(LastBytes(8), FirstBytes(8));
+/// assert_ne!(KeyValue("=".to_string()), KeyValue(":".to_string()));
+/// assert_ne!(Field(2, ":".to_string()), Field(3, ":".to_string()));
+/// assert_ne!(Field(2, ":".to_string()), Field(2, ",".to_string()));
+/// assert_ne!(
+///     NthWord(2, regex::Regex::new("\\w+").unwrap()),
+///     NthWord(3, regex::Regex::new("\\w+").unwrap())
+/// );
 /// assert_ne!(
-///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(0)),
-///     Regex(regex::Regex::new("bar").unwrap(), CaptureGroup::Number(0))
+///     NthWord(2, regex::Regex::new("\\w+").unwrap()),
+///     NthWord(2, regex::Regex::new("\\S+").unwrap())
 /// );
 /// assert_ne!(
-///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(0)),
-///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(1))
+///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(0), Unmatched::Empty),
+///     Regex(regex::Regex::new("bar").unwrap(), CaptureGroup::Number(0), Unmatched::Empty)
+/// );
+/// assert_ne!(
+///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(0), Unmatched::Empty),
+///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(1), Unmatched::Empty)
+/// );
+/// assert_ne!(
+///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(0), Unmatched::Empty),
+///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(0), Unmatched::Skip)
 /// );
 ///
 /// // Different variants are !=.
-/// assert_ne!(FirstChars(7), Regex(regex::Regex::new("bar").unwrap(), CaptureGroup::Number(0)));
+/// assert_ne!(FirstChars(7), Regex(regex::Regex::new("bar").unwrap(), CaptureGroup::Number(0), Unmatched::Empty));
 /// assert_ne!(LastChars(8), FirstChars(8));
 /// assert_ne!(
-///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(3)),
+///     Regex(regex::Regex::new("foo").unwrap(), CaptureGroup::Number(3), Unmatched::Empty),
 ///     LastChars(9)
 /// );
 /// assert_ne!(FirstChars(7), FileExtension);
 /// assert_ne!(FileExtension, Counter);
+/// assert_ne!(Counter, KeyValue("=".to_string()));
+/// assert_ne!(KeyValue(":".to_string()), Field(1, ":".to_string()));
+/// assert_ne!(Field(1, ":".to_string()), NthWord(1, regex::Regex::new(":").unwrap()));
+/// assert_ne!(
+///     Timestamp("%Y".parse().unwrap(), TimeBucket::Year),
+///     Timestamp("%Y".parse().unwrap(), TimeBucket::Month)
+/// );
+/// assert_ne!(Timestamp("%Y".parse().unwrap(), TimeBucket::Year), Counter);
+/// assert_ne!(NumericBucket(10), NumericBucket(100));
+/// assert_ne!(NumericBucket(10), FirstChars(10));
+/// assert_ne!(SizeBucket("1K".parse().unwrap()), SizeBucket("1M".parse().unwrap()));
+/// assert_ne!(SizeBucket("1K".parse().unwrap()), NumericBucket(1024));
+/// assert_ne!(Mtime(TimeBucket::Day), Mtime(TimeBucket::Week));
+/// assert_ne!(Mtime(TimeBucket::Day), Timestamp("%Y-%m-%d".parse().unwrap(), TimeBucket::Day));
+/// assert_ne!(MimeType, FileExtension);
+/// assert_ne!(PathComponent(1), PathComponent(2));
+/// assert_ne!(PathComponent(1), Field(1, "/".to_string()));
+/// assert_ne!(Basename, Dirname);
+/// assert_ne!(Length, Counter);
+/// assert_ne!(Shard(2), Shard(3));
+/// assert_ne!(Shard(2), NumericBucket(2));
+/// assert_ne!(Chunk(2), Chunk(3));
+/// assert_ne!(Chunk(2), Shard(2));
+/// assert_ne!(
+///     Glob(vec!["rust=*.rs".parse().unwrap()]),
+///     Glob(vec!["code=*.rs".parse().unwrap()])
+/// );
+/// assert_ne!(Glob(vec![]), FileExtension);
+/// assert_ne!(UrlHost(true), UrlHost(false));
+/// assert_ne!(UrlHost(false), Basename);
+/// assert_ne!(JsonKey(".a".parse().unwrap()), JsonKey(".b".parse().unwrap()));
+/// assert_ne!(JsonKey(".a".parse().unwrap()), KeyValue(".a".to_string()));
+/// assert_ne!(CsvColumn(Column::Index(2), ','), CsvColumn(Column::Index(3), ','));
+/// assert_ne!(CsvColumn(Column::Index(2), ','), CsvColumn(Column::Index(2), ';'));
+/// assert_ne!(CsvColumn(Column::Index(2), ','), Field(2, ",".to_string()));
+/// assert_ne!(Logfmt("level".to_string()), Logfmt("msg".to_string()));
+/// assert_ne!(Logfmt("=".to_string()), KeyValue("=".to_string()));
+/// assert_ne!(
+///     Composite(vec![Length, FileExtension], " ".to_string()),
+///     Composite(vec![FileExtension, Length], " ".to_string())
+/// );
+/// assert_ne!(
+///     Composite(vec![Length, FileExtension], " ".to_string()),
+///     Composite(vec![Length, FileExtension], "/".to_string())
+/// );
+/// assert_ne!(Composite(vec![Length], " ".to_string()), Length);
+/// assert_ne!(IgnoreCase(Box::new(FirstChars(1))), IgnoreCase(Box::new(FirstChars(2))));
+/// assert_ne!(IgnoreCase(Box::new(FirstChars(1))), FirstChars(1));
 /// ```
 impl PartialEq for GroupingSpecifier {
     fn eq(&self, other: &Self) -> bool {
@@ -202,12 +1272,108 @@ impl PartialEq for GroupingSpecifier {
                 LastChars(n) => m == n,
                 _ => false,
             },
-            Regex(re1, cg1) => match other {
-                Regex(re2, cg2) => re1.as_str() == re2.as_str() && cg1 == cg2,
+            FirstBytes(m) => match other {
+                FirstBytes(n) => m == n,
+                _ => false,
+            },
+            LastBytes(m) => match other {
+                LastBytes(n) => m == n,
+                _ => false,
+            },
+            Regex(re1, cg1, u1) => match other {
+                Regex(re2, cg2, u2) => re1.as_str() == re2.as_str() && cg1 == cg2 && u1 == u2,
+                _ => false,
+            },
+            RegexRules(r1, cg1, u1) => match other {
+                RegexRules(r2, cg2, u2) => r1 == r2 && cg1 == cg2 && u1 == u2,
                 _ => false,
             },
             FileExtension => matches!(other, FileExtension),
             Counter => matches!(other, Counter),
+            MimeType => matches!(other, MimeType),
+            Basename => matches!(other, Basename),
+            Dirname => matches!(other, Dirname),
+            Length => matches!(other, Length),
+            KeyValue(s) => match other {
+                KeyValue(t) => s == t,
+                _ => false,
+            },
+            Field(m, s) => match other {
+                Field(n, t) => m == n && s == t,
+                _ => false,
+            },
+            NthWord(m, re1) => match other {
+                NthWord(n, re2) => m == n && re1.as_str() == re2.as_str(),
+                _ => false,
+            },
+            Timestamp(f1, b1) => match other {
+                Timestamp(f2, b2) => f1 == f2 && b1 == b2,
+                _ => false,
+            },
+            NumericBucket(m) => match other {
+                NumericBucket(n) => m == n,
+                _ => false,
+            },
+            SizeBucket(b1) => match other {
+                SizeBucket(b2) => b1 == b2,
+                _ => false,
+            },
+            Mtime(b1) => match other {
+                Mtime(b2) => b1 == b2,
+                _ => false,
+            },
+            PathComponent(m) => match other {
+                PathComponent(n) => m == n,
+                _ => false,
+            },
+            Shard(m) => match other {
+                Shard(n) => m == n,
+                _ => false,
+            },
+            Chunk(m) => match other {
+                Chunk(n) => m == n,
+                _ => false,
+            },
+            Glob(r1) => match other {
+                Glob(r2) => r1 == r2,
+                _ => false,
+            },
+            UrlHost(a) => match other {
+                UrlHost(b) => a == b,
+                _ => false,
+            },
+            JsonKey(p1) => match other {
+                JsonKey(p2) => p1 == p2,
+                _ => false,
+            },
+            CsvColumn(c1, d1) => match other {
+                CsvColumn(c2, d2) => c1 == c2 && d1 == d2,
+                _ => false,
+            },
+            Logfmt(k1) => match other {
+                Logfmt(k2) => k1 == k2,
+                _ => false,
+            },
+            KeyExpr(e1) => match other {
+                KeyExpr(e2) => e1 == e2,
+                _ => false,
+            },
+            KeyCommand(c1, b1, s1) => match other {
+                KeyCommand(c2, b2, s2) => c1 == c2 && b1 == b2 && s1 == s2,
+                _ => false,
+            },
+            Composite(v1, s) => match other {
+                Composite(v2, t) => v1 == v2 && s == t,
+                _ => false,
+            },
+            IgnoreCase(s1) => match other {
+                IgnoreCase(s2) => s1 == s2,
+                _ => false,
+            },
+            Named(n1) => match other {
+                Named(n2) => n1 == n2,
+                _ => false,
+            },
         }
     }
 }
@@ -1,18 +1,35 @@
 //! Parses args from [args](mod@super::args) into [GroupByOptions].
 
+use crate::command_line::args::GROUPERS;
+use crate::command_line::error::GroupByError;
+use crate::command_line::key_command;
+use crate::command_line::labels::LabelRule;
 use crate::command_line::options::*;
+use crate::command_line::profiles;
+use crate::command_line::size_buckets::parse_size;
+use crate::groupers::registry;
 use clap::{ArgMatches, Command};
 use num::Num;
 use regex::{self, Regex};
+use std::path::PathBuf;
 use std::str::FromStr;
 
 // A testable function that holds the main logic of parse().
-fn parse_from<M>(command: Command<'static>, matcher: M) -> GroupByOptions
+fn parse_from<M>(command: Command<'static>, matcher: M) -> Result<GroupByOptions, GroupByError>
 where
     M: FnOnce(Command<'static>) -> ArgMatches,
 {
     let matches = matcher(command);
 
+    // Delimiters interpret escape sequences unless the user asks for them literally.
+    let delimiter = |s: &str| {
+        if matches.is_present("general_literal") {
+            Ok(s.to_string())
+        } else {
+            unescape(s)
+        }
+    };
+
     // Note: clap knows to validate groupings, e.g. "exactly one" or "zero or one" of a given
     // group. The logic below does not need to check for this.
 
@@ -22,15 +39,42 @@ where
             Separator::Space
         } else if matches.is_present("input_split_on_null") {
             Separator::Null
+        } else if matches.is_present("input_split_on_paragraphs") {
+            Separator::Paragraph
         } else if matches.is_present("input_split_on_custom") {
-            let s = matches
-                .value_of("input_split_on_custom")
-                .unwrap()
-                .to_string();
+            let s = delimiter(matches.value_of("input_split_on_custom").unwrap())?;
             Separator::Custom(s)
         } else {
             Separator::Line
         },
+        csv: matches.is_present("input_csv"),
+        invalid_utf8: match matches.value_of("input_invalid_utf8") {
+            Some("skip") => InvalidUtf8::Skip,
+            Some("strict") => InvalidUtf8::Strict,
+            _ => InvalidUtf8::Lossy,
+        },
+        files: matches
+            .values_of_os("input_files")
+            .map(|files| files.map(PathBuf::from).collect())
+            .unwrap_or_default(),
+        files0_from: matches.value_of_os("input_files0_from").map(PathBuf::from),
+        with_filename: matches.is_present("input_with_filename"),
+        walk: matches.value_of_os("input_walk").map(|root| Walk {
+            root: PathBuf::from(root),
+            hidden: matches.is_present("input_hidden"),
+            follow_symlinks: matches.is_present("input_follow_symlinks"),
+        }),
+        tee: matches.value_of_os("input_tee").map(PathBuf::from),
+        trim: matches.is_present("input_trim"),
+        skip_empty: matches.is_present("input_skip_empty"),
+        limit: matches
+            .is_present("input_limit")
+            .then(|| parse_numeric_value(&matches, "input_limit"))
+            .transpose()?,
+        sample: matches
+            .is_present("input_sample")
+            .then(|| parse_value(&matches, "input_sample"))
+            .transpose()?,
     };
 
     // Dummy match statement. If you're seeing an error here, you probably just added a Separator
@@ -40,30 +84,220 @@ where
     match Separator::Space {
         Separator::Space => (),
         Separator::Null => (),
+        Separator::Paragraph => (),
         Separator::Custom(_) => (),
         Separator::Line => (),
     };
 
-    // Parse grouping specifier.
-    let grouping = if matches.is_present("groupers_by_first_chars") {
-        let n = parse_numeric_value(&matches, "groupers_by_first_chars");
-        GroupingSpecifier::FirstChars(n)
-    } else if matches.is_present("groupers_by_last_chars") {
-        let n = parse_numeric_value(&matches, "groupers_by_last_chars");
-        GroupingSpecifier::LastChars(n)
-    } else if matches.is_present("groupers_by_regex") {
-        let re = parse_regex_value(&matches, "groupers_by_regex");
-        let cg = parse_capture_group(&matches);
-        GroupingSpecifier::Regex(re, cg)
-    } else if matches.is_present("groupers_by_file_extension") {
-        GroupingSpecifier::FileExtension
-    } else if matches.is_present("groupers_by_counter") {
-        GroupingSpecifier::Counter
-    } else {
-        panic!(
+    // Parse grouping specifier. Each grouper the user chose is parsed on its own; several make a
+    // composite key, in the order they appear on the command line.
+    let grouper = |id: &str| -> Result<GroupingSpecifier, GroupByError> {
+        Ok(match id {
+            "groupers_by_first_chars" => {
+                let n = parse_numeric_value(&matches, "groupers_by_first_chars")?;
+                if matches.is_present("grouper_options_bytes") {
+                    GroupingSpecifier::FirstBytes(n)
+                } else {
+                    GroupingSpecifier::FirstChars(n)
+                }
+            }
+            "groupers_by_last_chars" => {
+                let n = parse_numeric_value(&matches, "groupers_by_last_chars")?;
+                if matches.is_present("grouper_options_bytes") {
+                    GroupingSpecifier::LastBytes(n)
+                } else {
+                    GroupingSpecifier::LastChars(n)
+                }
+            }
+            "groupers_by_regex" => {
+                let joiner = delimiter(matches.value_of("grouper_options_key_sep").unwrap())?;
+                let cg = parse_capture_group(&matches, joiner)?;
+                let unmatched = matches
+                    .is_present("grouper_options_unmatched")
+                    .then(|| parse_value(&matches, "grouper_options_unmatched"))
+                    .transpose()?
+                    .unwrap_or_default();
+                // A single -r is always a plain pattern, so only repeated ones can have names.
+                let patterns = matches.values_of("groupers_by_regex").unwrap();
+                if patterns.len() == 1 {
+                    let re = parse_regex_value(&matches, "groupers_by_regex")?;
+                    check_capture_group(&re, &cg)?;
+                    GroupingSpecifier::Regex(re, cg, unmatched)
+                } else {
+                    let rules: Vec<RegexRule> = patterns
+                        .map(|rule| rule.parse().map_err(invalid))
+                        .collect::<Result<_, _>>()?;
+                    // Named rules don't use the capture group.
+                    for rule in rules.iter().filter(|rule| rule.name.is_none()) {
+                        check_capture_group(&rule.regex, &cg)?;
+                    }
+                    GroupingSpecifier::RegexRules(rules, cg, unmatched)
+                }
+            }
+            "groupers_by_file_extension" => GroupingSpecifier::FileExtension,
+            "groupers_by_counter" => GroupingSpecifier::Counter,
+            "groupers_by_key_value" => {
+                let sep = delimiter(matches.value_of("groupers_by_key_value").unwrap())?;
+                if sep.is_empty() {
+                    return Err(invalid("Key-value separators can't be empty"));
+                }
+                GroupingSpecifier::KeyValue(sep)
+            }
+            "groupers_by_field" => {
+                let n = parse_numeric_value(&matches, "groupers_by_field")?;
+                if n == 0 {
+                    return Err(invalid("Fields are numbered from 1, but got: 0"));
+                }
+                let delim = delimiter(
+                    matches
+                        .value_of("grouper_options_field_sep")
+                        .unwrap_or("\t"),
+                )?;
+                GroupingSpecifier::Field(n, delim)
+            }
+            "groupers_by_nth_word" => {
+                let n = parse_numeric_value(&matches, "groupers_by_nth_word")?;
+                if n == 0 {
+                    return Err(invalid("Words are numbered from 1, but got: 0"));
+                }
+                let re = parse_regex_value(&matches, "grouper_options_word_regex")?;
+                GroupingSpecifier::NthWord(n, re)
+            }
+            "groupers_by_timestamp" => {
+                let format = parse_value(&matches, "groupers_by_timestamp")?;
+                let bucket = parse_value(&matches, "grouper_options_bucket")?;
+                check_timestamp_bucket(&format, bucket)?;
+                GroupingSpecifier::Timestamp(format, bucket)
+            }
+            "groupers_by_numeric_bucket" => {
+                let size = parse_numeric_value(&matches, "groupers_by_numeric_bucket")?;
+                if size == 0 {
+                    return Err(invalid("Bucket sizes must be at least 1, but got: 0"));
+                }
+                GroupingSpecifier::NumericBucket(size)
+            }
+            "groupers_by_size_bucket" => {
+                GroupingSpecifier::SizeBucket(parse_value(&matches, "groupers_by_size_bucket")?)
+            }
+            "groupers_by_mtime" => {
+                GroupingSpecifier::Mtime(parse_value(&matches, "groupers_by_mtime")?)
+            }
+            "groupers_by_mime_type" => GroupingSpecifier::MimeType,
+            "groupers_by_path_component" => {
+                let depth = parse_numeric_value(&matches, "groupers_by_path_component")?;
+                if depth == 0 {
+                    return Err(invalid("Path depths are numbered from 1, but got: 0"));
+                }
+                GroupingSpecifier::PathComponent(depth)
+            }
+            "groupers_by_basename" => GroupingSpecifier::Basename,
+            "groupers_by_dirname" => GroupingSpecifier::Dirname,
+            "groupers_by_length" => GroupingSpecifier::Length,
+            "groupers_by_shard" => {
+                let shards = parse_numeric_value(&matches, "groupers_by_shard")?;
+                if shards == 0 {
+                    return Err(invalid(
+                        "The number of shards must be at least 1, but got: 0",
+                    ));
+                }
+                GroupingSpecifier::Shard(shards)
+            }
+            "groupers_by_chunk" => {
+                let size = parse_numeric_value(&matches, "groupers_by_chunk")?;
+                if size == 0 {
+                    return Err(invalid("Chunk sizes must be at least 1, but got: 0"));
+                }
+                GroupingSpecifier::Chunk(size)
+            }
+            "groupers_by_glob" => {
+                let rules = matches.values_of("groupers_by_glob").unwrap();
+                GroupingSpecifier::Glob(rules.map(parse_glob_rule).collect::<Result<_, _>>()?)
+            }
+            "groupers_by_url_host" => {
+                GroupingSpecifier::UrlHost(matches.is_present("grouper_options_domain"))
+            }
+            "groupers_by_json_key" => {
+                GroupingSpecifier::JsonKey(parse_value(&matches, "groupers_by_json_key")?)
+            }
+            "groupers_by_csv_column" => {
+                let column = parse_value(&matches, "groupers_by_csv_column")?;
+                if let Column::Name(name) = &column {
+                    if !matches.is_present("input_csv") {
+                        return Err(invalid(format!(
+                        "Column names like {} are looked up in a header row, so they need --csv",
+                        name
+                    )));
+                    }
+                }
+                let delimiter =
+                    delimiter(matches.value_of("grouper_options_field_sep").unwrap_or(","))?;
+                let mut chars = delimiter.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => GroupingSpecifier::CsvColumn(column, c),
+                    _ => {
+                        return Err(invalid(format!(
+                            "CSV delimiters must be a single character, but got: {}",
+                            delimiter
+                        )))
+                    }
+                }
+            }
+            "groupers_by_logfmt" => {
+                let key = matches.value_of("groupers_by_logfmt").unwrap();
+                GroupingSpecifier::Logfmt(key.to_string())
+            }
+            "groupers_by_key_expr" => {
+                GroupingSpecifier::KeyExpr(parse_value(&matches, "groupers_by_key_expr")?)
+            }
+            "groupers_by_key_command" => {
+                let command = matches.value_of("groupers_by_key_command").unwrap();
+                let batch = if matches.is_present("grouper_options_key_batch") {
+                    parse_numeric_value(&matches, "grouper_options_key_batch")?
+                } else {
+                    key_command::DEFAULT_BATCH
+                };
+                if batch == 0 {
+                    return Err(invalid("--key-batch must be at least 1, but got: 0"));
+                }
+                let shell = matches.value_of("output_shell").map(str::to_string);
+                GroupingSpecifier::KeyCommand(command.to_string(), batch, shell)
+            }
+            "groupers_by_named" => {
+                let name = matches.value_of("groupers_by_named").unwrap();
+                if registry::get(name).is_none() {
+                    let names = registry::names();
+                    return Err(invalid(if names.is_empty() {
+                        format!("No grouper is registered as {}; none are registered", name)
+                    } else {
+                        format!(
+                            "No grouper is registered as {}; the registered groupers are: {}",
+                            name,
+                            names.join(", ")
+                        )
+                    }));
+                }
+                GroupingSpecifier::Named(name.to_string())
+            }
+            _ => panic!("Unknown grouper {}. Please report this!", id),
+        })
+    };
+    let mut ids: Vec<&str> = GROUPERS
+        .iter()
+        .copied()
+        .filter(|id| matches.is_present(id))
+        .collect();
+    ids.sort_by_key(|id| matches.index_of(id));
+    let grouping = match ids.as_slice() {
+        [] => panic!(
             "No grouping option was specified, but the argument parser didn't catch \
             the issue. Please report this!"
-        );
+        ),
+        [id] => grouper(id)?,
+        ids => {
+            let separator = delimiter(matches.value_of("grouper_options_key_sep").unwrap())?;
+            let specs = ids.iter().map(|id| grouper(id)).collect::<Result<_, _>>()?;
+            GroupingSpecifier::Composite(specs, separator)
+        }
     };
 
     // Dummy match statement. If you're seeing an error here, you probably just added a
@@ -73,11 +307,53 @@ where
     match GroupingSpecifier::FirstChars(4) {
         GroupingSpecifier::FirstChars(_) => (),
         GroupingSpecifier::LastChars(_) => (),
-        GroupingSpecifier::Regex(_, _) => (),
+        GroupingSpecifier::FirstBytes(_) => (),
+        GroupingSpecifier::LastBytes(_) => (),
+        GroupingSpecifier::Regex(_, _, _) => (),
+        GroupingSpecifier::RegexRules(_, _, _) => (),
         GroupingSpecifier::FileExtension => (),
         GroupingSpecifier::Counter => (),
+        GroupingSpecifier::KeyValue(_) => (),
+        GroupingSpecifier::Field(_, _) => (),
+        GroupingSpecifier::NthWord(_, _) => (),
+        GroupingSpecifier::Timestamp(_, _) => (),
+        GroupingSpecifier::NumericBucket(_) => (),
+        GroupingSpecifier::SizeBucket(_) => (),
+        GroupingSpecifier::Mtime(_) => (),
+        GroupingSpecifier::MimeType => (),
+        GroupingSpecifier::PathComponent(_) => (),
+        GroupingSpecifier::Basename => (),
+        GroupingSpecifier::Dirname => (),
+        GroupingSpecifier::Length => (),
+        GroupingSpecifier::Shard(_) => (),
+        GroupingSpecifier::Chunk(_) => (),
+        GroupingSpecifier::Glob(_) => (),
+        GroupingSpecifier::UrlHost(_) => (),
+        GroupingSpecifier::JsonKey(_) => (),
+        GroupingSpecifier::CsvColumn(_, _) => (),
+        GroupingSpecifier::Logfmt(_) => (),
+        GroupingSpecifier::KeyExpr(_) => (),
+        GroupingSpecifier::KeyCommand(_, _, _) => (),
+        GroupingSpecifier::Composite(_, _) => (),
+        GroupingSpecifier::IgnoreCase(_) => (),
+        GroupingSpecifier::Named(_) => (),
     };
 
+    // Parse grouper options that apply to every grouper.
+    let grouping = if matches.is_present("grouper_options_ignore_case") {
+        GroupingSpecifier::IgnoreCase(Box::new(grouping))
+    } else {
+        grouping
+    };
+
+    let labels = Labels::new(
+        matches
+            .values_of("grouper_options_label")
+            .map(|rules| rules.map(parse_label_rule).collect::<Result<_, _>>())
+            .transpose()?
+            .unwrap_or_default(),
+    );
+
     // Parse output options. The nested scope prevents name confusion with nested options.
     let output;
     {
@@ -85,6 +361,8 @@ where
             Separator::Space
         } else if matches.is_present("output_null_separators") {
             Separator::Null
+        } else if let Some(s) = matches.value_of("output_custom_separators") {
+            Separator::Custom(delimiter(s)?)
         } else {
             Separator::Line
         };
@@ -95,19 +373,181 @@ where
         // Option<String>, so we can't just unwrap.
         let run_command = matches.value_of("output_run_command").map(str::to_string);
 
+        let shell = matches.value_of("output_shell").map(str::to_string);
+
+        let dry_run = matches.is_present("output_dry_run");
+
         let parallel = !matches.is_present("output_sequential");
 
+        let jobs = matches
+            .is_present("output_jobs")
+            .then(|| parse_numeric_value(&matches, "output_jobs"))
+            .transpose()?;
+        if jobs == Some(0) {
+            return Err(invalid("The number of jobs must be at least 1, but got: 0"));
+        }
+
+        let max_args = matches
+            .is_present("output_max_args")
+            .then(|| parse_numeric_value(&matches, "output_max_args"))
+            .transpose()?;
+        if max_args == Some(0) {
+            return Err(invalid(
+                "The maximum number of arguments must be at least 1, but got: 0",
+            ));
+        }
+
+        let max_stdin_bytes = matches
+            .is_present("output_max_stdin_bytes")
+            .then(|| parse_numeric_value(&matches, "output_max_stdin_bytes"))
+            .transpose()?;
+        if max_stdin_bytes == Some(0) {
+            return Err(invalid(
+                "The maximum number of bytes must be at least 1, but got: 0",
+            ));
+        }
+
+        let nice = matches
+            .is_present("output_nice")
+            .then(|| parse_numeric_value(&matches, "output_nice"))
+            .transpose()?;
+        if let Some(nice) = nice.filter(|nice| !(-20..=19).contains(nice)) {
+            return Err(invalid(format!(
+                "Niceness must be from -20 to 19, but got: {}",
+                nice
+            )));
+        }
+
+        let max_memory = matches
+            .value_of("output_max_memory")
+            .map(parse_size)
+            .transpose()
+            .map_err(invalid)?;
+
+        let max_open_files = matches
+            .is_present("output_max_open_files")
+            .then(|| parse_numeric_value(&matches, "output_max_open_files"))
+            .transpose()?;
+
+        let capture_stderr = matches.is_present("output_capture_stderr");
+
+        let live = matches.is_present("output_live");
+
+        let incremental = matches.is_present("output_incremental");
+
+        let with_values = matches.is_present("output_with_values");
+
+        let failure_policy = if matches.is_present("output_fail_fast") {
+            FailurePolicy::FailFast
+        } else {
+            FailurePolicy::KeepGoing
+        };
+
         let headers = !matches.is_present("output_no_headers");
 
         let stats = matches.is_present("output_stats");
 
+        let stats_detail = matches
+            .value_of("output_stats_detail")
+            .map(parse_percentiles)
+            .transpose()?;
+
+        let timings = matches.is_present("output_timings");
+
+        let checkpoint = matches.value_of("output_checkpoint").map(str::to_string);
+
+        let resume = matches.is_present("output_resume");
+
+        // --sort-groups natural and numeric are shorthand for the same --sort orders.
+        let group_sort = match matches.value_of("output_sort_groups") {
+            Some("natural") => Some(KeyOrder::Natural),
+            Some("numeric") => Some(KeyOrder::Numeric),
+            _ => None,
+        };
+        let sort = match (matches.value_of("output_sort"), group_sort) {
+            (Some(_), Some(_)) => {
+                return Err(invalid(
+                    "--sort-groups natural and numeric can't be combined with --sort",
+                ))
+            }
+            (None, Some(order)) => order,
+            (Some("collate"), _) => KeyOrder::Collate,
+            (Some("locale"), _) => KeyOrder::Locale(Tailoring::from_env()),
+            (Some("natural"), _) => KeyOrder::Natural,
+            (Some("numeric"), _) => KeyOrder::Numeric,
+            (Some(_), _) => KeyOrder::Bytes,
+            (None, None) => default_key_order(&grouping),
+        };
+
+        let group_order = match matches.value_of("output_sort_groups") {
+            Some("size") => GroupOrder::Size,
+            Some("size-desc") => GroupOrder::SizeDescending,
+            _ => GroupOrder::Key,
+        };
+
+        let reverse = matches.is_present("output_reverse");
+
+        let presorted_streaming = matches.is_present("output_presorted_streaming");
+
+        let stream = matches.is_present("output_stream");
+
+        let low_memory = matches.is_present("output_low_memory");
+
+        let intern = matches.is_present("output_intern");
+
+        let json = matches.is_present("output_json");
+
+        let count_only = matches.is_present("output_count_only");
+
+        let format = parse_template(&matches, "output_format", run_command.is_some())?;
+
+        let header_format = if matches.is_present("output_no_header_colon") {
+            Some("{key}".parse().unwrap())
+        } else {
+            parse_template(&matches, "output_header_format", run_command.is_some())?
+        };
+
+        let quote = match matches.value_of("output_quote") {
+            Some("shell") => Quote::Shell,
+            _ => Quote::None,
+        };
+
         output = OutputOptions {
             separator,
             only_group_names,
             run_command,
+            shell,
+            dry_run,
             parallel,
+            jobs,
+            max_args,
+            max_stdin_bytes,
+            nice,
+            max_memory,
+            max_open_files,
+            capture_stderr,
+            live,
+            incremental,
+            with_values,
+            failure_policy,
             headers,
+            header_format,
             stats,
+            stats_detail,
+            timings,
+            checkpoint,
+            resume,
+            sort,
+            group_order,
+            reverse,
+            presorted_streaming,
+            stream,
+            low_memory,
+            intern,
+            json,
+            count_only,
+            format,
+            quote,
         };
     }
 
@@ -118,55 +558,342 @@ where
     match Separator::Space {
         Separator::Space => (),
         Separator::Null => (),
+        Separator::Paragraph => (),
         Separator::Custom(_) => (),
         Separator::Line => (),
     };
 
-    GroupByOptions {
+    // Parse options that filter groups by size.
+    let min_size = matches
+        .is_present("grouper_options_min_size")
+        .then(|| parse_numeric_value(&matches, "grouper_options_min_size"))
+        .transpose()?;
+    let max_size = matches
+        .is_present("grouper_options_max_size")
+        .then(|| parse_numeric_value(&matches, "grouper_options_max_size"))
+        .transpose()?;
+    if let (Some(min), Some(max)) = (min_size, max_size) {
+        if min > max {
+            return Err(invalid(format!(
+                "--min-size ({}) can't be larger than --max-size ({})",
+                min, max
+            )));
+        }
+    }
+
+    // Parse options that apply across categories.
+    let seed = matches
+        .is_present("general_seed")
+        .then(|| parse_numeric_value(&matches, "general_seed"))
+        .transpose()?;
+
+    Ok(GroupByOptions {
         input,
         grouping,
+        labels,
+        unique: matches.is_present("grouper_options_unique"),
+        min_size,
+        max_size,
+        count_values: matches.is_present("grouper_options_count_values"),
         output,
-    }
+        seed,
+        explain: matches.is_present("general_explain"),
+    })
 }
 
 /// Converts a clap::Command into a [GroupByOptions].
-pub fn parse(command: Command<'static>) -> GroupByOptions {
-    // parse() wraps parse_from() so we can use dependency injection for testing.
-    parse_from(command, |c| c.get_matches())
+///
+/// This doesn't support subcommands, e.g. `groupby bench`; if the command line names one, this
+/// returns an error. To support them, use [parse_invocation] instead.
+///
+/// # Errors
+///
+/// Returns [GroupByError::InvalidArgument] if an argument's value is invalid, e.g. a malformed
+/// regular expression, and [GroupByError::Usage] for syntax errors that [clap] reports, such as
+/// unknown options. `--help` and `--version` are also returned as [GroupByError::Usage], so that
+/// the caller decides when to print them and exit.
+///
+/// [clap]: https://crates.io/crates/clap
+pub fn parse(command: Command<'static>) -> Result<GroupByOptions, GroupByError> {
+    match parse_invocation(command)? {
+        Invocation::Group(options) => Ok(options),
+        Invocation::Bench(_) => Err(invalid("The bench subcommand is not supported here.")),
+    }
+}
+
+/// Converts a clap::Command into an [Invocation], i.e. either a [GroupByOptions] or the options
+/// for a subcommand.
+///
+/// # Errors
+///
+/// Returns an error under the same conditions as [parse], except that subcommands are allowed, or
+/// if the profile selected by `--profile` or the preset selected by `--preset` can't be loaded.
+pub fn parse_invocation(command: Command<'static>) -> Result<Invocation, GroupByError> {
+    // parse_invocation() wraps parse_invocation_from() so we can use dependency injection for
+    // testing.
+    let args = profiles::with_profile(&command, std::env::args_os().collect())?;
+    let args = profiles::with_preset(&command, args)?;
+    parse_invocation_from(command, |c| c.try_get_matches_from(args))
+}
+
+// A testable function that holds the main logic of parse_invocation().
+fn parse_invocation_from<M>(
+    command: Command<'static>,
+    matcher: M,
+) -> Result<Invocation, GroupByError>
+where
+    M: FnOnce(Command<'static>) -> clap::Result<ArgMatches>,
+{
+    let matches = matcher(command).map_err(GroupByError::Usage)?;
+    Ok(match matches.subcommand() {
+        Some(("bench", matches)) => Invocation::Bench(BenchOptions {
+            items: parse_numeric_value(matches, "bench_items")?,
+            keys: parse_numeric_value(matches, "bench_keys")?,
+            run_command: matches.value_of("bench_run_command").map(str::to_string),
+            seed: matches
+                .is_present("bench_seed")
+                .then(|| parse_numeric_value(matches, "bench_seed"))
+                .transpose()?,
+        }),
+        _ => Invocation::Group(parse_from(Command::new("groupby"), |_| matches)?),
+    })
+}
+
+/// Parses command-line arguments, not including the program name, into a [GroupByOptions].
+///
+/// Unlike [parse], this reports invalid arguments as an `Err` value instead of exiting, and it
+/// doesn't apply `--profile`. It's meant for embedding the grouping engine in other programs.
+#[cfg(any(feature = "ffi", feature = "python"))]
+pub(crate) fn try_parse_args<S: AsRef<str>>(args: &[S]) -> Result<GroupByOptions, String> {
+    use crate::command_line::args::command;
+
+    let args = std::iter::once("groupby").chain(args.iter().map(AsRef::as_ref));
+    match parse_invocation_from(command(Command::new("groupby")), |c| {
+        c.try_get_matches_from(args)
+    }) {
+        Ok(Invocation::Group(options)) => Ok(options),
+        Ok(Invocation::Bench(_)) => Err("Subcommands are not supported here".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Extracts the message from a panic's payload, which is usually a String or &str.
+#[cfg(feature = "ffi")]
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else {
+        "Unknown error".to_string()
+    }
 }
 
 // Parses the capture group option.
 //
 // The capture group can be a number or a name, so if it doesn't parse as a usize, we'll assume
-// it's a name.
-fn parse_capture_group(matches: &ArgMatches) -> CaptureGroup {
+// it's a name. Several comma-separated groups are joined with joiner.
+fn parse_capture_group(matches: &ArgMatches, joiner: String) -> Result<CaptureGroup, GroupByError> {
+    let one = |s: &str| match s.parse() {
+        Ok(n) => Ok(CaptureGroup::Number(n)),
+        Err(_) if s.is_empty() => Err(invalid("Capture group names can't be empty")),
+        Err(_) => Ok(CaptureGroup::Name(s.to_string())),
+    };
     match matches.value_of("grouper_options_capture_group") {
-        Some(s) => match s.parse() {
-            Ok(n) => CaptureGroup::Number(n),
-            Err(_) => CaptureGroup::Name(s.to_string()),
-        },
-        None => CaptureGroup::Default,
+        Some(s) if s.contains(',') => Ok(CaptureGroup::Joined(
+            s.split(',').map(one).collect::<Result<_, _>>()?,
+            joiner,
+        )),
+        Some(s) => one(s),
+        None => Ok(CaptureGroup::Default),
     }
 }
 
+// Builds a GroupByError for an invalid argument.
+fn invalid<S: Into<String>>(message: S) -> GroupByError {
+    GroupByError::InvalidArgument(message.into())
+}
+
 // Parses a key with a numeric value; expects that the key is present and has a value.
-fn parse_numeric_value<T>(matches: &ArgMatches, key: &str) -> T
+fn parse_numeric_value<T>(matches: &ArgMatches, key: &str) -> Result<T, GroupByError>
 where
     T: Num + FromStr,
 {
     let s = matches.value_of(key).unwrap();
-    match s.parse() {
-        Ok(n) => n,
-        Err(_) => {
-            panic!("Expected a number, but got: {}", s);
+    s.parse()
+        .map_err(|_| invalid(format!("Expected a number, but got: {}", s)))
+}
+
+// Returns the key order to use when the user doesn't choose one: natural for groupers whose keys
+// are numbers, so that 9 comes before 10, numeric for ranges of numbers, so that -30--21 comes
+// before -10--1, and bytes otherwise.
+fn default_key_order(grouping: &GroupingSpecifier) -> KeyOrder {
+    match grouping {
+        GroupingSpecifier::Length | GroupingSpecifier::Shard(_) | GroupingSpecifier::Chunk(_) => {
+            KeyOrder::Natural
+        }
+        GroupingSpecifier::NumericBucket(_) => KeyOrder::Numeric,
+        GroupingSpecifier::IgnoreCase(spec) => default_key_order(spec),
+        GroupingSpecifier::Composite(specs, _) => {
+            // A composite key isn't a number or a range, but its numeric parts still compare best
+            // by value.
+            if specs
+                .iter()
+                .any(|spec| default_key_order(spec) != KeyOrder::Bytes)
+            {
+                KeyOrder::Natural
+            } else {
+                KeyOrder::Bytes
+            }
         }
+        _ => KeyOrder::Bytes,
     }
 }
 
+// Parses a value with a FromStr implementation whose errors are messages for the user; expects
+// that the key is present and has a value.
+fn parse_value<T>(matches: &ArgMatches, key: &str) -> Result<T, GroupByError>
+where
+    T: FromStr<Err = String>,
+{
+    matches.value_of(key).unwrap().parse().map_err(invalid)
+}
+
+// Parses an output template, if key is present, interpreting escape sequences as delimiters do.
+// Templates that use command output are only allowed if commands will run.
+fn parse_template(
+    matches: &ArgMatches,
+    key: &str,
+    runs_commands: bool,
+) -> Result<Option<Template>, GroupByError> {
+    let template: Option<Template> = match matches.value_of(key) {
+        Some(s) if matches.is_present("general_literal") => Some(s.parse().map_err(invalid)?),
+        Some(s) => Some(unescape(s)?.parse().map_err(invalid)?),
+        None => None,
+    };
+    if !runs_commands && template.as_ref().is_some_and(Template::uses_output) {
+        return Err(invalid(format!(
+            "--{} uses {{output}} or {{stderr}}, which only have values with -c",
+            key.trim_start_matches("output_").replace('_', "-")
+        )));
+    }
+    Ok(template)
+}
+
+// Parses a comma-separated list of percentiles, e.g. "90,99", for --stats-detail.
+fn parse_percentiles(list: &str) -> Result<Vec<u8>, GroupByError> {
+    list.split(',')
+        .map(|percentile| match percentile.trim().parse() {
+            Ok(percentile @ 0..=100) => Ok(percentile),
+            _ => Err(invalid(format!(
+                "Expected a comma-separated list of percentiles from 0 to 100, but got: {}",
+                list
+            ))),
+        })
+        .collect()
+}
+
+/// Parses a `--glob` rule, returning its error message if it's malformed.
+fn parse_glob_rule(rule: &str) -> Result<GlobRule, GroupByError> {
+    rule.parse().map_err(invalid)
+}
+
+// Ensures that a timestamp format has the fields that a time bucket needs.
+fn check_timestamp_bucket(
+    format: &TimestampFormat,
+    bucket: TimeBucket,
+) -> Result<(), GroupByError> {
+    if !format.supports(bucket) {
+        return Err(invalid(format!(
+            "The timestamp format {:?} doesn't have every field needed for {:?} buckets. Please \
+            choose a coarser --bucket.",
+            format.as_str(),
+            bucket
+        )));
+    }
+    Ok(())
+}
+
+// Ensures that a regex has the capture group that the user asked for, so that a typo is caught
+// here rather than quietly grouping every token under "".
+fn check_capture_group(regex: &Regex, capture_group: &CaptureGroup) -> Result<(), GroupByError> {
+    let requested = match capture_group {
+        CaptureGroup::Number(n) if *n >= regex.captures_len() => n.to_string(),
+        CaptureGroup::Name(name) if !regex.capture_names().any(|n| n == Some(name.as_str())) => {
+            format!("named {:?}", name)
+        }
+        CaptureGroup::Joined(groups, _) => {
+            return groups
+                .iter()
+                .try_for_each(|group| check_capture_group(regex, group))
+        }
+        _ => return Ok(()),
+    };
+    let available = regex
+        .capture_names()
+        .enumerate()
+        .map(|(i, name)| match name {
+            Some(name) => format!("{} ({})", i, name),
+            None if i == 0 => "0 (the whole match)".to_string(),
+            None => i.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(invalid(format!(
+        "The regular expression /{}/ has no capture group {}. Its capture groups are: {}",
+        regex.as_str(),
+        requested,
+        available
+    )))
+}
+
 // Parses a regex value; expects that the key is present and has a value.
-fn parse_regex_value(matches: &ArgMatches, key: &str) -> Regex {
+fn parse_regex_value(matches: &ArgMatches, key: &str) -> Result<Regex, GroupByError> {
     let pattern = matches.value_of(key).unwrap();
-    Regex::new(pattern).unwrap() // The provided messages are actually really good.
+    // The provided messages are actually really good.
+    Regex::new(pattern).map_err(|e| invalid(e.to_string()))
+}
+
+// Replaces escape sequences in a delimiter with the characters they stand for: \t, \n, \r, \0,
+// \xNN (ASCII only, so that the result is always valid UTF-8), and \\. Any other backslash is
+// kept as-is, since it's more likely to be meant literally than to be a typo.
+fn unescape(s: &str) -> Result<String, GroupByError> {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next_if(|e| "tnr0x\\".contains(*e)) {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let is_hex = hex.len() == 2 && hex.chars().all(|h| h.is_ascii_hexdigit());
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(n) if is_hex && n.is_ascii() => result.push(n as char),
+                    _ => {
+                        return Err(invalid(format!(
+                            "Expected \\x to be followed by two hex digits from 00 to 7f, but got: \
+                            {}",
+                            s
+                        )))
+                    }
+                }
+            }
+            Some(e) => result.push(e), // A backslash.
+            None => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+// Parses a label rule.
+fn parse_label_rule(rule: &str) -> Result<LabelRule, GroupByError> {
+    rule.parse().map_err(invalid)
 }
 
 #[cfg(test)]
@@ -191,8 +918,10 @@ mod tests {
             T: Eq + Debug,
         {
             let command = args::args();
+            // Report errors by panicking, so that tests of invalid arguments can use should_panic.
             let options =
-                crate::command_line::parse_args::parse_from(command, |c| c.get_matches_from(args));
+                crate::command_line::parse_args::parse_from(command, |c| c.get_matches_from(args))
+                    .unwrap_or_else(|e| panic!("{}", e));
             let parsed_value: T = selector(options);
             assert_eq!(expected, parsed_value);
         }
@@ -219,6 +948,23 @@ mod tests {
             // No long option
         }
 
+        #[test]
+        fn parses_input_split_on_paragraphs() {
+            // Short
+            parses(
+                &vec!["app", "-p", "-f1"],
+                |gbo: GroupByOptions| gbo.input.separator,
+                Separator::Paragraph,
+            );
+
+            // Long
+            parses(
+                &vec!["app", "--paragraphs", "-f1"],
+                |gbo: GroupByOptions| gbo.input.separator,
+                Separator::Paragraph,
+            );
+        }
+
         #[test]
         fn parses_input_split_on_custom() {
             // No short option
@@ -229,188 +975,2148 @@ mod tests {
                 |gbo: GroupByOptions| gbo.input.separator,
                 Separator::Custom("ZyX".to_string()),
             );
-        }
 
-        #[test]
-        fn parses_input_split_default() {
+            // With escape sequences
             parses(
-                &vec!["app", "-f1"],
+                &vec!["app", "--split", "\\t", "-f1"],
                 |gbo: GroupByOptions| gbo.input.separator,
-                Separator::Line,
+                Separator::Custom("\t".to_string()),
             );
-        }
-
-        #[test]
-        fn parses_groupers_by_first_chars() {
-            // Short
             parses(
-                &vec!["app", "-w", "-f8"],
-                |gbo: GroupByOptions| gbo.grouping,
-                GroupingSpecifier::FirstChars(8),
+                &vec!["app", "--split", "\\t", "--literal", "-f1"],
+                |gbo: GroupByOptions| gbo.input.separator,
+                Separator::Custom("\\t".to_string()),
             );
-            // No long option
         }
 
         #[test]
-        fn parses_groupers_by_last_chars() {
-            // Short
+        fn parses_input_csv() {
+            // No short option
+
+            // Long
             parses(
-                &vec!["app", "-w", "-l9"],
-                |gbo: GroupByOptions| gbo.grouping,
-                GroupingSpecifier::LastChars(9),
+                &vec!["app", "--csv", "-f1"],
+                |gbo: GroupByOptions| gbo.input.csv,
+                true,
+            );
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.input.csv,
+                false,
             );
         }
 
         #[test]
-        fn parses_groupers_by_regex() {
-            // Short
+        fn parses_input_files() {
             parses(
-                &vec!["app", "-w", "-r", "foo"],
-                |gbo: GroupByOptions| gbo.grouping,
-                GroupingSpecifier::Regex(Regex::new("foo").unwrap(), CaptureGroup::Default),
+                &vec!["app", "-f1", "a.log", "-", "b.log"],
+                |gbo: GroupByOptions| gbo.input.files,
+                vec![
+                    PathBuf::from("a.log"),
+                    PathBuf::from("-"),
+                    PathBuf::from("b.log"),
+                ],
             );
-
-            // Long
             parses(
-                &vec!["app", "-w", "--regex", "bar"],
-                |gbo: GroupByOptions| gbo.grouping,
-                GroupingSpecifier::Regex(Regex::new("bar").unwrap(), CaptureGroup::Default),
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.input.files,
+                vec![],
             );
         }
 
         #[test]
-        fn parses_groupers_by_file_extension() {
+        fn parses_input_files0_from() {
             // No short option
 
             // Long
             parses(
-                &vec!["app", "-w", "--extension"],
-                |gbo: GroupByOptions| gbo.grouping,
-                GroupingSpecifier::FileExtension,
+                &vec!["app", "--files0-from", "-", "-f1"],
+                |gbo: GroupByOptions| gbo.input.files0_from,
+                Some(PathBuf::from("-")),
             );
-        }
-
-        #[test]
-        fn parses_groupers_by_counter() {
-            // No short option
 
-            // Long
+            // When not specified
             parses(
-                &vec!["app", "-w", "--counter"],
-                |gbo: GroupByOptions| gbo.grouping,
-                GroupingSpecifier::Counter,
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.input.files0_from,
+                None,
             );
         }
 
         #[test]
-        fn parses_output_null_separators() {
+        fn parses_input_walk() {
             // No short option
 
             // Long
             parses(
-                &vec!["app", "--print0", "-f1"],
-                |gbo: GroupByOptions| gbo.output.separator,
-                Separator::Null,
+                &vec!["app", "--walk", "src", "-f1"],
+                |gbo: GroupByOptions| gbo.input.walk,
+                Some(Walk::new("src")),
+            );
+            parses(
+                &vec![
+                    "app",
+                    "--walk",
+                    "src",
+                    "--hidden",
+                    "--follow-symlinks",
+                    "-f1",
+                ],
+                |gbo: GroupByOptions| gbo.input.walk,
+                Some(Walk {
+                    hidden: true,
+                    follow_symlinks: true,
+                    ..Walk::new("src")
+                }),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.input.walk,
+                None,
             );
         }
 
         #[test]
-        fn parses_output_space_separators() {
+        fn parses_input_tee() {
             // No short option
 
-            // Long
+            // Long, without a file
             parses(
-                &vec!["app", "--printspace", "-f1"],
-                |gbo: GroupByOptions| gbo.output.separator,
-                Separator::Space,
+                &vec!["app", "--tee", "-f1", "a.log"],
+                |gbo: GroupByOptions| (gbo.input.tee, gbo.input.files),
+                (Some(PathBuf::from("-")), vec![PathBuf::from("a.log")]),
             );
-        }
 
-        #[test]
-        fn parses_output_default_separators() {
+            // Long, with a file
+            parses(
+                &vec!["app", "--tee=copy.log", "-f1"],
+                |gbo: GroupByOptions| gbo.input.tee,
+                Some(PathBuf::from("copy.log")),
+            );
+
+            // When not specified
             parses(
                 &vec!["app", "-f1"],
-                |gbo: GroupByOptions| gbo.output.separator,
-                Separator::Line,
+                |gbo: GroupByOptions| gbo.input.tee,
+                None,
             );
         }
 
         #[test]
-        fn parses_output_only_group_names() {
-            // No short option
-
-            // Long
+        fn parses_input_trim_and_skip_empty() {
+            // No short options
             parses(
-                &vec!["app", "--only-group-names", "-f1"],
-                |gbo: GroupByOptions| gbo.output.only_group_names,
-                true,
+                &vec!["app", "--trim", "--skip-empty", "-f1"],
+                |gbo: GroupByOptions| (gbo.input.trim, gbo.input.skip_empty),
+                (true, true),
             );
 
             // When not specified
             parses(
                 &vec!["app", "-f1"],
-                |gbo: GroupByOptions| gbo.output.only_group_names,
-                false,
+                |gbo: GroupByOptions| (gbo.input.trim, gbo.input.skip_empty),
+                (false, false),
             );
         }
 
         #[test]
-        fn parses_output_run_command() {
-            // Short
+        fn parses_input_limit_and_sample() {
+            // No short options
             parses(
-                &vec!["app", "-c", "tail | head", "-f1"],
-                |gbo: GroupByOptions| gbo.output.run_command,
-                Some("tail | head".to_string()),
+                &vec!["app", "--limit", "10", "--sample", "5%", "-f1"],
+                |gbo: GroupByOptions| (gbo.input.limit, gbo.input.sample),
+                (Some(10), Some(Fraction(0.05))),
             );
-            // No long option
 
             // When not specified
             parses(
                 &vec!["app", "-f1"],
-                |gbo: GroupByOptions| gbo.output.run_command,
+                |gbo: GroupByOptions| (gbo.input.limit, gbo.input.sample),
+                (None, None),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Expected a fraction from 0 to 1")]
+        fn rejects_input_sample_above_one() {
+            parses(
+                &vec!["app", "--sample", "2", "-f1"],
+                |gbo: GroupByOptions| gbo.input.sample,
                 None,
             );
         }
 
         #[test]
-        fn parses_output_sequential() {
-            // No short option
+        fn parses_input_with_filename() {
+            // Short
+            parses(
+                &vec!["app", "-H", "-f1"],
+                |gbo: GroupByOptions| gbo.input.with_filename,
+                true,
+            );
 
             // Long
             parses(
-                &vec!["app", "--sequential", "-f1"],
-                |gbo: GroupByOptions| gbo.output.parallel,
-                false,
+                &vec!["app", "--with-filename", "-f1"],
+                |gbo: GroupByOptions| gbo.input.with_filename,
+                true,
             );
+
+            // When not specified
             parses(
                 &vec!["app", "-f1"],
-                |gbo: GroupByOptions| gbo.output.parallel,
-                true,
+                |gbo: GroupByOptions| gbo.input.with_filename,
+                false,
             );
         }
 
         #[test]
-        fn parses_output_no_headers() {
-            // No short option
-
-            // Long
+        fn parses_input_invalid_utf8() {
+            for (arg, policy) in [
+                ("lossy", InvalidUtf8::Lossy),
+                ("skip", InvalidUtf8::Skip),
+                ("strict", InvalidUtf8::Strict),
+            ] {
+                parses(
+                    &vec!["app", "--invalid-utf8", arg, "-f1"],
+                    |gbo: GroupByOptions| gbo.input.invalid_utf8,
+                    policy,
+                );
+            }
             parses(
                 &vec!["app", "-f1"],
-                |gbo: GroupByOptions| gbo.output.headers,
-                true,
+                |gbo: GroupByOptions| gbo.input.invalid_utf8,
+                InvalidUtf8::Lossy,
             );
+        }
+
+        #[test]
+        fn parses_input_split_default() {
             parses(
-                &vec!["app", "--no-headers", "-f1"],
-                |gbo: GroupByOptions| gbo.output.headers,
-                false,
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.input.separator,
+                Separator::Line,
             );
         }
 
-        // TODO Write missing test parses_stats
-    }
-
-    #[cfg(test)]
-    mod parse_capture_group {
+        #[test]
+        fn parses_groupers_by_first_chars() {
+            // Short
+            parses(
+                &vec!["app", "-w", "-f8"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::FirstChars(8),
+            );
+            // No long option
+
+            // Counting bytes
+            parses(
+                &vec!["app", "-f8", "--bytes"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::FirstBytes(8),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_last_chars() {
+            // Short
+            parses(
+                &vec!["app", "-w", "-l9"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::LastChars(9),
+            );
+
+            // Counting bytes
+            parses(
+                &vec!["app", "-l9", "--bytes"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::LastBytes(9),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_regex() {
+            // Short
+            parses(
+                &vec!["app", "-w", "-r", "foo"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Regex(
+                    Regex::new("foo").unwrap(),
+                    CaptureGroup::Default,
+                    Unmatched::Empty,
+                ),
+            );
+
+            // Long
+            parses(
+                &vec!["app", "-w", "--regex", "bar"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Regex(
+                    Regex::new("bar").unwrap(),
+                    CaptureGroup::Default,
+                    Unmatched::Empty,
+                ),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_regex_rules() {
+            // A single pattern is never named.
+            parses(
+                &vec!["app", "-r", "errors=ERROR"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Regex(
+                    Regex::new("errors=ERROR").unwrap(),
+                    CaptureGroup::Default,
+                    Unmatched::Empty,
+                ),
+            );
+
+            // Repeated, in order
+            parses(
+                &vec![
+                    "app",
+                    "-r",
+                    "errors=ERROR|FATAL",
+                    "--regex",
+                    r"(\w+)=\d",
+                    "--unmatched",
+                    "skip",
+                ],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::RegexRules(
+                    vec![
+                        RegexRule {
+                            name: Some("errors".to_string()),
+                            regex: Regex::new("ERROR|FATAL").unwrap(),
+                        },
+                        RegexRule {
+                            name: None,
+                            regex: Regex::new(r"(\w+)=\d").unwrap(),
+                        },
+                    ],
+                    CaptureGroup::Default,
+                    Unmatched::Skip,
+                ),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "regex parse error")]
+        fn rejects_invalid_regex_rules() {
+            parses(
+                &vec!["app", "-r", "ok=ok", "-r", "broken=("],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::RegexRules(vec![], CaptureGroup::Default, Unmatched::Empty),
+            );
+        }
+
+        #[test]
+        fn parses_grouper_options_capture_groups() {
+            parses(
+                &vec![
+                    "app",
+                    "-r",
+                    "(a)(b)",
+                    "--capture-groups",
+                    "2,1",
+                    "--key-sep",
+                    "/",
+                ],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Regex(
+                    Regex::new("(a)(b)").unwrap(),
+                    CaptureGroup::Joined(
+                        vec![CaptureGroup::Number(2), CaptureGroup::Number(1)],
+                        "/".to_string(),
+                    ),
+                    Unmatched::Empty,
+                ),
+            );
+        }
+
+        #[test]
+        #[should_panic(
+            expected = "The regular expression /(a)(?P<b>b)/ has no capture group 3. Its capture \
+            groups are: 0 (the whole match), 1, 2 (b)"
+        )]
+        fn rejects_missing_numbered_capture_groups() {
+            parses(
+                &vec!["app", "-r", "(a)(?P<b>b)", "--capture-groups", "1,3"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Regex(
+                    Regex::new("(a)(?P<b>b)").unwrap(),
+                    CaptureGroup::Default,
+                    Unmatched::Empty,
+                ),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "has no capture group named \"yaer\"")]
+        fn rejects_missing_named_capture_groups() {
+            parses(
+                &vec!["app", "-r", r"(?P<year>\d{4})", "--capture-group", "yaer"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Regex(
+                    Regex::new(r"(?P<year>\d{4})").unwrap(),
+                    CaptureGroup::Default,
+                    Unmatched::Empty,
+                ),
+            );
+        }
+
+        #[test]
+        fn checks_capture_groups_of_unnamed_regex_rules_only() {
+            // The named rule has no group 1, but its name is the key anyway.
+            parses(
+                &vec![
+                    "app",
+                    "-r",
+                    "errors=ERROR",
+                    "-r",
+                    r"(\w+):",
+                    "--capture-group",
+                    "1",
+                ],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::RegexRules(
+                    vec!["errors=ERROR".parse().unwrap(), r"(\w+):".parse().unwrap()],
+                    CaptureGroup::Number(1),
+                    Unmatched::Empty,
+                ),
+            );
+        }
+
+        #[test]
+        fn parses_grouper_options_unmatched() {
+            let regex = Regex::new("foo").unwrap();
+            for (policy, expected) in [
+                ("empty", Unmatched::Empty),
+                ("skip", Unmatched::Skip),
+                ("error", Unmatched::Error),
+                ("group:other", Unmatched::Group("other".to_string())),
+            ] {
+                parses(
+                    &vec!["app", "-r", "foo", "--unmatched", policy],
+                    |gbo: GroupByOptions| gbo.grouping,
+                    GroupingSpecifier::Regex(regex.clone(), CaptureGroup::Default, expected),
+                );
+            }
+        }
+
+        #[test]
+        #[should_panic(expected = "Expected empty, skip, error, or group:NAME")]
+        fn rejects_unknown_unmatched_policy() {
+            parses(
+                &vec!["app", "-r", "foo", "--unmatched", "drop"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Counter,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_file_extension() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-w", "--extension"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::FileExtension,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_counter() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-w", "--counter"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Counter,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_key_value() {
+            // No short option
+
+            // Long, with and without a separator
+            parses(
+                &vec!["app", "--kv=="],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::KeyValue("=".to_string()),
+            );
+            parses(
+                &vec!["app", "--kv"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::KeyValue("\t".to_string()),
+            );
+
+            // A following argument is a file, not a separator
+            parses(
+                &vec!["app", "--kv", "pairs.txt"],
+                |gbo: GroupByOptions| (gbo.grouping, gbo.input.files),
+                (
+                    GroupingSpecifier::KeyValue("\t".to_string()),
+                    vec![PathBuf::from("pairs.txt")],
+                ),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Key-value separators can't be empty")]
+        fn rejects_empty_key_value_separator() {
+            parses(
+                &vec!["app", "--kv="],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::KeyValue("".to_string()),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_field() {
+            // No short option
+
+            // Long, with the default delimiter
+            parses(
+                &vec!["app", "--field", "3"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Field(3, "\t".to_string()),
+            );
+
+            // With a delimiter, short and long
+            parses(
+                &vec!["app", "--field", "1", "-d", ":"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Field(1, ":".to_string()),
+            );
+            parses(
+                &vec!["app", "--field", "1", "--field-sep", "\\x2c"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Field(1, ",".to_string()),
+            );
+        }
+
+        #[test]
+        #[should_panic]
+        fn rejects_groupers_by_field_zero() {
+            parses(
+                &vec!["app", "--field", "0"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Field(0, "\t".to_string()),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_nth_word() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--word", "3", "--word-regex", "\\S+"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::NthWord(3, Regex::new("\\S+").unwrap()),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_timestamp() {
+            // No short option
+
+            // Long, with the default bucket
+            parses(
+                &vec!["app", "--date-format", "%Y-%m-%d"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Timestamp("%Y-%m-%d".parse().unwrap(), TimeBucket::Day),
+            );
+
+            // With a bucket
+            parses(
+                &vec!["app", "--date-format", "%Y-%m-%dT%H", "--bucket", "hour"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Timestamp("%Y-%m-%dT%H".parse().unwrap(), TimeBucket::Hour),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "coarser --bucket")]
+        fn rejects_groupers_by_timestamp_with_unsupported_bucket() {
+            parses(
+                &vec!["app", "--date-format", "%Y-%m-%d", "--bucket", "hour"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Counter,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_numeric_bucket() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--bucket-size", "100"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::NumericBucket(100),
+            );
+
+            // Sorts numerically by default, so that negative ranges come in order
+            parses(
+                &vec!["app", "--bucket-size", "100"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Numeric,
+            );
+
+            // Unless told otherwise
+            parses(
+                &vec!["app", "--bucket-size", "100", "--sort", "natural"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Natural,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Bucket sizes must be at least 1")]
+        fn rejects_groupers_by_numeric_bucket_of_zero() {
+            parses(
+                &vec!["app", "--bucket-size", "0"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::NumericBucket(0),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_size_bucket() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--size-buckets", "1K,1M,100M"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::SizeBucket("1K,1M,100M".parse().unwrap()),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "increasing order")]
+        fn rejects_groupers_by_size_bucket_out_of_order() {
+            parses(
+                &vec!["app", "--size-buckets", "1M,1K"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Counter,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_mtime() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--mtime-bucket", "week"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Mtime(TimeBucket::Week),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_mime_type() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--mime-type"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::MimeType,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_path_component() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--path-depth", "2"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::PathComponent(2),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Path depths are numbered from 1")]
+        fn rejects_groupers_by_path_component_of_zero() {
+            parses(
+                &vec!["app", "--path-depth", "0"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::PathComponent(0),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_basename() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--basename"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Basename,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_dirname() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--dirname"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Dirname,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_length() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--length"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Length,
+            );
+
+            // Sorts naturally by default
+            parses(
+                &vec!["app", "--length"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Natural,
+            );
+
+            // Unless told otherwise
+            parses(
+                &vec!["app", "--length", "--sort", "bytes"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Bytes,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_shard() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--shard", "16"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Shard(16),
+            );
+
+            // Sorts naturally by default
+            parses(
+                &vec!["app", "--shard", "16"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Natural,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "The number of shards must be at least 1")]
+        fn rejects_groupers_by_shard_of_zero() {
+            parses(
+                &vec!["app", "--shard", "0"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Shard(0),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_chunk() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--chunk", "100"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Chunk(100),
+            );
+
+            // Sorts naturally by default
+            parses(
+                &vec!["app", "--chunk", "100"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Natural,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Chunk sizes must be at least 1")]
+        fn rejects_groupers_by_chunk_of_zero() {
+            parses(
+                &vec!["app", "--chunk", "0"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Chunk(0),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_glob() {
+            // No short option
+
+            // Long, in order
+            parses(
+                &vec!["app", "--glob", "images=*.{jpg,png}", "--glob", "code=*.rs"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Glob(vec![
+                    "images=*.{jpg,png}".parse().unwrap(),
+                    "code=*.rs".parse().unwrap(),
+                ]),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_url_host() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--url-host"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::UrlHost(false),
+            );
+
+            // Registrable domain
+            parses(
+                &vec!["app", "--url-host", "--domain"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::UrlHost(true),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_json_key() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--json-key", ".user.id"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::JsonKey(".user.id".parse().unwrap()),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Expected a JSON path like .user.id")]
+        fn rejects_groupers_by_json_key_without_a_leading_dot() {
+            parses(
+                &vec!["app", "--json-key", "user.id"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::JsonKey(".".parse().unwrap()),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_csv_column() {
+            // No short option
+
+            // Long, by number
+            parses(
+                &vec!["app", "--column", "2"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::CsvColumn(Column::Index(2), ','),
+            );
+
+            // By name, with a custom delimiter
+            parses(
+                &vec!["app", "--csv", "--column", "status", "-d", "\\t"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::CsvColumn(Column::Name("status".to_string()), '\t'),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "they need --csv")]
+        fn rejects_groupers_by_csv_column_name_without_csv() {
+            parses(
+                &vec!["app", "--column", "status"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::CsvColumn(Column::Name("status".to_string()), ','),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "CSV delimiters must be a single character")]
+        fn rejects_groupers_by_csv_column_with_long_delimiter() {
+            parses(
+                &vec!["app", "--column", "1", "-d", "::"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::CsvColumn(Column::Index(1), ':'),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_logfmt() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--logfmt", "level"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Logfmt("level".to_string()),
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_key_expr() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--key-expr", "lower(field(2, ':'))"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::KeyExpr("lower(field(2, ':'))".parse().unwrap()),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Unknown function frob")]
+        fn rejects_groupers_by_key_expr_with_unknown_functions() {
+            parses(
+                &vec!["app", "--key-expr", "frob(line)"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Counter,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_key_command() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--key-command", "cut -c1"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::KeyCommand(
+                    "cut -c1".to_string(),
+                    key_command::DEFAULT_BATCH,
+                    None,
+                ),
+            );
+
+            // With a batch size
+            parses(
+                &vec!["app", "--key-command", "cut -c1", "--key-batch", "10"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::KeyCommand("cut -c1".to_string(), 10, None),
+            );
+
+            // With a shell
+            parses(
+                &vec!["app", "--key-command", "cut -c1", "--shell", "/bin/dash"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::KeyCommand(
+                    "cut -c1".to_string(),
+                    key_command::DEFAULT_BATCH,
+                    Some("/bin/dash".to_string()),
+                ),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "--key-batch must be at least 1")]
+        fn rejects_groupers_by_key_command_with_empty_batches() {
+            parses(
+                &vec!["app", "--key-command", "cat", "--key-batch", "0"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Counter,
+            );
+        }
+
+        #[test]
+        fn parses_groupers_by_named() {
+            registry::register("parse_args_test", Box::new(|s: &str| s.to_string()));
+
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--grouper", "parse_args_test"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Named("parse_args_test".to_string()),
+            );
+        }
+
+        #[test]
+        #[should_panic]
+        fn rejects_unregistered_groupers_by_named() {
+            parses(
+                &vec!["app", "--grouper", "parse_args_test_missing"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Counter,
+            );
+        }
+
+        #[test]
+        fn parses_composite_groupers() {
+            // In command-line order
+            parses(
+                &vec!["app", "--extension", "-f", "3"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Composite(
+                    vec![
+                        GroupingSpecifier::FileExtension,
+                        GroupingSpecifier::FirstChars(3),
+                    ],
+                    " ".to_string(),
+                ),
+            );
+
+            // Custom separator
+            parses(
+                &vec!["app", "-f", "3", "--key-sep", "\\t", "--extension"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Composite(
+                    vec![
+                        GroupingSpecifier::FirstChars(3),
+                        GroupingSpecifier::FileExtension,
+                    ],
+                    "\t".to_string(),
+                ),
+            );
+
+            // Sorts naturally if any part does
+            parses(
+                &vec!["app", "--extension", "--length"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Natural,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Expected a glob rule like NAME=PATTERN")]
+        fn rejects_groupers_by_glob_without_a_name() {
+            parses(
+                &vec!["app", "--glob", "*.rs"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::Glob(vec![]),
+            );
+        }
+
+        #[test]
+        fn parses_output_null_separators() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--print0", "-f1"],
+                |gbo: GroupByOptions| gbo.output.separator,
+                Separator::Null,
+            );
+        }
+
+        #[test]
+        fn parses_output_space_separators() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--printspace", "-f1"],
+                |gbo: GroupByOptions| gbo.output.separator,
+                Separator::Space,
+            );
+        }
+
+        #[test]
+        fn parses_output_custom_separators() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--print-sep", ", ", "-f1"],
+                |gbo: GroupByOptions| gbo.output.separator,
+                Separator::Custom(", ".to_string()),
+            );
+
+            // With escape sequences
+            parses(
+                &vec!["app", "--print-sep", "\\t", "-f1"],
+                |gbo: GroupByOptions| gbo.output.separator,
+                Separator::Custom("\t".to_string()),
+            );
+            parses(
+                &vec!["app", "--print-sep", "\\t", "--literal", "-f1"],
+                |gbo: GroupByOptions| gbo.output.separator,
+                Separator::Custom("\\t".to_string()),
+            );
+        }
+
+        #[test]
+        fn parses_output_default_separators() {
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.separator,
+                Separator::Line,
+            );
+        }
+
+        #[test]
+        fn parses_output_only_group_names() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--only-group-names", "-f1"],
+                |gbo: GroupByOptions| gbo.output.only_group_names,
+                true,
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.only_group_names,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_run_command() {
+            // Short
+            parses(
+                &vec!["app", "-c", "tail | head", "-f1"],
+                |gbo: GroupByOptions| gbo.output.run_command,
+                Some("tail | head".to_string()),
+            );
+            // No long option
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.run_command,
+                None,
+            );
+        }
+
+        #[test]
+        fn parses_output_sequential() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--sequential", "-f1"],
+                |gbo: GroupByOptions| gbo.output.parallel,
+                false,
+            );
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.parallel,
+                true,
+            );
+        }
+
+        #[test]
+        fn parses_output_no_headers() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.headers,
+                true,
+            );
+            parses(
+                &vec!["app", "--no-headers", "-f1"],
+                |gbo: GroupByOptions| gbo.output.headers,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_checkpoint() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--checkpoint", "done.txt", "-f1"],
+                |gbo: GroupByOptions| gbo.output.checkpoint,
+                Some("done.txt".to_string()),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.checkpoint,
+                None,
+            );
+        }
+
+        #[test]
+        fn parses_output_shell() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--shell", "/bin/dash", "-f1"],
+                |gbo: GroupByOptions| gbo.output.shell,
+                Some("/bin/dash".to_string()),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.shell,
+                None,
+            );
+        }
+
+        #[test]
+        fn parses_output_dry_run() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--dry-run", "-f1"],
+                |gbo: GroupByOptions| gbo.output.dry_run,
+                true,
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.dry_run,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_jobs() {
+            // Short
+            parses(
+                &vec!["app", "-c", "cat", "-j", "4", "-f1"],
+                |gbo: GroupByOptions| gbo.output.jobs,
+                Some(4),
+            );
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--jobs", "2", "-f1"],
+                |gbo: GroupByOptions| gbo.output.jobs,
+                Some(2),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.jobs,
+                None,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "The number of jobs must be at least 1")]
+        fn rejects_zero_jobs() {
+            parses(
+                &vec!["app", "-c", "cat", "-j", "0", "-f1"],
+                |gbo: GroupByOptions| gbo.output.jobs,
+                Some(0),
+            );
+        }
+
+        #[test]
+        fn parses_output_max_args() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--max-args", "100", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_args,
+                Some(100),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_args,
+                None,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "The maximum number of arguments must be at least 1")]
+        fn rejects_zero_max_args() {
+            parses(
+                &vec!["app", "-c", "cat", "--max-args", "0", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_args,
+                Some(0),
+            );
+        }
+
+        #[test]
+        fn parses_output_max_stdin_bytes() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--max-stdin-bytes", "65536", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_stdin_bytes,
+                Some(65536),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_stdin_bytes,
+                None,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "The maximum number of bytes must be at least 1")]
+        fn rejects_zero_max_stdin_bytes() {
+            parses(
+                &vec!["app", "-c", "cat", "--max-stdin-bytes", "0", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_stdin_bytes,
+                Some(0),
+            );
+        }
+
+        #[test]
+        fn parses_output_nice() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--nice", "10", "-f1"],
+                |gbo: GroupByOptions| gbo.output.nice,
+                Some(10),
+            );
+            parses(
+                &vec!["app", "-c", "cat", "--nice", "-5", "-f1"],
+                |gbo: GroupByOptions| gbo.output.nice,
+                Some(-5),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.nice,
+                None,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Niceness must be from -20 to 19")]
+        fn rejects_niceness_out_of_range() {
+            parses(
+                &vec!["app", "-c", "cat", "--nice", "20", "-f1"],
+                |gbo: GroupByOptions| gbo.output.nice,
+                Some(20),
+            );
+        }
+
+        #[test]
+        fn parses_output_max_memory() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--max-memory", "2G", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_memory,
+                Some(2 << 30),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_memory,
+                None,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Expected a size like 512, 1K, or 100M")]
+        fn rejects_invalid_max_memory() {
+            parses(
+                &vec!["app", "-c", "cat", "--max-memory", "lots", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_memory,
+                None,
+            );
+        }
+
+        #[test]
+        fn parses_output_max_open_files() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--max-open-files", "256", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_open_files,
+                Some(256),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.max_open_files,
+                None,
+            );
+        }
+
+        #[test]
+        fn parses_output_capture_stderr() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--capture-stderr", "-f1"],
+                |gbo: GroupByOptions| gbo.output.capture_stderr,
+                true,
+            );
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.capture_stderr,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_live() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--live", "-f1"],
+                |gbo: GroupByOptions| gbo.output.live,
+                true,
+            );
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.live,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_incremental() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--incremental", "-f1"],
+                |gbo: GroupByOptions| gbo.output.incremental,
+                true,
+            );
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.incremental,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_with_values() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "-c", "wc -l", "--with-values", "-f1"],
+                |gbo: GroupByOptions| gbo.output.with_values,
+                true,
+            );
+            parses(
+                &vec!["app", "-c", "wc -l", "-f1"],
+                |gbo: GroupByOptions| gbo.output.with_values,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_failure_policy() {
+            // No short options
+
+            // Long
+            parses(
+                &vec!["app", "-c", "cat", "--fail-fast", "-f1"],
+                |gbo: GroupByOptions| gbo.output.failure_policy,
+                FailurePolicy::FailFast,
+            );
+            parses(
+                &vec!["app", "-c", "cat", "--keep-going", "-f1"],
+                |gbo: GroupByOptions| gbo.output.failure_policy,
+                FailurePolicy::KeepGoing,
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-c", "cat", "-f1"],
+                |gbo: GroupByOptions| gbo.output.failure_policy,
+                FailurePolicy::KeepGoing,
+            );
+        }
+
+        #[test]
+        fn parses_output_resume() {
+            // No short option
+
+            // Long
+            parses(
+                &vec![
+                    "app",
+                    "-c",
+                    "cat",
+                    "--checkpoint",
+                    "done.txt",
+                    "--resume",
+                    "-f1",
+                ],
+                |gbo: GroupByOptions| gbo.output.resume,
+                true,
+            );
+            parses(
+                &vec!["app", "-c", "cat", "--checkpoint", "done.txt", "-f1"],
+                |gbo: GroupByOptions| gbo.output.resume,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_count_only() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--count-only", "-f1"],
+                |gbo: GroupByOptions| gbo.output.count_only,
+                true,
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.count_only,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_sort_groups() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--sort-groups", "size", "-f1"],
+                |gbo: GroupByOptions| gbo.output.group_order,
+                GroupOrder::Size,
+            );
+            parses(
+                &vec!["app", "--sort-groups", "size-desc", "-f1"],
+                |gbo: GroupByOptions| gbo.output.group_order,
+                GroupOrder::SizeDescending,
+            );
+            parses(
+                &vec!["app", "--sort-groups", "key", "-f1"],
+                |gbo: GroupByOptions| gbo.output.group_order,
+                GroupOrder::Key,
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.group_order,
+                GroupOrder::Key,
+            );
+        }
+
+        #[test]
+        fn parses_output_sort_groups_as_key_order() {
+            parses(
+                &vec!["app", "--sort-groups", "natural", "-f1"],
+                |gbo: GroupByOptions| (gbo.output.sort, gbo.output.group_order),
+                (KeyOrder::Natural, GroupOrder::Key),
+            );
+            parses(
+                &vec!["app", "--sort-groups", "numeric", "-f1"],
+                |gbo: GroupByOptions| (gbo.output.sort, gbo.output.group_order),
+                (KeyOrder::Numeric, GroupOrder::Key),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "can't be combined with --sort")]
+        fn rejects_output_sort_groups_as_key_order_with_sort() {
+            parses(
+                &vec!["app", "--sort-groups", "numeric", "--sort", "bytes", "-f1"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Numeric,
+            );
+        }
+
+        #[test]
+        fn parses_output_reverse() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--reverse", "-f1"],
+                |gbo: GroupByOptions| gbo.output.reverse,
+                true,
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.reverse,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_sort() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--sort", "collate", "-f1"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Collate,
+            );
+            parses(
+                &vec!["app", "--sort", "natural", "-f1"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Natural,
+            );
+            parses(
+                &vec!["app", "--sort", "locale", "-f1"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Locale(Tailoring::from_env()),
+            );
+            parses(
+                &vec!["app", "--sort", "bytes", "-f1"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Bytes,
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "--sort", "numeric", "-f1"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Numeric,
+            );
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.sort,
+                KeyOrder::Bytes,
+            );
+        }
+
+        #[test]
+        fn parses_output_presorted_streaming() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--presorted-streaming", "-f1"],
+                |gbo: GroupByOptions| gbo.output.presorted_streaming,
+                true,
+            );
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.presorted_streaming,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_stream() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--stream", "-f1"],
+                |gbo: GroupByOptions| gbo.output.stream,
+                true,
+            );
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.stream,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_low_memory() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--low-memory", "-f1"],
+                |gbo: GroupByOptions| gbo.output.low_memory,
+                true,
+            );
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.low_memory,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_intern() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--intern", "-f1"],
+                |gbo: GroupByOptions| gbo.output.intern,
+                true,
+            );
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.intern,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_json() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--json", "-f1"],
+                |gbo: GroupByOptions| gbo.output.json,
+                true,
+            );
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.json,
+                false,
+            );
+        }
+
+        // TODO Write missing test parses_stats
+
+        #[test]
+        fn parses_output_format() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--format", "{key}={count}", "-f1"],
+                |gbo: GroupByOptions| gbo.output.format,
+                Some("{key}={count}".parse().unwrap()),
+            );
+            parses(
+                &vec!["app", "--format", "{key}: {output}", "-f1", "-c", "wc -l"],
+                |gbo: GroupByOptions| gbo.output.format.map(|t| t.uses_output()),
+                Some(true),
+            );
+
+            // With escape sequences
+            parses(
+                &vec!["app", "--format", "{key}\\t{count}", "-f1"],
+                |gbo: GroupByOptions| gbo.output.format,
+                Some("{key}\t{count}".parse().unwrap()),
+            );
+            parses(
+                &vec!["app", "--format", "{key}\\t{count}", "--literal", "-f1"],
+                |gbo: GroupByOptions| gbo.output.format,
+                Some("{key}\\t{count}".parse().unwrap()),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.format,
+                None,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "only have values with -c")]
+        fn rejects_output_format_placeholder_without_command() {
+            parses(
+                &vec!["app", "--format", "{key}: {output}", "-f1"],
+                |gbo: GroupByOptions| gbo.output.format,
+                None,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "in the template, but got: {size}")]
+        fn rejects_unknown_output_format_placeholder() {
+            parses(
+                &vec!["app", "--format", "{key}: {size}", "-f1"],
+                |gbo: GroupByOptions| gbo.output.format,
+                None,
+            );
+        }
+
+        #[test]
+        fn parses_output_header_format() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--header-format", "# {key}", "-f1"],
+                |gbo: GroupByOptions| gbo.output.header_format,
+                Some("# {key}".parse().unwrap()),
+            );
+
+            // With escape sequences
+            parses(
+                &vec!["app", "--header-format", "{key}\\t", "-f1"],
+                |gbo: GroupByOptions| gbo.output.header_format,
+                Some("{key}\t".parse().unwrap()),
+            );
+            parses(
+                &vec!["app", "--header-format", "{key}\\t", "--literal", "-f1"],
+                |gbo: GroupByOptions| gbo.output.header_format,
+                Some("{key}\\t".parse().unwrap()),
+            );
+            parses(
+                &vec!["app", "--no-header-colon", "-f1"],
+                |gbo: GroupByOptions| gbo.output.header_format,
+                Some("{key}".parse().unwrap()),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.header_format,
+                None,
+            );
+        }
+
+        #[test]
+        fn parses_output_quote() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--quote", "shell", "-f1"],
+                |gbo: GroupByOptions| gbo.output.quote,
+                Quote::Shell,
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.quote,
+                Quote::None,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "--header-format uses {output}")]
+        fn rejects_output_header_format_placeholder_without_command() {
+            parses(
+                &vec!["app", "--header-format", "{key}: {output}", "-f1"],
+                |gbo: GroupByOptions| gbo.output.header_format,
+                None,
+            );
+        }
+
+        #[test]
+        fn parses_output_timings() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--timings", "-f1"],
+                |gbo: GroupByOptions| gbo.output.timings,
+                true,
+            );
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.output.timings,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_output_stats_detail() {
+            // No short option
+
+            // Long, with and without percentiles
+            parses(
+                &vec!["app", "--stats", "--stats-detail", "-f1"],
+                |gbo: GroupByOptions| gbo.output.stats_detail,
+                Some(vec![90, 99]),
+            );
+            parses(
+                &vec!["app", "--stats", "--stats-detail=0,50,99", "-f1"],
+                |gbo: GroupByOptions| gbo.output.stats_detail,
+                Some(vec![0, 50, 99]),
+            );
+
+            // A following argument is a file, not a list of percentiles
+            parses(
+                &vec!["app", "--stats", "--stats-detail", "sizes.txt", "-f1"],
+                |gbo: GroupByOptions| (gbo.output.stats_detail, gbo.input.files),
+                (Some(vec![90, 99]), vec![PathBuf::from("sizes.txt")]),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "--stats", "-f1"],
+                |gbo: GroupByOptions| gbo.output.stats_detail,
+                None,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "percentiles from 0 to 100")]
+        fn rejects_out_of_range_percentiles() {
+            parses(
+                &vec!["app", "--stats", "--stats-detail=90,101", "-f1"],
+                |gbo: GroupByOptions| gbo.output.stats_detail,
+                None,
+            );
+        }
+
+        #[test]
+        fn parses_general_seed() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--seed", "1234", "-f1"],
+                |gbo: GroupByOptions| gbo.seed,
+                Some(1234),
+            );
+
+            // When not specified
+            parses(&vec!["app", "-f1"], |gbo: GroupByOptions| gbo.seed, None);
+        }
+
+        #[test]
+        fn parses_general_explain() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--explain", "-f1"],
+                |gbo: GroupByOptions| gbo.explain,
+                true,
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.explain,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_grouper_options_label() {
+            // No short option
+
+            // Long, repeated
+            parses(
+                &vec!["app", "--label", "a-m:first", "--label", "n-:second", "-f1"],
+                |gbo: GroupByOptions| gbo.labels,
+                Labels::new(vec![
+                    "a-m:first".parse().unwrap(),
+                    "n-:second".parse().unwrap(),
+                ]),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.labels,
+                Labels::default(),
+            );
+        }
+
+        #[test]
+        fn parses_grouper_options_unique() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--unique", "-f1"],
+                |gbo: GroupByOptions| gbo.unique,
+                true,
+            );
+
+            // When not specified
+            parses(&vec!["app", "-f1"], |gbo: GroupByOptions| gbo.unique, false);
+        }
+
+        #[test]
+        fn parses_grouper_options_count_values() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--count-values", "-f1"],
+                |gbo: GroupByOptions| gbo.count_values,
+                true,
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.count_values,
+                false,
+            );
+        }
+
+        #[test]
+        fn parses_grouper_options_min_size() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--min-size", "2", "-f1"],
+                |gbo: GroupByOptions| gbo.min_size,
+                Some(2),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.min_size,
+                None,
+            );
+        }
+
+        #[test]
+        fn parses_grouper_options_max_size() {
+            // No short option
+
+            // Long
+            parses(
+                &vec!["app", "--max-size", "5", "-f1"],
+                |gbo: GroupByOptions| gbo.max_size,
+                Some(5),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f1"],
+                |gbo: GroupByOptions| gbo.max_size,
+                None,
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "can't be larger than --max-size")]
+        fn rejects_min_size_larger_than_max_size() {
+            parses(
+                &vec!["app", "--min-size", "3", "--max-size", "2", "-f1"],
+                |gbo: GroupByOptions| gbo.min_size,
+                Some(3),
+            );
+        }
+
+        #[test]
+        fn parses_grouper_options_ignore_case() {
+            // Short
+            parses(
+                &vec!["app", "-i", "--extension"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::IgnoreCase(Box::new(GroupingSpecifier::FileExtension)),
+            );
+
+            // Long
+            parses(
+                &vec!["app", "--ignore-case", "-f2"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::IgnoreCase(Box::new(GroupingSpecifier::FirstChars(2))),
+            );
+
+            // When not specified
+            parses(
+                &vec!["app", "-f2"],
+                |gbo: GroupByOptions| gbo.grouping,
+                GroupingSpecifier::FirstChars(2),
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod parse_invocation_from {
+        use super::*;
+        use crate::command_line::args;
+
+        fn parses(args: &[&'static str]) -> Invocation {
+            crate::command_line::parse_args::parse_invocation_from(args::args(), |c| {
+                c.try_get_matches_from(args)
+            })
+            .unwrap()
+        }
+
+        #[test]
+        fn parses_bench() {
+            assert_eq!(
+                parses(&["app", "bench"]),
+                Invocation::Bench(BenchOptions {
+                    items: 1000000,
+                    keys: 1000,
+                    run_command: None,
+                    seed: None,
+                })
+            );
+            assert_eq!(
+                parses(&[
+                    "app", "bench", "--items", "10", "--keys", "2", "-c", "wc", "--seed", "3"
+                ]),
+                Invocation::Bench(BenchOptions {
+                    items: 10,
+                    keys: 2,
+                    run_command: Some("wc".to_string()),
+                    seed: Some(3),
+                })
+            );
+        }
+
+        #[test]
+        fn parses_group() {
+            match parses(&["app", "-f1"]) {
+                Invocation::Group(options) => {
+                    assert_eq!(options.grouping, GroupingSpecifier::FirstChars(1))
+                }
+                invocation => panic!("Expected Invocation::Group, got {:?}", invocation),
+            }
+        }
+
+        fn fails(args: &[&'static str]) -> GroupByError {
+            crate::command_line::parse_args::parse_invocation_from(args::args(), |c| {
+                c.try_get_matches_from(args)
+            })
+            .unwrap_err()
+        }
+
+        #[test]
+        fn returns_usage_error_for_unknown_flag() {
+            match fails(&["app", "-f1", "--no-such-flag"]) {
+                GroupByError::Usage(e) => assert_eq!(e.kind(), clap::ErrorKind::UnknownArgument),
+                error => panic!("Expected a usage error, got {:?}", error),
+            }
+            assert_eq!(fails(&["app", "-f1", "--no-such-flag"]).exit_code(), 2);
+        }
+
+        #[test]
+        fn returns_help_as_usage_error_that_exits_successfully() {
+            let error = fails(&["app", "--help"]);
+            assert!(
+                matches!(&error, GroupByError::Usage(e) if e.kind() == clap::ErrorKind::DisplayHelp)
+            );
+            assert_eq!(error.exit_code(), 0);
+        }
+    }
+
+    #[cfg(test)]
+    mod parse_capture_group {
         use super::*;
 
         #[test]
@@ -421,7 +3127,7 @@ mod tests {
                 .command;
             let args = vec!["appname", "--regex", "xeger--"];
             let matches = clap.get_matches_from(args);
-            let result = parse_capture_group(&matches);
+            let result = parse_capture_group(&matches, String::new()).unwrap();
             assert_eq!(CaptureGroup::Default, result);
         }
 
@@ -433,7 +3139,7 @@ mod tests {
                 .command;
             let args = vec!["appname", "--regex", "xeger--", "--capture-group", "4"];
             let matches = clap.get_matches_from(args);
-            let result = parse_capture_group(&matches);
+            let result = parse_capture_group(&matches, String::new()).unwrap();
             assert_eq!(CaptureGroup::Number(4), result);
         }
 
@@ -445,10 +3151,42 @@ mod tests {
                 .command;
             let args = vec!["appname", "--regex", "xeger--", "--capture-group", "four"];
             let matches = clap.get_matches_from(args);
-            let result = parse_capture_group(&matches);
+            let result = parse_capture_group(&matches, String::new()).unwrap();
             assert_eq!(CaptureGroup::Name("four".to_string()), result);
         }
 
+        #[test]
+        fn returns_joined_groups_on_list() {
+            let clap = cb()
+                .groupers_by_regex()
+                .grouper_options_capture_group()
+                .command;
+            let args = vec!["appname", "--regex", "xeger", "--capture-groups", "date,2"];
+            let matches = clap.get_matches_from(args);
+            let result = parse_capture_group(&matches, "/".to_string()).unwrap();
+            assert_eq!(
+                CaptureGroup::Joined(
+                    vec![
+                        CaptureGroup::Name("date".to_string()),
+                        CaptureGroup::Number(2)
+                    ],
+                    "/".to_string()
+                ),
+                result
+            );
+        }
+
+        #[test]
+        fn returns_error_on_empty_name_in_list() {
+            let clap = cb()
+                .groupers_by_regex()
+                .grouper_options_capture_group()
+                .command;
+            let args = vec!["appname", "--regex", "xeger", "--capture-group", "1,"];
+            let matches = clap.get_matches_from(args);
+            assert!(parse_capture_group(&matches, String::new()).is_err());
+        }
+
         #[test]
         fn returns_string_on_number_embedded_in_text() {
             let clap = cb()
@@ -457,7 +3195,7 @@ mod tests {
                 .command;
             let args = vec!["appname", "--regex", "xeger--", "--capture-group", "20four"];
             let matches = clap.get_matches_from(args);
-            let result = parse_capture_group(&matches);
+            let result = parse_capture_group(&matches, String::new()).unwrap();
             assert_eq!(CaptureGroup::Name("20four".to_string()), result);
         }
     }
@@ -471,16 +3209,19 @@ mod tests {
             let clap = cb().groupers_by_first_chars().command;
             let args = vec!["appname", "-f", "4"];
             let matches = clap.get_matches_from(args);
-            assert_eq!(4, parse_numeric_value(&matches, "groupers_by_first_chars"));
+            assert_eq!(
+                4,
+                parse_numeric_value::<usize>(&matches, "groupers_by_first_chars").unwrap()
+            );
         }
 
         #[test]
-        #[should_panic]
-        fn panics_on_failed_parse() {
+        fn returns_error_on_failed_parse() {
             let clap = cb().groupers_by_first_chars().command;
             let args = vec!["appname", "-f", "four"];
             let matches = clap.get_matches_from(args);
-            parse_numeric_value::<usize>(&matches, "groupers_by_first_chars");
+            let result = parse_numeric_value::<usize>(&matches, "groupers_by_first_chars");
+            assert!(matches!(result, Err(GroupByError::InvalidArgument(_))));
         }
     }
 
@@ -493,19 +3234,49 @@ mod tests {
             let clap = CommandBuilder::new(command!()).groupers_by_regex().command;
             let args = vec!["appname", "-r", "(foo)?bar"];
             let matches = clap.get_matches_from(args);
-            let re = parse_regex_value(&matches, "groupers_by_regex");
+            let re = parse_regex_value(&matches, "groupers_by_regex").unwrap();
             assert!(re.is_match("bar"));
             assert!(re.is_match("foobar"));
             assert!(!re.is_match("soap"));
         }
 
         #[test]
-        #[should_panic(expected = "unclosed group")]
-        fn panics_on_invalid_regex() {
+        fn returns_error_on_invalid_regex() {
             let clap = CommandBuilder::new(command!()).groupers_by_regex().command;
             let invalid_args = vec!["appname", "-r", "(foo"];
             let matches = clap.get_matches_from(invalid_args);
-            parse_regex_value(&matches, "groupers_by_regex"); // Should panic.
+            match parse_regex_value(&matches, "groupers_by_regex") {
+                Err(GroupByError::InvalidArgument(message)) => {
+                    assert!(message.contains("unclosed group"))
+                }
+                result => panic!("Expected an invalid argument, got {:?}", result),
+            }
+        }
+    }
+
+    mod unescape {
+        use super::*;
+
+        #[test]
+        fn replaces_escape_sequences() {
+            assert_eq!(unescape("a\\tb\\nc\\rd\\0e").unwrap(), "a\tb\nc\rd\0e");
+            assert_eq!(unescape("\\x2c\\x7F").unwrap(), ",\x7f");
+            assert_eq!(unescape("\\\\t").unwrap(), "\\t");
+        }
+
+        #[test]
+        fn keeps_other_backslashes() {
+            assert_eq!(unescape("\\d+\\").unwrap(), "\\d+\\");
+        }
+
+        #[test]
+        fn rejects_bad_hex_escape() {
+            assert!(unescape("\\xZZ").is_err());
+        }
+
+        #[test]
+        fn rejects_non_ascii_hex_escape() {
+            assert!(unescape("\\xff").is_err());
         }
     }
 }
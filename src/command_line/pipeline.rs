@@ -0,0 +1,334 @@
+//! A channel-based API for grouping tokens on a background thread, for long-running programs that
+//! receive tokens over time rather than reading them from files.
+//!
+//! [Pipeline::spawn] starts a worker thread that groups every token sent to it, as
+//! [build_groups()](crate::command_line::build_groups()) would, and sends each group back over a
+//! channel once the group is final:
+//!
+//! - With [OutputOptions::presorted_streaming], a group is final as soon as a token with a
+//!   different key arrives, as with [stream_groups()](crate::command_line::stream_groups()).
+//! - Otherwise, groups are held until [TokenSender::flush] or [Pipeline::finish] is called, and
+//!   then every group is sent in byte order of keys and the worker starts over with no groups.
+//!
+//! [GroupByOptions::unique], the size limits, and [GroupByOptions::count_values] apply to each
+//! group before it's sent. [InputOptions] doesn't apply, since tokens arrive whole, and nothing
+//! is written or run: the rest of [OutputOptions] is up to the receiver.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::options::*;
+//! use groupby::command_line::pipeline::Pipeline;
+//!
+//! let options = GroupByOptions {
+//!     grouping: GroupingSpecifier::FirstChars(1),
+//!     ..Default::default()
+//! };
+//!
+//! let pipeline = Pipeline::spawn(options);
+//! let sender = pipeline.sender();
+//! for fruit in ["banana", "apple", "avocado"] {
+//!     sender.send(fruit).unwrap();
+//! }
+//! sender.flush().unwrap();
+//! assert_eq!(
+//!     pipeline.groups().recv().unwrap(),
+//!     ("a".to_string(), vec!["apple".to_string(), "avocado".to_string()]),
+//! );
+//! assert_eq!(
+//!     pipeline.groups().recv().unwrap(),
+//!     ("b".to_string(), vec!["banana".to_string()]),
+//! );
+//!
+//! sender.send("cherry").unwrap();
+//! drop(sender);
+//! assert_eq!(
+//!     pipeline.finish().unwrap(),
+//!     vec![("c".to_string(), vec!["cherry".to_string()])],
+//! );
+//! ```
+
+use crate::command_line::build_groups::send_tokens;
+use crate::command_line::error::GroupByError;
+use crate::command_line::options::*;
+use crate::grouped_collections::GroupedCollection;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::mem;
+use std::panic;
+use std::sync::mpsc::{self, Receiver, SendError, Sender};
+use std::thread::{self, JoinHandle};
+
+/// A group that a [Pipeline] has finished: its key and its values.
+pub type Group = (String, Vec<String>);
+
+// A message to the worker.
+#[derive(Debug)]
+enum Message {
+    Token(String),
+    Flush,
+}
+
+/// A grouping worker running on its own thread. See the [module documentation](self) for details.
+#[derive(Debug)]
+pub struct Pipeline {
+    sender: TokenSender,
+    groups: Receiver<Group>,
+    worker: JoinHandle<Result<(), GroupByError>>,
+}
+
+impl Pipeline {
+    /// Starts a worker thread that groups tokens following `options`.
+    pub fn spawn(options: GroupByOptions) -> Self {
+        let (sender, messages) = mpsc::channel();
+        let (finished, groups) = mpsc::channel();
+        let worker = thread::spawn(move || work(messages, finished, &options));
+        Pipeline {
+            sender: TokenSender { sender },
+            groups,
+            worker,
+        }
+    }
+
+    /// Returns a new handle for sending tokens to the worker, e.g. from another thread.
+    pub fn sender(&self) -> TokenSender {
+        self.sender.clone()
+    }
+
+    /// Returns the channel on which the worker sends each group once it's final.
+    pub fn groups(&self) -> &Receiver<Group> {
+        &self.groups
+    }
+
+    /// Stops taking tokens, waits for the worker to group the tokens already sent, and returns
+    /// every group that hasn't been received yet, including those still held by the worker.
+    ///
+    /// The worker only stops once every [TokenSender] is dropped, so drop any others first, or
+    /// this waits forever.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that stopped the worker early, if any, e.g.
+    /// [GroupByError::InvalidInput] for a token that didn't match the grouper under
+    /// [Unmatched::Error]. Groups that were final before the error can still be received from
+    /// [Pipeline::groups] until this is called.
+    pub fn finish(self) -> Result<Vec<Group>, GroupByError> {
+        drop(self.sender);
+        match self.worker.join() {
+            Ok(result) => result?,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+        Ok(self.groups.try_iter().collect())
+    }
+}
+
+/// Sends tokens to a [Pipeline]'s worker. Clone it to send from several threads at once.
+#[derive(Clone, Debug)]
+pub struct TokenSender {
+    sender: Sender<Message>,
+}
+
+impl TokenSender {
+    /// Sends a token to be grouped.
+    ///
+    /// # Errors
+    ///
+    /// If the worker has stopped because of an error, returns the token. [Pipeline::finish]
+    /// returns the error.
+    pub fn send<S: Into<String>>(&self, token: S) -> Result<(), SendError<String>> {
+        self.sender
+            .send(Message::Token(token.into()))
+            .map_err(|SendError(message)| match message {
+                Message::Token(token) => SendError(token),
+                Message::Flush => unreachable!(),
+            })
+    }
+
+    /// Asks the worker to send every group it's holding, as if the input ended here, and then
+    /// start over with no groups. With [OutputOptions::presorted_streaming], that's only the
+    /// current group. Tokens that a [GroupingSpecifier::KeyCommand] is holding for its next batch
+    /// aren't grouped yet, so they aren't sent.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the worker has stopped because of an error. [Pipeline::finish] returns the error.
+    pub fn flush(&self) -> Result<(), SendError<()>> {
+        self.sender.send(Message::Flush).map_err(|_| SendError(()))
+    }
+}
+
+// The worker thread's main loop: groups each token from messages, sending finished groups to
+// finished, until every sender is dropped.
+fn work(
+    messages: Receiver<Message>,
+    finished: Sender<Group>,
+    options: &GroupByOptions,
+) -> Result<(), GroupByError> {
+    let groups = RefCell::new(Groups::new(finished, options));
+    let tokens = messages.iter().filter_map(|message| match message {
+        Message::Token(token) => Some(token),
+        Message::Flush => {
+            groups.borrow_mut().flush();
+            None
+        }
+    });
+    send_tokens(
+        tokens,
+        &mut |key, value| {
+            groups.borrow_mut().add(key, value);
+            Ok(())
+        },
+        options,
+    )?;
+    groups.into_inner().flush();
+    Ok(())
+}
+
+// The groups a worker is holding, which it sends on once they're final.
+struct Groups<'a> {
+    finished: Sender<Group>,
+    options: &'a GroupByOptions,
+
+    // Without presorted_streaming, every group since the last flush.
+    held: BTreeMap<String, Vec<String>>,
+
+    // With presorted_streaming, the group that's still growing.
+    current: Option<Group>,
+}
+
+impl<'a> Groups<'a> {
+    fn new(finished: Sender<Group>, options: &'a GroupByOptions) -> Self {
+        Groups {
+            finished,
+            options,
+            held: BTreeMap::new(),
+            current: None,
+        }
+    }
+
+    fn add(&mut self, key: String, value: String) {
+        if !self.options.output.presorted_streaming {
+            self.held.add(key, value);
+            return;
+        }
+        match &mut self.current {
+            Some((current, values)) if *current == key => values.push(value),
+            _ => {
+                if let Some((key, values)) = self.current.replace((key, vec![value])) {
+                    self.send(key, values);
+                }
+            }
+        }
+    }
+
+    // Sends every group being held, in byte order of keys.
+    fn flush(&mut self) {
+        for (key, values) in mem::take(&mut self.held) {
+            self.send(key, values);
+        }
+        if let Some((key, values)) = self.current.take() {
+            self.send(key, values);
+        }
+    }
+
+    // Sends a final group, unless it's outside the size limits. If nothing's receiving groups any
+    // more, there's nobody to tell, so the group is dropped.
+    fn send(&self, key: String, values: Vec<String>) {
+        let mut group = BTreeMap::new();
+        group.insert(key, values);
+        self.options.finish_groups(&mut group);
+        for group in group {
+            let _ = self.finished.send(group);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn options(grouping: GroupingSpecifier) -> GroupByOptions {
+        GroupByOptions {
+            input: InputOptions::default(),
+            grouping,
+            labels: Labels::default(),
+            unique: false,
+            min_size: None,
+            max_size: None,
+            count_values: false,
+            output: OutputOptions::default(),
+            seed: None,
+            explain: false,
+        }
+    }
+
+    fn pipeline(grouping: GroupingSpecifier, output: OutputOptions) -> Pipeline {
+        let mut options = options(grouping);
+        options.output = output;
+        Pipeline::spawn(options)
+    }
+
+    fn group(key: &str, values: &[&str]) -> Group {
+        (
+            key.to_string(),
+            values.iter().map(ToString::to_string).collect(),
+        )
+    }
+
+    #[test]
+    fn sends_groups_as_they_finish_when_presorted() {
+        let pipeline = pipeline(
+            GroupingSpecifier::FirstChars(1),
+            OutputOptions {
+                presorted_streaming: true,
+                ..Default::default()
+            },
+        );
+        let sender = pipeline.sender();
+        for token in ["a1", "a2", "b1"] {
+            sender.send(token).unwrap();
+        }
+        assert_eq!(pipeline.groups().recv().unwrap(), group("a", &["a1", "a2"]));
+
+        drop(sender);
+        assert_eq!(pipeline.finish().unwrap(), vec![group("b", &["b1"])]);
+    }
+
+    #[test]
+    fn applies_size_limits_and_unique() {
+        let mut options = options(GroupingSpecifier::FirstChars(1));
+        options.unique = true;
+        options.min_size = Some(2);
+        let pipeline = Pipeline::spawn(options);
+        let sender = pipeline.sender();
+        for token in ["a1", "a1", "b1", "b2"] {
+            sender.send(token).unwrap();
+        }
+        drop(sender);
+        assert_eq!(pipeline.finish().unwrap(), vec![group("b", &["b1", "b2"])]);
+    }
+
+    #[test]
+    fn reports_grouper_errors() {
+        let pipeline = pipeline(
+            GroupingSpecifier::Regex(
+                Regex::new("x").unwrap(),
+                CaptureGroup::Default,
+                Unmatched::Error,
+            ),
+            OutputOptions::default(),
+        );
+        let sender = pipeline.sender();
+        sender.send("x").unwrap();
+        sender.send("y").unwrap();
+        while sender.send("y").is_ok() {
+            thread::yield_now();
+        }
+        drop(sender);
+        assert!(matches!(
+            pipeline.finish(),
+            Err(GroupByError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,733 @@
+//! Named option profiles, stored in a configuration file and selected with `--profile <name>`.
+//!
+//! A profile is a named set of command-line options. Teams can keep their standard invocations in
+//! a shared, versionable file instead of copying long command lines around.
+//!
+//! # Configuration file
+//!
+//! Profiles live in `$XDG_CONFIG_HOME/groupby/config.toml`, or `~/.config/groupby/config.toml` if
+//! `XDG_CONFIG_HOME` is not set. The file uses a small subset of TOML: one table per profile,
+//! containing one key per option. Keys are option names without leading hyphens; a single-letter
+//! key is a short option. Values may be:
+//!
+//! - a string or integer, for options that take a value, e.g. `regex = '^\w+'` or `f = 3`,
+//! - `true` (or `false`, which omits the option), for flags, e.g. `print0 = true`, or
+//! - an array of strings, for options that may be repeated, e.g. `label = ["a-m:a", "n-z:n"]`.
+//!
+//! ```toml
+//! # Group log lines by date and count them.
+//! [profile.logs]
+//! regex = '^(\d{4}-\d{2}-\d{2})'
+//! run-command = "wc -l"
+//! ```
+//!
+//! # Layering
+//!
+//! Options given explicitly on the command line take precedence. A profile's option is dropped if
+//! the command line already sets it or sets an option that conflicts with it, e.g. a different
+//! grouper. Options that may be repeated, like `--label`, combine instead.
+//!
+//! # Presets
+//!
+//! A preset, selected with `--preset <name>`, is a profile for a common workflow. Several are
+//! built in (see [builtin_presets()]), and the configuration file can define more, or replace the
+//! built-in ones, in `[preset.<name>]` tables with the same syntax as profiles. A preset is
+//! layered beneath the command line and any `--profile`, so both take precedence over it.
+//!
+//! ```toml
+//! # Group Apache access logs by day.
+//! [preset.access-by-day]
+//! date-format = '%d/%b/%Y'
+//! ```
+
+use crate::command_line::args::GROUPERS;
+use crate::command_line::error::GroupByError;
+use clap::error::ErrorKind;
+use clap::Command;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io;
+use std::path::PathBuf;
+
+/// The value of a single key in a profile.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ProfileValue {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<String>),
+}
+
+/// A named set of options. See the [module documentation](self) for details.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Profile {
+    /// The profile's options, in the order they appear in the configuration file.
+    pub settings: Vec<(String, ProfileValue)>,
+}
+
+impl Profile {
+    /// Converts the profile into command-line arguments, with one entry per option occurrence.
+    ///
+    /// A long option's value is joined to it with `=`, as options like `--kv` require; a short
+    /// option's value follows it as a separate argument.
+    ///
+    /// ```
+    /// use groupby::command_line::profiles::*;
+    ///
+    /// let profile = Profile {
+    ///     settings: vec![
+    ///         ("f".to_string(), ProfileValue::Integer(3)),
+    ///         ("kv".to_string(), ProfileValue::String(":".to_string())),
+    ///         ("print0".to_string(), ProfileValue::Boolean(true)),
+    ///         ("stats".to_string(), ProfileValue::Boolean(false)),
+    ///     ],
+    /// };
+    /// assert_eq!(
+    ///     profile.args(),
+    ///     vec![
+    ///         vec!["-f".to_string(), "3".to_string()],
+    ///         vec!["--kv=:".to_string()],
+    ///         vec!["--print0".to_string()],
+    ///     ],
+    /// );
+    /// ```
+    pub fn args(&self) -> Vec<Vec<String>> {
+        let short = |key: &str| key.chars().count() == 1;
+        let with_value = |key: &str, value: String| {
+            if short(key) {
+                vec![format!("-{}", key), value]
+            } else {
+                vec![format!("--{}={}", key, value)]
+            }
+        };
+
+        let mut args = vec![];
+        for (key, value) in &self.settings {
+            match value {
+                ProfileValue::String(s) => args.push(with_value(key, s.clone())),
+                ProfileValue::Integer(n) => args.push(with_value(key, n.to_string())),
+                ProfileValue::Boolean(true) if short(key) => args.push(vec![format!("-{}", key)]),
+                ProfileValue::Boolean(true) => args.push(vec![format!("--{}", key)]),
+                ProfileValue::Boolean(false) => (),
+                ProfileValue::Array(values) => {
+                    for s in values {
+                        args.push(with_value(key, s.clone()));
+                    }
+                }
+            }
+        }
+        args
+    }
+}
+
+/// Returns the path to the configuration file, if a home or configuration directory is known.
+pub fn config_path() -> Option<PathBuf> {
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("groupby").join("config.toml"))
+}
+
+/// Parses the profiles in a configuration file. See the [module documentation](self) for the
+/// supported syntax.
+///
+/// Tables other than `[profile.<name>]` are ignored, so that the file can hold other settings in
+/// the future.
+///
+/// ```
+/// use groupby::command_line::profiles::*;
+///
+/// let config = "
+///     [profile.logs]
+///     regex = '^(\\d+)' # Leading digits.
+///     labels = [\"0-4:low\", \"5-9:high\"]
+/// ";
+/// let profiles = parse_config(config).unwrap();
+/// assert_eq!(
+///     profiles["logs"].settings,
+///     vec![
+///         ("regex".to_string(), ProfileValue::String("^(\\d+)".to_string())),
+///         (
+///             "labels".to_string(),
+///             ProfileValue::Array(vec!["0-4:low".to_string(), "5-9:high".to_string()]),
+///         ),
+///     ],
+/// );
+/// ```
+pub fn parse_config(text: &str) -> Result<BTreeMap<String, Profile>, String> {
+    parse_tables(text, "profile")
+}
+
+/// Parses the presets in a configuration file, i.e. its `[preset.<name>]` tables, just as
+/// [parse_config()] parses its profiles.
+///
+/// ```
+/// use groupby::command_line::profiles::*;
+///
+/// let config = "
+///     [profile.logs]
+///     stats = true
+///
+///     [preset.names]
+///     basename = true
+/// ";
+/// let presets = parse_presets(config).unwrap();
+/// assert_eq!(presets.len(), 1);
+/// assert_eq!(
+///     presets["names"].settings,
+///     vec![("basename".to_string(), ProfileValue::Boolean(true))],
+/// );
+/// ```
+pub fn parse_presets(text: &str) -> Result<BTreeMap<String, Profile>, String> {
+    parse_tables(text, "preset")
+}
+
+/// Returns the presets built into `groupby`, by name:
+///
+/// - `extensions`: group file names by extension.
+/// - `log-by-day`: group log lines by the first date in each, written like `2024-03-09`.
+/// - `dupe-names`: group paths by file name, keeping only names that appear more than once.
+/// - `domains`: group URLs by registrable domain, e.g. `example.com`.
+///
+/// ```
+/// use groupby::command_line::profiles::*;
+///
+/// let presets = builtin_presets();
+/// assert_eq!(
+///     presets["extensions"].args(),
+///     vec![vec!["--extension".to_string()]],
+/// );
+/// ```
+pub fn builtin_presets() -> BTreeMap<String, Profile> {
+    let preset = |settings: &[(&str, ProfileValue)]| Profile {
+        settings: settings
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect(),
+    };
+    let flag = ProfileValue::Boolean(true);
+    BTreeMap::from([
+        (
+            "extensions".to_string(),
+            preset(&[("extension", flag.clone())]),
+        ),
+        (
+            "log-by-day".to_string(),
+            preset(&[("date-format", ProfileValue::String("%Y-%m-%d".to_string()))]),
+        ),
+        (
+            "dupe-names".to_string(),
+            preset(&[
+                ("basename", flag.clone()),
+                ("min-size", ProfileValue::Integer(2)),
+            ]),
+        ),
+        (
+            "domains".to_string(),
+            preset(&[("url-host", flag.clone()), ("domain", flag)]),
+        ),
+    ])
+}
+
+// Parses the [<kind>.<name>] tables in a configuration file, ignoring every other table.
+fn parse_tables(text: &str, kind: &str) -> Result<BTreeMap<String, Profile>, String> {
+    let mut profiles: BTreeMap<String, Profile> = BTreeMap::new();
+
+    // The profile that the current table belongs to, or None for any other table.
+    let mut current: Option<String> = None;
+    let mut in_table = false;
+
+    for (i, line) in text.lines().enumerate() {
+        let error = |message: &str| format!("Line {}: {}: {}", i + 1, message, line.trim());
+
+        let mut parser = Parser::new(line);
+        parser.skip_whitespace();
+        if parser.at_end() {
+            continue;
+        }
+
+        if parser.eat('[') {
+            let mut path = vec![parser.key().ok_or_else(|| error("expected a table name"))?];
+            while parser.eat('.') {
+                path.push(parser.key().ok_or_else(|| error("expected a table name"))?);
+            }
+            if !parser.eat(']') || !parser.at_end() {
+                return Err(error("expected ]"));
+            }
+
+            current = match path.as_slice() {
+                [table, name] if table == kind => {
+                    profiles.entry(name.clone()).or_default();
+                    Some(name.clone())
+                }
+                _ => None,
+            };
+            in_table = true;
+            continue;
+        }
+
+        let key = parser.key().ok_or_else(|| error("expected a key"))?;
+        if !parser.eat('=') {
+            return Err(error("expected ="));
+        }
+        let value = parser.value().ok_or_else(|| error("expected a value"))?;
+        if !parser.at_end() {
+            return Err(error("unexpected text after value"));
+        }
+
+        match &current {
+            Some(name) => profiles.get_mut(name).unwrap().settings.push((key, value)),
+            None if in_table => (),
+            None => {
+                return Err(error(&format!(
+                    "expected a [{}.<name>] table before any keys",
+                    kind
+                )))
+            }
+        }
+    }
+
+    Ok(profiles)
+}
+
+// A minimal parser for a single line of the configuration file. Each method skips whitespace
+// before (and comments after) what it parses.
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn new(line: &'a str) -> Self {
+        Parser { rest: line }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+        if self.rest.starts_with('#') {
+            self.rest = "";
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.rest.is_empty()
+    }
+
+    // Consumes c, if it's next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Parses a bare or quoted key.
+    fn key(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        if self.rest.starts_with(['"', '\''].as_ref()) {
+            return self.string();
+        }
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return None;
+        }
+        let key = self.rest[..end].to_string();
+        self.rest = &self.rest[end..];
+        Some(key)
+    }
+
+    fn value(&mut self) -> Option<ProfileValue> {
+        self.skip_whitespace();
+        if self.eat('[') {
+            let mut values = vec![];
+            while !self.eat(']') {
+                values.push(self.string()?);
+                if !self.eat(',') && !self.rest.starts_with(']') {
+                    return None;
+                }
+            }
+            return Some(ProfileValue::Array(values));
+        }
+        if self.rest.starts_with(['"', '\''].as_ref()) {
+            return self.string().map(ProfileValue::String);
+        }
+
+        let end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || c == '#')
+            .unwrap_or(self.rest.len());
+        let word = &self.rest[..end];
+        let value = match word {
+            "true" => ProfileValue::Boolean(true),
+            "false" => ProfileValue::Boolean(false),
+            _ => ProfileValue::Integer(word.replace('_', "").parse().ok()?),
+        };
+        self.rest = &self.rest[end..];
+        Some(value)
+    }
+
+    // Parses a basic ("...") or literal ('...') string.
+    fn string(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let mut chars = self.rest.char_indices();
+        let quote = match chars.next()? {
+            (_, c @ '"') | (_, c @ '\'') => c,
+            _ => return None,
+        };
+
+        let mut result = String::new();
+        while let Some((i, c)) = chars.next() {
+            if c == quote {
+                self.rest = &self.rest[i + 1..];
+                return Some(result);
+            }
+            if c == '\\' && quote == '"' {
+                result.push(match chars.next()?.1 {
+                    'b' => '\u{8}',
+                    't' => '\t',
+                    'n' => '\n',
+                    'f' => '\u{c}',
+                    'r' => '\r',
+                    '"' => '"',
+                    '\\' => '\\',
+                    _ => return None,
+                });
+            } else {
+                result.push(c);
+            }
+        }
+        None // Unterminated string.
+    }
+}
+
+/// Adds the options from the profile selected by `--profile`, if any, to the command-line `args`.
+///
+/// Reads profiles from [config_path()]. Returns `args` unchanged if no profile is selected.
+///
+/// # Errors
+///
+/// Returns [GroupByError::InvalidArgument] if a profile is selected but the configuration file
+/// can't be read or parsed, or if it doesn't contain the selected profile.
+pub fn with_profile(
+    command: &Command<'static>,
+    args: Vec<OsString>,
+) -> Result<Vec<OsString>, GroupByError> {
+    let name = match selected(command, &args, "general_profile") {
+        Some(name) => name,
+        None => return Ok(args),
+    };
+
+    let fail = |message: String| {
+        GroupByError::InvalidArgument(format!("Couldn't load profile {}: {}", name, message))
+    };
+
+    let path = config_path().ok_or_else(|| fail("no home directory".to_string()))?;
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| fail(format!("couldn't read {}: {}", path.display(), e)))?;
+    let mut profiles = parse_config(&text)
+        .map_err(|e| fail(format!("couldn't parse {}: {}", path.display(), e)))?;
+    let profile = profiles
+        .remove(&name)
+        .ok_or_else(|| fail(format!("no [profile.{}] in {}", name, path.display())))?;
+
+    Ok(layer(command, &profile, args))
+}
+
+/// Adds the options from the preset selected by `--preset`, if any, to the command-line `args`.
+///
+/// User-defined presets in the file at [config_path()] replace built-in presets of the same name.
+/// The file is optional, since the built-in presets don't need it. Returns `args` unchanged if no
+/// preset is selected.
+///
+/// # Errors
+///
+/// Returns [GroupByError::InvalidArgument] if a preset is selected but the configuration file
+/// exists and can't be read or parsed, or if no preset has the selected name.
+pub fn with_preset(
+    command: &Command<'static>,
+    args: Vec<OsString>,
+) -> Result<Vec<OsString>, GroupByError> {
+    let name = match selected(command, &args, "general_preset") {
+        Some(name) => name,
+        None => return Ok(args),
+    };
+
+    let fail = |message: String| {
+        GroupByError::InvalidArgument(format!("Couldn't load preset {}: {}", name, message))
+    };
+
+    let mut presets = builtin_presets();
+    if let Some(path) = config_path() {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => presets.extend(
+                parse_presets(&text)
+                    .map_err(|e| fail(format!("couldn't parse {}: {}", path.display(), e)))?,
+            ),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(fail(format!("couldn't read {}: {}", path.display(), e))),
+        }
+    }
+    let names: Vec<String> = presets.keys().cloned().collect();
+    let preset = presets.remove(&name).ok_or_else(|| {
+        fail(format!(
+            "no such preset; the presets are {}",
+            names.join(", ")
+        ))
+    })?;
+
+    Ok(layer(command, &preset, args))
+}
+
+// Returns the value given to the option id in args, if any, e.g. the name given to --profile.
+fn selected(command: &Command<'static>, args: &[OsString], id: &str) -> Option<String> {
+    // The profile might supply required options, so we can't insist that args is complete yet.
+    command
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(args)
+        .ok()?
+        .value_of(id)
+        .map(str::to_string)
+}
+
+/// Inserts the options from `profile` into `args` (which starts with the program's name), except
+/// for those that `args` already sets or that conflict with options in `args`.
+///
+/// ```
+/// use groupby::command_line::args;
+/// use groupby::command_line::profiles::*;
+///
+/// let profile = Profile {
+///     settings: vec![
+///         ("f".to_string(), ProfileValue::Integer(3)),
+///         ("print0".to_string(), ProfileValue::Boolean(true)),
+///     ],
+/// };
+///
+/// // The user chose a different grouper, so only --print0 applies.
+/// let layered = layer(&args(), &profile, vec!["groupby".into(), "--extension".into()]);
+/// assert_eq!(layered, vec!["groupby", "--print0", "--extension"]);
+/// ```
+pub fn layer(command: &Command<'static>, profile: &Profile, args: Vec<OsString>) -> Vec<OsString> {
+    let mut args = args.into_iter();
+    let mut accepted: Vec<OsString> = args.next().into_iter().collect();
+    let explicit: Vec<OsString> = args.collect();
+
+    // Several groupers make a composite key rather than conflicting, so clap won't stop a
+    // profile's grouper from joining the user's. Treat them as clashing anyway.
+    let program = accepted.clone();
+    let sets_grouper = |options: &[OsString]| {
+        let mut candidate = program.clone();
+        candidate.extend(options.iter().cloned());
+        command
+            .clone()
+            .ignore_errors(true)
+            .try_get_matches_from(&candidate)
+            .map(|matches| GROUPERS.iter().any(|id| matches.is_present(id)))
+            .unwrap_or(false)
+    };
+    let explicit_grouper = sets_grouper(&explicit);
+
+    for option in profile.args() {
+        let option: Vec<OsString> = option.into_iter().map(OsString::from).collect();
+        let mut candidate = accepted.clone();
+        candidate.extend(option.iter().cloned());
+        candidate.extend(explicit.iter().cloned());
+
+        // Let clap decide whether the option clashes with the explicit ones. Other errors, e.g. a
+        // missing grouper, are for the final parse to report.
+        let clashes = match command.clone().try_get_matches_from(&candidate) {
+            Ok(_) => false,
+            Err(e) => matches!(
+                e.kind(),
+                ErrorKind::ArgumentConflict | ErrorKind::UnexpectedMultipleUsage
+            ),
+        } || (explicit_grouper && sets_grouper(&option));
+        if !clashes {
+            accepted.extend(option);
+        }
+    }
+
+    accepted.extend(explicit);
+    accepted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_line::args;
+
+    fn strings(values: &[&str]) -> Vec<OsString> {
+        values.iter().map(OsString::from).collect()
+    }
+
+    mod parse_config {
+        use super::*;
+
+        #[test]
+        fn parses_value_types() {
+            let config = r#"
+                # A comment.
+                [profile."two words"]
+                split = "\t" # Basic strings interpret escapes.
+                regex = '\d'
+                f = 1_000
+                stats = false
+                "print0" = true
+            "#;
+            let profiles = parse_config(config).unwrap();
+            assert_eq!(
+                profiles["two words"].settings,
+                vec![
+                    ("split".to_string(), ProfileValue::String("\t".to_string())),
+                    ("regex".to_string(), ProfileValue::String("\\d".to_string())),
+                    ("f".to_string(), ProfileValue::Integer(1000)),
+                    ("stats".to_string(), ProfileValue::Boolean(false)),
+                    ("print0".to_string(), ProfileValue::Boolean(true)),
+                ]
+            );
+        }
+
+        #[test]
+        fn ignores_other_tables() {
+            let config = "[other]\nkey = 1\n[profile.a]\nw = true";
+            let profiles = parse_config(config).unwrap();
+            assert_eq!(profiles.len(), 1);
+            assert_eq!(profiles["a"].settings.len(), 1);
+        }
+
+        #[test]
+        fn rejects_keys_outside_tables() {
+            assert!(parse_config("w = true").is_err());
+        }
+
+        #[test]
+        fn keeps_presets_and_profiles_apart() {
+            let config = "[profile.a]\nw = true\n[preset.b]\nextension = true";
+            assert_eq!(
+                parse_config(config).unwrap().keys().collect::<Vec<_>>(),
+                vec!["a"]
+            );
+            assert_eq!(
+                parse_presets(config).unwrap().keys().collect::<Vec<_>>(),
+                vec!["b"]
+            );
+        }
+
+        #[test]
+        fn rejects_malformed_lines() {
+            assert!(parse_config("[profile.a").is_err());
+            assert!(parse_config("[profile.a]\nw true").is_err());
+            assert!(parse_config("[profile.a]\nsplit = \"unterminated").is_err());
+            assert!(parse_config("[profile.a]\nf = 3 4").is_err());
+            assert!(parse_config("[profile.a]\nlabel = [\"a\" \"b\"]").is_err());
+        }
+    }
+
+    mod layer {
+        use super::*;
+
+        fn profile(settings: &[(&str, ProfileValue)]) -> Profile {
+            Profile {
+                settings: settings
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.clone()))
+                    .collect(),
+            }
+        }
+
+        #[test]
+        fn adds_profile_options() {
+            let profile = profile(&[("f", ProfileValue::Integer(2))]);
+            let layered = layer(&args::args(), &profile, strings(&["app", "--stats"]));
+            assert_eq!(layered, strings(&["app", "-f", "2", "--stats"]));
+        }
+
+        #[test]
+        fn explicit_options_take_precedence() {
+            let profile = profile(&[
+                ("run-command", ProfileValue::String("wc -l".to_string())),
+                ("printspace", ProfileValue::Boolean(true)),
+            ]);
+            let layered = layer(
+                &args::args(),
+                &profile,
+                strings(&["app", "-f1", "-c", "cat", "--print0"]),
+            );
+            assert_eq!(layered, strings(&["app", "-f1", "-c", "cat", "--print0"]));
+        }
+
+        #[test]
+        fn explicit_groupers_replace_profile_groupers() {
+            let profile = profile(&[
+                ("f", ProfileValue::Integer(2)),
+                ("extension", ProfileValue::Boolean(true)),
+                ("stats", ProfileValue::Boolean(true)),
+            ]);
+
+            // Without an explicit grouper, the profile's groupers make a composite key.
+            let layered = layer(&args::args(), &profile, strings(&["app"]));
+            assert_eq!(
+                layered,
+                strings(&["app", "-f", "2", "--extension", "--stats"])
+            );
+
+            let layered = layer(&args::args(), &profile, strings(&["app", "--length"]));
+            assert_eq!(layered, strings(&["app", "--stats", "--length"]));
+        }
+
+        #[test]
+        fn joins_long_option_values() {
+            let profile = profile(&[
+                ("kv", ProfileValue::String(":".to_string())),
+                ("stats", ProfileValue::Boolean(true)),
+                ("stats-detail", ProfileValue::Integer(50)),
+            ]);
+            let layered = layer(&args::args(), &profile, strings(&["app"]));
+            assert_eq!(
+                layered,
+                strings(&["app", "--kv=:", "--stats", "--stats-detail=50"])
+            );
+
+            let matches = args::args().try_get_matches_from(&layered).unwrap();
+            assert_eq!(matches.value_of("groupers_by_key_value"), Some(":"));
+            assert_eq!(matches.value_of("output_stats_detail"), Some("50"));
+            assert!(matches.values_of("input_files").is_none());
+        }
+
+        #[test]
+        fn builtin_presets_are_valid() {
+            for (name, preset) in builtin_presets() {
+                let layered = layer(&args::args(), &preset, strings(&["app"]));
+                assert_eq!(layered.len(), preset.args().concat().len() + 1, "{}", name);
+                assert!(
+                    args::args().try_get_matches_from(&layered).is_ok(),
+                    "{}",
+                    name
+                );
+            }
+        }
+
+        #[test]
+        fn repeatable_options_combine() {
+            let profile = profile(&[("label", ProfileValue::Array(vec!["a-m:first".to_string()]))]);
+            let layered = layer(
+                &args::args(),
+                &profile,
+                strings(&["app", "-f1", "--label", "n-z:second"]),
+            );
+            assert_eq!(
+                layered,
+                strings(&["app", "--label=a-m:first", "-f1", "--label", "n-z:second"])
+            );
+        }
+    }
+}
@@ -1,5 +1,5 @@
 //! The [RecordWriter] type, which provides a record-oriented wrapper around a [writer](Write).
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
 
 /// Record-oriented wrapper around a [writer](Write).
 ///
@@ -25,30 +25,30 @@ impl<'a, W: Write> RecordWriter<'a, W> {
     }
 
     /// Writes a single value followed by a separator.
-    pub fn write(&mut self, value: &'_ str) {
-        self._write(value);
-        self.writer.flush().unwrap();
+    pub fn write(&mut self, value: &'_ str) -> io::Result<()> {
+        self._write(value)?;
+        self.writer.flush()
     }
 
     /// Writes a sequence of values, each followed by a separator.
     ///
     /// Because this method calls [BufWriter::flush()] once at the end instead of after each
     /// separator, it's faster than iterating yourself and calling [write] for each value.
-    pub fn write_all<I, S>(&mut self, values: I)
+    pub fn write_all<'v, I, S>(&mut self, values: I) -> io::Result<()>
     where
-        I: Iterator<Item = &'a S>,
-        S: 'a + ToString,
+        I: Iterator<Item = &'v S>,
+        S: 'v + ToString,
     {
         for value in values {
-            self._write(&value.to_string());
+            self._write(&value.to_string())?;
         }
-        self.writer.flush().unwrap();
+        self.writer.flush()
     }
 
     /// Write a value followed by a separator. (Does not flush.)
-    fn _write(&mut self, value: &str) {
-        self.writer.write_all(value.as_bytes()).unwrap();
-        self.writer.write_all(self.separator).unwrap();
+    fn _write(&mut self, value: &str) -> io::Result<()> {
+        self.writer.write_all(value.as_bytes())?;
+        self.writer.write_all(self.separator)
     }
 
     /// Consume self and return the inner [BufWriter].
@@ -60,7 +60,6 @@ impl<'a, W: Write> RecordWriter<'a, W> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io;
 
     #[derive(Clone, Debug, Eq, PartialEq)]
     struct MockWriter {
@@ -113,17 +112,17 @@ mod tests {
         #[test]
         fn writes_with_separator_and_flushes() {
             let mut writer = RecordWriter::new(MockWriter::new(), b"hoo");
-            writer.write("boo");
+            writer.write("boo").unwrap();
             writer.writer.into_inner().unwrap().check("boohoo", true);
         }
 
         #[test]
-        #[should_panic(expected = "WriteZero")]
-        fn panics_if_write_fails() {
+        fn returns_error_if_write_fails() {
             let mut buf = [0, 0];
             let writer = &mut buf[0..2];
             let mut writer = RecordWriter::new(writer, b"\0\0");
-            writer.write("ab");
+            let error = writer.write("ab").unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::WriteZero);
         }
     }
 
@@ -136,7 +135,7 @@ mod tests {
             let sep = ",\t";
 
             let mut writer = RecordWriter::new(MockWriter::new(), sep.as_bytes());
-            writer.write_all(values.iter());
+            writer.write_all(values.iter()).unwrap();
 
             let expected: String = values.join(sep) + sep;
             writer.writer.into_inner().unwrap().check(&expected, true);
@@ -153,7 +152,7 @@ mod tests {
             let mut buf = vec![];
 
             let mut writer = RecordWriter::new(&mut buf, sep.as_bytes());
-            writer.write_all(values.iter());
+            writer.write_all(values.iter()).unwrap();
 
             let expected: Vec<u8> = (values.join(sep) + sep).into_bytes();
 
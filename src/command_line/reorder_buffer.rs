@@ -0,0 +1,229 @@
+//! The [ReorderBuffer] type, which writes command results in order as soon as they're available.
+//!
+//! Commands that run in parallel finish in arbitrary order, so normally nothing is written until
+//! every command has finished. A [ReorderBuffer] is a [Report] that knows the order in which groups
+//! should be written. Each time a result arrives, it writes every result that's now next in line
+//! and holds on to the rest. Long runs show progress as they go, and the output is exactly what it
+//! would have been if it had been written all at once.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::command_runner::Report;
+//! use groupby::command_line::options::OutputOptions;
+//! use groupby::command_line::reorder_buffer::ReorderBuffer;
+//! use groupby::command_line::run_command::CommandOutput;
+//!
+//! let (a, b) = ("a".to_string(), "b".to_string());
+//! let values = vec!["1".to_string()];
+//! let options = OutputOptions {
+//!     run_command: Some("cat".to_string()),
+//!     ..Default::default()
+//! };
+//! let result = |stdout: &str| CommandOutput {
+//!     stdout: stdout.as_bytes().to_vec(),
+//!     ..Default::default()
+//! };
+//!
+//! let mut buffer = ReorderBuffer::new(vec![], vec![(&a, &values), (&b, &values)], &options);
+//!
+//! // b finishes first, but has to wait for a.
+//! buffer.report(&b, result("B"));
+//! assert!(buffer.output().is_empty());
+//!
+//! buffer.report(&a, result("A"));
+//! assert_eq!(String::from_utf8_lossy(buffer.output()), "a:\nA\nb:\nB\n");
+//!
+//! let results = buffer.finish().unwrap();
+//! assert_eq!(results.len(), 2);
+//! ```
+
+use crate::command_line::command_runner::Report;
+use crate::command_line::error::GroupByError;
+use crate::command_line::options::OutputOptions;
+use crate::command_line::run_command::CommandOutput;
+use crate::command_line::write_results::write_group_result;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+
+/// A [Report] that writes each group's result with [write_group_result()] as soon as the results
+/// for every group before it have been written.
+///
+/// Every result is also kept, so that [ReorderBuffer::finish] can return them all for
+/// [write_results()](crate::command_line::write_results()) to write statistics and for
+/// [check_failures()](crate::command_line::run_command::check_failures()).
+pub struct ReorderBuffer<'a, O: Write> {
+    // Where results are written.
+    output: O,
+
+    // The groups whose results haven't been written yet, in the order they should be written.
+    pending: VecDeque<(&'a String, &'a Vec<String>)>,
+
+    // Every result reported so far.
+    results: BTreeMap<&'a String, CommandOutput>,
+
+    // The options to write results with.
+    options: OutputOptions,
+
+    // The first error from writing, if any. Nothing more is written after an error.
+    error: Option<GroupByError>,
+}
+
+impl<'a, O: Write> ReorderBuffer<'a, O> {
+    /// Creates a buffer that writes results to `output` in the order of `groups`, using `options`
+    /// as [write_group_result()] does.
+    ///
+    /// `groups` should list exactly the groups whose commands will run. If a group's result never
+    /// arrives, e.g. because its command failed under
+    /// [FailurePolicy::FailFast](crate::command_line::options::FailurePolicy::FailFast), no later
+    /// results are written.
+    pub fn new(
+        output: O,
+        groups: Vec<(&'a String, &'a Vec<String>)>,
+        options: &OutputOptions,
+    ) -> Self {
+        ReorderBuffer {
+            output,
+            pending: groups.into(),
+            results: BTreeMap::new(),
+            options: options.clone(),
+            error: None,
+        }
+    }
+
+    /// Returns the output written so far.
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    /// Writes any results still held, in order, skipping groups whose results never arrived, e.g.
+    /// after an [interrupt](crate::command_line::interrupt). Then consumes the buffer and returns
+    /// every result reported to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from writing a result, if any.
+    pub fn finish(mut self) -> Result<BTreeMap<&'a String, CommandOutput>, GroupByError> {
+        let results = &self.results;
+        self.pending.retain(|(key, _)| results.contains_key(key));
+        self.write_ready();
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.results),
+        }
+    }
+
+    // Writes every result that's next in line.
+    fn write_ready(&mut self) {
+        while let Some((key, values)) = self.pending.front() {
+            let result = match self.results.get(key) {
+                Some(result) => result,
+                None => break,
+            };
+            let written = write_group_result(&mut self.output, key, values, result, &self.options)
+                .and_then(|()| self.output.flush().map_err(GroupByError::Io));
+            if let Err(e) = written {
+                self.error = Some(e);
+                self.pending.clear();
+                break;
+            }
+            self.pending.pop_front();
+        }
+    }
+}
+
+impl<'a, O: Write> Report<&'a String, CommandOutput> for ReorderBuffer<'a, O> {
+    /// Records `output` as the result for `key`, then writes every result that's next in line.
+    fn report(&mut self, key: &'a String, output: CommandOutput) {
+        self.results.insert(key, output);
+        self.write_ready();
+    }
+
+    fn get(&self, key: &&'a String) -> Option<&CommandOutput> {
+        self.results.get(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn result(stdout: &str) -> CommandOutput {
+        CommandOutput {
+            stdout: stdout.as_bytes().to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn options() -> OutputOptions {
+        OutputOptions {
+            run_command: Some("cat".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn writes_results_in_order_as_they_become_ready() {
+        let keys: Vec<String> = ["a", "b", "c"].iter().map(ToString::to_string).collect();
+        let values = vec![];
+        let groups = keys.iter().map(|key| (key, &values)).collect();
+        let mut buffer = ReorderBuffer::new(vec![], groups, &options());
+
+        buffer.report(&keys[1], result("B"));
+        assert_eq!(buffer.output(), b"");
+        buffer.report(&keys[0], result("A"));
+        assert_eq!(buffer.output(), b"a:\nA\nb:\nB\n");
+        buffer.report(&keys[2], result("C"));
+        assert_eq!(buffer.output(), b"a:\nA\nb:\nB\nc:\nC\n");
+
+        let results = buffer.finish().unwrap();
+        assert_eq!(results.get(&keys[1]), Some(&result("B")));
+    }
+
+    #[test]
+    fn holds_later_results_while_one_is_missing() {
+        let keys: Vec<String> = ["a", "b"].iter().map(ToString::to_string).collect();
+        let values = vec![];
+        let groups = keys.iter().map(|key| (key, &values)).collect();
+        let mut buffer = ReorderBuffer::new(vec![], groups, &options());
+
+        buffer.report(&keys[1], result("B"));
+        assert_eq!(buffer.output(), b"");
+    }
+
+    #[test]
+    fn finish_writes_held_results() {
+        let keys: Vec<String> = ["a", "b", "c"].iter().map(ToString::to_string).collect();
+        let values = vec![];
+        let groups = keys.iter().map(|key| (key, &values)).collect();
+        let mut output = vec![];
+        let mut buffer = ReorderBuffer::new(&mut output, groups, &options());
+
+        buffer.report(&keys[2], result("C"));
+        buffer.report(&keys[1], result("B"));
+        assert_eq!(buffer.finish().unwrap().len(), 2);
+        assert_eq!(output, b"b:\nB\nc:\nC\n");
+    }
+
+    #[test]
+    fn returns_first_write_error() {
+        struct Broken;
+
+        impl Write for Broken {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let key = "a".to_string();
+        let values = vec![];
+        let mut buffer = ReorderBuffer::new(Broken, vec![(&key, &values)], &options());
+        buffer.report(&key, result("A"));
+        assert!(matches!(buffer.finish(), Err(GroupByError::Io(_))));
+    }
+}
@@ -9,26 +9,71 @@
 //! as a user of this library.
 //!
 //! [current_shell()] and [shell_args()] abstract away the details of setting up a shell to run a
-//! command. Both functions are trivial.
+//! command. Both functions are nearly trivial. [substitute_key()] replaces the `{}` and `{key}`
+//! placeholders in a command with a group's key, quoted by [shell_quote()].
 //!
 //! [run_commands_in_parallel()] and [run_commands_sequentially()] take a shell command
 //! configuration (specified using a [ShellCommandOptions]) and a [GroupedCollection] and run the
-//! command over each group, building a [Report] with each group's standard output.
+//! command over each group, building a [Report] with each group's [CommandOutput].
 //!
 //! [capture_command_output] runs a single shell command and captures its output. This function, in
-//! turn, uses [command_runner::run()] to run the shell command.
+//! turn, uses [command_runner::run()] to run the shell command. [run_group_command] does the same
+//! but returns the command's exit status along with its output.
+//!
+//! Each command's environment describes its group: see [KEY_VAR], [SIZE_VAR], and [INDEX_VAR].
+//!
+//! With [ShellCommandOptions::live], each command's output is printed as it runs, one line at a
+//! time prefixed with the group's key, rather than captured.
+//!
+//! Both multi-group runners accept an optional [Checkpoint]. Groups the checkpoint already lists as
+//! complete are skipped, and each group whose command exits successfully is recorded in it. They
+//! also follow [ShellCommandOptions::failure_policy]; with [FailurePolicy::KeepGoing],
+//! [check_failures()] reports the failed commands once the results have been written.
+//!
+//! [dry_run()] describes the command each group would run, without running anything.
+//!
+//! After an [interrupt], the multi-group runners start no new commands and leave out the results of
+//! commands that the interrupt cut short.
+//!
+//! Every function that can fail returns a [GroupByError], so callers can decide how to report it.
 
+use crate::command_line::checkpoint::Checkpoint;
 use crate::command_line::command_runner::{self, *};
-use crate::command_line::OutputOptions;
+use crate::command_line::error::GroupByError;
+use crate::command_line::interrupt::{self, Running};
+#[cfg(feature = "rayon")]
+use crate::command_line::reorder_buffer::ReorderBuffer;
+use crate::command_line::{FailurePolicy, OutputOptions};
 use crate::grouped_collections::GroupedCollection;
-use rayon::prelude::*;
+#[cfg(feature = "rayon")]
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 use std::collections::BTreeMap;
+use std::env::VarError;
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::ops::Deref;
+use std::process::{ExitStatus, Output};
+#[cfg(feature = "rayon")]
 use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// The environment variable that stores the name of the current shell.
 const SHELL_VAR: &str = "SHELL";
 
+/// The shell to use when the `SHELL` environment variable isn't set.
+pub const DEFAULT_SHELL: &str = "/bin/sh";
+
+/// The environment variable that gives each command its group's key.
+pub const KEY_VAR: &str = "GROUPBY_KEY";
+
+/// The environment variable that gives each command the number of values in its group.
+pub const SIZE_VAR: &str = "GROUPBY_SIZE";
+
+/// The environment variable that gives each command its group's position, counting from 0, among
+/// the groups that [run_commands_in_parallel()] or [run_commands_sequentially()] runs, in the order
+/// they'd be printed. Groups skipped because of a [Checkpoint] still count.
+pub const INDEX_VAR: &str = "GROUPBY_INDEX";
+
 /// Options needed for running a shell command over a group.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ShellCommandOptions<'a> {
@@ -48,64 +93,379 @@ pub struct ShellCommandOptions<'a> {
     /// If false, for each value in the group, write the value followed by `line_separator` to the
     /// command's standard input.
     pub only_group_names: bool,
+
+    /// If `Some`, write at most this many values to each invocation of the command, running it
+    /// again for the rest of the group. Doesn't apply if `only_group_names` is true.
+    pub max_args: Option<usize>,
+
+    /// If `Some`, write at most this many bytes of values, counting `line_separator` after each, to
+    /// each invocation of the command. A single value larger than this is written on its own.
+    pub max_stdin_bytes: Option<usize>,
+
+    /// Limits on the resources each invocation of the command may use.
+    pub limits: Limits,
+
+    /// If true, capture the command's standard error in [CommandOutput::stderr]. If false, the
+    /// command writes to the standard error inherited from the caller, and
+    /// [CommandOutput::stderr] is empty.
+    pub capture_stderr: bool,
+
+    /// If true, print each line of the command's standard output to our standard output as soon
+    /// as it's written, prefixed with the group's key and `" | "`, and leave
+    /// [CommandOutput::stdout] empty. See [print_lines()].
+    pub live: bool,
+
+    /// What to do when a command exits unsuccessfully.
+    pub failure_policy: FailurePolicy,
+
+    /// Environment variables to set for every command, in addition to the ones it inherits from the
+    /// caller. These take precedence over the variables that describe the group, such as
+    /// [KEY_VAR].
+    pub env: Vec<(String, String)>,
+}
+
+impl<'a> ShellCommandOptions<'a> {
+    /// Sets up the options for running `command` in [OutputOptions::shell] or else the
+    /// [current shell](current_shell()), writing groups to it as specified by `options`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [OutputOptions::shell] is `None` and the current shell can't be
+    /// determined. See [current_shell()].
+    pub fn new(command: &'a str, options: &OutputOptions) -> Result<Self, GroupByError> {
+        let shell = match &options.shell {
+            Some(shell) => shell.clone(),
+            None => current_shell()?,
+        };
+        Ok(ShellCommandOptions {
+            shell,
+            shell_args: shell_args(command),
+            line_separator: options.separator.sep(),
+            only_group_names: options.only_group_names,
+            max_args: options.max_args,
+            max_stdin_bytes: options.max_stdin_bytes,
+            limits: Limits {
+                nice: options.nice,
+                max_memory: options.max_memory,
+                max_open_files: options.max_open_files,
+            },
+            capture_stderr: options.capture_stderr,
+            live: options.live,
+            failure_policy: options.failure_policy,
+            env: vec![],
+        })
+    }
+}
+
+/// The result of running a command over a single group.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::run_command::CommandOutput;
+/// use std::time::Duration;
+///
+/// let output = CommandOutput {
+///     stdout: b"3\n".to_vec(),
+///     duration: Duration::from_millis(12),
+///     ..Default::default()
+/// };
+/// assert!(output.success());
+/// assert_eq!(output.describe(), "12.00 ms");
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CommandOutput {
+    /// Everything the command wrote to standard output, unless [ShellCommandOptions::live] was
+    /// true, in which case it was printed instead, and this is empty.
+    pub stdout: Vec<u8>,
+
+    /// Everything the command wrote to standard error, if [ShellCommandOptions::capture_stderr]
+    /// was true. Otherwise, empty.
+    pub stderr: Vec<u8>,
+
+    /// The command's exit status.
+    pub status: ExitStatus,
+
+    /// How long the command took, from starting the shell until it exited.
+    pub duration: Duration,
+}
+
+impl CommandOutput {
+    /// Returns true if the command exited successfully.
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
+
+    /// Describes how the command went for a human reader: how long it took and, if it failed, its
+    /// exit status, e.g. `"exit status: 1, 3.50 ms"`.
+    pub fn describe(&self) -> String {
+        let duration = format!("{:.2} ms", self.duration.as_secs_f64() * 1000.0);
+        if self.success() {
+            duration
+        } else {
+            format!("{}, {}", self.status, duration)
+        }
+    }
 }
 
 /// Runs commands over a [GroupedCollection], if requested by [OutputOptions].
 ///
 /// If [OutputOptions::run_command] is `None`, returns `None` without doing anything else.
 /// Otherwise, runs the command over each group, using the provided options, and returns a
-/// [BTreeMap] mapping `map`'s keys to the captured standard output of each group's command.
+/// [BTreeMap] mapping `map`'s keys to the [CommandOutput] of each group's command. The output
+/// includes the command's standard error only if [OutputOptions::capture_stderr] is true.
 ///
 /// If [OutputOptions::parallel] is `true`, runs commands in parallel across all available CPU
-/// cores. If `false`, runs one command at a time. Note that sequential commands run according to
-/// the key sort order, whereas parallel commands may run in arbitrary order.
-pub fn run_command<'a, M>(
+/// cores, or in a dedicated thread pool of [OutputOptions::jobs] threads if that's a `Some` value.
+/// If `false`, runs one command at a time. Note that sequential commands run in the order that
+/// groups are printed (see [OutputOptions::order_groups]), whereas parallel commands may run in
+/// arbitrary order.
+///
+/// If [OutputOptions::incremental] is true, also writes each group's result to `output` as soon as
+/// it's ready, using a [ReorderBuffer]. Otherwise, nothing is written to `output`.
+///
+/// If [OutputOptions::checkpoint] is a `Some` value, opens that [Checkpoint] (resuming from it if
+/// [OutputOptions::resume] is true). Groups skipped because they were already complete are not
+/// present in the returned map.
+///
+/// # Errors
+///
+/// Returns an error if the current shell can't be determined, if the checkpoint file can't be
+/// opened, if a command can't be run, or if a command fails under [FailurePolicy::FailFast]. See
+/// [current_shell()], [open_checkpoint()], and [run_commands_in_parallel()].
+///
+/// Under [FailurePolicy::KeepGoing], failed commands aren't errors here; pass the results to
+/// [check_failures()] once you're done with them.
+///
+/// Requires the `rayon` feature.
+#[cfg(feature = "rayon")]
+pub fn run_command<'a, M, W>(
+    output: W,
     map: &'a M,
     options: &OutputOptions,
-) -> Option<BTreeMap<&'a String, Vec<u8>>>
+) -> Result<Option<BTreeMap<&'a String, CommandOutput>>, GroupByError>
 where
-    M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
-    &'a M: IntoParallelIterator<Item = (&'a String, &'a Vec<String>)>,
+    M: for<'s> GroupedCollection<'s, String, String, Vec<String>> + Sync,
+    W: Write + Send,
 {
     // Get the command to run, e.g. $SHELL -c "command", or return None.
-    let command: &String = options.run_command.as_ref()?;
+    let command: &String = match options.run_command.as_ref() {
+        Some(command) => command,
+        None => return Ok(None),
+    };
 
     // Set up the options our command runner needs.
-    let shell_command_options = ShellCommandOptions {
-        shell: current_shell(),
-        shell_args: shell_args(command),
-        line_separator: options.separator.sep(),
-        only_group_names: options.only_group_names,
+    let shell_command_options = ShellCommandOptions::new(command, options)?;
+
+    // Open the checkpoint file, if requested.
+    let checkpoint = open_checkpoint(options)?;
+
+    // Run commands and capture their output in a BTreeMap. For incremental output, write each
+    // group's result as soon as it's ready, in the order that write_results would use.
+    let checkpoint = checkpoint.as_ref();
+    let results = if options.incremental {
+        let mut groups: Vec<_> = map
+            .iter()
+            .filter(|(key, _)| !is_complete(checkpoint, key))
+            .collect();
+        options.order_groups(&mut groups, |values| values.len());
+        let buffer = ReorderBuffer::new(output, groups, options);
+        run_with(map, shell_command_options, options, checkpoint, buffer)?.finish()?
+    } else {
+        run_with(
+            map,
+            shell_command_options,
+            options,
+            checkpoint,
+            BTreeMap::new(),
+        )?
     };
 
-    // Run commands and capture standard output in a BTreeMap.
-    let results = BTreeMap::new();
-    let results = if options.parallel {
-        run_commands_in_parallel(map, shell_command_options, results)
+    Ok(Some(results))
+}
+
+// Runs commands with the runner that `options` asks for, reporting to `results`.
+#[cfg(feature = "rayon")]
+fn run_with<'a, M, R>(
+    map: &'a M,
+    shell_command_options: ShellCommandOptions,
+    options: &OutputOptions,
+    checkpoint: Option<&Checkpoint>,
+    results: R,
+) -> Result<R, GroupByError>
+where
+    M: for<'s> GroupedCollection<'s, String, String, Vec<String>> + Sync,
+    R: Report<&'a String, CommandOutput> + Send,
+{
+    if let (true, Some(jobs)) = (options.parallel, options.jobs) {
+        thread_pool(jobs)?.install(|| {
+            run_commands_in_parallel(map, shell_command_options, options, checkpoint, results)
+        })
+    } else if options.parallel {
+        run_commands_in_parallel(map, shell_command_options, options, checkpoint, results)
     } else {
-        run_commands_sequentially(map, shell_command_options, results)
+        run_commands_sequentially(map, shell_command_options, options, checkpoint, results)
+    }
+}
+
+// Builds a thread pool that runs at most `jobs` commands at a time.
+#[cfg(feature = "rayon")]
+fn thread_pool(jobs: usize) -> Result<rayon::ThreadPool, GroupByError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .map_err(|e| GroupByError::Command(io::Error::other(e)))
+}
+
+/// The most values of each batch that [dry_run()] shows.
+pub const DRY_RUN_PREVIEW: usize = 5;
+
+/// Describes, for each group, the command that [run_command()] would run, without running
+/// anything: the shell and its arguments, the working directory, the environment variables that
+/// describe the group, and a preview of what would be written to the command's standard input.
+///
+/// Groups are listed in the order they'd be printed; see [OutputOptions::order_groups]. If
+/// [OutputOptions::max_args] or [OutputOptions::max_stdin_bytes] would split a group's values over
+/// several invocations, each invocation's standard input is shown separately. At most
+/// [DRY_RUN_PREVIEW] values are shown per invocation.
+///
+/// [OutputOptions::checkpoint] is ignored, so that a dry run never creates or changes a checkpoint
+/// file; every group is listed, even one that a resumed run would skip.
+///
+/// If [OutputOptions::run_command] is `None`, does nothing.
+///
+/// # Errors
+///
+/// Returns an error if the current shell or working directory can't be determined, or if writing
+/// to `output` fails.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::OutputOptions;
+/// use groupby::command_line::run_command::dry_run;
+/// use std::collections::BTreeMap;
+///
+/// let mut map = BTreeMap::new();
+/// map.insert("a".to_string(), vec!["apple".to_string()]);
+/// let options = OutputOptions {
+///     run_command: Some("wc -l > {}.txt".to_string()),
+///     shell: Some("/bin/sh".to_string()),
+///     ..Default::default()
+/// };
+///
+/// let mut output = vec![];
+/// dry_run(&mut output, &map, &options).unwrap();
+/// let output = String::from_utf8(output).unwrap();
+/// assert!(output.contains("  Command: /bin/sh -c 'wc -l > a.txt'\n"));
+/// assert!(output.contains("  Environment: GROUPBY_KEY=a GROUPBY_SIZE=1 GROUPBY_INDEX=0\n"));
+/// assert!(output.contains("    \"apple\"\n"));
+/// ```
+pub fn dry_run<M, O>(mut output: O, map: &M, options: &OutputOptions) -> Result<(), GroupByError>
+where
+    M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
+    O: Write,
+{
+    let command = match options.run_command.as_ref() {
+        Some(command) => command,
+        None => return Ok(()),
     };
+    let shell_command_options = ShellCommandOptions::new(command, options)?;
+    let directory = std::env::current_dir()?;
+
+    let mut groups: Vec<_> = map.iter().collect();
+    options.order_groups(&mut groups, |values| values.len());
+    for (index, (key, values)) in groups.into_iter().enumerate() {
+        let command_line: Vec<String> = std::iter::once(shell_command_options.shell.clone())
+            .chain(
+                shell_command_options
+                    .shell_args
+                    .iter()
+                    .map(|arg| substitute_key(arg, key)),
+            )
+            .map(|arg| shell_quote(&arg))
+            .collect();
+        let env: Vec<String> = group_env(&shell_command_options, Some(index), key, values)
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, shell_quote(value)))
+            .collect();
+        writeln!(output, "{}:", key)?;
+        writeln!(output, "  Command: {}", command_line.join(" "))?;
+        writeln!(output, "  Directory: {}", directory.display())?;
+        writeln!(output, "  Environment: {}", env.join(" "))?;
 
-    Some(results)
+        let separator = &shell_command_options.line_separator;
+        if shell_command_options.only_group_names {
+            writeln!(
+                output,
+                "  Standard input: {:?}",
+                format!("{}{}", key, separator)
+            )?;
+            continue;
+        }
+        let batches = batches(&shell_command_options, values);
+        for (i, batch) in batches.iter().enumerate() {
+            let run = if batches.len() > 1 {
+                format!("run {} of {}, ", i + 1, batches.len())
+            } else {
+                String::new()
+            };
+            writeln!(
+                output,
+                "  Standard input ({}{} value{}, each followed by {:?}):",
+                run,
+                batch.len(),
+                if batch.len() == 1 { "" } else { "s" },
+                separator
+            )?;
+            for value in batch.iter().take(DRY_RUN_PREVIEW) {
+                writeln!(output, "    {:?}", value)?;
+            }
+            if batch.len() > DRY_RUN_PREVIEW {
+                writeln!(output, "    ... and {} more", batch.len() - DRY_RUN_PREVIEW)?;
+            }
+        }
+    }
+    Ok(())
 }
 
-/// Returns the current shell, e.g. `/usr/bin/zsh`.
-///
-/// # Panics
-///
-/// Exits with an error if it can't retrieve the current shell. This is because the function is
-/// meant only for internal use in the context of [run_command()], which is a top-level convenience
-/// method. A library user who prefers to handle this differently is free to invoke either
-/// [run_commands_in_parallel] or [run_commands_sequentially] directly and provide their own
-/// wrapping code.
-pub fn current_shell() -> String {
-    std::env::var(SHELL_VAR).unwrap_or_else(|e| {
-        eprintln!(
-            "Couldn't retrieve environment variable {}: {}",
-            SHELL_VAR, e
-        );
-        std::process::exit(1);
-    })
+/// Opens the [Checkpoint] requested by [OutputOptions::checkpoint], if any, resuming from it if
+/// [OutputOptions::resume] is true.
+///
+/// # Errors
+///
+/// Returns [GroupByError::Checkpoint] if the checkpoint file can't be opened or read.
+pub fn open_checkpoint(options: &OutputOptions) -> Result<Option<Checkpoint>, GroupByError> {
+    options
+        .checkpoint
+        .as_ref()
+        .map(|path| {
+            Checkpoint::open(path, options.resume)
+                .map_err(|e| GroupByError::Checkpoint(path.clone(), e))
+        })
+        .transpose()
+}
+
+/// Returns the current shell, e.g. `/usr/bin/zsh`, as given by the `SHELL` environment variable,
+/// or [DEFAULT_SHELL] if `SHELL` isn't set or is empty, as it often is for cron jobs and in
+/// containers.
+///
+/// # Errors
+///
+/// Returns [GroupByError::Shell] if the `SHELL` environment variable isn't valid Unicode. A
+/// library user who prefers to handle this differently is free to build a [ShellCommandOptions]
+/// directly and invoke either [run_commands_in_parallel] or [run_commands_sequentially].
+pub fn current_shell() -> Result<String, GroupByError> {
+    shell_from(std::env::var(SHELL_VAR))
+}
+
+// Holds the logic of current_shell(), given the value of SHELL_VAR.
+fn shell_from(var: Result<String, VarError>) -> Result<String, GroupByError> {
+    match var {
+        Ok(shell) if !shell.is_empty() => Ok(shell),
+        Ok(_) | Err(VarError::NotPresent) => Ok(DEFAULT_SHELL.to_string()),
+        Err(e) => Err(GroupByError::Shell(e)),
+    }
 }
 
 /// Initializes the shell arguments required to run a command via the current shell.
@@ -116,25 +476,136 @@ pub fn shell_args(cmd: &str) -> Vec<&str> {
     vec!["-c", cmd]
 }
 
+/// Replaces each `{}` or `{key}` placeholder in `command` with `key`, quoted for the shell by
+/// [shell_quote()].
+///
+/// Placeholders are found in a single pass, so a key that itself contains `{}` isn't substituted
+/// again. Doubled placeholders, `{{}}` and `{{key}}`, stand for a literal `{}` and `{key}`, for
+/// commands that need them, e.g. `xargs -I{{}} cp {{}} dest/`. Other braces, e.g. in
+/// `awk '{print $1}'`, are left alone.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::run_command::substitute_key;
+///
+/// assert_eq!(
+///     substitute_key("mkdir -p out/{} && cat > out/{key}/list.txt", "jpg"),
+///     "mkdir -p out/jpg && cat > out/jpg/list.txt",
+/// );
+/// assert_eq!(substitute_key("touch {}", "my file"), "touch 'my file'");
+/// assert_eq!(
+///     substitute_key("find {} -exec ls {{}} +", "src"),
+///     "find src -exec ls {} +",
+/// );
+/// ```
+pub fn substitute_key(command: &str, key: &str) -> String {
+    const PLACEHOLDERS: [&str; 2] = ["{}", "{key}"];
+    const ESCAPES: [(&str, &str); 2] = [("{{}}", "{}"), ("{{key}}", "{key}")];
+
+    let quoted = shell_quote(key);
+    let mut substituted = String::with_capacity(command.len());
+    let mut rest = command;
+    while let Some(start) = rest.find('{') {
+        substituted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some((escape, literal)) = ESCAPES.iter().find(|(e, _)| rest.starts_with(*e)) {
+            substituted.push_str(literal);
+            rest = &rest[escape.len()..];
+            continue;
+        }
+        match PLACEHOLDERS.iter().find(|p| rest.starts_with(*p)) {
+            Some(placeholder) => {
+                substituted.push_str(&quoted);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                substituted.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+/// Quotes `s` so that a POSIX shell reads it as a single word with exactly its contents.
+///
+/// Strings made only of characters that are never special to the shell are returned unchanged.
+/// Anything else is wrapped in single quotes, with each single quote written as `'\''`.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::run_command::shell_quote;
+///
+/// assert_eq!(shell_quote("out/2024-01.log"), "out/2024-01.log");
+/// assert_eq!(shell_quote("it's $HOME"), r"'it'\''s $HOME'");
+/// assert_eq!(shell_quote(""), "''");
+/// ```
+pub fn shell_quote(s: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "%+,-./:=@_".contains(c);
+    if !s.is_empty() && s.chars().all(is_safe) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
 /// Runs commands over groups in parallel.
 ///
 /// Runs the command specified by `options` once per group. See [capture_command_output()] for
 /// details on how the command is run.
 ///
 /// This version uses [Rayon](rayon) to run as many commands at a time as there are logical CPU
-/// cores. For a single-threaded version, see [run_commands_sequentially].
-pub fn run_commands_in_parallel<'a, M, R>(map: &'a M, options: ShellCommandOptions, results: R) -> R
+/// cores, in arbitrary order. Groups are ordered by `output` as [OutputOptions::order_groups] would
+/// print them, which only determines each group's [INDEX_VAR]. For a single-threaded version, see
+/// [run_commands_sequentially].
+///
+/// If `checkpoint` is a `Some` value, skips groups it lists as complete and records each group
+/// whose command exits successfully.
+///
+/// # Errors
+///
+/// Returns the first error from [run_group_command()], or [GroupByError::CommandFailed] for the
+/// first command that exits unsuccessfully if `options.failure_policy` is
+/// [FailurePolicy::FailFast]. Commands that have already started still run to completion, but no
+/// new commands start.
+#[cfg(feature = "rayon")]
+pub fn run_commands_in_parallel<'a, M, R>(
+    map: &'a M,
+    options: ShellCommandOptions,
+    output: &OutputOptions,
+    checkpoint: Option<&Checkpoint>,
+    results: R,
+) -> Result<R, GroupByError>
 where
     M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
-    &'a M: IntoParallelIterator<Item = (&'a String, &'a Vec<String>)>,
-    R: Report<&'a String, Vec<u8>> + Send,
+    R: Report<&'a String, CommandOutput> + Send,
 {
+    let mut groups: Vec<_> = map.iter().collect();
+    output.order_groups(&mut groups, |values| values.len());
+
     let results = Mutex::new(results);
-    map.par_iter().for_each(|(key, value)| {
-        let result = capture_command_output(&options, key, value);
-        results.report(key, result);
-    });
-    results.into_inner().unwrap()
+    groups
+        .into_par_iter()
+        .enumerate()
+        .filter(|(_, (key, _))| !is_complete(checkpoint, key))
+        .try_for_each(|(index, (key, value))| {
+            // After an interrupt, start no new commands, and leave out any command it cut short.
+            if interrupt::is_interrupted() {
+                return Ok(());
+            }
+            let output = run_indexed_group_command(&options, index, key, value)?;
+            if interrupt::is_interrupted() {
+                return Ok(());
+            }
+            record_if_successful(checkpoint, key, &output);
+            stop_if_failed(&options, key, &output)?;
+            results.report(key, output);
+            Ok::<_, GroupByError>(())
+        })?;
+    Ok(results.into_inner().unwrap())
 }
 
 /// Runs commands over groups, one at a time.
@@ -142,31 +613,122 @@ where
 /// Runs the command specified by `options` once per group. See [capture_command_output()] for
 /// details on how the command is run.
 ///
-/// This version is single-threaded, running only one command at a time. For a multi-threaded
-/// version, see [run_commands_in_parallel].
+/// This version is single-threaded, running only one command at a time, in the order that
+/// [OutputOptions::order_groups] gives for `output`. For a multi-threaded version, see
+/// [run_commands_in_parallel].
+///
+/// If `checkpoint` is a `Some` value, skips groups it lists as complete and records each group
+/// whose command exits successfully.
+///
+/// # Errors
+///
+/// Returns the first error from [run_group_command()], or [GroupByError::CommandFailed] for the
+/// first command that exits unsuccessfully if `options.failure_policy` is
+/// [FailurePolicy::FailFast], without running any later groups.
 pub fn run_commands_sequentially<'a, M, R>(
     map: &'a M,
     options: ShellCommandOptions,
+    output: &OutputOptions,
+    checkpoint: Option<&Checkpoint>,
     mut results: R,
-) -> R
+) -> Result<R, GroupByError>
 where
     M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
-    &'a M: IntoParallelIterator<Item = (&'a String, &'a Vec<String>)>,
-    R: Report<&'a String, Vec<u8>>,
+    R: Report<&'a String, CommandOutput>,
 {
+    let mut groups: Vec<_> = map.iter().collect();
+    output.order_groups(&mut groups, |values| values.len());
+
     // For simplicity, we'll match the format to run_commands_in_parallel.
-    map.iter().for_each(|(key, value)| {
-        let result = capture_command_output(&options, key, value);
-        results.report(key, result);
-    });
-    results
+    groups
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (key, _))| !is_complete(checkpoint, key))
+        .try_for_each(|(index, (key, value))| {
+            // After an interrupt, start no new commands, and leave out any command it cut short.
+            if interrupt::is_interrupted() {
+                return Ok(());
+            }
+            let output = run_indexed_group_command(&options, index, key, value)?;
+            if interrupt::is_interrupted() {
+                return Ok(());
+            }
+            record_if_successful(checkpoint, key, &output);
+            stop_if_failed(&options, key, &output)?;
+            results.report(key, output);
+            Ok::<_, GroupByError>(())
+        })?;
+    Ok(results)
+}
+
+// Returns true if `checkpoint` lists `key` as already complete.
+fn is_complete(checkpoint: Option<&Checkpoint>, key: &str) -> bool {
+    checkpoint.is_some_and(|checkpoint| checkpoint.is_complete(key))
+}
+
+// Records `key` in `checkpoint` if the command's `output` shows that it exited successfully.
+//
+// A failure to record a group shouldn't abort a long run, so we only warn about it. The worst case
+// is that the group runs again on resume.
+fn record_if_successful(checkpoint: Option<&Checkpoint>, key: &str, output: &CommandOutput) {
+    if let Some(checkpoint) = checkpoint {
+        if output.success() {
+            if let Err(e) = checkpoint.record(key) {
+                eprintln!("Couldn't record group {} in checkpoint: {}", key, e);
+            }
+        }
+    }
+}
+
+// Returns an error if the command's `output` shows that it failed and `options` says to stop.
+fn stop_if_failed(
+    options: &ShellCommandOptions,
+    key: &str,
+    output: &CommandOutput,
+) -> Result<(), GroupByError> {
+    match options.failure_policy {
+        FailurePolicy::FailFast if !output.success() => {
+            Err(GroupByError::CommandFailed(key.to_string(), output.status))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns [GroupByError::CommandsFailed], listing the keys of the groups whose commands exited
+/// unsuccessfully, if there are any.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::run_command::*;
+/// use std::collections::BTreeMap;
+///
+/// let mut results = BTreeMap::new();
+/// results.insert("a", CommandOutput::default());
+/// assert!(check_failures(&results).is_ok());
+/// ```
+pub fn check_failures<K: AsRef<str>>(
+    results: &BTreeMap<K, CommandOutput>,
+) -> Result<(), GroupByError> {
+    let failed: Vec<String> = results
+        .iter()
+        .filter(|(_, output)| !output.success())
+        .map(|(key, _)| key.as_ref().to_string())
+        .collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(GroupByError::CommandsFailed(failed))
+    }
 }
 
 /// Runs a shell command against a single group and returns its captured output.
 ///
-/// Runs the command specified by `options` once. Depending on `options.only_group_names`, it will
-/// pass either the group's `key` or the group's `values` to the command via standard input. In
-/// either case, each item passed to the group is followed by `options.line_separator`.
+/// Runs the command specified by `options` once, after replacing any `{}` or `{key}` placeholders
+/// in its shell arguments with the group's `key` (see [substitute_key()]). Depending on
+/// `options.only_group_names`, it will pass either the group's `key` or the group's `values` to the
+/// command via standard input. In either case, each item passed to the group is followed by
+/// `options.line_separator`.
 ///
 /// This is meant to sit on the inside of an iterator of the user's choice.
 /// [run_commands_in_parallel] and [run_commands_sequentially] essentially wrap this function in
@@ -174,12 +736,19 @@ where
 ///
 /// # Returns
 ///
-/// The captured standard output from the command. Standard error is not captured but is instead
-/// written to the standard error inherited from the caller.
+/// The captured standard output from the command. Standard error is discarded if
+/// `options.capture_stderr` is true, or otherwise written to the standard error inherited from the
+/// caller. To get the captured standard error, use [run_group_command()] instead.
+///
+/// # Errors
+///
+/// Returns an error under the same conditions as [run_group_command()].
 ///
 /// # Examples
 ///
 /// ```
+/// use groupby::command_line::command_runner::Limits;
+/// use groupby::command_line::options::FailurePolicy;
 /// use groupby::command_line::run_command::*;
 ///
 /// let options = ShellCommandOptions {
@@ -187,6 +756,13 @@ where
 ///     shell_args: vec!["-c", "cat"],
 ///     line_separator: "\n".to_string(),
 ///     only_group_names: false,
+///     max_args: None,
+///     max_stdin_bytes: None,
+///     limits: Limits::default(),
+///     capture_stderr: false,
+///     live: false,
+///     failure_policy: FailurePolicy::KeepGoing,
+///     env: vec![],
 /// };
 ///
 /// let key = "ABCs";
@@ -195,31 +771,255 @@ where
 ///     .map(ToString::to_string)
 ///     .collect();
 ///
-/// let output = capture_command_output(&options, &key, &values);
+/// let output = capture_command_output(&options, key, &values).unwrap();
 /// assert_eq!(&String::from_utf8_lossy(&output), "a\nb\nc\n");
 /// ```
 pub fn capture_command_output<'a>(
     options: &'a ShellCommandOptions,
     key: &'a str,
     values: &'a [String],
-) -> Vec<u8> {
+) -> Result<Vec<u8>, GroupByError> {
+    Ok(run_group_command(options, key, values)?.stdout)
+}
+
+/// Runs a shell command against a single group and returns its exit status, duration, and captured
+/// output.
+///
+/// This works exactly like [capture_command_output()] but returns the full [CommandOutput], so
+/// callers can tell whether the command succeeded, how long it took, and, if
+/// `options.capture_stderr` is true, what it wrote to standard error.
+///
+/// The command's environment includes [KEY_VAR] and [SIZE_VAR] for the group, then
+/// `options.env`. [INDEX_VAR] is only set by [run_commands_in_parallel()] and
+/// [run_commands_sequentially()], which know each group's position.
+///
+/// If `options.max_args` or `options.max_stdin_bytes` is a `Some` value, the group's values are
+/// split into batches, and the command runs once per batch, one after another. The batches' output
+/// is concatenated, and the group's status is that of the first batch that failed, if any.
+/// [SIZE_VAR] still gives the size of the whole group.
+///
+/// A command that exits without reading all of its standard input, e.g. `head -n 1`, isn't an
+/// error.
+///
+/// # Errors
+///
+/// Returns [GroupByError::Command] if the shell can't be started, if writing to its standard input
+/// fails, or if waiting for it fails.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::command_runner::Limits;
+/// use groupby::command_line::options::FailurePolicy;
+/// use groupby::command_line::run_command::*;
+///
+/// let options = ShellCommandOptions {
+///     shell: "/usr/bin/bash".to_string(),
+///     shell_args: vec!["-c", "cat; echo failed >&2; exit 3"],
+///     line_separator: "\n".to_string(),
+///     only_group_names: true,
+///     max_args: None,
+///     max_stdin_bytes: None,
+///     limits: Limits::default(),
+///     capture_stderr: true,
+///     live: false,
+///     failure_policy: FailurePolicy::KeepGoing,
+///     env: vec![],
+/// };
+///
+/// let values: Vec<String> = vec![];
+/// let output = run_group_command(&options, "ABCs", &values).unwrap();
+/// assert_eq!(output.status.code(), Some(3));
+/// assert!(!output.success());
+/// assert_eq!(&String::from_utf8_lossy(&output.stdout), "ABCs\n");
+/// assert_eq!(&String::from_utf8_lossy(&output.stderr), "failed\n");
+/// ```
+pub fn run_group_command<'a>(
+    options: &'a ShellCommandOptions,
+    key: &'a str,
+    values: &'a [String],
+) -> Result<CommandOutput, GroupByError> {
+    spawn_group_command(options, None, key, values)
+}
+
+// Runs a shell command against a single group like run_group_command(), also setting INDEX_VAR.
+fn run_indexed_group_command(
+    options: &ShellCommandOptions,
+    index: usize,
+    key: &str,
+    values: &[String],
+) -> Result<CommandOutput, GroupByError> {
+    spawn_group_command(options, Some(index), key, values)
+}
+
+// Holds the logic of run_group_command() and run_indexed_group_command().
+fn spawn_group_command(
+    options: &ShellCommandOptions,
+    index: Option<usize>,
+    key: &str,
+    values: &[String],
+) -> Result<CommandOutput, GroupByError> {
+    let env = group_env(options, index, key, values);
+
+    // Fill in the group's key wherever the command asks for it.
+    let shell_args: Vec<String> = options
+        .shell_args
+        .iter()
+        .map(|arg| substitute_key(arg, key))
+        .collect();
+
+    // Run the command once per batch of values, gathering the batches' output together. The group
+    // fails if any batch fails, and its status is that of the first batch that failed.
+    let start = Instant::now();
+    let mut output = CommandOutput::default();
+    for batch in batches(options, values) {
+        if interrupt::is_interrupted() {
+            break;
+        }
+        let batch_output = spawn_batch(options, &shell_args, &env, key, batch)?;
+        output.stdout.extend(batch_output.stdout);
+        output.stderr.extend(batch_output.stderr);
+        if output.success() {
+            output.status = batch_output.status;
+        }
+    }
+    output.duration = start.elapsed();
+    Ok(output)
+}
+
+// Describes the group in the command's environment. The caller's variables come last, so that
+// they take precedence.
+fn group_env(
+    options: &ShellCommandOptions,
+    index: Option<usize>,
+    key: &str,
+    values: &[String],
+) -> Vec<(String, String)> {
+    let mut env = vec![
+        (KEY_VAR.to_string(), key.to_string()),
+        (SIZE_VAR.to_string(), values.len().to_string()),
+    ];
+    if let Some(index) = index {
+        env.push((INDEX_VAR.to_string(), index.to_string()));
+    }
+    env.extend(options.env.iter().cloned());
+    env
+}
+
+// Splits `values` into batches, each small enough for a single invocation of the command under
+// options.max_args and options.max_stdin_bytes. There's always at least one batch, even if
+// `values` is empty, so that the command runs at least once, and no other batch is empty.
+fn batches<'v>(options: &ShellCommandOptions, values: &'v [String]) -> Vec<&'v [String]> {
+    if options.only_group_names {
+        return vec![values];
+    }
+    let max_args = options.max_args.unwrap_or(usize::MAX);
+    let max_bytes = options.max_stdin_bytes.unwrap_or(usize::MAX);
+
+    let mut batches = vec![];
+    let (mut start, mut bytes) = (0, 0);
+    for (i, value) in values.iter().enumerate() {
+        let size = value.len() + options.line_separator.len();
+        if i > start && (i - start == max_args || bytes + size > max_bytes) {
+            batches.push(&values[start..i]);
+            (start, bytes) = (i, 0);
+        }
+        bytes += size;
+    }
+    batches.push(&values[start..]);
+    batches
+}
+
+// Runs the command once, with `values` as its input, and returns its output.
+fn spawn_batch(
+    options: &ShellCommandOptions,
+    shell_args: &[String],
+    env: &[(String, String)],
+    key: &str,
+    values: &[String],
+) -> Result<Output, GroupByError> {
     // Spawn the new shell process.
     let mut handle = command_runner::run(
         &options.shell,
-        options.shell_args.iter().map(Deref::deref),
+        shell_args.iter().map(Deref::deref),
         &options.line_separator,
-    );
+        options.capture_stderr,
+        env,
+        interrupt::is_handled(),
+        options.limits,
+    )
+    .map_err(GroupByError::Command)?;
+    let _running = Running::new(handle.id());
 
-    // Pass along the group's contents (or name, if output.only_group_names) via stdin.
-    if options.only_group_names {
-        handle.stdin.write(key);
+    // Pass along the group's contents (or name, if output.only_group_names) via stdin, then wait
+    // for the process to finish. For live output, a second thread prints the command's output
+    // while we write, so that neither side blocks on a full pipe. Otherwise, we return its output
+    // so we can print it later.
+    if options.live {
+        let stdout = handle.stdout();
+        thread::scope(|scope| {
+            let printer = scope.spawn(|| print_lines(io::stdout(), key, stdout));
+            write_group(&mut handle, options, key, values)?;
+            let output = handle.wait_with_output().map_err(GroupByError::Command)?;
+            printer.join().expect("printing thread panicked")?;
+            Ok(output)
+        })
     } else {
-        handle.stdin.write_all(values.iter());
+        write_group(&mut handle, options, key, values)?;
+        handle.wait_with_output().map_err(GroupByError::Command)
     }
+}
 
-    // Wait for the process to finish, then record its output so we can print it later.
-    let output = handle.wait_with_output().unwrap();
-    output.stdout
+// Writes the group's key or values, as requested by `options`, to the command's standard input.
+fn write_group<'a>(
+    handle: &mut Handle<'a, std::process::Child>,
+    options: &ShellCommandOptions,
+    key: &str,
+    values: &'a [String],
+) -> Result<(), GroupByError> {
+    let written = if options.only_group_names {
+        handle.stdin.write(key)
+    } else {
+        handle.stdin.write_all(values.iter())
+    };
+    match written {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(GroupByError::Command(e)),
+        _ => Ok(()),
+    }
+}
+
+/// Copies each line from `input` to `output` as soon as it's read, prefixed with `key` and
+/// `" | "`, like the logs of `docker compose`.
+///
+/// Each line is written with a single call to [Write::write_all], so lines printed to
+/// [io::stdout()] by commands running in parallel are never mixed together. A final line without a
+/// newline gets one.
+///
+/// # Errors
+///
+/// Returns an error if reading from `input` or writing to `output` fails.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::run_command::print_lines;
+///
+/// let mut output = vec![];
+/// print_lines(&mut output, "web", &b"starting\nready"[..]).unwrap();
+/// assert_eq!(String::from_utf8_lossy(&output), "web | starting\nweb | ready\n");
+/// ```
+pub fn print_lines<W: Write, R: Read>(mut output: W, key: &str, input: R) -> io::Result<()> {
+    let prefix = format!("{} | ", key);
+    let mut input = BufReader::new(input);
+    let mut line = prefix.clone().into_bytes();
+    while input.read_until(b'\n', &mut line)? > 0 {
+        if !line.ends_with(b"\n") {
+            line.push(b'\n');
+        }
+        output.write_all(&line)?;
+        line.truncate(prefix.len());
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -227,7 +1027,9 @@ mod tests {
     use super::*;
     use crate::command_line::options::*;
     use crate::command_line::test_helpers::*;
+    use std::collections::BTreeMap;
 
+    #[cfg(feature = "rayon")]
     mod run_command {
         use super::*;
 
@@ -238,10 +1040,13 @@ mod tests {
             parallel: bool,
         ) -> GroupByOptions {
             GroupByOptions {
-                input: InputOptions {
-                    separator: Separator::Line,
-                },
+                input: InputOptions::default(),
                 grouping: GroupingSpecifier::FirstChars(1),
+                labels: Labels::default(),
+                unique: false,
+                min_size: None,
+                max_size: None,
+                count_values: false,
                 output: OutputOptions {
                     separator,
                     only_group_names,
@@ -249,7 +1054,10 @@ mod tests {
                     parallel,
                     headers: true,
                     stats: false,
+                    ..Default::default()
                 },
+                seed: None,
+                explain: false,
             }
         }
 
@@ -298,11 +1106,52 @@ mod tests {
                     );
 
                     let expected = expected_results(&map, &separator.sep(), only_group_names);
-                    let sequential_results = run_command(&map, &sequential_options.output);
-                    let parallel_results = run_command(&map, &parallel_options.output);
+                    let sequential_results =
+                        run_command(io::sink(), &map, &sequential_options.output).unwrap();
+                    let parallel_results =
+                        run_command(io::sink(), &map, &parallel_options.output).unwrap();
 
-                    verify_results(&expected, &sequential_results.as_ref().unwrap());
-                    verify_results(&expected, &parallel_results.as_ref().unwrap());
+                    verify_results(&expected, &stdouts(sequential_results.unwrap()));
+                    verify_results(&expected, &stdouts(parallel_results.unwrap()));
+                }
+
+                #[test]
+                fn with_jobs_limits_concurrent_commands() {
+                    // Each command holds a lock directory while it runs, so a second command
+                    // running at the same time would fail to create it.
+                    let lock = std::env::temp_dir()
+                        .join(format!("groupby-run-command-jobs-{}", std::process::id()));
+                    let command =
+                        format!("mkdir '{0}' && sleep 0.02 && rmdir '{0}'", lock.display());
+                    let mut map = BTreeMap::new();
+                    for key in ["a", "b", "c", "d"] {
+                        map.insert(key.to_string(), vec![]);
+                    }
+                    let mut options = options_for(Separator::Line, Some(command), false, true);
+                    options.output.jobs = Some(1);
+
+                    let results = run_command(io::sink(), &map, &options.output)
+                        .unwrap()
+                        .unwrap();
+
+                    assert_eq!(results.len(), 4);
+                    assert!(check_failures(&results).is_ok());
+                }
+
+                #[test]
+                fn writes_incremental_results_to_output() {
+                    let mut map = BTreeMap::new();
+                    map.insert("a".to_string(), vec!["1".to_string()]);
+                    map.insert("b".to_string(), vec!["2".to_string()]);
+                    let mut options =
+                        options_for(Separator::Line, Some("cat".to_string()), false, true);
+                    options.output.incremental = true;
+
+                    let mut output: Vec<u8> = vec![];
+                    let results = run_command(&mut output, &map, &options.output).unwrap();
+
+                    assert_eq!(results.unwrap().len(), 2);
+                    assert_eq!(String::from_utf8(output).unwrap(), "a:\n1\n\nb:\n2\n\n");
                 }
 
                 mod with_only_group_names {
@@ -331,6 +1180,111 @@ mod tests {
         }
     }
 
+    mod dry_run {
+        use super::*;
+
+        fn dry_run_output(map: &BTreeMap<String, Vec<String>>, options: OutputOptions) -> String {
+            let mut output = vec![];
+            dry_run(&mut output, map, &options).unwrap();
+            String::from_utf8(output).unwrap()
+        }
+
+        fn options_for(command: &str) -> OutputOptions {
+            OutputOptions {
+                run_command: Some(command.to_string()),
+                shell: Some("/bin/sh".to_string()),
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn does_nothing_without_command() {
+            assert_eq!(dry_run_output(&map(), OutputOptions::default()), "");
+        }
+
+        #[test]
+        fn describes_each_group_in_order() {
+            let mut map = BTreeMap::new();
+            map.insert("b c".to_string(), vec!["x".to_string()]);
+            map.insert("a".to_string(), vec![]);
+            let options = OutputOptions {
+                sort: KeyOrder::Bytes,
+                ..options_for("cat > {}")
+            };
+            let directory = std::env::current_dir().unwrap();
+            let expected = format!(
+                "a:\n  \
+                Command: /bin/sh -c 'cat > a'\n  \
+                Directory: {0}\n  \
+                Environment: GROUPBY_KEY=a GROUPBY_SIZE=0 GROUPBY_INDEX=0\n  \
+                Standard input (0 values, each followed by \"\\n\"):\n\
+                b c:\n  \
+                Command: /bin/sh -c 'cat > '\\''b c'\\'''\n  \
+                Directory: {0}\n  \
+                Environment: GROUPBY_KEY='b c' GROUPBY_SIZE=1 GROUPBY_INDEX=1\n  \
+                Standard input (1 value, each followed by \"\\n\"):\n    \
+                \"x\"\n",
+                directory.display()
+            );
+            assert_eq!(dry_run_output(&map, options), expected);
+        }
+
+        #[test]
+        fn numbers_groups_in_printed_order() {
+            let mut map = BTreeMap::new();
+            map.insert("a".to_string(), vec!["x".to_string(), "y".to_string()]);
+            map.insert("b".to_string(), vec!["z".to_string()]);
+            let options = OutputOptions {
+                group_order: GroupOrder::Size,
+                ..options_for("cat")
+            };
+            let output = dry_run_output(&map, options);
+            assert!(output.starts_with("b:\n"));
+            assert!(output.contains("GROUPBY_KEY=b GROUPBY_SIZE=1 GROUPBY_INDEX=0\n"));
+            assert!(output.contains("GROUPBY_KEY=a GROUPBY_SIZE=2 GROUPBY_INDEX=1\n"));
+        }
+
+        #[test]
+        fn shows_only_group_names() {
+            let options = OutputOptions {
+                only_group_names: true,
+                ..options_for("cat")
+            };
+            let output = dry_run_output(&map(), options);
+            assert!(output.contains("  Standard input: \"Cats\\n\"\n"));
+        }
+
+        #[test]
+        fn shows_each_batch_and_truncates_previews() {
+            let mut map = BTreeMap::new();
+            let values: Vec<String> = (0..8).map(|i| i.to_string()).collect();
+            map.insert("n".to_string(), values);
+            let options = OutputOptions {
+                max_args: Some(6),
+                ..options_for("cat")
+            };
+            let output = dry_run_output(&map, options);
+            assert!(output.contains(
+                "  Standard input (run 1 of 2, 6 values, each followed by \"\\n\"):\n    \
+                \"0\"\n    \"1\"\n    \"2\"\n    \"3\"\n    \"4\"\n    ... and 1 more\n  \
+                Standard input (run 2 of 2, 2 values, each followed by \"\\n\"):\n    \
+                \"6\"\n    \"7\"\n"
+            ));
+        }
+
+        #[test]
+        fn ignores_checkpoint() {
+            let path = std::env::temp_dir().join("groupby-dry-run-checkpoint");
+            let _ = std::fs::remove_file(&path);
+            let options = OutputOptions {
+                checkpoint: Some(path.to_string_lossy().into_owned()),
+                ..options_for("cat")
+            };
+            dry_run_output(&map(), options);
+            assert!(!path.exists());
+        }
+    }
+
     mod current_shell {
         use super::*;
 
@@ -339,7 +1293,20 @@ mod tests {
             // A cursory test will suffice here. Over-complicating things by swapping out the
             // environment variable for the running test probably doesn't make much sense.
             let expected = std::env::var(SHELL_VAR).unwrap();
-            assert_eq!(expected, current_shell());
+            assert_eq!(expected, current_shell().unwrap());
+        }
+
+        #[test]
+        fn falls_back_to_default_shell() {
+            for var in [Err(VarError::NotPresent), Ok("".to_string())] {
+                assert_eq!(shell_from(var).unwrap(), DEFAULT_SHELL);
+            }
+        }
+
+        #[test]
+        fn rejects_shell_that_isnt_unicode() {
+            let var = Err(VarError::NotUnicode("\u{fffd}".into()));
+            assert!(matches!(shell_from(var), Err(GroupByError::Shell(_))));
         }
     }
 
@@ -356,38 +1323,304 @@ mod tests {
         }
     }
 
+    mod substitute_key {
+        use super::*;
+
+        #[test]
+        fn replaces_every_placeholder() {
+            assert_eq!(substitute_key("{}-{key}-{}", "a"), "a-a-a");
+        }
+
+        #[test]
+        fn leaves_other_braces_alone() {
+            let command = "awk '{print $1}' | sed 's/x{2}/y/' {";
+            assert_eq!(substitute_key(command, "a"), command);
+        }
+
+        #[test]
+        fn keeps_doubled_placeholders_as_literals() {
+            assert_eq!(
+                substitute_key("xargs -I{{}} cp {{}} dest/{}", "a"),
+                "xargs -I{} cp {} dest/a"
+            );
+            assert_eq!(substitute_key("echo {{key}} {key}", "a"), "echo {key} a");
+            assert_eq!(substitute_key("awk '{{print}}'", "a"), "awk '{{print}}'");
+        }
+
+        #[test]
+        fn does_not_substitute_placeholders_in_keys() {
+            assert_eq!(substitute_key("echo {} {key}", "{}"), "echo '{}' '{}'");
+        }
+    }
+
+    mod shell_quote {
+        use super::*;
+
+        #[test]
+        fn quotes_special_characters() {
+            for (s, expected) in [
+                ("plain_Name-1.txt", "plain_Name-1.txt"),
+                ("two words", "'two words'"),
+                ("$(rm -rf ~)", "'$(rm -rf ~)'"),
+                ("a'b", r"'a'\''b'"),
+                ("tab\there", "'tab\there'"),
+                ("ünïcode", "'ünïcode'"),
+            ] {
+                assert_eq!(shell_quote(s), expected);
+            }
+        }
+    }
+
+    // Runs `runner` with a checkpoint that lists "Cats" as complete, then verifies that it skipped
+    // "Cats" and recorded "Dogs" in the checkpoint.
+    fn verify_checkpoint_use<F>(name: &str, runner: F)
+    where
+        F: for<'a> FnOnce(
+            &'a BTreeMap<String, Vec<String>>,
+            &Checkpoint,
+        ) -> Result<BTreeMap<&'a String, CommandOutput>, GroupByError>,
+    {
+        let path = std::env::temp_dir().join(format!(
+            "groupby-run-command-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, b"Cats\0").unwrap();
+        let checkpoint = Checkpoint::open(&path, true).unwrap();
+        let map = map();
+
+        let results = runner(&map, &checkpoint).unwrap();
+
+        let mut expected = expected_results(&map, "   ", false);
+        expected.remove(&"Cats".to_string());
+        assert_eq!(expected, stdouts(results));
+
+        drop(checkpoint);
+        assert_eq!(std::fs::read(&path).unwrap(), b"Cats\0Dogs\0");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "rayon")]
     mod run_commands_in_parallel {
         use super::*;
 
+        #[test]
+        fn with_checkpoint_skips_complete_groups_and_records_others() {
+            verify_checkpoint_use("parallel", |map, checkpoint| {
+                run_commands_in_parallel(
+                    map,
+                    options(false),
+                    &OutputOptions::default(),
+                    Some(checkpoint),
+                    results(),
+                )
+            });
+        }
+
+        #[test]
+        fn sets_index_in_given_order() {
+            let map = map();
+            let options = ShellCommandOptions {
+                shell_args: shell_args("printf %s \"$GROUPBY_INDEX\""),
+                ..options(false)
+            };
+            let results =
+                run_commands_in_parallel(&map, options, &OutputOptions::default(), None, results())
+                    .unwrap();
+            let indexes: Vec<_> = stdouts(results).into_values().collect();
+            assert_eq!(indexes, vec![b"0".to_vec(), b"1".to_vec()]);
+        }
+
+        #[test]
+        fn sets_index_in_printed_order() {
+            let map = map();
+            let options = ShellCommandOptions {
+                shell_args: shell_args("printf %s \"$GROUPBY_INDEX\""),
+                ..options(false)
+            };
+            let output = OutputOptions {
+                reverse: true,
+                ..Default::default()
+            };
+            let results =
+                run_commands_in_parallel(&map, options, &output, None, results()).unwrap();
+            let indexes: Vec<_> = stdouts(results).into_values().collect();
+            assert_eq!(indexes, vec![b"1".to_vec(), b"0".to_vec()]);
+        }
+
+        #[test]
+        fn with_fail_fast_returns_failed_group() {
+            let map = map();
+            let options = ShellCommandOptions {
+                shell_args: shell_args("grep -q Meowser"),
+                failure_policy: FailurePolicy::FailFast,
+                ..options(false)
+            };
+            let result =
+                run_commands_in_parallel(&map, options, &OutputOptions::default(), None, results());
+            assert!(matches!(result, Err(GroupByError::CommandFailed(key, _)) if key == "Dogs"),);
+        }
+
         #[test]
         fn returns_correct_results() {
             let map = map();
             let options = options(false);
             let results = results();
-            let results = run_commands_in_parallel(&map, options, results);
+            let results =
+                run_commands_in_parallel(&map, options, &OutputOptions::default(), None, results)
+                    .unwrap();
             let expected = expected_results(&map, "   ", false);
-            assert_eq!(expected, results);
+            assert_eq!(expected, stdouts(results));
         }
     }
 
     mod run_commands_sequentially {
         use super::*;
 
+        #[test]
+        fn with_checkpoint_skips_complete_groups_and_records_others() {
+            verify_checkpoint_use("sequential", |map, checkpoint| {
+                run_commands_sequentially(
+                    map,
+                    options(false),
+                    &OutputOptions::default(),
+                    Some(checkpoint),
+                    results(),
+                )
+            });
+        }
+
         #[test]
         fn returns_correct_results() {
             let map = map();
             let options = options(false);
             let results = results();
-            let results = run_commands_sequentially(&map, options, results);
+            let results =
+                run_commands_sequentially(&map, options, &OutputOptions::default(), None, results)
+                    .unwrap();
             let expected = expected_results(&map, "   ", false);
-            assert_eq!(expected, results);
+            assert_eq!(expected, stdouts(results));
+        }
+
+        #[test]
+        fn counts_skipped_groups_in_index() {
+            let path = std::env::temp_dir().join(format!(
+                "groupby-run-sequentially-index-{}",
+                std::process::id()
+            ));
+            std::fs::write(&path, b"Cats\0").unwrap();
+            let checkpoint = Checkpoint::open(&path, true).unwrap();
+            let map = map();
+            let options = ShellCommandOptions {
+                shell_args: shell_args("printf %s \"$GROUPBY_INDEX\""),
+                ..options(false)
+            };
+
+            let results = run_commands_sequentially(
+                &map,
+                options,
+                &OutputOptions::default(),
+                Some(&checkpoint),
+                results(),
+            )
+            .unwrap();
+
+            assert_eq!(results[&"Dogs".to_string()].stdout, b"1");
+            drop(checkpoint);
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn with_fail_fast_stops_at_first_failure() {
+            // "Cats" fails, so "Dogs" never runs, and "Cats" isn't recorded as complete.
+            let path = std::env::temp_dir().join(format!(
+                "groupby-run-sequentially-fail-fast-{}",
+                std::process::id()
+            ));
+            let checkpoint = Checkpoint::open(&path, false).unwrap();
+            let map = map();
+            let options = ShellCommandOptions {
+                shell_args: shell_args("grep -q Lassy"),
+                failure_policy: FailurePolicy::FailFast,
+                ..options(false)
+            };
+
+            let result = run_commands_sequentially(
+                &map,
+                options,
+                &OutputOptions::default(),
+                Some(&checkpoint),
+                results(),
+            );
+
+            assert!(
+                matches!(result, Err(GroupByError::CommandFailed(key, status)) if key == "Cats" && status.code() == Some(1)),
+            );
+            drop(checkpoint);
+            assert_eq!(std::fs::read(&path).unwrap(), b"");
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn with_keep_going_runs_every_command() {
+            let map = map();
+            let options = ShellCommandOptions {
+                shell_args: shell_args("grep -q Lassy"),
+                ..options(false)
+            };
+
+            let results = run_commands_sequentially(
+                &map,
+                options,
+                &OutputOptions::default(),
+                None,
+                results(),
+            )
+            .unwrap();
+
+            assert_eq!(results.len(), 2);
+            assert!(matches!(
+                check_failures(&results),
+                Err(GroupByError::CommandsFailed(keys)) if keys == vec!["Cats".to_string()]
+            ));
+        }
+
+        #[test]
+        fn runs_commands_in_order() {
+            // Each command appends its group's name to a shared file, so the file's contents show
+            // the order in which the commands ran.
+            let path = std::env::temp_dir().join(format!(
+                "groupby-run-sequentially-order-{}",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            let command = format!("cat >> '{}'", path.display());
+            let options = ShellCommandOptions {
+                shell_args: shell_args(&command),
+                ..options(true)
+            };
+
+            let mut map = BTreeMap::new();
+            for key in ["img10", "img2", "img1"] {
+                map.insert(key.to_string(), vec![]);
+            }
+
+            let output = OutputOptions {
+                sort: KeyOrder::Natural,
+                ..Default::default()
+            };
+            run_commands_sequentially(&map, options, &output, None, results()).unwrap();
+
+            let order = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(order, "img1   img2   img10   ");
+            std::fs::remove_file(&path).unwrap();
         }
     }
 
     mod capture_command_output {
         use super::*;
 
-        fn kv<'a>() -> (&'static str, Vec<String>) {
+        fn kv() -> (&'static str, Vec<String>) {
             (
                 "dogs",
                 vec!["Fido".to_string(), "Sam".to_string(), "Spot".to_string()],
@@ -401,7 +1634,7 @@ mod tests {
 
             // By converting values to strings, we make error output much easier to read.
             let expected = "dogs   ".to_string();
-            let actual = capture_command_output(&options, &key, &values);
+            let actual = capture_command_output(&options, key, &values).unwrap();
             let actual = String::from_utf8_lossy(&actual);
             assert_eq!(expected, actual);
         }
@@ -413,9 +1646,235 @@ mod tests {
 
             // By converting values to strings, we make error output much easier to read.
             let expected = "Fido   Sam   Spot   ".to_string();
-            let actual = capture_command_output(&options, &key, &values);
+            let actual = capture_command_output(&options, key, &values).unwrap();
             let actual = String::from_utf8_lossy(&actual);
             assert_eq!(expected, actual);
         }
     }
+
+    mod command_output {
+        use super::*;
+        use std::os::unix::process::ExitStatusExt;
+
+        #[test]
+        fn describes_failures_with_exit_status() {
+            let output = CommandOutput {
+                status: ExitStatus::from_raw(1 << 8),
+                duration: Duration::from_micros(3500),
+                ..Default::default()
+            };
+            assert!(!output.success());
+            assert_eq!(output.describe(), "exit status: 1, 3.50 ms");
+        }
+    }
+
+    mod run_group_command {
+        use super::*;
+
+        #[test]
+        fn describes_group_in_environment() {
+            let options = ShellCommandOptions {
+                shell_args: shell_args(
+                    "printf '%s|' \"$GROUPBY_KEY\" \"$GROUPBY_SIZE\" \"${GROUPBY_INDEX-unset}\"",
+                ),
+                ..options(false)
+            };
+            let (key, values) = ("dogs", vec!["Fido".to_string(), "Sam".to_string()]);
+            let output = run_group_command(&options, key, &values).unwrap();
+            assert_eq!(output.stdout, b"dogs|2|unset|");
+        }
+
+        #[test]
+        fn lets_caller_environment_take_precedence() {
+            let options = ShellCommandOptions {
+                shell_args: shell_args("printf %s \"$GROUPBY_KEY\""),
+                env: vec![(KEY_VAR.to_string(), "override".to_string())],
+                ..options(false)
+            };
+            let output = run_group_command(&options, "dogs", &[]).unwrap();
+            assert_eq!(output.stdout, b"override");
+        }
+
+        #[test]
+        fn substitutes_key_into_command() {
+            let options = ShellCommandOptions {
+                shell_args: shell_args("printf '%s|' {} {key}"),
+                ..options(false)
+            };
+            let output = run_group_command(&options, "it's a key", &[]).unwrap();
+            assert_eq!(output.stdout, b"it's a key|it's a key|");
+        }
+
+        #[test]
+        fn with_live_output_leaves_stdout_empty() {
+            // The command reads its input but prints nothing, to keep the test's output clean.
+            let options = ShellCommandOptions {
+                shell_args: shell_args("cat > /dev/null; exit 4"),
+                live: true,
+                ..options(false)
+            };
+            let values: Vec<String> = (0..100_000).map(|i| i.to_string()).collect();
+            let output = run_group_command(&options, "key", &values).unwrap();
+            assert_eq!(output.status.code(), Some(4));
+            assert!(output.stdout.is_empty());
+        }
+
+        #[test]
+        fn runs_command_once_per_batch() {
+            let options = ShellCommandOptions {
+                shell_args: shell_args("printf '%s:' \"$GROUPBY_SIZE\"; tr '\\n' ,; echo"),
+                line_separator: "\n".to_string(),
+                max_args: Some(2),
+                ..options(false)
+            };
+            let values: Vec<String> = ["a", "b", "c"].iter().map(ToString::to_string).collect();
+            let output = run_group_command(&options, "key", &values).unwrap();
+            assert_eq!(output.stdout, b"3:a,b,\n3:c,\n");
+        }
+
+        #[test]
+        fn reports_first_failed_batch() {
+            let options = ShellCommandOptions {
+                shell_args: shell_args("read n; exit $n"),
+                line_separator: "\n".to_string(),
+                max_args: Some(1),
+                ..options(false)
+            };
+            let values: Vec<String> = ["0", "3", "4"].iter().map(ToString::to_string).collect();
+            let output = run_group_command(&options, "key", &values).unwrap();
+            assert_eq!(output.status.code(), Some(3));
+        }
+
+        #[test]
+        fn measures_duration() {
+            let options = ShellCommandOptions {
+                shell_args: shell_args("sleep 0.05"),
+                ..options(false)
+            };
+            let output = run_group_command(&options, "key", &[]).unwrap();
+            assert!(output.duration >= Duration::from_millis(50));
+        }
+
+        #[test]
+        fn reports_shells_that_cant_start() {
+            let options = ShellCommandOptions {
+                shell: "/nonexistent/groupby-shell".to_string(),
+                ..options(false)
+            };
+            let result = run_group_command(&options, "key", &[]);
+            assert!(matches!(result, Err(GroupByError::Command(_))));
+        }
+
+        #[test]
+        fn allows_commands_that_dont_read_input() {
+            // Enough input to fill the pipe, so that writing fails once the command exits.
+            let values = vec!["x".repeat(1000); 1000];
+            let options = ShellCommandOptions {
+                shell_args: shell_args("exit 0"),
+                ..options(false)
+            };
+            let output = run_group_command(&options, "key", &values).unwrap();
+            assert!(output.status.success());
+        }
+
+        #[test]
+        fn captures_stderr_if_requested() {
+            for capture_stderr in [false, true] {
+                let options = ShellCommandOptions {
+                    shell_args: shell_args("cat; echo oops >&2"),
+                    capture_stderr,
+                    ..options(true)
+                };
+                let output = run_group_command(&options, "key", &[]).unwrap();
+                assert_eq!(output.stdout, b"key   ");
+                let expected: &[u8] = if capture_stderr { b"oops\n" } else { b"" };
+                assert_eq!(output.stderr, expected);
+            }
+        }
+    }
+
+    mod batches {
+        use super::*;
+
+        fn values(values: &[&str]) -> Vec<String> {
+            values.iter().map(ToString::to_string).collect()
+        }
+
+        #[test]
+        fn without_limits_returns_one_batch() {
+            let values = values(&["a", "b", "c"]);
+            assert_eq!(batches(&options(false), &values), vec![&values[..]]);
+        }
+
+        #[test]
+        fn with_no_values_returns_one_empty_batch() {
+            let options = ShellCommandOptions {
+                max_args: Some(1),
+                ..options(false)
+            };
+            assert_eq!(batches(&options, &[]), vec![&[] as &[String]]);
+        }
+
+        #[test]
+        fn limits_values_per_batch() {
+            let values = values(&["a", "b", "c", "d", "e"]);
+            let options = ShellCommandOptions {
+                max_args: Some(2),
+                ..options(false)
+            };
+            assert_eq!(
+                batches(&options, &values),
+                vec![&values[0..2], &values[2..4], &values[4..]]
+            );
+        }
+
+        #[test]
+        fn limits_bytes_per_batch_counting_separators() {
+            // The separator is three bytes, so each value takes up five.
+            let values = values(&["aa", "bb", "cc", "dddddddd", "ee"]);
+            let options = ShellCommandOptions {
+                max_stdin_bytes: Some(10),
+                ..options(false)
+            };
+            assert_eq!(
+                batches(&options, &values),
+                vec![&values[0..2], &values[2..3], &values[3..4], &values[4..]]
+            );
+        }
+
+        #[test]
+        fn ignores_limits_for_group_names() {
+            let values = values(&["a", "b"]);
+            let options = ShellCommandOptions {
+                max_args: Some(1),
+                ..options(true)
+            };
+            assert_eq!(batches(&options, &values), vec![&values[..]]);
+        }
+    }
+
+    mod print_lines {
+        use super::*;
+
+        #[test]
+        fn prefixes_each_line_with_key() {
+            let mut output = vec![];
+            print_lines(&mut output, "a b", &b"one\n\ntwo\n"[..]).unwrap();
+            assert_eq!(output, b"a b | one\na b | \na b | two\n");
+        }
+
+        #[test]
+        fn preserves_bytes_that_arent_utf8() {
+            let mut output = vec![];
+            print_lines(&mut output, "k", &b"caf\xe9"[..]).unwrap();
+            assert_eq!(output, b"k | caf\xe9\n");
+        }
+
+        #[test]
+        fn prints_nothing_without_input() {
+            let mut output = vec![];
+            print_lines(&mut output, "k", &b""[..]).unwrap();
+            assert!(output.is_empty());
+        }
+    }
 }
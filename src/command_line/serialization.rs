@@ -0,0 +1,351 @@
+//! Saving and restoring runs with [serde]. Requires the `serde` feature.
+//!
+//! With the feature enabled, [GroupByOptions] and every type it contains, such as [Separator] and
+//! [GroupingSpecifier], implement [Serialize] and [Deserialize]. Patterns that `groupby` compiles,
+//! such as regular expressions, globs, and JSON paths, are stored as the strings they were parsed
+//! from and compiled again when loaded, so a restored [GroupByOptions] groups exactly as the
+//! original did.
+//!
+//! Grouped collections and command results come in too many shapes to implement serde's traits
+//! directly, so this module provides functions for serde's `serialize_with` and
+//! `deserialize_with` attributes instead:
+//!
+//! - [serialize_groups] writes any [GroupedCollection] as a map from keys to groups, and
+//!   [deserialize_groups] reads such a map back into any [GroupedCollection] with a [Default].
+//!
+//! - [serialize_results] writes command outputs, such as the standard output of each command run by
+//!   [run_command](crate::command_line::run_command()), as a map from keys to outputs, and
+//!   [deserialize_results] reads them back. Each output is written as a string if it's valid
+//!   UTF-8, or as bytes otherwise.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::options::*;
+//! use groupby::command_line::serialization::*;
+//! use serde::{Deserialize, Serialize};
+//! use std::collections::BTreeMap;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Run {
+//!     options: GroupByOptions,
+//!     #[serde(serialize_with = "serialize_groups")]
+//!     #[serde(deserialize_with = "deserialize_groups")]
+//!     groups: BTreeMap<String, Vec<String>>,
+//!     #[serde(serialize_with = "serialize_results")]
+//!     #[serde(deserialize_with = "deserialize_results")]
+//!     results: BTreeMap<String, Vec<u8>>,
+//! }
+//!
+//! let options = GroupByOptions {
+//!     grouping: GroupingSpecifier::FirstChars(1),
+//!     ..Default::default()
+//! };
+//! let mut groups = BTreeMap::new();
+//! groups.insert("a".to_string(), vec!["apple".to_string(), "avocado".to_string()]);
+//! let mut results = BTreeMap::new();
+//! results.insert("a".to_string(), b"2\n".to_vec());
+//!
+//! let json = serde_json::to_string(&Run { options, groups, results }).unwrap();
+//! assert!(json.contains(r#""groups":{"a":["apple","avocado"]}"#));
+//! assert!(json.contains(r#""results":{"a":"2\n"}"#));
+//!
+//! let run: Run = serde_json::from_str(&json).unwrap();
+//! assert_eq!(run.options.grouping, GroupingSpecifier::FirstChars(1));
+//! assert_eq!(run.groups["a"], vec!["apple", "avocado"]);
+//! assert_eq!(run.results["a"], b"2\n");
+//! ```
+//!
+//! [GroupByOptions]: crate::command_line::options::GroupByOptions
+//! [GroupingSpecifier]: crate::command_line::options::GroupingSpecifier
+//! [Separator]: crate::command_line::options::Separator
+
+use crate::command_line::globs::Glob;
+use crate::command_line::json::JsonPath;
+use crate::command_line::key_expr::KeyExpr;
+use crate::command_line::size_buckets::SizeBuckets;
+use crate::command_line::template::Template;
+use crate::command_line::timestamps::TimestampFormat;
+use crate::grouped_collections::GroupedCollection;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter;
+use std::marker::PhantomData;
+
+// Implements Serialize and Deserialize for types that can be written with an as_str method and
+// read back with FromStr.
+macro_rules! serialize_as_str {
+    ($($type:ty),*) => {
+        $(
+            impl Serialize for $type {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_str(self.as_str())
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $type {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    String::deserialize(deserializer)?
+                        .parse()
+                        .map_err(de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+serialize_as_str!(
+    Glob,
+    JsonPath,
+    KeyExpr,
+    SizeBuckets,
+    Template,
+    TimestampFormat
+);
+
+/// Serializes a [Regex](regex::Regex) as its pattern, for serde's `with` attribute.
+pub mod regex_pattern {
+    use regex::Regex;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(regex.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        Regex::new(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// Serializes any [GroupedCollection] as a map from each key to its group, in the collection's
+/// iteration order. For serde's `serialize_with` attribute.
+pub fn serialize_groups<'s, M, Key, Value, List, S>(
+    map: &'s M,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    M: GroupedCollection<'s, Key, Value, List>,
+    Key: Serialize + 's,
+    Value: 's,
+    List: Serialize + 's,
+    S: Serializer,
+{
+    serializer.collect_map(map.iter())
+}
+
+/// Deserializes a map written by [serialize_groups] into a new, [Default] collection, adding
+/// each group with [GroupedCollection::merge]. For serde's `deserialize_with` attribute.
+pub fn deserialize_groups<'de, M, Key, Value, List, D>(deserializer: D) -> Result<M, D::Error>
+where
+    M: Default + for<'s> GroupedCollection<'s, Key, Value, List>,
+    Key: Clone + Deserialize<'de>,
+    List: IntoIterator<Item = Value> + Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_map(GroupsVisitor(PhantomData))
+}
+
+struct GroupsVisitor<M, Key, Value, List>(PhantomData<(M, Key, Value, List)>);
+
+impl<'de, M, Key, Value, List> Visitor<'de> for GroupsVisitor<M, Key, Value, List>
+where
+    M: Default + for<'s> GroupedCollection<'s, Key, Value, List>,
+    Key: Clone + Deserialize<'de>,
+    List: IntoIterator<Item = Value> + Deserialize<'de>,
+{
+    type Value = M;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map from keys to groups")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<M, A::Error> {
+        let mut map = M::default();
+        while let Some(group) = access.next_entry::<Key, List>()? {
+            map.merge(iter::once(group));
+        }
+        Ok(map)
+    }
+}
+
+/// Serializes command outputs, such as the standard output of each command run by
+/// [run_command](crate::command_line::run_command()), as a map from each key to its output. Each
+/// output is written as a string if it's valid UTF-8, or as bytes otherwise. For serde's
+/// `serialize_with` attribute.
+pub fn serialize_results<Key, Output, S>(
+    results: &BTreeMap<Key, Output>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    Key: Serialize,
+    Output: AsRef<[u8]>,
+    S: Serializer,
+{
+    serializer.collect_map(
+        results
+            .iter()
+            .map(|(key, output)| (key, CommandOutput(output.as_ref()))),
+    )
+}
+
+/// Deserializes a map written by [serialize_results]. For serde's `deserialize_with` attribute.
+pub fn deserialize_results<'de, D>(deserializer: D) -> Result<BTreeMap<String, Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let results: BTreeMap<String, OwnedCommandOutput> = Deserialize::deserialize(deserializer)?;
+    Ok(results
+        .into_iter()
+        .map(|(key, output)| (key, output.0))
+        .collect())
+}
+
+// A command's output, which serializes as a string when possible.
+struct CommandOutput<'a>(&'a [u8]);
+
+impl<'a> Serialize for CommandOutput<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match std::str::from_utf8(self.0) {
+            Ok(output) => serializer.serialize_str(output),
+            Err(_) => serializer.serialize_bytes(self.0),
+        }
+    }
+}
+
+// A command's output, which deserializes from a string, bytes, or a sequence of bytes.
+struct OwnedCommandOutput(Vec<u8>);
+
+impl<'de> Deserialize<'de> for OwnedCommandOutput {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(OutputVisitor)
+    }
+}
+
+struct OutputVisitor;
+
+impl<'de> Visitor<'de> for OutputVisitor {
+    type Value = OwnedCommandOutput;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a command's output, as a string or bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, output: &str) -> Result<Self::Value, E> {
+        Ok(OwnedCommandOutput(output.as_bytes().to_vec()))
+    }
+
+    fn visit_bytes<E: de::Error>(self, output: &[u8]) -> Result<Self::Value, E> {
+        Ok(OwnedCommandOutput(output.to_vec()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut output = Vec::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some(byte) = access.next_element()? {
+            output.push(byte);
+        }
+        Ok(OwnedCommandOutput(output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_line::options::*;
+    use regex::Regex;
+
+    #[test]
+    fn round_trips_compiled_patterns() {
+        let grouping = GroupingSpecifier::Composite(
+            vec![
+                GroupingSpecifier::Regex(
+                    Regex::new(r"(?P<year>\d{4})").unwrap(),
+                    CaptureGroup::Name("year".to_string()),
+                    Unmatched::Group("undated".to_string()),
+                ),
+                GroupingSpecifier::Glob(vec!["docs=*.md".parse().unwrap()]),
+                GroupingSpecifier::RegexRules(
+                    vec!["errors=ERROR".parse().unwrap(), r"\d+".parse().unwrap()],
+                    CaptureGroup::Number(0),
+                    Unmatched::Skip,
+                ),
+                GroupingSpecifier::JsonKey(".user.name".parse().unwrap()),
+                GroupingSpecifier::SizeBucket("1K, 1M".parse().unwrap()),
+                GroupingSpecifier::IgnoreCase(Box::new(GroupingSpecifier::NthWord(
+                    2,
+                    Regex::new(r"\s+").unwrap(),
+                ))),
+            ],
+            "/".to_string(),
+        );
+
+        let json = serde_json::to_string(&grouping).unwrap();
+        assert!(json.contains(r#""glob":"*.md""#));
+        assert!(json.contains(r#""1K,1M""#));
+        let restored: GroupingSpecifier = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, grouping);
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let error = serde_json::from_str::<GroupingSpecifier>(r#"{"Regex":["(",{"Number":1}]}"#)
+            .unwrap_err();
+        assert!(error.to_string().contains("regex parse error"));
+        assert!(serde_json::from_str::<SizeBuckets>(r#""1M,1K""#).is_err());
+    }
+
+    #[test]
+    fn round_trips_separators() {
+        for separator in [
+            Separator::Line,
+            Separator::Space,
+            Separator::Null,
+            Separator::Custom(", ".to_string()),
+        ] {
+            let json = serde_json::to_string(&separator).unwrap();
+            assert_eq!(serde_json::from_str::<Separator>(&json).unwrap(), separator);
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Results {
+        #[serde(serialize_with = "serialize_results")]
+        #[serde(deserialize_with = "deserialize_results")]
+        results: BTreeMap<String, Vec<u8>>,
+    }
+
+    #[test]
+    fn round_trips_binary_results() {
+        let mut results = BTreeMap::new();
+        results.insert("text".to_string(), b"ok\n".to_vec());
+        results.insert("binary".to_string(), vec![0xff, 0]);
+
+        let json = serde_json::to_string(&Results { results }).unwrap();
+        assert_eq!(json, r#"{"results":{"binary":[255,0],"text":"ok\n"}}"#);
+        let restored: Results = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.results["binary"], vec![0xff, 0]);
+        assert_eq!(restored.results["text"], b"ok\n");
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Groups {
+        #[serde(serialize_with = "serialize_groups")]
+        #[serde(deserialize_with = "deserialize_groups")]
+        groups: std::collections::HashMap<u32, Vec<u32>>,
+    }
+
+    #[test]
+    fn round_trips_groups() {
+        let mut groups = std::collections::HashMap::new();
+        groups.add(1, 10);
+        groups.add(2, 20);
+        groups.add(1, 11);
+
+        let json = serde_json::to_string(&Groups { groups }).unwrap();
+        let restored: Groups = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.groups.len(), 2);
+        assert_eq!(restored.groups.get(&1), Some(&vec![10, 11]));
+        assert_eq!(restored.groups.get(&2), Some(&vec![20]));
+    }
+}
@@ -0,0 +1,161 @@
+//! Size buckets, for grouping files by how much space they take up.
+//!
+//! [SizeBuckets] divides sizes into ranges at a list of boundaries, such as `1K,1M,100M`, and names
+//! each range after the boundaries around it: `<1K`, `1K-1M`, `1M-100M`, and `100M+`. Each range
+//! includes its lower boundary, so a file of exactly 1K falls in `1K-1M`.
+//!
+//! # Size syntax
+//!
+//! A size is a whole number of bytes, optionally followed by a unit: `K`, `M`, `G`, or `T`, for
+//! kibibytes, mebibytes, and so on (powers of 1024, like `du` and `ls -h`). Units are
+//! case-insensitive and may be followed by `B`, so `1k`, `1K`, and `1KB` all mean 1024 bytes.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::size_buckets::SizeBuckets;
+//!
+//! let buckets: SizeBuckets = "1K,1M,100M".parse().unwrap();
+//! assert_eq!(buckets.bucket(0), "<1K");
+//! assert_eq!(buckets.bucket(1024), "1K-1M");
+//! assert_eq!(buckets.bucket(5 << 20), "1M-100M");
+//! assert_eq!(buckets.bucket(1 << 40), "100M+");
+//! ```
+
+use std::str::FromStr;
+
+/// Parses a size, e.g. `"100M"`, into a number of bytes. See the
+/// [module documentation](self) for the syntax.
+///
+/// ```
+/// use groupby::command_line::size_buckets::parse_size;
+///
+/// assert_eq!(parse_size("512"), Ok(512));
+/// assert_eq!(parse_size("1K"), Ok(1024));
+/// assert_eq!(parse_size("2mb"), Ok(2 << 20));
+/// assert!(parse_size("1.5G").is_err());
+/// assert!(parse_size("1X").is_err());
+/// ```
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let error = || format!("Expected a size like 512, 1K, or 100M, but got: {}", s);
+
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+    let number: u64 = number.parse().map_err(|_| error())?;
+
+    let unit = unit.to_ascii_uppercase();
+    let unit = unit.strip_suffix('B').unwrap_or(&unit);
+    let shift = match unit {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(error()),
+    };
+    number.checked_mul(1 << shift).ok_or_else(error)
+}
+
+/// A sorted list of size boundaries that divides sizes into named ranges. See the
+/// [module documentation](self) for details.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SizeBuckets {
+    // The boundaries as written, without surrounding spaces, separated by commas.
+    spec: String,
+
+    boundaries: Vec<u64>,
+
+    // One more name than there are boundaries: names[i] is the range just below boundaries[i].
+    names: Vec<String>,
+}
+
+impl FromStr for SizeBuckets {
+    type Err = String;
+
+    /// Parses a comma-separated list of sizes in increasing order, e.g. `"1K,1M,100M"`.
+    ///
+    /// ```
+    /// use groupby::command_line::size_buckets::SizeBuckets;
+    ///
+    /// assert!("1K,1M".parse::<SizeBuckets>().is_ok());
+    /// assert!("1M,1K".parse::<SizeBuckets>().is_err());
+    /// assert!("".parse::<SizeBuckets>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let specs: Vec<&str> = s.split(',').map(str::trim).collect();
+        let boundaries = specs
+            .iter()
+            .map(|spec| parse_size(spec))
+            .collect::<Result<Vec<u64>, String>>()?;
+        if boundaries.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(format!(
+                "Size bucket boundaries must be in increasing order, but got: {}",
+                s
+            ));
+        }
+
+        let mut names = vec![format!("<{}", specs[0])];
+        for pair in specs.windows(2) {
+            names.push(format!("{}-{}", pair[0], pair[1]));
+        }
+        names.push(format!("{}+", specs[specs.len() - 1]));
+
+        Ok(SizeBuckets {
+            spec: specs.join(","),
+            boundaries,
+            names,
+        })
+    }
+}
+
+impl SizeBuckets {
+    /// Returns the boundaries as they were written, separated by commas.
+    ///
+    /// ```
+    /// use groupby::command_line::size_buckets::SizeBuckets;
+    ///
+    /// let buckets: SizeBuckets = "1K, 1M".parse().unwrap();
+    /// assert_eq!(buckets.as_str(), "1K,1M");
+    /// ```
+    pub fn as_str(&self) -> &str {
+        &self.spec
+    }
+
+    /// Returns the names of every range, from smallest to largest.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns the name of the range that contains `size`.
+    pub fn bucket(&self, size: u64) -> &str {
+        let index = self
+            .boundaries
+            .partition_point(|boundary| *boundary <= size);
+        &self.names[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn includes_lower_boundaries() {
+        let buckets: SizeBuckets = "10,20".parse().unwrap();
+        assert_eq!(buckets.bucket(9), "<10");
+        assert_eq!(buckets.bucket(10), "10-20");
+        assert_eq!(buckets.bucket(19), "10-20");
+        assert_eq!(buckets.bucket(20), "20+");
+    }
+
+    #[test]
+    fn names_ranges_as_written() {
+        let buckets: SizeBuckets = "1k, 2MB".parse().unwrap();
+        assert_eq!(buckets.bucket(1 << 20), "1k-2MB");
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert!(parse_size("99999999999T").is_err());
+    }
+}
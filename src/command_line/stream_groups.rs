@@ -0,0 +1,596 @@
+//! Low-memory processing for input that is already sorted by key.
+//!
+//! Normally, `groupby` reads all of its input before it can output anything, since any token might
+//! belong to any group. If the input is known to be ordered by the computed key, though, each group
+//! is complete as soon as a token with a different key arrives. [stream_groups()] takes advantage
+//! of this: it writes each group (or runs its command) as soon as the group is complete and then
+//! drops it, so memory use is proportional to the largest group rather than to the whole input.
+//!
+//! If the input is not actually sorted, nothing breaks, but a key that appears in several separate
+//! runs produces several separate groups, one per run.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::options::*;
+//! use groupby::command_line::stream_groups::stream_groups;
+//! use std::io::BufReader;
+//!
+//! let input = BufReader::new("apple\navocado\nbanana\ncherry\nclementine".as_bytes());
+//! let mut output = vec![];
+//! let options = GroupByOptions {
+//!     grouping: GroupingSpecifier::FirstChars(1),
+//!     output: OutputOptions {
+//!         presorted_streaming: true,
+//!         ..Default::default()
+//!     },
+//!     ..Default::default()
+//! };
+//!
+//! stream_groups(input, &mut output, &options).unwrap();
+//! assert_eq!(
+//!     String::from_utf8_lossy(&output),
+//!     "a:\napple\navocado\nb:\nbanana\nc:\ncherry\nclementine\n",
+//! );
+//! ```
+
+use crate::command_line::build_groups::build_groups;
+use crate::command_line::checkpoint::Checkpoint;
+use crate::command_line::error::GroupByError;
+use crate::command_line::options::*;
+use crate::command_line::run_command::*;
+use crate::command_line::write_results::write_results;
+use crate::grouped_collections::{GroupEntry, GroupedCollection};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+/// A [GroupedCollection] that holds only its current group.
+///
+/// When a value arrives with a different key than the current group's, the current group is
+/// complete: it is passed to the `emit` function and dropped, and a new group begins. Call
+/// [GroupStream::finish] after adding the last value to emit the final group.
+///
+/// Reads ([GroupedCollection::get] and [GroupedCollection::iter]) only see the current group.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::stream_groups::GroupStream;
+/// use groupby::grouped_collections::GroupedCollection;
+///
+/// let mut emitted = vec![];
+/// let mut stream = GroupStream::new(|key, values| emitted.push((key, values)));
+/// stream.add("a".to_string(), "1".to_string());
+/// stream.add("a".to_string(), "2".to_string());
+/// stream.add("b".to_string(), "3".to_string());
+/// stream.finish();
+///
+/// assert_eq!(
+///     emitted,
+///     vec![
+///         ("a".to_string(), vec!["1".to_string(), "2".to_string()]),
+///         ("b".to_string(), vec!["3".to_string()]),
+///     ]
+/// );
+/// ```
+pub struct GroupStream<F>
+where
+    F: FnMut(String, Vec<String>),
+{
+    current: Option<(String, Vec<String>)>,
+    emit: F,
+}
+
+impl<F> GroupStream<F>
+where
+    F: FnMut(String, Vec<String>),
+{
+    pub fn new(emit: F) -> Self {
+        GroupStream {
+            current: None,
+            emit,
+        }
+    }
+
+    /// Emits the current group, if any.
+    pub fn finish(mut self) {
+        if let Some((key, values)) = self.current.take() {
+            (self.emit)(key, values);
+        }
+    }
+}
+
+impl<'s, F> GroupedCollection<'s, String, String, Vec<String>> for GroupStream<F>
+where
+    F: FnMut(String, Vec<String>),
+{
+    type Iter = std::option::IntoIter<(&'s String, &'s Vec<String>)>;
+    type IntoIter = std::option::IntoIter<(String, Vec<String>)>;
+    type Entry = StreamEntry<'s>;
+
+    fn add(&mut self, key: String, value: String) {
+        match &mut self.current {
+            Some((current_key, values)) if *current_key == key => values.push(value),
+            current => {
+                if let Some((key, values)) = current.replace((key, vec![value])) {
+                    (self.emit)(key, values);
+                }
+            }
+        }
+    }
+
+    /// Emits the current group first if its key isn't `key`, since the current group must then be
+    /// complete.
+    fn entry(&'s mut self, key: String) -> Self::Entry {
+        if matches!(&self.current, Some((current_key, _)) if *current_key != key) {
+            let (key, values) = self.current.take().unwrap();
+            (self.emit)(key, values);
+        }
+        StreamEntry {
+            current: &mut self.current,
+            key,
+        }
+    }
+
+    fn get(&'s self, key: &String) -> Option<&'s Vec<String>> {
+        match &self.current {
+            Some((current_key, values)) if current_key == key => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Removes the current group, without emitting it, if its key is `key`.
+    fn remove(&mut self, key: &String) -> Option<Vec<String>> {
+        match &self.current {
+            Some((current_key, _)) if current_key == key => {
+                self.current.take().map(|(_, values)| values)
+            }
+            _ => None,
+        }
+    }
+
+    /// Filters only the current group, since earlier groups have already been emitted. A group
+    /// that is removed this way is never emitted.
+    fn retain<Keep>(&mut self, mut keep: Keep)
+    where
+        Keep: FnMut(&String, &mut Vec<String>) -> bool,
+    {
+        if let Some((key, values)) = &mut self.current {
+            if !keep(key, values) {
+                self.current = None;
+            }
+        }
+    }
+
+    /// Counts only the current group, if any, since earlier groups have already been emitted.
+    fn len(&self) -> usize {
+        self.current.iter().count()
+    }
+
+    fn contains_key(&self, key: &String) -> bool {
+        matches!(&self.current, Some((current_key, _)) if current_key == key)
+    }
+
+    fn iter(&'s self) -> Self::Iter {
+        self.current
+            .as_ref()
+            .map(|(key, values)| (key, values))
+            .into_iter()
+    }
+
+    /// Removes the current group, if any, without emitting it.
+    fn drain(&mut self) -> Self::IntoIter {
+        self.current.take().into_iter()
+    }
+}
+
+/// The [GroupEntry] that [GroupStream::entry] returns, which can only refer to the current group.
+pub struct StreamEntry<'s> {
+    current: &'s mut Option<(String, Vec<String>)>,
+    key: String,
+}
+
+impl<'s> GroupEntry<'s, String, Vec<String>> for StreamEntry<'s> {
+    fn push(self, value: String) {
+        self.or_insert_with(Vec::new).push(value);
+    }
+
+    fn or_insert_with<F>(self, default: F) -> &'s mut Vec<String>
+    where
+        F: FnOnce() -> Vec<String>,
+    {
+        let key = self.key;
+        &mut self.current.get_or_insert_with(|| (key, default())).1
+    }
+}
+
+/// Processes input that is sorted by key, writing each group to `output` as soon as it's complete.
+///
+/// Each group is written exactly as [write_results()] would write it. If
+/// [OutputOptions::run_command] is a `Some` value, each group's command runs as soon as the group
+/// is complete, and its output is written in place of the group's contents. Commands run one at a
+/// time, in input order, and honor [OutputOptions::checkpoint] and
+/// [OutputOptions::failure_policy]. Each command's [INDEX_VAR] counts groups in input order.
+///
+/// Groups outside [GroupByOptions::min_size] and [GroupByOptions::max_size] are skipped, and
+/// aren't counted by [INDEX_VAR]. If [GroupByOptions::count_values] is true, each group's values
+/// are counted before it's written.
+///
+/// [OutputOptions::stats] and [OutputOptions::sort] need every group at once, so they are ignored.
+///
+/// # Errors
+///
+/// Returns an error under the same conditions as [build_groups()], [run_command()], and
+/// [write_results()]. Groups after the first error aren't written, and their commands don't run,
+/// and neither is the group in progress when [build_groups()] fails. Otherwise, returns an error
+/// from [check_failures()] if any commands failed.
+pub fn stream_groups<I, O>(
+    input: I,
+    output: O,
+    options: &GroupByOptions,
+) -> Result<(), GroupByError>
+where
+    I: BufRead,
+    O: Write,
+{
+    let mut emitter = Emitter::new(output, options)?;
+
+    // Groups are emitted from inside the stream, which can't return errors, so we keep the first
+    // one here and skip every later group.
+    let mut error = None;
+    let mut stream = GroupStream::new(|key, values| {
+        if error.is_none() {
+            error = emitter.emit(key, values).err();
+        }
+    });
+
+    // If the input couldn't be processed, the current group might not be complete, so it's dropped.
+    let built = build_groups(input, &mut stream, options);
+    match built {
+        Ok(()) => stream.finish(),
+        Err(_) => drop(stream),
+    }
+    match error {
+        Some(error) => Err(error),
+        None => built.and_then(|()| emitter.finish()),
+    }
+}
+
+/// Writes complete groups to `output`, one at a time, in the order given, exactly as
+/// [stream_groups()] writes them. Only one group is held in memory at a time, so this can write
+/// the groups of a [DiskMap](crate::grouped_collections::DiskMap) as
+/// [into_groups](crate::grouped_collections::DiskMap::into_groups) reads them back.
+///
+/// ```
+/// use groupby::command_line::options::*;
+/// use groupby::command_line::stream_groups::write_groups;
+///
+/// let groups = vec![
+///     Ok(("a".to_string(), vec!["apple".to_string()])),
+///     Ok(("b".to_string(), vec!["banana".to_string(), "blueberry".to_string()])),
+/// ];
+/// let options = GroupByOptions {
+///     grouping: GroupingSpecifier::FirstChars(1),
+///     min_size: Some(2),
+///     ..Default::default()
+/// };
+///
+/// let mut output = vec![];
+/// write_groups(groups, &mut output, &options).unwrap();
+/// assert_eq!(String::from_utf8_lossy(&output), "b:\nbanana\nblueberry\n");
+/// ```
+///
+/// # Errors
+///
+/// Returns the first error from `groups`, as [GroupByError::Io], or any error that
+/// [stream_groups()] returns for the same groups.
+pub fn write_groups<G, O>(
+    groups: G,
+    output: O,
+    options: &GroupByOptions,
+) -> Result<(), GroupByError>
+where
+    G: IntoIterator<Item = io::Result<(String, Vec<String>)>>,
+    O: Write,
+{
+    let mut emitter = Emitter::new(output, options)?;
+    for group in groups {
+        let (key, values) = group?;
+        emitter.emit(key, values)?;
+    }
+    emitter.finish()
+}
+
+// Writes groups one at a time, or runs their commands, for stream_groups and write_groups.
+struct Emitter<'a, O> {
+    output: O,
+    options: &'a GroupByOptions,
+
+    // options.output, without statistics, which need every group at once.
+    output_options: OutputOptions,
+
+    shell_command_options: Option<ShellCommandOptions<'a>>,
+    checkpoint: Option<Checkpoint>,
+    failed: BTreeMap<String, CommandOutput>,
+
+    // Each command runs over a one-group collection, so we count the groups ourselves.
+    index: usize,
+}
+
+impl<'a, O: Write> Emitter<'a, O> {
+    fn new(output: O, options: &'a GroupByOptions) -> Result<Self, GroupByError> {
+        let output_options = OutputOptions {
+            stats: false,
+            ..options.output.clone()
+        };
+        let shell_command_options = options
+            .output
+            .run_command
+            .as_ref()
+            .map(|command| ShellCommandOptions::new(command, &options.output))
+            .transpose()?;
+        let checkpoint = open_checkpoint(&output_options)?;
+        Ok(Emitter {
+            output,
+            options,
+            output_options,
+            shell_command_options,
+            checkpoint,
+            failed: BTreeMap::new(),
+            index: 0,
+        })
+    }
+
+    // Writes a complete group, or runs its command, unless it's outside the size limits.
+    fn emit(&mut self, key: String, values: Vec<String>) -> Result<(), GroupByError> {
+        let mut group = BTreeMap::new();
+        group.insert(key, values);
+        self.options.finish_groups(&mut group);
+        if group.is_empty() {
+            return Ok(());
+        }
+
+        let group_index = self.index;
+        self.index += 1;
+
+        let results = self
+            .shell_command_options
+            .as_ref()
+            .map(|shell_command_options| {
+                let mut shell_command_options = shell_command_options.clone();
+                shell_command_options
+                    .env
+                    .push((INDEX_VAR.to_string(), group_index.to_string()));
+                run_commands_sequentially(
+                    &group,
+                    shell_command_options,
+                    &self.output_options,
+                    self.checkpoint.as_ref(),
+                    BTreeMap::new(),
+                )
+            })
+            .transpose()?;
+        write_results(&mut self.output, &group, &results, &self.output_options)?;
+        for (key, result) in results.into_iter().flatten() {
+            if !result.success() {
+                self.failed.insert(key.clone(), result);
+            }
+        }
+        Ok(())
+    }
+
+    // Reports the commands that failed, if any.
+    fn finish(&self) -> Result<(), GroupByError> {
+        check_failures(&self.failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    fn options_for(run_command: Option<&str>) -> GroupByOptions {
+        GroupByOptions {
+            input: InputOptions::default(),
+            grouping: GroupingSpecifier::FirstChars(1),
+            labels: Labels::default(),
+            unique: false,
+            min_size: None,
+            max_size: None,
+            count_values: false,
+            output: OutputOptions {
+                run_command: run_command.map(str::to_string),
+                stats: true,
+                presorted_streaming: true,
+                ..Default::default()
+            },
+            seed: None,
+            explain: false,
+        }
+    }
+
+    mod group_stream {
+        use super::*;
+
+        #[test]
+        fn emits_repeated_runs_separately() {
+            let mut emitted = vec![];
+            let mut stream = GroupStream::new(|key, _| emitted.push(key));
+            for key in ["a", "b", "a"] {
+                stream.add(key.to_string(), "value".to_string());
+            }
+            stream.finish();
+            assert_eq!(emitted, vec!["a", "b", "a"]);
+        }
+
+        #[test]
+        fn reads_see_only_current_group() {
+            let mut stream = GroupStream::new(|_, _| ());
+            stream.add("a".to_string(), "1".to_string());
+            stream.add("b".to_string(), "2".to_string());
+            assert_eq!(stream.get(&"a".to_string()), None);
+            assert_eq!(stream.get(&"b".to_string()), Some(&vec!["2".to_string()]));
+            assert_eq!(stream.iter().count(), 1);
+            assert_eq!(stream.len(), 1);
+            assert!(!stream.contains_key(&"a".to_string()));
+            assert!(stream.contains_key(&"b".to_string()));
+        }
+
+        #[test]
+        fn removed_group_is_not_emitted() {
+            let mut emitted = vec![];
+            let mut stream = GroupStream::new(|key, _| emitted.push(key));
+            stream.add("a".to_string(), "1".to_string());
+            stream.add("b".to_string(), "2".to_string());
+            assert_eq!(stream.remove(&"a".to_string()), None);
+            assert_eq!(stream.remove(&"b".to_string()), Some(vec!["2".to_string()]));
+            stream.add("c".to_string(), "3".to_string());
+            stream.retain(|key, _| key != "c");
+            stream.finish();
+            assert_eq!(emitted, vec!["a"]);
+        }
+
+        #[test]
+        fn entry_for_new_key_emits_current_group() {
+            let mut emitted = vec![];
+            let mut stream = GroupStream::new(|key, values| emitted.push((key, values)));
+            stream.entry("a".to_string()).push("1".to_string());
+            stream.entry("a".to_string()).push("2".to_string());
+            stream
+                .entry("b".to_string())
+                .or_insert_with(Vec::new)
+                .push("3".to_string());
+            stream.finish();
+            assert_eq!(
+                emitted,
+                vec![
+                    ("a".to_string(), vec!["1".to_string(), "2".to_string()]),
+                    ("b".to_string(), vec!["3".to_string()]),
+                ]
+            );
+        }
+
+        #[test]
+        fn finish_without_values_emits_nothing() {
+            let mut emitted = 0;
+            GroupStream::new(|_, _| emitted += 1).finish();
+            assert_eq!(emitted, 0);
+        }
+    }
+
+    mod stream_groups {
+        use super::*;
+
+        #[test]
+        fn runs_commands_and_ignores_stats() {
+            let input = BufReader::new("ab\nac\nbd".as_bytes());
+            let mut output = vec![];
+            stream_groups(input, &mut output, &options_for(Some("wc -l"))).unwrap();
+
+            // Trim any padding that wc adds.
+            let output = String::from_utf8_lossy(&output).replace(' ', "");
+            assert_eq!(output, "a:\n2\n\nb:\n1\n\n");
+        }
+
+        #[test]
+        fn writes_every_group_then_reports_failures() {
+            let input = BufReader::new("ab\nbc\ncd".as_bytes());
+            let mut output = vec![];
+            let options = GroupByOptions {
+                output: OutputOptions {
+                    stats: false,
+                    ..options_for(Some("grep -q c")).output
+                },
+                ..options_for(None)
+            };
+            let result = stream_groups(input, &mut output, &options);
+            assert!(matches!(
+                result,
+                Err(GroupByError::CommandsFailed(keys)) if keys == vec!["a".to_string()]
+            ));
+            assert_eq!(String::from_utf8_lossy(&output), "a:\n\nb:\n\nc:\n\n");
+        }
+
+        #[test]
+        fn counts_groups_in_input_order() {
+            let input = BufReader::new("ab\nbc\nad".as_bytes());
+            let mut output = vec![];
+            let options = GroupByOptions {
+                output: OutputOptions {
+                    headers: false,
+                    stats: false,
+                    ..options_for(Some("printf %s \"$GROUPBY_KEY$GROUPBY_INDEX\"")).output
+                },
+                ..options_for(None)
+            };
+            stream_groups(input, &mut output, &options).unwrap();
+            assert_eq!(String::from_utf8_lossy(&output), "a0\nb1\na2\n");
+        }
+
+        #[test]
+        fn removes_duplicates_from_each_group() {
+            let input = BufReader::new("ab\nab\nac\nbd\nbd".as_bytes());
+            let mut output = vec![];
+            let options = GroupByOptions {
+                unique: true,
+                ..options_for(None)
+            };
+            stream_groups(input, &mut output, &options).unwrap();
+            assert_eq!(String::from_utf8_lossy(&output), "a:\nab\nac\nb:\nbd\n");
+        }
+
+        #[test]
+        fn skips_groups_outside_size_limits() {
+            let input = BufReader::new("ab\nac\nbd\ncd\nce\ncf".as_bytes());
+            let mut output = vec![];
+            let options = GroupByOptions {
+                min_size: Some(2),
+                max_size: Some(2),
+                ..options_for(Some("printf %s \"$GROUPBY_INDEX\""))
+            };
+            stream_groups(input, &mut output, &options).unwrap();
+            assert_eq!(String::from_utf8_lossy(&output), "a:\n0\n");
+        }
+
+        #[test]
+        fn counts_values() {
+            let input = BufReader::new("ab\nab\nac\nbd".as_bytes());
+            let mut output = vec![];
+            let options = GroupByOptions {
+                count_values: true,
+                ..options_for(None)
+            };
+            stream_groups(input, &mut output, &options).unwrap();
+            assert_eq!(
+                String::from_utf8_lossy(&output),
+                "a:\n2 ab\n1 ac\nb:\n1 bd\n"
+            );
+        }
+
+        #[test]
+        fn stops_at_the_first_error() {
+            let input = BufReader::new(&b"ab\nbc\n\xff\ncd"[..]);
+            let mut output = vec![];
+            let options = GroupByOptions {
+                input: InputOptions {
+                    invalid_utf8: InvalidUtf8::Strict,
+                    files: vec![],
+                    files0_from: None,
+                    with_filename: false,
+                    walk: None,
+                    tee: None,
+                    trim: false,
+                    skip_empty: false,
+                    limit: None,
+                    sample: None,
+                    ..options_for(None).input
+                },
+                ..options_for(None)
+            };
+            let result = stream_groups(input, &mut output, &options);
+            assert!(matches!(result, Err(GroupByError::InvalidInput(_))));
+            assert_eq!(String::from_utf8_lossy(&output), "a:\nab\n");
+        }
+    }
+}
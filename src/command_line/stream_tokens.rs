@@ -0,0 +1,150 @@
+//! Token streaming, for watching long-running input be classified as it arrives.
+//!
+//! Normally, `groupby` reads all of its input before it writes anything. With
+//! [OutputOptions::stream], [stream_tokens()] instead writes each token as soon as its key is
+//! known, as the key, a tab, and the token, followed by [OutputOptions::separator]. Nothing is
+//! collected, so memory use stays flat no matter how long the input runs, and a pipeline fed by
+//! `tail -f` sees each token right away. Tokens aren't gathered into groups; to gather them later,
+//! pipe the output through `sort` or `groupby --kv`.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::stream_tokens::TokenWriter;
+//! use groupby::command_line::build_groups::Sink;
+//! use groupby::command_line::options::Separator;
+//!
+//! let mut output = vec![];
+//! let mut writer = TokenWriter::new(&mut output, &Separator::Line);
+//! writer.send("a".to_string(), "apple".to_string()).unwrap();
+//! writer.send("b".to_string(), "banana".to_string()).unwrap();
+//! assert_eq!(String::from_utf8_lossy(&output), "a\tapple\nb\tbanana\n");
+//! ```
+
+use crate::command_line::build_groups::{send_tokens_from_inputs, Sink};
+use crate::command_line::error::GroupByError;
+use crate::command_line::options::*;
+use std::io::Write;
+
+/// A [Sink] that writes each token it receives as its key, a tab, the token, and a separator,
+/// flushing after each one so that the token is seen at once.
+pub struct TokenWriter<W: Write> {
+    output: W,
+    separator: String,
+
+    // Reused for each token, so that it's written with a single call.
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> TokenWriter<W> {
+    pub fn new(output: W, separator: &Separator) -> Self {
+        TokenWriter {
+            output,
+            separator: separator.sep(),
+            buffer: vec![],
+        }
+    }
+}
+
+impl<W: Write> Sink for TokenWriter<W> {
+    fn send(&mut self, key: String, value: String) -> Result<(), GroupByError> {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(key.as_bytes());
+        self.buffer.push(b'\t');
+        self.buffer.extend_from_slice(value.as_bytes());
+        self.buffer.extend_from_slice(self.separator.as_bytes());
+        self.output.write_all(&self.buffer)?;
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads the inputs named by `options` and writes each token to `output` as soon as its key is
+/// known. See the [module documentation](self).
+///
+/// # Errors
+///
+/// Returns any error that [send_tokens_from_inputs] returns, including [GroupByError::Io] if
+/// writing to `output` fails, after which no more input is read.
+pub fn stream_tokens<O: Write>(output: O, options: &GroupByOptions) -> Result<(), GroupByError> {
+    let mut writer = TokenWriter::new(output, &options.output.separator);
+    send_tokens_from_inputs(&mut writer, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    // An output that accepts a fixed number of writes, then fails.
+    struct FailingWriter(usize);
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            self.0 -= 1;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn options(files: Vec<std::path::PathBuf>) -> GroupByOptions {
+        GroupByOptions {
+            input: InputOptions {
+                files,
+                ..Default::default()
+            },
+            grouping: GroupingSpecifier::FirstChars(1),
+            labels: Labels::new(vec!["b:berries".parse().unwrap()]),
+            unique: false,
+            min_size: None,
+            max_size: None,
+            count_values: false,
+            output: OutputOptions {
+                separator: Separator::Null,
+                stream: true,
+                ..Default::default()
+            },
+            seed: None,
+            explain: false,
+        }
+    }
+
+    #[test]
+    fn writes_each_labeled_token_with_the_output_separator() {
+        let path = std::env::temp_dir().join(format!("groupby-stream-{}", std::process::id()));
+        std::fs::write(&path, "apple\nbanana\n").unwrap();
+
+        let mut output = vec![];
+        stream_tokens(&mut output, &options(vec![path.clone()])).unwrap();
+        assert_eq!(output, b"a\tapple\0berries\tbanana\0");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn stops_reading_at_the_first_write_error() {
+        let path = std::env::temp_dir().join(format!("groupby-stream-err-{}", std::process::id()));
+        std::fs::write(&path, "apple\nbanana\ncherry\n").unwrap();
+
+        let mut sent = 0;
+        let mut writer = TokenWriter::new(FailingWriter(1), &Separator::Line);
+        let result = send_tokens_from_inputs(
+            &mut |key, value| {
+                sent += 1;
+                writer.send(key, value)
+            },
+            &options(vec![path.clone()]),
+        );
+        match result {
+            Err(GroupByError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::BrokenPipe),
+            result => panic!("Expected a broken pipe, got {:?}", result),
+        }
+        assert_eq!(sent, 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,237 @@
+//! Output templates, for printing each group in a user-defined format with `--format`.
+//!
+//! A [Template] is text with placeholders in braces, which [Template::render] fills in for each
+//! group:
+//!
+//! - `{key}`: the group's key.
+//! - `{count}`: the number of values in the group.
+//! - `{values}`: the group's values, joined by the output separator. `{values:JOINER}` joins them
+//!   with `JOINER` instead, e.g. `{values:, }`.
+//! - `{output}`: the standard output of the group's command, with trailing newlines removed, like
+//!   `$(...)` in a shell. Requires a command.
+//! - `{stderr}`: the group's captured standard error, like `{output}`.
+//!
+//! `{{` and `}}` stand for literal braces. Everything else is copied as written; on the command
+//! line, escape sequences such as `\t` are replaced before the template is parsed, as they are in
+//! delimiters.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::template::Template;
+//!
+//! let template: Template = r"{key} ({count}): {values:, }".parse().unwrap();
+//! let values = ["apple".to_string(), "avocado".to_string()];
+//! assert_eq!(template.render("a", &values, None, "\n"), "a (2): apple, avocado");
+//!
+//! assert!("{nope}".parse::<Template>().is_err());
+//! ```
+
+use crate::command_line::run_command::CommandOutput;
+use std::str::FromStr;
+
+/// A parsed output template. See the [module documentation](self) for the syntax.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Template {
+    // The template as written.
+    source: String,
+
+    parts: Vec<Part>,
+}
+
+// A piece of a template: literal text or a placeholder.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Part {
+    Text(String),
+    Key,
+    Count,
+
+    // The joiner, if one was given.
+    Values(Option<String>),
+
+    Output,
+    Stderr,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| format!("Unclosed {{ in the template: {}", s))?;
+                    let placeholder = &rest[..end];
+                    chars = rest[end + 1..].chars();
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(match placeholder.split_once(':') {
+                        Some(("values", joiner)) => Part::Values(Some(joiner.to_string())),
+                        _ => match placeholder {
+                            "key" => Part::Key,
+                            "count" => Part::Count,
+                            "values" => Part::Values(None),
+                            "output" => Part::Output,
+                            "stderr" => Part::Stderr,
+                            _ => {
+                                return Err(format!(
+                                    "Expected {{key}}, {{count}}, {{values}}, \
+                                    {{values:JOINER}}, {{output}}, or {{stderr}} in the template, \
+                                    but got: {{{}}}",
+                                    placeholder
+                                ))
+                            }
+                        },
+                    });
+                }
+                '}' => return Err(format!("Unmatched }} in the template: {}", s)),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template {
+            source: s.to_string(),
+            parts,
+        })
+    }
+}
+
+impl Template {
+    /// Returns the template as written.
+    ///
+    /// ```
+    /// use groupby::command_line::template::Template;
+    ///
+    /// let template: Template = "{key}: {count}".parse().unwrap();
+    /// assert_eq!(template.as_str(), "{key}: {count}");
+    /// ```
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns true if the template uses `{output}` or `{stderr}`, which only have values when
+    /// commands run.
+    ///
+    /// ```
+    /// use groupby::command_line::template::Template;
+    ///
+    /// assert!("{key}: {output}".parse::<Template>().unwrap().uses_output());
+    /// assert!(!"{key}: {values}".parse::<Template>().unwrap().uses_output());
+    /// ```
+    pub fn uses_output(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::Output | Part::Stderr))
+    }
+
+    /// Fills in the template for a group with the given key and values, and its command's
+    /// `result`, if any. `{values}` without a joiner joins values with `separator`. `{output}` and
+    /// `{stderr}` are empty if `result` is `None`.
+    pub fn render(
+        &self,
+        key: &str,
+        values: &[String],
+        result: Option<&CommandOutput>,
+        separator: &str,
+    ) -> String {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => rendered.push_str(text),
+                Part::Key => rendered.push_str(key),
+                Part::Count => rendered.push_str(&values.len().to_string()),
+                Part::Values(joiner) => {
+                    rendered.push_str(&values.join(joiner.as_deref().unwrap_or(separator)))
+                }
+                Part::Output => {
+                    if let Some(result) = result {
+                        rendered.push_str(&trim_newlines(&result.stdout));
+                    }
+                }
+                Part::Stderr => {
+                    if let Some(result) = result {
+                        rendered.push_str(&trim_newlines(&result.stderr));
+                    }
+                }
+            }
+        }
+        rendered
+    }
+}
+
+// Decodes command output for a template, dropping trailing newlines.
+fn trim_newlines(output: &[u8]) -> String {
+    String::from_utf8_lossy(output)
+        .trim_end_matches(['\n', '\r'])
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, result: Option<&CommandOutput>) -> String {
+        let values = ["Lassy".to_string(), "Buddy".to_string()];
+        template
+            .parse::<Template>()
+            .unwrap()
+            .render("Dogs", &values, result, "\n")
+    }
+
+    #[test]
+    fn renders_every_placeholder() {
+        let result = CommandOutput {
+            stdout: b"2\n".to_vec(),
+            stderr: b"warning\n".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(
+            render(
+                "{key}|{count}|{values}|{values:+}|{output}|{stderr}",
+                Some(&result)
+            ),
+            "Dogs|2|Lassy\nBuddy|Lassy+Buddy|2|warning",
+        );
+    }
+
+    #[test]
+    fn leaves_output_empty_without_a_result() {
+        assert_eq!(render("{key}: [{output}]", None), "Dogs: []");
+    }
+
+    #[test]
+    fn keeps_text_and_joiners_as_written() {
+        assert_eq!(render("{key}\t{values:\n  }", None), "Dogs\tLassy\n  Buddy");
+        assert_eq!(render(r"a\\b\q", None), r"a\\b\q");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{{key}}}", None), "{Dogs}");
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        for template in ["{key", "key}", "{value}", "{values;x}"] {
+            assert!(template.parse::<Template>().is_err(), "{}", template);
+        }
+    }
+}
@@ -1,15 +1,24 @@
 #![allow(dead_code)]
 
+use crate::command_line::command_runner::Limits;
+use crate::command_line::options::FailurePolicy;
 use crate::command_line::run_command::*;
 use std::collections::BTreeMap;
 
 // Returns a ShellCommandOptions for use in run* tests.
 pub fn options<'a>(only_group_names: bool) -> ShellCommandOptions<'a> {
     ShellCommandOptions {
-        shell: current_shell(),
+        shell: current_shell().unwrap(),
         shell_args: shell_args("cat"),
         line_separator: "   ".to_string(),
         only_group_names,
+        max_args: None,
+        max_stdin_bytes: None,
+        limits: Limits::default(),
+        capture_stderr: false,
+        live: false,
+        failure_policy: FailurePolicy::KeepGoing,
+        env: vec![],
     }
 }
 
@@ -26,10 +35,18 @@ pub fn map() -> BTreeMap<String, Vec<String>> {
     map
 }
 
-pub fn results<'a>() -> BTreeMap<&'a String, Vec<u8>> {
+pub fn results<'a>() -> BTreeMap<&'a String, CommandOutput> {
     BTreeMap::new()
 }
 
+// Keeps only the standard output of each command's results, for comparison with expected_results.
+pub fn stdouts(results: BTreeMap<&String, CommandOutput>) -> BTreeMap<&String, Vec<u8>> {
+    results
+        .into_iter()
+        .map(|(key, output)| (key, output.stdout))
+        .collect()
+}
+
 pub fn expected_results<'a>(
     map: &'a BTreeMap<String, Vec<String>>,
     separator: &str,
@@ -0,0 +1,498 @@
+//! Timestamp formats and time buckets, for grouping tokens by when they happened.
+//!
+//! A [TimestampFormat] describes how timestamps are written, using a small, strftime-like
+//! syntax. A [TimeBucket] says how coarsely to group them. Together, they let
+//! [match_timestamp](crate::matchers::string::match_timestamp) find a timestamp anywhere in a
+//! token and reduce it to a bucket key, such as `2024-03-09T14` for the hour bucket.
+//! [bucket_time] reduces a [SystemTime], such as a file's modification time, the same way.
+//!
+//! # Format syntax
+//!
+//! A format is literal text with these conversion specifications:
+//!
+//! | Spec | Matches                                     |
+//! |------|---------------------------------------------|
+//! | `%Y` | a four-digit year, e.g. `2024`              |
+//! | `%m` | a two-digit month, `01` through `12`        |
+//! | `%b` | an English month abbreviation, e.g. `Mar`   |
+//! | `%d` | a two-digit day of the month, `01`-`31`     |
+//! | `%H` | a two-digit hour, `00` through `23`         |
+//! | `%M` | a two-digit minute, `00` through `59`       |
+//! | `%S` | a two-digit second, `00` through `60`       |
+//! | `%%` | a literal `%`                               |
+//!
+//! Every other character matches itself.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::timestamps::*;
+//!
+//! let format: TimestampFormat = "%d/%b/%Y:%H:%M:%S".parse().unwrap();
+//! let line = r#"127.0.0.1 - - [09/Mar/2024:14:05:59 +0000] "GET / HTTP/1.1" 200"#;
+//!
+//! assert_eq!(format.bucket(line, TimeBucket::Day), Some("2024-03-09".to_string()));
+//! assert_eq!(format.bucket(line, TimeBucket::Hour), Some("2024-03-09T14".to_string()));
+//! assert_eq!(format.bucket("no timestamp here", TimeBucket::Day), None);
+//! ```
+
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How coarsely to group timestamps. Each bucket's key is an ISO 8601 week or a prefix of an ISO
+/// 8601 timestamp, so keys sort chronologically.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimeBucket {
+    /// Keys like `2024`.
+    Year,
+
+    /// Keys like `2024-03`.
+    Month,
+
+    /// ISO 8601 weeks, which start on Monday, with keys like `2024-W10`. The first week of a year
+    /// is the one with the year's first Thursday, so a few days around New Year's Day may belong
+    /// to a week of the previous or next year.
+    Week,
+
+    /// Keys like `2024-03-09`.
+    Day,
+
+    /// Keys like `2024-03-09T14`.
+    Hour,
+
+    /// Keys like `2024-03-09T14:05`.
+    Minute,
+}
+
+impl FromStr for TimeBucket {
+    type Err = String;
+
+    /// Parses a bucket from its lowercase name, e.g. `"hour"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "year" => Ok(TimeBucket::Year),
+            "month" => Ok(TimeBucket::Month),
+            "week" => Ok(TimeBucket::Week),
+            "day" => Ok(TimeBucket::Day),
+            "hour" => Ok(TimeBucket::Hour),
+            "minute" => Ok(TimeBucket::Minute),
+            _ => Err(format!(
+                "Expected year, month, week, day, hour, or minute, but got: {}",
+                s
+            )),
+        }
+    }
+}
+
+// A single part of a TimestampFormat.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Item {
+    Literal(char),
+    Year,
+    Month,
+    MonthName,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+// The fields of a parsed timestamp. Fields that the format doesn't include are 0.
+#[derive(Default)]
+struct Fields {
+    year: u32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+}
+
+impl Fields {
+    // Returns the key for bucket.
+    fn key(&self, bucket: TimeBucket) -> String {
+        if bucket == TimeBucket::Week {
+            let (year, week) = iso_week(days_from_civil(self.year, self.month, self.day));
+            return format!("{:04}-W{:02}", year, week);
+        }
+
+        let mut key = format!("{:04}", self.year);
+        if bucket == TimeBucket::Year {
+            return key;
+        }
+        write!(key, "-{:02}", self.month).unwrap();
+        if bucket == TimeBucket::Month {
+            return key;
+        }
+        write!(key, "-{:02}", self.day).unwrap();
+        if bucket == TimeBucket::Day {
+            return key;
+        }
+        write!(key, "T{:02}", self.hour).unwrap();
+        if bucket == TimeBucket::Hour {
+            return key;
+        }
+        write!(key, ":{:02}", self.minute).unwrap();
+        key
+    }
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// A parsed, strftime-like timestamp format. See the [module documentation](self) for the syntax.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimestampFormat {
+    format: String,
+    items: Vec<Item>,
+}
+
+impl FromStr for TimestampFormat {
+    type Err = String;
+
+    /// Parses a format, e.g. `"%Y-%m-%dT%H:%M"`.
+    ///
+    /// ```
+    /// use groupby::command_line::timestamps::TimestampFormat;
+    ///
+    /// assert!("%Y-%m-%d".parse::<TimestampFormat>().is_ok());
+    /// assert!("%Y-%q".parse::<TimestampFormat>().is_err());
+    /// assert!("100%".parse::<TimestampFormat>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut items = Vec::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                items.push(Item::Literal(c));
+                continue;
+            }
+            items.push(match chars.next() {
+                Some('Y') => Item::Year,
+                Some('m') => Item::Month,
+                Some('b') => Item::MonthName,
+                Some('d') => Item::Day,
+                Some('H') => Item::Hour,
+                Some('M') => Item::Minute,
+                Some('S') => Item::Second,
+                Some('%') => Item::Literal('%'),
+                Some(c) => return Err(format!("Unsupported timestamp format: %{}", c)),
+                None => return Err("Timestamp format ends with a lone %".to_string()),
+            });
+        }
+        Ok(TimestampFormat {
+            format: s.to_string(),
+            items,
+        })
+    }
+}
+
+impl TimestampFormat {
+    /// Returns the format as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.format
+    }
+
+    /// Returns true if the format includes every field needed to compute keys for `bucket`.
+    ///
+    /// ```
+    /// use groupby::command_line::timestamps::*;
+    ///
+    /// let format: TimestampFormat = "%b %d %H".parse().unwrap();
+    /// assert!(!format.supports(TimeBucket::Day)); // No year.
+    ///
+    /// let format: TimestampFormat = "%Y-%m-%d".parse().unwrap();
+    /// assert!(format.supports(TimeBucket::Day));
+    /// assert!(!format.supports(TimeBucket::Hour));
+    /// ```
+    pub fn supports(&self, bucket: TimeBucket) -> bool {
+        let has = |wanted: &[Item]| self.items.iter().any(|item| wanted.contains(item));
+        let needed: &[&[Item]] = match bucket {
+            TimeBucket::Year => &[&[Item::Year]],
+            TimeBucket::Month => &[&[Item::Year], &[Item::Month, Item::MonthName]],
+            TimeBucket::Week | TimeBucket::Day => {
+                &[&[Item::Year], &[Item::Month, Item::MonthName], &[Item::Day]]
+            }
+            TimeBucket::Hour => &[
+                &[Item::Year],
+                &[Item::Month, Item::MonthName],
+                &[Item::Day],
+                &[Item::Hour],
+            ],
+            TimeBucket::Minute => &[
+                &[Item::Year],
+                &[Item::Month, Item::MonthName],
+                &[Item::Day],
+                &[Item::Hour],
+                &[Item::Minute],
+            ],
+        };
+        needed.iter().all(|items| has(items))
+    }
+
+    /// Finds the first timestamp in `s` that matches this format and returns its key for
+    /// `bucket`, or `None` if there is no such timestamp.
+    ///
+    /// Timestamps with out-of-range fields, e.g. month `13` or February 30, don't match. Without
+    /// `%Y`, February 29 always matches. If the format doesn't
+    /// [support](TimestampFormat::supports) `bucket`, missing fields appear as zeros.
+    pub fn bucket(&self, s: &str, bucket: TimeBucket) -> Option<String> {
+        let fields = s.char_indices().find_map(|(i, _)| self.match_at(&s[i..]))?;
+        Some(fields.key(bucket))
+    }
+
+    // Matches the format against the start of s.
+    fn match_at(&self, mut s: &str) -> Option<Fields> {
+        let mut fields = Fields::default();
+        for item in &self.items {
+            match item {
+                Item::Literal(c) => s = s.strip_prefix(*c)?,
+                Item::Year => fields.year = take_number(&mut s, 4, 0..=9999)?,
+                Item::Month => fields.month = take_number(&mut s, 2, 1..=12)?,
+                Item::MonthName => {
+                    let name = s.get(..3)?.to_ascii_lowercase();
+                    let month = MONTH_NAMES.iter().position(|m| *m == name)?;
+                    fields.month = month as u32 + 1;
+                    s = &s[3..];
+                }
+                Item::Day => fields.day = take_number(&mut s, 2, 1..=31)?,
+                Item::Hour => fields.hour = take_number(&mut s, 2, 0..=23)?,
+                Item::Minute => fields.minute = take_number(&mut s, 2, 0..=59)?,
+                Item::Second => {
+                    take_number(&mut s, 2, 0..=60)?;
+                }
+            }
+        }
+        if fields.day > days_in_month(fields.year, fields.month) {
+            return None;
+        }
+        Some(fields)
+    }
+}
+
+/// Returns the key for `bucket` that contains `time`, in UTC.
+///
+/// ```
+/// use groupby::command_line::timestamps::*;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// // 2024-03-09T14:05:59Z
+/// let time = UNIX_EPOCH + Duration::from_secs(1_709_993_159);
+/// assert_eq!(bucket_time(time, TimeBucket::Minute), "2024-03-09T14:05");
+/// assert_eq!(bucket_time(time, TimeBucket::Week), "2024-W10");
+/// ```
+pub fn bucket_time(time: SystemTime, bucket: TimeBucket) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(error) => -(error.duration().as_secs_f64().ceil() as i64),
+    };
+    let days = seconds.div_euclid(86_400);
+    let seconds_of_day = seconds.rem_euclid(86_400) as u32;
+    let (year, month, day) = civil_from_days(days);
+    Fields {
+        year,
+        month,
+        day,
+        hour: seconds_of_day / 3600,
+        minute: seconds_of_day % 3600 / 60,
+    }
+    .key(bucket)
+}
+
+// Returns the number of days from 1970-01-01 to the given date, which may be before it. Uses
+// Howard Hinnant's algorithm; see https://howardhinnant.github.io/date_algorithms.html.
+fn days_from_civil(year: u32, month: u32, day: u32) -> i64 {
+    let year = year as i64 - if month <= 2 { 1 } else { 0 };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// The inverse of days_from_civil. Years before 0 are clamped to 0, since keys have no room for
+// a sign.
+fn civil_from_days(days: i64) -> (u32, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year.max(0) as u32, month as u32, day as u32)
+}
+
+// Returns the number of days in the given month, or 31 if the month is unknown (0). Year 0, which
+// is what a format without %Y leaves, is a leap year.
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Returns the ISO 8601 week-numbering year and week of the given day, counted from 1970-01-01.
+fn iso_week(days: i64) -> (u32, u32) {
+    // 1970-01-01 was a Thursday. A week belongs to the year that contains its Thursday.
+    let weekday = (days + 3).rem_euclid(7);
+    let thursday = days - weekday + 3;
+    let (year, _, _) = civil_from_days(thursday);
+    let week = (thursday - days_from_civil(year, 1, 1)) / 7 + 1;
+    (year, week as u32)
+}
+
+// Consumes exactly `digits` ASCII digits from the start of s and returns their value, if it's in
+// range.
+fn take_number(s: &mut &str, digits: usize, range: std::ops::RangeInclusive<u32>) -> Option<u32> {
+    let number = s.get(..digits)?;
+    if !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let value = number.parse().ok().filter(|n| range.contains(n))?;
+    *s = &s[digits..];
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(s: &str) -> TimestampFormat {
+        s.parse().unwrap()
+    }
+
+    mod bucket {
+        use super::*;
+
+        #[test]
+        fn formats_every_bucket() {
+            let format = format("%Y-%m-%dT%H:%M:%S");
+            let line = "at 2024-03-09T14:05:59Z";
+            for (bucket, expected) in [
+                (TimeBucket::Year, "2024"),
+                (TimeBucket::Month, "2024-03"),
+                (TimeBucket::Week, "2024-W10"),
+                (TimeBucket::Day, "2024-03-09"),
+                (TimeBucket::Hour, "2024-03-09T14"),
+                (TimeBucket::Minute, "2024-03-09T14:05"),
+            ] {
+                assert_eq!(format.bucket(line, bucket), Some(expected.to_string()));
+            }
+        }
+
+        #[test]
+        fn skips_out_of_range_fields() {
+            let format = format("%Y-%m-%d");
+            assert_eq!(
+                format.bucket("2024-13-01 then 2024-12-01", TimeBucket::Month),
+                Some("2024-12".to_string())
+            );
+        }
+
+        #[test]
+        fn skips_days_past_the_end_of_the_month() {
+            let format = format("%Y-%m-%d");
+            for line in ["2023-02-29", "2024-02-31", "2024-04-31", "1900-02-29"] {
+                assert_eq!(format.bucket(line, TimeBucket::Day), None, "{}", line);
+            }
+            assert_eq!(
+                format.bucket("2024-02-29", TimeBucket::Week),
+                Some("2024-W09".to_string())
+            );
+            assert_eq!(
+                format.bucket("2000-02-29", TimeBucket::Day),
+                Some("2000-02-29".to_string())
+            );
+            assert_eq!(
+                super::format("%m-%d").bucket("02-29", TimeBucket::Day),
+                Some("0000-02-29".to_string())
+            );
+        }
+
+        #[test]
+        fn requires_exact_digit_counts() {
+            let format = format("%Y-%m");
+            assert_eq!(format.bucket("2024-3", TimeBucket::Month), None);
+        }
+
+        #[test]
+        fn handles_multibyte_text() {
+            let format = format("%Y");
+            assert_eq!(
+                format.bucket("日付: 2024", TimeBucket::Year),
+                Some("2024".to_string())
+            );
+        }
+
+        #[test]
+        fn matches_percent_literally() {
+            let format = format("%Y%%");
+            assert_eq!(
+                format.bucket("2024 2025%", TimeBucket::Year),
+                Some("2025".to_string())
+            );
+        }
+    }
+
+    mod bucket_time {
+        use super::*;
+        use std::time::Duration;
+
+        #[test]
+        fn handles_times_before_the_epoch() {
+            let time = UNIX_EPOCH - Duration::from_secs(1);
+            assert_eq!(bucket_time(time, TimeBucket::Minute), "1969-12-31T23:59");
+        }
+
+        #[test]
+        fn handles_leap_days() {
+            // 2024-02-29T00:00:00Z
+            let time = UNIX_EPOCH + Duration::from_secs(1_709_164_800);
+            assert_eq!(bucket_time(time, TimeBucket::Day), "2024-02-29");
+        }
+    }
+
+    #[test]
+    fn numbers_iso_weeks() {
+        for (date, expected) in [
+            ((2021, 1, 3), (2020, 53)),
+            ((2021, 1, 4), (2021, 1)),
+            ((2024, 12, 30), (2025, 1)),
+            ((2026, 10, 17), (2026, 42)),
+        ] {
+            let (year, month, day) = date;
+            assert_eq!(iso_week(days_from_civil(year, month, day)), expected);
+        }
+    }
+
+    #[test]
+    fn converts_days_both_ways() {
+        for days in [-719_468, -1, 0, 59, 19_792, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn parses_time_buckets() {
+        assert_eq!("minute".parse(), Ok(TimeBucket::Minute));
+        assert_eq!("week".parse(), Ok(TimeBucket::Week));
+        assert!("fortnight".parse::<TimeBucket>().is_err());
+    }
+}
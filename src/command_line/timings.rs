@@ -0,0 +1,211 @@
+//! Measures how long each phase of a run takes, for `--timings`.
+//!
+//! Reading input and grouping it are interleaved, so they can't be timed separately from the
+//! outside. Instead, [build_groups_from_inputs()] reads each input through [Timed], which adds the
+//! time spent waiting on the input to a process-wide total, [reading_time()].
+//! [Timings::time_groups] then counts the rest of the time spent building groups as grouping.
+//!
+//! [build_groups_from_inputs()]: crate::command_line::build_groups::build_groups_from_inputs
+
+use crate::command_line::run_command::CommandOutput;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{self, BufRead, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// The time spent reading input through Timed, in nanoseconds, for the process as a whole.
+static READING: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the total time that every [Timed] reader in this process has spent reading so far.
+pub fn reading_time() -> Duration {
+    Duration::from_nanos(READING.load(Ordering::Relaxed))
+}
+
+/// Wraps a reader, adding the time spent in each read to [reading_time()].
+pub struct Timed<R> {
+    inner: R,
+}
+
+impl<R> Timed<R> {
+    pub fn new(inner: R) -> Self {
+        Timed { inner }
+    }
+}
+
+// Adds the time since start to READING.
+fn record(start: Instant) {
+    let elapsed = start.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+    READING.fetch_add(elapsed, Ordering::Relaxed);
+}
+
+impl<R: Read> Read for Timed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = Instant::now();
+        let read = self.inner.read(buf);
+        record(start);
+        read
+    }
+}
+
+impl<R: BufRead> BufRead for Timed<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let start = Instant::now();
+        let filled = self.inner.fill_buf();
+        record(start);
+        filled
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+/// The time spent in each phase of a run, in the order the phases ran.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::timings::Timings;
+/// use std::time::Duration;
+///
+/// let mut timings = Timings::new();
+/// timings.record("output", Duration::from_micros(1500));
+///
+/// let mut report = vec![];
+/// timings.write::<_, String>(&mut report, None).unwrap();
+/// assert_eq!(
+///     String::from_utf8_lossy(&report),
+///     "Timings:\n  output: 1.50 ms\n  total: 1.50 ms\n",
+/// );
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Timings {
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `phase` took `duration`.
+    pub fn record(&mut self, phase: &'static str, duration: Duration) {
+        self.phases.push((phase, duration));
+    }
+
+    /// Calls `f`, records how long it took as `phase`, and returns its result.
+    pub fn time<T, F: FnOnce() -> T>(&mut self, phase: &'static str, f: F) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(phase, start.elapsed());
+        result
+    }
+
+    /// Calls `f`, which builds groups from input read through [Timed], and records how long it
+    /// took as two phases: `input`, the time spent reading, and `grouping`, the rest.
+    pub fn time_groups<T, F: FnOnce() -> T>(&mut self, f: F) -> T {
+        let reading = reading_time();
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+        let reading = reading_time().saturating_sub(reading).min(elapsed);
+        self.record("input", reading);
+        self.record("grouping", elapsed - reading);
+        result
+    }
+
+    /// Returns the total time of every phase recorded so far.
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    /// Writes each phase's time and the total. If `commands` is a `Some` value, also writes how
+    /// long each group's command took, slowest first.
+    pub fn write<O: Write, Key: Display>(
+        &self,
+        mut output: O,
+        commands: Option<&BTreeMap<Key, CommandOutput>>,
+    ) -> io::Result<()> {
+        writeln!(output, "Timings:")?;
+        for (phase, duration) in &self.phases {
+            writeln!(output, "  {}: {}", phase, milliseconds(*duration))?;
+        }
+        writeln!(output, "  total: {}", milliseconds(self.total()))?;
+
+        if let Some(commands) = commands {
+            let mut commands: Vec<_> = commands.iter().collect();
+            commands.sort_by_key(|(_, command)| Reverse(command.duration));
+            writeln!(output, "\nCommand timings, slowest first:")?;
+            for (key, command) in commands {
+                writeln!(output, "  {}: {}", key, milliseconds(command.duration))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Formats a duration the way CommandOutput::describe does.
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    fn command(millis: u64) -> CommandOutput {
+        CommandOutput {
+            stdout: vec![],
+            stderr: vec![],
+            status: ExitStatus::from_raw(0),
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn writes_phases_and_slowest_commands_first() {
+        let mut timings = Timings::new();
+        timings.record("input", Duration::from_millis(2));
+        timings.record("grouping", Duration::from_millis(1));
+        let commands =
+            BTreeMap::from([("a".to_string(), command(5)), ("b".to_string(), command(7))]);
+
+        let mut report = vec![];
+        timings.write(&mut report, Some(&commands)).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&report),
+            "Timings:\n  \
+                input: 2.00 ms\n  \
+                grouping: 1.00 ms\n  \
+                total: 3.00 ms\n\
+            \n\
+            Command timings, slowest first:\n  \
+                b: 7.00 ms\n  \
+                a: 5.00 ms\n"
+        );
+    }
+
+    #[test]
+    fn splits_building_groups_into_input_and_grouping() {
+        let mut timings = Timings::new();
+        let mut input = Timed::new(&b"abc"[..]);
+        let read = timings.time_groups(|| {
+            let mut buffer = String::new();
+            input.read_to_string(&mut buffer).unwrap();
+            buffer
+        });
+        assert_eq!(read, "abc");
+        assert_eq!(
+            timings
+                .phases
+                .iter()
+                .map(|(phase, _)| *phase)
+                .collect::<Vec<_>>(),
+            vec!["input", "grouping"]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Per-value counts, for summarizing groups with many repeated values, like `uniq -c`.
+//!
+//! After grouping, [count_values] collapses identical values within each group into a single
+//! value prefixed with the number of times it occurred, e.g. `3 foo.txt`. Values keep the order of
+//! their first occurrence, and the same value may still appear in different groups. Everything
+//! after this stage, such as printing groups or running commands over them, sees the counted
+//! values in place of the originals.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::value_counts::count_values;
+//! use groupby::grouped_collections::GroupedCollection;
+//! use std::collections::BTreeMap;
+//!
+//! let mut map: BTreeMap<String, Vec<String>> = BTreeMap::new();
+//! for file in ["a.txt", "b.rs", "a.txt", "c.txt", "a.txt"] {
+//!     let extension = file.rsplit('.').next().unwrap().to_string();
+//!     map.add(extension, file.to_string());
+//! }
+//!
+//! count_values(&mut map);
+//! assert_eq!(map["txt"], vec!["3 a.txt", "1 c.txt"]);
+//! assert_eq!(map["rs"], vec!["1 b.rs"]);
+//! ```
+
+use crate::grouped_collections::GroupedCollection;
+use std::collections::HashMap;
+
+/// Replaces each group's values with one `"{count} {value}"` value per distinct value. See the
+/// [module documentation](self) for details.
+pub fn count_values<M>(map: &mut M)
+where
+    M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
+{
+    map.retain(|_, values| {
+        *values = counted(values);
+        true
+    });
+}
+
+/// Counts the occurrences of each distinct value in `values` and returns one `"{count} {value}"`
+/// string per distinct value, in order of first occurrence.
+///
+/// ```
+/// use groupby::command_line::value_counts::counted;
+///
+/// let values = ["b", "a", "b", "b"].map(String::from);
+/// assert_eq!(counted(&values), vec!["3 b", "1 a"]);
+/// ```
+pub fn counted(values: &[String]) -> Vec<String> {
+    let mut indices = HashMap::with_capacity(values.len());
+    let mut counts: Vec<(&String, usize)> = vec![];
+    for value in values {
+        let index = *indices.entry(value).or_insert_with(|| {
+            counts.push((value, 0));
+            counts.len() - 1
+        });
+        counts[index].1 += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(value, count)| format!("{} {}", count, value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counted_handles_empty_groups() {
+        assert!(counted(&[]).is_empty());
+    }
+
+    #[test]
+    fn counted_keeps_values_with_spaces_intact() {
+        let values = ["a b", "a", "a b"].map(String::from);
+        assert_eq!(counted(&values), vec!["2 a b", "1 a"]);
+    }
+}
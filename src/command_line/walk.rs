@@ -0,0 +1,234 @@
+//! Walks a directory tree, for grouping file paths without piping them in from `find`.
+//!
+//! A [Walk] lists every file under its root, recursively, as `find ROOT -not -type d` would, but
+//! in sorted order. Hidden files and directories, whose names start with `.`, are skipped unless
+//! [Walk::hidden] is set. Symbolic links are listed like files unless [Walk::follow_symlinks] is
+//! set, in which case links to directories are walked, too, and each directory is walked only
+//! once.
+//!
+//! Entries that can't be read, such as directories without read permission, are skipped with a
+//! warning on standard error, so one unreadable directory doesn't stop the whole walk.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::walk::Walk;
+//! use std::fs;
+//!
+//! let root = std::env::temp_dir().join("groupby-walk-doctest");
+//! fs::create_dir_all(root.join("src/.git")).unwrap();
+//! fs::write(root.join("README.md"), "").unwrap();
+//! fs::write(root.join("src/main.rs"), "").unwrap();
+//! fs::write(root.join("src/.git/HEAD"), "").unwrap();
+//!
+//! let paths: Vec<_> = Walk::new(&root).paths().unwrap().collect();
+//! assert_eq!(paths, vec![root.join("README.md"), root.join("src/main.rs")]);
+//! # fs::remove_dir_all(&root).unwrap();
+//! ```
+
+use crate::command_line::error::GroupByError;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A recursive listing of the files under a directory. See the [module documentation](self).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Walk {
+    /// The directory to walk. If it's a file instead, it's the only path listed.
+    pub root: PathBuf,
+
+    /// List hidden files and walk hidden directories, too.
+    pub hidden: bool,
+
+    /// Walk symbolic links to directories as if they were directories.
+    pub follow_symlinks: bool,
+}
+
+impl Walk {
+    /// Returns a walk of `root` that skips hidden files and doesn't follow symbolic links.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Walk {
+            root: root.into(),
+            hidden: false,
+            follow_symlinks: false,
+        }
+    }
+
+    /// Returns an iterator over the paths of the files under [Walk::root], in sorted order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [GroupByError::Input] if the root doesn't exist or can't be read.
+    pub fn paths(&self) -> Result<Paths, GroupByError> {
+        fs::metadata(&self.root)
+            .map_err(|e| GroupByError::Input(self.root.display().to_string(), e))?;
+        Ok(Paths {
+            pending: vec![self.root.clone()],
+            walk: self.clone(),
+            visited: HashSet::new(),
+        })
+    }
+}
+
+/// An iterator over the files in a [Walk], returned by [Walk::paths].
+pub struct Paths {
+    // Paths still to list or walk, with the next one last.
+    pending: Vec<PathBuf>,
+
+    walk: Walk,
+
+    // The canonical paths of the directories walked so far, when following symbolic links, so
+    // that a link back to a parent directory isn't walked forever.
+    visited: HashSet<PathBuf>,
+}
+
+impl Iterator for Paths {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        while let Some(path) = self.pending.pop() {
+            let metadata = if self.walk.follow_symlinks {
+                fs::metadata(&path)
+            } else {
+                fs::symlink_metadata(&path)
+            };
+            match metadata {
+                Ok(metadata) if metadata.is_dir() => self.enter(&path),
+                Ok(_) => return Some(path),
+
+                // With follow_symlinks, a broken link is still listed, as find -L lists it.
+                Err(_) if self.walk.follow_symlinks && path.is_symlink() => return Some(path),
+                Err(e) => skip(&path, e),
+            }
+        }
+        None
+    }
+}
+
+impl Paths {
+    // Queues the entries of the directory at path, unless it was already walked.
+    fn enter(&mut self, path: &Path) {
+        if self.walk.follow_symlinks {
+            match path.canonicalize() {
+                Ok(real) => {
+                    if !self.visited.insert(real) {
+                        return;
+                    }
+                }
+                Err(e) => return skip(path, e),
+            }
+        }
+
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => return skip(path, e),
+        };
+        let mut children = vec![];
+        for entry in entries {
+            match entry {
+                Ok(entry) if self.walk.hidden || !is_hidden(&entry.file_name()) => {
+                    children.push(entry.path())
+                }
+                Ok(_) => (),
+                Err(e) => skip(path, e),
+            }
+        }
+        children.sort_unstable_by(|a, b| b.cmp(a));
+        self.pending.extend(children);
+    }
+}
+
+// Returns true if a file name is hidden, i.e. starts with a dot.
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+// Warns that path is being skipped because of e.
+fn skip(path: &Path, e: std::io::Error) {
+    eprintln!("Skipping {}: {}", path.display(), e);
+}
+
+/// Returns a path's bytes, for decoding as an input token. On Unix, these are the path's raw
+/// bytes, which needn't be valid UTF-8. Elsewhere, the path is converted to UTF-8 lossily.
+pub fn path_bytes(path: PathBuf) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        path.into_os_string().into_vec()
+    }
+
+    #[cfg(not(unix))]
+    {
+        path.to_string_lossy().into_owned().into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Creates a fresh directory tree for a test, removing any left over from earlier runs.
+    fn tree(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("groupby-walk-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("b/.hidden")).unwrap();
+        fs::write(root.join("a"), "").unwrap();
+        fs::write(root.join("b/c"), "").unwrap();
+        fs::write(root.join("b/.hidden/d"), "").unwrap();
+        fs::write(root.join(".e"), "").unwrap();
+        root
+    }
+
+    fn relative(walk: &Walk) -> Vec<String> {
+        walk.paths()
+            .unwrap()
+            .map(|path| path.strip_prefix(&walk.root).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lists_files_in_sorted_order_without_hidden_ones() {
+        let root = tree("sorted");
+        assert_eq!(relative(&Walk::new(&root)), vec!["a", "b/c"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn lists_hidden_files_when_asked() {
+        let root = tree("hidden");
+        let walk = Walk {
+            hidden: true,
+            ..Walk::new(&root)
+        };
+        assert_eq!(relative(&walk), vec![".e", "a", "b/.hidden/d", "b/c"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follows_symlinks_only_when_asked_and_only_once() {
+        let root = tree("symlinks");
+        std::os::unix::fs::symlink(root.join("b"), root.join("link")).unwrap();
+        std::os::unix::fs::symlink(&root, root.join("b/loop")).unwrap();
+
+        assert_eq!(
+            relative(&Walk::new(&root)),
+            vec!["a", "b/c", "b/loop", "link"]
+        );
+
+        let walk = Walk {
+            follow_symlinks: true,
+            ..Walk::new(&root)
+        };
+        assert_eq!(relative(&walk), vec!["a", "b/c"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn rejects_missing_roots() {
+        let walk = Walk::new("/nonexistent/groupby-walk");
+        assert!(matches!(walk.paths(), Err(GroupByError::Input(_, _))));
+    }
+}
@@ -27,11 +27,12 @@
 //!     parallel: true,
 //!     headers: true,
 //!     stats: false,
+//!     ..Default::default()
 //! };
 //!
 //! // If we didn't know that options.run_command would be None, we would call run_command here.
 //!
-//! command_line::write_results(&mut output, &map, &None, &options);
+//! command_line::write_results(&mut output, &map, &None, &options).unwrap();
 //!
 //! let expected = "seasons:\n\
 //!     winter\n\
@@ -44,7 +45,10 @@
 //!
 //! [GroupByOptions]: crate::command_line::options::GroupByOptions
 
-use crate::command_line::{OutputOptions, RecordWriter, Separator};
+use crate::command_line::error::GroupByError;
+use crate::command_line::json;
+use crate::command_line::run_command::{shell_quote, CommandOutput};
+use crate::command_line::{FailurePolicy, OutputOptions, Quote, RecordWriter, Separator};
 use crate::grouped_collections::GroupedCollection;
 use std::collections::BTreeMap;
 use std::io::Write;
@@ -58,9 +62,38 @@ pub fn default_output_options(base: &OutputOptions) -> OutputOptions {
         separator: Separator::Line,
         only_group_names: false,
         run_command: None,
+        shell: None,
+        dry_run: false,
         parallel: base.parallel,
+        jobs: None,
+        max_args: None,
+        max_stdin_bytes: None,
+        nice: None,
+        max_memory: None,
+        max_open_files: None,
+        capture_stderr: base.capture_stderr,
+        live: base.live,
+        incremental: base.incremental,
+        with_values: base.with_values,
+        failure_policy: FailurePolicy::KeepGoing,
         headers: base.headers,
+        header_format: base.header_format.clone(),
         stats: base.stats,
+        stats_detail: base.stats_detail.clone(),
+        timings: false,
+        checkpoint: None,
+        resume: false,
+        sort: base.sort,
+        group_order: base.group_order,
+        reverse: base.reverse,
+        presorted_streaming: false,
+        stream: false,
+        low_memory: false,
+        intern: false,
+        json: base.json,
+        count_only: false,
+        format: base.format.clone(),
+        quote: base.quote,
     }
 }
 
@@ -70,11 +103,26 @@ pub fn default_output_options(base: &OutputOptions) -> OutputOptions {
 /// and an optional map of outputs from running commands over the [GroupedCollection]). The rules
 /// (with some minor details like punctuation omitted) are as follows:
 ///
+/// - If [OutputOptions::json] is true, write a single JSON object, followed by a newline, that maps
+///   each group's key to its result (as a string) if `results` is a `Some` value, or otherwise to
+///   an array of its contents. If [OutputOptions::capture_stderr] is also true, each result is
+///   instead an object with `"stdout"` and `"stderr"` strings, and if [OutputOptions::with_values]
+///   is true, the object also has a `"values"` array of the group's contents. Other options that
+///   affect formatting don't apply.
+///
 /// - If `results` is a `Some` value, print each group's result instead of its contents, using
-///   default options. Otherwise:
+///   default options. If [OutputOptions::with_values] is true, print the group's contents, then an
+///   `Output:` line if [OutputOptions::headers] is true, then its result. A result's standard
+///   error, if any was captured, is printed after its standard output. If [OutputOptions::stats]
+///   is also true, each group's header describes how its command went (see
+///   [CommandOutput::describe]), and the statistics end with the number of commands that failed.
+///   If [OutputOptions::live] or [OutputOptions::incremental] is true, the results were already
+///   printed while the commands ran (see [write_group_result()]), so only the statistics are
+///   written. Otherwise:
 ///
 ///   - If `results` is `None` and [OutputOptions::only_group_names] is true, print group headers
-///     but not group contents.
+///     but not group contents. If [OutputOptions::separator] is also [Separator::Null], print each
+///     group's name exactly as it is, with no statistics or quoting.
 ///
 ///   - Write [OutputOptions::separator] after each header and each group member.
 ///
@@ -82,18 +130,21 @@ pub fn default_output_options(base: &OutputOptions) -> OutputOptions {
 ///
 /// If `results` is a `Some` value, it should have the same set of keys as `map`. This method
 /// iterates over `map` and looks up each key from `map` in `results`. As a result, any keys in
-/// `results` that are not present in `map` will not be retrieved, and if any keys in `map` are
-/// not present in `results`, the method will panic.
+/// `results` that are not present in `map` will not be retrieved, and any groups in `map` whose
+/// keys are not present in `results` are skipped entirely. (This happens, for instance, when
+/// resuming from a [checkpoint](crate::command_line::checkpoint), since groups that already
+/// completed aren't run again.)
 ///
-/// # Panics
+/// # Errors
 ///
-/// This method panics if a key in `map` is not present in `results`.
+/// Returns [GroupByError::Io] if writing to `output` fails.
 pub fn write_results<'a, 'b, M, O>(
     output: O,
     map: &'a M,
-    results: &Option<BTreeMap<&'b String, Vec<u8>>>,
+    results: &Option<BTreeMap<&'b String, CommandOutput>>,
     options: &'_ OutputOptions,
-) where
+) -> Result<(), GroupByError>
+where
     M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
     O: Write,
 {
@@ -103,46 +154,229 @@ pub fn write_results<'a, 'b, M, O>(
         None => options,
     };
 
+    let mut groups: Vec<_> = map.iter().collect();
+    options.order_groups(&mut groups, |values| values.len());
+
+    if options.json {
+        return write_json(output, groups, results, options);
+    }
+
     let separator = options.separator.sep();
     let mut writer = RecordWriter::new(output, separator.as_bytes());
 
-    for (key, values) in map.iter() {
-        if options.only_group_names {
-            // Group names are replacing group conents, so we don't count them as headers. This
-            // means that options.headers does not apply, so there is no corresponding logic here.
+    for (key, values) in groups {
+        match results {
+            // With live or incremental output, the results were printed while the commands ran.
+            Some(_) if options.live || options.incremental => (),
 
-            if options.stats {
-                writer.write(&format!("{} ({})", key, item_count(values)));
-            } else {
-                writer.write(key);
+            // If there's a result set, groups without results didn't run, so there's nothing to
+            // print.
+            Some(results) => {
+                if let Some(result) = results.get(key) {
+                    write_group(&mut writer, key, values, Some(result), options)?;
+                }
             }
+            None => write_group(&mut writer, key, values, None, options)?,
+        }
+    }
+
+    if options.stats {
+        writer.write("")?;
+        writer.write(&statistics_for(
+            map,
+            &separator,
+            options.stats_detail.as_deref(),
+        ))?;
+        if let Some(results) = results {
+            let failed = results.values().filter(|result| !result.success()).count();
+            writer.write(&format!("  Failed commands: {}", failed))?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a single group's command result exactly as [write_results()] would write it, including
+/// its header, if any.
+///
+/// This is for writing results one group at a time as commands finish. [write_results()] can then
+/// be called with [OutputOptions::incremental] set to write only the statistics, if requested.
+///
+/// # Errors
+///
+/// Returns [GroupByError::Io] if writing to `output` fails.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::options::OutputOptions;
+/// use groupby::command_line::run_command::CommandOutput;
+/// use groupby::command_line::write_results::write_group_result;
+///
+/// let mut output = vec![];
+/// let values = vec!["Lassie".to_string(), "Buddy".to_string()];
+/// let result = CommandOutput {
+///     stdout: b"2".to_vec(),
+///     ..Default::default()
+/// };
+/// let options = OutputOptions {
+///     run_command: Some("wc -l".to_string()),
+///     ..Default::default()
+/// };
+///
+/// write_group_result(&mut output, &"Dogs".to_string(), &values, &result, &options).unwrap();
+/// assert_eq!(String::from_utf8_lossy(&output), "Dogs:\n2\n");
+/// ```
+pub fn write_group_result<O: Write>(
+    output: O,
+    key: &str,
+    values: &[String],
+    result: &CommandOutput,
+    options: &OutputOptions,
+) -> Result<(), GroupByError> {
+    let options = default_output_options(options);
+    let separator = options.separator.sep();
+    let mut writer = RecordWriter::new(output, separator.as_bytes());
+    write_group(&mut writer, key, values, Some(result), &options)
+}
+
+// Writes a single group, or its command's result if there is one. See write_results.
+fn write_group<'a, O: Write>(
+    writer: &mut RecordWriter<'a, O>,
+    key: &str,
+    values: &'a [String],
+    result: Option<&CommandOutput>,
+    options: &OutputOptions,
+) -> Result<(), GroupByError> {
+    // Statistics describe the group as it is, so only the printed copies are quoted.
+    let quoted;
+    let (printed_key, printed_values): (&str, &[String]) = match options.quote {
+        Quote::None => (key, values),
+        Quote::Shell => {
+            quoted = (
+                shell_quote(key),
+                values
+                    .iter()
+                    .map(|value| shell_quote(value))
+                    .collect::<Vec<_>>(),
+            );
+            (&quoted.0, &quoted.1)
+        }
+    };
+
+    if options.only_group_names {
+        // Group names are replacing group conents, so we don't count them as headers. This
+        // means that options.headers does not apply, so there is no corresponding logic here.
+
+        if options.prints_bare_group_names() {
+            writer.write(key)?;
+        } else if options.stats {
+            writer.write(&format!(
+                "{} ({})",
+                printed_key,
+                size_description(values, options)
+            ))?;
         } else {
-            // Write header
-            if options.headers {
-                if options.stats {
-                    writer.write(&format!("{}: ({})", key, item_count(values)));
-                } else {
-                    writer.write(&format!("{}:", key));
+            writer.write(printed_key)?;
+        }
+        return Ok(());
+    }
+
+    // A template replaces the header and contents entirely.
+    if let Some(template) = &options.format {
+        let separator = options.separator.sep();
+        writer.write(&template.render(printed_key, printed_values, result, &separator))?;
+        return Ok(());
+    }
+
+    // Write header
+    if options.headers {
+        let header = match &options.header_format {
+            Some(template) => template.render(
+                printed_key,
+                printed_values,
+                result,
+                &options.separator.sep(),
+            ),
+            None => format!("{}:", printed_key),
+        };
+        if options.stats {
+            let description = match result {
+                Some(result) => format!(
+                    "{}, {}",
+                    size_description(values, options),
+                    result.describe()
+                ),
+                None => size_description(values, options),
+            };
+            writer.write(&format!("{} ({})", header, description))?;
+        } else {
+            writer.write(&header)?;
+        }
+    }
+
+    // If there's a result (from running a command over the group), write it as the group's
+    // output, and do not write the group's contents unless asked to. Otherwise, write the group's
+    // contents normally.
+    match result {
+        Some(result) => {
+            if options.with_values {
+                writer.write_all(printed_values.iter())?;
+                if options.headers {
+                    writer.write("Output:")?;
                 }
             }
-
-            // If there's a result set (from running a command over each group), write it as the
-            // group's output, and do not write the grou's contents. Otherwise, write the group's
-            // contents normally.
-            if let Some(results) = results {
-                let result_utf8 = results.get(key).unwrap();
-                let result = String::from_utf8_lossy(result_utf8);
-                writer.write(&result);
-            } else {
-                writer.write_all(values.iter());
+            writer.write(&String::from_utf8_lossy(&result.stdout))?;
+            if !result.stderr.is_empty() {
+                writer.write(&String::from_utf8_lossy(&result.stderr))?;
             }
         }
+        None => writer.write_all(printed_values.iter())?,
     }
+    Ok(())
+}
 
-    if options.stats {
-        writer.write("");
-        writer.write(&statistics_for(map));
+// Writes groups, in the order given, as a JSON object. See write_results.
+fn write_json<O: Write>(
+    mut output: O,
+    groups: Vec<(&String, &Vec<String>)>,
+    results: &Option<BTreeMap<&String, CommandOutput>>,
+    options: &OutputOptions,
+) -> Result<(), GroupByError> {
+    let mut document = String::new();
+    match results {
+        Some(results) => {
+            // As with other output, groups without results didn't run, so they're skipped.
+            let results = groups
+                .into_iter()
+                .filter_map(|(key, values)| Some((key, (values, results.get(key)?))));
+            json::write_object(&mut document, results, |document, (values, result)| {
+                if options.with_values || options.capture_stderr {
+                    let mut fields = vec![];
+                    if options.with_values {
+                        fields.push("values");
+                    }
+                    fields.push("stdout");
+                    if options.capture_stderr {
+                        fields.push("stderr");
+                    }
+                    let fields = fields.into_iter().map(|field| (field, field));
+                    json::write_object(document, fields, |document, field| match field {
+                        "values" => json::write_array(document, values),
+                        "stdout" => {
+                            json::write_string(document, &String::from_utf8_lossy(&result.stdout))
+                        }
+                        _ => json::write_string(document, &String::from_utf8_lossy(&result.stderr)),
+                    });
+                } else {
+                    json::write_string(document, &String::from_utf8_lossy(&result.stdout))
+                }
+            });
+        }
+        None => json::write_object(&mut document, groups, json::write_array),
     }
+    document.push('\n');
+    output.write_all(document.as_bytes())?;
+    Ok(())
 }
 
 /// Provides a human-readable description of the length of a vector, like "1 item" or "48 items".
@@ -154,55 +388,422 @@ pub fn item_count<_T>(items: &[_T]) -> String {
     }
 }
 
-/// Reports statistics for a given [GroupedCollection].
-pub fn statistics_for<M>(map: &M) -> String
+/// Counts the bytes in a group as written with a given separator: the length of each value, plus
+/// one separator after each.
+///
+/// ```
+/// use groupby::command_line::write_results::byte_count;
+///
+/// let values = ["Lassy".to_string(), "Buddy".to_string()];
+/// assert_eq!(byte_count(&values, "\n"), 12);
+/// assert_eq!(byte_count(&values, ", "), 14);
+/// ```
+pub fn byte_count(values: &[String], separator: &str) -> usize {
+    values
+        .iter()
+        .map(|value| value.len() + separator.len())
+        .sum()
+}
+
+// Describes a group's size for --stats, e.g. "2 items, 12 bytes".
+fn size_description(values: &[String], options: &OutputOptions) -> String {
+    match byte_count(values, &options.separator.sep()) {
+        1 => format!("{}, 1 byte", item_count(values)),
+        bytes => format!("{}, {} bytes", item_count(values), bytes),
+    }
+}
+
+/// Summary statistics about the groups in a [GroupedCollection].
+///
+/// The [Display](std::fmt::Display) implementation formats them for `--stats`.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::write_results::Statistics;
+/// use groupby::grouped_collections::GroupedCollection;
+/// use std::collections::BTreeMap;
+///
+/// let mut map = BTreeMap::new();
+/// for (key, value) in [("a", "1"), ("a", "2"), ("a", "3"), ("b", "4")] {
+///     map.add(key.to_string(), value.to_string());
+/// }
+///
+/// let stats = Statistics::of(&map);
+/// assert_eq!(stats.total_items, 4);
+/// assert_eq!(stats.total_groups, 2);
+/// assert_eq!(stats.total_bytes, 8);
+/// assert_eq!(stats.group_size_median, 3);
+/// assert_eq!(stats.group_size_average, 2.0);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statistics {
+    /// Total items across all groups.
+    pub total_items: usize,
+
+    /// Number of groups in the collection.
+    pub total_groups: usize,
+
+    /// Total bytes across all groups, counting one separator after each item. See [byte_count].
+    pub total_bytes: usize,
+
+    /// Median group size. With an even number of groups, this is the larger of the two middle
+    /// sizes.
+    pub group_size_median: usize,
+
+    /// Average group size.
+    pub group_size_average: f64,
+
+    /// Smallest group size.
+    pub group_size_min: usize,
+
+    /// Largest group size.
+    pub group_size_max: usize,
+}
+
+impl Statistics {
+    /// Computes statistics for a given [GroupedCollection], counting a newline after each item
+    /// toward [Statistics::total_bytes]. Every statistic is 0 if it's empty.
+    pub fn of<M>(map: &M) -> Self
+    where
+        M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
+    {
+        Self::with_separator(map, &Separator::Line.sep())
+    }
+
+    /// Computes statistics for a given [GroupedCollection], like [Statistics::of], but counts
+    /// `separator` after each item toward [Statistics::total_bytes].
+    pub fn with_separator<M>(map: &M, separator: &str) -> Self
+    where
+        M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
+    {
+        // We'll reuse this time and time again, so might as well cache it and sort it.
+        let group_sizes = sorted_group_sizes(map);
+        let total_groups = group_sizes.len();
+
+        let total_items: usize = group_sizes.iter().sum();
+
+        Statistics {
+            total_items,
+            total_groups,
+            total_bytes: map
+                .iter()
+                .map(|(_, items)| byte_count(items, separator))
+                .sum(),
+            group_size_median: group_sizes.get(group_sizes.len() / 2).copied().unwrap_or(0),
+            group_size_average: if total_groups == 0 {
+                0.00
+            } else {
+                total_items as f64 / total_groups as f64
+            },
+            group_size_min: group_sizes.first().copied().unwrap_or(0),
+            group_size_max: group_sizes.last().copied().unwrap_or(0),
+        }
+    }
+}
+
+impl std::fmt::Display for Statistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Statistics:\n  \
+              Total items: {}\n  \
+              Total groups: {}\n  \
+              Total bytes: {}\n\
+              \n  \
+              Group size:\n    \
+                Median: {}\n    \
+                Average: {:.2}\n    \
+                Min: {}\n    \
+                Max: {}\n",
+            self.total_items,
+            self.total_groups,
+            self.total_bytes,
+            self.group_size_median,
+            self.group_size_average,
+            self.group_size_min,
+            self.group_size_max
+        )
+    }
+}
+
+/// More detailed statistics about the sizes of the groups in a [GroupedCollection], for
+/// distributions that [Statistics] alone doesn't describe well, such as heavily skewed ones.
+///
+/// The [Display](std::fmt::Display) implementation formats them for `--stats-detail`, to follow
+/// [Statistics].
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::write_results::{HistogramBucket, StatisticsDetail};
+/// use groupby::grouped_collections::GroupedCollection;
+/// use std::collections::BTreeMap;
+///
+/// let mut map = BTreeMap::new();
+/// for (key, value) in [("a", "1"), ("a", "2"), ("a", "3"), ("b", "4"), ("c", "5")] {
+///     map.add(key.to_string(), value.to_string());
+/// }
+///
+/// let detail = StatisticsDetail::of(&map, &[50, 90]);
+/// assert_eq!(format!("{:.3}", detail.group_size_stddev), "0.943");
+/// assert_eq!(detail.group_size_percentiles, vec![(50, 1), (90, 3)]);
+/// assert_eq!(
+///     detail.histogram,
+///     vec![
+///         HistogramBucket { min: 1, max: 1, groups: 2 },
+///         HistogramBucket { min: 2, max: 3, groups: 1 },
+///     ],
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatisticsDetail {
+    /// Population standard deviation of the group sizes.
+    pub group_size_stddev: f64,
+
+    /// Each requested percentile, from 0 to 100, with the smallest group size that at least that
+    /// percentage of groups don't exceed (the nearest-rank method). With an even number of groups,
+    /// the 50th percentile is the smaller of the two middle sizes, so it can be less than
+    /// [Statistics::group_size_median].
+    pub group_size_percentiles: Vec<(u8, usize)>,
+
+    /// Group sizes divided into buckets by powers of two, from the bucket holding the smallest
+    /// group to the bucket holding the largest, including empty buckets between them.
+    pub histogram: Vec<HistogramBucket>,
+}
+
+/// A range of group sizes in a [StatisticsDetail::histogram], with the number of groups in it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HistogramBucket {
+    /// Smallest group size in the bucket.
+    pub min: usize,
+
+    /// Largest group size in the bucket.
+    pub max: usize,
+
+    /// Number of groups whose sizes fall in the bucket.
+    pub groups: usize,
+}
+
+/// The width, in characters, of the longest bar in a printed histogram.
+const HISTOGRAM_WIDTH: usize = 40;
+
+impl StatisticsDetail {
+    /// Computes detailed statistics for a given [GroupedCollection], including the given
+    /// percentiles. Every statistic is 0, and the histogram is empty, if it's empty.
+    pub fn of<M>(map: &M, percentiles: &[u8]) -> Self
+    where
+        M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
+    {
+        let group_sizes = sorted_group_sizes(map);
+
+        let group_size_stddev = if group_sizes.is_empty() {
+            0.0
+        } else {
+            let count = group_sizes.len() as f64;
+            let mean = group_sizes.iter().sum::<usize>() as f64 / count;
+            let variance = group_sizes
+                .iter()
+                .map(|&size| (size as f64 - mean).powi(2))
+                .sum::<f64>()
+                / count;
+            variance.sqrt()
+        };
+
+        let group_size_percentiles = percentiles
+            .iter()
+            .map(|&percentile| {
+                let rank = (percentile as usize * group_sizes.len()).div_ceil(100);
+                let size = group_sizes
+                    .get(rank.saturating_sub(1))
+                    .copied()
+                    .unwrap_or(0);
+                (percentile, size)
+            })
+            .collect();
+
+        let mut histogram: Vec<HistogramBucket> = vec![];
+        if let (Some(&smallest), Some(&largest)) = (group_sizes.first(), group_sizes.last()) {
+            let mut min = smallest;
+            if min > 0 {
+                // Start at the power of two at or below the smallest size.
+                min = 1 << min.ilog2();
+            }
+            while min <= largest {
+                let max = if min == 0 { 0 } else { min * 2 - 1 };
+                let groups = group_sizes
+                    .iter()
+                    .filter(|&&size| min <= size && size <= max)
+                    .count();
+                histogram.push(HistogramBucket { min, max, groups });
+                min = max + 1;
+            }
+        }
+
+        StatisticsDetail {
+            group_size_stddev,
+            group_size_percentiles,
+            histogram,
+        }
+    }
+}
+
+impl std::fmt::Display for StatisticsDetail {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "    Standard deviation: {:.2}", self.group_size_stddev)?;
+        for (percentile, size) in &self.group_size_percentiles {
+            writeln!(f, "    p{}: {}", percentile, size)?;
+        }
+
+        writeln!(f, "\n  Group size histogram:")?;
+        let labels: Vec<String> = self
+            .histogram
+            .iter()
+            .map(|bucket| {
+                if bucket.min == bucket.max {
+                    bucket.min.to_string()
+                } else {
+                    format!("{}-{}", bucket.min, bucket.max)
+                }
+            })
+            .collect();
+        let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+        let most_groups = self.histogram.iter().map(|b| b.groups).max().unwrap_or(0);
+        for (bucket, label) in self.histogram.iter().zip(labels) {
+            // Every non-empty bucket gets at least one character, so it's visible.
+            let width = (bucket.groups * HISTOGRAM_WIDTH)
+                .div_ceil(most_groups.max(1))
+                .min(HISTOGRAM_WIDTH);
+            let bar = match width {
+                0 => bucket.groups.to_string(),
+                _ => format!("{} {}", "#".repeat(width), bucket.groups),
+            };
+            writeln!(
+                f,
+                "    {:<label_width$} | {}",
+                label,
+                bar,
+                label_width = label_width,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Returns the size of each group in map, from smallest to largest.
+fn sorted_group_sizes<M>(map: &M) -> Vec<usize>
 where
     M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
 {
-    // We'll reuse this time and time again, so might as well cache it and sort it.
-    let mut group_sizes: Vec<usize> = map.iter().map(|(_, items)| items.len()).collect();
+    let mut group_sizes = Vec::with_capacity(map.len());
+    group_sizes.extend(map.iter().map(|(_, items)| items.len()));
     group_sizes.sort_unstable();
-    let group_sizes = group_sizes;
-
-    // Total items across all groups.
-    let total_items: usize = group_sizes.iter().sum();
-
-    // Number of groups in the collection.
-    let total_groups: usize = group_sizes.len();
-
-    // Smallest group size.
-    let group_size_min: usize = group_sizes.iter().min().copied().unwrap_or(0);
+    group_sizes
+}
 
-    // Largest group size.
-    let group_size_max: usize = group_sizes.iter().max().copied().unwrap_or(0);
+/// Writes the final output for a [CountingCollection]: each group's key and count, in the order
+/// given by [OutputOptions::sort], or as a JSON object mapping keys to counts if
+/// [OutputOptions::json] is true.
+///
+/// [CountingCollection]: crate::grouped_collections::CountingCollection
+///
+/// ```
+/// use groupby::command_line::options::OutputOptions;
+/// use groupby::command_line::write_results::write_counts;
+/// use groupby::grouped_collections::{CountingCollection, GroupedCollection};
+///
+/// let mut counts = CountingCollection::new();
+/// for word in ["apple", "avocado", "banana"] {
+///     counts.add(word[..1].to_string(), word.to_string());
+/// }
+///
+/// let mut output = vec![];
+/// write_counts(&mut output, &counts, &OutputOptions::default()).unwrap();
+/// assert_eq!(output, b"a: 2\nb: 1\n");
+/// ```
+///
+/// # Errors
+///
+/// Returns [GroupByError::Io] if writing to `output` fails.
+pub fn write_counts<M, O>(
+    mut output: O,
+    map: &M,
+    options: &OutputOptions,
+) -> Result<(), GroupByError>
+where
+    M: for<'s> GroupedCollection<'s, String, String, usize>,
+    O: Write,
+{
+    let mut groups: Vec<_> = map.iter().collect();
+    options.order_groups(&mut groups, |count| **count);
+
+    if options.json {
+        let mut document = String::new();
+        json::write_object(&mut document, groups, |document, count| {
+            document.push_str(&count.to_string())
+        });
+        document.push('\n');
+        output.write_all(document.as_bytes())?;
+        return Ok(());
+    }
 
-    // Lower median group size.
-    let group_size_median: usize = group_sizes.get(group_sizes.len() / 2).copied().unwrap_or(0);
+    let records: Vec<String> = groups
+        .into_iter()
+        .map(|(key, count)| match options.quote {
+            Quote::None => format!("{}: {}", key, count),
+            Quote::Shell => format!("{}: {}", shell_quote(key), count),
+        })
+        .collect();
+    let separator = options.separator.sep();
+    RecordWriter::new(output, separator.as_bytes()).write_all(records.iter())?;
+    Ok(())
+}
 
-    // Average group size.
-    let group_size_average: f64 = if total_groups == 0 {
-        0.00
-    } else {
-        total_items as f64 / total_groups as f64
-    };
+/// Writes the number of duplicate values removed by
+/// [crate::command_line::options::GroupByOptions::unique] to `output`, as an addendum to the
+/// statistics that [write_results] writes. Writes nothing unless [OutputOptions::stats] is true.
+///
+/// ```
+/// use groupby::command_line::options::OutputOptions;
+/// use groupby::command_line::write_results::write_duplicates_removed;
+///
+/// let mut output = vec![];
+/// let options = OutputOptions {
+///     stats: true,
+///     ..Default::default()
+/// };
+/// write_duplicates_removed(&mut output, 3, &options).unwrap();
+/// assert_eq!(output, b"  Duplicates removed: 3\n");
+/// ```
+///
+/// # Errors
+///
+/// Returns [GroupByError::Io] if writing to `output` fails.
+pub fn write_duplicates_removed<O: Write>(
+    output: O,
+    count: usize,
+    options: &OutputOptions,
+) -> Result<(), GroupByError> {
+    if options.stats {
+        let separator = options.separator.sep();
+        RecordWriter::new(output, separator.as_bytes())
+            .write(&format!("  Duplicates removed: {}", count))?;
+    }
+    Ok(())
+}
 
-    format!(
-        "Statistics:\n  \
-          Total items: {}\n  \
-          Total groups: {}\n\
-          \n  \
-          Group size:\n    \
-            Median: {}\n    \
-            Average: {:.2}\n    \
-            Min: {}\n    \
-            Max: {}\n",
-        total_items,
-        total_groups,
-        group_size_median,
-        group_size_average,
-        group_size_min,
-        group_size_max
-    )
+/// Reports statistics for a given [GroupedCollection], counting bytes as if written with
+/// `separator`. If `percentiles` is a `Some` value, the report also includes a [StatisticsDetail]
+/// with those percentiles.
+pub fn statistics_for<M>(map: &M, separator: &str, percentiles: Option<&[u8]>) -> String
+where
+    M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
+{
+    let mut report = Statistics::with_separator(map, separator).to_string();
+    if let Some(percentiles) = percentiles {
+        report.push_str(&StatisticsDetail::of(map, percentiles).to_string());
+    }
+    report
 }
 
 #[cfg(test)]
@@ -210,6 +811,7 @@ mod tests {
     use super::*;
     use crate::command_line::options::*;
     use crate::command_line::test_helpers::*;
+    use crate::grouped_collections::CountingCollection;
 
     mod default_output_options {
         use super::*;
@@ -220,17 +822,75 @@ mod tests {
                 separator: Separator::Null,
                 only_group_names: true,
                 run_command: Some("command".to_string()),
+                shell: Some("/bin/sh".to_string()),
+                dry_run: true,
                 parallel: false,
+                jobs: Some(2),
+                max_args: Some(10),
+                max_stdin_bytes: Some(4096),
+                nice: Some(10),
+                max_memory: Some(1 << 30),
+                max_open_files: Some(256),
+                capture_stderr: true,
+                live: true,
+                incremental: true,
+                with_values: true,
+                failure_policy: FailurePolicy::FailFast,
                 headers: false,
+                header_format: Some("# {key}".parse().unwrap()),
                 stats: false,
+                stats_detail: Some(vec![90]),
+                timings: true,
+                checkpoint: Some("checkpoint".to_string()),
+                resume: true,
+                sort: KeyOrder::Collate,
+                group_order: GroupOrder::SizeDescending,
+                reverse: true,
+                presorted_streaming: true,
+                stream: true,
+                low_memory: true,
+                intern: true,
+                json: false,
+                count_only: true,
+                format: Some("{key}".parse().unwrap()),
+                quote: Quote::Shell,
             };
             let expected = OutputOptions {
                 separator: Separator::Line,
                 only_group_names: false,
                 run_command: None,
+                shell: None,
+                dry_run: false,
                 parallel: false,
+                jobs: None,
+                max_args: None,
+                max_stdin_bytes: None,
+                nice: None,
+                max_memory: None,
+                max_open_files: None,
+                capture_stderr: true,
+                live: true,
+                incremental: true,
+                with_values: true,
+                failure_policy: FailurePolicy::KeepGoing,
                 headers: false,
+                header_format: Some("# {key}".parse().unwrap()),
                 stats: false,
+                stats_detail: Some(vec![90]),
+                timings: false,
+                checkpoint: None,
+                resume: false,
+                sort: KeyOrder::Collate,
+                group_order: GroupOrder::SizeDescending,
+                reverse: true,
+                presorted_streaming: false,
+                stream: false,
+                low_memory: false,
+                intern: false,
+                json: false,
+                count_only: false,
+                format: Some("{key}".parse().unwrap()),
+                quote: Quote::Shell,
             };
             assert_eq!(expected, default_output_options(&unsafe_base));
         }
@@ -245,6 +905,7 @@ mod tests {
                     parallel: val,
                     headers: true,
                     stats: true,
+                    ..Default::default()
                 };
                 let expected = OutputOptions {
                     separator: Separator::Line,
@@ -253,6 +914,7 @@ mod tests {
                     parallel: val,
                     headers: true,
                     stats: true,
+                    ..Default::default()
                 };
                 assert_eq!(expected, default_output_options(&unsafe_base));
             }
@@ -268,6 +930,7 @@ mod tests {
                     parallel: true,
                     headers: val,
                     stats: true,
+                    ..Default::default()
                 };
                 let expected = OutputOptions {
                     separator: Separator::Line,
@@ -276,6 +939,7 @@ mod tests {
                     parallel: true,
                     headers: val,
                     stats: true,
+                    ..Default::default()
                 };
                 assert_eq!(expected, default_output_options(&unsafe_base));
             }
@@ -291,6 +955,7 @@ mod tests {
                     parallel: true,
                     headers: true,
                     stats: val,
+                    ..Default::default()
                 };
                 let expected = OutputOptions {
                     separator: Separator::Line,
@@ -299,6 +964,70 @@ mod tests {
                     parallel: true,
                     headers: true,
                     stats: val,
+                    ..Default::default()
+                };
+                assert_eq!(expected, default_output_options(&unsafe_base));
+            }
+        }
+
+        #[test]
+        fn preserves_sort() {
+            for val in [KeyOrder::Bytes, KeyOrder::Collate] {
+                let unsafe_base = OutputOptions {
+                    separator: Separator::Null,
+                    only_group_names: true,
+                    run_command: Some("command".to_string()),
+                    sort: val,
+                    ..Default::default()
+                };
+                let expected = OutputOptions {
+                    separator: Separator::Line,
+                    only_group_names: false,
+                    run_command: None,
+                    sort: val,
+                    ..Default::default()
+                };
+                assert_eq!(expected, default_output_options(&unsafe_base));
+            }
+        }
+
+        #[test]
+        fn preserves_capture_stderr() {
+            for val in [false, true] {
+                let unsafe_base = OutputOptions {
+                    separator: Separator::Null,
+                    only_group_names: true,
+                    run_command: Some("command".to_string()),
+                    capture_stderr: val,
+                    ..Default::default()
+                };
+                let expected = OutputOptions {
+                    separator: Separator::Line,
+                    only_group_names: false,
+                    run_command: None,
+                    capture_stderr: val,
+                    ..Default::default()
+                };
+                assert_eq!(expected, default_output_options(&unsafe_base));
+            }
+        }
+
+        #[test]
+        fn preserves_json() {
+            for val in [false, true] {
+                let unsafe_base = OutputOptions {
+                    separator: Separator::Null,
+                    only_group_names: true,
+                    run_command: Some("command".to_string()),
+                    json: val,
+                    ..Default::default()
+                };
+                let expected = OutputOptions {
+                    separator: Separator::Line,
+                    only_group_names: false,
+                    run_command: None,
+                    json: val,
+                    ..Default::default()
                 };
                 assert_eq!(expected, default_output_options(&unsafe_base));
             }
@@ -328,19 +1057,26 @@ mod tests {
                 parallel: true,
                 headers,
                 stats,
+                ..Default::default()
             }
         }
 
-        // Constructs a results map where each key's value is its reverse.
-        fn results<'a, M>(map: &'a M) -> BTreeMap<&'a String, Vec<u8>>
+        // Constructs a results map where each key's standard output is its reverse.
+        fn results<'a, M>(map: &'a M) -> BTreeMap<&'a String, CommandOutput>
         where
             M: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
         {
             let mut results = BTreeMap::new();
             for (key, _) in map.iter() {
-                let mut output = Vec::<u8>::from(key.clone());
-                output.reverse();
-                results.insert(key, output);
+                let mut stdout = Vec::<u8>::from(key.clone());
+                stdout.reverse();
+                results.insert(
+                    key,
+                    CommandOutput {
+                        stdout,
+                        ..Default::default()
+                    },
+                );
             }
             results
         }
@@ -349,6 +1085,7 @@ mod tests {
         fn statistics_report_for(
             ti: usize,
             tg: usize,
+            tb: usize,
             gmed: usize,
             gavg: f64,
             min: usize,
@@ -357,14 +1094,15 @@ mod tests {
             format!(
                 "Statistics:\n  \
                       Total items: {}\n  \
-                      Total groups: {}\n\
+                      Total groups: {}\n  \
+                      Total bytes: {}\n\
                       \n  \
                       Group size:\n    \
                         Median: {}\n    \
                         Average: {:.2}\n    \
                         Min: {}\n    \
                         Max: {}\n",
-                ti, tg, gmed, gavg, min, max,
+                ti, tg, tb, gmed, gavg, min, max,
             )
         }
 
@@ -380,13 +1118,55 @@ mod tests {
             let map = map();
             let results = Some(results(&map));
 
-            write_results(&mut output, &map, &results, &options);
+            write_results(&mut output, &map, &results, &options).unwrap();
 
             let expected = "Cats:\nstaC\nDogs:\nsgoD\n".to_string();
             let actual = String::from_utf8_lossy(&output);
             assert_eq!(expected, actual);
         }
 
+        #[test]
+        fn with_results_and_stats_describes_commands() {
+            use std::os::unix::process::ExitStatusExt;
+            use std::process::ExitStatus;
+
+            let mut output = buffer();
+            let options = options_for(false, true, true);
+            let map = map();
+            let mut results = results(&map);
+            results.get_mut(&"Dogs".to_string()).unwrap().status = ExitStatus::from_raw(1 << 8);
+
+            write_results(&mut output, &map, &Some(results), &options).unwrap();
+
+            let expected = format!(
+                "Cats: (2 items, 16 bytes, 0.00 ms)\n\
+                staC\n\
+                Dogs: (2 items, 12 bytes, exit status: 1, 0.00 ms)\n\
+                sgoD\n\
+                \n\
+                {}\n  \
+                Failed commands: 1\n",
+                statistics_report_for(4, 2, 28, 2, 2.00, 2, 2)
+            );
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn with_results_skips_groups_without_results() {
+            let mut output = buffer();
+            let options = options_for(false, true, false);
+            let map = map();
+            let mut results = results(&map);
+            results.remove(&"Cats".to_string());
+
+            write_results(&mut output, &map, &Some(results), &options).unwrap();
+
+            let expected = "Dogs:\nsgoD\n".to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
         // The tests below verify all the other logic of write_results(). The code these cases test
         // applies equally to either the original options struct (if results == &None) or to an
         // options struct masked with default values (if results is a Some value); these tests,
@@ -400,13 +1180,320 @@ mod tests {
             options.separator = Separator::Null;
             let map = map();
 
-            write_results(&mut output, &map, &None, &options);
+            write_results(&mut output, &map, &None, &options).unwrap();
 
             let expected = "Cats:\0Meowser\0Mittens\0Dogs:\0Lassy\0Buddy\0".to_string();
             let actual = String::from_utf8_lossy(&output);
             assert_eq!(expected, actual);
         }
 
+        #[test]
+        fn writes_json() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, false);
+            options.json = true;
+            options.separator = Separator::Null; // JSON output should ignore this.
+            let map = map();
+
+            write_results(&mut output, &map, &None, &options).unwrap();
+
+            let expected = r#"{"Cats":["Meowser","Mittens"],"Dogs":["Lassy","Buddy"]}"#;
+            assert_eq!(format!("{}\n", expected), String::from_utf8_lossy(&output));
+        }
+
+        #[test]
+        fn writes_json_results() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, false);
+            options.json = true;
+            let map = map();
+            let mut results = results(&map);
+            results.remove(&"Cats".to_string());
+
+            write_results(&mut output, &map, &Some(results), &options).unwrap();
+
+            assert_eq!("{\"Dogs\":\"sgoD\"}\n", String::from_utf8_lossy(&output));
+        }
+
+        #[test]
+        fn writes_captured_stderr_after_results() {
+            let mut output = buffer();
+            let options = options_for(false, true, false);
+            let map = map();
+            let mut results = results(&map);
+            results.get_mut(&"Cats".to_string()).unwrap().stderr = b"oops".to_vec();
+
+            write_results(&mut output, &map, &Some(results), &options).unwrap();
+
+            let expected = "Cats:\nstaC\noops\nDogs:\nsgoD\n".to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn with_values_writes_contents_before_results() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, false);
+            options.with_values = true;
+            let map = map();
+            let results = Some(results(&map));
+
+            write_results(&mut output, &map, &results, &options).unwrap();
+
+            let expected = "Cats:\nMeowser\nMittens\nOutput:\nstaC\n\
+                Dogs:\nLassy\nBuddy\nOutput:\nsgoD\n"
+                .to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn with_values_and_no_headers_leaves_out_output_lines() {
+            let mut output = buffer();
+            let mut options = options_for(false, false, false);
+            options.with_values = true;
+            let map = map();
+            let results = Some(results(&map));
+
+            write_results(&mut output, &map, &results, &options).unwrap();
+
+            let expected = "Meowser\nMittens\nstaC\nLassy\nBuddy\nsgoD\n".to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn with_format_fills_in_template() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, false);
+            options.format = Some("{key} ({count}): {values:, }".parse().unwrap());
+            let map = map();
+
+            write_results(&mut output, &map, &None, &options).unwrap();
+
+            let expected = "Cats (2): Meowser, Mittens
+Dogs (2): Lassy, Buddy
+"
+            .to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn with_format_and_results_fills_in_output() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, true);
+            options.format = Some("{key}={output}".parse().unwrap());
+            let map = map();
+            let results = Some(results(&map));
+
+            write_results(&mut output, &map, &results, &options).unwrap();
+
+            let expected = format!(
+                "Cats=staC\nDogs=sgoD\n\n{}\n  Failed commands: 0\n",
+                statistics_report_for(4, 2, 28, 2, 2.00, 2, 2)
+            );
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn with_header_format_fills_in_headers() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, true);
+            options.header_format = Some("## {key} ({count})".parse().unwrap());
+            let map = map();
+
+            write_results(&mut output, &map, &None, &options).unwrap();
+
+            let actual = String::from_utf8_lossy(&output);
+            assert!(actual.starts_with(
+                "## Cats (2) (2 items, 16 bytes)\n\
+                Meowser\n\
+                Mittens\n\
+                ## Dogs (2) (2 items, 12 bytes)\n"
+            ));
+        }
+
+        #[test]
+        fn with_shell_quote_quotes_keys_and_values() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, true);
+            options.quote = Quote::Shell;
+            let mut map = BTreeMap::new();
+            map.add("my pets".to_string(), "Rex".to_string());
+            map.add("my pets".to_string(), "it's $HOME".to_string());
+
+            write_results(&mut output, &map, &None, &options).unwrap();
+
+            // Byte counts are for the values as they are, not as quoted.
+            let actual = String::from_utf8_lossy(&output);
+            assert!(actual.starts_with(
+                "'my pets': (2 items, 15 bytes)\n\
+                Rex\n\
+                'it'\\''s $HOME'\n\n"
+            ));
+        }
+
+        #[test]
+        fn with_shell_quote_quotes_counts_keys() {
+            let mut output = buffer();
+            let options = OutputOptions {
+                quote: Quote::Shell,
+                ..Default::default()
+            };
+            let mut counts = CountingCollection::new();
+            for (key, value) in [("a b", "x"), ("a b", "y"), ("c", "z")] {
+                counts.add(key.to_string(), value.to_string());
+            }
+
+            write_counts(&mut output, &counts, &options).unwrap();
+
+            assert_eq!(output, b"'a b': 2\nc: 1\n");
+        }
+
+        #[test]
+        fn with_live_output_writes_only_stats() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, true);
+            options.live = true;
+            let map = map();
+            let results = results(&map);
+
+            write_results(&mut output, &map, &Some(results), &options).unwrap();
+
+            let expected = format!(
+                "\n{}\n  Failed commands: 0\n",
+                statistics_report_for(4, 2, 28, 2, 2.00, 2, 2)
+            );
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn writes_json_results_with_stderr() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, false);
+            options.json = true;
+            options.capture_stderr = true;
+            let map = map();
+            let mut results = results(&map);
+            results.remove(&"Cats".to_string());
+            results.get_mut(&"Dogs".to_string()).unwrap().stderr = b"oops\n".to_vec();
+
+            write_results(&mut output, &map, &Some(results), &options).unwrap();
+
+            assert_eq!(
+                "{\"Dogs\":{\"stdout\":\"sgoD\",\"stderr\":\"oops\\n\"}}\n",
+                String::from_utf8_lossy(&output)
+            );
+        }
+
+        #[test]
+        fn writes_json_results_with_values() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, false);
+            options.json = true;
+            options.with_values = true;
+            let map = map();
+            let mut results = results(&map);
+            results.remove(&"Cats".to_string());
+
+            write_results(&mut output, &map, &Some(results), &options).unwrap();
+
+            assert_eq!(
+                "{\"Dogs\":{\"values\":[\"Lassy\",\"Buddy\"],\"stdout\":\"sgoD\"}}\n",
+                String::from_utf8_lossy(&output)
+            );
+        }
+
+        #[test]
+        fn uses_sort_order() {
+            let mut output = buffer();
+            let mut options = options_for(true, false, false);
+            options.sort = KeyOrder::Collate;
+            let mut map = BTreeMap::new();
+            for key in ["banana", "Äpfel", "apple", "Apple"] {
+                map.add(key.to_string(), key.to_string());
+            }
+
+            write_results(&mut output, &map, &None, &options).unwrap();
+
+            let expected = "Äpfel\napple\nApple\nbanana\n".to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn uses_group_order() {
+            let mut output = buffer();
+            let mut options = options_for(true, false, false);
+            options.group_order = GroupOrder::SizeDescending;
+            let mut map = BTreeMap::new();
+            for (key, value) in [("a", "1"), ("b", "1"), ("b", "2"), ("c", "1")] {
+                map.add(key.to_string(), value.to_string());
+            }
+
+            write_results(&mut output, &map, &None, &options).unwrap();
+
+            let expected = "b\na\nc\n".to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn uses_reverse_order() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, false);
+            options.reverse = true;
+            let map = map();
+
+            write_results(&mut output, &map, &None, &options).unwrap();
+
+            let expected = "Dogs:\nLassy\nBuddy\nCats:\nMeowser\nMittens\n".to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn uses_reverse_order_with_results() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, false);
+            options.reverse = true;
+            let map = map();
+            let results = Some(results(&map));
+
+            write_results(&mut output, &map, &results, &options).unwrap();
+
+            let expected = "Dogs:\nsgoD\nCats:\nstaC\n".to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn uses_group_order_with_results() {
+            let mut output = buffer();
+            let mut options = options_for(false, true, false);
+            options.group_order = GroupOrder::Size;
+            let mut map = map();
+            map.add("Dogs".to_string(), "Rex".to_string());
+            let results = Some(results(&map));
+
+            write_results(&mut output, &map, &results, &options).unwrap();
+
+            let expected = "Cats:\nstaC\nDogs:\nsgoD\n".to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+
+            options.group_order = GroupOrder::SizeDescending;
+            let mut output = buffer();
+            write_results(&mut output, &map, &results, &options).unwrap();
+
+            let expected = "Dogs:\nsgoD\nCats:\nstaC\n".to_string();
+            let actual = String::from_utf8_lossy(&output);
+            assert_eq!(expected, actual);
+        }
+
         mod with_only_group_names {
             use super::*;
 
@@ -419,14 +1506,14 @@ mod tests {
                 let options = options_for(true, true, true);
                 let map = map();
 
-                write_results(&mut output, &map, &None, &options);
+                write_results(&mut output, &map, &None, &options).unwrap();
 
                 let expected = format!(
-                    "Cats (2 items)\n\
-                    Dogs (2 items)\n\
+                    "Cats (2 items, 16 bytes)\n\
+                    Dogs (2 items, 12 bytes)\n\
                     \n\
                     {}\n",
-                    statistics_report_for(4, 2, 2, 2.00, 2, 2),
+                    statistics_report_for(4, 2, 28, 2, 2.00, 2, 2),
                 );
                 let actual = String::from_utf8_lossy(&output);
                 assert_eq!(expected, actual);
@@ -438,12 +1525,27 @@ mod tests {
                 let options = options_for(true, false, false);
                 let map = map();
 
-                write_results(&mut output, &map, &None, &options);
+                write_results(&mut output, &map, &None, &options).unwrap();
 
                 let expected = "Cats\nDogs\n".to_string();
                 let actual = String::from_utf8_lossy(&output);
                 assert_eq!(expected, actual);
             }
+
+            #[test]
+            fn with_null_separators_prints_bare_names() {
+                let mut output = buffer();
+                let mut options = options_for(true, true, true);
+                options.separator = Separator::Null;
+                options.quote = Quote::Shell;
+                let mut map = map();
+                map.add("Big cats".to_string(), "Tiger".to_string());
+
+                write_results(&mut output, &map, &None, &options).unwrap();
+
+                let actual = String::from_utf8_lossy(&output);
+                assert!(actual.starts_with("Big cats\0Cats\0Dogs\0\0"));
+            }
         }
 
         mod without_only_group_names {
@@ -458,18 +1560,18 @@ mod tests {
                     let options = options_for(false, true, true);
                     let map = map();
 
-                    write_results(&mut output, &map, &None, &options);
+                    write_results(&mut output, &map, &None, &options).unwrap();
 
                     let expected = format!(
-                        "Cats: (2 items)\n\
+                        "Cats: (2 items, 16 bytes)\n\
                         Meowser\n\
                         Mittens\n\
-                        Dogs: (2 items)\n\
+                        Dogs: (2 items, 12 bytes)\n\
                         Lassy\n\
                         Buddy\n\
                         \n\
                         {}\n",
-                        statistics_report_for(4, 2, 2, 2.00, 2, 2)
+                        statistics_report_for(4, 2, 28, 2, 2.00, 2, 2)
                     );
                     let actual = String::from_utf8_lossy(&output);
                     assert_eq!(expected, actual);
@@ -481,7 +1583,7 @@ mod tests {
                     let options = options_for(false, true, false);
                     let map = map();
 
-                    write_results(&mut output, &map, &None, &options);
+                    write_results(&mut output, &map, &None, &options).unwrap();
 
                     let expected = "Cats:\nMeowser\nMittens\nDogs:\nLassy\nBuddy\n".to_string();
                     let actual = String::from_utf8_lossy(&output);
@@ -489,6 +1591,22 @@ mod tests {
                 }
             }
 
+            #[test]
+            fn with_stats_counts_bytes_with_the_separator() {
+                let mut output = buffer();
+                let mut options = options_for(false, true, true);
+                options.separator = Separator::Custom(", ".to_string());
+                let map = map();
+
+                write_results(&mut output, &map, &None, &options).unwrap();
+
+                let actual = String::from_utf8_lossy(&output);
+                assert!(actual.starts_with(
+                    "Cats: (2 items, 18 bytes), Meowser, Mittens, Dogs: (2 items, 14 bytes), "
+                ));
+                assert!(actual.contains("Total bytes: 32\n"));
+            }
+
             mod without_headers {
                 use super::*;
 
@@ -498,7 +1616,7 @@ mod tests {
                     let options = options_for(false, false, true);
                     let map = map();
 
-                    write_results(&mut output, &map, &None, &options);
+                    write_results(&mut output, &map, &None, &options).unwrap();
 
                     let expected = format!(
                         "Meowser\n\
@@ -507,7 +1625,7 @@ mod tests {
                         Buddy\n\
                         \n\
                         {}\n",
-                        statistics_report_for(4, 2, 2, 2.00, 2, 2)
+                        statistics_report_for(4, 2, 28, 2, 2.00, 2, 2)
                     );
                     let actual = String::from_utf8_lossy(&output);
                     assert_eq!(expected, actual);
@@ -519,7 +1637,7 @@ mod tests {
                     let options = options_for(false, false, false);
                     let map = map();
 
-                    write_results(&mut output, &map, &None, &options);
+                    write_results(&mut output, &map, &None, &options).unwrap();
 
                     let expected = "Meowser\nMittens\nLassy\nBuddy\n".to_string();
                     let actual = String::from_utf8_lossy(&output);
@@ -543,7 +1661,7 @@ mod tests {
         #[test]
         fn works_with_1_item() {
             let expected = "1 item".to_string();
-            let actual = item_count(&vec![1]);
+            let actual = item_count(&[1]);
             assert_eq!(expected, actual);
         }
 
@@ -563,10 +1681,11 @@ mod tests {
         #[test]
         fn works_with_empty_collection() {
             assert_eq!(
-                statistics_for(&BTreeMap::new()),
+                statistics_for(&BTreeMap::new(), "\n", None),
                 "Statistics:\n  \
                   Total items: 0\n  \
-                  Total groups: 0\n\
+                  Total groups: 0\n  \
+                  Total bytes: 0\n\
                   \n  \
                   Group size:\n    \
                     Median: 0\n    \
@@ -584,10 +1703,11 @@ mod tests {
             map.insert("C".to_string(), (1..=4).map(|i| i.to_string()).collect());
 
             assert_eq!(
-                statistics_for(&map),
+                statistics_for(&map, "\n", None),
                 "Statistics:\n  \
                   Total items: 6\n  \
-                  Total groups: 3\n\
+                  Total groups: 3\n  \
+                  Total bytes: 12\n\
                   \n  \
                   Group size:\n    \
                     Median: 2\n    \
@@ -605,10 +1725,11 @@ mod tests {
             map.insert("C".to_string(), (1..=3).map(|i| i.to_string()).collect());
 
             assert_eq!(
-                statistics_for(&map),
+                statistics_for(&map, "\n", None),
                 "Statistics:\n  \
                   Total items: 5\n  \
-                  Total groups: 3\n\
+                  Total groups: 3\n  \
+                  Total bytes: 10\n\
                   \n  \
                   Group size:\n    \
                     Median: 2\n    \
@@ -617,5 +1738,82 @@ mod tests {
                     Max: 3\n",
             );
         }
+
+        #[test]
+        fn includes_detail_when_requested() {
+            let mut map = BTreeMap::new();
+            map.insert("A".to_string(), vec![]);
+            map.insert("B".to_string(), vec!["1".to_string(), "2".to_string()]);
+            map.insert("C".to_string(), (1..=4).map(|i| i.to_string()).collect());
+
+            let bar = "#".repeat(HISTOGRAM_WIDTH);
+            assert_eq!(
+                statistics_for(&map, "\n", Some(&[50, 90])),
+                format!(
+                    "Statistics:\n  \
+                      Total items: 6\n  \
+                      Total groups: 3\n  \
+                      Total bytes: 12\n\
+                      \n  \
+                      Group size:\n    \
+                        Median: 2\n    \
+                        Average: 2.00\n    \
+                        Min: 0\n    \
+                        Max: 4\n    \
+                        Standard deviation: 1.63\n    \
+                        p50: 2\n    \
+                        p90: 4\n\
+                      \n  \
+                      Group size histogram:\n    \
+                        0   | {bar} 1\n    \
+                        1   | 0\n    \
+                        2-3 | {bar} 1\n    \
+                        4-7 | {bar} 1\n",
+                    bar = bar
+                ),
+            );
+        }
+
+        #[test]
+        fn detail_works_with_empty_collection() {
+            let detail = StatisticsDetail::of(&BTreeMap::new(), &[0, 100]);
+            assert_eq!(detail.group_size_stddev, 0.0);
+            assert_eq!(detail.group_size_percentiles, vec![(0, 0), (100, 0)]);
+            assert!(detail.histogram.is_empty());
+        }
+
+        #[test]
+        fn detail_scales_histogram_bars() {
+            let mut map = BTreeMap::new();
+            for i in 0..80 {
+                map.insert(i.to_string(), vec!["x".to_string()]);
+            }
+            map.insert("big".to_string(), vec!["x".to_string(); 5]);
+
+            let detail = StatisticsDetail::of(&map, &[]);
+            assert_eq!(
+                detail.histogram,
+                vec![
+                    HistogramBucket {
+                        min: 1,
+                        max: 1,
+                        groups: 80
+                    },
+                    HistogramBucket {
+                        min: 2,
+                        max: 3,
+                        groups: 0
+                    },
+                    HistogramBucket {
+                        min: 4,
+                        max: 7,
+                        groups: 1
+                    },
+                ],
+            );
+            let lines: Vec<String> = detail.to_string().lines().map(String::from).collect();
+            assert!(lines.contains(&format!("    1   | {} 80", "#".repeat(HISTOGRAM_WIDTH))));
+            assert!(lines.contains(&"    4-7 | # 1".to_string()));
+        }
     }
 }
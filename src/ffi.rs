@@ -0,0 +1,265 @@
+//! A C ABI for embedding the grouping engine in non-Rust applications. Requires the `ffi` feature.
+//!
+//! The interface has a single entry point, [groupby_group_json], which splits its input into
+//! tokens, groups them, and returns the groups serialized as a JSON object mapping each group's
+//! key to an array of its values (or to its count, with `--count-only`). Options are given as
+//! `groupby` command-line arguments, e.g. `{"-w", "--extension"}`, so everything documented in
+//! `groupby --help` about splitting and grouping applies. Options that run commands are rejected,
+//! as are options that choose or label inputs, e.g. file arguments, `--walk`, `--files0-from`,
+//! `--tee`, and `-H`, since the input is always the one that the caller passes in.
+//!
+//! The boundary never unwinds: invalid arguments, invalid input, and any panic inside the engine
+//! are reported through the `error` out-parameter instead.
+//!
+//! # Building
+//!
+//! To build a shared library:
+//!
+//! ```text
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! ```
+//!
+//! The matching C declarations are in `include/groupby.h`.
+
+use crate::command_line::build_groups::build_groups;
+use crate::command_line::json;
+use crate::command_line::parse_args::{panic_message, try_parse_args};
+use crate::command_line::OutputOptions;
+use crate::grouped_collections::{CountingCollection, GroupedCollection};
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// Groups `input` as specified by the command-line arguments `args` and returns the groups as a
+/// JSON object, e.g. `{"a":["apple","avocado"],"b":["banana"]}`.
+///
+/// Groups appear in the order given by `--sort` (bytes by default), `--reverse`, and
+/// `--sort-groups`. This is the safe function behind [groupby_group_json].
+///
+/// # Examples
+///
+/// ```
+/// use groupby::ffi::group_json;
+///
+/// let json = group_json(b"apple\navocado\nbanana", &["-f1"]).unwrap();
+/// assert_eq!(json, r#"{"a":["apple","avocado"],"b":["banana"]}"#);
+///
+/// let json = group_json(b"apple\navocado\nbanana", &["-f1", "--count-only"]).unwrap();
+/// assert_eq!(json, r#"{"a":2,"b":1}"#);
+///
+/// let json = group_json(b"apple\napple\nbanana", &["-f1", "--count-values"]).unwrap();
+/// assert_eq!(json, r#"{"a":["2 apple"],"b":["1 banana"]}"#);
+///
+/// assert!(group_json(b"", &["--no-such-option"]).is_err());
+/// ```
+pub fn group_json<S: AsRef<str>>(input: &[u8], args: &[S]) -> Result<String, String> {
+    let options = try_parse_args(args)?;
+    if options.output.run_command.is_some() || options.grouping.runs_commands() {
+        return Err("Running commands is not supported here".to_string());
+    }
+    if options.input.selects_inputs() {
+        return Err(
+            "Input files are not supported here, since the input is passed in directly".to_string(),
+        );
+    }
+
+    // Catch any panic so that it's reported like any other error.
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        if options.output.count_only {
+            let mut counts = CountingCollection::new();
+            build_groups(input, &mut counts, &options).map_err(|e| e.to_string())?;
+            counts.retain(|_, count| options.keeps_size(*count));
+            let mut groups: Vec<_> = counts.iter().collect();
+            options.output.order_groups(&mut groups, |count| **count);
+
+            let mut json = String::new();
+            json::write_object(&mut json, groups, |json, count| {
+                json.push_str(&count.to_string())
+            });
+            return Ok(json);
+        }
+
+        let mut map = BTreeMap::new();
+        build_groups(input, &mut map, &options).map_err(|e| e.to_string())?;
+        options.finish_groups(&mut map);
+        Ok(to_json(&map, &options.output))
+    }))
+    .unwrap_or_else(|payload| Err(panic_message(&*payload)))
+}
+
+// Serializes map as a JSON object, with groups in the order that `options` gives.
+fn to_json(map: &BTreeMap<String, Vec<String>>, options: &OutputOptions) -> String {
+    let mut groups: Vec<_> = map.iter().collect();
+    options.order_groups(&mut groups, |values| values.len());
+
+    let mut json = String::new();
+    json::write_object(&mut json, groups, json::write_array);
+    json
+}
+
+// Converts s into a C string that the caller frees with groupby_string_free. Interior null
+// characters can't be represented, so they are escaped (JSON) or replaced (error messages) first.
+fn into_c_string(s: String) -> *mut c_char {
+    CString::new(s.replace('\0', "\\u0000"))
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Groups `input_len` bytes at `input` as specified by the `argc` command-line arguments in
+/// `argv`, and returns the groups as a JSON object. See [group_json] for details.
+///
+/// On success, returns a null-terminated string that the caller must free with
+/// [groupby_string_free]. On failure, returns null and, if `error` is not null, stores an error
+/// message in `*error`, which the caller must also free with [groupby_string_free].
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes (or may be null if `input_len` is 0). `argv`
+/// must point to `argc` valid, null-terminated strings (or may be null if `argc` is 0). `error`
+/// must be null or point to writable storage for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn groupby_group_json(
+    input: *const u8,
+    input_len: usize,
+    argv: *const *const c_char,
+    argc: usize,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let input = if input_len == 0 {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(input, input_len)
+        };
+
+        let mut args = Vec::with_capacity(argc);
+        for i in 0..argc {
+            let arg = CStr::from_ptr(*argv.add(i))
+                .to_str()
+                .map_err(|_| format!("Argument {} is not valid UTF-8", i))?;
+            args.push(arg);
+        }
+
+        group_json(input, &args)
+    }))
+    .unwrap_or_else(|payload| Err(panic_message(&*payload)));
+
+    match result {
+        Ok(json) => into_c_string(json),
+        Err(message) => {
+            if !error.is_null() {
+                *error = into_c_string(message);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a string returned by this library. Does nothing if `s` is null.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library that hasn't already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn groupby_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod group_json {
+        use super::*;
+
+        #[test]
+        fn escapes_strings() {
+            let json = group_json("a\"\\\t\u{1}".as_bytes(), &["-f1"]).unwrap();
+            assert_eq!(json, r#"{"a":["a\"\\\t\u0001"]}"#);
+        }
+
+        #[test]
+        fn honors_sort_order() {
+            let json = group_json(b"img10\nimg2", &["-f5", "--sort", "natural"]).unwrap();
+            assert_eq!(json, r#"{"img2":["img2"],"img10":["img10"]}"#);
+        }
+
+        #[test]
+        fn rejects_run_command() {
+            assert!(group_json(b"a", &["-f1", "-c", "cat"]).is_err());
+        }
+
+        #[test]
+        fn rejects_key_command() {
+            assert!(group_json(b"a", &["--key-command", "cat"]).is_err());
+            assert!(group_json(b"a", &["-f1", "--key-command", "cat"]).is_err());
+            assert!(group_json(b"a", &["--ignore-case", "--key-command", "cat"]).is_err());
+        }
+
+        #[test]
+        fn rejects_input_files() {
+            for args in [
+                &["-f1", "data.log"][..],
+                &["-f1", "--walk", "."],
+                &["-f1", "--files0-from", "list"],
+                &["-f1", "--tee", "copy"],
+                &["-f1", "-H"],
+            ] {
+                let error = group_json(b"a", args).unwrap_err();
+                assert!(error.contains("Input files"), "{:?}: {}", args, error);
+            }
+        }
+
+        #[test]
+        fn reports_parse_errors() {
+            let error = group_json(b"a", &["-fx"]).unwrap_err();
+            assert!(error.contains("Expected a number"));
+        }
+    }
+
+    mod groupby_group_json {
+        use super::*;
+
+        #[test]
+        fn round_trips_through_c_strings() {
+            let input = b"apple\nbanana";
+            let args = [CString::new("-f1").unwrap()];
+            let argv: Vec<*const c_char> = args.iter().map(|a| a.as_ptr()).collect();
+            let mut error = ptr::null_mut();
+
+            unsafe {
+                let json = groupby_group_json(
+                    input.as_ptr(),
+                    input.len(),
+                    argv.as_ptr(),
+                    argv.len(),
+                    &mut error,
+                );
+                assert!(error.is_null());
+                assert_eq!(
+                    CStr::from_ptr(json).to_str().unwrap(),
+                    r#"{"a":["apple"],"b":["banana"]}"#
+                );
+                groupby_string_free(json);
+            }
+        }
+
+        #[test]
+        fn reports_errors() {
+            let args = [CString::new("--bogus").unwrap()];
+            let argv: Vec<*const c_char> = args.iter().map(|a| a.as_ptr()).collect();
+            let mut error = ptr::null_mut();
+
+            unsafe {
+                let json = groupby_group_json(ptr::null(), 0, argv.as_ptr(), 1, &mut error);
+                assert!(json.is_null());
+                assert!(!error.is_null());
+                groupby_string_free(error);
+            }
+        }
+    }
+}
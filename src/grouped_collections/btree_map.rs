@@ -1,7 +1,8 @@
 #![doc(hidden)]
 
-use crate::grouped_collections::GroupedCollection;
+use crate::grouped_collections::{GroupEntry, GroupedCollection};
 use std::collections::{btree_map, BTreeMap};
+use std::mem;
 
 impl<'s, Key, Value> GroupedCollection<'s, Key, Value, Vec<Value>> for BTreeMap<Key, Vec<Value>>
 where
@@ -9,6 +10,8 @@ where
     Key: Ord,
 {
     type Iter = btree_map::Iter<'s, Key, Vec<Value>>;
+    type IntoIter = btree_map::IntoIter<Key, Vec<Value>>;
+    type Entry = btree_map::Entry<'s, Key, Vec<Value>>;
 
     /// Adds `value` to the `Vec<Value>`  at `key` in insertion order.
     ///
@@ -32,11 +35,60 @@ where
         }
     }
 
+    /// Appends each group of `other` to the group with the same key, moving whole groups rather
+    /// than one value at a time. Like the default implementation, it skips empty groups.
+    fn merge<Other>(&mut self, other: Other)
+    where
+        Other: IntoIterator<Item = (Key, Vec<Value>)>,
+    {
+        for (key, mut values) in other {
+            if values.is_empty() {
+                continue;
+            }
+            match self.entry(key) {
+                btree_map::Entry::Occupied(mut vec) => {
+                    vec.get_mut().append(&mut values);
+                }
+                btree_map::Entry::Vacant(slot) => {
+                    slot.insert(values);
+                }
+            }
+        }
+    }
+
+    /// Wraps [BTreeMap::entry()](std::collections::BTreeMap::entry()).
+    fn entry(&'s mut self, key: Key) -> Self::Entry {
+        Self::entry(self, key)
+    }
+
     /// Wraps [BTreeMap::get()](std::collections::BTreeMap::get()).
     fn get(&'s self, key: &Key) -> Option<&'s Vec<Value>> {
         Self::get(self, key)
     }
 
+    /// Wraps [BTreeMap::remove()](std::collections::BTreeMap::remove()).
+    fn remove(&mut self, key: &Key) -> Option<Vec<Value>> {
+        Self::remove(self, key)
+    }
+
+    /// Wraps [BTreeMap::retain()](std::collections::BTreeMap::retain()).
+    fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&Key, &mut Vec<Value>) -> bool,
+    {
+        Self::retain(self, keep)
+    }
+
+    /// Wraps [BTreeMap::len()](std::collections::BTreeMap::len()).
+    fn len(&self) -> usize {
+        Self::len(self)
+    }
+
+    /// Wraps [BTreeMap::contains_key()](std::collections::BTreeMap::contains_key()).
+    fn contains_key(&self, key: &Key) -> bool {
+        Self::contains_key(self, key)
+    }
+
     /// Wraps [BTreeMap::iter()](std::collections::BTreeMap::iter()).
     ///
     /// Iterates over key->group mappings in sort order by `key`. (Groups still preserve insertion
@@ -60,6 +112,29 @@ where
     fn iter(&'s self) -> Self::Iter {
         Self::iter(self)
     }
+
+    /// Moves every group out of the map, leaving it empty, and iterates over them in sort order by
+    /// `key`.
+    fn drain(&mut self) -> Self::IntoIter {
+        IntoIterator::into_iter(mem::take(self))
+    }
+}
+
+impl<'e, Key, Value> GroupEntry<'e, Value, Vec<Value>> for btree_map::Entry<'e, Key, Vec<Value>>
+where
+    Key: Ord,
+{
+    fn push(self, value: Value) {
+        self.or_default().push(value);
+    }
+
+    /// Wraps [Entry::or_insert_with()](std::collections::btree_map::Entry::or_insert_with()).
+    fn or_insert_with<F>(self, default: F) -> &'e mut Vec<Value>
+    where
+        F: FnOnce() -> Vec<Value>,
+    {
+        Self::or_insert_with(self, default)
+    }
 }
 
 #[cfg(test)]
@@ -68,7 +143,7 @@ mod tests {
     use crate::grouped_collections::test_helpers::*;
 
     #[test]
-    fn add_get_iter() {
+    fn add_get_remove_iter() {
         verify_grouped_collection(BTreeMap::new());
     }
 }
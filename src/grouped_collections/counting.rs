@@ -0,0 +1,157 @@
+//! Provides [CountingCollection], a [GroupedCollection] that counts values instead of storing them.
+
+use crate::grouped_collections::{GroupEntry, GroupedCollection};
+use std::collections::{btree_map, BTreeMap};
+use std::marker::PhantomData;
+use std::mem;
+
+/// A [GroupedCollection] that keeps only the number of values added to each group, discarding the
+/// values themselves.
+///
+/// Each group's `List` is its count, so memory use is proportional to the number of groups rather
+/// than the size of the input. This makes it possible to tally inputs far too large to hold in
+/// memory. Groups are kept in sort order by key, as with [BTreeMap].
+///
+/// `Value` is the type of value that the collection counts. It isn't stored; it only pins down
+/// which [GroupedCollection] the collection is.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::grouped_collections::{CountingCollection, GroupedCollection};
+///
+/// let mut counts = CountingCollection::new();
+/// for word in ["apple", "avocado", "banana"] {
+///     counts.add(word.chars().next().unwrap(), word);
+/// }
+///
+/// assert_eq!(counts.get(&'a'), Some(&2));
+/// assert_eq!(counts.get(&'b'), Some(&1));
+/// assert_eq!(counts.get(&'c'), None);
+/// assert_eq!(counts.total(), 3);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CountingCollection<Key, Value> {
+    counts: BTreeMap<Key, usize>,
+    values: PhantomData<fn(Value)>,
+}
+
+impl<Key: Ord, Value> CountingCollection<Key, Value> {
+    pub fn new() -> Self {
+        CountingCollection {
+            counts: BTreeMap::new(),
+            values: PhantomData,
+        }
+    }
+
+    /// Returns the total number of values added to all groups.
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+}
+
+impl<Key: Ord, Value> Default for CountingCollection<Key, Value> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'s, Key, Value> GroupedCollection<'s, Key, Value, usize> for CountingCollection<Key, Value>
+where
+    Self: 's,
+    Key: Ord + 's,
+    Value: 's,
+{
+    type Iter = btree_map::Iter<'s, Key, usize>;
+    type IntoIter = btree_map::IntoIter<Key, usize>;
+    type Entry = btree_map::Entry<'s, Key, usize>;
+
+    /// Counts `value` toward the group at `key`, then drops it.
+    fn add(&mut self, key: Key, _value: Value) {
+        *self.counts.entry(key).or_insert(0) += 1;
+    }
+
+    fn entry(&'s mut self, key: Key) -> Self::Entry {
+        self.counts.entry(key)
+    }
+
+    fn get(&'s self, key: &Key) -> Option<&'s usize> {
+        self.counts.get(key)
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<usize> {
+        self.counts.remove(key)
+    }
+
+    fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&Key, &mut usize) -> bool,
+    {
+        self.counts.retain(keep)
+    }
+
+    fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    fn contains_key(&self, key: &Key) -> bool {
+        self.counts.contains_key(key)
+    }
+
+    /// Iterates over key->count mappings in sort order by `key`.
+    fn iter(&'s self) -> Self::Iter {
+        self.counts.iter()
+    }
+
+    /// Moves every count out of the collection, in sort order by `key`.
+    fn drain(&mut self) -> Self::IntoIter {
+        mem::take(&mut self.counts).into_iter()
+    }
+}
+
+impl<'e, Key: Ord, Value> GroupEntry<'e, Value, usize> for btree_map::Entry<'e, Key, usize> {
+    /// Counts `value` toward the group, then drops it.
+    fn push(self, _value: Value) {
+        *self.or_insert(0) += 1;
+    }
+
+    fn or_insert_with<F>(self, default: F) -> &'e mut usize
+    where
+        F: FnOnce() -> usize,
+    {
+        Self::or_insert_with(self, default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_values_per_key() {
+        let mut counts = CountingCollection::new();
+        for (key, value) in [(2, "b"), (1, "a"), (2, "c"), (2, "b")] {
+            counts.add(key, value);
+        }
+        assert_eq!(counts.iter().collect::<Vec<_>>(), vec![(&1, &1), (&2, &3)]);
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts.total(), 4);
+    }
+
+    #[test]
+    fn removes_and_retains_groups() {
+        let mut counts = CountingCollection::new();
+        for key in ["a", "b", "b", "c", "c", "c"] {
+            counts.add(key, ());
+        }
+
+        counts.retain(|_, count| *count > 1);
+        assert!(!counts.contains_key(&"a"));
+        assert_eq!(counts.remove(&"b"), Some(2));
+        assert_eq!(counts.keys().collect::<Vec<_>>(), vec![&"c"]);
+        counts.entry("c").push(());
+        *counts.entry("d").or_insert_with(|| 0) += 2;
+        assert_eq!(counts.drain().collect::<Vec<_>>(), vec![("c", 4), ("d", 2)]);
+        assert!(counts.is_empty());
+    }
+}
@@ -0,0 +1,491 @@
+//! Provides [DiskMap], a [GroupedCollection] that spills values to a temporary file when they
+//! outgrow a memory budget.
+
+use crate::grouped_collections::{GroupEntry, GroupedCollection};
+use std::cmp::Ordering;
+use std::collections::{btree_map, BTreeMap};
+use std::convert::TryInto;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{self, AtomicUsize};
+use std::vec;
+
+/// The memory budget that [DiskMap::new] uses: 64 MiB of buffered values.
+pub const DEFAULT_BUDGET: usize = 64 * 1024 * 1024;
+
+/// A [GroupedCollection] of strings that keeps only a bounded amount of its values in memory,
+/// writing the rest to a temporary file, so that inputs far larger than memory can be grouped.
+///
+/// Values are buffered in memory until their total size exceeds the collection's budget. Then
+/// every buffered value is appended to the spill file, which is created on the first spill and
+/// deleted when the collection is dropped. Keys always stay in memory, as with
+/// [CountingCollection](crate::grouped_collections::CountingCollection), so memory use is
+/// proportional to the number of groups plus the budget.
+///
+/// Groups are kept in sort order by key, as with [BTreeMap]. To read groups in full, use
+/// [DiskMap::into_groups], which loads one group at a time and reports errors reading the spill
+/// file. The [GroupedCollection] methods that return references, [get](GroupedCollection::get),
+/// [iter](GroupedCollection::iter), and [entry](GroupedCollection::entry), only see the values
+/// that are still in memory, and [retain](GroupedCollection::retain) can only filter those.
+/// Values pushed through a reference from an entry aren't counted toward the budget.
+///
+/// [add](GroupedCollection::add) can't return errors, so if a spill fails, the values stay in
+/// memory and the error is kept for [DiskMap::into_groups] to return.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::grouped_collections::{DiskMap, GroupedCollection};
+///
+/// // A tiny budget, so that almost every value is spilled.
+/// let mut map = DiskMap::with_budget(4);
+/// for word in ["apple", "banana", "avocado"] {
+///     map.add(word[..1].to_string(), word.to_string());
+/// }
+///
+/// let groups: Vec<_> = map.into_groups().unwrap().map(Result::unwrap).collect();
+/// assert_eq!(
+///     groups,
+///     vec![
+///         ("a".to_string(), vec!["apple".to_string(), "avocado".to_string()]),
+///         ("b".to_string(), vec!["banana".to_string()]),
+///     ]
+/// );
+/// ```
+#[derive(Debug)]
+pub struct DiskMap {
+    groups: BTreeMap<String, Group>,
+
+    // The total length of the buffered values, in bytes, and the most it may reach before they're
+    // spilled.
+    buffered: usize,
+    budget: usize,
+
+    // Created by the first spill.
+    spill: Option<Spill>,
+
+    // The first error that a spill returned, if any.
+    error: Option<io::Error>,
+}
+
+// A group's values: those spilled to disk first, in order, then those still in memory.
+#[derive(Debug, Default)]
+struct Group {
+    // The offset and length, in bytes, of each run of this group's values in the spill file.
+    extents: Vec<(u64, u64)>,
+
+    buffered: Vec<String>,
+}
+
+// The spill file, which is removed when it's dropped.
+#[derive(Debug)]
+struct Spill {
+    file: File,
+    path: PathBuf,
+    len: u64,
+}
+
+impl Spill {
+    fn create() -> io::Result<Self> {
+        // Distinguishes the spill files of several DiskMaps in the same process.
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "groupby-spill-{}-{}",
+            process::id(),
+            COUNT.fetch_add(1, atomic::Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Spill { file, path, len: 0 })
+    }
+
+    // Appends bytes to the end of the file and returns their extent.
+    fn append(&mut self, bytes: &[u8]) -> io::Result<(u64, u64)> {
+        self.file.seek(SeekFrom::Start(self.len))?;
+        self.file.write_all(bytes)?;
+        let extent = (self.len, bytes.len() as u64);
+        self.len += bytes.len() as u64;
+        Ok(extent)
+    }
+
+    // Reads the values in an extent, appending them to values.
+    fn read(&mut self, (offset, len): (u64, u64), values: &mut Vec<String>) -> io::Result<()> {
+        let mut bytes = vec![0; len as usize];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut bytes)?;
+
+        // Each value is its length, as 8 little-endian bytes, then its contents.
+        let mut rest = &bytes[..];
+        while !rest.is_empty() {
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, "corrupt spill file");
+            if rest.len() < 8 {
+                return Err(invalid());
+            }
+            let (length, tail) = rest.split_at(8);
+            let length = u64::from_le_bytes(length.try_into().unwrap()) as usize;
+            if tail.len() < length {
+                return Err(invalid());
+            }
+            let (value, tail) = tail.split_at(length);
+            values.push(String::from_utf8(value.to_vec()).map_err(|_| invalid())?);
+            rest = tail;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl DiskMap {
+    /// Creates an empty map with the [DEFAULT_BUDGET].
+    pub fn new() -> Self {
+        Self::with_budget(DEFAULT_BUDGET)
+    }
+
+    /// Creates an empty map that spills its values once they total more than `budget` bytes.
+    pub fn with_budget(budget: usize) -> Self {
+        DiskMap {
+            groups: BTreeMap::new(),
+            buffered: 0,
+            budget,
+            spill: None,
+            error: None,
+        }
+    }
+
+    /// Returns true if any values have been written to disk.
+    pub fn spilled(&self) -> bool {
+        self.spill.is_some()
+    }
+
+    // Writes every buffered value to the spill file, creating it if needed. On failure, the
+    // values that weren't written stay in memory.
+    fn spill(&mut self) -> io::Result<()> {
+        if self.spill.is_none() {
+            self.spill = Some(Spill::create()?);
+        }
+        let spill = self.spill.as_mut().unwrap();
+        let mut bytes = vec![];
+        for group in self.groups.values_mut() {
+            if group.buffered.is_empty() {
+                continue;
+            }
+            bytes.clear();
+            for value in &group.buffered {
+                bytes.extend_from_slice(&(value.len() as u64).to_le_bytes());
+                bytes.extend_from_slice(value.as_bytes());
+            }
+            group.extents.push(spill.append(&bytes)?);
+            self.buffered -= group.buffered.iter().map(String::len).sum::<usize>();
+            group.buffered = vec![];
+        }
+        Ok(())
+    }
+
+    // Returns a group's values in full, reading any that were spilled.
+    fn load(spill: &mut Option<Spill>, group: Group) -> io::Result<Vec<String>> {
+        let mut values = vec![];
+        if let Some(spill) = spill {
+            for extent in group.extents {
+                spill.read(extent, &mut values)?;
+            }
+        }
+        values.extend(group.buffered);
+        Ok(values)
+    }
+
+    /// Consumes the map and returns an iterator over its groups, in sort order by key, that reads
+    /// each group from disk only when it's reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error that writing to the spill file returned, if any. Each group is an
+    /// error instead if it can't be read back.
+    pub fn into_groups(self) -> io::Result<Groups> {
+        self.into_groups_by(Ord::cmp)
+    }
+
+    /// Like [DiskMap::into_groups], but returns the groups in the order that `compare` gives their
+    /// keys. Only the keys are sorted, so no values are read back any sooner.
+    ///
+    /// ```
+    /// use groupby::grouped_collections::{DiskMap, GroupedCollection};
+    ///
+    /// let mut map = DiskMap::with_budget(4);
+    /// for word in ["apple", "banana", "avocado"] {
+    ///     map.add(word[..1].to_string(), word.to_string());
+    /// }
+    ///
+    /// let groups = map.into_groups_by(|a, b| b.cmp(a)).unwrap();
+    /// let keys: Vec<_> = groups.map(|group| group.unwrap().0).collect();
+    /// assert_eq!(keys, vec!["b", "a"]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The same as [DiskMap::into_groups].
+    pub fn into_groups_by<F>(mut self, mut compare: F) -> io::Result<Groups>
+    where
+        F: FnMut(&str, &str) -> Ordering,
+    {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        let mut groups: Vec<_> = mem::take(&mut self.groups).into_iter().collect();
+        groups.sort_by(|(a, _), (b, _)| compare(a, b));
+        Ok(Groups {
+            groups: groups.into_iter(),
+            spill: self.spill.take(),
+        })
+    }
+}
+
+impl Default for DiskMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An iterator over the groups of a [DiskMap], returned by [DiskMap::into_groups].
+pub struct Groups {
+    groups: vec::IntoIter<(String, Group)>,
+    spill: Option<Spill>,
+}
+
+impl Iterator for Groups {
+    type Item = io::Result<(String, Vec<String>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, group) = self.groups.next()?;
+        Some(DiskMap::load(&mut self.spill, group).map(|values| (key, values)))
+    }
+}
+
+/// The iterator that [DiskMap]'s [drain](GroupedCollection::drain) returns. Like [Groups], but
+/// panics if a group can't be read back.
+pub struct Drain(Groups);
+
+impl Iterator for Drain {
+    type Item = (String, Vec<String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|group| group.expect("couldn't read DiskMap's spill file"))
+    }
+}
+
+/// The iterator that [DiskMap]'s [iter](GroupedCollection::iter) returns, over the values of each
+/// group that are still in memory.
+pub struct Iter<'s>(btree_map::Iter<'s, String, Group>);
+
+impl<'s> Iterator for Iter<'s> {
+    type Item = (&'s String, &'s Vec<String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, group)| (key, &group.buffered))
+    }
+}
+
+/// The [GroupEntry] that [DiskMap]'s [entry](GroupedCollection::entry) returns.
+pub struct DiskEntry<'s> {
+    map: &'s mut DiskMap,
+    key: String,
+}
+
+impl<'s> GroupEntry<'s, String, Vec<String>> for DiskEntry<'s> {
+    /// Adds `value` as [add](GroupedCollection::add) would, spilling if needed.
+    fn push(self, value: String) {
+        self.map.add(self.key, value);
+    }
+
+    /// Returns the group's values that are still in memory, creating the group with `default`
+    /// values if it doesn't exist.
+    fn or_insert_with<F>(self, default: F) -> &'s mut Vec<String>
+    where
+        F: FnOnce() -> Vec<String>,
+    {
+        &mut self
+            .map
+            .groups
+            .entry(self.key)
+            .or_insert_with(|| Group {
+                extents: vec![],
+                buffered: default(),
+            })
+            .buffered
+    }
+}
+
+impl<'s> GroupedCollection<'s, String, String, Vec<String>> for DiskMap {
+    type Iter = Iter<'s>;
+    type IntoIter = Drain;
+    type Entry = DiskEntry<'s>;
+
+    /// Adds `value` to the group at `key`, then spills every buffered value to disk if they've
+    /// outgrown the budget.
+    fn add(&mut self, key: String, value: String) {
+        self.buffered += value.len();
+        self.groups.entry(key).or_default().buffered.push(value);
+        if self.buffered > self.budget && self.error.is_none() {
+            if let Err(e) = self.spill() {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn entry(&'s mut self, key: String) -> Self::Entry {
+        DiskEntry { map: self, key }
+    }
+
+    /// Returns the group's values that are still in memory, if the group exists.
+    fn get(&'s self, key: &String) -> Option<&'s Vec<String>> {
+        self.groups.get(key).map(|group| &group.buffered)
+    }
+
+    /// Removes the group at `key` and returns every one of its values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the group's spilled values can't be read back.
+    fn remove(&mut self, key: &String) -> Option<Vec<String>> {
+        let group = self.groups.remove(key)?;
+        self.buffered -= group.buffered.iter().map(String::len).sum::<usize>();
+        Some(Self::load(&mut self.spill, group).expect("couldn't read DiskMap's spill file"))
+    }
+
+    /// Filters groups by the values that are still in memory.
+    fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&String, &mut Vec<String>) -> bool,
+    {
+        let buffered = &mut self.buffered;
+        self.groups.retain(|key, group| {
+            *buffered -= group.buffered.iter().map(String::len).sum::<usize>();
+            let kept = keep(key, &mut group.buffered);
+            if kept {
+                *buffered += group.buffered.iter().map(String::len).sum::<usize>();
+            }
+            kept
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    fn contains_key(&self, key: &String) -> bool {
+        self.groups.contains_key(key)
+    }
+
+    /// Iterates over each group's values that are still in memory, in sort order by key.
+    fn iter(&'s self) -> Self::Iter {
+        Iter(self.groups.iter())
+    }
+
+    /// Moves every group out of the map, in sort order by key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any spilled values can't be read back. Use [DiskMap::into_groups] to handle
+    /// errors instead.
+    fn drain(&mut self) -> Self::IntoIter {
+        self.buffered = 0;
+        Drain(Groups {
+            groups: mem::take(&mut self.groups)
+                .into_iter()
+                .collect::<Vec<_>>()
+                .into_iter(),
+            spill: self.spill.take(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(map: DiskMap) -> Vec<(String, Vec<String>)> {
+        map.into_groups().unwrap().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn keeps_values_in_memory_within_budget() {
+        let mut map = DiskMap::with_budget(100);
+        map.add("a".to_string(), "apple".to_string());
+        assert!(!map.spilled());
+        assert_eq!(map.get(&"a".to_string()), Some(&vec!["apple".to_string()]));
+    }
+
+    #[test]
+    fn preserves_value_order_across_spills() {
+        let mut map = DiskMap::with_budget(10);
+        let values = ["apple", "avocado", "", "a\nb\0c", "apricot", "acai"];
+        for value in values {
+            map.add("a".to_string(), value.to_string());
+            map.add("b".to_string(), "banana".to_string());
+        }
+        assert!(map.spilled());
+        assert!(map.buffered <= 10 + "banana".len());
+
+        let groups = groups(map);
+        assert_eq!(groups[0].1, values);
+        assert_eq!(groups[1].1, vec!["banana"; values.len()]);
+    }
+
+    #[test]
+    fn removes_spilled_groups_in_full() {
+        let mut map = DiskMap::with_budget(0);
+        map.add("a".to_string(), "apple".to_string());
+        map.add("a".to_string(), "avocado".to_string());
+        assert_eq!(map.get(&"a".to_string()), Some(&vec![]));
+        assert_eq!(
+            map.remove(&"a".to_string()),
+            Some(vec!["apple".to_string(), "avocado".to_string()])
+        );
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn reads_spilled_groups_in_the_given_key_order() {
+        use crate::command_line::key_order::KeyOrder;
+
+        let mut map = DiskMap::with_budget(0);
+        for value in ["ten chars!", "a", "nine char"] {
+            map.add(value.len().to_string(), value.to_string());
+        }
+        let groups = map
+            .into_groups_by(|a, b| KeyOrder::Natural.compare(a, b))
+            .unwrap();
+        let groups: Vec<_> = groups.map(Result::unwrap).collect();
+        assert_eq!(
+            groups,
+            vec![
+                ("1".to_string(), vec!["a".to_string()]),
+                ("9".to_string(), vec!["nine char".to_string()]),
+                ("10".to_string(), vec!["ten chars!".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn deletes_its_spill_file_when_dropped() {
+        let mut map = DiskMap::with_budget(0);
+        map.add("a".to_string(), "apple".to_string());
+        let path = map.spill.as_ref().unwrap().path.clone();
+        assert!(path.exists());
+        drop(map);
+        assert!(!path.exists());
+    }
+}
@@ -1,24 +1,53 @@
 #![cfg(test)]
 
-use crate::grouped_collections::GroupedCollection;
+use crate::grouped_collections::{GroupEntry, GroupedCollection};
+use std::iter;
 
 // A test double that records calls to GroupedCollection::add().
+#[derive(Default)]
 pub struct FakeMap {
     calls: Vec<String>,
 }
 
 impl<'s> GroupedCollection<'s, String, String, Vec<String>> for FakeMap {
     type Iter = FakeMapIter<'s>;
+    type IntoIter = iter::Empty<(String, Vec<String>)>;
+    type Entry = FakeEntry<'s>;
 
     // Record the key so we can check which grouper was used.
     fn add(&mut self, key: String, value: String) {
         self.calls.push(format!("{}:{}", key, value));
     }
 
+    fn entry(&'s mut self, key: String) -> Self::Entry {
+        FakeEntry {
+            calls: &mut self.calls,
+            key,
+        }
+    }
+
     fn get(&'s self, _key: &String) -> Option<&'s Vec<String>> {
         None
     }
 
+    fn remove(&mut self, _key: &String) -> Option<Vec<String>> {
+        None
+    }
+
+    fn retain<F>(&mut self, _keep: F)
+    where
+        F: FnMut(&String, &mut Vec<String>) -> bool,
+    {
+    }
+
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn contains_key(&self, _key: &String) -> bool {
+        false
+    }
+
     fn iter(&'s self) -> Self::Iter {
         FakeMapIter {
             _keys: "".to_string(),
@@ -26,6 +55,10 @@ impl<'s> GroupedCollection<'s, String, String, Vec<String>> for FakeMap {
             _fake_ref: &4,
         }
     }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        iter::empty()
+    }
 }
 
 impl FakeMap {
@@ -52,3 +85,22 @@ impl<'s> Iterator for FakeMapIter<'s> {
         None
     }
 }
+
+// Records a pushed value as though it were added with GroupedCollection::add().
+pub struct FakeEntry<'s> {
+    calls: &'s mut Vec<String>,
+    key: String,
+}
+
+impl<'s> GroupEntry<'s, String, Vec<String>> for FakeEntry<'s> {
+    fn push(self, value: String) {
+        self.calls.push(format!("{}:{}", self.key, value));
+    }
+
+    fn or_insert_with<F>(self, _default: F) -> &'s mut Vec<String>
+    where
+        F: FnOnce() -> Vec<String>,
+    {
+        panic!("FakeMap doesn't store groups");
+    }
+}
@@ -0,0 +1,76 @@
+//! Provides [GroupByKey], which groups the items of any iterator in one call.
+
+use crate::grouped_collections::GroupedCollection;
+use std::collections::BTreeMap;
+
+/// Extends every [Iterator] with methods that group its items by a key computed from each item.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::grouped_collections::{CountingCollection, GroupByKey, GroupedCollection};
+/// use std::collections::BTreeMap;
+///
+/// let words = ["apple", "banana", "avocado"];
+///
+/// let map = words.iter().group_by_key(|word| word.len());
+/// assert_eq!(map.get(&6), Some(&vec![&"banana"]));
+///
+/// let counts = words
+///     .into_iter()
+///     .group_by_into(CountingCollection::new(), |word| word.chars().next());
+/// assert_eq!(counts.get(&Some('a')), Some(&2));
+/// ```
+pub trait GroupByKey: Iterator + Sized {
+    /// Groups the items into a [BTreeMap] by the key that `key` returns for each item. Each group
+    /// keeps its items in iteration order.
+    fn group_by_key<Key, F>(self, key: F) -> BTreeMap<Key, Vec<Self::Item>>
+    where
+        Key: Ord,
+        F: FnMut(&Self::Item) -> Key,
+    {
+        self.group_by_into(BTreeMap::new(), key)
+    }
+
+    /// Adds the items to `map`, which may already hold groups, by the key that `key` returns for
+    /// each item, and returns the map.
+    fn group_by_into<'m, Map, Key, List, F>(self, mut map: Map, mut key: F) -> Map
+    where
+        Map: GroupedCollection<'m, Key, Self::Item, List>,
+        Key: 'm,
+        Self::Item: 'm,
+        List: 'm,
+        F: FnMut(&Self::Item) -> Key,
+    {
+        for item in self {
+            map.add(key(&item), item);
+        }
+        map
+    }
+}
+
+impl<I: Iterator> GroupByKey for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn groups_in_iteration_order() {
+        let map = (1..=6).group_by_key(|n| n % 3);
+        assert_eq!(
+            map,
+            BTreeMap::from([(0, vec![3, 6]), (1, vec![1, 4]), (2, vec![2, 5])])
+        );
+    }
+
+    #[test]
+    fn adds_to_existing_groups() {
+        let mut map = HashMap::new();
+        map.add(true, 0);
+        let map = (1..4).group_by_into(map, |n| n % 2 == 0);
+        assert_eq!(map.get(&true), Some(&vec![0, 2]));
+        assert_eq!(map.get(&false), Some(&vec![1, 3]));
+    }
+}
@@ -1,5 +1,9 @@
 //! Provides the [GroupedCollection] trait.
 
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::iter;
+
 // TODO Diagnose & hopefully fix issue lifetime issue on Box<GroupedCollection>.iter().
 //
 // The example below doesn't work, and I don't quite know why. I've done everything I can at
@@ -36,6 +40,10 @@
 // assert_eq!(map.get(&true).unwrap(), &vec![1, 2]);
 // ```
 
+/// The iterator that [GroupedCollection::keys] returns, given the collection's
+/// [Iter](GroupedCollection::Iter) type.
+pub type Keys<'s, Iter, Key, List> = iter::Map<Iter, fn((&'s Key, &'s List)) -> &'s Key>;
+
 /// Provides a common interface over collections that map keys to lists of values, e.g.
 /// `BTreeMap<K, Vec<V>>`
 ///
@@ -103,6 +111,13 @@ pub trait GroupedCollection<'s, Key: 's, Value: 's, List: 's> {
     /// The type of iterator that [iter](GroupedCollection::iter) returns.
     type Iter: Iterator<Item = (&'s Key, &'s List)>;
 
+    /// The type of iterator that [into_iter](GroupedCollection::into_iter) and
+    /// [drain](GroupedCollection::drain) return, which yields groups by value.
+    type IntoIter: Iterator<Item = (Key, List)>;
+
+    /// The type of entry that [entry](GroupedCollection::entry) returns.
+    type Entry: GroupEntry<'s, Value, List>;
+
     /// Adds `value` to the list at `key`.
     ///
     /// If `key` is not found, adds `key` to the mapping with a new `List` containing `value`.
@@ -113,16 +128,219 @@ pub trait GroupedCollection<'s, Key: 's, Value: 's, List: 's> {
     /// ```
     /// # use groupby::grouped_collections::GroupedCollection;
     /// # use std::collections::{hash_map, HashMap};
-    /// let map: &mut dyn GroupedCollection<bool, usize, Vec<usize>, Iter = hash_map::Iter<bool, Vec<usize>>> = &mut HashMap::new();
+    /// let map: &mut dyn GroupedCollection<
+    ///     bool,
+    ///     usize,
+    ///     Vec<usize>,
+    ///     Iter = hash_map::Iter<bool, Vec<usize>>,
+    ///     IntoIter = hash_map::IntoIter<bool, Vec<usize>>,
+    ///     Entry = hash_map::Entry<bool, Vec<usize>>,
+    /// > = &mut HashMap::new();
     /// map.add(true, 1);
     /// map.add(true, 2);
     /// assert_eq!(map.get(&true).unwrap(), &vec![1, 2]);
     /// ```
     fn add(&mut self, key: Key, value: Value);
 
+    /// Appends the groups of `other`, e.g. another collection of the same type, group by group:
+    /// each value is added to the group with the same key, after the values already there.
+    ///
+    /// This lets you group parts of the input separately, e.g. on separate threads, and then
+    /// combine the results.
+    ///
+    /// The default implementation calls [add](GroupedCollection::add) once per value; the
+    /// implementations for [BTreeMap](std::collections::BTreeMap) and
+    /// [HashMap](std::collections::HashMap) move whole groups at once.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::BTreeMap;
+    /// let mut left: BTreeMap<char, Vec<&str>> = BTreeMap::new();
+    /// left.add('a', "apple");
+    /// left.add('b', "banana");
+    ///
+    /// let mut right: BTreeMap<char, Vec<&str>> = BTreeMap::new();
+    /// right.add('a', "avocado");
+    /// right.add('c', "cherry");
+    ///
+    /// left.merge(right);
+    /// assert_eq!(left.get(&'a'), Some(&vec!["apple", "avocado"]));
+    /// assert_eq!(left.get(&'b'), Some(&vec!["banana"]));
+    /// assert_eq!(left.get(&'c'), Some(&vec!["cherry"]));
+    /// ```
+    fn merge<Other>(&mut self, other: Other)
+    where
+        Self: Sized,
+        Other: IntoIterator<Item = (Key, List)>,
+        Key: Clone,
+        List: IntoIterator<Item = Value>,
+    {
+        for (key, list) in other {
+            for value in list {
+                self.add(key.clone(), value);
+            }
+        }
+    }
+
+    /// Returns the [GroupEntry] for the group at `key`, which may not exist yet, so that you can
+    /// add to or change the group in place with a single lookup.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::{GroupedCollection, GroupEntry};
+    /// # use std::collections::BTreeMap;
+    /// let mut map: BTreeMap<char, Vec<&str>> = BTreeMap::new();
+    /// GroupedCollection::entry(&mut map, 'a').push("apple");
+    /// GroupedCollection::entry(&mut map, 'a').push("avocado");
+    ///
+    /// let group = GroupedCollection::entry(&mut map, 'a').or_insert_with(Vec::new);
+    /// group.reverse();
+    /// assert_eq!(map.get(&'a'), Some(&vec!["avocado", "apple"]));
+    /// ```
+    fn entry(&'s mut self, key: Key) -> Self::Entry;
+
     /// Retrieves the group (i.e. `List`) of values corresponding to `key`, if any.
     fn get(&'s self, key: &Key) -> Option<&'s List>;
 
+    /// Removes the group at `key` from the collection and returns it, if any.
+    ///
+    /// This lets you consume groups one at a time after building them, without cloning them.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::BTreeMap;
+    /// let mut map: BTreeMap<bool, Vec<usize>> = BTreeMap::new();
+    /// map.add(true, 1);
+    /// map.add(true, 2);
+    /// assert_eq!(GroupedCollection::remove(&mut map, &true), Some(vec![1, 2]));
+    /// assert_eq!(GroupedCollection::remove(&mut map, &true), None);
+    /// ```
+    fn remove(&mut self, key: &Key) -> Option<List>;
+
+    /// Removes the group at `key` from the collection and returns it, or returns an empty group if
+    /// there is none.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::HashMap;
+    /// let mut map: HashMap<bool, Vec<usize>> = HashMap::new();
+    /// map.add(true, 1);
+    /// assert_eq!(map.take(&true), vec![1]);
+    /// assert_eq!(map.take(&true), vec![]);
+    /// ```
+    fn take(&mut self, key: &Key) -> List
+    where
+        List: Default,
+    {
+        self.remove(key).unwrap_or_default()
+    }
+
+    /// Keeps only the groups for which `keep` returns true, removing the rest in place.
+    ///
+    /// `keep` receives each group's key and a mutable reference to the group, so it may also
+    /// change the groups that it keeps.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::BTreeMap;
+    /// let mut map: BTreeMap<char, Vec<&str>> = BTreeMap::new();
+    /// for word in ["apple", "avocado", "banana", "cherry", "cranberry"] {
+    ///     map.add(word.chars().next().unwrap(), word);
+    /// }
+    ///
+    /// GroupedCollection::retain(&mut map, |_, group| group.len() > 1);
+    /// assert_eq!(GroupedCollection::keys(&map).collect::<Vec<_>>(), vec![&'a', &'c']);
+    ///
+    /// GroupedCollection::retain(&mut map, |key, _| *key != 'a');
+    /// assert_eq!(GroupedCollection::keys(&map).collect::<Vec<_>>(), vec![&'c']);
+    /// ```
+    fn retain<F>(&mut self, keep: F)
+    where
+        Self: Sized,
+        F: FnMut(&Key, &mut List) -> bool;
+
+    /// Removes duplicate values from each group, keeping the first occurrence of each, and returns
+    /// the number of values removed.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::BTreeMap;
+    /// let mut map: BTreeMap<char, Vec<&str>> = BTreeMap::new();
+    /// for word in ["bob", "alice", "bill", "alice", "bob", "alice"] {
+    ///     map.add(word.chars().next().unwrap(), word);
+    /// }
+    ///
+    /// assert_eq!(map.dedup_values(), 3);
+    /// assert_eq!(map.get(&'a'), Some(&vec!["alice"]));
+    /// assert_eq!(map.get(&'b'), Some(&vec!["bob", "bill"]));
+    /// ```
+    fn dedup_values(&mut self) -> usize
+    where
+        Self: Sized,
+        List: AsMut<Vec<Value>>,
+        Value: Eq + Hash,
+    {
+        let mut removed = 0;
+        self.retain(|_, group| {
+            removed += dedup(group.as_mut());
+            true
+        });
+        removed
+    }
+
+    /// Returns the number of groups (not values) in the collection.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::BTreeMap;
+    /// let mut map: BTreeMap<bool, Vec<usize>> = BTreeMap::new();
+    /// map.add(true, 1);
+    /// map.add(true, 2);
+    /// map.add(false, 3);
+    /// assert_eq!(GroupedCollection::len(&map), 2);
+    /// ```
+    fn len(&self) -> usize;
+
+    /// Returns true if the collection has no groups.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::HashMap;
+    /// let mut map: HashMap<bool, Vec<usize>> = HashMap::new();
+    /// assert!(GroupedCollection::is_empty(&map));
+    /// map.add(true, 1);
+    /// assert!(!GroupedCollection::is_empty(&map));
+    /// ```
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the collection has a group at `key`.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::HashMap;
+    /// let mut map: HashMap<bool, Vec<usize>> = HashMap::new();
+    /// map.add(true, 1);
+    /// assert!(GroupedCollection::contains_key(&map, &true));
+    /// assert!(!GroupedCollection::contains_key(&map, &false));
+    /// ```
+    fn contains_key(&self, key: &Key) -> bool;
+
+    /// Returns an iterator over the keys of the groups, in the same order as
+    /// [iter](GroupedCollection::iter).
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::BTreeMap;
+    /// let mut map: BTreeMap<u8, Vec<char>> = BTreeMap::new();
+    /// map.add(2, 'b');
+    /// map.add(1, 'a');
+    /// assert_eq!(GroupedCollection::keys(&map).collect::<Vec<_>>(), vec![&1, &2]);
+    /// ```
+    fn keys(&'s self) -> Keys<'s, Self::Iter, Key, List> {
+        self.iter().map(|(key, _)| key)
+    }
+
     /// Returns an iterator over key->group mappings.
     ///
     /// The order in which the iterator returns the mappings depends on the implementor; see the
@@ -140,4 +358,149 @@ pub trait GroupedCollection<'s, Key: 's, Value: 's, List: 's> {
     /// assert_eq!(group, &vec![1, 2]);
     /// ```
     fn iter(&'s self) -> Self::Iter;
+
+    /// Consumes the collection, returning an iterator over its key->group mappings by value, in
+    /// the same order as [iter](GroupedCollection::iter).
+    ///
+    /// This lets you move whole groups elsewhere, e.g. into worker threads, without cloning them.
+    /// Since [BTreeMap](std::collections::BTreeMap) and [HashMap](std::collections::HashMap) also
+    /// implement [IntoIterator], call this method by its full path on those types.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::BTreeMap;
+    /// let mut map: BTreeMap<bool, Vec<usize>> = BTreeMap::new();
+    /// map.add(true, 1);
+    /// map.add(false, 2);
+    /// let groups: Vec<(bool, Vec<usize>)> = GroupedCollection::into_iter(map).collect();
+    /// assert_eq!(groups, vec![(false, vec![2]), (true, vec![1])]);
+    /// ```
+    fn into_iter(mut self) -> Self::IntoIter
+    where
+        Self: Sized,
+    {
+        self.drain()
+    }
+
+    /// Removes every group from the collection and returns an iterator over them by value, in the
+    /// same order as [iter](GroupedCollection::iter). The collection is left empty but usable.
+    ///
+    /// The groups are removed even if the iterator is dropped before it's used up.
+    ///
+    /// ```
+    /// # use groupby::grouped_collections::GroupedCollection;
+    /// # use std::collections::BTreeMap;
+    /// let mut map: BTreeMap<bool, Vec<usize>> = BTreeMap::new();
+    /// map.add(true, 1);
+    /// map.add(true, 2);
+    /// let drained: Vec<_> = GroupedCollection::drain(&mut map).collect();
+    /// assert_eq!(drained, vec![(true, vec![1, 2])]);
+    /// assert!(GroupedCollection::is_empty(&map));
+    /// ```
+    fn drain(&mut self) -> Self::IntoIter;
+}
+
+/// A single group in a [GroupedCollection], which may or may not exist yet. See
+/// [GroupedCollection::entry].
+///
+/// The [Entry](std::collections::btree_map::Entry) types of
+/// [BTreeMap](std::collections::BTreeMap) and [HashMap](std::collections::HashMap) implement this
+/// trait, so their own methods of the same names work as usual.
+pub trait GroupEntry<'e, Value, List> {
+    /// Adds `value` to the group, creating the group if it doesn't exist yet, just as
+    /// [GroupedCollection::add] would.
+    fn push(self, value: Value);
+
+    /// Returns the group, first inserting the result of `default` as the group if it doesn't
+    /// exist yet.
+    fn or_insert_with<F>(self, default: F) -> &'e mut List
+    where
+        F: FnOnce() -> List;
+}
+
+// Removes later duplicates from values, without cloning them, and returns how many it removed.
+fn dedup<Value: Eq + Hash>(values: &mut Vec<Value>) -> usize {
+    let mut seen = HashSet::with_capacity(values.len());
+    let keep: Vec<bool> = values.iter().map(|value| seen.insert(value)).collect();
+
+    let before = values.len();
+    let mut keep = keep.into_iter();
+    values.retain(|_| keep.next().unwrap());
+    before - values.len()
+}
+
+/// Lets a mutable reference stand in for the collection it refers to, e.g. so a wrapper that owns
+/// its inner collection can wrap a borrowed one instead.
+///
+/// ```
+/// use groupby::grouped_collections::GroupedCollection;
+/// use std::collections::BTreeMap;
+///
+/// fn add_one<Map>(mut map: Map)
+/// where
+///     Map: for<'s> GroupedCollection<'s, u8, u8, Vec<u8>>,
+/// {
+///     map.add(1, 1);
+/// }
+///
+/// let mut map = BTreeMap::new();
+/// add_one(&mut map);
+/// assert_eq!(map.get(&1), Some(&vec![1]));
+/// ```
+impl<'s, Key: 's, Value: 's, List: 's, Map> GroupedCollection<'s, Key, Value, List> for &mut Map
+where
+    Map: GroupedCollection<'s, Key, Value, List>,
+{
+    type Iter = Map::Iter;
+    type IntoIter = Map::IntoIter;
+    type Entry = Map::Entry;
+
+    fn add(&mut self, key: Key, value: Value) {
+        (**self).add(key, value)
+    }
+
+    fn merge<Other>(&mut self, other: Other)
+    where
+        Other: IntoIterator<Item = (Key, List)>,
+        Key: Clone,
+        List: IntoIterator<Item = Value>,
+    {
+        (**self).merge(other)
+    }
+
+    fn entry(&'s mut self, key: Key) -> Self::Entry {
+        (**self).entry(key)
+    }
+
+    fn get(&'s self, key: &Key) -> Option<&'s List> {
+        (**self).get(key)
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<List> {
+        (**self).remove(key)
+    }
+
+    fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&Key, &mut List) -> bool,
+    {
+        (**self).retain(keep)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn contains_key(&self, key: &Key) -> bool {
+        (**self).contains_key(key)
+    }
+
+    fn iter(&'s self) -> Self::Iter {
+        (**self).iter()
+    }
+
+    /// Drains the collection that `self` refers to, leaving it empty.
+    fn drain(&mut self) -> Self::IntoIter {
+        (**self).drain()
+    }
 }
@@ -1,8 +1,9 @@
 #![doc(hidden)]
 
-use crate::grouped_collections::GroupedCollection;
+use crate::grouped_collections::{GroupEntry, GroupedCollection};
 use std::collections::{hash_map, HashMap};
 use std::hash::Hash;
+use std::mem;
 
 impl<'s, Key, Value> GroupedCollection<'s, Key, Value, Vec<Value>> for HashMap<Key, Vec<Value>>
 where
@@ -10,6 +11,8 @@ where
     Key: Eq + Hash,
 {
     type Iter = hash_map::Iter<'s, Key, Vec<Value>>;
+    type IntoIter = hash_map::IntoIter<Key, Vec<Value>>;
+    type Entry = hash_map::Entry<'s, Key, Vec<Value>>;
 
     /// Adds `value` to the `Vec<Value>`  at `key` in insertion order.
     ///
@@ -33,6 +36,32 @@ where
         }
     }
 
+    /// Appends each group of `other` to the group with the same key, moving whole groups rather
+    /// than one value at a time. Like the default implementation, it skips empty groups.
+    fn merge<Other>(&mut self, other: Other)
+    where
+        Other: IntoIterator<Item = (Key, Vec<Value>)>,
+    {
+        for (key, mut values) in other {
+            if values.is_empty() {
+                continue;
+            }
+            match self.entry(key) {
+                hash_map::Entry::Occupied(mut vec) => {
+                    vec.get_mut().append(&mut values);
+                }
+                hash_map::Entry::Vacant(slot) => {
+                    slot.insert(values);
+                }
+            }
+        }
+    }
+
+    /// Wraps [HashMap::entry()](std::collections::HashMap::entry()).
+    fn entry(&'s mut self, key: Key) -> Self::Entry {
+        Self::entry(self, key)
+    }
+
     /// Wraps [HashMap::get()](std::collections::HashMap::get()).
     ///
     /// ```
@@ -46,6 +75,29 @@ where
         Self::get(self, key)
     }
 
+    /// Wraps [HashMap::remove()](std::collections::HashMap::remove()).
+    fn remove(&mut self, key: &Key) -> Option<Vec<Value>> {
+        Self::remove(self, key)
+    }
+
+    /// Wraps [HashMap::retain()](std::collections::HashMap::retain()).
+    fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&Key, &mut Vec<Value>) -> bool,
+    {
+        Self::retain(self, keep)
+    }
+
+    /// Wraps [HashMap::len()](std::collections::HashMap::len()).
+    fn len(&self) -> usize {
+        Self::len(self)
+    }
+
+    /// Wraps [HashMap::contains_key()](std::collections::HashMap::contains_key()).
+    fn contains_key(&self, key: &Key) -> bool {
+        Self::contains_key(self, key)
+    }
+
     /// Wraps [HashMap::iter()](std::collections::HashMap::iter()).
     ///
     /// Iterates over key->group mappings in arbitrary order. (Groups still preserve insertion
@@ -53,6 +105,29 @@ where
     fn iter(&'s self) -> Self::Iter {
         Self::iter(self)
     }
+
+    /// Moves every group out of the map, leaving it empty, and iterates over them in arbitrary
+    /// order.
+    fn drain(&mut self) -> Self::IntoIter {
+        IntoIterator::into_iter(mem::take(self))
+    }
+}
+
+impl<'e, Key, Value> GroupEntry<'e, Value, Vec<Value>> for hash_map::Entry<'e, Key, Vec<Value>>
+where
+    Key: Eq + Hash,
+{
+    fn push(self, value: Value) {
+        self.or_default().push(value);
+    }
+
+    /// Wraps [Entry::or_insert_with()](std::collections::hash_map::Entry::or_insert_with()).
+    fn or_insert_with<F>(self, default: F) -> &'e mut Vec<Value>
+    where
+        F: FnOnce() -> Vec<Value>,
+    {
+        Self::or_insert_with(self, default)
+    }
 }
 
 #[cfg(test)]
@@ -61,7 +136,7 @@ mod tests {
     use crate::grouped_collections::test_helpers::*;
 
     #[test]
-    fn add_get_iter() {
+    fn add_get_remove_iter() {
         verify_grouped_collection(HashMap::new());
     }
 }
@@ -0,0 +1,247 @@
+//! Provides [InternedMap], a [GroupedCollection] that stores each distinct value only once.
+
+use crate::grouped_collections::{GroupEntry, GroupedCollection};
+use std::cmp::Ordering;
+use std::collections::{btree_map, BTreeMap, HashSet};
+use std::mem;
+use std::rc::Rc;
+use std::vec;
+
+/// A [GroupedCollection] of strings that interns its values: identical values, in any group, share
+/// a single allocation.
+///
+/// Each group's `List` is a `Vec<Rc<str>>`. Adding a value that the collection has seen before
+/// stores another reference to the existing copy rather than a new one, so inputs with many
+/// repeated values, like most logs, take far less memory than with a `BTreeMap<String,
+/// Vec<String>>`. Inputs with few repeats take a little more, for the pool that finds them. Keys
+/// aren't interned, since each key is stored only once anyway. Groups are kept in sort order by
+/// key, as with [BTreeMap].
+///
+/// To read groups back as [Strings](String), use [InternedMap::into_groups], which copies out one
+/// group at a time.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::grouped_collections::{GroupedCollection, InternedMap};
+///
+/// let mut map = InternedMap::new();
+/// for line in ["GET /", "GET /", "POST /login", "GET /"] {
+///     map.add(line[..1].to_string(), line.to_string());
+/// }
+/// assert_eq!(map.distinct_values(), 2);
+///
+/// let groups: Vec<_> = map.into_groups().collect();
+/// assert_eq!(groups[0], ("G".to_string(), vec!["GET /".to_string(); 3]));
+/// assert_eq!(groups[1], ("P".to_string(), vec!["POST /login".to_string()]));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct InternedMap {
+    groups: BTreeMap<String, Vec<Rc<str>>>,
+
+    // One reference to every distinct value that's been added.
+    pool: HashSet<Rc<str>>,
+}
+
+impl InternedMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of distinct values that have been added to the collection, which is
+    /// the number of values actually stored.
+    pub fn distinct_values(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Returns the groups, in sort order by key, with each group's values copied into new
+    /// [Strings](String) only as the group is reached. The pool is dropped first, so each group's
+    /// shared values are freed once no later group refers to them.
+    pub fn into_groups(self) -> Groups {
+        self.into_groups_by(Ord::cmp)
+    }
+
+    /// Like [InternedMap::into_groups], but returns the groups in the order that `compare` gives
+    /// their keys.
+    ///
+    /// ```
+    /// use groupby::grouped_collections::{GroupedCollection, InternedMap};
+    ///
+    /// let mut map = InternedMap::new();
+    /// for line in ["GET /", "POST /login"] {
+    ///     map.add(line[..1].to_string(), line.to_string());
+    /// }
+    ///
+    /// let keys: Vec<_> = map.into_groups_by(|a, b| b.cmp(a)).map(|(key, _)| key).collect();
+    /// assert_eq!(keys, vec!["P", "G"]);
+    /// ```
+    pub fn into_groups_by<F>(self, mut compare: F) -> Groups
+    where
+        F: FnMut(&str, &str) -> Ordering,
+    {
+        let mut groups: Vec<_> = IntoIterator::into_iter(self.groups).collect();
+        groups.sort_by(|(a, _), (b, _)| compare(a, b));
+        Groups {
+            groups: groups.into_iter(),
+        }
+    }
+}
+
+// Returns the pooled copy of value, adding it to the pool if it's new.
+fn intern(pool: &mut HashSet<Rc<str>>, value: String) -> Rc<str> {
+    match pool.get(value.as_str()) {
+        Some(interned) => Rc::clone(interned),
+        None => {
+            let interned: Rc<str> = Rc::from(value);
+            pool.insert(Rc::clone(&interned));
+            interned
+        }
+    }
+}
+
+/// The iterator that [InternedMap::into_groups] returns.
+#[derive(Debug)]
+pub struct Groups {
+    groups: vec::IntoIter<(String, Vec<Rc<str>>)>,
+}
+
+impl Iterator for Groups {
+    type Item = (String, Vec<String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, values) = self.groups.next()?;
+        Some((key, values.iter().map(|value| value.to_string()).collect()))
+    }
+}
+
+/// The [GroupEntry] that [InternedMap] returns, which interns each value pushed through it.
+pub struct InternedEntry<'s> {
+    entry: btree_map::Entry<'s, String, Vec<Rc<str>>>,
+    pool: &'s mut HashSet<Rc<str>>,
+}
+
+impl<'s> GroupEntry<'s, String, Vec<Rc<str>>> for InternedEntry<'s> {
+    fn push(self, value: String) {
+        let value = intern(self.pool, value);
+        self.entry.or_default().push(value);
+    }
+
+    /// Returns the group's values. Values added through the reference aren't interned.
+    fn or_insert_with<F>(self, default: F) -> &'s mut Vec<Rc<str>>
+    where
+        F: FnOnce() -> Vec<Rc<str>>,
+    {
+        self.entry.or_insert_with(default)
+    }
+}
+
+impl<'s> GroupedCollection<'s, String, String, Vec<Rc<str>>> for InternedMap {
+    type Iter = btree_map::Iter<'s, String, Vec<Rc<str>>>;
+    type IntoIter = btree_map::IntoIter<String, Vec<Rc<str>>>;
+    type Entry = InternedEntry<'s>;
+
+    /// Adds `value` to the group at `key`, sharing the stored copy of `value` if there is one.
+    fn add(&mut self, key: String, value: String) {
+        let value = intern(&mut self.pool, value);
+        self.groups.entry(key).or_default().push(value);
+    }
+
+    fn entry(&'s mut self, key: String) -> Self::Entry {
+        InternedEntry {
+            entry: self.groups.entry(key),
+            pool: &mut self.pool,
+        }
+    }
+
+    fn get(&'s self, key: &String) -> Option<&'s Vec<Rc<str>>> {
+        self.groups.get(key)
+    }
+
+    /// Removes the group at `key`. Its values stay in the pool, so adding them again shares them.
+    fn remove(&mut self, key: &String) -> Option<Vec<Rc<str>>> {
+        self.groups.remove(key)
+    }
+
+    fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&String, &mut Vec<Rc<str>>) -> bool,
+    {
+        self.groups.retain(keep)
+    }
+
+    fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    fn contains_key(&self, key: &String) -> bool {
+        self.groups.contains_key(key)
+    }
+
+    /// Iterates over key->list mappings in sort order by `key`.
+    fn iter(&'s self) -> Self::Iter {
+        self.groups.iter()
+    }
+
+    /// Moves every group out of the collection, in sort order by `key`, and empties the pool.
+    fn drain(&mut self) -> Self::IntoIter {
+        self.pool.clear();
+        IntoIterator::into_iter(mem::take(&mut self.groups))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_repeated_values() {
+        let mut map = InternedMap::new();
+        map.add("a".to_string(), "x".to_string());
+        map.add("b".to_string(), "x".to_string());
+        map.entry("a".to_string()).push("x".to_string());
+        map.add("a".to_string(), "y".to_string());
+
+        let a = map.get(&"a".to_string()).unwrap();
+        let b = map.get(&"b".to_string()).unwrap();
+        assert!(Rc::ptr_eq(&a[0], &b[0]));
+        assert!(Rc::ptr_eq(&a[0], &a[1]));
+        assert_eq!(&*a[2], "y");
+        assert_eq!(map.distinct_values(), 2);
+    }
+
+    #[test]
+    fn removes_and_retains_groups() {
+        let mut map = InternedMap::new();
+        for (key, value) in [("a", "1"), ("b", "2"), ("b", "2"), ("c", "3")] {
+            map.add(key.to_string(), value.to_string());
+        }
+
+        map.retain(|_, values| values.len() > 1 || &*values[0] == "3");
+        assert!(!map.contains_key(&"a".to_string()));
+        assert_eq!(map.remove(&"c".to_string()).unwrap().len(), 1);
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec!["b"]);
+        assert_eq!(map.drain().count(), 1);
+        assert!(map.is_empty());
+        assert_eq!(map.distinct_values(), 0);
+    }
+
+    #[test]
+    fn returns_groups_in_the_given_key_order() {
+        use crate::command_line::key_order::KeyOrder;
+
+        let mut map = InternedMap::new();
+        for (key, value) in [("10", "x"), ("9", "y"), ("10", "x")] {
+            map.add(key.to_string(), value.to_string());
+        }
+        let groups: Vec<_> = map
+            .into_groups_by(|a, b| KeyOrder::Natural.compare(a, b))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                ("9".to_string(), vec!["y".to_string()]),
+                ("10".to_string(), vec!["x".to_string(); 2]),
+            ]
+        );
+    }
+}
@@ -1,16 +1,47 @@
 //! The [GroupedCollection] trait and implementations for
 //! [BTreeMap](std::collections::BTreeMap) and [HashMap](std::collections::HashMap).
 //!
+//! With the `rayon` feature (enabled by default), the [ParallelGroupedCollection] trait adds
+//! parallel iteration over the same backends.
+//!
+//! [CountingCollection] implements the trait with a count in place of each group's values, for
+//! tallying inputs too large to hold in memory.
+//!
+//! [DiskMap] spills its values to a temporary file when they outgrow a memory budget, for grouping
+//! inputs larger than memory.
+//!
+//! [InternedMap] stores each distinct value only once, for inputs full of repeated values, like
+//! logs.
+//!
+//! [ShardedMap] splits its groups across several maps so that several threads can add to it at
+//! once.
+//!
+//! [GroupByKey] groups the items of any iterator into a collection in one call.
+//!
 //! If you're here, you're probably looking for the [GroupedCollection] trait, which provides a
 //! common interface over different mapping data structures so that you can swap them out without
 //! affecting calling code.
 
 pub mod btree_map;
+pub mod counting;
+pub mod disk;
 #[cfg(test)]
 pub mod fake_map;
+pub mod group_by_key;
 pub mod grouped_collection;
 pub mod hash_map;
+pub mod interned;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod sharded;
 #[cfg(test)]
 mod test_helpers;
 
-pub use grouped_collection::GroupedCollection;
+pub use counting::CountingCollection;
+pub use disk::DiskMap;
+pub use group_by_key::GroupByKey;
+pub use grouped_collection::{GroupEntry, GroupedCollection, Keys};
+pub use interned::InternedMap;
+#[cfg(feature = "rayon")]
+pub use parallel::ParallelGroupedCollection;
+pub use sharded::ShardedMap;
@@ -0,0 +1,102 @@
+//! Provides the [ParallelGroupedCollection] trait. Requires the `rayon` feature.
+
+use rayon::iter::ParallelIterator;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Extends [GroupedCollection] with a parallel iterator over key->group mappings.
+///
+/// This lets code that processes groups in parallel accept any backend with a single bound, rather
+/// than spelling out each backend's [rayon] bounds, e.g.
+/// `&'a M: IntoParallelIterator<Item = (&'a Key, &'a List)>`.
+///
+/// The trait deliberately doesn't require [GroupedCollection] as a supertrait, so that it can be
+/// combined with higher-ranked bounds like `for<'s> GroupedCollection<'s, ..>` without making them
+/// ambiguous.
+///
+/// [GroupedCollection]: crate::grouped_collections::GroupedCollection
+///
+/// # Examples
+///
+/// ```
+/// use groupby::grouped_collections::*;
+/// use rayon::iter::ParallelIterator;
+/// use std::collections::{BTreeMap, HashMap};
+///
+/// fn total_len<'s, Map>(map: &'s Map) -> usize
+/// where
+///     Map: ParallelGroupedCollection<'s, String, String, Vec<String>>,
+/// {
+///     map.par_iter().map(|(_, group)| group.len()).sum()
+/// }
+///
+/// let mut btree_map = BTreeMap::new();
+/// let mut hash_map = HashMap::new();
+/// for (key, value) in [("a", "1"), ("a", "2"), ("b", "3")] {
+///     btree_map.add(key.to_string(), value.to_string());
+///     hash_map.add(key.to_string(), value.to_string());
+/// }
+///
+/// assert_eq!(total_len(&btree_map), 3);
+/// assert_eq!(total_len(&hash_map), 3);
+/// ```
+pub trait ParallelGroupedCollection<'s, Key, Value, List>: Sync
+where
+    Key: 's + Sync,
+    Value: 's,
+    List: 's + Sync,
+{
+    /// The type of parallel iterator that [par_iter](ParallelGroupedCollection::par_iter) returns.
+    type ParIter: ParallelIterator<Item = (&'s Key, &'s List)>;
+
+    /// Returns a parallel iterator over key->group mappings, in arbitrary order.
+    fn par_iter(&'s self) -> Self::ParIter;
+}
+
+impl<'s, Key, Value> ParallelGroupedCollection<'s, Key, Value, Vec<Value>>
+    for BTreeMap<Key, Vec<Value>>
+where
+    Self: 's,
+    Key: Ord + Sync,
+    Value: Sync,
+{
+    type ParIter = rayon::collections::btree_map::Iter<'s, Key, Vec<Value>>;
+
+    fn par_iter(&'s self) -> Self::ParIter {
+        rayon::iter::IntoParallelRefIterator::par_iter(self)
+    }
+}
+
+impl<'s, Key, Value> ParallelGroupedCollection<'s, Key, Value, Vec<Value>>
+    for HashMap<Key, Vec<Value>>
+where
+    Self: 's,
+    Key: Eq + Hash + Sync,
+    Value: Sync,
+{
+    type ParIter = rayon::collections::hash_map::Iter<'s, Key, Vec<Value>>;
+
+    fn par_iter(&'s self) -> Self::ParIter {
+        rayon::iter::IntoParallelRefIterator::par_iter(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grouped_collections::GroupedCollection;
+
+    #[test]
+    fn visits_every_group() {
+        let mut map: HashMap<u8, Vec<u8>> = HashMap::new();
+        for i in 0..100 {
+            map.add(i % 10, i);
+        }
+
+        let mut keys: Vec<u8> = ParallelGroupedCollection::par_iter(&map)
+            .map(|(key, _)| *key)
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(keys, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,218 @@
+//! Provides [ShardedMap], a [GroupedCollection] that several threads can add to at once.
+
+use crate::grouped_collections::GroupedCollection;
+use std::collections::hash_map::{self, RandomState};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::iter::Flatten;
+use std::mem;
+use std::slice;
+use std::sync::Mutex;
+use std::thread;
+use std::vec;
+
+/// A [GroupedCollection] that splits its groups across several [HashMap]s, called shards, by the
+/// hash of their keys, so that several threads can add values at the same time.
+///
+/// On its own, a [ShardedMap] works like any other [GroupedCollection]. To add from several
+/// threads, call [concurrent](ShardedMap::concurrent), which returns a [ConcurrentShards] that
+/// locks only the shard that each value goes to. Threads adding to different shards never wait
+/// for each other. Reads aren't possible until the [ConcurrentShards] is dropped, so they never
+/// need locks.
+///
+/// As with [HashMap], groups are iterated in arbitrary order, and each group preserves the order
+/// in which its values were added (from any one thread).
+///
+/// # Examples
+///
+/// ```
+/// use groupby::grouped_collections::{GroupedCollection, ShardedMap};
+/// use std::thread;
+///
+/// let mut map = ShardedMap::new();
+/// let shards = map.concurrent();
+/// thread::scope(|scope| {
+///     for words in [["apple", "banana"], ["avocado", "cherry"]] {
+///         let shards = &shards;
+///         scope.spawn(move || {
+///             for word in words {
+///                 shards.add(word.chars().next().unwrap(), word);
+///             }
+///         });
+///     }
+/// });
+/// drop(shards);
+///
+/// assert_eq!(map.get(&'a').map(Vec::len), Some(2));
+/// assert_eq!(map.get(&'b'), Some(&vec!["banana"]));
+/// assert_eq!(map.len(), 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ShardedMap<Key, Value> {
+    shards: Vec<HashMap<Key, Vec<Value>>>,
+    hasher: RandomState,
+}
+
+impl<Key: Eq + Hash, Value> ShardedMap<Key, Value> {
+    /// Creates an empty map with four shards per available CPU, so that threads rarely contend
+    /// for the same shard.
+    pub fn new() -> Self {
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards(cpus * 4)
+    }
+
+    /// Creates an empty map with `shards` shards (at least one).
+    pub fn with_shards(shards: usize) -> Self {
+        ShardedMap {
+            shards: (0..shards.max(1)).map(|_| HashMap::new()).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Returns a handle through which several threads can add values at once. The map can't be
+    /// read until the handle is dropped.
+    pub fn concurrent(&mut self) -> ConcurrentShards<'_, Key, Value> {
+        ConcurrentShards {
+            shards: self.shards.iter_mut().map(Mutex::new).collect(),
+            hasher: &self.hasher,
+        }
+    }
+
+    // Returns the index of the shard that holds key.
+    fn shard_for(&self, key: &Key) -> usize {
+        shard_index(&self.hasher, key, self.shards.len())
+    }
+}
+
+impl<Key: Eq + Hash, Value> Default for ShardedMap<Key, Value> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'s, Key, Value> GroupedCollection<'s, Key, Value, Vec<Value>> for ShardedMap<Key, Value>
+where
+    Self: 's,
+    Key: Eq + Hash,
+{
+    type Iter = Flatten<slice::Iter<'s, HashMap<Key, Vec<Value>>>>;
+    type IntoIter = Flatten<vec::IntoIter<HashMap<Key, Vec<Value>>>>;
+    type Entry = hash_map::Entry<'s, Key, Vec<Value>>;
+
+    /// Adds `value` to the `Vec<Value>` at `key` in insertion order.
+    fn add(&mut self, key: Key, value: Value) {
+        let shard = self.shard_for(&key);
+        add_to(&mut self.shards[shard], key, value);
+    }
+
+    fn entry(&'s mut self, key: Key) -> Self::Entry {
+        let shard = self.shard_for(&key);
+        self.shards[shard].entry(key)
+    }
+
+    fn get(&'s self, key: &Key) -> Option<&'s Vec<Value>> {
+        self.shards[self.shard_for(key)].get(key)
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<Vec<Value>> {
+        let shard = self.shard_for(key);
+        self.shards[shard].remove(key)
+    }
+
+    fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&Key, &mut Vec<Value>) -> bool,
+    {
+        for shard in &mut self.shards {
+            shard.retain(&mut keep);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.shards.iter().map(HashMap::len).sum()
+    }
+
+    fn contains_key(&self, key: &Key) -> bool {
+        self.shards[self.shard_for(key)].contains_key(key)
+    }
+
+    /// Iterates over key->group mappings in arbitrary order, one shard at a time.
+    fn iter(&'s self) -> Self::Iter {
+        self.shards.iter().flatten()
+    }
+
+    /// Moves every group out of the map, one shard at a time, leaving the same number of empty
+    /// shards behind.
+    fn drain(&mut self) -> Self::IntoIter {
+        let empty = self.shards.iter().map(|_| HashMap::new()).collect();
+        mem::replace(&mut self.shards, empty).into_iter().flatten()
+    }
+}
+
+/// A handle for adding to a [ShardedMap] from several threads at once. See
+/// [ShardedMap::concurrent].
+pub struct ConcurrentShards<'m, Key, Value> {
+    shards: Vec<Mutex<&'m mut HashMap<Key, Vec<Value>>>>,
+    hasher: &'m RandomState,
+}
+
+impl<'m, Key: Eq + Hash, Value> ConcurrentShards<'m, Key, Value> {
+    /// Adds `value` to the group at `key`, waiting only for other threads that are adding to the
+    /// same shard.
+    pub fn add(&self, key: Key, value: Value) {
+        let shard = &self.shards[shard_index(self.hasher, &key, self.shards.len())];
+        add_to(&mut shard.lock().unwrap(), key, value);
+    }
+}
+
+fn shard_index<Key: Hash>(hasher: &RandomState, key: &Key, shards: usize) -> usize {
+    (hasher.hash_one(key) % shards as u64) as usize
+}
+
+fn add_to<Key: Eq + Hash, Value>(shard: &mut HashMap<Key, Vec<Value>>, key: Key, value: Value) {
+    match shard.entry(key) {
+        hash_map::Entry::Occupied(mut vec) => {
+            vec.get_mut().push(value);
+        }
+        hash_map::Entry::Vacant(slot) => {
+            slot.insert(vec![value]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grouped_collections::test_helpers::*;
+
+    #[test]
+    fn add_get_remove_iter() {
+        verify_grouped_collection(ShardedMap::new());
+        verify_grouped_collection(ShardedMap::with_shards(1));
+    }
+
+    #[test]
+    fn adds_concurrently() {
+        let mut map = ShardedMap::with_shards(3);
+        let shards = map.concurrent();
+        thread::scope(|scope| {
+            for thread in 0..4 {
+                let shards = &shards;
+                scope.spawn(move || {
+                    for i in 0..100 {
+                        shards.add(i % 10, thread);
+                    }
+                });
+            }
+        });
+        drop(shards);
+
+        assert_eq!(map.len(), 10);
+        for key in 0..10 {
+            let mut group = map.get(&key).unwrap().clone();
+            group.sort_unstable();
+            let expected: Vec<_> = (0..4).flat_map(|thread| vec![thread; 10]).collect();
+            assert_eq!(group, expected);
+        }
+    }
+}
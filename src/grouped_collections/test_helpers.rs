@@ -1,9 +1,9 @@
 #![cfg(test)]
 #![allow(dead_code)]
 
-use crate::grouped_collections::GroupedCollection;
+use crate::grouped_collections::{GroupEntry, GroupedCollection};
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Animal {
     Beaver,
     Cat,
@@ -98,4 +98,118 @@ where
     map.add(Claw, Beaver);
     refmap.add(Claw, Beaver);
     boxmap.add(Claw, Beaver);
+
+    // Check merge()
+    let mut merged = map.clone();
+    merged.merge(vec![
+        (Hoof, vec![Donkey]),
+        (Talon, vec![]),
+        (Hoof, vec![Horse]),
+    ]);
+    assert_eq!(merged.get(&Claw), map.get(&Claw));
+    assert_eq!(merged.get(&Hoof), Some(&vec![Horse, Donkey, Donkey, Horse]));
+    assert_eq!(merged.get(&Talon), None);
+
+    let mut merged = map.clone();
+    let refmerged: &mut Map = &mut merged;
+    refmerged.merge(vec![(Claw, vec![Cat])]);
+    assert_eq!(
+        merged.get(&Claw),
+        Some(&vec![Beaver, Cat, Beaver, Beaver, Cat])
+    );
+
+    // Check retain()
+    let mut retained = map.clone();
+    retained.retain(|key, _| *key != Claw);
+    assert_eq!(retained.get(&Claw), None);
+    assert_eq!(retained.get(&Hoof), Some(&hooves));
+
+    let mut retained = map.clone();
+    let refretained: &mut Map = &mut retained;
+    refretained.retain(|_, group| {
+        group.retain(|animal| *animal != Donkey);
+        group.len() > 3
+    });
+    assert_eq!(retained.get(&Claw), map.get(&Claw));
+    assert_eq!(retained.get(&Hoof), None);
+
+    // Check dedup_values()
+    let mut deduped = map.clone();
+    assert_eq!(deduped.dedup_values(), 2);
+    assert_eq!(deduped.get(&Claw), Some(&vec![Beaver, Cat]));
+    assert_eq!(deduped.get(&Hoof), Some(&hooves));
+    assert_eq!(deduped.dedup_values(), 0);
+
+    // Check len(), is_empty(), contains_key(), and keys()
+    assert_eq!(map.len(), 2);
+    assert!(!map.is_empty());
+    assert!(map.contains_key(&Claw));
+    assert!(!map.contains_key(&Talon));
+    let keys = map.keys().collect::<Vec<_>>();
+    assert_eq!(keys.len(), 2);
+    assert!(keys.contains(&&Claw));
+    assert!(keys.contains(&&Hoof));
+
+    assert_eq!(refmap.len(), 2);
+    assert!(refmap.contains_key(&Hoof));
+    assert_eq!(refmap.keys().count(), 2);
+
+    assert_eq!(boxmap.len(), 2);
+    assert!(!boxmap.contains_key(&Talon));
+
+    // Check entry()
+    let mut entered = map.clone();
+    entered.entry(Talon).push(Cat);
+    entered.entry(Talon).push(Beaver);
+    assert_eq!(entered.get(&Talon), Some(&vec![Cat, Beaver]));
+    entered.entry(Hoof).or_insert_with(Vec::new).clear();
+    assert_eq!(entered.get(&Hoof), Some(&vec![]));
+    assert_eq!(entered.get(&Claw), map.get(&Claw));
+
+    let mut entered = map.clone();
+    let refentered: &mut Map = &mut entered;
+    refentered.entry(Hoof).push(Horse);
+    assert_eq!(entered.get(&Hoof), Some(&vec![Horse, Donkey, Horse]));
+
+    // Check drain() and into_iter()
+    let mut drained = map.clone();
+    let mut groups = drained.drain().collect::<Vec<_>>();
+    assert_eq!(groups.len(), 2);
+    assert!(groups.contains(&(Claw, map.get(&Claw).unwrap().clone())));
+    assert!(groups.contains(&(Hoof, hooves.clone())));
+    assert!(drained.is_empty());
+    drained.add(Talon, Cat);
+    assert_eq!(drained.get(&Talon), Some(&vec![Cat]));
+
+    let mut drained = map.clone();
+    let refdrained: &mut Map = &mut drained;
+    assert_eq!(refdrained.drain().count(), 2);
+    assert!(drained.is_empty());
+
+    groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut owned = GroupedCollection::into_iter(map.clone()).collect::<Vec<_>>();
+    owned.sort_by(|(a, _), (b, _)| a.cmp(b));
+    assert!(owned == groups);
+
+    // Check remove() and take()
+    let mut claws = claws;
+    claws.push(Beaver);
+
+    assert_eq!(map.remove(&Claw), Some(claws.clone()));
+    assert_eq!(map.remove(&Claw), None);
+    assert_eq!(map.get(&Claw), None);
+    assert_eq!(map.get(&Hoof), Some(&hooves));
+
+    assert_eq!(refmap.take(&Hoof), hooves);
+    assert_eq!(refmap.take(&Hoof), vec![]);
+    assert_eq!(refmap.get(&Claw), Some(&claws));
+
+    assert_eq!(boxmap.remove(&Talon), None);
+    assert_eq!(boxmap.take(&Claw), claws);
+    assert_eq!(boxmap.iter().count(), 1);
+
+    assert!(!boxmap.is_empty());
+    boxmap.remove(&Hoof);
+    assert!(boxmap.is_empty());
+    assert_eq!(boxmap.keys().count(), 0);
 }
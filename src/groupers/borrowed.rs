@@ -0,0 +1,269 @@
+//! Groupers for `&str` slices borrowed from a buffer that's already in memory.
+//!
+//! These parallel the groupers in [string](crate::groupers::string), but keys and values are
+//! slices of the input rather than new [Strings](String), so grouping a large, already-loaded
+//! buffer doesn't allocate once per line. Any [GroupedCollection] keyed by `&str`, e.g.
+//! `BTreeMap<&str, Vec<&str>>`, works; the buffer simply has to outlive the collection.
+//!
+//! Only matchers that return a slice of their input have a borrowed grouper. Lines that don't
+//! match are added to the blank group, `""`.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::grouped_collections::*;
+//! use groupby::groupers::borrowed::Groupers;
+//! use std::collections::BTreeMap;
+//!
+//! let text = "apple\navocado\nbanana\n".to_string();
+//! let mut map = BTreeMap::new();
+//! for line in text.lines() {
+//!     map.group_by_first_chars(line, 1);
+//! }
+//!
+//! assert_eq!(Some(&vec!["apple", "avocado"]), map.get(&"a"));
+//! assert_eq!(Some(&vec!["banana"]), map.get(&"b"));
+//! ```
+
+use crate::command_line::error::GroupByError;
+use crate::command_line::CaptureGroup;
+use crate::grouped_collections::GroupedCollection;
+use crate::matchers::string::*;
+use regex::Regex;
+use std::borrow::Cow;
+
+/// Provides helper methods for grouping borrowed string slices into a [GroupedCollection].
+///
+/// Each method corresponds to a [matcher](crate::matchers::string).
+pub trait Groupers<'a, List> {
+    /// Groups a line according to its first `n` characters and adds it to the collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::borrowed::Groupers;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut map = HashMap::new();
+    /// map.group_by_first_chars("kaledonia", 4);
+    ///
+    /// assert_eq!(Some(&vec!["kaledonia"]), map.get(&"kale"));
+    /// ```
+    fn group_by_first_chars(&mut self, line: &'a str, n: usize);
+
+    /// Groups a line according to its last `n` characters and adds it to the collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::borrowed::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_last_chars("Sally", 4);
+    ///
+    /// assert_eq!(Some(&vec!["Sally"]), map.get(&"ally"));
+    /// ```
+    fn group_by_last_chars(&mut self, line: &'a str, n: usize);
+
+    /// Groups a line according to the provided Regex and adds it to the collection. Lines that
+    /// don't match are grouped under an empty key.
+    ///
+    /// See [match_regex] for details on how the key is determined.
+    ///
+    /// # Errors
+    ///
+    /// Returns [GroupByError::InvalidArgument], without adding `line`, if `capture_group` is
+    /// [CaptureGroup::Joined], since a joined key isn't a slice of `line`. Use
+    /// [string::Groupers](crate::groupers::string::Groupers) instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::CaptureGroup;
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::borrowed::Groupers;
+    /// use regex::Regex;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let regex = Regex::new(r"\d+").unwrap();
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_regex("Nineteen99", &regex, &CaptureGroup::Default).unwrap();
+    /// map.group_by_regex("none", &regex, &CaptureGroup::Default).unwrap();
+    ///
+    /// assert_eq!(Some(&vec!["Nineteen99"]), map.get(&"99"));
+    /// assert_eq!(Some(&vec!["none"]), map.get(&""));
+    ///
+    /// let joined = CaptureGroup::Joined(vec![], "-".to_string());
+    /// assert!(map.group_by_regex("Nineteen99", &regex, &joined).is_err());
+    /// ```
+    fn group_by_regex(
+        &mut self,
+        line: &'a str,
+        regex: &Regex,
+        capture_group: &CaptureGroup,
+    ) -> Result<(), GroupByError>;
+
+    /// Groups a filename by its extension. Filenames without one are added to the blank group,
+    /// `""`. See [match_file_extension] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::borrowed::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_file_extension("notes.txt");
+    /// map.group_by_file_extension("Gemfile");
+    ///
+    /// assert_eq!(Some(&vec!["notes.txt"]), map.get(&"txt"));
+    /// assert_eq!(Some(&vec!["Gemfile"]), map.get(&""));
+    /// ```
+    fn group_by_file_extension(&mut self, filename: &'a str);
+
+    /// Groups a line by its `index`th field, counting from 1, where fields are separated by
+    /// `delimiter`. Lines with fewer fields are added to the blank group, `""`. See
+    /// [match_field] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::borrowed::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_field("alice,admin", ",", 2);
+    /// map.group_by_field("bob", ",", 2);
+    ///
+    /// assert_eq!(Some(&vec!["alice,admin"]), map.get(&"admin"));
+    /// assert_eq!(Some(&vec!["bob"]), map.get(&""));
+    /// ```
+    fn group_by_field(&mut self, line: &'a str, delimiter: &str, index: usize);
+
+    /// Adds a path to a group based on its final component, like `basename`. Paths without a
+    /// final component are added to the blank group, `""`. See [match_basename] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::borrowed::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_basename("a/README.md");
+    /// map.group_by_basename("b/README.md");
+    ///
+    /// assert_eq!(Some(&vec!["a/README.md", "b/README.md"]), map.get(&"README.md"));
+    /// ```
+    fn group_by_basename(&mut self, path: &'a str);
+
+    /// Adds a path to a group based on everything but its final component, like `dirname`.
+    /// Roots are added to the blank group, `""`. See [match_dirname] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::borrowed::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_dirname("src/lib.rs");
+    /// map.group_by_dirname("src/main.rs");
+    ///
+    /// assert_eq!(Some(&vec!["src/lib.rs", "src/main.rs"]), map.get(&"src"));
+    /// ```
+    fn group_by_dirname(&mut self, path: &'a str);
+
+    /// Adds a URL to a group based on its host or, if `domain` is true, its registrable domain.
+    /// Lines without a host are added to the blank group, `""`. See [match_url_host] and
+    /// [match_registrable_domain] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::borrowed::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_url_host("https://www.example.com/a", true);
+    /// map.group_by_url_host("http://mail.example.com/b", true);
+    ///
+    /// assert_eq!(
+    ///     Some(&vec!["https://www.example.com/a", "http://mail.example.com/b"]),
+    ///     map.get(&"example.com"),
+    /// );
+    /// ```
+    fn group_by_url_host(&mut self, url: &'a str, domain: bool);
+}
+
+impl<'s, 'a: 's, List, GC> Groupers<'a, List> for GC
+where
+    List: 's,
+    GC: GroupedCollection<'s, &'a str, &'a str, List>,
+{
+    fn group_by_first_chars(&mut self, line: &'a str, n: usize) {
+        self.add(match_first_n_chars(line, n), line);
+    }
+
+    fn group_by_last_chars(&mut self, line: &'a str, n: usize) {
+        self.add(match_last_n_chars(line, n), line);
+    }
+
+    fn group_by_regex(
+        &mut self,
+        line: &'a str,
+        regex: &Regex,
+        capture_group: &CaptureGroup,
+    ) -> Result<(), GroupByError> {
+        // Check up front, so that lines the Regex doesn't match are rejected too.
+        let joined = || {
+            GroupByError::InvalidArgument(
+                "Joined capture groups can't be borrowed from the line".to_string(),
+            )
+        };
+        if let CaptureGroup::Joined(_, _) = capture_group {
+            return Err(joined());
+        }
+        let key = match match_regex(line, regex, capture_group) {
+            Some(Cow::Borrowed(key)) => key,
+            Some(Cow::Owned(_)) => return Err(joined()),
+            None => "",
+        };
+        self.add(key, line);
+        Ok(())
+    }
+
+    fn group_by_file_extension(&mut self, filename: &'a str) {
+        self.add(match_file_extension(filename).unwrap_or(""), filename);
+    }
+
+    fn group_by_field(&mut self, line: &'a str, delimiter: &str, index: usize) {
+        self.add(match_field(line, delimiter, index).unwrap_or(""), line);
+    }
+
+    fn group_by_basename(&mut self, path: &'a str) {
+        self.add(match_basename(path).unwrap_or(""), path);
+    }
+
+    fn group_by_dirname(&mut self, path: &'a str) {
+        self.add(match_dirname(path).unwrap_or(""), path);
+    }
+
+    fn group_by_url_host(&mut self, url: &'a str, domain: bool) {
+        let host = match_url_host(url).unwrap_or("");
+        let key = if domain {
+            match_registrable_domain(host)
+        } else {
+            host
+        };
+        self.add(key, url);
+    }
+}
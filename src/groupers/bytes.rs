@@ -0,0 +1,109 @@
+//! Groupers for byte strings, i.e. `Vec<u8>` values, which need not be valid UTF-8.
+//!
+//! These parallel the groupers in [string](crate::groupers::string) for input that can't be read
+//! as text. Keys are byte strings too, so nothing is lost or replaced along the way.
+
+use crate::command_line::CaptureGroup;
+use crate::grouped_collections::GroupedCollection;
+use crate::matchers::bytes::*;
+use regex::bytes::Regex;
+use std::borrow::Cow;
+
+/// Provides helper methods for grouping byte strings into a [GroupedCollection].
+///
+/// Each method corresponds to a [matcher](crate::matchers::bytes).
+pub trait Groupers<List> {
+    /// Groups a byte string according to its first `n` bytes and adds it to the collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::bytes::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let expected = vec![b"caf\xe9".to_vec()];
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_first_bytes(expected[0].clone(), 3);
+    ///
+    /// assert_eq!(Some(&expected), map.get(&b"caf".to_vec()));
+    /// ```
+    fn group_by_first_bytes<B: Into<Vec<u8>>>(&mut self, line: B, n: usize);
+
+    /// Groups a byte string according to its last `n` bytes and adds it to the collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::bytes::Groupers;
+    /// use std::collections::HashMap;
+    ///
+    /// let expected = vec![b"caf\xe9".to_vec()];
+    /// let mut map = HashMap::new();
+    /// map.group_by_last_bytes(expected[0].clone(), 2);
+    ///
+    /// assert_eq!(Some(&expected), map.get(&b"f\xe9".to_vec()));
+    /// ```
+    fn group_by_last_bytes<B: Into<Vec<u8>>>(&mut self, line: B, n: usize);
+
+    /// Groups a byte string according to the provided Regex and adds it to the collection. Byte
+    /// strings that don't match are grouped under an empty key.
+    ///
+    /// See [match_regex] for details on how the key is determined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::CaptureGroup;
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::bytes::Groupers;
+    /// use regex::bytes::Regex;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let regex = Regex::new(r"\d+").unwrap();
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_regex(b"\xff1999".to_vec(), &regex, &CaptureGroup::Default);
+    /// map.group_by_regex(b"\xff".to_vec(), &regex, &CaptureGroup::Default);
+    ///
+    /// assert_eq!(Some(&vec![b"\xff1999".to_vec()]), map.get(&b"1999".to_vec()));
+    /// assert_eq!(Some(&vec![b"\xff".to_vec()]), map.get(&vec![]));
+    /// ```
+    fn group_by_regex<B: Into<Vec<u8>>>(
+        &mut self,
+        line: B,
+        regex: &Regex,
+        capture_group: &CaptureGroup,
+    );
+}
+
+impl<'s, List, GC> Groupers<List> for GC
+where
+    List: 's,
+    GC: GroupedCollection<'s, Vec<u8>, Vec<u8>, List>,
+{
+    fn group_by_first_bytes<B: Into<Vec<u8>>>(&mut self, line: B, n: usize) {
+        let line = line.into();
+        let key = match_first_n_bytes(&line, n).to_vec();
+        self.add(key, line);
+    }
+
+    fn group_by_last_bytes<B: Into<Vec<u8>>>(&mut self, line: B, n: usize) {
+        let line = line.into();
+        let key = match_last_n_bytes(&line, n).to_vec();
+        self.add(key, line);
+    }
+
+    fn group_by_regex<B: Into<Vec<u8>>>(
+        &mut self,
+        line: B,
+        regex: &Regex,
+        capture_group: &CaptureGroup,
+    ) {
+        let line = line.into();
+        let key = match_regex(&line, regex, capture_group)
+            .map(Cow::into_owned)
+            .unwrap_or_default();
+        self.add(key, line);
+    }
+}
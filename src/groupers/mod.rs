@@ -56,9 +56,11 @@
 //!    that you can reuse an existing matcher, but these cases are probably rare.)
 //!
 //! 1. Add a corresponding grouper, following the examples of the existing groupers. For String
-//!    groupers, add your method to [Groupers]. (At time of writing, there are only
-//!    String groupers. If you're adding the the first non-String grouper, please exercise your
-//!    best judgement in designing the module and update the documentation here accordingly.)
+//!    groupers, add your method to [Groupers]; for byte-string groupers, add it to
+//!    [bytes::Groupers]. If your matcher returns a slice of its input, consider adding a
+//!    grouper to [borrowed::Groupers] too, so that library users can group borrowed slices without
+//!    allocating. (If you're adding groupers for some other type of value, please exercise
+//!    your best judgement in designing the module and update the documentation here accordingly.)
 //!    Remember to add documentation and tests, preferably as doctests.
 //!
 //! 1. For String groupers, you'll probably want to expand the command-line application. (If not,
@@ -79,6 +81,9 @@
 //!
 //! 1. Update any other relevant documentation, code, or tests, using your best judgement.
 //!
+//! Applications that only need a grouper of their own, rather than one for everyone, don't need
+//! any of the above: they can add it at runtime through the [registry].
+//!
 //! [args]: mod@crate::command_line::args
 //! [CommandBuilder]: crate::command_line::args::CommandBuilder
 //! [group_groupers()]: crate::command_line::args::CommandBuilder::group_groupers
@@ -89,4 +94,7 @@
 //! [GroupingSpecifier]: crate::command_line::options::GroupingSpecifier
 //! [Runner]: string::Runner
 
+pub mod borrowed;
+pub mod bytes;
+pub mod registry;
 pub mod string;
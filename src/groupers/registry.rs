@@ -0,0 +1,101 @@
+//! A process-wide registry of named groupers that applications supply at runtime.
+//!
+//! The groupers in [string](crate::groupers::string) are fixed when `groupby` is compiled. A
+//! program built on this crate can add its own by registering a closure that maps each token to
+//! its key, under a name of its choosing. Once registered, the grouper can be selected like any
+//! other: as [GroupingSpecifier::Named](crate::command_line::options::GroupingSpecifier::Named)
+//! from library code, or with `--grouper <name>` on the command line, as long as the program
+//! registers its groupers before it parses arguments.
+//!
+//! The `groupby` binary registers none. To offer the whole `groupby` command line with groupers of
+//! your own, write a binary that registers them and then calls
+//! [command_line::main()](crate::command_line::main()), which does everything else that the
+//! `groupby` binary does; see [its module](mod@crate::command_line::main) for an example.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::command_line::options::GroupingSpecifier;
+//! use groupby::groupers::registry;
+//! use groupby::groupers::string::Runner;
+//! use std::collections::BTreeMap;
+//!
+//! registry::register("vowels", Box::new(|token: &str| {
+//!     token.chars().filter(|c| "aeiou".contains(*c)).collect()
+//! }));
+//!
+//! let mut map = BTreeMap::new();
+//! let spec = GroupingSpecifier::Named("vowels".to_string());
+//! let mut runner = Runner::new(&mut map, &spec);
+//! runner.run("banana".to_string()).unwrap();
+//! runner.run("rhythm".to_string()).unwrap();
+//! drop(runner);
+//!
+//! assert_eq!(map.get("aaa"), Some(&vec!["banana".to_string()]));
+//! assert_eq!(map.get(""), Some(&vec!["rhythm".to_string()]));
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// A grouper that can be registered: a function from a token to its key.
+pub type NamedGrouper = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+// A registered grouper. Groupers are shared so that running one doesn't hold the lock, since
+// groupers may run on several threads at once.
+type Shared = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+// Every registered grouper, by name.
+static REGISTRY: RwLock<BTreeMap<String, Shared>> = RwLock::new(BTreeMap::new());
+
+/// Registers `grouper` under `name`, replacing any grouper already registered under that name.
+///
+/// Groupers that are already running, e.g. in a [Runner](crate::groupers::string::Runner), keep
+/// the grouper they started with.
+pub fn register<S: Into<String>>(name: S, grouper: NamedGrouper) {
+    REGISTRY
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(name.into(), Arc::from(grouper));
+}
+
+/// Returns the grouper registered under `name`, if any.
+pub fn get(name: &str) -> Option<Shared> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(name)
+        .cloned()
+}
+
+/// Returns the name of every registered grouper, in sort order.
+pub fn names() -> Vec<String> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .keys()
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The registry is shared by every test in the process, so each test uses names of its own.
+
+    #[test]
+    fn registers_and_replaces_groupers() {
+        register("registry_test_len", Box::new(|s: &str| s.len().to_string()));
+        assert_eq!(get("registry_test_len").unwrap()("abc"), "3");
+
+        register("registry_test_len", Box::new(|_: &str| "x".to_string()));
+        assert_eq!(get("registry_test_len").unwrap()("abc"), "x");
+        assert!(names().contains(&"registry_test_len".to_string()));
+    }
+
+    #[test]
+    fn returns_none_for_unregistered_names() {
+        assert!(get("registry_test_missing").is_none());
+    }
+}
@@ -1,9 +1,22 @@
 //! A collection of helper methods for grouping [Strings](String) into a [GroupedCollection].
 
-use crate::command_line::options::{CaptureGroup, GroupingSpecifier};
+use crate::command_line::error::GroupByError;
+use crate::command_line::globs::GlobRule;
+use crate::command_line::json::JsonPath;
+use crate::command_line::key_command;
+use crate::command_line::key_expr::KeyExpr;
+use crate::command_line::options::{CaptureGroup, Column, GroupingSpecifier, RegexRule, Unmatched};
+use crate::command_line::run_command::current_shell;
+use crate::command_line::size_buckets::SizeBuckets;
+use crate::command_line::timestamps::{TimeBucket, TimestampFormat};
 use crate::grouped_collections::*;
+use crate::groupers::registry;
 use crate::matchers::string::*;
 use regex::Regex;
+use std::cell::RefCell;
+use std::iter;
+use std::mem;
+use std::rc::Rc;
 
 /// Provides helper methods for grouping strings into a [GroupedCollection].
 ///
@@ -43,14 +56,58 @@ pub trait Groupers<List> {
     /// ```
     fn group_by_last_chars<S: Into<String>>(&mut self, line: S, n: usize);
 
+    /// Groups a String according to its first `n` bytes and adds it to the collection. A
+    /// multibyte character cut off at the end of the key becomes U+FFFD. See
+    /// [match_first_n_bytes] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let expected = vec!["café".to_string()];
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_first_bytes(expected[0].clone(), 5);
+    ///
+    /// assert_eq!(Some(&expected), map.get(&"café".to_string()));
+    /// ```
+    fn group_by_first_bytes<S: Into<String>>(&mut self, line: S, n: usize);
+
+    /// Groups a String according to its last `n` bytes and adds it to the collection. A
+    /// multibyte character cut off at the start of the key becomes U+FFFD. See
+    /// [match_last_n_bytes] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let expected = vec!["café".to_string()];
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_last_bytes(expected[0].clone(), 3);
+    ///
+    /// assert_eq!(Some(&expected), map.get(&"fé".to_string()));
+    /// ```
+    fn group_by_last_bytes<S: Into<String>>(&mut self, line: S, n: usize);
+
     /// Groups a String according to the provided Regex and adds it to the collection.
     ///
-    /// See [match_regex] for details on how the key is determined.
+    /// See [match_regex] for details on how the key is determined. If the Regex doesn't match,
+    /// `unmatched` decides what happens to the line.
+    ///
+    /// # Errors
+    ///
+    /// If the Regex doesn't match and `unmatched` is [Unmatched::Error], returns the line without
+    /// adding it.
     ///
     /// # Examples
     ///
     /// ```
-    /// use groupby::command_line::CaptureGroup;
+    /// use groupby::command_line::{CaptureGroup, Unmatched};
     /// use groupby::grouped_collections::*;
     /// use groupby::groupers::string::Groupers;
     /// use regex::Regex;
@@ -60,16 +117,61 @@ pub trait Groupers<List> {
     /// let regex = Regex::new(r"\d+").unwrap();
     /// let capture_group = CaptureGroup::Number(0);
     /// let mut map = HashMap::new();
-    /// map.group_by_regex(expected[0].clone(), &regex, &capture_group);
-    ///
+    /// map.group_by_regex(expected[0].clone(), &regex, &capture_group, &Unmatched::Empty)
+    ///     .unwrap();
     /// assert_eq!(Some(&expected), map.get(&"99".to_string()));
+    ///
+    /// let result = map.group_by_regex("none", &regex, &capture_group, &Unmatched::Error);
+    /// assert_eq!(result, Err("none".to_string()));
     /// ```
     fn group_by_regex<S: Into<String>>(
         &mut self,
         line: S,
         regex: &Regex,
         capture_group: &CaptureGroup,
-    );
+        unmatched: &Unmatched,
+    ) -> Result<(), String>;
+
+    /// Groups a String according to the first of the provided rules that matches it and adds it
+    /// to the collection.
+    ///
+    /// See [match_regex_rules] for details on how the key is determined. If no rule matches,
+    /// `unmatched` decides what happens to the line.
+    ///
+    /// # Errors
+    ///
+    /// If no rule matches and `unmatched` is [Unmatched::Error], returns the line without adding
+    /// it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::{CaptureGroup, Unmatched};
+    /// use groupby::command_line::options::RegexRule;
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::HashMap;
+    ///
+    /// let rules: Vec<RegexRule> = vec![
+    ///     "errors=ERROR".parse().unwrap(),
+    ///     "warnings=WARN".parse().unwrap(),
+    /// ];
+    /// let cg = CaptureGroup::Default;
+    /// let unmatched = Unmatched::Group("other".to_string());
+    /// let mut map = HashMap::new();
+    /// map.group_by_regex_rules("WARN: low disk", &rules, &cg, &unmatched).unwrap();
+    /// map.group_by_regex_rules("all good", &rules, &cg, &unmatched).unwrap();
+    ///
+    /// assert_eq!(Some(&vec!["WARN: low disk".to_string()]), map.get("warnings"));
+    /// assert_eq!(Some(&vec!["all good".to_string()]), map.get("other"));
+    /// ```
+    fn group_by_regex_rules<S: Into<String>>(
+        &mut self,
+        line: S,
+        rules: &[RegexRule],
+        capture_group: &CaptureGroup,
+        unmatched: &Unmatched,
+    ) -> Result<(), String>;
 
     /// Groups a filename string by its extension.
     ///
@@ -120,48 +222,835 @@ pub trait Groupers<List> {
     /// }
     /// ```
     fn group_by_counter<S: Into<String>>(&mut self, line: S);
+
+    /// Splits a pre-keyed record into a key and a value and adds the value to the key's group.
+    ///
+    /// Unlike other groupers, this adds only the part of the record after the separator, not the
+    /// whole record. Records without the separator are added, whole, to the blank group, `""`.
+    /// See [match_key_value] for details on how records are split.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_key_value("fruit=apple", "=");
+    /// map.group_by_key_value("fruit=banana", "=");
+    /// map.group_by_key_value("unkeyed", "=");
+    ///
+    /// let expected = vec!["apple".to_string(), "banana".to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"fruit".to_string()));
+    /// assert_eq!(Some(&vec!["unkeyed".to_string()]), map.get(&"".to_string()));
+    /// ```
+    fn group_by_key_value<S: Into<String>>(&mut self, record: S, separator: &str);
+
+    /// Adds a line to a group based on its `index`th field, counting from 1, where fields are
+    /// separated by `delimiter`. Lines with fewer fields are added to the blank group, `""`. See
+    /// [match_field] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_field("alice:1000:100", ":", 3);
+    /// map.group_by_field("bob:1001:100", ":", 3);
+    /// map.group_by_field("carol", ":", 3);
+    ///
+    /// let expected = vec!["alice:1000:100".to_string(), "bob:1001:100".to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"100".to_string()));
+    /// assert_eq!(Some(&vec!["carol".to_string()]), map.get(&"".to_string()));
+    /// ```
+    fn group_by_field<S: Into<String>>(&mut self, line: S, delimiter: &str, index: usize);
+
+    /// Adds a line to a group based on its `n`th word, counting from 1, where a word is any match
+    /// of `word_regex`. Lines with fewer words are added to the blank group, `""`. See
+    /// [match_nth_word] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use regex::Regex;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let word = Regex::new(r"\S+").unwrap();
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_nth_word("10:00 web GET /", 2, &word);
+    /// map.group_by_nth_word("10:01 db SELECT", 2, &word);
+    /// map.group_by_nth_word("10:02 web POST /login", 2, &word);
+    /// map.group_by_nth_word("10:03", 2, &word);
+    ///
+    /// let expected = vec!["10:00 web GET /".to_string(), "10:02 web POST /login".to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"web".to_string()));
+    /// assert_eq!(Some(&vec!["10:03".to_string()]), map.get(&"".to_string()));
+    /// ```
+    fn group_by_nth_word<S: Into<String>>(&mut self, line: S, n: usize, word_regex: &Regex);
+
+    /// Adds a line to a group based on the first timestamp in it that matches `format`, reduced to
+    /// `bucket`, e.g. one group per hour. Lines without a matching timestamp are added to the
+    /// blank group, `""`. See [match_timestamp] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::timestamps::*;
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let format: TimestampFormat = "%Y-%m-%dT%H:%M".parse().unwrap();
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_timestamp("2024-03-09T14:05 start", &format, TimeBucket::Hour);
+    /// map.group_by_timestamp("2024-03-09T14:59 stop", &format, TimeBucket::Hour);
+    /// map.group_by_timestamp("2024-03-09T15:00 start", &format, TimeBucket::Hour);
+    ///
+    /// let expected = vec![
+    ///     "2024-03-09T14:05 start".to_string(),
+    ///     "2024-03-09T14:59 stop".to_string(),
+    /// ];
+    /// assert_eq!(Some(&expected), map.get(&"2024-03-09T14".to_string()));
+    /// ```
+    fn group_by_timestamp<S: Into<String>>(
+        &mut self,
+        line: S,
+        format: &TimestampFormat,
+        bucket: TimeBucket,
+    );
+
+    /// Adds a line to a group based on the range of width `bucket_size` that contains the first
+    /// number in it, e.g. `"100-199"`. Lines without a number are added to the blank group, `""`.
+    /// See [match_numeric_bucket] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_numeric_bucket("GET / 12ms", 100);
+    /// map.group_by_numeric_bucket("GET /login 85ms", 100);
+    /// map.group_by_numeric_bucket("GET /search 340ms", 100);
+    ///
+    /// let expected = vec!["GET / 12ms".to_string(), "GET /login 85ms".to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"0-99".to_string()));
+    /// assert_eq!(
+    ///     Some(&vec!["GET /search 340ms".to_string()]),
+    ///     map.get(&"300-399".to_string())
+    /// );
+    /// ```
+    fn group_by_numeric_bucket<S: Into<String>>(&mut self, line: S, bucket_size: u64);
+
+    /// Adds a path to a group based on the size of the file it names, divided into [SizeBuckets].
+    /// Paths whose metadata can't be read, e.g. because they don't exist, are added to the blank
+    /// group, `""`. See [match_size_bucket] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::size_buckets::SizeBuckets;
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let buckets: SizeBuckets = "10,1M".parse().unwrap();
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_size_bucket("Cargo.toml", &buckets);
+    /// map.group_by_size_bucket("no/such/file", &buckets);
+    ///
+    /// assert_eq!(Some(&vec!["Cargo.toml".to_string()]), map.get(&"10-1M".to_string()));
+    /// assert_eq!(Some(&vec!["no/such/file".to_string()]), map.get(&"".to_string()));
+    /// ```
+    fn group_by_size_bucket<S: Into<String>>(&mut self, path: S, buckets: &SizeBuckets);
+
+    /// Adds a path to a group based on the modification time of the file it names, reduced to
+    /// `bucket` in UTC. Paths whose modification time can't be read, e.g. because they don't
+    /// exist, are added to the blank group, `""`. See [match_mtime] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::timestamps::TimeBucket;
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_mtime("Cargo.toml", TimeBucket::Year);
+    /// map.group_by_mtime("no/such/file", TimeBucket::Year);
+    ///
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(Some(&vec!["no/such/file".to_string()]), map.get(&"".to_string()));
+    /// ```
+    fn group_by_mtime<S: Into<String>>(&mut self, path: S, bucket: TimeBucket);
+
+    /// Adds a path to a group based on the MIME type of the file it names, detected from its
+    /// contents. Paths that can't be read, e.g. because they don't exist, are added to the blank
+    /// group, `""`. See [match_mime_type] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_mime_type("Cargo.toml");
+    /// map.group_by_mime_type("no/such/file");
+    ///
+    /// assert_eq!(Some(&vec!["Cargo.toml".to_string()]), map.get(&"text/plain".to_string()));
+    /// assert_eq!(Some(&vec!["no/such/file".to_string()]), map.get(&"".to_string()));
+    /// ```
+    fn group_by_mime_type<S: Into<String>>(&mut self, path: S);
+
+    /// Adds a path to a group based on its component at `depth`, counting from 1, e.g. its
+    /// top-level directory at depth 1. Paths with fewer than `depth` components are added to the
+    /// blank group, `""`. See [match_path_component] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_path_component("./src/lib.rs", 1);
+    /// map.group_by_path_component("./src/bin/groupby.rs", 1);
+    /// map.group_by_path_component("./Cargo.toml", 1);
+    ///
+    /// let expected = vec!["./src/lib.rs".to_string(), "./src/bin/groupby.rs".to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"src".to_string()));
+    /// assert_eq!(Some(&vec!["./Cargo.toml".to_string()]), map.get(&"Cargo.toml".to_string()));
+    /// ```
+    fn group_by_path_component<S: Into<String>>(&mut self, path: S, depth: usize);
+
+    /// Adds a path to a group based on its final component, like `basename`, so that files with
+    /// the same name in different directories share a group. Paths without a final component,
+    /// e.g. `/`, are added to the blank group, `""`. See [match_basename] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_basename("a/README.md");
+    /// map.group_by_basename("b/README.md");
+    /// map.group_by_basename("b/main.rs");
+    ///
+    /// let expected = vec!["a/README.md".to_string(), "b/README.md".to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"README.md".to_string()));
+    /// ```
+    fn group_by_basename<S: Into<String>>(&mut self, path: S);
+
+    /// Adds a path to a group based on everything but its final component, like `dirname`, i.e.
+    /// its parent directory. Roots, e.g. `/`, are added to the blank group, `""`. See
+    /// [match_dirname] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_dirname("src/lib.rs");
+    /// map.group_by_dirname("src/main.rs");
+    /// map.group_by_dirname("Cargo.toml");
+    ///
+    /// let expected = vec!["src/lib.rs".to_string(), "src/main.rs".to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"src".to_string()));
+    /// assert_eq!(Some(&vec!["Cargo.toml".to_string()]), map.get(&".".to_string()));
+    /// ```
+    fn group_by_dirname<S: Into<String>>(&mut self, path: S);
+
+    /// Adds a line to a group based on its length in characters. See [match_length] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_length("pear");
+    /// map.group_by_length("plum");
+    /// map.group_by_length("fig");
+    ///
+    /// let expected = vec!["pear".to_string(), "plum".to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"4".to_string()));
+    /// assert_eq!(Some(&vec!["fig".to_string()]), map.get(&"3".to_string()));
+    /// ```
+    fn group_by_length<S: Into<String>>(&mut self, line: S);
+
+    /// Adds a line to one of `shards` groups, numbered from 0, based on a stable hash of the line.
+    /// Identical lines always land in the same group. See [match_shard] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// for word in ["apple", "banana", "cherry", "apple"] {
+    ///     map.group_by_shard(word, 2);
+    /// }
+    ///
+    /// assert!(map.keys().all(|key| key == "0" || key == "1"));
+    /// assert_eq!(map.values().map(Vec::len).sum::<usize>(), 4);
+    /// ```
+    fn group_by_shard<S: Into<String>>(&mut self, line: S, shards: u64);
+
+    /// Adds a line to a numbered group of `chunk_size` consecutive lines, given the line's
+    /// `position` in the input, counting from 0. For instance, with a chunk size of 100, lines
+    /// 0-99 go in group `"0"`, lines 100-199 in group `"1"`, and so on. See [match_chunk] for
+    /// details.
+    ///
+    /// The caller tracks each line's position; [Runner] does this automatically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// for (position, line) in ["a", "b", "c"].iter().enumerate() {
+    ///     map.group_by_chunk(*line, position, 2);
+    /// }
+    ///
+    /// assert_eq!(Some(&vec!["a".to_string(), "b".to_string()]), map.get(&"0".to_string()));
+    /// assert_eq!(Some(&vec!["c".to_string()]), map.get(&"1".to_string()));
+    /// ```
+    fn group_by_chunk<S: Into<String>>(&mut self, line: S, position: usize, chunk_size: usize);
+
+    /// Adds a line to the group named by the first [GlobRule] whose pattern matches it. Lines that
+    /// match no rule are added to the group `"other"`. See [match_glob_rules] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::globs::GlobRule;
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let rules: Vec<GlobRule> = vec!["rust=*.rs".parse().unwrap()];
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_glob("src/main.rs", &rules);
+    /// map.group_by_glob("README.md", &rules);
+    ///
+    /// assert_eq!(Some(&vec!["src/main.rs".to_string()]), map.get(&"rust".to_string()));
+    /// assert_eq!(Some(&vec!["README.md".to_string()]), map.get(&"other".to_string()));
+    /// ```
+    fn group_by_glob<S: Into<String>>(&mut self, line: S, rules: &[GlobRule]);
+
+    /// Adds a URL to a group based on its host, or, if `domain` is true, the host's registrable
+    /// domain, so that `www.example.com` and `api.example.com` share the group `example.com`.
+    /// Strings that aren't URLs are added to the blank group, `""`. See [match_url_host] and
+    /// [match_registrable_domain] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_url_host("https://www.example.com/", true);
+    /// map.group_by_url_host("https://api.example.com/v1", true);
+    /// map.group_by_url_host("not a url", true);
+    ///
+    /// let expected = vec![
+    ///     "https://www.example.com/".to_string(),
+    ///     "https://api.example.com/v1".to_string(),
+    /// ];
+    /// assert_eq!(Some(&expected), map.get(&"example.com".to_string()));
+    /// assert_eq!(Some(&vec!["not a url".to_string()]), map.get(&"".to_string()));
+    /// ```
+    fn group_by_url_host<S: Into<String>>(&mut self, url: S, domain: bool);
+
+    /// Adds a JSON document, e.g. one line of JSON Lines, to a group based on the value at `path`.
+    /// Documents without a value there, or that are malformed, are added to the blank group,
+    /// `""`. See [match_json_key] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::json::JsonPath;
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let path: JsonPath = ".level".parse().unwrap();
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_json_key(r#"{"level": "warn", "msg": "low disk"}"#, &path);
+    /// map.group_by_json_key("not json", &path);
+    ///
+    /// let expected = vec![r#"{"level": "warn", "msg": "low disk"}"#.to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"warn".to_string()));
+    /// assert_eq!(Some(&vec!["not json".to_string()]), map.get(&"".to_string()));
+    /// ```
+    fn group_by_json_key<S: Into<String>>(&mut self, line: S, path: &JsonPath);
+
+    /// Adds a CSV record to a group based on its `column`th field, counting from 1. Records with
+    /// fewer fields are added to the blank group, `""`. See [match_csv_column] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_csv_column(r#"1,"New York, NY",open"#, 2, ',');
+    /// map.group_by_csv_column("2", 2, ',');
+    ///
+    /// let expected = vec![r#"1,"New York, NY",open"#.to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"New York, NY".to_string()));
+    /// assert_eq!(Some(&vec!["2".to_string()]), map.get(&"".to_string()));
+    /// ```
+    fn group_by_csv_column<S: Into<String>>(&mut self, record: S, column: usize, delimiter: char);
+
+    /// Adds a logfmt-style line, e.g. `level=warn msg="low disk"`, to a group based on the value
+    /// for `key`. Lines without `key` are added to the blank group, `""`. See [match_logfmt] for
+    /// details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_logfmt(r#"level=warn msg="low disk""#, "level");
+    /// map.group_by_logfmt("msg=hello", "level");
+    ///
+    /// let expected = vec![r#"level=warn msg="low disk""#.to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"warn".to_string()));
+    /// assert_eq!(Some(&vec!["msg=hello".to_string()]), map.get(&"".to_string()));
+    /// ```
+    fn group_by_logfmt<S: Into<String>>(&mut self, line: S, key: &str);
+
+    /// Adds a line to a group based on the key that `expr` computes for it. See [match_key_expr]
+    /// for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use groupby::command_line::key_expr::KeyExpr;
+    /// use groupby::grouped_collections::*;
+    /// use groupby::groupers::string::Groupers;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let expr: KeyExpr = "lower(ext())".parse().unwrap();
+    /// let mut map = BTreeMap::new();
+    /// map.group_by_key_expr("Photo.JPG", &expr);
+    /// map.group_by_key_expr("scan.jpg", &expr);
+    ///
+    /// let expected = vec!["Photo.JPG".to_string(), "scan.jpg".to_string()];
+    /// assert_eq!(Some(&expected), map.get(&"jpg".to_string()));
+    /// ```
+    fn group_by_key_expr<S: Into<String>>(&mut self, line: S, expr: &KeyExpr);
+}
+
+impl<'s, List, GC> Groupers<List> for GC
+where
+    List: 's,
+    GC: GroupedCollection<'s, String, String, List>,
+{
+    fn group_by_first_chars<S: Into<String>>(&mut self, line: S, n: usize) {
+        let line = line.into();
+        let key = match_first_n_chars(&line, n).to_string();
+        self.add(key, line);
+    }
+
+    fn group_by_last_chars<S: Into<String>>(&mut self, line: S, n: usize) {
+        let line = line.into();
+        let key = match_last_n_chars(&line, n).to_string();
+        self.add(key, line);
+    }
+
+    fn group_by_first_bytes<S: Into<String>>(&mut self, line: S, n: usize) {
+        let line = line.into();
+        let key = match_first_n_bytes(&line, n).into_owned();
+        self.add(key, line);
+    }
+
+    fn group_by_last_bytes<S: Into<String>>(&mut self, line: S, n: usize) {
+        let line = line.into();
+        let key = match_last_n_bytes(&line, n).into_owned();
+        self.add(key, line);
+    }
+
+    fn group_by_regex<S: Into<String>>(
+        &mut self,
+        line: S,
+        regex: &Regex,
+        capture_group: &CaptureGroup,
+        unmatched: &Unmatched,
+    ) -> Result<(), String> {
+        let line = line.into();
+        let key = match (match_regex(&line, regex, capture_group), unmatched) {
+            (Some(key), _) => key.into_owned(),
+            (None, Unmatched::Empty) => String::new(),
+            (None, Unmatched::Skip) => return Ok(()),
+            (None, Unmatched::Error) => return Err(line),
+            (None, Unmatched::Group(name)) => name.clone(),
+        };
+        self.add(key, line);
+        Ok(())
+    }
+
+    fn group_by_regex_rules<S: Into<String>>(
+        &mut self,
+        line: S,
+        rules: &[RegexRule],
+        capture_group: &CaptureGroup,
+        unmatched: &Unmatched,
+    ) -> Result<(), String> {
+        let line = line.into();
+        let key = match (match_regex_rules(&line, rules, capture_group), unmatched) {
+            (Some(key), _) => key.into_owned(),
+            (None, Unmatched::Empty) => String::new(),
+            (None, Unmatched::Skip) => return Ok(()),
+            (None, Unmatched::Error) => return Err(line),
+            (None, Unmatched::Group(name)) => name.clone(),
+        };
+        self.add(key, line);
+        Ok(())
+    }
+
+    fn group_by_file_extension<S: Into<String>>(&mut self, filename: S) {
+        let filename = filename.into();
+        let key = match_file_extension(&filename).unwrap_or("").to_string();
+        self.add(key, filename);
+    }
+
+    fn group_by_counter<S: Into<String>>(&mut self, line: S) {
+        let line = line.into();
+        let key = match_counter().to_string();
+        self.add(key, line);
+    }
+
+    fn group_by_key_value<S: Into<String>>(&mut self, record: S, separator: &str) {
+        let record = record.into();
+        match match_key_value(&record, separator) {
+            Some((key, value)) => self.add(key.to_string(), value.to_string()),
+            None => self.add("".to_string(), record),
+        }
+    }
+
+    fn group_by_field<S: Into<String>>(&mut self, line: S, delimiter: &str, index: usize) {
+        let line = line.into();
+        let key = match_field(&line, delimiter, index)
+            .unwrap_or("")
+            .to_string();
+        self.add(key, line);
+    }
+
+    fn group_by_nth_word<S: Into<String>>(&mut self, line: S, n: usize, word_regex: &Regex) {
+        let line = line.into();
+        let key = match_nth_word(&line, n, word_regex)
+            .unwrap_or("")
+            .to_string();
+        self.add(key, line);
+    }
+
+    fn group_by_timestamp<S: Into<String>>(
+        &mut self,
+        line: S,
+        format: &TimestampFormat,
+        bucket: TimeBucket,
+    ) {
+        let line = line.into();
+        let key = match_timestamp(&line, format, bucket).unwrap_or_default();
+        self.add(key, line);
+    }
+
+    fn group_by_numeric_bucket<S: Into<String>>(&mut self, line: S, bucket_size: u64) {
+        let line = line.into();
+        let key = match_numeric_bucket(&line, bucket_size).unwrap_or_default();
+        self.add(key, line);
+    }
+
+    fn group_by_size_bucket<S: Into<String>>(&mut self, path: S, buckets: &SizeBuckets) {
+        let path = path.into();
+        let key = match_size_bucket(&path, buckets).unwrap_or("").to_string();
+        self.add(key, path);
+    }
+
+    fn group_by_mtime<S: Into<String>>(&mut self, path: S, bucket: TimeBucket) {
+        let path = path.into();
+        let key = match_mtime(&path, bucket).unwrap_or_default();
+        self.add(key, path);
+    }
+
+    fn group_by_mime_type<S: Into<String>>(&mut self, path: S) {
+        let path = path.into();
+        let key = match_mime_type(&path).unwrap_or("").to_string();
+        self.add(key, path);
+    }
+
+    fn group_by_path_component<S: Into<String>>(&mut self, path: S, depth: usize) {
+        let path = path.into();
+        let key = match_path_component(&path, depth).unwrap_or("").to_string();
+        self.add(key, path);
+    }
+
+    fn group_by_basename<S: Into<String>>(&mut self, path: S) {
+        let path = path.into();
+        let key = match_basename(&path).unwrap_or("").to_string();
+        self.add(key, path);
+    }
+
+    fn group_by_dirname<S: Into<String>>(&mut self, path: S) {
+        let path = path.into();
+        let key = match_dirname(&path).unwrap_or("").to_string();
+        self.add(key, path);
+    }
+
+    fn group_by_length<S: Into<String>>(&mut self, line: S) {
+        let line = line.into();
+        let key = match_length(&line).to_string();
+        self.add(key, line);
+    }
+
+    fn group_by_shard<S: Into<String>>(&mut self, line: S, shards: u64) {
+        let line = line.into();
+        let key = match_shard(&line, shards).to_string();
+        self.add(key, line);
+    }
+
+    fn group_by_chunk<S: Into<String>>(&mut self, line: S, position: usize, chunk_size: usize) {
+        let line = line.into();
+        let key = match_chunk(position, chunk_size).to_string();
+        self.add(key, line);
+    }
+
+    fn group_by_glob<S: Into<String>>(&mut self, line: S, rules: &[GlobRule]) {
+        let line = line.into();
+        let key = match_glob_rules(&line, rules)
+            .unwrap_or("other")
+            .to_string();
+        self.add(key, line);
+    }
+
+    fn group_by_url_host<S: Into<String>>(&mut self, url: S, domain: bool) {
+        let url = url.into();
+        let host = match_url_host(&url).unwrap_or("");
+        let key = if domain {
+            match_registrable_domain(host)
+        } else {
+            host
+        };
+        let key = key.to_string();
+        self.add(key, url);
+    }
+
+    fn group_by_json_key<S: Into<String>>(&mut self, line: S, path: &JsonPath) {
+        let line = line.into();
+        let key = match_json_key(&line, path).unwrap_or_default();
+        self.add(key, line);
+    }
+
+    fn group_by_csv_column<S: Into<String>>(&mut self, record: S, column: usize, delimiter: char) {
+        let record = record.into();
+        let key = match_csv_column(&record, column, delimiter).unwrap_or_default();
+        self.add(key, record);
+    }
+
+    fn group_by_logfmt<S: Into<String>>(&mut self, line: S, key: &str) {
+        let line = line.into();
+        let value = match_logfmt(&line, key).unwrap_or_default();
+        self.add(value, line);
+    }
+
+    fn group_by_key_expr<S: Into<String>>(&mut self, line: S, expr: &KeyExpr) {
+        let line = line.into();
+        let key = match_key_expr(&line, expr);
+        self.add(key, line);
+    }
+}
+
+/// Wraps a [GroupedCollection], lowercasing every key as it's added.
+///
+/// This lets any grouper ignore case: for instance, grouping `Foo.TXT` and `foo.txt` by file
+/// extension puts both in the group `txt`. Reads pass straight through to the wrapped collection,
+/// so they see lowercased keys.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::grouped_collections::GroupedCollection;
+/// use groupby::groupers::string::{CaseFolded, Groupers};
+/// use std::collections::BTreeMap;
+///
+/// let mut map = BTreeMap::new();
+/// let mut folded = CaseFolded::new(&mut map);
+/// folded.group_by_file_extension("Foo.TXT");
+/// folded.group_by_file_extension("foo.txt");
+///
+/// let expected = vec!["Foo.TXT".to_string(), "foo.txt".to_string()];
+/// assert_eq!(map.get(&"txt".to_string()), Some(&expected));
+/// ```
+pub struct CaseFolded<Map> {
+    map: Map,
+}
+
+impl<Map> CaseFolded<Map> {
+    pub fn new(map: Map) -> Self {
+        CaseFolded { map }
+    }
+}
+
+impl<'s, Map, List> GroupedCollection<'s, String, String, List> for CaseFolded<Map>
+where
+    Map: GroupedCollection<'s, String, String, List>,
+    List: 's,
+{
+    type Iter = Map::Iter;
+    type IntoIter = Map::IntoIter;
+    type Entry = Map::Entry;
+
+    fn add(&mut self, key: String, value: String) {
+        self.map.add(key.to_lowercase(), value)
+    }
+
+    fn entry(&'s mut self, key: String) -> Self::Entry {
+        self.map.entry(key.to_lowercase())
+    }
+
+    fn get(&'s self, key: &String) -> Option<&'s List> {
+        self.map.get(key)
+    }
+
+    fn remove(&mut self, key: &String) -> Option<List> {
+        self.map.remove(key)
+    }
+
+    fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&String, &mut List) -> bool,
+    {
+        self.map.retain(keep)
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn contains_key(&self, key: &String) -> bool {
+        self.map.contains_key(key)
+    }
+
+    fn iter(&'s self) -> Self::Iter {
+        self.map.iter()
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        self.map.drain()
+    }
 }
 
-impl<'s, List, GC> Groupers<List> for GC
-where
-    List: 's,
-    GC: GroupedCollection<'s, String, String, List>,
-{
-    fn group_by_first_chars<S: Into<String>>(&mut self, line: S, n: usize) {
-        let line = line.into();
-        let key = match_first_n_chars(&line, n).to_string();
-        self.add(key, line);
+// A GroupedCollection that only records the keys added to it, so that Runner can build composite
+// keys out of any other grouper. It's always empty.
+struct KeyRecorder {
+    keys: Rc<RefCell<Vec<String>>>,
+}
+
+impl KeyRecorder {
+    fn new(keys: &Rc<RefCell<Vec<String>>>) -> Self {
+        KeyRecorder {
+            keys: Rc::clone(keys),
+        }
     }
+}
 
-    fn group_by_last_chars<S: Into<String>>(&mut self, line: S, n: usize) {
-        let line = line.into();
-        let key = match_last_n_chars(&line, n).to_string();
-        self.add(key, line);
+impl<'s> GroupedCollection<'s, String, String, Vec<String>> for KeyRecorder {
+    type Iter = iter::Empty<(&'s String, &'s Vec<String>)>;
+    type IntoIter = iter::Empty<(String, Vec<String>)>;
+    type Entry = RecordedKey<'s>;
+
+    fn add(&mut self, key: String, _value: String) {
+        self.keys.borrow_mut().push(key);
     }
 
-    fn group_by_regex<S: Into<String>>(
-        &mut self,
-        line: S,
-        regex: &Regex,
-        capture_group: &CaptureGroup,
-    ) {
-        let line = line.into();
-        let key = match_regex(&line, regex, capture_group)
-            .unwrap_or("")
-            .to_string();
-        self.add(key, line);
+    fn entry(&'s mut self, key: String) -> Self::Entry {
+        RecordedKey {
+            keys: &self.keys,
+            key,
+        }
     }
 
-    fn group_by_file_extension<S: Into<String>>(&mut self, filename: S) {
-        let filename = filename.into();
-        let key = match_file_extension(&filename).unwrap_or("").to_string();
-        self.add(key, filename);
+    fn get(&'s self, _key: &String) -> Option<&'s Vec<String>> {
+        None
     }
 
-    fn group_by_counter<S: Into<String>>(&mut self, line: S) {
-        let line = line.into();
-        let key = match_counter().to_string();
-        self.add(key, line);
+    fn remove(&mut self, _key: &String) -> Option<Vec<String>> {
+        None
+    }
+
+    fn retain<F>(&mut self, _keep: F)
+    where
+        F: FnMut(&String, &mut Vec<String>) -> bool,
+    {
+    }
+
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn contains_key(&self, _key: &String) -> bool {
+        false
+    }
+
+    fn iter(&'s self) -> Self::Iter {
+        iter::empty()
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        iter::empty()
+    }
+}
+
+// KeyRecorder's entry, which records its key when a value is pushed.
+struct RecordedKey<'s> {
+    keys: &'s RefCell<Vec<String>>,
+    key: String,
+}
+
+impl<'s> GroupEntry<'s, String, Vec<String>> for RecordedKey<'s> {
+    fn push(self, _value: String) {
+        self.keys.borrow_mut().push(self.key);
+    }
+
+    fn or_insert_with<F>(self, _default: F) -> &'s mut Vec<String>
+    where
+        F: FnOnce() -> Vec<String>,
+    {
+        panic!("KeyRecorder only records keys, so it has no groups to return");
     }
 }
 
@@ -185,33 +1074,305 @@ where
 /// let spec = GroupingSpecifier::FirstChars(2);
 /// let mut runner = Runner::new(&mut map, &spec);
 ///
-/// runner.run("Hi there".to_string());
+/// runner.run("Hi there".to_string()).unwrap();
 /// drop(runner); // Runner stores &mut map and is meant for batch insertions
 ///
 /// assert_eq!(map.get("Hi"), Some(&vec!["Hi there".to_string()]));
 /// ```
 pub struct Runner<'a, S: Into<String>> {
-    run: Box<dyn FnMut(S) + 'a>,
+    run: Grouper<'a, S>,
+}
+
+// A closure that adds a value to a collection, failing if the grouper rejects it. Called with
+// None, it adds any values it's still holding; see Runner::finish.
+type Grouper<'a, S> = Box<dyn FnMut(Option<S>) -> Result<(), GroupByError> + 'a>;
+
+// Wraps a grouper that adds each value at once as a Grouper.
+fn fallible<'a, S>(mut grouper: impl FnMut(S) -> Result<(), GroupByError> + 'a) -> Grouper<'a, S> {
+    Box::new(move |s| match s {
+        Some(s) => grouper(s),
+        None => Ok(()),
+    })
+}
+
+// Wraps a grouper that adds each value at once and can't fail as a Grouper.
+fn infallible<'a, S>(mut grouper: impl FnMut(S) + 'a) -> Grouper<'a, S> {
+    fallible(move |s| {
+        grouper(s);
+        Ok(())
+    })
 }
 
 impl<'a, S: Into<String>> Runner<'a, S> {
-    pub fn new<Map>(map: &'a mut Map, spec: &'a GroupingSpecifier) -> Self
+    pub fn new<Map, List>(map: &'a mut Map, spec: &'a GroupingSpecifier) -> Self
     where
-        Map: for<'s> GroupedCollection<'s, String, String, Vec<String>>,
+        Map: for<'s> GroupedCollection<'s, String, String, List>,
+        List: 'static,
     {
-        let run: Box<dyn FnMut(S)> = match spec {
-            GroupingSpecifier::FirstChars(n) => Box::new(move |s| map.group_by_first_chars(s, *n)),
-            GroupingSpecifier::LastChars(n) => Box::new(move |s| map.group_by_last_chars(s, *n)),
-            GroupingSpecifier::Regex(re, cg) => Box::new(move |s| map.group_by_regex(s, re, cg)),
-            GroupingSpecifier::FileExtension => Box::new(move |s| map.group_by_file_extension(s)),
-            GroupingSpecifier::Counter => Box::new(move |s| map.group_by_counter(s)),
-        };
-        Runner { run }
+        Runner {
+            run: Self::runner(map, spec),
+        }
+    }
+
+    // Returns a closure that runs the grouper chosen by spec, adding to map, which it takes by
+    // value so that composite keys can run each part against a KeyRecorder of their own.
+    fn runner<Map, List>(map: Map, spec: &'a GroupingSpecifier) -> Grouper<'a, S>
+    where
+        Map: for<'s> GroupedCollection<'s, String, String, List> + 'a,
+        List: 'static,
+    {
+        // Peel off any IgnoreCase wrappers first, so that grouper() is only ever instantiated with
+        // Map and CaseFolded<Map>, rather than recursing into ever-deeper CaseFolded types.
+        let mut spec = spec;
+        let mut ignore_case = false;
+        while let GroupingSpecifier::IgnoreCase(inner) = spec {
+            ignore_case = true;
+            spec = inner;
+        }
+
+        if ignore_case {
+            Self::grouper(CaseFolded::new(map), spec)
+        } else {
+            Self::grouper(map, spec)
+        }
+    }
+
+    // Returns a closure that runs the grouper chosen by spec, which must not be IgnoreCase.
+    fn grouper<Map, List>(mut map: Map, spec: &'a GroupingSpecifier) -> Grouper<'a, S>
+    where
+        Map: for<'s> GroupedCollection<'s, String, String, List> + 'a,
+        List: 'static,
+    {
+        match spec {
+            GroupingSpecifier::FirstChars(n) => {
+                infallible(move |s| map.group_by_first_chars(s, *n))
+            }
+            GroupingSpecifier::LastChars(n) => infallible(move |s| map.group_by_last_chars(s, *n)),
+            GroupingSpecifier::FirstBytes(n) => {
+                infallible(move |s| map.group_by_first_bytes(s, *n))
+            }
+            GroupingSpecifier::LastBytes(n) => infallible(move |s| map.group_by_last_bytes(s, *n)),
+            GroupingSpecifier::Regex(re, cg, unmatched) => fallible(move |s| {
+                map.group_by_regex(s, re, cg, unmatched).map_err(|line| {
+                    GroupByError::InvalidInput(format!(
+                        "The regular expression /{}/ doesn't match: {}",
+                        re.as_str(),
+                        line
+                    ))
+                })
+            }),
+            GroupingSpecifier::RegexRules(rules, cg, unmatched) => fallible(move |s| {
+                map.group_by_regex_rules(s, rules, cg, unmatched)
+                    .map_err(|line| {
+                        GroupByError::InvalidInput(format!(
+                            "None of the regular expressions {} match: {}",
+                            rules
+                                .iter()
+                                .map(|rule| format!("/{}/", rule.regex.as_str()))
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                            line
+                        ))
+                    })
+            }),
+            GroupingSpecifier::FileExtension => infallible(move |s| map.group_by_file_extension(s)),
+            GroupingSpecifier::Counter => infallible(move |s| map.group_by_counter(s)),
+            GroupingSpecifier::KeyValue(sep) => infallible(move |s| map.group_by_key_value(s, sep)),
+            GroupingSpecifier::Field(n, delim) => {
+                infallible(move |s| map.group_by_field(s, delim, *n))
+            }
+            GroupingSpecifier::NthWord(n, re) => {
+                infallible(move |s| map.group_by_nth_word(s, *n, re))
+            }
+            GroupingSpecifier::Timestamp(format, bucket) => {
+                infallible(move |s| map.group_by_timestamp(s, format, *bucket))
+            }
+            GroupingSpecifier::NumericBucket(size) => {
+                infallible(move |s| map.group_by_numeric_bucket(s, *size))
+            }
+            GroupingSpecifier::SizeBucket(buckets) => {
+                infallible(move |s| map.group_by_size_bucket(s, buckets))
+            }
+            GroupingSpecifier::Mtime(bucket) => infallible(move |s| map.group_by_mtime(s, *bucket)),
+            GroupingSpecifier::MimeType => infallible(move |s| map.group_by_mime_type(s)),
+            GroupingSpecifier::PathComponent(depth) => {
+                infallible(move |s| map.group_by_path_component(s, *depth))
+            }
+            GroupingSpecifier::Basename => infallible(move |s| map.group_by_basename(s)),
+            GroupingSpecifier::Dirname => infallible(move |s| map.group_by_dirname(s)),
+            GroupingSpecifier::Length => infallible(move |s| map.group_by_length(s)),
+            GroupingSpecifier::Shard(n) => infallible(move |s| map.group_by_shard(s, *n)),
+            GroupingSpecifier::Chunk(size) => {
+                let mut position = 0;
+                infallible(move |s| {
+                    map.group_by_chunk(s, position, *size);
+                    position += 1;
+                })
+            }
+            GroupingSpecifier::Glob(rules) => infallible(move |s| map.group_by_glob(s, rules)),
+            GroupingSpecifier::UrlHost(domain) => {
+                infallible(move |s| map.group_by_url_host(s, *domain))
+            }
+            GroupingSpecifier::JsonKey(path) => infallible(move |s| map.group_by_json_key(s, path)),
+            GroupingSpecifier::CsvColumn(Column::Index(n), delimiter) => {
+                infallible(move |s| map.group_by_csv_column(s, *n, *delimiter))
+            }
+            GroupingSpecifier::CsvColumn(Column::Name(name), _) => fallible(move |_| {
+                Err(GroupByError::InvalidArgument(format!(
+                    "The CSV column {} must be looked up in a header row before grouping, so it \
+                    needs --csv",
+                    name
+                )))
+            }),
+            GroupingSpecifier::Logfmt(key) => infallible(move |s| map.group_by_logfmt(s, key)),
+            GroupingSpecifier::KeyExpr(expr) => infallible(move |s| map.group_by_key_expr(s, expr)),
+            GroupingSpecifier::KeyCommand(command, batch, shell) => {
+                // Hold values until there's a batch's worth, then run the command over them all.
+                let mut pending: Vec<String> = vec![];
+                Box::new(move |s: Option<S>| {
+                    if let Some(s) = s {
+                        pending.push(s.into());
+                        if pending.len() < *batch {
+                            return Ok(());
+                        }
+                    }
+                    if pending.is_empty() {
+                        return Ok(());
+                    }
+                    let shell = match shell {
+                        Some(shell) => shell.clone(),
+                        None => current_shell()?,
+                    };
+                    let keys = key_command::keys(&shell, command, &pending)?;
+                    for (key, value) in keys.into_iter().zip(pending.drain(..)) {
+                        map.add(key, value);
+                    }
+                    Ok(())
+                })
+            }
+            GroupingSpecifier::Composite(specs, separator) => {
+                // Give each part a recorder of its own, so its keys can be matched to lines.
+                let mut parts: Vec<_> = specs
+                    .iter()
+                    .map(|spec| {
+                        let keys = Rc::new(RefCell::new(vec![]));
+                        let batch = match spec {
+                            GroupingSpecifier::KeyCommand(_, batch, _) => Some(*batch),
+                            _ => None,
+                        };
+                        (
+                            Runner::<String>::runner(KeyRecorder::new(&keys), spec),
+                            keys,
+                            batch,
+                        )
+                    })
+                    .collect();
+
+                // Hold lines until the largest key command batch is full, so that key commands
+                // run over whole batches, as they do outside composites.
+                let size = parts.iter().filter_map(|(_, _, batch)| *batch).max();
+                let mut pending: Vec<String> = vec![];
+                Box::new(move |s: Option<S>| {
+                    if let Some(s) = s {
+                        pending.push(s.into());
+                        if pending.len() < size.unwrap_or(1) {
+                            return Ok(());
+                        }
+                    }
+                    if pending.is_empty() {
+                        return Ok(());
+                    }
+                    let lines = mem::take(&mut pending);
+
+                    // The keys each line gets from each part, in order. A part that skips a line
+                    // (see Unmatched::Skip) chooses no key for it.
+                    let mut chosen: Vec<Vec<Option<String>>> = vec![vec![]; lines.len()];
+                    for (part, keys, batch) in parts.iter_mut() {
+                        let result = if batch.is_some() {
+                            // A key command chooses a key for every line, in order.
+                            lines
+                                .iter()
+                                .try_for_each(|line| part(Some(line.clone())))
+                                .and_then(|()| part(None))
+                                .map(|()| keys.borrow_mut().drain(..).map(Some).collect())
+                        } else {
+                            // Finish the part after each line, so its key is known at once.
+                            lines
+                                .iter()
+                                .map(|line| {
+                                    part(Some(line.clone())).and_then(|()| part(None))?;
+                                    Ok(keys.borrow_mut().pop())
+                                })
+                                .collect::<Result<Vec<_>, _>>()
+                        };
+                        match result {
+                            Ok(part_keys) => {
+                                for (line_keys, key) in chosen.iter_mut().zip(part_keys) {
+                                    line_keys.push(key);
+                                }
+                            }
+                            Err(e) => {
+                                // Drop any keys already chosen, so the next line starts fresh.
+                                for (_, keys, _) in parts.iter() {
+                                    keys.borrow_mut().clear();
+                                }
+                                return Err(e);
+                            }
+                        }
+                    }
+
+                    // A line that any part skips is skipped as a whole.
+                    for (line, line_keys) in lines.into_iter().zip(chosen) {
+                        if let Some(line_keys) = line_keys.into_iter().collect::<Option<Vec<_>>>() {
+                            map.add(line_keys.join(separator), line);
+                        }
+                    }
+                    Ok(())
+                })
+            }
+            GroupingSpecifier::IgnoreCase(_) => unreachable!("Runner::new unwraps IgnoreCase"),
+            GroupingSpecifier::Named(name) => match registry::get(name) {
+                Some(grouper) => infallible(move |s: S| {
+                    let line: String = s.into();
+                    map.add(grouper(&line), line);
+                }),
+                None => fallible(move |_| {
+                    Err(GroupByError::InvalidArgument(format!(
+                        "No grouper is registered as {}",
+                        name
+                    )))
+                }),
+            },
+        }
     }
 
     /// Syntactic sugar so you can write `runner.run(value)` instead of `(runner.run)(value)`.
-    pub fn run(&mut self, value: S) {
-        (self.run)(value);
+    ///
+    /// Most groupers add `value` to the collection at once, but [GroupingSpecifier::KeyCommand],
+    /// and any [GroupingSpecifier::Composite] with one among its parts, holds values until it has
+    /// a batch, so call [Runner::finish] after the last value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [GroupByError::InvalidInput] if the grouper rejects `value`, e.g. a
+    /// [GroupingSpecifier::Regex] with [Unmatched::Error] that doesn't match it. Returns
+    /// [GroupByError::InvalidArgument] if the specifier can't be run at all, e.g. a
+    /// [GroupingSpecifier::Named] grouper that isn't registered, or a
+    /// [GroupingSpecifier::CsvColumn] name that hasn't been resolved with
+    /// [crate::command_line::csv::resolve_columns].
+    pub fn run(&mut self, value: S) -> Result<(), GroupByError> {
+        (self.run)(Some(value))
+    }
+
+    /// Adds any values that the grouper is still holding to the collection. Call this once every
+    /// value has been run; afterward, the runner can keep running values.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error that adding the held values causes, e.g. a
+    /// [GroupingSpecifier::KeyCommand] whose command fails.
+    pub fn finish(&mut self) -> Result<(), GroupByError> {
+        (self.run)(None)
     }
 }
 
@@ -226,7 +1387,7 @@ mod tests {
         fn matches(spec: GroupingSpecifier, value: &str, expected_key: &str) {
             let mut map = FakeMap::new();
             let mut runner = Runner::new(&mut map, &spec);
-            runner.run(value.to_string());
+            runner.run(value.to_string()).unwrap();
             drop(runner);
             assert_eq!(*map.calls(), vec![format!("{}:{}", expected_key, value)]);
         }
@@ -241,15 +1402,119 @@ mod tests {
             matches(GroupingSpecifier::LastChars(1), "abc", "c");
         }
 
+        #[test]
+        fn matches_first_bytes() {
+            matches(GroupingSpecifier::FirstBytes(2), "ñb", "ñ");
+        }
+
+        #[test]
+        fn matches_last_bytes() {
+            matches(GroupingSpecifier::LastBytes(2), "añ", "ñ");
+        }
+
         #[test]
         fn matches_regex() {
             matches(
-                GroupingSpecifier::Regex(Regex::new("b").unwrap(), CaptureGroup::Number(0)),
+                GroupingSpecifier::Regex(
+                    Regex::new("b").unwrap(),
+                    CaptureGroup::Number(0),
+                    Unmatched::Empty,
+                ),
                 "abc",
                 "b",
             );
         }
 
+        #[test]
+        fn applies_unmatched_policy() {
+            let regex = Regex::new("b").unwrap();
+            for (unmatched, expected) in [
+                (Unmatched::Empty, vec![":xyz"]),
+                (Unmatched::Skip, vec![]),
+                (Unmatched::Group("other".to_string()), vec!["other:xyz"]),
+            ] {
+                let mut map = FakeMap::new();
+                let spec =
+                    GroupingSpecifier::Regex(regex.clone(), CaptureGroup::Number(0), unmatched);
+                let mut runner = Runner::new(&mut map, &spec);
+                runner.run("xyz".to_string()).unwrap();
+                drop(runner);
+                assert_eq!(*map.calls(), expected);
+            }
+        }
+
+        #[test]
+        fn fails_on_unmatched_token_with_error_policy() {
+            let mut map = FakeMap::new();
+            let spec = GroupingSpecifier::Regex(
+                Regex::new("b").unwrap(),
+                CaptureGroup::Number(0),
+                Unmatched::Error,
+            );
+            let mut runner = Runner::new(&mut map, &spec);
+            runner.run("abc".to_string()).unwrap();
+            let error = runner.run("xyz".to_string()).unwrap_err();
+            drop(runner);
+            assert_eq!(
+                error.to_string(),
+                GroupByError::InvalidInput(
+                    "The regular expression /b/ doesn't match: xyz".to_string()
+                )
+                .to_string(),
+            );
+            assert_eq!(*map.calls(), vec!["b:abc"]);
+        }
+
+        #[test]
+        fn groups_by_first_matching_regex_rule() {
+            let rules = vec![
+                "errors=ERROR".parse().unwrap(),
+                r"^(\w+):".parse().unwrap(),
+                "digits=\\d".parse().unwrap(),
+            ];
+            let mut map = FakeMap::new();
+            let spec =
+                GroupingSpecifier::RegexRules(rules, CaptureGroup::Default, Unmatched::Error);
+            let mut runner = Runner::new(&mut map, &spec);
+            runner.run("db: ERROR 1".to_string()).unwrap();
+            runner.run("db: ok 2".to_string()).unwrap();
+            runner.run("3 ok".to_string()).unwrap();
+            let error = runner.run("ok".to_string()).unwrap_err();
+            drop(runner);
+            assert_eq!(
+                error.to_string(),
+                GroupByError::InvalidInput(
+                    r"None of the regular expressions /ERROR/, /^(\w+):/, /\d/ match: ok"
+                        .to_string()
+                )
+                .to_string(),
+            );
+            assert_eq!(
+                *map.calls(),
+                vec!["errors:db: ERROR 1", "db:db: ok 2", "digits:3 ok"]
+            );
+        }
+
+        #[test]
+        fn skips_composite_token_when_a_part_skips_it() {
+            let parts = vec![
+                GroupingSpecifier::FirstChars(1),
+                GroupingSpecifier::Regex(
+                    Regex::new(r"\d").unwrap(),
+                    CaptureGroup::Number(0),
+                    Unmatched::Skip,
+                ),
+            ];
+            let spec = GroupingSpecifier::Composite(parts, " ".to_string());
+            let mut map = FakeMap::new();
+            let mut runner = Runner::new(&mut map, &spec);
+            for value in ["a1", "bc", "d2"] {
+                runner.run(value.to_string()).unwrap();
+            }
+            drop(runner);
+            assert_eq!(*map.calls(), vec!["a 1:a1", "d 2:d2"]);
+        }
+
         #[test]
         fn matches_file_extension() {
             matches(GroupingSpecifier::FileExtension, "abc.txt", "txt");
@@ -261,5 +1526,268 @@ mod tests {
             matches(GroupingSpecifier::Counter, "abc", "1");
             matches(GroupingSpecifier::Counter, "abc", "2");
         }
+
+        #[test]
+        fn matches_key_value() {
+            let mut map = FakeMap::new();
+            let spec = GroupingSpecifier::KeyValue("=".to_string());
+            let mut runner = Runner::new(&mut map, &spec);
+            runner.run("a=b".to_string()).unwrap();
+            drop(runner);
+            assert_eq!(*map.calls(), vec!["a:b"]);
+        }
+
+        #[test]
+        fn matches_nth_word() {
+            matches(
+                GroupingSpecifier::NthWord(2, Regex::new(r"\w+").unwrap()),
+                "a-b-c",
+                "b",
+            );
+        }
+
+        #[test]
+        fn matches_timestamp() {
+            matches(
+                GroupingSpecifier::Timestamp("%Y-%m".parse().unwrap(), TimeBucket::Year),
+                "at 2024-03",
+                "2024",
+            );
+        }
+
+        #[test]
+        fn matches_numeric_bucket() {
+            matches(GroupingSpecifier::NumericBucket(10), "size 25", "20-29");
+        }
+
+        #[test]
+        fn matches_ignore_case() {
+            let spec = GroupingSpecifier::FileExtension;
+            matches(
+                GroupingSpecifier::IgnoreCase(Box::new(spec)),
+                "a.TxT",
+                "txt",
+            );
+        }
+
+        #[test]
+        fn matches_size_bucket() {
+            let spec = GroupingSpecifier::SizeBucket("10,1M".parse().unwrap());
+            matches(spec, "Cargo.toml", "10-1M");
+        }
+
+        #[test]
+        fn matches_mtime() {
+            matches(
+                GroupingSpecifier::Mtime(TimeBucket::Day),
+                "no/such/file",
+                "",
+            );
+        }
+
+        #[test]
+        fn matches_mime_type() {
+            matches(GroupingSpecifier::MimeType, "Cargo.toml", "text/plain");
+        }
+
+        #[test]
+        fn matches_path_component() {
+            matches(GroupingSpecifier::PathComponent(2), "./a/b/c", "b");
+        }
+
+        #[test]
+        fn matches_basename() {
+            matches(GroupingSpecifier::Basename, "a/b/c.txt", "c.txt");
+        }
+
+        #[test]
+        fn matches_dirname() {
+            matches(GroupingSpecifier::Dirname, "a/b/c.txt", "a/b");
+        }
+
+        #[test]
+        fn matches_length() {
+            matches(GroupingSpecifier::Length, "naïve", "5");
+        }
+
+        #[test]
+        fn matches_shard() {
+            matches(GroupingSpecifier::Shard(1), "abc", "0");
+        }
+
+        #[test]
+        fn matches_chunk() {
+            let mut map = FakeMap::new();
+            let spec = GroupingSpecifier::Chunk(2);
+            let mut runner = Runner::new(&mut map, &spec);
+            for value in ["a", "b", "c"] {
+                runner.run(value.to_string()).unwrap();
+            }
+            drop(runner);
+            assert_eq!(*map.calls(), vec!["0:a", "0:b", "1:c"]);
+        }
+
+        #[test]
+        fn matches_composite() {
+            let parts = vec![
+                GroupingSpecifier::Field(1, ",".to_string()),
+                GroupingSpecifier::IgnoreCase(Box::new(GroupingSpecifier::FileExtension)),
+            ];
+            let spec = GroupingSpecifier::Composite(parts, "/".to_string());
+            matches(spec, "x,y.TXT", "x/txt");
+        }
+
+        #[test]
+        fn matches_composite_with_stateful_parts() {
+            let parts = vec![
+                GroupingSpecifier::Chunk(2),
+                GroupingSpecifier::FirstChars(1),
+            ];
+            let spec = GroupingSpecifier::Composite(parts, " ".to_string());
+            let mut map = FakeMap::new();
+            let mut runner = Runner::new(&mut map, &spec);
+            for value in ["ab", "cd", "ef"] {
+                runner.run(value.to_string()).unwrap();
+            }
+            drop(runner);
+            assert_eq!(*map.calls(), vec!["0 a:ab", "0 c:cd", "1 e:ef"]);
+        }
+
+        #[test]
+        fn matches_url_host() {
+            let url = "https://www.example.co.uk:443/";
+            matches(GroupingSpecifier::UrlHost(false), url, "www.example.co.uk");
+            matches(GroupingSpecifier::UrlHost(true), url, "example.co.uk");
+        }
+
+        #[test]
+        fn matches_json_key() {
+            let spec = GroupingSpecifier::JsonKey(".user.id".parse().unwrap());
+            matches(spec, r#"{"user": {"id": 3}}"#, "3");
+        }
+
+        #[test]
+        fn matches_csv_column() {
+            let spec = GroupingSpecifier::CsvColumn(Column::Index(2), ';');
+            matches(spec, "a;\"b;c\"", "b;c");
+        }
+
+        #[test]
+        fn matches_logfmt() {
+            let spec = GroupingSpecifier::Logfmt("user".to_string());
+            matches(spec, "at=info user=\"ann lee\"", "ann lee");
+        }
+
+        #[test]
+        fn matches_key_expr() {
+            let spec = GroupingSpecifier::KeyExpr("upper(first(2, word(2)))".parse().unwrap());
+            matches(spec, "GET /index.html", "/I");
+        }
+
+        #[test]
+        fn holds_key_command_values_until_a_batch_is_full() {
+            let spec = GroupingSpecifier::KeyCommand("tr a-z A-Z".to_string(), 2, None);
+            let mut map = FakeMap::new();
+            let mut runner = Runner::new(&mut map, &spec);
+            runner.run("ab".to_string()).unwrap();
+            runner.run("cd".to_string()).unwrap();
+            runner.run("e".to_string()).unwrap();
+            runner.finish().unwrap();
+            drop(runner);
+            assert_eq!(*map.calls(), vec!["AB:ab", "CD:cd", "E:e"]);
+        }
+
+        #[test]
+        fn runs_key_commands_in_the_given_shell() {
+            let spec =
+                GroupingSpecifier::KeyCommand("cat".to_string(), 1, Some("/nonexistent".into()));
+            let mut map = FakeMap::new();
+            let mut runner = Runner::new(&mut map, &spec);
+            assert!(runner.run("ab".to_string()).is_err());
+        }
+
+        #[test]
+        fn finishes_key_commands_in_composite_keys() {
+            let parts = vec![
+                GroupingSpecifier::KeyCommand("cut -c1".to_string(), 100, None),
+                GroupingSpecifier::LastChars(1),
+            ];
+            let spec = GroupingSpecifier::Composite(parts, "-".to_string());
+            let mut map = FakeMap::new();
+            let mut runner = Runner::new(&mut map, &spec);
+            runner.run("abc".to_string()).unwrap();
+            runner.finish().unwrap();
+            drop(runner);
+            assert_eq!(*map.calls(), vec!["a-c:abc"]);
+        }
+
+        #[test]
+        fn batches_key_commands_in_composite_keys() {
+            // The key command's key is the number of tokens in its batch.
+            let parts = vec![
+                GroupingSpecifier::KeyCommand(
+                    "awk '{ n++ } END { while (n2++ < n) print n }'".to_string(),
+                    2,
+                    None,
+                ),
+                GroupingSpecifier::Regex(
+                    Regex::new(r"\d").unwrap(),
+                    CaptureGroup::Number(0),
+                    Unmatched::Skip,
+                ),
+            ];
+            let spec = GroupingSpecifier::Composite(parts, " ".to_string());
+            let mut map = FakeMap::new();
+            let mut runner = Runner::new(&mut map, &spec);
+            runner.run("a1".to_string()).unwrap();
+            runner.run("bc".to_string()).unwrap();
+            runner.run("d2".to_string()).unwrap();
+            runner.finish().unwrap();
+            drop(runner);
+            assert_eq!(*map.calls(), vec!["2 1:a1", "1 2:d2"]);
+        }
+
+        #[test]
+        fn matches_named() {
+            registry::register("runner_test_upper", Box::new(|s: &str| s.to_uppercase()));
+            let spec = GroupingSpecifier::Named("runner_test_upper".to_string());
+            matches(spec, "abc", "ABC");
+        }
+
+        #[test]
+        fn rejects_unregistered_names() {
+            let mut map = FakeMap::new();
+            let spec = GroupingSpecifier::Named("runner_test_missing".to_string());
+            let mut runner = Runner::new(&mut map, &spec);
+            assert!(matches!(
+                runner.run("abc".to_string()),
+                Err(GroupByError::InvalidArgument(_))
+            ));
+        }
+
+        #[test]
+        fn rejects_unresolved_csv_column_names() {
+            let mut map = FakeMap::new();
+            let spec = GroupingSpecifier::CsvColumn(Column::Name("status".to_string()), ',');
+            let mut runner = Runner::new(&mut map, &spec);
+            assert!(matches!(
+                runner.run("200,ok".to_string()),
+                Err(GroupByError::InvalidArgument(_))
+            ));
+        }
+
+        #[test]
+        fn matches_glob() {
+            let rules = vec!["rust=*.rs".parse().unwrap(), "docs=*.md".parse().unwrap()];
+            let spec = GroupingSpecifier::Glob(rules);
+            matches(spec.clone(), "src/lib.rs", "rust");
+            matches(spec.clone(), "README.md", "docs");
+            matches(spec, "Cargo.toml", "other");
+        }
+
+        #[test]
+        fn matches_field() {
+            matches(GroupingSpecifier::Field(2, ",".to_string()), "a,b,c", "b");
+        }
     }
 }
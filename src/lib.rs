@@ -51,16 +51,14 @@
 //! let options = GroupByOptions {
 //!     input: InputOptions {
 //!         separator: Separator::Null,
+//!         ..Default::default()
 //!     },
 //!     grouping: GroupingSpecifier::FirstChars(6),
 //!     output: OutputOptions {
-//!         separator: Separator::Line,
-//!         only_group_names: false,
 //!         run_command: Some("wc -l".to_string()),
-//!         parallel: true,
-//!         headers: true,
-//!         stats: false,
+//!         ..Default::default()
 //!     },
+//!     ..Default::default()
 //! };
 //!
 //! // The GroupedCollection we'll use. HashMap is also supported but doesn't preserve group order.
@@ -73,17 +71,18 @@
 //!     ecs450 study guide.pdf";
 //!
 //! // Split tokens by null characters, group them by class, and add them to the map.
-//! command_line::build_groups(simulated_input.as_bytes(), &mut map, &options);
+//! command_line::build_groups(simulated_input.as_bytes(), &mut map, &options)?;
 //!
 //! // A complete application might use io::stdout().
 //! let mut output = Vec::new();
 //!
 //! // Run `wc -l` once for each group, pass the group's contents to the group's stdin, and collect
-//! // each command's stdout in a BTreeMap.
-//! let results = command_line::run_command(&map, &options.output);
+//! // each command's output in a BTreeMap. (With incremental output, results would be written to
+//! // the output writer as they arrive.)
+//! let results = command_line::run_command(&mut output, &map, &options.output)?;
 //!
 //! // Report the results to the output writer.
-//! command_line::write_results(&mut output, &map, &results, &options.output);
+//! command_line::write_results(&mut output, &map, &results, &options.output)?;
 //!
 //! assert_eq!(String::from_utf8_lossy(&output),
 //! "ecs440:
@@ -91,9 +90,15 @@
 //!
 //! ecs450:
 //! 2\n\n");
+//! # Ok::<(), command_line::GroupByError>(())
 //! ```
 
 pub mod command_line;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod grouped_collections;
 pub mod groupers;
 pub mod matchers;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod random;
@@ -0,0 +1,97 @@
+//! Matchers for byte strings, i.e. `[u8]` values, which need not be valid UTF-8.
+//!
+//! These parallel the matchers in [string](crate::matchers::string) for input that can't be read
+//! as text, such as file names on Unix or logs with binary fields.
+
+use crate::command_line::CaptureGroup;
+use regex::bytes::{Captures, Regex};
+use std::borrow::Cow;
+
+/// Returns the first n bytes of a byte string, or all of it if it's shorter.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::bytes;
+///
+/// assert_eq!(b"Hello", bytes::match_first_n_bytes(b"Hello, world", 5));
+/// assert_eq!(b"Gr\xfc", bytes::match_first_n_bytes(b"Gr\xfc\xdfe", 3));
+/// assert_eq!(b"", bytes::match_first_n_bytes(b"", 3));
+/// ```
+pub fn match_first_n_bytes(bytes: &[u8], n: usize) -> &[u8] {
+    &bytes[..n.min(bytes.len())]
+}
+
+/// Returns the last n bytes of a byte string, or all of it if it's shorter.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::bytes;
+///
+/// assert_eq!(b"world", bytes::match_last_n_bytes(b"Hello, world", 5));
+/// assert_eq!(b"\xdfe", bytes::match_last_n_bytes(b"Gr\xfc\xdfe", 2));
+/// assert_eq!(b"", bytes::match_last_n_bytes(b"Hello", 0));
+/// ```
+pub fn match_last_n_bytes(bytes: &[u8], n: usize) -> &[u8] {
+    &bytes[bytes.len().saturating_sub(n)..]
+}
+
+/// Returns the first match of the regular expression (or capture group) within a byte string, if
+/// any.
+///
+/// Capture groups work as in [string::match_regex](crate::matchers::string::match_regex). Unlike
+/// [Regex](regex::Regex), a [bytes::Regex](regex::bytes::Regex) can match bytes that aren't valid
+/// UTF-8, e.g. with `(?-u:\xff)`.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::CaptureGroup;
+/// use groupby::matchers::bytes;
+/// use regex::bytes::Regex;
+///
+/// let name = b"report-\xff\xfe.log";
+/// let stem = Regex::new(r"^(\w+)-").unwrap();
+/// let extension = Regex::new(r"\.(?P<ext>\w+)$").unwrap();
+///
+/// assert_eq!(
+///     Some(b"report"[..].into()),
+///     bytes::match_regex(name, &stem, &CaptureGroup::Default),
+/// );
+/// assert_eq!(
+///     Some(b"report-"[..].into()),
+///     bytes::match_regex(name, &stem, &CaptureGroup::Number(0)),
+/// );
+/// assert_eq!(
+///     Some(b"log"[..].into()),
+///     bytes::match_regex(name, &extension, &CaptureGroup::Name("ext".to_string())),
+/// );
+/// assert_eq!(None, bytes::match_regex(b"\xff", &stem, &CaptureGroup::Default));
+/// ```
+pub fn match_regex<'a>(
+    bytes: &'a [u8],
+    regex: &Regex,
+    capture_group: &CaptureGroup,
+) -> Option<Cow<'a, [u8]>> {
+    capture(&regex.captures(bytes)?, capture_group)
+}
+
+// Returns capture_group out of captures, for match_regex.
+fn capture<'a>(captures: &Captures<'a>, capture_group: &CaptureGroup) -> Option<Cow<'a, [u8]>> {
+    match capture_group {
+        CaptureGroup::Number(n) => captures.get(*n).map(|mat| mat.as_bytes().into()),
+        CaptureGroup::Name(s) => captures.name(s).map(|mat| mat.as_bytes().into()),
+        CaptureGroup::Default => captures
+            .get(1)
+            .or_else(|| captures.get(0))
+            .map(|mat| mat.as_bytes().into()),
+        CaptureGroup::Joined(groups, joiner) => {
+            let parts: Vec<_> = groups
+                .iter()
+                .map(|group| capture(captures, group))
+                .collect::<Option<_>>()?;
+            Some(parts.join(joiner.as_bytes()).into())
+        }
+    }
+}
@@ -16,4 +16,5 @@
 //!
 //! The organization of this module and submodules parallels that of [groupers](crate::groupers).
 
+pub mod bytes;
 pub mod string;
@@ -1,12 +1,26 @@
 //! Matchers for [String] values.
 
+use crate::command_line::csv;
+use crate::command_line::globs::{self, GlobRule};
+use crate::command_line::json::{self, JsonPath};
+use crate::command_line::key_expr::KeyExpr;
+use crate::command_line::mime;
+use crate::command_line::options::RegexRule;
+use crate::command_line::size_buckets::SizeBuckets;
+use crate::command_line::timestamps::{self, TimeBucket, TimestampFormat};
 use crate::command_line::CaptureGroup;
+use crate::random::{self, Rng};
 use global_counter::primitive::exact::CounterUsize;
-use regex::Regex;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Component, Path};
 
 /// Returns the first n characters of a string.
 ///
-/// Returns the first `n` characters of `string`, or all of `string` if `n > string.len()`.
+/// Returns the first `n` characters (Unicode scalar values, i.e. [char]s) of `string`, or all of
+/// `string` if it has fewer than `n` characters.
 ///
 /// If `string == ""` or `n == 0`, returns `""`.
 ///
@@ -20,18 +34,19 @@ use regex::Regex;
 /// assert_eq!("Hello, world", string::match_first_n_chars(string, 20));
 /// assert_eq!("", string::match_first_n_chars("", 5));
 /// assert_eq!("", string::match_first_n_chars(string, 0));
+/// assert_eq!("Grü", string::match_first_n_chars("Grüße", 3));
 /// ```
 pub fn match_first_n_chars(string: &str, n: usize) -> &str {
-    if n > string.len() {
-        string
-    } else {
-        &string[0..n]
+    match string.char_indices().nth(n) {
+        Some((end, _)) => &string[..end],
+        None => string,
     }
 }
 
-/// Returns the lsat n characters of a string.
+/// Returns the last n characters of a string.
 ///
-/// Returns the last `n` characters of `string`, or all of `string` if `n > string.len()`.
+/// Returns the last `n` characters (Unicode scalar values, i.e. [char]s) of `string`, or all of
+/// `string` if it has fewer than `n` characters.
 ///
 /// If `string == ""` or `n == 0`, returns `""`.
 ///
@@ -45,17 +60,61 @@ pub fn match_first_n_chars(string: &str, n: usize) -> &str {
 /// assert_eq!("Hello, world", string::match_last_n_chars(string, 20));
 /// assert_eq!("", string::match_last_n_chars("", 5));
 /// assert_eq!("", string::match_last_n_chars(string, 0));
+/// assert_eq!("ße", string::match_last_n_chars("Grüße", 2));
 /// ```
 pub fn match_last_n_chars(string: &str, n: usize) -> &str {
-    if n > string.len() {
-        string
-    } else {
-        &string[(string.len() - n)..]
+    if n == 0 {
+        return "";
+    }
+    match string.char_indices().nth_back(n - 1) {
+        Some((start, _)) => &string[start..],
+        None => string,
     }
 }
 
+/// Returns the first n bytes of a string, or all of it if it's shorter.
+///
+/// If the nth byte falls in the middle of a multibyte character, the partial character is
+/// replaced with U+FFFD, the replacement character, as with [String::from_utf8_lossy].
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!("Hello", string::match_first_n_bytes("Hello, world", 5));
+/// assert_eq!("Gr\u{fffd}", string::match_first_n_bytes("Grüße", 3));
+/// assert_eq!("Grü", string::match_first_n_bytes("Grüße", 4));
+/// ```
+pub fn match_first_n_bytes(string: &str, n: usize) -> Cow<'_, str> {
+    let bytes = string.as_bytes();
+    String::from_utf8_lossy(&bytes[..n.min(bytes.len())])
+}
+
+/// Returns the last n bytes of a string, or all of it if it's shorter.
+///
+/// If the nth-from-last byte falls in the middle of a multibyte character, the partial character
+/// is replaced with U+FFFD, the replacement character, as with [String::from_utf8_lossy].
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!("world", string::match_last_n_bytes("Hello, world", 5));
+/// assert_eq!("\u{fffd}e", string::match_last_n_bytes("Grüße", 2));
+/// assert_eq!("ße", string::match_last_n_bytes("Grüße", 3));
+/// ```
+pub fn match_last_n_bytes(string: &str, n: usize) -> Cow<'_, str> {
+    let bytes = string.as_bytes();
+    String::from_utf8_lossy(&bytes[bytes.len().saturating_sub(n)..])
+}
+
 /// Returns the first match of the regular expression (or capture group) within a string, if any.
 ///
+/// With [CaptureGroup::Joined], returns the capture groups joined into a new string, as long as
+/// every one of them took part in the match.
+///
 /// # Examples
 ///
 /// ```
@@ -70,37 +129,37 @@ pub fn match_last_n_chars(string: &str, n: usize) -> &str {
 ///
 /// // Default behavior: returns capture group 1 if present; otherwise, returns the entire match.
 /// assert_eq!(
-///     Some("Bishop"),
+///     Some("Bishop".into()),
 ///     string::match_regex("Bishop takes queen", &first_word, &CaptureGroup::Default),
 /// );
 /// assert_eq!(
-///     Some("takes"),
+///     Some("takes".into()),
 ///     string::match_regex("Bishop takes queen", &second_word, &CaptureGroup::Default),
 /// );
 /// assert_eq!(
-///     Some("queen"),
+///     Some("queen".into()),
 ///     string::match_regex("Bishop takes queen", &third_word, &CaptureGroup::Default),
 /// );
 ///
 /// // `CaptureGroup::Number(0)` returns the entire match.
 /// assert_eq!(
-///     Some("Bishop"),
+///     Some("Bishop".into()),
 ///     string::match_regex("Bishop takes queen", &first_word, &CaptureGroup::Number(0)),
 /// );
 ///
 /// // `CaptureGroup::Number(1)` returns the first capture group.
 /// assert_eq!(
-///     Some("takes"),
+///     Some("takes".into()),
 ///     string::match_regex("Bishop takes queen", &second_word, &CaptureGroup::Number(1)),
 /// );
 /// assert_eq!(
-///     Some("queen"),
+///     Some("queen".into()),
 ///     string::match_regex("Bishop takes queen", &third_word, &CaptureGroup::Number(1)),
 /// );
 ///
 /// // `CaptureGroup::Number(n)` returns the nth capture group.
 /// assert_eq!(
-///     Some("queen"),
+///     Some("queen".into()),
 ///     string::match_regex(
 ///         "Bishop takes queen", &third_capture_group, &CaptureGroup::Number(3),
 ///     ),
@@ -108,29 +167,84 @@ pub fn match_last_n_chars(string: &str, n: usize) -> &str {
 ///
 /// // `CaptureGroup::Name(s)` returns a capture group by name.
 /// assert_eq!(
-///     Some("takes"),
+///     Some("takes".into()),
 ///     string::match_regex(
 ///         "Bishop takes queen", &named_second_word, &CaptureGroup::Name("second".to_string()),
 ///     ),
 /// );
+///
+/// // `CaptureGroup::Joined` joins several capture groups, in the order given.
+/// let joined = CaptureGroup::Joined(
+///     vec![CaptureGroup::Number(3), CaptureGroup::Number(1)],
+///     "/".to_string(),
+/// );
+/// assert_eq!(
+///     Some("queen/Bishop".into()),
+///     string::match_regex("Bishop takes queen", &third_capture_group, &joined),
+/// );
 /// ```
 pub fn match_regex<'a>(
     string: &'a str,
     regex: &Regex,
     capture_group: &CaptureGroup,
-) -> Option<&'a str> {
-    let captures = match regex.captures(string) {
-        Some(captures) => captures,
-        None => return None,
-    };
+) -> Option<Cow<'a, str>> {
+    capture(&regex.captures(string)?, capture_group)
+}
 
+/// Returns the key from the first of `rules` that matches `string`, or `None` if none match.
+///
+/// A named rule's key is its name. An unnamed rule's key is the part of its match that
+/// `capture_group` picks out, as for [match_regex]; an unnamed rule whose capture group doesn't
+/// take part in the match doesn't count as matching, and the next rule is tried.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::options::{CaptureGroup, RegexRule};
+/// use groupby::matchers::string;
+///
+/// let rules: Vec<RegexRule> = vec![
+///     "errors=ERROR|FATAL".parse().unwrap(),
+///     r"\[(\w+)\]".parse().unwrap(),
+/// ];
+/// let cg = CaptureGroup::Default;
+/// assert_eq!(
+///     Some("errors".into()),
+///     string::match_regex_rules("[db] FATAL: disk full", &rules, &cg),
+/// );
+/// assert_eq!(
+///     Some("db".into()),
+///     string::match_regex_rules("[db] connected", &rules, &cg),
+/// );
+/// assert_eq!(None, string::match_regex_rules("hello", &rules, &cg));
+/// ```
+pub fn match_regex_rules<'a>(
+    string: &'a str,
+    rules: &'a [RegexRule],
+    capture_group: &CaptureGroup,
+) -> Option<Cow<'a, str>> {
+    rules.iter().find_map(|rule| match &rule.name {
+        Some(name) => rule.regex.is_match(string).then(|| name.as_str().into()),
+        None => match_regex(string, &rule.regex, capture_group),
+    })
+}
+
+// Returns capture_group out of captures, for match_regex.
+fn capture<'a>(captures: &Captures<'a>, capture_group: &CaptureGroup) -> Option<Cow<'a, str>> {
     match capture_group {
-        CaptureGroup::Number(n) => captures.get(*n).map(|mat| mat.as_str()),
-        CaptureGroup::Name(s) => captures.name(s).map(|mat| mat.as_str()),
+        CaptureGroup::Number(n) => captures.get(*n).map(|mat| mat.as_str().into()),
+        CaptureGroup::Name(s) => captures.name(s).map(|mat| mat.as_str().into()),
         CaptureGroup::Default => captures
             .get(1)
-            .map(|mat| mat.as_str())
-            .or_else(|| captures.get(0).map(|mat| mat.as_str())),
+            .or_else(|| captures.get(0))
+            .map(|mat| mat.as_str().into()),
+        CaptureGroup::Joined(groups, joiner) => {
+            let parts: Vec<_> = groups
+                .iter()
+                .map(|group| capture(captures, group))
+                .collect::<Option<_>>()?;
+            Some(parts.join(joiner.as_str()).into())
+        }
     }
 }
 
@@ -169,6 +283,590 @@ pub fn match_file_extension(filename: &str) -> Option<&str> {
     }
 }
 
+/// Splits a pre-keyed record into its key and value at the first occurrence of a separator.
+///
+/// Returns `None` if the record doesn't contain the separator.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!(Some(("fruit", "apple")), string::match_key_value("fruit\tapple", "\t"));
+/// assert_eq!(Some(("a", "b=c")), string::match_key_value("a=b=c", "="));
+/// assert_eq!(Some(("", "value")), string::match_key_value("=value", "="));
+/// assert_eq!(None, string::match_key_value("no separator", "\t"));
+/// ```
+pub fn match_key_value<'a>(record: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
+    record.split_once(separator)
+}
+
+/// Scans a string for logfmt-style `key=value` pairs and returns the value for `key`.
+///
+/// Pairs are separated by whitespace. A value may be wrapped in double quotes to include spaces,
+/// in which case `\"` and `\\` stand for `"` and `\`. A key without `=` has an empty value. If
+/// `key` appears more than once, the first wins. Returns `None` if `key` doesn't appear.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// let line = r#"level=warn msg="disk \"/\" is full" retry"#;
+/// assert_eq!(Some("warn".to_string()), string::match_logfmt(line, "level"));
+/// assert_eq!(Some(r#"disk "/" is full"#.to_string()), string::match_logfmt(line, "msg"));
+/// assert_eq!(Some("".to_string()), string::match_logfmt(line, "retry"));
+/// assert_eq!(None, string::match_logfmt(line, "user"));
+/// ```
+pub fn match_logfmt(string: &str, key: &str) -> Option<String> {
+    let mut chars = string.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        chars.peek()?;
+
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| *c != '=' && !c.is_whitespace()) {
+            name.push(c);
+        }
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            if chars.next_if_eq(&'"').is_some() {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+            }
+        }
+
+        if name == key {
+            return Some(value);
+        }
+    }
+}
+
+/// Returns the `index`th field of a record whose fields are separated by `delimiter`, counting
+/// from 1, like `cut -f`.
+///
+/// Returns `None` if `index` is 0 or the record has fewer than `index` fields. A field can be
+/// empty, e.g. between two consecutive delimiters.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!(Some("root"), string::match_field("root:x:0:0", ":", 1));
+/// assert_eq!(Some("0"), string::match_field("root:x:0:0", ":", 3));
+/// assert_eq!(Some(""), string::match_field("a::c", ":", 2));
+/// assert_eq!(Some("a b"), string::match_field("a b", ":", 1));
+/// assert_eq!(None, string::match_field("a:b", ":", 3));
+/// assert_eq!(None, string::match_field("a:b", ":", 0));
+/// ```
+pub fn match_field<'a>(record: &'a str, delimiter: &str, index: usize) -> Option<&'a str> {
+    record.split(delimiter).nth(index.checked_sub(1)?)
+}
+
+/// Returns the `n`th word of a string, counting from 1, where a word is any match of `word_regex`.
+///
+/// There are many ways to define a word, and when grouping by words, the exact definition
+/// matters, so the caller must supply one. Words are found left to right and don't overlap, as
+/// with [Regex::find_iter]. Returns `None` if `n` is 0 or the string has fewer than `n` words.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+/// use regex::Regex;
+///
+/// let non_space = Regex::new(r"\S+").unwrap();
+/// let alphabetic = Regex::new(r"[[:alpha:]]+").unwrap();
+/// let line = "2024-01-01 ERROR: disk full";
+///
+/// assert_eq!(Some("ERROR:"), string::match_nth_word(line, 2, &non_space));
+/// assert_eq!(Some("ERROR"), string::match_nth_word(line, 1, &alphabetic));
+/// assert_eq!(Some("full"), string::match_nth_word(line, 3, &alphabetic));
+/// assert_eq!(None, string::match_nth_word(line, 5, &non_space));
+/// assert_eq!(None, string::match_nth_word(line, 0, &non_space));
+/// ```
+pub fn match_nth_word<'a>(string: &'a str, n: usize, word_regex: &Regex) -> Option<&'a str> {
+    word_regex
+        .find_iter(string)
+        .nth(n.checked_sub(1)?)
+        .map(|m| m.as_str())
+}
+
+/// Treats a string as a path and returns its final component, like `basename`.
+///
+/// A trailing `/` is ignored. Returns `None` if the path has no final component, e.g. `/` or
+/// a path ending in `..`.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!(Some("lib.rs"), string::match_basename("./src/lib.rs"));
+/// assert_eq!(Some("lib.rs"), string::match_basename("lib.rs"));
+/// assert_eq!(Some("src"), string::match_basename("/home/me/src/"));
+/// assert_eq!(None, string::match_basename("/"));
+/// assert_eq!(None, string::match_basename("src/.."));
+/// ```
+pub fn match_basename(path: &str) -> Option<&str> {
+    // Every component of a &str path is valid UTF-8.
+    Path::new(path).file_name().and_then(|name| name.to_str())
+}
+
+/// Treats a string as a path and returns everything but its final component, like `dirname`.
+///
+/// A trailing `/` is ignored. Paths with only one component, e.g. `lib.rs`, yield `.`, the
+/// current directory. Returns `None` if the path is a root, e.g. `/`, or empty.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!(Some("./src"), string::match_dirname("./src/lib.rs"));
+/// assert_eq!(Some("/home/me"), string::match_dirname("/home/me/src/"));
+/// assert_eq!(Some("/"), string::match_dirname("/etc"));
+/// assert_eq!(Some("."), string::match_dirname("lib.rs"));
+/// assert_eq!(None, string::match_dirname("/"));
+/// assert_eq!(None, string::match_dirname(""));
+/// ```
+pub fn match_dirname(path: &str) -> Option<&str> {
+    // Every component of a &str path is valid UTF-8.
+    match Path::new(path).parent()?.to_str()? {
+        "" => Some("."),
+        parent => Some(parent),
+    }
+}
+
+/// Treats a string as a path and returns its component at `depth`, counting from 1, e.g. the
+/// top-level directory at depth 1.
+///
+/// Leading `/` and `.` components are skipped, so depth 1 is `src` in `src/lib.rs`,
+/// `./src/lib.rs`, and `/src/lib.rs` alike. Repeated separators are ignored. Returns `None` if
+/// `depth` is 0 or the path has fewer than `depth` components.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!(Some("src"), string::match_path_component("./src/bin/groupby.rs", 1));
+/// assert_eq!(Some("bin"), string::match_path_component("./src/bin/groupby.rs", 2));
+/// assert_eq!(Some("usr"), string::match_path_component("/usr//lib", 1));
+/// assert_eq!(Some("groupby.rs"), string::match_path_component("src/bin/groupby.rs", 3));
+/// assert_eq!(None, string::match_path_component("src/bin/groupby.rs", 4));
+/// assert_eq!(None, string::match_path_component("src", 0));
+/// ```
+pub fn match_path_component(path: &str, depth: usize) -> Option<&str> {
+    Path::new(path)
+        .components()
+        .filter(|component| {
+            !matches!(
+                component,
+                Component::Prefix(_) | Component::RootDir | Component::CurDir
+            )
+        })
+        .nth(depth.checked_sub(1)?)
+        // Every component of a &str path is valid UTF-8.
+        .and_then(|component| component.as_os_str().to_str())
+}
+
+/// Finds the first timestamp in a string that matches `format` and returns its key for `bucket`,
+/// e.g. `"2024-03-09T14"` for [TimeBucket::Hour].
+///
+/// Returns `None` if the string contains no such timestamp. See
+/// [crate::command_line::timestamps] for the format syntax and bucket keys.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::timestamps::*;
+/// use groupby::matchers::string;
+///
+/// let format: TimestampFormat = "%Y-%m-%d %H:%M".parse().unwrap();
+/// let line = "2024-03-09 14:05 ERROR disk full";
+/// assert_eq!(
+///     Some("2024-03-09T14".to_string()),
+///     string::match_timestamp(line, &format, TimeBucket::Hour),
+/// );
+/// assert_eq!(
+///     Some("2024-03".to_string()),
+///     string::match_timestamp(line, &format, TimeBucket::Month),
+/// );
+/// assert_eq!(None, string::match_timestamp("ERROR disk full", &format, TimeBucket::Hour));
+/// ```
+pub fn match_timestamp(
+    string: &str,
+    format: &TimestampFormat,
+    bucket: TimeBucket,
+) -> Option<String> {
+    format.bucket(string, bucket)
+}
+
+/// Finds the first number in a string and returns the range of width `bucket_size` that contains
+/// it, e.g. `"100-199"` for 123 with a bucket size of 100.
+///
+/// Ranges start at multiples of `bucket_size` and are written as the first and last integers in
+/// them, separated by `-`. A number is a run of ASCII digits, optionally preceded by `-` (unless
+/// the `-` follows a letter or digit, as in `file-2`) and optionally followed by `.` and a
+/// fraction. Fractions count toward the range, so `99.5` falls in `0-99` and `-0.5` in `-100--1`.
+///
+/// Returns `None` if the string contains no number or the number doesn't fit in an [i64].
+///
+/// # Panics
+///
+/// Panics if `bucket_size` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!(Some("0-99".to_string()), string::match_numeric_bucket("42", 100));
+/// assert_eq!(Some("100-199".to_string()), string::match_numeric_bucket("took 123ms", 100));
+/// assert_eq!(Some("1000-1999".to_string()), string::match_numeric_bucket("1999.9 KB", 1000));
+/// assert_eq!(Some("-10--1".to_string()), string::match_numeric_bucket("delta: -3", 10));
+/// assert_eq!(Some("0-9".to_string()), string::match_numeric_bucket("file-2", 10));
+/// assert_eq!(None, string::match_numeric_bucket("no digits", 10));
+/// ```
+pub fn match_numeric_bucket(string: &str, bucket_size: u64) -> Option<String> {
+    assert!(bucket_size > 0, "Bucket size must be at least 1");
+    let bytes = string.as_bytes();
+    let start = bytes.iter().position(u8::is_ascii_digit)?;
+    let end = start
+        + bytes[start..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+    let negative = start > 0
+        && bytes[start - 1] == b'-'
+        && (start < 2 || !bytes[start - 2].is_ascii_alphanumeric());
+    let has_fraction = bytes.get(end) == Some(&b'.')
+        && bytes[end + 1..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .any(|b| *b != b'0');
+
+    let mut number: i64 = string[start..end].parse().ok()?;
+    if negative {
+        number = -number;
+        if has_fraction {
+            number = number.checked_sub(1)?;
+        }
+    }
+
+    let size = i64::try_from(bucket_size).ok()?;
+    let low = number.div_euclid(size).checked_mul(size)?;
+    let high = low.checked_add(size - 1)?;
+    Some(format!("{}-{}", low, high))
+}
+
+/// Treats a string as a path and returns the name of the [SizeBuckets] range that contains the
+/// size of the file it names, e.g. `"1K-1M"`.
+///
+/// Follows symbolic links. Returns `None` if the file's metadata can't be read, e.g. because it
+/// doesn't exist.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::size_buckets::SizeBuckets;
+/// use groupby::matchers::string;
+///
+/// let buckets: SizeBuckets = "10,1M".parse().unwrap();
+/// assert_eq!(Some("10-1M"), string::match_size_bucket("Cargo.toml", &buckets));
+/// assert_eq!(None, string::match_size_bucket("no/such/file", &buckets));
+/// ```
+pub fn match_size_bucket<'a>(path: &str, buckets: &'a SizeBuckets) -> Option<&'a str> {
+    let metadata = fs::metadata(path).ok()?;
+    Some(buckets.bucket(metadata.len()))
+}
+
+/// Treats a string as a path and returns the [TimeBucket] key, in UTC, of the modification time
+/// of the file it names, e.g. `"2024-W10"` for [TimeBucket::Week].
+///
+/// Follows symbolic links. Returns `None` if the modification time can't be read, e.g. because
+/// the file doesn't exist. See [timestamps::bucket_time] for details.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::timestamps::TimeBucket;
+/// use groupby::matchers::string;
+///
+/// let month = string::match_mtime("Cargo.toml", TimeBucket::Month).unwrap();
+/// assert_eq!(month.len(), "2024-03".len());
+/// assert_eq!(None, string::match_mtime("no/such/file", TimeBucket::Month));
+/// ```
+pub fn match_mtime(path: &str, bucket: TimeBucket) -> Option<String> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(timestamps::bucket_time(modified, bucket))
+}
+
+/// Treats a string as a path and returns the MIME type of the file it names, based on its
+/// contents rather than its name, e.g. `"image/png"`.
+///
+/// Follows symbolic links. Returns `None` if the file can't be read, e.g. because it doesn't
+/// exist. See [mime] for details.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!(Some("text/plain"), string::match_mime_type("Cargo.toml"));
+/// assert_eq!(Some("inode/directory"), string::match_mime_type("src"));
+/// assert_eq!(None, string::match_mime_type("no/such/file"));
+/// ```
+pub fn match_mime_type(path: &str) -> Option<&'static str> {
+    mime::sniff_file(path).ok()
+}
+
+/// Returns the length of a string in characters (Unicode scalar values), not bytes.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!(5, string::match_length("apple"));
+/// assert_eq!(5, string::match_length("Äpfel"));
+/// assert_eq!(0, string::match_length(""));
+/// ```
+pub fn match_length(string: &str) -> usize {
+    string.chars().count()
+}
+
+/// Returns the shard, from 0 to `shards - 1`, that a string belongs to, based on a hash of it.
+///
+/// Equal strings always get the same shard, and different strings are spread evenly across
+/// shards. The hash is stable: it doesn't change between runs, platforms, or versions of this
+/// crate, so shards can be recomputed later. It is **not** suitable for cryptographic use.
+///
+/// # Panics
+///
+/// Panics if `shards` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// let shard = string::match_shard("apple", 4);
+/// assert!(shard < 4);
+/// assert_eq!(shard, string::match_shard("apple", 4));
+/// assert_eq!(0, string::match_shard("apple", 1));
+///
+/// // Shards never change, so this holds in every version.
+/// assert_eq!(691, string::match_shard("apple", 1000));
+/// ```
+pub fn match_shard(string: &str, shards: u64) -> u64 {
+    assert!(shards > 0, "The number of shards must be at least 1");
+    // FNV-1a is stable but mixes its low bits poorly, so run it through SplitMix64 before
+    // reducing it to a shard.
+    Rng::new(random::fnv1a(string.as_bytes())).next_u64() % shards
+}
+
+/// Returns the chunk, counting from 0, that holds the token at `position` (also counting from 0)
+/// when tokens are split into consecutive chunks of `chunk_size`.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!(0, string::match_chunk(0, 3));
+/// assert_eq!(0, string::match_chunk(2, 3));
+/// assert_eq!(1, string::match_chunk(3, 3));
+/// ```
+pub fn match_chunk(position: usize, chunk_size: usize) -> usize {
+    assert!(chunk_size > 0, "Chunk size must be at least 1");
+    position / chunk_size
+}
+
+/// Treats a string as a URL and returns its host, e.g. `"www.example.com"`.
+///
+/// The URL must have a scheme, as in `https://example.com/`, or start with `//`. User info and
+/// ports are dropped, and IPv6 addresses keep their brackets. The host is returned as written,
+/// without lowercasing. Returns `None` if the string isn't a URL or has an empty host.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!(Some("www.example.com"), string::match_url_host("https://www.example.com/a?b"));
+/// assert_eq!(Some("example.com"), string::match_url_host("ftp://me:pw@example.com:21"));
+/// assert_eq!(Some("[::1]"), string::match_url_host("http://[::1]:8080/"));
+/// assert_eq!(Some("cdn.example.com"), string::match_url_host("//cdn.example.com/x.js"));
+/// assert_eq!(None, string::match_url_host("example.com/index.html"));
+/// assert_eq!(None, string::match_url_host("file:///etc/hosts"));
+/// ```
+pub fn match_url_host(string: &str) -> Option<&str> {
+    let rest = match string.find("://") {
+        Some(i) => {
+            let scheme = &string[..i];
+            let is_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+            if !is_scheme {
+                return None;
+            }
+            &string[i + 3..]
+        }
+        None => string.strip_prefix("//")?,
+    };
+
+    let authority = &rest[..rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len())];
+    let host_port = match authority.rfind('@') {
+        Some(i) => &authority[i + 1..],
+        None => authority,
+    };
+    let host = if host_port.starts_with('[') {
+        &host_port[..=host_port.find(']')?]
+    } else {
+        host_port.split(':').next().unwrap_or("")
+    };
+    let host = host.strip_suffix('.').unwrap_or(host);
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+/// Returns the registrable domain of a host: the part a person or organization registers, e.g.
+/// `"example.com"` for `"www.example.com"`.
+///
+/// This is a heuristic rather than a lookup in the Public Suffix List: it keeps the last two
+/// labels, or three if the host ends in a common second-level domain under a country code, such
+/// as `.co.uk` or `.com.au`. IP addresses and single-label hosts are returned unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// assert_eq!("example.com", string::match_registrable_domain("www.example.com"));
+/// assert_eq!("example.co.uk", string::match_registrable_domain("a.b.example.co.uk"));
+/// assert_eq!("example.com", string::match_registrable_domain("example.com"));
+/// assert_eq!("192.168.0.1", string::match_registrable_domain("192.168.0.1"));
+/// assert_eq!("localhost", string::match_registrable_domain("localhost"));
+/// ```
+pub fn match_registrable_domain(host: &str) -> &str {
+    const SECOND_LEVEL: [&str; 10] = [
+        "ac", "co", "com", "edu", "gov", "go", "ne", "net", "or", "org",
+    ];
+
+    let is_ip = host.starts_with('[') || host.chars().all(|c| c.is_ascii_digit() || c == '.');
+    if is_ip {
+        return host;
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    let n = labels.len();
+    let keep = if n >= 3 && labels[n - 1].len() == 2 && SECOND_LEVEL.contains(&labels[n - 2]) {
+        3
+    } else {
+        2
+    };
+    if n <= keep {
+        return host;
+    }
+    let start = labels[..n - keep].iter().map(|label| label.len() + 1).sum();
+    &host[start..]
+}
+
+/// Treats a string as a JSON document, e.g. one line of JSON Lines, and returns the value at
+/// `path`. Strings are returned without quotes; other values are returned as written.
+///
+/// Returns `None` if there's no value at `path` or the document is malformed. See [json::lookup]
+/// for details.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::json::JsonPath;
+/// use groupby::matchers::string;
+///
+/// let path: JsonPath = ".user.id".parse().unwrap();
+/// let line = r#"{"level": "info", "user": {"id": "u7"}}"#;
+/// assert_eq!(Some("u7".to_string()), string::match_json_key(line, &path));
+/// assert_eq!(None, string::match_json_key(r#"{"level": "info"}"#, &path));
+/// ```
+pub fn match_json_key(string: &str, path: &JsonPath) -> Option<String> {
+    json::lookup(string, path)
+}
+
+/// Returns the key that `expr` computes for a string. See [KeyExpr] for the expression language.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::key_expr::KeyExpr;
+/// use groupby::matchers::string;
+///
+/// let expr: KeyExpr = "lower(field(2, ':'))".parse().unwrap();
+/// assert_eq!("admin", string::match_key_expr("alice:Admin:/bin/sh", &expr));
+/// assert_eq!("", string::match_key_expr("nobody", &expr));
+/// ```
+pub fn match_key_expr(string: &str, expr: &KeyExpr) -> String {
+    expr.evaluate(string)
+}
+
+/// Treats a string as a CSV record whose fields are separated by `delimiter` and returns its
+/// `column`th field, counting from 1, with any quotes removed.
+///
+/// Returns `None` if `column` is 0 or the record has fewer than `column` fields. See
+/// [csv::split_record] for details.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::matchers::string;
+///
+/// let record = r#"7,"Lee, Ann","say ""hi""""#;
+/// assert_eq!(Some("Lee, Ann".to_string()), string::match_csv_column(record, 2, ','));
+/// assert_eq!(Some(r#"say "hi""#.to_string()), string::match_csv_column(record, 3, ','));
+/// assert_eq!(None, string::match_csv_column(record, 4, ','));
+/// ```
+pub fn match_csv_column(record: &str, column: usize, delimiter: char) -> Option<String> {
+    csv::split_record(record, delimiter)
+        .into_iter()
+        .nth(column.checked_sub(1)?)
+}
+
+/// Returns the name of the first [GlobRule] whose pattern matches `string`, or `None` if none
+/// match. See [globs] for the pattern syntax.
+///
+/// # Examples
+///
+/// ```
+/// use groupby::command_line::globs::GlobRule;
+/// use groupby::matchers::string;
+///
+/// let rules: Vec<GlobRule> = vec!["images=*.{jpg,png}".parse().unwrap()];
+/// assert_eq!(Some("images"), string::match_glob_rules("photos/cat.png", &rules));
+/// assert_eq!(None, string::match_glob_rules("notes.txt", &rules));
+/// ```
+pub fn match_glob_rules<'a>(string: &str, rules: &'a [GlobRule]) -> Option<&'a str> {
+    globs::classify(string, rules)
+}
+
 /// Returns the number of times the function has been called before.
 ///
 /// Returns the next number from a thread-safe, global counter (starting from 0). This can be used
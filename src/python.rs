@@ -0,0 +1,370 @@
+//! Python bindings for the grouping engine, via [pyo3]. Requires the `python` feature.
+//!
+//! The Python module, `groupby`, provides:
+//!
+//! - `group_by(iterable, spec) -> dict[str, list[str]]`, which groups an iterable of strings.
+//!   `spec` holds `groupby` command-line arguments, either as a single string that is split on
+//!   whitespace, e.g. `"-w --extension"`, or as a list of arguments, e.g. `["--regex", r"\d+ \w"]`,
+//!   for arguments that contain whitespace. Everything documented in `groupby --help` about
+//!   grouping, labels, and sorting applies; options that split input are irrelevant, since each
+//!   item is already a token, though `--trim`, `--skip-empty`, `--limit`, and `--sample` apply to
+//!   the items. Options that run commands, read CSV records, select input files, count instead of
+//!   collecting values (`--count-only`), or change how groups are collected or written
+//!   (`--stream`, `--presorted-streaming`, `--low-memory`, and `--intern`) are rejected.
+//!   Groups appear in the order given by `--sort` (bytes by default), `--reverse`, and
+//!   `--sort-groups`.
+//! - `statistics(groups) -> Statistics`, which summarizes a `dict[str, list[str]]` such as the one
+//!   `group_by` returns. `Statistics` has the same read-only attributes as [Statistics], and
+//!   `str()` formats it like `groupby --stats`.
+//!
+//! Invalid arguments raise `ValueError`.
+//!
+//! ```text
+//! >>> import groupby
+//! >>> groupby.group_by(["a.txt", "b.rs", "c.txt"], "--extension")
+//! {'rs': ['b.rs'], 'txt': ['a.txt', 'c.txt']}
+//! >>> groupby.statistics(_).total_groups
+//! 2
+//! ```
+//!
+//! # Building
+//!
+//! To build an importable extension module, enable pyo3's `extension-module` feature, then rename
+//! the resulting shared library to `groupby.so` (or `groupby.pyd` on Windows):
+//!
+//! ```text
+//! cargo rustc --release --lib --features python,pyo3/extension-module --crate-type cdylib
+//! ```
+//!
+//! [Statistics]: crate::command_line::write_results::Statistics
+
+use crate::command_line::build_groups::TokenFilter;
+use crate::command_line::labels::Labeled;
+use crate::command_line::parse_args::try_parse_args;
+use crate::command_line::write_results::Statistics;
+use crate::groupers::string::Runner;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::BTreeMap;
+
+/// The `spec` argument to `group_by`: either one whitespace-separated string or a list.
+#[derive(FromPyObject)]
+enum Spec {
+    Line(String),
+    Args(Vec<String>),
+}
+
+impl Spec {
+    fn into_args(self) -> Vec<String> {
+        match self {
+            Spec::Line(line) => line.split_whitespace().map(str::to_string).collect(),
+            Spec::Args(args) => args,
+        }
+    }
+}
+
+/// group_by(iterable, spec)
+/// --
+///
+/// Groups an iterable of strings as specified by groupby command-line arguments and returns a dict
+/// mapping each group's key to a list of its values.
+#[pyfunction]
+fn group_by(py: Python, iterable: &PyAny, spec: Spec) -> PyResult<PyObject> {
+    let options = try_parse_args(&spec.into_args()).map_err(PyValueError::new_err)?;
+    if options.output.run_command.is_some() || options.grouping.runs_commands() {
+        return Err(PyValueError::new_err(
+            "Running commands is not supported here",
+        ));
+    }
+    if options.input.csv {
+        return Err(PyValueError::new_err(
+            "CSV input is not supported here, since each item is already a token",
+        ));
+    }
+    if options.input.selects_inputs() {
+        return Err(PyValueError::new_err(
+            "Input files are not supported here, since the items are passed in directly",
+        ));
+    }
+    if options.output.count_only {
+        return Err(PyValueError::new_err(
+            "Counting is not supported here, since group_by returns each group's values",
+        ));
+    }
+    let output = &options.output;
+    if output.stream || output.presorted_streaming || output.low_memory || output.intern {
+        return Err(PyValueError::new_err(
+            "Streaming and memory-saving options are not supported here, since group_by \
+             returns every group at once",
+        ));
+    }
+
+    let mut map = BTreeMap::new();
+    {
+        let mut labeled = Labeled::new(&mut map, &options.labels);
+        let mut runner = Runner::new(&mut labeled, &options.grouping);
+        let mut filter = TokenFilter::new(&options);
+        for item in iterable.iter()? {
+            if filter.is_done() {
+                break;
+            }
+            if let Some(token) = filter.admit(item?.extract::<String>()?) {
+                runner
+                    .run(token)
+                    .map_err(|e| PyValueError::new_err(e.to_string()))?;
+            }
+        }
+        runner
+            .finish()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    }
+
+    options.finish_groups(&mut map);
+
+    let mut groups: Vec<_> = map.iter().collect();
+    options
+        .output
+        .order_groups(&mut groups, |values: &&Vec<String>| values.len());
+
+    let dict = PyDict::new(py);
+    for (key, values) in groups {
+        dict.set_item(key, values)?;
+    }
+    Ok(dict.into())
+}
+
+/// Summary statistics about a collection of groups.
+#[pyclass(name = "Statistics", module = "groupby", get_all, frozen)]
+struct PyStatistics {
+    total_items: usize,
+    total_groups: usize,
+    total_bytes: usize,
+    group_size_median: usize,
+    group_size_average: f64,
+    group_size_min: usize,
+    group_size_max: usize,
+    text: String,
+}
+
+#[pymethods]
+impl PyStatistics {
+    fn __str__(&self) -> &str {
+        &self.text
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Statistics(total_items={}, total_groups={}, total_bytes={}, group_size_median={}, \
+             group_size_average={}, group_size_min={}, group_size_max={})",
+            self.total_items,
+            self.total_groups,
+            self.total_bytes,
+            self.group_size_median,
+            self.group_size_average,
+            self.group_size_min,
+            self.group_size_max,
+        )
+    }
+}
+
+impl From<Statistics> for PyStatistics {
+    fn from(stats: Statistics) -> Self {
+        PyStatistics {
+            text: stats.to_string(),
+            total_items: stats.total_items,
+            total_groups: stats.total_groups,
+            total_bytes: stats.total_bytes,
+            group_size_median: stats.group_size_median,
+            group_size_average: stats.group_size_average,
+            group_size_min: stats.group_size_min,
+            group_size_max: stats.group_size_max,
+        }
+    }
+}
+
+/// statistics(groups)
+/// --
+///
+/// Summarizes a dict mapping keys to lists of values, such as the one group_by returns.
+#[pyfunction]
+fn statistics(groups: BTreeMap<String, Vec<String>>) -> PyStatistics {
+    Statistics::of(&groups).into()
+}
+
+/// Groups strings by common keys.
+#[pymodule]
+fn groupby(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(group_by, module)?)?;
+    module.add_function(wrap_pyfunction!(statistics, module)?)?;
+    module.add_class::<PyStatistics>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs f with a fresh instance of the groupby module.
+    fn with_module<F: FnOnce(Python, &PyModule)>(f: F) {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "groupby").unwrap();
+            groupby(py, module).unwrap();
+            f(py, module);
+        });
+    }
+
+    mod group_by {
+        use super::*;
+
+        #[test]
+        fn groups_in_sort_order() {
+            with_module(|_, module| {
+                let groups = module
+                    .getattr("group_by")
+                    .unwrap()
+                    .call1((vec!["img10", "img2", "img10"], "-f5 --sort natural"))
+                    .unwrap();
+                let groups: Vec<(String, Vec<String>)> = groups
+                    .downcast::<PyDict>()
+                    .unwrap()
+                    .iter()
+                    .map(|(k, v)| (k.extract().unwrap(), v.extract().unwrap()))
+                    .collect();
+                assert_eq!(
+                    groups,
+                    vec![
+                        ("img2".to_string(), vec!["img2".to_string()]),
+                        ("img10".to_string(), vec!["img10".to_string(); 2]),
+                    ]
+                );
+            });
+        }
+
+        #[test]
+        fn accepts_list_spec() {
+            with_module(|_, module| {
+                let groups = module
+                    .getattr("group_by")
+                    .unwrap()
+                    .call1((vec!["a b", "a c"], vec!["--regex", "^a \\w"]))
+                    .unwrap();
+                let groups: BTreeMap<String, Vec<String>> = groups.extract().unwrap();
+                assert_eq!(groups.get("a b"), Some(&vec!["a b".to_string()]));
+                assert_eq!(groups.get("a c"), Some(&vec!["a c".to_string()]));
+            });
+        }
+
+        #[test]
+        fn removes_duplicates_before_filtering_sizes() {
+            with_module(|_, module| {
+                let groups = module
+                    .getattr("group_by")
+                    .unwrap()
+                    .call1((
+                        vec!["ab", "ab", "ac", "bc", "bc"],
+                        "-f1 --unique --min-size 2",
+                    ))
+                    .unwrap();
+                let groups: BTreeMap<String, Vec<String>> = groups.extract().unwrap();
+                assert_eq!(
+                    groups,
+                    BTreeMap::from([("a".to_string(), vec!["ab".to_string(), "ac".to_string()])])
+                );
+            });
+        }
+
+        #[test]
+        fn filters_items_like_input_tokens() {
+            with_module(|_, module| {
+                let groups = module
+                    .getattr("group_by")
+                    .unwrap()
+                    .call1((
+                        vec![" ab ", "", "ac", "bc"],
+                        "-f1 --trim --skip-empty --limit 2",
+                    ))
+                    .unwrap();
+                let groups: BTreeMap<String, Vec<String>> = groups.extract().unwrap();
+                assert_eq!(
+                    groups,
+                    BTreeMap::from([("a".to_string(), vec!["ab".to_string(), "ac".to_string()])])
+                );
+            });
+        }
+
+        #[test]
+        fn rejects_ignored_options() {
+            with_module(|py, module| {
+                let group_by = module.getattr("group_by").unwrap();
+                let file = std::env::temp_dir().join(format!("python-{}", std::process::id()));
+                std::fs::write(&file, "b\n").unwrap();
+                let tee = std::env::temp_dir().join(format!("python-tee-{}", std::process::id()));
+                for spec in [
+                    vec!["-f1", "--count-only"],
+                    vec!["-f1", file.to_str().unwrap()],
+                    vec!["-f1", "--tee", tee.to_str().unwrap()],
+                    vec!["-f1", "--with-filename"],
+                    vec!["-f1", "--stream"],
+                    vec!["-f1", "--presorted-streaming"],
+                    vec!["-f1", "--low-memory"],
+                    vec!["-f1", "--intern"],
+                ] {
+                    let error = group_by.call1((vec!["a"], spec.clone())).unwrap_err();
+                    assert!(error.is_instance_of::<PyValueError>(py), "{:?}", spec);
+                }
+                std::fs::remove_file(&file).unwrap();
+                assert!(!tee.exists());
+            });
+        }
+
+        #[test]
+        fn raises_value_error() {
+            with_module(|py, module| {
+                let group_by = module.getattr("group_by").unwrap();
+                for spec in [
+                    "--bogus",
+                    "-fx",
+                    "-f1 -c cat",
+                    "--key-command cat",
+                    "-f1 --key-command cat",
+                    "--csv --column status",
+                ] {
+                    let error = group_by.call1((vec!["a"], spec)).unwrap_err();
+                    assert!(error.is_instance_of::<PyValueError>(py), "{}", spec);
+                }
+            });
+        }
+    }
+
+    mod statistics {
+        use super::*;
+
+        #[test]
+        fn summarizes_groups() {
+            with_module(|_, module| {
+                let groups = module
+                    .getattr("group_by")
+                    .unwrap()
+                    .call1((vec!["apple", "avocado", "banana"], "-f1"))
+                    .unwrap();
+                let stats = module
+                    .getattr("statistics")
+                    .unwrap()
+                    .call1((groups,))
+                    .unwrap();
+                let total_groups: usize = stats.getattr("total_groups").unwrap().extract().unwrap();
+                assert_eq!(total_groups, 2);
+                let total_bytes: usize = stats.getattr("total_bytes").unwrap().extract().unwrap();
+                assert_eq!(total_bytes, 21);
+                assert!(stats
+                    .str()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .contains("Total items: 3"));
+            });
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! A small, seedable pseudo-random number generator shared by every randomized feature.
+//!
+//! Any feature that makes random choices (sampling input, shuffling values, breaking ties, etc.)
+//! should draw its numbers from an [Rng] created through [Rng::for_stream], passing along the
+//! user's seed (e.g. [GroupByOptions::seed]). This guarantees that a run with a given seed is
+//! fully reproducible, which matters for testing and audits.
+//!
+//! Each feature uses its own named stream, derived from the seed and the stream's name. That way,
+//! enabling one randomized feature never changes the numbers that another feature sees.
+//!
+//! The generator is [SplitMix64](https://prng.di.unimi.it/splitmix64.c). It is fast, has a tiny
+//! state, and is more than good enough for sampling and shuffling. It is **not** suitable for
+//! cryptographic use.
+//!
+//! # Examples
+//!
+//! ```
+//! use groupby::random::Rng;
+//!
+//! let mut a = Rng::for_stream(Some(42), "sample");
+//! let mut b = Rng::for_stream(Some(42), "sample");
+//! assert_eq!(a.next_u64(), b.next_u64());
+//!
+//! let mut values = vec![1, 2, 3, 4, 5];
+//! a.shuffle(&mut values);
+//! values.sort_unstable();
+//! assert_eq!(values, vec![1, 2, 3, 4, 5]);
+//! ```
+//!
+//! [GroupByOptions::seed]: crate::command_line::options::GroupByOptions::seed
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// A seedable pseudo-random number generator. See the [module documentation](self) for details.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator from a fixed seed. The same seed always produces the same sequence.
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Creates a generator with an unpredictable seed, for runs where the user didn't ask for one.
+    pub fn from_entropy() -> Self {
+        // RandomState is randomly keyed for each process, which gives us a good seed without
+        // pulling in an extra dependency.
+        Rng::new(RandomState::new().build_hasher().finish())
+    }
+
+    /// Creates the generator for the named stream `stream`.
+    ///
+    /// If `seed` is `None`, returns a generator seeded from [Rng::from_entropy]. Otherwise, mixes
+    /// the name of the stream into `seed`, so that different features get independent sequences
+    /// from the same user-provided seed.
+    ///
+    /// ```
+    /// use groupby::random::Rng;
+    ///
+    /// let mut sample = Rng::for_stream(Some(7), "sample");
+    /// let mut shuffle = Rng::for_stream(Some(7), "shuffle");
+    /// assert_ne!(sample.next_u64(), shuffle.next_u64());
+    /// ```
+    pub fn for_stream(seed: Option<u64>, stream: &str) -> Self {
+        match seed {
+            Some(seed) => Rng::new(seed ^ fnv1a(stream.as_bytes())),
+            None => Rng::from_entropy(),
+        }
+    }
+
+    /// Returns the next pseudo-random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a pseudo-random `f64` in the range `[0, 1)`.
+    ///
+    /// ```
+    /// use groupby::random::Rng;
+    ///
+    /// let mut rng = Rng::new(1);
+    /// for _ in 0..100 {
+    ///     let f = rng.next_f64();
+    ///     assert!((0.0..1.0).contains(&f));
+    /// }
+    /// ```
+    pub fn next_f64(&mut self) -> f64 {
+        // Use the top 53 bits, which is exactly the precision of an f64's mantissa.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a pseudo-random number in the range `[0, n)`, or 0 if `n` is 0.
+    ///
+    /// ```
+    /// use groupby::random::Rng;
+    ///
+    /// let mut rng = Rng::new(1);
+    /// for _ in 0..100 {
+    ///     assert!(rng.below(6) < 6);
+    /// }
+    /// assert_eq!(rng.below(0), 0);
+    /// ```
+    pub fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+
+        // Reject values from the incomplete final block so that every result is equally likely.
+        let zone = u64::MAX - (u64::MAX % n);
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % n;
+            }
+        }
+    }
+
+    /// Shuffles `items` in place using a Fisher-Yates shuffle.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+// Hashes bytes with 64-bit FNV-1a. Unlike std's hashers, its output is guaranteed not to change
+// between Rust versions or platforms, which keeps seeded runs reproducible.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod next_u64 {
+        use super::*;
+
+        // Reference values from the SplitMix64 reference implementation, seeded with 0.
+        #[test]
+        fn matches_reference_implementation() {
+            let mut rng = Rng::new(0);
+            assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+            assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+            assert_eq!(rng.next_u64(), 0x06c4_5d18_8009_454f);
+        }
+    }
+
+    mod for_stream {
+        use super::*;
+
+        #[test]
+        fn is_reproducible_with_seed() {
+            let a: Vec<u64> = {
+                let mut rng = Rng::for_stream(Some(99), "test");
+                (0..10).map(|_| rng.next_u64()).collect()
+            };
+            let b: Vec<u64> = {
+                let mut rng = Rng::for_stream(Some(99), "test");
+                (0..10).map(|_| rng.next_u64()).collect()
+            };
+            assert_eq!(a, b);
+        }
+
+        #[test]
+        fn differs_between_seeds() {
+            let mut a = Rng::for_stream(Some(1), "test");
+            let mut b = Rng::for_stream(Some(2), "test");
+            assert_ne!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    mod shuffle {
+        use super::*;
+
+        #[test]
+        fn is_reproducible_and_keeps_all_items() {
+            let original: Vec<usize> = (0..50).collect();
+
+            let mut a = original.clone();
+            Rng::new(5).shuffle(&mut a);
+            let mut b = original.clone();
+            Rng::new(5).shuffle(&mut b);
+            assert_eq!(a, b);
+            assert_ne!(a, original);
+
+            a.sort_unstable();
+            assert_eq!(a, original);
+        }
+
+        #[test]
+        fn works_with_empty_slice() {
+            let mut empty: Vec<u8> = vec![];
+            Rng::new(5).shuffle(&mut empty);
+            assert!(empty.is_empty());
+        }
+    }
+}